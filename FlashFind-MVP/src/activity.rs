@@ -0,0 +1,445 @@
+use ahash::{AHashMap, AHashSet};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{debug, warn};
+
+use crate::dates::{format_day, today};
+use crate::error::{FlashFindError, Result};
+use crate::index::{EntryMeta, UNKNOWN_SIZE};
+use crate::persistence::get_app_data_dir;
+use crate::redact::redact;
+use crate::size::format_size;
+
+/// Days of rollups kept, twelve weeks
+pub const RETENTION_DAYS: i64 = 84;
+
+/// Busiest folders kept per day and per week
+const TOP_FOLDERS: usize = 10;
+
+/// Biggest new files kept per day and per week
+const TOP_FILES: usize = 5;
+
+/// Folders counted in a day before new ones are ignored, so a scan of a
+/// huge tree cannot grow the counts without bound
+const MAX_TRACKED_FOLDERS: usize = 2_000;
+
+/// Rollup file in the app data folder
+const ACTIVITY_FILE: &str = "activity.json";
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// What the watcher and scans changed in the index on one day
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DayRollup {
+    /// Local calendar day (days since 1970-01-01)
+    pub day: i64,
+    pub added: u64,
+    pub removed: u64,
+    /// Folders with the most files added or removed, most first
+    pub folders: Vec<(PathBuf, u64)>,
+    /// Biggest files added, biggest first
+    pub biggest: Vec<(PathBuf, u64)>,
+}
+
+#[derive(Debug, Default)]
+struct ActivityState {
+    /// Oldest first, at most one per day
+    days: Vec<DayRollup>,
+    /// Day `folder_counts` belongs to
+    counted_day: Option<i64>,
+    /// Files added or removed per folder on `counted_day`; only the busiest
+    /// are kept in its rollup
+    folder_counts: AHashMap<PathBuf, u64>,
+    /// Changed since loaded or saved
+    dirty: bool,
+}
+
+impl ActivityState {
+    /// Position of the rollup for `day`, created if there is none
+    ///
+    /// Moving to a new day settles the folder counts of the last one and
+    /// drops rollups past `RETENTION_DAYS`.
+    fn rollup(&mut self, day: i64) -> usize {
+        if self.counted_day != Some(day) {
+            self.settle_folders();
+            self.days.retain(|rollup| rollup.day > day - RETENTION_DAYS);
+            self.counted_day = Some(day);
+            self.folder_counts = self
+                .days
+                .iter()
+                .find(|rollup| rollup.day == day)
+                .map(|rollup| rollup.folders.iter().cloned().collect())
+                .unwrap_or_default();
+        }
+        match self.days.binary_search_by_key(&day, |rollup| rollup.day) {
+            Ok(at) => at,
+            Err(at) => {
+                self.days.insert(at, DayRollup { day, ..Default::default() });
+                at
+            }
+        }
+    }
+
+    /// Copy the busiest counted folders into their day's rollup
+    fn settle_folders(&mut self) {
+        let Some(day) = self.counted_day else {
+            return;
+        };
+        if let Some(rollup) = self.days.iter_mut().find(|rollup| rollup.day == day) {
+            rollup.folders = top(self.folder_counts.iter().map(|(folder, &count)| (folder.clone(), count)), TOP_FOLDERS);
+        }
+    }
+
+    fn count_folder(&mut self, folder: &Path, count: u64) {
+        if let Some(counted) = self.folder_counts.get_mut(folder) {
+            *counted += count;
+        } else if self.folder_counts.len() < MAX_TRACKED_FOLDERS {
+            self.folder_counts.insert(folder.to_path_buf(), count);
+        }
+    }
+
+    fn add(&mut self, day: i64, files: &[(PathBuf, EntryMeta)]) {
+        let at = self.rollup(day);
+        for (path, meta) in files.iter().filter(|(_, meta)| !meta.is_dir) {
+            self.days[at].added += 1;
+            if let Some(folder) = path.parent() {
+                self.count_folder(folder, 1);
+            }
+
+            let biggest = &mut self.days[at].biggest;
+            let fits = biggest.len() < TOP_FILES || biggest.last().is_some_and(|&(_, size)| meta.size > size);
+            if meta.size != UNKNOWN_SIZE && fits && !biggest.iter().any(|(big, _)| big == path) {
+                let at = biggest.partition_point(|&(_, size)| size >= meta.size);
+                biggest.insert(at, (path.clone(), meta.size));
+                biggest.truncate(TOP_FILES);
+            }
+        }
+        self.dirty = true;
+    }
+
+    fn remove(&mut self, day: i64, folder: Option<&Path>, count: u64) {
+        let at = self.rollup(day);
+        self.days[at].removed += count;
+        if let Some(folder) = folder {
+            self.count_folder(folder, count);
+        }
+        self.dirty = true;
+    }
+
+    fn week(&mut self, start: i64) -> WeekSummary {
+        self.settle_folders();
+        let mut summary = WeekSummary { start, ..Default::default() };
+        let mut folders: AHashMap<&Path, u64> = AHashMap::new();
+        let mut biggest = Vec::new();
+        for rollup in self.days.iter().filter(|rollup| (start..start + 7).contains(&rollup.day)) {
+            summary.days[(rollup.day - start) as usize] = Some((rollup.added, rollup.removed));
+            summary.added += rollup.added;
+            summary.removed += rollup.removed;
+            for (folder, count) in &rollup.folders {
+                *folders.entry(folder).or_default() += count;
+            }
+            biggest.extend(rollup.biggest.iter().cloned());
+        }
+        summary.folders = top(folders.into_iter().map(|(folder, count)| (folder.to_path_buf(), count)), TOP_FOLDERS);
+        // A file removed and added again may be among the biggest on two days
+        let mut seen = AHashSet::new();
+        summary.biggest = top(biggest.into_iter(), usize::MAX);
+        summary.biggest.retain(|(path, _)| seen.insert(path.clone()));
+        summary.biggest.truncate(TOP_FILES);
+        summary
+    }
+}
+
+/// The `limit` largest values, largest first, ties by path
+fn top(items: impl Iterator<Item = (PathBuf, u64)>, limit: usize) -> Vec<(PathBuf, u64)> {
+    let mut items: Vec<_> = items.collect();
+    items.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    items.truncate(limit);
+    items
+}
+
+/// The Monday starting the week that holds `day`
+pub fn week_start(day: i64) -> i64 {
+    // 1970-01-01 was a Thursday
+    day - (day + 3).rem_euclid(7)
+}
+
+/// Daily rollups of files added and removed, for the weekly summary
+///
+/// Recording is opt-in: while disabled, nothing is counted, but rollups
+/// already kept can still be summarized. Scans and the watcher record once
+/// per batch, so the cost is a lock and a few counters per batch.
+#[derive(Debug, Default)]
+pub struct ActivityLog {
+    /// File the rollups are kept in; none keeps them in memory only
+    path: Option<PathBuf>,
+    enabled: AtomicBool,
+    state: Mutex<ActivityState>,
+}
+
+impl ActivityLog {
+    /// Rollups kept in `path`; an unreadable file starts them afresh
+    pub fn open(path: PathBuf, enabled: bool) -> Self {
+        let days = match fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str::<Vec<DayRollup>>(&data).unwrap_or_else(|e| {
+                warn!("Discarding unreadable activity rollups: {}", e);
+                Vec::new()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                warn!("Failed to read activity rollups: {}", e);
+                Vec::new()
+            }
+        };
+        let mut days: Vec<DayRollup> = days.into_iter().filter(|rollup| rollup.day > today() - RETENTION_DAYS).collect();
+        days.sort_by_key(|rollup| rollup.day);
+        days.dedup_by_key(|rollup| rollup.day);
+
+        Self {
+            path: Some(path),
+            enabled: AtomicBool::new(enabled),
+            state: Mutex::new(ActivityState { days, ..Default::default() }),
+        }
+    }
+
+    /// Rollups kept in the app data folder, or in memory only if it is
+    /// unavailable
+    pub fn open_default(enabled: bool) -> Self {
+        match get_app_data_dir() {
+            Ok(dir) => Self::open(dir.join(ACTIVITY_FILE), enabled),
+            Err(e) => {
+                warn!("Activity rollups kept in memory only: {}", e);
+                Self { enabled: AtomicBool::new(enabled), ..Default::default() }
+            }
+        }
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Count entries just added to the index; folders are not counted
+    pub fn record_added(&self, files: &[(PathBuf, EntryMeta)]) {
+        if self.is_enabled() && files.iter().any(|(_, meta)| !meta.is_dir) {
+            self.state.lock().add(today(), files);
+        }
+    }
+
+    /// Count `count` entries just removed from the index, under `folder`
+    /// when they share one
+    pub fn record_removed(&self, folder: Option<&Path>, count: u64) {
+        if self.is_enabled() && count > 0 {
+            self.state.lock().remove(today(), folder, count);
+        }
+    }
+
+    /// Summary of the week starting on Monday `start`
+    pub fn week(&self, start: i64) -> WeekSummary {
+        self.state.lock().week(start)
+    }
+
+    /// Monday of the oldest week with a rollup
+    pub fn oldest_week(&self) -> Option<i64> {
+        self.state.lock().days.first().map(|rollup| week_start(rollup.day))
+    }
+
+    /// Write the rollups if they changed
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut state = self.state.lock();
+        if !state.dirty {
+            return Ok(());
+        }
+        state.settle_folders();
+
+        let data = serde_json::to_string(&state.days).map_err(|e| FlashFindError::InvalidConfig(format!("Serialization error: {}", e)))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| FlashFindError::DirectoryCreationError {
+                path: dir.display().to_string(),
+                source: e,
+            })?;
+        }
+        fs::write(path, data).map_err(|e| FlashFindError::FileWriteError {
+            path: path.display().to_string(),
+            source: e,
+        })?;
+        state.dirty = false;
+
+        debug!("Saved {} days of activity to {}", state.days.len(), redact(path));
+        Ok(())
+    }
+}
+
+/// Files added and removed during one week
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WeekSummary {
+    /// The Monday starting the week
+    pub start: i64,
+    /// Files added and removed on each day, none where nothing was recorded
+    pub days: [Option<(u64, u64)>; 7],
+    pub added: u64,
+    pub removed: u64,
+    /// Folders with the most files added or removed, most first
+    pub folders: Vec<(PathBuf, u64)>,
+    /// Biggest files added, biggest first
+    pub biggest: Vec<(PathBuf, u64)>,
+}
+
+impl WeekSummary {
+    /// Whether nothing was recorded on any day of the week
+    pub fn is_empty(&self) -> bool {
+        self.days.iter().all(Option::is_none)
+    }
+
+    /// Days with a record, of the seven
+    pub fn recorded_days(&self) -> usize {
+        self.days.iter().flatten().count()
+    }
+
+    /// "Week of 2026-10-12 to 2026-10-18"
+    pub fn title(&self) -> String {
+        format!("Week of {} to {}", format_day(self.start), format_day(self.start + 6))
+    }
+
+    /// Label of day `offset` (0-6) of the week, "Mon 2026-10-12"
+    pub fn day_label(&self, offset: usize) -> String {
+        format!("{} {}", WEEKDAYS[offset], format_day(self.start + offset as i64))
+    }
+
+    /// The summary as a Markdown document
+    pub fn to_markdown(&self) -> String {
+        let mut text = format!("# FlashFind activity\n\n{}\n\n", self.title());
+        if self.is_empty() {
+            text.push_str("No activity was recorded this week.\n");
+            return text;
+        }
+
+        let _ = writeln!(text, "- Files added: {}", self.added);
+        let _ = writeln!(text, "- Files removed: {}", self.removed);
+        if self.recorded_days() < 7 {
+            let _ = writeln!(text, "- Days without data: {}", 7 - self.recorded_days());
+        }
+
+        text.push_str("\n## Daily\n\n| Day | Added | Removed |\n| --- | ---: | ---: |\n");
+        for (offset, day) in self.days.iter().enumerate() {
+            match day {
+                Some((added, removed)) => writeln!(text, "| {} | {} | {} |", self.day_label(offset), added, removed),
+                None => writeln!(text, "| {} | no data | no data |", self.day_label(offset)),
+            }
+            .ok();
+        }
+
+        if !self.folders.is_empty() {
+            text.push_str("\n## Busiest folders\n\n");
+            for (folder, count) in &self.folders {
+                let _ = writeln!(text, "- `{}`: {} files", folder.display(), count);
+            }
+        }
+        if !self.biggest.is_empty() {
+            text.push_str("\n## Biggest new files\n\n");
+            for (path, size) in &self.biggest {
+                let _ = writeln!(text, "- `{}`: {}", path.display(), format_size(*size));
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dates::days_from_civil;
+    use crate::test_dir::TestDir;
+
+    fn file(path: &str, size: u64) -> (PathBuf, EntryMeta) {
+        (PathBuf::from(path), EntryMeta { size, ..EntryMeta::UNKNOWN })
+    }
+
+    #[test]
+    fn test_weeks_start_on_monday() {
+        let monday = days_from_civil(2026, 10, 12);
+        for day in monday..monday + 7 {
+            assert_eq!(week_start(day), monday);
+        }
+        assert_eq!(week_start(monday - 1), monday - 7);
+        assert_eq!(week_start(days_from_civil(1969, 12, 31)), days_from_civil(1969, 12, 29));
+    }
+
+    #[test]
+    fn test_rollups_summarize_a_week() {
+        let monday = days_from_civil(2026, 10, 12);
+        let mut state = ActivityState::default();
+        state.add(monday, &[file("/docs/a.txt", 10), file("/docs/b.txt", 300), file("/music/c.mp3", UNKNOWN_SIZE)]);
+        state.add(monday, &[(PathBuf::from("/docs/new"), EntryMeta { is_dir: true, ..EntryMeta::UNKNOWN })]);
+        state.remove(monday, Some(Path::new("/music")), 4);
+        state.add(monday + 2, &[file("/docs/d.txt", 50), file("/docs/b.txt", 300)]);
+        // Next week
+        state.add(monday + 7, &[file("/docs/e.txt", 9_000)]);
+
+        let week = state.week(monday);
+        assert_eq!(week.days[0], Some((3, 4)));
+        assert_eq!(week.days[1], None);
+        assert_eq!(week.days[2], Some((2, 0)));
+        assert_eq!((week.added, week.removed, week.recorded_days()), (5, 4, 2));
+        assert_eq!(week.folders, [(PathBuf::from("/music"), 5), (PathBuf::from("/docs"), 4)]);
+        // b.txt, added on two days, is listed once
+        assert_eq!(week.biggest.iter().map(|(_, size)| *size).collect::<Vec<_>>(), [300, 50, 10]);
+
+        let markdown = week.to_markdown();
+        assert!(markdown.contains("Week of 2026-10-12 to 2026-10-18"));
+        assert!(markdown.contains("| Tue 2026-10-13 | no data | no data |"));
+        assert!(markdown.contains("- `/music`: 5 files"));
+        assert!(markdown.contains("300 B"));
+
+        let empty = state.week(monday - 7);
+        assert!(empty.is_empty());
+        assert!(empty.to_markdown().contains("No activity was recorded"));
+    }
+
+    #[test]
+    fn test_old_rollups_expire() {
+        let day = days_from_civil(2026, 1, 1);
+        let mut state = ActivityState::default();
+        state.add(day, &[file("/a.txt", 1)]);
+        state.add(day + RETENTION_DAYS - 1, &[file("/b.txt", 1)]);
+        assert_eq!(state.days.len(), 2);
+        state.add(day + RETENTION_DAYS, &[file("/c.txt", 1)]);
+        assert_eq!(state.days.iter().map(|rollup| rollup.day).collect::<Vec<_>>(), [day + RETENTION_DAYS - 1, day + RETENTION_DAYS]);
+    }
+
+    #[test]
+    fn test_rollups_are_saved_and_reloaded() {
+        let dir = TestDir::new("activity");
+        let path = dir.join(ACTIVITY_FILE);
+
+        let log = ActivityLog::open(path.clone(), false);
+        log.record_added(&[file("/docs/a.txt", 10)]);
+        log.save().unwrap();
+        assert!(!path.exists(), "nothing is recorded until enabled");
+
+        log.set_enabled(true);
+        log.record_added(&[file("/docs/a.txt", 10)]);
+        log.record_removed(Some(Path::new("/docs")), 2);
+        log.save().unwrap();
+
+        let reloaded = ActivityLog::open(path.clone(), true);
+        let week = reloaded.week(week_start(today()));
+        assert_eq!((week.added, week.removed), (1, 2));
+        assert_eq!(week.folders, [(PathBuf::from("/docs"), 3)]);
+        assert_eq!(reloaded.oldest_week(), Some(week_start(today())));
+
+        // A damaged file starts afresh instead of failing
+        fs::write(&path, "not json").unwrap();
+        assert!(ActivityLog::open(path, true).week(week_start(today())).is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::config::SavedSearch;
+use crate::index::{explain_parsed, parse_query, ParsedQuery};
+
+/// Minimum gap between two alerts for the same saved search
+pub const ALERT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Alerts kept while the UI is not collecting them; the oldest are dropped
+const MAX_PENDING_ALERTS: usize = 20;
+
+/// A newly indexed file matching a watched saved search
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchAlert {
+    /// Name of the saved search
+    pub search: String,
+    pub query: String,
+    /// First matching file
+    pub path: PathBuf,
+    /// Further matches folded into this alert by the rate limit
+    pub more: usize,
+}
+
+/// A watched saved search with its query compiled once
+struct WatchedSearch {
+    name: String,
+    query: String,
+    parsed: ParsedQuery,
+    last_alert: Option<Instant>,
+    /// Matches held back by the rate limit, reported with the next alert or
+    /// once the interval has passed
+    suppressed: usize,
+    /// First of the held-back matches
+    held: Option<PathBuf>,
+}
+
+/// Checks newly indexed files against watched saved searches
+///
+/// The watcher evaluates each batch of new entries on its own thread; the UI
+/// drains the resulting alerts every frame, which also releases matches the
+/// rate limit held back once their interval has passed.
+#[derive(Default)]
+pub struct SearchAlerts {
+    watches: Mutex<Vec<WatchedSearch>>,
+    pending: Mutex<VecDeque<SearchAlert>>,
+}
+
+impl SearchAlerts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the watched searches, keeping rate-limit state for unchanged queries
+    pub fn set_watches(&self, searches: &[SavedSearch]) {
+        let mut watches = self.watches.lock();
+        let previous = std::mem::take(&mut *watches);
+
+        *watches = searches
+            .iter()
+            .filter(|saved| saved.watch)
+            .map(|saved| {
+                let old = previous.iter().find(|w| w.query == saved.query);
+                WatchedSearch {
+                    name: saved.name.clone(),
+                    query: saved.query.clone(),
+                    parsed: parse_query(&saved.query),
+                    last_alert: old.and_then(|w| w.last_alert),
+                    suppressed: old.map_or(0, |w| w.suppressed),
+                    held: old.and_then(|w| w.held.clone()),
+                }
+            })
+            .collect();
+    }
+
+    /// Whether any saved search is watched
+    pub fn is_active(&self) -> bool {
+        !self.watches.lock().is_empty()
+    }
+
+    /// Check a batch of newly indexed files against every watched search
+    pub fn evaluate(&self, added: &[PathBuf]) {
+        self.evaluate_at(added, Instant::now());
+    }
+
+    fn evaluate_at(&self, added: &[PathBuf], now: Instant) {
+        let mut watches = self.watches.lock();
+        let mut raised = Vec::new();
+
+        for watch in watches.iter_mut() {
+            let mut matches = added.iter().filter(|path| explain_parsed(&watch.parsed, path).is_some());
+            let Some(first) = matches.next() else {
+                continue;
+            };
+            let count = 1 + matches.count();
+
+            if watch.last_alert.is_some_and(|last| now.duration_since(last) < ALERT_INTERVAL) {
+                watch.suppressed += count;
+                watch.held.get_or_insert_with(|| first.clone());
+                continue;
+            }
+
+            raised.push(SearchAlert {
+                search: watch.name.clone(),
+                query: watch.query.clone(),
+                path: first.clone(),
+                more: count - 1 + watch.suppressed,
+            });
+            watch.last_alert = Some(now);
+            watch.suppressed = 0;
+            watch.held = None;
+        }
+        drop(watches);
+        self.raise(raised);
+    }
+
+    /// Raise the matches held back by searches whose interval has passed
+    fn flush_held_at(&self, now: Instant) {
+        let mut watches = self.watches.lock();
+        let mut raised = Vec::new();
+
+        for watch in watches.iter_mut() {
+            if watch.last_alert.is_some_and(|last| now.duration_since(last) < ALERT_INTERVAL) {
+                continue;
+            }
+            let Some(path) = watch.held.take() else {
+                continue;
+            };
+            raised.push(SearchAlert {
+                search: watch.name.clone(),
+                query: watch.query.clone(),
+                path,
+                more: watch.suppressed - 1,
+            });
+            watch.last_alert = Some(now);
+            watch.suppressed = 0;
+        }
+        drop(watches);
+        self.raise(raised);
+    }
+
+    fn raise(&self, raised: Vec<SearchAlert>) {
+        if raised.is_empty() {
+            return;
+        }
+        debug!("{} saved-search alerts raised", raised.len());
+        let mut pending = self.pending.lock();
+        pending.extend(raised);
+        while pending.len() > MAX_PENDING_ALERTS {
+            pending.pop_front();
+        }
+    }
+
+    /// Take the alerts raised since the last call, releasing held-back
+    /// matches whose interval has passed
+    pub fn take(&self) -> Vec<SearchAlert> {
+        self.take_at(Instant::now())
+    }
+
+    fn take_at(&self, now: Instant) -> Vec<SearchAlert> {
+        self.flush_held_at(now);
+        self.pending.lock().drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(name: &str, query: &str, watch: bool) -> SavedSearch {
+        SavedSearch { name: name.to_string(), query: query.to_string(), filter: None, watch }
+    }
+
+    #[test]
+    fn test_only_watched_matching_searches_alert() {
+        let alerts = SearchAlerts::new();
+        alerts.set_watches(&[saved("Invoices", "invoice", true), saved("PDFs", ".pdf", false)]);
+        assert!(alerts.is_active());
+
+        alerts.evaluate(&[PathBuf::from("C:\\in\\notes.txt"), PathBuf::from("C:\\in\\invoice_0042.pdf")]);
+        let raised = alerts.take();
+        assert_eq!(raised.len(), 1);
+        assert_eq!(raised[0].search, "Invoices");
+        assert_eq!(raised[0].path, PathBuf::from("C:\\in\\invoice_0042.pdf"));
+        assert!(alerts.take().is_empty());
+    }
+
+    #[test]
+    fn test_bulk_copies_are_rate_limited() {
+        let alerts = SearchAlerts::new();
+        alerts.set_watches(&[saved("Photos", ".jpg", true)]);
+        let batch = |n: usize| -> Vec<PathBuf> {
+            (0..n).map(|i| PathBuf::from(format!("D:\\dcim\\{}.jpg", i))).collect()
+        };
+
+        let start = Instant::now();
+        alerts.evaluate_at(&batch(3), start);
+        for _ in 0..50 {
+            alerts.evaluate_at(&batch(10), start + Duration::from_secs(1));
+        }
+        let raised = alerts.take_at(start + Duration::from_secs(1));
+        assert_eq!(raised.len(), 1);
+        assert_eq!(raised[0].more, 2);
+
+        // Held-back matches are reported with the next alert after the interval
+        alerts.evaluate_at(&batch(1), start + ALERT_INTERVAL + Duration::from_secs(1));
+        let raised = alerts.take_at(start + ALERT_INTERVAL + Duration::from_secs(1));
+        assert_eq!(raised.len(), 1);
+        assert_eq!(raised[0].more, 500);
+    }
+
+    #[test]
+    fn test_held_back_matches_are_flushed_when_the_interval_ends() {
+        let alerts = SearchAlerts::new();
+        alerts.set_watches(&[saved("Photos", ".jpg", true)]);
+        let start = Instant::now();
+        alerts.evaluate_at(&[PathBuf::from("D:\\dcim\\a.jpg")], start);
+        alerts.evaluate_at(&[PathBuf::from("D:\\dcim\\b.jpg"), PathBuf::from("D:\\dcim\\c.jpg")], start + Duration::from_secs(1));
+        assert_eq!(alerts.take_at(start + Duration::from_secs(2)).len(), 1);
+
+        // No further file arrives, yet the held matches surface on time
+        assert!(alerts.take_at(start + ALERT_INTERVAL - Duration::from_secs(1)).is_empty());
+        let flushed = alerts.take_at(start + ALERT_INTERVAL);
+        assert_eq!(flushed.len(), 1);
+        assert_eq!((flushed[0].path.clone(), flushed[0].more), (PathBuf::from("D:\\dcim\\b.jpg"), 1));
+        assert!(alerts.take_at(start + ALERT_INTERVAL * 3).is_empty());
+    }
+
+    #[test]
+    fn test_path_prefix_searches_can_be_watched() {
+        let alerts = SearchAlerts::new();
+        alerts.set_watches(&[saved("Downloads", "C:\\Users\\me\\Downloads\\", true)]);
+
+        alerts.evaluate(&[PathBuf::from("C:\\Users\\me\\Documents\\a.txt")]);
+        assert!(alerts.take().is_empty());
+        alerts.evaluate(&[PathBuf::from("C:\\Users\\me\\Downloads\\setup.exe")]);
+        assert_eq!(alerts.take().len(), 1);
+    }
+}
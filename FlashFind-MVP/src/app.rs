@@ -1,126 +1,546 @@
+use ahash::AHashMap;
 use eframe::egui;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 
-use crate::config::{Config, Theme};
-use crate::index::FileIndex;
-use crate::indexer::{Indexer, IndexState};
-use crate::persistence::{load_index, save_index};
-use crate::watcher::{get_default_directories, Watcher};
+use crate::activity::{week_start, ActivityLog, WeekSummary};
+use crate::alerts::{SearchAlert, SearchAlerts};
+use crate::archive::{backing_file, entry_kind, extract_member, EntryKind};
+use crate::changes::ChangeKind;
+use crate::config::{Config, IncludeFilter, ResultActivation, SavedSearch, Theme};
+use crate::dates::{format_date, format_day, local_utc_offset, today};
+use crate::dedup::{alias_candidates, dedup_results, AliasJob, DEDUP_WINDOW};
+use crate::duplicates::{DuplicateJob, DuplicateReport, COLLAPSED_GROUP_SIZE};
+use crate::error::FlashFindError;
+use crate::export::{self, ExportJob};
+use crate::fold;
+use crate::grep::{ContentMatch, ContentSearcher, GrepEvent, GrepSummary, GREP_TIME_LIMIT};
+use crate::health::{format_ago, since, HealthReport, SAMPLE_SIZE};
+use crate::history::{HistoryRecall, HISTORY_DROPDOWN_ROWS};
+use crate::index::{
+    explain_match, parse_query, query_operator, EntryMeta, MemoryBreakdown, ParsedQuery, SearchResult, SortOrder, TypoTolerance,
+    PATH_PAGE_SIZE, QUERY_OPERATORS, RECENT_CAPACITY,
+};
+use crate::indexer::{Indexer, IndexState, COMPACTION_THRESHOLD};
+use crate::kinds::{self, default_kind_groups, kind_label, KindGroup};
+use crate::logging::{build_filter, LogControl, LogLevel};
+use crate::paths::{is_windows_style, to_long_path, OpenPolicy};
+use crate::persistence::{check_storage, load_index, SaveCoordinator};
+use crate::ranking::RankingPreset;
+use crate::rename::{apply_renames, plan_renames, undo_renames, PlannedRename, RenamePattern, RenameSummary, MAX_BATCH_RENAME, NUMBER_TOKEN};
+use crate::redact::{self, redact};
+use crate::roots::RootMonitor;
+use crate::search::{SearchBatch, SearchJob, SearchRequest, SearchSummary};
+use crate::shard::{cleanup_stale_shards, ElevatedScan, ShardStatus};
+use crate::sharded::{ShardedCache, ShardedIndex};
+use crate::size::{format_size, SizeJob};
+use crate::shortcut::{create_shortcut, desktop_dir, existing_shortcut, shortcut_name, LaunchSearch};
+use crate::snapshot::{SnapshotDiff, SnapshotInfo, SnapshotJob, SnapshotOutcome, SnapshotStore};
+use crate::suggest::{suggest, Suggestion, TokenIndex, TokenIndexJob};
+use crate::usage::{UsageJob, UsageSort};
+use crate::platform::{self, get_default_directories};
+use crate::privacy;
+use crate::watcher::{Exclusions, Watcher};
 
-/// File type filter options
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// File type filter options; kinds come from `Config::kind_groups`, so the
+/// dropdown offers the same groups as `kind:` queries
+#[derive(Debug, Clone, PartialEq)]
 enum FileTypeFilter {
     All,
-    Documents,
-    Images,
-    Videos,
-    Audio,
-    Code,
-    Archives,
+    /// A kind group, by name
+    Kind(String),
+    Folders,
 }
 
 impl FileTypeFilter {
-    fn matches(&self, path: &Path) -> bool {
-        if matches!(self, FileTypeFilter::All) {
-            return true;
-        }
-        
-        let ext = path.extension()
-            .and_then(|e| e.to_str())
-            .map(|s| s.to_lowercase());
-        
-        match ext.as_deref() {
-            Some(e) => match self {
-                FileTypeFilter::Documents => matches!(e, "pdf" | "doc" | "docx" | "txt" | "rtf" | "odt" | "md"),
-                FileTypeFilter::Images => matches!(e, "jpg" | "jpeg" | "png" | "gif" | "bmp" | "svg" | "webp" | "ico"),
-                FileTypeFilter::Videos => matches!(e, "mp4" | "avi" | "mkv" | "mov" | "wmv" | "flv" | "webm"),
-                FileTypeFilter::Audio => matches!(e, "mp3" | "wav" | "flac" | "aac" | "ogg" | "m4a" | "wma"),
-                FileTypeFilter::Code => matches!(e, "rs" | "py" | "js" | "ts" | "java" | "c" | "cpp" | "h" | "cs" | "go" | "rb" | "php" | "html" | "css" | "json" | "xml" | "yaml" | "toml"),
-                FileTypeFilter::Archives => matches!(e, "zip" | "rar" | "7z" | "tar" | "gz" | "bz2" | "xz"),
-                FileTypeFilter::All => true,
-            },
-            None => false,
+    fn matches(&self, result: &SearchResult, groups: &[KindGroup]) -> bool {
+        let name = match self {
+            FileTypeFilter::All => return true,
+            FileTypeFilter::Folders => return result.is_dir,
+            FileTypeFilter::Kind(_) if result.is_dir => return false,
+            FileTypeFilter::Kind(name) => name,
+        };
+        // A kind deleted in Settings no longer filters anything out
+        groups
+            .iter()
+            .find(|group| &group.name == name)
+            .is_none_or(|group| group.matches(&result.file_name))
+    }
+    
+    /// Stable name used in shortcut arguments and saved searches
+    fn id(&self) -> &str {
+        match self {
+            FileTypeFilter::All => "all",
+            FileTypeFilter::Kind(name) => name,
+            FileTypeFilter::Folders => "folders",
+        }
+    }
+    
+    /// Filter named by `id`; unknown or missing ids mean all files
+    fn from_id(id: Option<&str>, groups: &[KindGroup]) -> Self {
+        match id {
+            Some("folders") => FileTypeFilter::Folders,
+            Some(id) if groups.iter().any(|group| group.name == id) => FileTypeFilter::Kind(id.to_string()),
+            _ => FileTypeFilter::All,
         }
     }
     
-    fn label(&self) -> &'static str {
+    fn label(&self) -> String {
         match self {
-            FileTypeFilter::All => "All Files",
-            FileTypeFilter::Documents => "Documents",
-            FileTypeFilter::Images => "Images",
-            FileTypeFilter::Videos => "Videos",
-            FileTypeFilter::Audio => "Audio",
-            FileTypeFilter::Code => "Code",
-            FileTypeFilter::Archives => "Archives",
+            FileTypeFilter::All => "All Files".to_string(),
+            FileTypeFilter::Kind(name) => kind_label(name),
+            FileTypeFilter::Folders => "Folders".to_string(),
+        }
+    }
+}
+
+/// Modal windows that take keyboard precedence over the search box
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Modal {
+    Settings,
+    Welcome,
+    MatchInfo,
+    SearchSyntax,
+    BatchRename,
+    SpaceUsage,
+    SnapshotDiff,
+    ReplaceShortcut,
+}
+
+/// Open modal windows in stacking order (last is topmost)
+#[derive(Debug, Default)]
+struct ModalStack(Vec<Modal>);
+
+impl ModalStack {
+    fn open(&mut self, modal: Modal) {
+        self.close(modal);
+        self.0.push(modal);
+    }
+    
+    fn close(&mut self, modal: Modal) {
+        self.0.retain(|m| *m != modal);
+    }
+    
+    fn is_open(&self, modal: Modal) -> bool {
+        self.0.contains(&modal)
+    }
+    
+    fn top(&self) -> Option<Modal> {
+        self.0.last().copied()
+    }
+}
+
+/// Widget that held keyboard focus at the end of the previous frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FocusTarget {
+    Search,
+    /// A result row, by position in the results list
+    Results(usize),
+    Other,
+}
+
+/// What Esc and Enter act on while the search box has focus
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SearchBoxState {
+    /// The results match the query
+    Searched,
+    /// The completion dropdown is open
+    Suggesting,
+    /// An accepted completion has not been searched yet
+    Unsearched,
+}
+
+/// What a global shortcut does in the current context
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ShortcutAction {
+    CloseModal(Modal),
+    CloseSuggestions,
+    ClearSearch,
+    RunSearch,
+    OpenResult(usize),
+}
+
+/// Resolve Esc/Enter by precedence: topmost modal first, then the
+/// completion dropdown, then the search box or results list; anything else
+/// keeps the key for itself
+fn resolve_shortcut(
+    key: egui::Key,
+    top_modal: Option<Modal>,
+    focus: FocusTarget,
+    search_box: SearchBoxState,
+) -> Option<ShortcutAction> {
+    if let Some(modal) = top_modal {
+        return (key == egui::Key::Escape).then_some(ShortcutAction::CloseModal(modal));
+    }
+    
+    if focus == FocusTarget::Other {
+        return None;
+    }
+    
+    match (key, focus) {
+        (egui::Key::Escape, FocusTarget::Search) if search_box == SearchBoxState::Suggesting => {
+            Some(ShortcutAction::CloseSuggestions)
+        }
+        (egui::Key::Enter, FocusTarget::Search) if search_box == SearchBoxState::Unsearched => {
+            Some(ShortcutAction::RunSearch)
         }
+        (egui::Key::Escape, _) => Some(ShortcutAction::ClearSearch),
+        (egui::Key::Enter, FocusTarget::Results(row)) => Some(ShortcutAction::OpenResult(row)),
+        (egui::Key::Enter, _) => Some(ShortcutAction::OpenResult(0)),
+        _ => None,
     }
 }
 
+/// What an edit to the search box starts
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TypedSearch {
+    /// Search on this keystroke
+    Now,
+    /// Search once typing has paused this long
+    After(Duration),
+    /// Too short to search: show the "keep typing" hint right away
+    KeepTyping,
+}
+
+/// Decide when an edited query is searched; a query too short to search
+/// never waits out the typing pause or reaches the search worker
+fn resolve_typed_search(query: &str, min_length: usize, debounce: Duration) -> TypedSearch {
+    if needs_more_typing(query, min_length) {
+        TypedSearch::KeepTyping
+    } else if debounce.is_zero() {
+        TypedSearch::Now
+    } else {
+        TypedSearch::After(debounce)
+    }
+}
+
+/// Whether `query` is a filename query shorter than `min_length`, which
+/// would match most of the index; extension, `*` and other operator
+/// queries are narrow enough to search at any length
+fn needs_more_typing(query: &str, min_length: usize) -> bool {
+    matches!(parse_query(query), ParsedQuery::Filename(needle) if needle.chars().count() < min_length)
+}
+
+/// Least time between rebuilds of the completion words while the index changes
+const SUGGESTION_REFRESH: Duration = Duration::from_secs(10);
+
+/// Id of the main search box, for setting its cursor from code
+fn search_box_id() -> egui::Id {
+    egui::Id::new("search_box")
+}
+
+/// Most selected results opened at once by Enter
+const MAX_OPEN_SELECTION: usize = 10;
+
+/// Maximum number of detached results windows open at once
+const MAX_RESULT_WINDOWS: usize = 2;
+
+/// Saved-search alerts shown at once; older ones make room
+const MAX_TOASTS: usize = 3;
+
+/// How long a saved-search alert stays on screen
+const TOAST_LIFETIME: Duration = Duration::from_secs(20);
+
+/// Folders listed under "Largest folders" in the Statistics tab
+const LARGEST_FOLDERS: usize = 5;
+
+/// Extensions listed under "Top file types" in the Statistics tab
+const TOP_EXTENSIONS: usize = 10;
+
+/// A read-only snapshot of search results shown in its own OS window
+///
+/// It keeps its rows while the main window goes on searching, and closes
+/// with the main window.
+struct ResultsWindow {
+    id: egui::ViewportId,
+    query: String,
+    results: Vec<SearchResult>,
+}
+
+/// Batch rename dialog over a snapshot of the results
+struct BatchRenameDialog {
+    paths: Vec<PathBuf>,
+    /// Results past `MAX_BATCH_RENAME`, left out of the batch
+    omitted: usize,
+    pattern: RenamePattern,
+    preview: Vec<PlannedRename>,
+    /// Pattern the preview was built from; rebuilt when the pattern changes
+    previewed: Option<RenamePattern>,
+    summary: Option<RenameSummary>,
+}
+
+/// Space usage explorer over one indexed root
+struct SpaceUsageView {
+    job: UsageJob,
+    root: PathBuf,
+    /// Folders drilled into below the root, innermost last
+    trail: Vec<PathBuf>,
+    sort: UsageSort,
+}
+
+/// Row action picked in the space usage list
+enum UsageAction {
+    DrillDown(PathBuf),
+    Up,
+    OpenFolder(PathBuf),
+    SearchWithin(PathBuf),
+}
+
 /// Main application state
 pub struct FlashFindApp {
-    index: Arc<RwLock<FileIndex>>,
+    index: Arc<ShardedIndex>,
     indexer: Indexer,
     watcher: Option<Watcher>,
+    exclusions: Arc<RwLock<Exclusions>>,
+    roots: Arc<RootMonitor>,
     config: Config,
     query: String,
+    /// Completions for the query, shown in the dropdown while it is open
+    suggestions: Vec<Suggestion>,
+    /// Typing opens the dropdown; Esc or accepting a completion closes it
+    suggestions_open: bool,
+    /// Completion highlighted with the arrow keys
+    suggestion_selected: Option<usize>,
+    /// Position while recalling past queries with Up and Down
+    history_recall: HistoryRecall,
+    /// Words for completions, from the index at its `generation()`
+    token_index: Option<TokenIndex>,
+    token_job: Option<TokenIndexJob>,
+    tokens_started: Option<Instant>,
+    /// The query was completed but not yet searched
+    search_pending: bool,
+    /// When the typed query gets searched, unless another key comes first
+    search_due: Option<Instant>,
     file_type_filter: FileTypeFilter,
-    results: Vec<PathBuf>,
+    results: Vec<SearchResult>,
+    /// Selected results, in the order they were selected
+    selection: Vec<PathBuf>,
     search_time_ms: f64,
+    browse_page: usize,
+    browse_total: Option<usize>,
+    search_hint: Option<String>,
+    /// Why the query cannot be parsed, shown in red under the search box
+    query_error: Option<String>,
+    /// The last search hit the candidate cap
+    results_truncated: bool,
+    /// Matches of the last filename query, narrowed while the user keeps typing
+    search_cache: Arc<Mutex<ShardedCache>>,
+    /// Search running on a worker thread; a newer query cancels it
+    search_job: Option<SearchJob>,
+    /// The search job has sent nothing yet, so its first batch replaces the
+    /// results of the query before
+    stream_replaces: bool,
+    /// Nothing matched exactly, so the results come from the fuzzy search
+    fuzzy_fallback: bool,
+    /// Close filenames offered when the search found nothing
+    did_you_mean: Vec<String>,
+    /// The search ran out of time, so the results are only the first found
+    results_timed_out: bool,
+    /// Searches file contents when asked after a filename search finds nothing
+    content_search: ContentSearcher,
+    /// Lines found by the content search, as they stream in
+    content_matches: Vec<ContentMatch>,
+    /// How the last content search ended, once it has
+    content_summary: Option<GrepSummary>,
+    /// Duplicate scan running on a worker thread
+    duplicate_job: Option<DuplicateJob>,
+    /// Files sharing a name, shown instead of the results until closed
+    duplicates: Option<DuplicateReport>,
+    /// Group duplicates by size as well as name
+    duplicates_by_size: bool,
+    /// Other paths of results that stand for several aliases of one file
+    aliases: AHashMap<PathBuf, Vec<PathBuf>>,
+    /// Aliases of the shown results being resolved on a worker thread
+    alias_job: Option<AliasJob>,
+    /// Result and query shown in the "Why this match?" window
+    match_info: Option<(PathBuf, String)>,
     last_error: Option<String>,
-    show_settings: bool,
-    show_welcome: bool,
+    /// Why the app is running in ephemeral mode (storage unwritable), if it is
+    storage_warning: Option<String>,
+    modals: ModalStack,
+    focus: FocusTarget,
     settings_tab: SettingsTab,
-    last_save: Instant,
+    /// Memory estimate shown in the Statistics tab, with the generation it
+    /// was taken at
+    memory_usage: Option<(u64, MemoryBreakdown)>,
+    /// Folders holding the most files directly, as shown in the Statistics
+    /// tab, with the generation they were counted at
+    largest_folders: Option<(u64, Vec<(PathBuf, usize)>)>,
+    /// Entries per drive letter for the Statistics tab, with the generation
+    /// they were counted at
+    drive_counts: Option<(u64, Vec<(char, usize)>)>,
+    /// Most common extensions for the Statistics tab, with the generation
+    /// they were counted at
+    top_extensions: Option<(u64, Vec<(String, usize)>)>,
+    /// Files of each kind group, in `Config::kind_groups` order, shown in
+    /// the filter dropdown; counted when it opens at a new generation
+    kind_counts: Option<(u64, Vec<usize>)>,
+    /// Drives the index holds, so applying a new selection only touches
+    /// the drives that changed
+    indexed_drives: Vec<char>,
+    saver: Arc<SaveCoordinator>,
+    /// Elevated helper scan of permission-denied folders, while it runs
+    elevated_scan: Option<ElevatedScan>,
+    /// Latest sampled index verification, computed in the background
+    health: Arc<RwLock<Option<HealthReport>>>,
+    health_running: Arc<AtomicBool>,
+    started_at: Instant,
+    result_windows: Vec<ResultsWindow>,
+    /// On-demand size total for the current results
+    size_job: Option<SizeJob>,
+    /// Source of unique viewport ids for results windows
+    next_window: u64,
+    /// Watched saved searches, checked by the watcher against new files
+    alerts: Arc<SearchAlerts>,
+    /// Saved-search alerts on screen, with when they arrived
+    toasts: Vec<(SearchAlert, Instant)>,
+    /// Daily counts of files added and removed, kept by scans and the watcher
+    activity: Arc<ActivityLog>,
+    /// Last week's summary, shown on the empty state until dismissed
+    weekly_card: Option<WeekSummary>,
+    /// Monday of the week shown in the Statistics tab
+    activity_week: i64,
+    /// Reloadable log filter; dropping it on exit flushes the log file
+    logging: LogControl,
+    /// Module filter being edited, applied only once it parses
+    log_filter_input: String,
+    log_filter_error: Option<String>,
+    batch_rename: Option<BatchRenameDialog>,
+    /// Last applied batch rename, renamed back on undo; kept for the session
+    rename_undo: Vec<(PathBuf, PathBuf)>,
+    space_usage: Option<SpaceUsageView>,
+    /// Saved index snapshots, newest first
+    snapshots: Vec<SnapshotInfo>,
+    snapshot_job: Option<SnapshotJob>,
+    snapshot_name: String,
+    /// Whole-index CSV export running in the background
+    export_job: Option<ExportJob>,
+    /// Saved search whose desktop shortcut would replace an existing one
+    pending_shortcut: Option<SavedSearch>,
+    /// Snapshot being renamed: its id and the name being edited
+    snapshot_rename: Option<(String, String)>,
+    /// Snapshot compared with the index, shown in the diff window
+    snapshot_diff: Option<(SnapshotInfo, Arc<SnapshotDiff>)>,
+    new_exclusion: String,
+    /// Patterns for the next include filter, before its folder is picked
+    new_include_patterns: String,
+    new_network_location: String,
+    exclusions_changed: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum SettingsTab {
     Configuration,
     Drives,
+    Locations,
     Statistics,
+    Health,
     Status,
+    SavedSearches,
     Directories,
     About,
 }
 
 impl FlashFindApp {
     /// Create a new FlashFindApp instance
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        // Initialize logging
-        init_logging();
+    pub fn new(cc: &eframe::CreationContext<'_>, launch: Option<LaunchSearch>) -> Self {
+        // Initialize logging; the configured level is applied once config is loaded
+        let logging = LogControl::init(LogLevel::default_for_build(), "");
         
         info!("FlashFind starting up");
         
+        // Detect unwritable storage once, instead of failing every save
+        let storage_warning = match check_storage() {
+            Ok(_) => None,
+            Err(e) => {
+                warn!("App data storage unavailable ({}), running in ephemeral mode", e);
+                Some(e.user_message())
+            }
+        };
+        let ephemeral = storage_warning.is_some();
+        
         // Load configuration
-        let config = Config::load().unwrap_or_else(|e| {
+        let mut config = Config::load().unwrap_or_else(|e| {
             warn!("Failed to load config ({}), using defaults", e);
             Config::default()
         });
+        config.ephemeral = ephemeral;
+        
+        if let Err(e) = logging.apply(config.log_level, &config.log_module_filter) {
+            warn!("Ignoring configured log filter: {}", e);
+            let _ = logging.apply(config.log_level, "");
+        }
+        let log_filter_input = config.log_module_filter.clone();
+        redact::set_enabled(config.log_redaction);
+        fold::set_ignore_accents(config.ignore_accents);
+        kinds::set_kind_groups(&config.kind_groups);
+        info!("Log path redaction: {}", if redact::is_enabled() { "on" } else { "off" });
         
         // Check if this is first launch for welcome screen
-        let show_welcome = config.first_launch;
+        let mut modals = ModalStack::default();
+        if config.first_launch {
+            modals.open(Modal::Welcome);
+        }
         
         // Setup UI styling with theme
         setup_ui_style(&cc.egui_ctx, config.theme);
         
-        // Load or create index
-        let index = match load_index() {
+        // Load or create index (ephemeral mode always starts empty)
+        let loaded = if ephemeral { Ok(ShardedIndex::new()) } else { load_index() };
+        let index = match loaded {
             Ok(idx) => {
-                info!("Loaded existing index with {} files", idx.len());
-                Arc::new(RwLock::new(idx))
+                info!("Loaded existing index with {} files", idx.read().len());
+                idx
             }
             Err(e) => {
                 warn!("Failed to load index ({}), creating new one", e);
-                Arc::new(RwLock::new(FileIndex::new()))
+                ShardedIndex::new()
             }
         };
+        let mut settings = index.write();
+        settings.sync_key_folding();
+        settings.set_trigram_index(config.trigram_index);
+        settings.set_acronym_matching(config.acronym_matching);
+        settings.set_show_hidden(config.show_hidden_files);
+        drop(settings);
+        let index = Arc::new(index);
+        
+        // Shards left by a helper scan from an earlier session are never imported
+        cleanup_stale_shards();
+        
+        // Exclusion rules shared by the scanner and the watcher
+        let exclusions = Arc::new(RwLock::new(Exclusions::from_config(&config)));
+        
+        // All saves go through one coordinator so they never overlap
+        let saver = Arc::new(if ephemeral {
+            SaveCoordinator::ephemeral(index.clone())
+        } else {
+            SaveCoordinator::new(index.clone())
+        });
+        
+        // Opt-in daily rollups for the weekly summary, kept in memory only
+        // when storage is unwritable
+        let activity = Arc::new(if ephemeral { ActivityLog::default() } else { ActivityLog::open_default(config.weekly_summary) });
+        activity.set_enabled(config.weekly_summary);
+        
+        // The first launch of a week greets the user with last week's summary
+        let this_week = week_start(today());
+        let mut weekly_card = None;
+        if config.weekly_summary && config.weekly_summary_seen < this_week {
+            weekly_card = Some(activity.week(this_week - 7)).filter(|week| !week.is_empty());
+            config.weekly_summary_seen = this_week;
+            if let Err(e) = config.save() {
+                warn!("Failed to save config after weekly summary: {}", e);
+            }
+        }
         
         // Create indexer
-        let indexer = match Indexer::new(index.clone()) {
+        let indexer = match Indexer::new(index.clone(), exclusions.clone(), saver.clone(), activity.clone()) {
             Ok(idx) => idx,
             Err(e) => {
                 error!("Failed to create indexer: {}", e);
@@ -128,8 +548,16 @@ impl FlashFindApp {
             }
         };
         
+        // Availability of indexed roots, shared with the watcher
+        let roots = Arc::new(RootMonitor::new());
+        roots.set_roots(&get_default_directories());
+        
+        // Saved searches the watcher raises alerts for
+        let alerts = Arc::new(SearchAlerts::new());
+        alerts.set_watches(&config.saved_searches);
+        
         // Setup filesystem watcher
-        let watcher = match Watcher::new(index.clone()) {
+        let watcher = match Watcher::new(index.clone(), exclusions.clone(), roots.clone(), alerts.clone(), activity.clone()) {
             Ok(mut w) => {
                 let dirs = get_default_directories();
                 match w.watch_directories(dirs) {
@@ -156,55 +584,414 @@ impl FlashFindApp {
             if let Err(e) = indexer.start_scan(dirs) {
                 error!("Failed to start initial scan: {}", e);
             }
+        } else if config.prune_on_load {
+            // Files deleted while the app was closed stay listed until pruned
+            if let Err(e) = indexer.prune(get_default_directories()) {
+                error!("Failed to start pruning: {}", e);
+            }
         }
         
-        Self {
+        let content_search = ContentSearcher::new(index.clone());
+        
+        let mut app = Self {
             index,
             indexer,
             watcher,
+            exclusions,
+            roots,
             config,
             query: String::new(),
+            suggestions: Vec::new(),
+            suggestions_open: false,
+            suggestion_selected: None,
+            history_recall: HistoryRecall::default(),
+            token_index: None,
+            token_job: None,
+            tokens_started: None,
+            search_pending: false,
+            search_due: None,
             file_type_filter: FileTypeFilter::All,
             results: Vec::new(),
+            selection: Vec::new(),
             search_time_ms: 0.0,
+            browse_page: 0,
+            browse_total: None,
+            search_hint: None,
+            query_error: None,
+            results_truncated: false,
+            search_cache: Arc::new(Mutex::default()),
+            search_job: None,
+            stream_replaces: false,
+            fuzzy_fallback: false,
+            did_you_mean: Vec::new(),
+            results_timed_out: false,
+            content_search,
+            content_matches: Vec::new(),
+            content_summary: None,
+            duplicate_job: None,
+            duplicates: None,
+            duplicates_by_size: true,
+            aliases: AHashMap::new(),
+            alias_job: None,
+            match_info: None,
             last_error: None,
-            show_settings: false,
-            show_welcome,
+            storage_warning,
+            modals,
+            focus: FocusTarget::Search,
             settings_tab: SettingsTab::Configuration,
-            last_save: Instant::now(),
+            memory_usage: None,
+            largest_folders: None,
+            drive_counts: None,
+            top_extensions: None,
+            kind_counts: None,
+            indexed_drives: Vec::new(),
+            saver,
+            elevated_scan: None,
+            health: Arc::new(RwLock::new(None)),
+            health_running: Arc::new(AtomicBool::new(false)),
+            started_at: Instant::now(),
+            result_windows: Vec::new(),
+            size_job: None,
+            next_window: 0,
+            alerts,
+            toasts: Vec::new(),
+            activity,
+            weekly_card,
+            activity_week: this_week,
+            log_filter_input,
+            log_filter_error: None,
+            logging,
+            batch_rename: None,
+            rename_undo: Vec::new(),
+            space_usage: None,
+            snapshots: SnapshotStore::open_default().map(|store| store.list()).unwrap_or_default(),
+            snapshot_job: None,
+            snapshot_name: String::new(),
+            export_job: None,
+            snapshot_rename: None,
+            snapshot_diff: None,
+            pending_shortcut: None,
+            new_exclusion: String::new(),
+            new_include_patterns: String::new(),
+            new_network_location: String::new(),
+            exclusions_changed: false,
+        };
+        // The saved selection is the one the index was last built from
+        app.indexed_drives = app.config.enabled_drives.clone();
+        
+        if let Some(launch) = launch {
+            let filter = FileTypeFilter::from_id(launch.filter.as_deref(), &app.config.kind_groups);
+            app.run_saved_search(launch.query, filter);
         }
+        app
     }
     
-    /// Perform a search
+    /// Perform a search, replacing any search still running
     fn do_search(&mut self) {
         let start = Instant::now();
-        let all_results = self.index.read().search(&self.query);
         
-        // Apply file type filter
-        self.results = if matches!(self.file_type_filter, FileTypeFilter::All) {
-            all_results
+        if let Some(job) = self.search_job.take() {
+            job.cancel();
+        }
+        self.browse_total = None;
+        self.search_hint = None;
+        self.query_error = None;
+        self.results_truncated = false;
+        self.fuzzy_fallback = false;
+        self.did_you_mean.clear();
+        self.results_timed_out = false;
+        self.search_pending = false;
+        self.search_due = None;
+        self.selection.clear();
+        self.content_search.cancel();
+        self.content_matches.clear();
+        self.content_summary = None;
+        self.duplicate_job = None;
+        self.duplicates = None;
+        if let Some(job) = self.size_job.take() {
+            job.cancel();
+        }
+        let offset = self.browse_page * PATH_PAGE_SIZE;
+        
+        // Path-like and `*` queries browse the index one page at a time
+        let all_results = match parse_query(&self.query) {
+            ParsedQuery::Empty => Vec::new(),
+            ParsedQuery::Filename(_) if needs_more_typing(&self.query, self.config.min_query_length) => {
+                self.search_hint = Some(format!("Keep typing… names are searched from {} characters", self.config.min_query_length));
+                Vec::new()
+            }
+            ParsedQuery::Hint(hint) => {
+                self.search_hint = Some(hint.to_string());
+                Vec::new()
+            }
+            ParsedQuery::Invalid(reason) => {
+                self.query_error = Some(reason);
+                Vec::new()
+            }
+            // Matching can take a while on a large index, so it runs on a worker
+            // and the previous results stay up until it reports back
+            ParsedQuery::BrowseAll
+            | ParsedQuery::PathPrefix(_)
+            | ParsedQuery::FullPath(_)
+            | ParsedQuery::Size(_)
+            | ParsedQuery::Modified(_)
+            | ParsedQuery::Boolean(_)
+            | ParsedQuery::Glob(_)
+            | ParsedQuery::Regex(_)
+            | ParsedQuery::Extension(_)
+            | ParsedQuery::Extensions(_)
+            | ParsedQuery::Kind { .. }
+            | ParsedQuery::Hidden
+            | ParsedQuery::StartsWith(_)
+            | ParsedQuery::Words(_)
+            | ParsedQuery::WholeWord(_)
+            | ParsedQuery::InFolder { .. }
+            | ParsedQuery::Filename(_) => {
+                let request = SearchRequest {
+                    query: self.query.clone(),
+                    limit: self.config.max_search_candidates,
+                    sort: self.config.sort_order,
+                    profile: self.config.ranking_preset.profile(&self.config.custom_ranking),
+                    fuzzy: self.config.fuzzy_search,
+                    typo_tolerance: self.config.typo_tolerance,
+                    offset,
+                    timeout: Some(Duration::from_millis(self.config.search_timeout_ms)).filter(|limit| !limit.is_zero()),
+                    favorites: self.config.favorite_folders.clone(),
+                    favorites_only: self.config.favorites_only,
+                    whole_word: self.config.whole_word,
+                };
+                self.search_job = Some(SearchJob::start(self.index.clone(), request, self.search_cache.clone()));
+                self.stream_replaces = true;
+                // Aliases of the previous results no longer apply
+                self.alias_job = None;
+                return;
+            }
+        };
+        
+        let all_results = self.index.describe(all_results);
+        self.show_results(all_results, start.elapsed());
+    }
+    
+    /// Search the typed query once typing has paused for
+    /// `Config::search_debounce_ms`
+    fn run_due_search(&mut self, ctx: &egui::Context) {
+        let Some(due) = self.search_due else {
+            return;
+        };
+        let now = Instant::now();
+        if now >= due {
+            self.do_search();
         } else {
-            all_results.into_iter()
-                .filter(|path| self.file_type_filter.matches(path))
-                .collect()
+            ctx.request_repaint_after(due - now);
+        }
+    }
+    
+    /// Pick up the results the background search has sent since the last frame
+    fn poll_search_job(&mut self, ctx: &egui::Context) {
+        let Some(job) = &self.search_job else {
+            return;
+        };
+        let batches = job.take_batches();
+        self.apply_batches(batches);
+        if self.search_job.is_some() {
+            ctx.request_repaint_after(Duration::from_millis(15));
+        }
+    }
+    
+    /// Append streamed results to the shown ones, ending the search at its
+    /// last batch
+    fn apply_batches(&mut self, batches: Vec<SearchBatch>) {
+        for batch in batches {
+            match batch {
+                SearchBatch::Results(results) => {
+                    if std::mem::take(&mut self.stream_replaces) {
+                        self.results.clear();
+                    }
+                    self.append_results(results);
+                }
+                SearchBatch::Finished(summary) => {
+                    let elapsed = self.search_job.take().map(|job| job.elapsed()).unwrap_or_default();
+                    self.finish_search(summary, elapsed);
+                }
+            }
+        }
+    }
+    
+    /// Tidy edited kinds, then use them for `kind:` queries and the filter
+    ///
+    /// Kinds without a name, or repeating an earlier name, are dropped.
+    fn apply_kind_groups(&mut self) {
+        let mut groups: Vec<KindGroup> = Vec::new();
+        for mut group in std::mem::take(&mut self.config.kind_groups) {
+            group.normalize();
+            if !group.name.is_empty() && !groups.iter().any(|kept| kept.name == group.name) {
+                groups.push(group);
+            }
+        }
+        self.config.kind_groups = groups;
+        kinds::set_kind_groups(&self.config.kind_groups);
+        self.kind_counts = None;
+        
+        if let FileTypeFilter::Kind(name) = &self.file_type_filter {
+            if !self.config.kind_groups.iter().any(|group| &group.name == name) {
+                self.file_type_filter = FileTypeFilter::All;
+            }
+        }
+        if let Err(e) = self.config.save() {
+            warn!("Failed to save config: {}", e);
+        }
+        self.do_search();
+    }
+    
+    /// Look for indexed files that share a name on a worker thread
+    fn find_duplicates(&mut self) {
+        self.duplicates = None;
+        self.duplicate_job = Some(DuplicateJob::start(self.index.clone(), self.duplicates_by_size));
+    }
+    
+    /// Show the duplicate groups once the scan has finished
+    fn poll_duplicate_job(&mut self, ctx: &egui::Context) {
+        let Some(job) = &self.duplicate_job else {
+            return;
         };
+        match job.take_report() {
+            Some(report) => {
+                self.duplicates = Some(report);
+                self.duplicate_job = None;
+            }
+            None => ctx.request_repaint_after(Duration::from_millis(50)),
+        }
+    }
+    
+    /// Search the contents of indexed text files for the query
+    fn start_content_search(&mut self) {
+        self.content_matches.clear();
+        self.content_summary = None;
+        if let Err(e) = self.content_search.search(&self.query) {
+            error!("Failed to start content search: {}", e);
+            self.last_error = Some(e.user_message());
+        }
+    }
+    
+    /// Collect lines the content search found since the last frame
+    fn poll_content_search(&mut self, ctx: &egui::Context) {
+        if !self.content_search.is_running() {
+            return;
+        }
+        for event in self.content_search.poll() {
+            match event {
+                GrepEvent::Match(found) => self.content_matches.push(found),
+                GrepEvent::Finished(summary) => self.content_summary = Some(summary),
+            }
+        }
+        ctx.request_repaint_after(Duration::from_millis(50));
+    }
+    
+    /// Wait for the background search, so Enter opens a result of the query
+    /// as typed rather than of the one before it
+    fn wait_for_search(&mut self) {
+        if let Some(job) = &self.search_job {
+            let batches = job.wait();
+            self.apply_batches(batches);
+        }
+    }
+    
+    fn finish_search(&mut self, summary: Result<SearchSummary, FlashFindError>, elapsed: Duration) {
+        // A search that found nothing sent no batch to replace the old results
+        if std::mem::take(&mut self.stream_replaces) {
+            self.results.clear();
+        }
+        match summary {
+            Ok(summary) => {
+                self.results_truncated = summary.truncated;
+                self.fuzzy_fallback = summary.fuzzy_fallback;
+                self.browse_total = summary.total;
+                self.did_you_mean = summary.suggestions;
+                self.results_timed_out = summary.timed_out;
+                self.settle_results(elapsed);
+            }
+            // Only jobs replaced by a newer query are cancelled, and those are dropped
+            Err(FlashFindError::Cancelled) => {}
+            Err(FlashFindError::InvalidQuery(reason)) => self.query_error = Some(reason),
+            Err(e) => self.last_error = Some(e.user_message()),
+        }
+    }
+    
+    /// Filter, deduplicate and display the matches of a finished search
+    fn show_results(&mut self, all_results: Vec<SearchResult>, elapsed: Duration) {
+        self.results.clear();
+        self.append_results(all_results);
+        self.settle_results(elapsed);
+    }
+    
+    /// Add matches below the shown results, through the file type filter
+    fn append_results(&mut self, results: Vec<SearchResult>) {
+        if matches!(self.file_type_filter, FileTypeFilter::All) {
+            self.results.extend(results);
+        } else {
+            let groups = &self.config.kind_groups;
+            self.results.extend(results.into_iter().filter(|result| self.file_type_filter.matches(result, groups)));
+        }
+    }
+    
+    /// Deduplicate the results once every batch is in, and time the search
+    fn settle_results(&mut self, elapsed: Duration) {
+        // Collapse junction and drive-letter aliases of the same file, once
+        // their paths are resolved off the UI thread
+        self.aliases.clear();
+        self.alias_job = None;
+        if self.config.merge_aliases {
+            let candidates = alias_candidates(&self.results, DEDUP_WINDOW);
+            if !candidates.is_empty() {
+                self.alias_job = Some(AliasJob::start(candidates));
+            }
+        }
         
-        self.search_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+        self.search_time_ms = elapsed.as_secs_f64() * 1000.0;
         debug!("Search completed in {:.2}ms, {} results after filter", self.search_time_ms, self.results.len());
     }
     
-    /// Handle manual save button
+    /// Merge aliases among the shown results once their paths are resolved
+    fn poll_alias_job(&mut self, ctx: &egui::Context) {
+        let Some(job) = &self.alias_job else {
+            return;
+        };
+        match job.take_identities() {
+            Some(identities) => {
+                self.alias_job = None;
+                let deduped = dedup_results(std::mem::take(&mut self.results), DEDUP_WINDOW, |path| identities.get(path).cloned());
+                self.results = deduped.paths;
+                self.aliases = deduped.aliases;
+            }
+            None => ctx.request_repaint_after(Duration::from_millis(50)),
+        }
+    }
+    
+    /// Handle manual save button and auto-save
     fn handle_save(&mut self) {
-        match save_index(&*self.index.read()) {
-            Ok(()) => {
+        if self.saver.is_ephemeral() {
+            self.last_error = Some("Saving is disabled: app storage is not writable".to_string());
+            return;
+        }
+        
+        match self.saver.save() {
+            Ok(true) => {
                 info!("Manual save successful");
                 self.last_error = None;
             }
+            Ok(false) => {
+                debug!("Save request merged into the save already running");
+                self.last_error = None;
+            }
             Err(e) => {
                 error!("Manual save failed: {}", e);
                 self.last_error = Some(e.user_message());
             }
         }
+        
+        if let Err(e) = self.activity.save() {
+            warn!("Failed to save activity rollups: {}", e);
+        }
     }
     
     /// Handle re-index button
@@ -222,147 +1009,1942 @@ impl FlashFindApp {
         }
     }
     
-    /// Safely open a file
-    fn open_file(&mut self, path: &Path) {
-        // Sanitize path
-        if !Self::is_safe_path(path) {
-            self.last_error = Some(format!("Unsafe path: {}", path.display()));
-            warn!("Attempted to open unsafe path: {}", path.display());
-            return;
-        }
+    /// Close a modal window, applying any side effects of dismissing it
+    fn close_modal(&mut self, modal: Modal) {
+        self.modals.close(modal);
         
-        if !path.exists() {
-            self.last_error = Some(format!("File not found: {}", path.display()));
-            return;
+        // Closing the welcome screen ends the first launch
+        if modal == Modal::Welcome && self.config.first_launch {
+            self.config.first_launch = false;
+            if let Err(e) = self.config.save() {
+                warn!("Failed to save config after welcome: {}", e);
+            }
+        }
+    }
+    
+    /// Persist exclusion settings and push them to the scanner and watcher
+    fn apply_exclusion_settings(&mut self) {
+        *self.exclusions.write() = Exclusions::from_config(&self.config);
+        self.exclusions_changed = true;
+        if let Err(e) = self.config.save() {
+            warn!("Failed to save config: {}", e);
+        }
+    }
+    
+    /// Drop newly excluded entries and rescan so newly included files appear
+    fn rescan_for_exclusions(&mut self) {
+        let rules = self.exclusions.read().clone();
+        let archives_off = rules.archive_limit().is_none();
+        let mut removed = self.index.write().remove_where(|path| {
+            rules.is_excluded(path) || (archives_off && entry_kind(path) == EntryKind::ArchiveMember)
+        });
+        removed += self.index.write().remove_files_where(|path| !rules.includes(path));
+        if !rules.indexes_directories() {
+            removed += self.index.write().remove_directories();
         }
+        info!("Exclusion change removed {} entries, rescanning", removed);
         
-        match open::that(path) {
-            Ok(()) => debug!("Opened file: {}", path.display()),
+        let dirs = platform::configured_directories(&self.config);
+        match self.indexer.start_scan(dirs) {
+            Ok(()) => {
+                self.exclusions_changed = false;
+                self.last_error = None;
+            }
             Err(e) => {
-                error!("Failed to open file: {}", e);
-                self.last_error = Some(format!("Cannot open file: {}", e));
+                error!("Failed to start rescan: {}", e);
+                self.last_error = Some(e.user_message());
             }
         }
+        
+        if !self.query.is_empty() {
+            self.do_search();
+        }
     }
     
-    /// Safely open a folder
-    fn open_folder(&mut self, path: &Path) {
-        // Sanitize path
-        if !Self::is_safe_path(path) {
-            self.last_error = Some(format!("Unsafe path: {}", path.display()));
-            warn!("Attempted to open unsafe path: {}", path.display());
+    /// Save the drive/location selection, then rebuild and re-watch the index
+    fn apply_index_locations(&mut self) {
+        if let Err(e) = self.config.save() {
+            warn!("Failed to save config: {}", e);
+            self.last_error = Some(format!("Failed to save config: {}", e.user_message()));
             return;
         }
         
-        match open::that(path) {
-            Ok(()) => debug!("Opened folder: {}", path.display()),
-            Err(e) => {
-                error!("Failed to open folder: {}", e);
-                self.last_error = Some(format!("Cannot open folder: {}", e));
-            }
+        // Clear and re-index with the new selection
+        let dirs = platform::configured_directories(&self.config);
+        if let Err(e) = self.indexer.rebuild(dirs.clone()) {
+            error!("Failed to start re-indexing: {}", e);
+            self.last_error = Some(e.user_message());
+            return;
         }
+        self.indexed_drives = self.config.enabled_drives.clone();
+        self.watch_index_roots(dirs);
     }
     
-    /// Export search results to CSV file
-    fn export_to_csv(&mut self) {
-        use std::fs::File;
-        use std::io::Write;
+    /// Save the drive selection, then drop the entries of drives turned off
+    /// and scan only the drives turned on; other drives keep their entries
+    fn apply_drive_selection(&mut self) {
+        if let Err(e) = self.config.save() {
+            warn!("Failed to save config: {}", e);
+            self.last_error = Some(format!("Failed to save config: {}", e.user_message()));
+            return;
+        }
         
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        let enabled = &self.config.enabled_drives;
+        let removed: Vec<char> = self.indexed_drives.iter().filter(|d| !enabled.contains(d)).copied().collect();
+        let added: Vec<char> = enabled.iter().filter(|d| !self.indexed_drives.contains(d)).copied().collect();
+        if removed.is_empty() && added.is_empty() {
+            self.last_error = Some("✓ Drive selection unchanged".to_string());
+            return;
+        }
         
-        let filename = format!("flashfind_export_{}.csv", timestamp);
-        let export_path = std::env::current_dir()
-            .unwrap_or_else(|_| std::path::PathBuf::from("."))
-            .join(&filename);
+        if let Err(e) = self.indexer.change_drives(removed, platform::get_directories_for_drives(&added)) {
+            error!("Failed to update drives: {}", e);
+            self.last_error = Some(e.user_message());
+            return;
+        }
+        self.indexed_drives = self.config.enabled_drives.clone();
+        self.watch_index_roots(platform::configured_directories(&self.config));
+    }
+    
+    /// Track availability of and watch the folders now indexed
+    fn watch_index_roots(&mut self, dirs: Vec<PathBuf>) {
+        self.roots.set_roots(&dirs);
         
-        match File::create(&export_path) {
-            Ok(mut file) => {
-                // Write CSV header
-                if let Err(e) = writeln!(file, "Path,Filename,Extension,Size") {
-                    self.last_error = Some(format!("Failed to write CSV: {}", e));
-                    return;
-                }
-                
-                // Write each result
-                for path in &self.results {
-                    let filename = path.file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or("N/A");
-                    
-                    let extension = path.extension()
-                        .and_then(|e| e.to_str())
-                        .unwrap_or("N/A");
-                    
-                    let size = std::fs::metadata(path)
-                        .ok()
-                        .map(|m| m.len())
-                        .unwrap_or(0);
-                    
-                    let path_str = path.to_string_lossy();
-                    
-                    if let Err(e) = writeln!(file, "\"{}\",\"{}\",{},{}", path_str, filename, extension, size) {
-                        warn!("Failed to write row: {}", e);
+        // Update watcher
+        if let Some(ref mut watcher) = self.watcher {
+            match watcher.watch_directories(dirs.clone()) {
+                Ok(errors) => {
+                    for err in errors {
+                        warn!("Watcher error: {}", err);
                     }
                 }
-                
-                info!("Exported {} results to {}", self.results.len(), export_path.display());
-                self.last_error = Some(format!("✓ Exported to {}", filename));
-                
-                // Open the folder containing the CSV
-                if let Some(parent) = export_path.parent() {
-                    let _ = open::that(parent);
-                }
-            }
-            Err(e) => {
-                error!("Failed to create CSV file: {}", e);
-                self.last_error = Some(format!("Failed to export: {}", e));
+                Err(e) => error!("Failed to setup watchers: {}", e),
             }
         }
+        info!("Indexing {} directories", dirs.len());
     }
     
-    /// Validate path is safe to open (no command injection, symlink attacks)
-    fn is_safe_path(path: &Path) -> bool {
-        let path_str = path.to_string_lossy();
-        
-        // Reject paths with suspicious characters
-        if path_str.contains('&') || path_str.contains('|') || path_str.contains(';') {
-            return false;
+    /// Validate a path against the configured open policy
+    fn check_open(&self, path: &Path, allow_missing: bool) -> crate::error::Result<()> {
+        if let Some(root) = self.roots.offline_root(path) {
+            return Err(FlashFindError::RootUnavailable(root.display().to_string()));
         }
         
-        // Reject UNC paths that could be malicious
-        if path_str.starts_with("\\\\") {
-            return false;
-        }
+        let roots = match &self.watcher {
+            Some(w) => w.watched_directories().to_vec(),
+            None => platform::configured_directories(&self.config),
+        };
         
-        // Path must be absolute
-        if !path.is_absolute() {
-            return false;
+        OpenPolicy {
+            roots: &roots,
+            network_locations: &self.config.network_locations,
+            strict: self.config.strict_open_mode,
         }
-        
-        true
+        .check(path, allow_missing)
     }
     
-    /// Render settings window
-    fn render_settings(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
-        ui.horizontal(|ui| {
-            ui.selectable_value(&mut self.settings_tab, SettingsTab::Configuration, "⚙️ Configuration");
-            ui.selectable_value(&mut self.settings_tab, SettingsTab::Drives, "💾 Drives");
-            ui.selectable_value(&mut self.settings_tab, SettingsTab::Statistics, "📊 Statistics");
-            ui.selectable_value(&mut self.settings_tab, SettingsTab::Status, "⚙️ Status");
-            ui.selectable_value(&mut self.settings_tab, SettingsTab::Directories, "👁 Directories");
-            ui.selectable_value(&mut self.settings_tab, SettingsTab::About, "ℹ About");
-        });
+    /// Safely open a file; archive members are extracted to temp first, and
+    /// indexed folders open as folders
+    fn open_file(&mut self, path: &Path) {
+        if self.index.is_directory(path) {
+            self.open_folder(path);
+            return;
+        }
         
-        ui.separator();
-        ui.add_space(10.0);
+        if let Err(e) = self.check_open(&backing_file(path), false) {
+            warn!("Refused to open {}: {}", redact(&path), e);
+            self.last_error = Some(e.user_message());
+            return;
+        }
         
-        egui::ScrollArea::vertical()
-            .max_height(400.0)
-            .show(ui, |ui| {
-                match self.settings_tab {
+        let target = match entry_kind(path) {
+            EntryKind::File => to_long_path(path),
+            EntryKind::ArchiveMember => match extract_member(path) {
+                Ok(extracted) => extracted,
+                Err(e) => {
+                    error!("Failed to extract {}: {}", redact(&path), e);
+                    self.last_error = Some(e.user_message());
+                    return;
+                }
+            },
+        };
+        
+        match open::that(target) {
+            Ok(()) => debug!("Opened file: {}", redact(&path)),
+            Err(e) => {
+                error!("Failed to open file: {}", e);
+                self.last_error = Some(format!("Cannot open file: {}", e));
+            }
+        }
+    }
+    
+    /// Safely open the folder containing a file (the file itself may be gone)
+    ///
+    /// For archive members this is the folder holding the archive.
+    fn reveal_in_folder(&mut self, path: &Path) {
+        let path = &backing_file(path);
+        if let Err(e) = self.check_open(path, true) {
+            warn!("Refused to reveal {}: {}", redact(&path), e);
+            self.last_error = Some(e.user_message());
+            return;
+        }
+        
+        let Some(parent) = path.parent() else {
+            return;
+        };
+        
+        match open::that(to_long_path(parent)) {
+            Ok(()) => debug!("Opened folder: {}", redact(&parent)),
+            Err(e) => {
+                error!("Failed to open folder: {}", e);
+                self.last_error = Some(format!("Cannot open folder: {}", e));
+            }
+        }
+    }
+    
+    /// Safely open a folder itself, as listed by the space usage view
+    fn open_folder(&mut self, dir: &Path) {
+        if let Err(e) = self.check_open(dir, false) {
+            warn!("Refused to open {}: {}", redact(&dir), e);
+            self.last_error = Some(e.user_message());
+            return;
+        }
+        
+        match open::that(to_long_path(dir)) {
+            Ok(()) => debug!("Opened folder: {}", redact(&dir)),
+            Err(e) => {
+                error!("Failed to open folder: {}", e);
+                self.last_error = Some(format!("Cannot open folder: {}", e));
+            }
+        }
+    }
+    
+    /// Start aggregating the indexed files under `root` and show the view
+    fn open_space_usage(&mut self, root: PathBuf) {
+        if let Some(view) = &self.space_usage {
+            view.job.cancel();
+        }
+        self.space_usage = Some(SpaceUsageView {
+            job: UsageJob::start(self.index.clone(), root.clone()),
+            root,
+            trail: Vec::new(),
+            sort: UsageSort::default(),
+        });
+        self.modals.open(Modal::SpaceUsage);
+    }
+    
+    /// Folder list of the space usage view, one level at a time
+    fn render_space_usage(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let Some(view) = &mut self.space_usage else {
+            return;
+        };
+        
+        let progress = view.job.progress();
+        if !progress.done {
+            let mut cancel = false;
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(format!("Sizing {} of {} indexed files...", progress.processed, progress.total));
+                cancel = ui.button("Cancel").clicked();
+            });
+            if cancel {
+                view.job.cancel();
+            }
+            ctx.request_repaint_after(Duration::from_millis(100));
+            return;
+        }
+        let Some(report) = progress.report else {
+            ui.label("Cancelled.");
+            if ui.button("🔄 Start again").clicked() {
+                let root = view.root.clone();
+                self.open_space_usage(root);
+            }
+            return;
+        };
+        
+        let current = view.trail.last().unwrap_or(&view.root).clone();
+        let mut action = None;
+        
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!view.trail.is_empty(), egui::Button::new("⬆ Up")).clicked() {
+                action = Some(UsageAction::Up);
+            }
+            ui.label(egui::RichText::new(current.display().to_string()).strong());
+        });
+        
+        let total = report.usage(&current);
+        let mut summary = format!("{} in {} files", format_size(total.bytes), total.files);
+        if total.unsized_files > 0 {
+            summary.push_str(&format!(" ({} without a known size, not in the total)", total.unsized_files));
+        }
+        ui.label(egui::RichText::new(summary).size(12.0));
+        
+        ui.horizontal(|ui| {
+            ui.label("Sort by:");
+            for sort in UsageSort::ALL {
+                ui.selectable_value(&mut view.sort, sort, sort.label());
+            }
+        });
+        ui.add_space(6.0);
+        
+        let children = report.children(&current, view.sort);
+        let direct = report.direct_files(&current);
+        egui::ScrollArea::vertical().id_source("space_usage").max_height(360.0).show(ui, |ui| {
+            egui::Grid::new("space_usage_grid").num_columns(5).spacing([16.0, 4.0]).striped(true).show(ui, |ui| {
+                ui.label(egui::RichText::new("Folder").strong());
+                ui.label(egui::RichText::new("Files").strong());
+                ui.label(egui::RichText::new("Size").strong());
+                ui.label(egui::RichText::new("No size").strong());
+                ui.label("");
+                ui.end_row();
+                
+                for (dir, usage) in &children {
+                    let name = dir.to_string_lossy().rsplit(['/', '\\']).next().unwrap_or_default().to_string();
+                    if ui.link(format!("📁 {}", name)).on_hover_text("Show the folders inside").clicked() {
+                        action = Some(UsageAction::DrillDown(dir.clone()));
+                    }
+                    ui.label(usage.files.to_string());
+                    ui.label(format_size(usage.bytes));
+                    ui.label(if usage.unsized_files > 0 { usage.unsized_files.to_string() } else { String::new() });
+                    ui.horizontal(|ui| {
+                        if ui.small_button("📂").on_hover_text("Open folder").clicked() {
+                            action = Some(UsageAction::OpenFolder(dir.clone()));
+                        }
+                        if ui.small_button("🔍").on_hover_text("Search within this folder").clicked() {
+                            action = Some(UsageAction::SearchWithin(dir.clone()));
+                        }
+                    });
+                    ui.end_row();
+                }
+                
+                if direct.files > 0 {
+                    ui.label(egui::RichText::new("Files in this folder").italics());
+                    ui.label(direct.files.to_string());
+                    ui.label(format_size(direct.bytes));
+                    ui.label(if direct.unsized_files > 0 { direct.unsized_files.to_string() } else { String::new() });
+                    ui.label("");
+                    ui.end_row();
+                }
+            });
+        });
+        
+        match action {
+            Some(UsageAction::DrillDown(dir)) => view.trail.push(dir),
+            Some(UsageAction::Up) => {
+                view.trail.pop();
+            }
+            Some(UsageAction::OpenFolder(dir)) => self.open_folder(&dir),
+            Some(UsageAction::SearchWithin(dir)) => {
+                let dir = dir.to_string_lossy().into_owned();
+                self.query = if is_windows_style(&dir) { format!("{}\\", dir) } else { dir };
+                self.browse_page = 0;
+                self.last_error = None;
+                self.do_search();
+                self.close_modal(Modal::SpaceUsage);
+                self.close_modal(Modal::Settings);
+            }
+            None => {}
+        }
+    }
+    
+    /// Sliders for the Custom ranking weights, re-ranking as they move
+    fn render_custom_ranking(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        let mut released = false;
+        egui::CollapsingHeader::new("Custom ranking weights").default_open(true).show(ui, |ui| {
+            let weights = &mut self.config.custom_ranking;
+            egui::Grid::new("custom_ranking").num_columns(4).spacing([12.0, 4.0]).show(ui, |ui| {
+                for (i, (label, value)) in [
+                    ("Exact name", &mut weights.exact),
+                    ("Prefix", &mut weights.prefix),
+                    ("Word start", &mut weights.token),
+                    ("Substring", &mut weights.substring),
+                    ("Recency", &mut weights.recency),
+                    ("Shallow path", &mut weights.path_depth),
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    ui.label(label);
+                    let slider = ui.add(egui::Slider::new(value, 0.0..=100.0).show_value(false));
+                    changed |= slider.changed();
+                    released |= slider.drag_stopped() || (slider.changed() && !slider.dragged());
+                    if i % 2 == 1 {
+                        ui.end_row();
+                    }
+                }
+            });
+        });
+        
+        if changed {
+            self.do_search();
+        }
+        if released {
+            if let Err(e) = self.config.save() {
+                warn!("Failed to save config: {}", e);
+            }
+        }
+    }
+    
+    /// Save the current query and file type filter as a saved search named after the query
+    fn save_current_search(&mut self) {
+        let filter = (self.file_type_filter != FileTypeFilter::All).then(|| self.file_type_filter.id());
+        if self.config.add_saved_search(&self.query, filter) {
+            self.apply_saved_searches();
+        }
+    }
+    
+    /// Switch incognito searching on or off
+    fn toggle_incognito(&mut self) {
+        let enabled = !privacy::is_incognito();
+        privacy::set_incognito(enabled);
+        info!("Incognito search {}", if enabled { "on" } else { "off" });
+    }
+    
+    /// Persist saved searches and hand the watched ones to the watcher
+    fn apply_saved_searches(&mut self) {
+        self.alerts.set_watches(&self.config.saved_searches);
+        if let Err(e) = self.config.save() {
+            warn!("Failed to save config: {}", e);
+        }
+    }
+    
+    /// Put a saved query in the search box and run it with `filter`
+    fn run_saved_search(&mut self, query: String, filter: FileTypeFilter) {
+        self.query = query;
+        self.file_type_filter = filter;
+        self.browse_page = 0;
+        self.last_error = None;
+        self.do_search();
+    }
+    
+    /// Log level and per-module filter, applied without a restart
+    fn render_logging(&mut self, ui: &mut egui::Ui) {
+        ui.label(egui::RichText::new("📝 Logging").size(14.0).strong());
+        ui.add_space(6.0);
+        
+        let mut level = self.config.log_level;
+        ui.horizontal(|ui| {
+            ui.label("Level:");
+            egui::ComboBox::from_id_source("log_level")
+                .selected_text(level.label())
+                .show_ui(ui, |ui| {
+                    for option in LogLevel::ALL {
+                        ui.selectable_value(&mut level, option, option.label());
+                    }
+                });
+        });
+        if level != self.config.log_level {
+            self.config.log_level = level;
+            self.apply_log_filter();
+        }
+        
+        ui.collapsing("Advanced", |ui| {
+            ui.label(egui::RichText::new("Per-module levels, comma-separated (e.g. flashfind::watcher=debug)").weak().size(11.5));
+            let mut apply = false;
+            ui.horizontal(|ui| {
+                let response = ui.add(egui::TextEdit::singleline(&mut self.log_filter_input)
+                    .hint_text("flashfind::watcher=debug")
+                    .desired_width(280.0));
+                apply = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                apply |= ui.button("Apply").clicked();
+            });
+            if apply {
+                match build_filter(self.config.log_level, &self.log_filter_input) {
+                    Ok(_) => {
+                        self.config.log_module_filter = self.log_filter_input.trim().to_string();
+                        self.log_filter_error = None;
+                        self.apply_log_filter();
+                    }
+                    Err(e) => self.log_filter_error = Some(e.user_message()),
+                }
+            }
+            if let Some(err) = &self.log_filter_error {
+                ui.colored_label(egui::Color32::from_rgb(255, 100, 100), format!("❌ {}", err));
+            }
+        });
+    }
+    
+    /// Reload the log filter from config and persist it
+    fn apply_log_filter(&mut self) {
+        if let Err(e) = self.logging.apply(self.config.log_level, &self.config.log_module_filter) {
+            self.log_filter_error = Some(e.user_message());
+            return;
+        }
+        info!("Log level set to {}", self.config.log_level.label());
+        if let Err(e) = self.config.save() {
+            warn!("Failed to save config: {}", e);
+        }
+    }
+    
+    /// Offer a rescan while exclusion or include filter changes are pending
+    fn render_exclusions_rescan(&mut self, ui: &mut egui::Ui) {
+        if self.exclusions_changed {
+            ui.add_space(6.0);
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new("ℹ Exclusions changed").weak().small());
+                if ui.button("🔄 Rescan now").on_hover_text("Remove newly excluded files and index newly included ones").clicked() {
+                    self.rescan_for_exclusions();
+                }
+            });
+        }
+    }
+    
+    /// Per-folder include filters with add and remove buttons, in the Directories tab
+    fn render_include_filters(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Include Filters");
+        ui.add_space(6.0);
+        ui.label(
+            egui::RichText::new("Under these folders only files matching a pattern are indexed: extensions (mp4), kinds (videos) or wildcards (*.srt).")
+                .weak()
+                .small(),
+        );
+        ui.add_space(10.0);
+        
+        let mut changed = false;
+        let mut remove_idx = None;
+        for (i, filter) in self.config.include_filters.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("✖").on_hover_text("Index every file under this folder again").clicked() {
+                    remove_idx = Some(i);
+                }
+                ui.label(format!("📁 {}", filter.root.display()));
+                ui.label(egui::RichText::new(filter.patterns.join(", ")).monospace());
+            });
+        }
+        if let Some(i) = remove_idx {
+            self.config.include_filters.remove(i);
+            changed = true;
+        }
+        
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.new_include_patterns)
+                .hint_text("e.g. videos, *.srt")
+                .desired_width(220.0));
+            let enabled = !self.new_include_patterns.trim().is_empty();
+            if ui.add_enabled(enabled, egui::Button::new("➕ Add folder…")).clicked() {
+                if let Some(root) = rfd::FileDialog::new().set_title("Choose a folder to filter").pick_folder() {
+                    if let Some(filter) = IncludeFilter::parse(root, &self.new_include_patterns) {
+                        // A folder has one filter; adding it again replaces the patterns
+                        self.config.include_filters.retain(|f| f.root != filter.root);
+                        self.config.include_filters.push(filter);
+                        changed = true;
+                    }
+                    self.new_include_patterns.clear();
+                }
+            }
+        });
+        
+        if changed {
+            self.apply_exclusion_settings();
+        }
+        self.render_exclusions_rescan(ui);
+    }
+    
+    /// Saved searches with run, watch, shortcut and delete controls
+    fn render_saved_searches(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Saved Searches");
+        ui.add_space(6.0);
+        ui.label(egui::RichText::new("Watched searches show an alert when a newly indexed file matches them.").weak().small());
+        ui.add_space(10.0);
+        
+        if self.config.saved_searches.is_empty() {
+            ui.label(egui::RichText::new("No saved searches yet. Use ⭐ Save search next to the results.").weak());
+            return;
+        }
+        
+        let mut changed = false;
+        let mut run = None;
+        let mut remove_idx = None;
+        let mut shortcut = None;
+        for (i, saved) in self.config.saved_searches.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("✖").on_hover_text("Delete saved search").clicked() {
+                    remove_idx = Some(i);
+                }
+                if ui.small_button("▶").on_hover_text("Run this search").clicked() {
+                    run = Some((saved.query.clone(), FileTypeFilter::from_id(saved.filter.as_deref(), &self.config.kind_groups)));
+                }
+                changed |= ui.checkbox(&mut saved.watch, "🔔 Watch")
+                    .on_hover_text("Alert when a new file matches, at most once every 10 seconds")
+                    .changed();
+                changed |= ui.add(egui::TextEdit::singleline(&mut saved.name).desired_width(140.0)).lost_focus();
+                let query = ui.add(egui::Label::new(egui::RichText::new(&saved.query).monospace().weak()).sense(egui::Sense::click()))
+                    .on_hover_text("Right-click for more");
+                query.context_menu(|ui| {
+                    if ui.button("🔗 Create shortcut…").on_hover_text("Put a Desktop shortcut that opens this search").clicked() {
+                        shortcut = Some(saved.clone());
+                        ui.close_menu();
+                    }
+                });
+                if let Some(filter) = &saved.filter {
+                    ui.label(egui::RichText::new(format!("[{}]", filter)).weak().small());
+                }
+            });
+        }
+        if let Some(saved) = shortcut {
+            self.create_search_shortcut(saved, false);
+        }
+        
+        if let Some(i) = remove_idx {
+            self.config.saved_searches.remove(i);
+            changed = true;
+        }
+        if changed {
+            self.apply_saved_searches();
+        }
+        if let Some((query, filter)) = run {
+            self.close_modal(Modal::Settings);
+            self.run_saved_search(query, filter);
+        }
+    }
+    
+    /// Write a Desktop shortcut opening `saved`, asking first if it would replace one
+    fn create_search_shortcut(&mut self, saved: SavedSearch, replace: bool) {
+        let desktop = match desktop_dir() {
+            Ok(dir) => dir,
+            Err(e) => {
+                self.last_error = Some(e.user_message());
+                return;
+            }
+        };
+        if !replace && existing_shortcut(&desktop, &saved.name).is_some() {
+            self.pending_shortcut = Some(saved);
+            self.modals.open(Modal::ReplaceShortcut);
+            return;
+        }
+        
+        let search = LaunchSearch { query: saved.query.clone(), filter: saved.filter.clone() };
+        match create_shortcut(&desktop, &saved.name, &search) {
+            Ok(path) => {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                self.last_error = Some(format!("✓ Created {} on the Desktop", name));
+            }
+            Err(e) => {
+                error!("Failed to create shortcut for '{}': {}", saved.name, e);
+                self.last_error = Some(e.user_message());
+            }
+        }
+    }
+    
+    /// Start a snapshot job against the default store
+    fn start_snapshot_job(&mut self, start: impl FnOnce(SnapshotStore) -> SnapshotJob) {
+        match SnapshotStore::open_default() {
+            Ok(store) => self.snapshot_job = Some(start(store)),
+            Err(e) => {
+                error!("Snapshot store unavailable: {}", e);
+                self.last_error = Some(e.user_message());
+            }
+        }
+    }
+    
+    /// Recompute completions for the query and open the dropdown
+    ///
+    /// The word list is rebuilt in the background once the index has changed,
+    /// at most every `SUGGESTION_REFRESH`; until then the previous words are used.
+    fn update_suggestions(&mut self) {
+        let generation = self.index.read().generation();
+        let stale = self.token_index.as_ref().is_none_or(|tokens| tokens.generation() != generation);
+        let due = self.tokens_started.is_none_or(|at| at.elapsed() >= SUGGESTION_REFRESH);
+        if stale && due && self.token_job.is_none() {
+            self.token_job = Some(TokenIndexJob::start(self.index.clone()));
+            self.tokens_started = Some(Instant::now());
+        }
+        
+        self.suggestions = suggest(&self.query, self.token_index.as_ref(), &self.config.saved_searches);
+        self.suggestion_selected = None;
+        self.suggestions_open = true;
+    }
+    
+    /// Pick up rebuilt completion words, refreshing an open dropdown
+    fn poll_token_job(&mut self, ctx: &egui::Context) {
+        let Some(job) = &self.token_job else {
+            return;
+        };
+        let Some(tokens) = job.take() else {
+            ctx.request_repaint_after(Duration::from_millis(100));
+            return;
+        };
+        self.token_job = None;
+        self.token_index = Some(tokens);
+        
+        if self.suggestions_open {
+            self.suggestions = suggest(&self.query, self.token_index.as_ref(), &self.config.saved_searches);
+            self.suggestion_selected = self.suggestion_selected.filter(|&row| row < self.suggestions.len());
+        }
+    }
+    
+    fn close_suggestions(&mut self) {
+        self.suggestions_open = false;
+        self.suggestion_selected = None;
+    }
+    
+    /// Put a completion in the search box; it is searched now only if `run`
+    fn accept_suggestion(&mut self, ctx: &egui::Context, row: usize, run: bool) {
+        let Some(suggestion) = self.suggestions.get(row) else {
+            return;
+        };
+        self.query = suggestion.query.clone();
+        self.close_suggestions();
+        
+        // Leave the cursor after the completed text
+        self.move_cursor_to_end(ctx);
+        
+        if run {
+            self.browse_page = 0;
+            self.last_error = None;
+            self.do_search();
+        } else {
+            self.search_pending = true;
+        }
+    }
+    
+    fn move_cursor_to_end(&self, ctx: &egui::Context) {
+        if let Some(mut state) = egui::TextEdit::load_state(ctx, search_box_id()) {
+            let end = egui::text::CCursor::new(self.query.chars().count());
+            state.cursor.set_char_range(Some(egui::text::CCursorRange::one(end)));
+            state.store(ctx, search_box_id());
+        }
+    }
+    
+    /// Arrow keys move through the completions, Tab accepts, and Enter
+    /// accepts and searches once one is highlighted
+    fn handle_suggestion_keys(&mut self, ctx: &egui::Context) {
+        let selected = self.suggestion_selected;
+        let (down, up, tab, enter) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Tab),
+                selected.is_some() && i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+            )
+        });
+        
+        let last = self.suggestions.len() - 1;
+        if down {
+            self.suggestion_selected = Some(selected.map_or(0, |row| (row + 1).min(last)));
+        }
+        if up {
+            self.suggestion_selected = selected.and_then(|row| row.checked_sub(1));
+        }
+        if tab {
+            self.accept_suggestion(ctx, self.suggestion_selected.unwrap_or(0), false);
+        } else if enter {
+            self.accept_suggestion(ctx, self.suggestion_selected.unwrap_or(0), true);
+        }
+    }
+    
+    /// Completion dropdown under the search box; it never takes keyboard focus
+    fn render_suggestions(&mut self, ctx: &egui::Context, search: &egui::Response) {
+        let mut clicked = None;
+        egui::Area::new(egui::Id::new("search_suggestions"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(search.rect.left_bottom() + egui::vec2(0.0, 2.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_width(search.rect.width() - 12.0);
+                    for (row, suggestion) in self.suggestions.iter().enumerate() {
+                        let selected = self.suggestion_selected == Some(row);
+                        if ui.selectable_label(selected, suggestion.label()).clicked() {
+                            clicked = Some(row);
+                        }
+                    }
+                    ui.label(egui::RichText::new("↓ select · Tab complete · Esc close").small().weak());
+                });
+            });
+        
+        if let Some(row) = clicked {
+            self.accept_suggestion(ctx, row, false);
+            search.request_focus();
+        }
+    }
+    
+    /// Add a query the user acted on to the search history, unless incognito
+    fn remember_query(&mut self, query: &str) {
+        if privacy::should_record_query(query) && self.config.search_history.record(query) {
+            if let Err(e) = self.config.save() {
+                warn!("Failed to save config: {}", e);
+            }
+        }
+    }
+    
+    /// Put a query in the search box with the cursor at its end and search it
+    fn set_query(&mut self, ctx: &egui::Context, query: String) {
+        self.query = query;
+        self.close_suggestions();
+        self.move_cursor_to_end(ctx);
+        self.browse_page = 0;
+        self.last_error = None;
+        self.do_search();
+    }
+    
+    /// Up from an empty search box recalls older queries and Down newer
+    /// ones, back to an empty box
+    fn handle_history_keys(&mut self, ctx: &egui::Context) {
+        let recalling = self.history_recall.is_active();
+        if !self.query.is_empty() && !recalling {
+            return;
+        }
+        let (up, down) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                recalling && i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+            )
+        });
+        
+        let history = &self.config.search_history;
+        let recalled = if up {
+            self.history_recall.older(history)
+        } else if down {
+            self.history_recall.newer(history)
+        } else {
+            None
+        };
+        if let Some(query) = recalled.map(str::to_string) {
+            self.set_query(ctx, query);
+        }
+    }
+    
+    /// Recent queries under the empty search box; a click searches one again
+    fn render_history(&mut self, ctx: &egui::Context, search: &egui::Response) {
+        let mut clicked = None;
+        egui::Area::new(egui::Id::new("search_history"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(search.rect.left_bottom() + egui::vec2(0.0, 2.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_width(search.rect.width() - 12.0);
+                    for query in self.config.search_history.recent(HISTORY_DROPDOWN_ROWS) {
+                        if ui.selectable_label(false, format!("🕘 {}", query)).clicked() {
+                            clicked = Some(query.to_string());
+                        }
+                    }
+                    ui.label(egui::RichText::new("↑ recall previous searches").small().weak());
+                });
+            });
+        
+        if let Some(query) = clicked {
+            self.set_query(ctx, query);
+            search.request_focus();
+        }
+    }
+    
+    /// Pick up a finished snapshot job
+    fn poll_snapshot_job(&mut self, ctx: &egui::Context) {
+        let Some(job) = &self.snapshot_job else {
+            return;
+        };
+        let Some(outcome) = job.take_outcome() else {
+            ctx.request_repaint_after(Duration::from_millis(100));
+            return;
+        };
+        self.snapshot_job = None;
+        
+        match outcome {
+            Ok(SnapshotOutcome::Created(info)) => {
+                self.last_error = Some(format!("✓ Saved snapshot '{}' ({} files)", info.name, info.files));
+                self.snapshot_name.clear();
+                self.refresh_snapshots();
+            }
+            Ok(SnapshotOutcome::Diffed(info, diff)) => {
+                self.snapshot_diff = Some((info, diff));
+                self.modals.open(Modal::SnapshotDiff);
+            }
+            Err(FlashFindError::Cancelled) => {}
+            Err(e) => {
+                error!("Snapshot failed: {}", e);
+                self.last_error = Some(e.user_message());
+            }
+        }
+    }
+    
+    fn refresh_snapshots(&mut self) {
+        self.snapshots = SnapshotStore::open_default().map(|store| store.list()).unwrap_or_default();
+    }
+    
+    /// The weekly summary opt-in, and the summary of any kept week
+    fn render_weekly_activity(&mut self, ui: &mut egui::Ui) {
+        ui.label(egui::RichText::new("📅 Weekly activity").size(14.0).strong());
+        ui.add_space(8.0);
+        ui.label(egui::RichText::new("Files added and removed each day, kept for 12 weeks in the app data folder.").size(12.0).weak());
+        ui.add_space(8.0);
+        
+        let record = ui.checkbox(&mut self.config.weekly_summary, "Record activity")
+            .on_hover_text("Count files that scans and the watcher add and remove, and show last week's summary on the first launch of each week");
+        if record.changed() {
+            self.activity.set_enabled(self.config.weekly_summary);
+            if !self.config.weekly_summary {
+                self.weekly_card = None;
+            }
+            if let Err(e) = self.config.save() {
+                warn!("Failed to save config: {}", e);
+            }
+        }
+        ui.add_space(4.0);
+        
+        let this_week = week_start(today());
+        let oldest = self.activity.oldest_week().unwrap_or(this_week).min(this_week);
+        let mut shown = self.activity_week.clamp(oldest, this_week);
+        let week = self.activity.week(shown);
+        ui.horizontal(|ui| {
+            if ui.add_enabled(shown > oldest, egui::Button::new("◀")).on_hover_text("Previous week").clicked() {
+                shown -= 7;
+            }
+            ui.label(egui::RichText::new(week.title()).strong());
+            if ui.add_enabled(shown < this_week, egui::Button::new("▶")).on_hover_text("Next week").clicked() {
+                shown += 7;
+            }
+        });
+        self.activity_week = shown;
+        render_week_summary(ui, &week);
+        
+        ui.add_space(8.0);
+        if ui.add_enabled(!week.is_empty(), egui::Button::new("📝 Export as Markdown")).clicked() {
+            self.export_week(&week);
+        }
+    }
+    
+    /// Snapshot controls and the list of saved snapshots
+    fn render_snapshots(&mut self, ui: &mut egui::Ui) {
+        ui.label(egui::RichText::new("📸 Snapshots").size(14.0).strong());
+        ui.add_space(8.0);
+        ui.label(egui::RichText::new("Save the indexed file list and sizes, then compare it with the index later.").size(12.0).weak());
+        ui.add_space(8.0);
+        
+        if let Some(job) = &self.snapshot_job {
+            let (processed, total) = job.progress();
+            let mut cancel = false;
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label(format!("Processing {} of {} files...", processed, total));
+                cancel = ui.button("Cancel").clicked();
+            });
+            if cancel {
+                job.cancel();
+            }
+        } else {
+            let storage_ok = !self.config.ephemeral;
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut self.snapshot_name).hint_text("Before cleanup").desired_width(200.0));
+                let take = ui.add_enabled(storage_ok, egui::Button::new("📸 Take snapshot"))
+                    .on_disabled_hover_text("App storage is not writable");
+                if take.clicked() {
+                    let name = match self.snapshot_name.trim() {
+                        "" => "Snapshot".to_string(),
+                        name => name.to_string(),
+                    };
+                    let index = self.index.clone();
+                    self.start_snapshot_job(|store| SnapshotJob::create(store, index, name));
+                }
+            });
+        }
+        
+        if self.snapshots.is_empty() {
+            return;
+        }
+        ui.add_space(8.0);
+        
+        let busy = self.snapshot_job.is_some();
+        let mut compare = None;
+        let mut delete = None;
+        let mut save_name = None;
+        egui::Grid::new("snapshots_grid").num_columns(4).spacing([16.0, 4.0]).striped(true).show(ui, |ui| {
+            for info in &self.snapshots {
+                match &mut self.snapshot_rename {
+                    Some((id, name)) if *id == info.id => {
+                        let response = ui.text_edit_singleline(name);
+                        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            save_name = Some((id.clone(), name.clone()));
+                        }
+                    }
+                    _ => {
+                        ui.label(&info.name);
+                    }
+                }
+                ui.label(egui::RichText::new(format_ago(since(info.created_at()))).weak());
+                ui.label(format!("{} files", info.files));
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!busy, egui::Button::new("🔍 Compare")).on_hover_text("Show what changed since this snapshot").clicked() {
+                        compare = Some(info.clone());
+                    }
+                    if ui.small_button("✏").on_hover_text("Rename").clicked() {
+                        self.snapshot_rename = Some((info.id.clone(), info.name.clone()));
+                    }
+                    if ui.add_enabled(!busy, egui::Button::new("🗑").small()).on_hover_text("Delete snapshot").clicked() {
+                        delete = Some(info.id.clone());
+                    }
+                });
+                ui.end_row();
+            }
+        });
+        
+        if let Some(info) = compare {
+            let index = self.index.clone();
+            self.start_snapshot_job(|store| SnapshotJob::diff(store, index, info));
+        }
+        let store = match SnapshotStore::open_default() {
+            Ok(store) => store,
+            Err(_) => return,
+        };
+        if let Some((id, name)) = save_name {
+            self.snapshot_rename = None;
+            if let Err(e) = store.rename(&id, &name) {
+                self.last_error = Some(e.user_message());
+            }
+            self.refresh_snapshots();
+        }
+        if let Some(id) = delete {
+            if let Err(e) = store.delete(&id) {
+                self.last_error = Some(e.user_message());
+            }
+            self.refresh_snapshots();
+        }
+    }
+    
+    /// Added, removed and resized files since a snapshot
+    fn render_snapshot_diff(&mut self, ui: &mut egui::Ui) {
+        let Some((info, diff)) = &self.snapshot_diff else {
+            return;
+        };
+        let diff = diff.clone();
+        
+        ui.label(egui::RichText::new(format!(
+            "Since '{}' ({}): {} added, {} removed, {} changed size",
+            info.name,
+            format_ago(since(info.created_at())),
+            diff.added.len(),
+            diff.removed.len(),
+            diff.changed.len()
+        )).size(13.0));
+        ui.add_space(6.0);
+        
+        let mut reveal = None;
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        let mut section = |ui: &mut egui::Ui, title: String, rows: usize, row: &dyn Fn(&mut egui::Ui, usize) -> Option<PathBuf>| {
+            egui::CollapsingHeader::new(title).default_open(rows > 0).show(ui, |ui| {
+                egui::ScrollArea::vertical().id_source(ui.id().with("rows")).max_height(180.0).show_rows(ui, row_height, rows, |ui, range| {
+                    for i in range {
+                        if let Some(path) = row(ui, i) {
+                            reveal = Some(path);
+                        }
+                    }
+                });
+            });
+        };
+        
+        let path_row = |ui: &mut egui::Ui, path: &Path, detail: String| -> Option<PathBuf> {
+            ui.horizontal(|ui| {
+                let clicked = ui.small_button("📂").on_hover_text("Open containing folder").clicked();
+                ui.label(egui::RichText::new(format!("{} {}", get_file_icon(path), path.display())).size(12.0));
+                ui.label(egui::RichText::new(detail).weak().size(11.5));
+                clicked.then(|| path.to_path_buf())
+            }).inner
+        };
+        
+        section(ui, format!("➕ Added ({})", diff.added.len()), diff.added.len(), &|ui, i| {
+            path_row(ui, &diff.added[i], String::new())
+        });
+        section(ui, format!("➖ Removed ({})", diff.removed.len()), diff.removed.len(), &|ui, i| {
+            let (path, size) = &diff.removed[i];
+            path_row(ui, path, size.map(format_size).unwrap_or_default())
+        });
+        section(ui, format!("📏 Size changed ({})", diff.changed.len()), diff.changed.len(), &|ui, i| {
+            let change = &diff.changed[i];
+            path_row(ui, &change.path, format!("{} → {}", format_size(change.before), format_size(change.after)))
+        });
+        
+        ui.add_space(8.0);
+        if ui.button("📊 Export").on_hover_text("Export the changes to CSV").clicked() {
+            self.export_snapshot_diff(&diff);
+        }
+        if let Some(path) = reveal {
+            self.reveal_in_folder(&path);
+        }
+    }
+    
+    /// Move alerts raised by the watcher onto the screen
+    fn collect_alerts(&mut self, ctx: &egui::Context) {
+        let raised = self.alerts.take();
+        if !raised.is_empty() {
+            // Flash the taskbar entry when FlashFind is in the background
+            ctx.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(egui::UserAttentionType::Informational));
+            let now = Instant::now();
+            self.toasts.extend(raised.into_iter().map(|alert| (alert, now)));
+            let excess = self.toasts.len().saturating_sub(MAX_TOASTS);
+            self.toasts.drain(..excess);
+        }
+        self.toasts.retain(|(_, shown)| shown.elapsed() < TOAST_LIFETIME);
+        
+        // The watcher cannot wake the UI, so poll while alerts can arrive or
+        // held-back ones fall due
+        if self.alerts.is_active() || !self.toasts.is_empty() {
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+    }
+    
+    /// Saved-search alerts in the bottom-right corner
+    fn show_alert_toasts(&mut self, ctx: &egui::Context) {
+        if self.toasts.is_empty() {
+            return;
+        }
+        
+        let mut open = None;
+        let mut show = None;
+        let mut dismiss = None;
+        egui::Area::new(egui::Id::new("alert_toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -12.0))
+            .show(ctx, |ui| {
+                for (i, (alert, _)) in self.toasts.iter().enumerate() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(320.0);
+                        ui.horizontal(|ui| {
+                            ui.label(egui::RichText::new(format!("🔔 {}", alert.search)).strong());
+                            if ui.small_button("✖").clicked() {
+                                dismiss = Some(i);
+                            }
+                        });
+                        let name = alert.path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                        ui.label(name).on_hover_text(alert.path.display().to_string());
+                        if alert.more > 0 {
+                            ui.label(egui::RichText::new(format!("and {} more", alert.more)).weak().small());
+                        }
+                        ui.horizontal(|ui| {
+                            if ui.button("📂 Open").clicked() {
+                                open = Some(i);
+                            }
+                            if ui.button("🔍 Show search").clicked() {
+                                show = Some(i);
+                            }
+                        });
+                    });
+                    ui.add_space(6.0);
+                }
+            });
+        
+        if let Some(i) = open {
+            let (alert, _) = self.toasts.remove(i);
+            self.open_file(&alert.path);
+        } else if let Some(i) = show {
+            let (alert, _) = self.toasts.remove(i);
+            ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+            self.run_saved_search(alert.query, self.file_type_filter.clone());
+        } else if let Some(i) = dismiss {
+            self.toasts.remove(i);
+        }
+    }
+    
+    /// Probe indexed roots; re-watch and rescan the ones that returned
+    fn handle_root_availability(&mut self, ctx: &egui::Context) {
+        for root in self.roots.probe() {
+            if let Some(watcher) = &mut self.watcher {
+                if let Err(e) = watcher.rewatch(&root) {
+                    warn!("Failed to re-watch {}: {}", redact(&root), e);
+                }
+            }
+            
+            // Pick up changes made while the root was offline
+            if let Err(e) = self.indexer.start_scan(vec![root.clone()]) {
+                warn!("Failed to rescan {}: {}", redact(&root), e);
+            }
+        }
+        
+        // Keep probing while something is offline, even without user input
+        if !self.roots.unavailable_roots().is_empty() {
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+    }
+    
+    /// Start an elevated helper scan of the folders the last scans could not read
+    fn start_elevated_scan(&mut self) {
+        match ElevatedScan::start(self.indexer.denied_dirs()) {
+            Ok(scan) => {
+                self.elevated_scan = Some(scan);
+                self.last_error = None;
+            }
+            Err(e) => {
+                warn!("Elevated scan not started: {}", e);
+                self.last_error = Some(e.user_message());
+            }
+        }
+    }
+    
+    /// Import the elevated scan's shard once the helper has written it
+    fn handle_elevated_scan(&mut self, ctx: &egui::Context) {
+        let Some(scan) = &self.elevated_scan else {
+            return;
+        };
+        
+        match scan.poll() {
+            ShardStatus::Pending => ctx.request_repaint_after(Duration::from_secs(1)),
+            ShardStatus::Ready => {
+                let scan = self.elevated_scan.take().expect("checked above");
+                if let Err(e) = self.indexer.import_shard(scan.shard, scan.roots) {
+                    error!("Failed to import elevated scan: {}", e);
+                    self.last_error = Some(e.user_message());
+                }
+            }
+            ShardStatus::TimedOut => {
+                if let Some(scan) = self.elevated_scan.take() {
+                    scan.cancel();
+                }
+                self.last_error = Some("The elevated scan did not finish and was abandoned".to_string());
+            }
+        }
+    }
+    
+    /// Size total for the current results: a button, progress, then the result
+    fn render_size_total(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let Some(job) = &self.size_job else {
+            if ui.small_button("∑ Calculate total size").on_hover_text("Sum the sizes of these results").clicked() {
+                self.size_job = Some(SizeJob::start(self.results.iter().map(|result| result.path.clone()).collect()));
+            }
+            return;
+        };
+        
+        let total = job.progress();
+        if !total.done {
+            ui.spinner();
+            ui.label(egui::RichText::new(format!("Measuring {} of {} files...", total.files + total.missing, job.total)).size(12.0));
+            if ui.small_button("Cancel").clicked() {
+                job.cancel();
+            }
+            ctx.request_repaint_after(Duration::from_millis(100));
+            return;
+        }
+        
+        let mut summary = format!(
+            "∑ {} in {} files (avg {})",
+            format_size(total.bytes),
+            total.files,
+            format_size(total.average())
+        );
+        if total.skipped > 0 {
+            summary.push_str(&format!(", first {} only", job.total));
+        }
+        if total.cancelled {
+            summary.push_str(", cancelled");
+        }
+        ui.label(egui::RichText::new(summary).size(12.0));
+        if ui.small_button("✖").on_hover_text("Clear total").clicked() {
+            self.size_job = None;
+        }
+    }
+    
+    /// Result order picker; the choice is saved with the config
+    fn render_sort_selector(&mut self, ui: &mut egui::Ui) {
+        let mut sort_changed = false;
+        egui::ComboBox::from_id_source("sort_order")
+            .selected_text(egui::RichText::new(format!("⇅ {}", self.config.sort_order.label())).size(12.0))
+            .width(120.0)
+            .show_ui(ui, |ui| {
+                for sort in SortOrder::ALL {
+                    sort_changed |= ui.selectable_value(&mut self.config.sort_order, sort, sort.label()).clicked();
+                }
+            });
+        
+        if sort_changed {
+            self.do_search();
+            if let Err(e) = self.config.save() {
+                warn!("Failed to save config: {}", e);
+            }
+        }
+    }
+
+    /// Switch between searching everywhere and only in the favorite folders
+    fn render_favorites_toggle(&mut self, ui: &mut egui::Ui) {
+        let toggle = ui
+            .selectable_label(self.config.favorites_only, egui::RichText::new("★ Favorites only").size(12.0))
+            .on_hover_text("Search only inside the favorite folders set up in Settings");
+        if toggle.clicked() {
+            self.config.favorites_only = !self.config.favorites_only;
+            self.do_search();
+            if let Err(e) = self.config.save() {
+                warn!("Failed to save config: {}", e);
+            }
+        }
+    }
+    
+    /// Favorite folders list with add and remove buttons, in the Directories tab
+    fn render_favorite_folders(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Favorite Folders");
+        ui.add_space(6.0);
+        ui.label(
+            egui::RichText::new("Results inside these folders are listed first; ★ Favorites only limits searches to them.")
+                .weak()
+                .small(),
+        );
+        ui.add_space(10.0);
+        
+        let mut changed = false;
+        let mut remove_idx = None;
+        for (i, folder) in self.config.favorite_folders.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("✖").on_hover_text("Remove from favorites").clicked() {
+                    remove_idx = Some(i);
+                }
+                ui.label(format!("⭐ {}", folder.display()));
+            });
+        }
+        if let Some(i) = remove_idx {
+            self.config.favorite_folders.remove(i);
+            changed = true;
+        }
+        
+        if ui.button("➕ Add folder…").clicked() {
+            if let Some(folder) = rfd::FileDialog::new().set_title("Choose a favorite folder").pick_folder() {
+                if !self.config.favorite_folders.contains(&folder) {
+                    self.config.favorite_folders.push(folder);
+                    changed = true;
+                }
+            }
+        }
+        
+        if changed {
+            if self.config.favorite_folders.is_empty() {
+                self.config.favorites_only = false;
+            }
+            self.do_search();
+            if let Err(e) = self.config.save() {
+                warn!("Failed to save config: {}", e);
+            }
+        }
+    }
+
+    /// Run a row action from the main list or a results window
+    fn handle_result_action(&mut self, path: PathBuf, action: ResultAction, query: &str) {
+        if matches!(action, ResultAction::Open | ResultAction::OpenFolder) {
+            self.remember_query(query);
+        }
+        match action {
+            ResultAction::Open => self.open_file(&path),
+            ResultAction::Select => self.selection = vec![path],
+            ResultAction::ToggleSelect => {
+                if let Some(pos) = self.selection.iter().position(|p| *p == path) {
+                    self.selection.remove(pos);
+                } else {
+                    self.selection.push(path);
+                }
+            }
+            ResultAction::OpenFolder => self.reveal_in_folder(&path),
+            ResultAction::CopyPath => {},
+            ResultAction::Explain => {
+                self.match_info = Some((path, query.to_string()));
+                self.modals.open(Modal::MatchInfo);
+            }
+        }
+    }
+    
+    /// Open the selected results, or the result at `row` when none is selected
+    fn open_selection(&mut self, row: usize) {
+        if self.selection.is_empty() {
+            if let Some(path) = self.results.get(row).map(|result| result.path.clone()) {
+                self.open_file(&path);
+            }
+            return;
+        }
+        
+        let selection = self.selection.clone();
+        if selection.len() > MAX_OPEN_SELECTION {
+            warn!("Opening the first {} of {} selected results", MAX_OPEN_SELECTION, selection.len());
+        }
+        for path in selection.iter().take(MAX_OPEN_SELECTION) {
+            self.open_file(path);
+        }
+    }
+    
+    /// Snapshot the current results into a new results window
+    fn open_results_window(&mut self) {
+        if self.result_windows.len() >= MAX_RESULT_WINDOWS {
+            return;
+        }
+        
+        self.next_window += 1;
+        self.result_windows.push(ResultsWindow {
+            id: egui::ViewportId::from_hash_of(("results_window", self.next_window)),
+            query: self.query.clone(),
+            results: self.results.clone(),
+        });
+        info!("Opened results window with {} results", self.results.len());
+    }
+    
+    /// Draw the results windows as child viewports and run their row actions
+    ///
+    /// Immediate viewports only live while the main window draws them, so
+    /// closing the main window closes them too.
+    fn show_results_windows(&mut self, ctx: &egui::Context) {
+        let mut actions: Vec<(usize, PathBuf, ResultAction)> = Vec::new();
+        let mut closed: Vec<usize> = Vec::new();
+        
+        let activation = self.config.result_activation;
+        for (n, window) in self.result_windows.iter().enumerate() {
+            let title = format!("FlashFind — {} ({} results)", window.query.trim(), window.results.len());
+            let builder = egui::ViewportBuilder::default()
+                .with_title(title.clone())
+                .with_inner_size([700.0, 500.0]);
+            
+            ctx.show_viewport_immediate(window.id, builder, |ctx, class| {
+                let mut queue = Vec::new();
+                let mut draw = |ui: &mut egui::Ui| {
+                    ui.push_id(window.id, |ui| {
+                        ui.label(egui::RichText::new(format!("🔍 {}", window.query.trim())).strong());
+                        ui.separator();
+                        render_results(ui, &window.results, &self.roots, activation, &[], None, &mut queue);
+                    });
+                };
+                
+                if class == egui::ViewportClass::Embedded {
+                    // The backend cannot open OS windows, so fall back to an in-app window
+                    let mut open = true;
+                    egui::Window::new(title.as_str())
+                        .id(egui::Id::new(window.id))
+                        .open(&mut open)
+                        .default_size([600.0, 400.0])
+                        .show(ctx, |ui| draw(ui));
+                    if !open {
+                        closed.push(n);
+                    }
+                } else {
+                    egui::CentralPanel::default().show(ctx, |ui| draw(ui));
+                    if ctx.input(|i| i.viewport().close_requested()) {
+                        closed.push(n);
+                    }
+                }
+                
+                actions.extend(queue.into_iter().map(|(path, action)| (n, path, action)));
+            });
+        }
+        
+        for (n, path, action) in actions {
+            // Selection belongs to the main results list
+            if matches!(action, ResultAction::Select | ResultAction::ToggleSelect) {
+                continue;
+            }
+            let query = self.result_windows[n].query.clone();
+            self.handle_result_action(path, action, &query);
+        }
+        for n in closed.into_iter().rev() {
+            self.result_windows.remove(n);
+        }
+    }
+    
+    /// Recompute the sampled verification on a background thread
+    fn refresh_health(&self) {
+        if self.health_running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        
+        let index = self.index.clone();
+        let monitor = self.roots.clone();
+        let health = self.health.clone();
+        let running = self.health_running.clone();
+        let roots = get_default_directories();
+        std::thread::spawn(move || {
+            let report = HealthReport::verify(&index, &monitor, &roots, SAMPLE_SIZE);
+            *health.write() = Some(report);
+            running.store(false, Ordering::SeqCst);
+        });
+    }
+    
+    /// Folders that should be watched but are not (empty when the watcher is fine)
+    fn unwatched_roots(&self) -> Vec<PathBuf> {
+        let watched = self.watcher.as_ref().map(|w| w.watched_directories()).unwrap_or_default();
+        get_default_directories()
+            .into_iter()
+            .filter(|root| self.roots.is_available(root) && !watched.contains(root))
+            .collect()
+    }
+    
+    /// Run the actions the health dashboard recommends
+    ///
+    /// Prunes sampled entries that no longer exist, rescans drifted roots and
+    /// restores missing watches, then recomputes the report.
+    fn fix_health_issues(&mut self) {
+        let report = self.health.read().clone();
+        let mut pruned = 0;
+        let mut rescanned = 0;
+        
+        if let Some(report) = report {
+            for path in report.missing.iter().filter(|p| !to_long_path(p).exists()) {
+                if let Ok(true) = self.index.remove(path) {
+                    pruned += 1;
+                }
+            }
+            // The report below is worked out from what searches see
+            self.index.publish();
+            
+            if !report.drifted_roots.is_empty() {
+                rescanned = report.drifted_roots.len();
+                if let Err(e) = self.indexer.start_scan(report.drifted_roots) {
+                    warn!("Failed to rescan drifted roots: {}", e);
+                }
+            }
+        }
+        
+        // Restart the watcher if it failed to start, or re-add lost watches
+        let unwatched = self.unwatched_roots();
+        if self.watcher.is_none() {
+            match Watcher::new(self.index.clone(), self.exclusions.clone(), self.roots.clone(), self.alerts.clone(), self.activity.clone()) {
+                Ok(w) => self.watcher = Some(w),
+                Err(e) => warn!("Watcher restart failed: {}", e),
+            }
+        }
+        if let Some(watcher) = &mut self.watcher {
+            for root in &unwatched {
+                if let Err(e) = watcher.rewatch(root) {
+                    warn!("Failed to re-watch {}: {}", redact(root), e);
+                }
+            }
+        }
+        
+        info!("Health fixes: pruned {}, rescanning {} roots, re-watched {}", pruned, rescanned, unwatched.len());
+        self.last_error = Some(format!(
+            "✓ Pruned {} dead entries, rescanning {} folders, restored {} watches",
+            pruned, rescanned, unwatched.len()
+        ));
+        self.refresh_health();
+    }
+    
+    /// Render the Health tab: verification, scan age, watcher and save status
+    fn render_health(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.heading("Index Health");
+        ui.add_space(10.0);
+        
+        let report = self.health.read().clone();
+        let running = self.health_running.load(Ordering::SeqCst);
+        if running {
+            ctx.request_repaint_after(Duration::from_millis(250));
+        }
+        
+        let good = egui::Color32::from_rgb(100, 255, 100);
+        let warn_color = egui::Color32::from_rgb(255, 200, 100);
+        let unwatched = self.unwatched_roots();
+        let watcher_events = self.index.read().stats().session.watcher_events;
+        let minutes = self.started_at.elapsed().as_secs_f64() / 60.0;
+        
+        egui::Grid::new("health_grid").num_columns(2).spacing([20.0, 8.0]).show(ui, |ui| {
+            // Sampled verification
+            ui.label("Entries on disk:");
+            match &report {
+                Some(r) if r.missing.is_empty() => {
+                    ui.colored_label(good, format!("✓ {:.1}% of {} sampled", r.existing_percent(), r.checked));
+                }
+                Some(r) => {
+                    ui.colored_label(warn_color, format!(
+                        "⚠ {:.1}% of {} sampled ({} missing)",
+                        r.existing_percent(), r.checked, r.missing.len()
+                    ));
+                }
+                None => {
+                    ui.label(egui::RichText::new("Not checked yet").weak());
+                }
+            }
+            ui.end_row();
+            
+            // Time since the last full scan of each root
+            ui.label("Last full scan:");
+            ui.vertical(|ui| {
+                let scans = self.indexer.last_scans();
+                for root in get_default_directories() {
+                    let age = scans
+                        .iter()
+                        .find(|(done, _)| *done == root)
+                        .map(|(_, when)| format_ago(since(*when)))
+                        .unwrap_or_else(|| "not this session".to_string());
+                    ui.label(format!("{} — {}", root.display(), age));
+                }
+            });
+            ui.end_row();
+            
+            // Watcher coverage and activity
+            ui.label("File watcher:");
+            if self.watcher.is_none() {
+                ui.colored_label(warn_color, "⚠ Not running");
+            } else if !unwatched.is_empty() {
+                ui.colored_label(warn_color, format!("⚠ {} folders not watched", unwatched.len()));
+            } else if watcher_events == 0 && minutes >= 30.0 {
+                ui.colored_label(warn_color, "⚠ No file changes seen in 30+ minutes");
+            } else {
+                ui.colored_label(good, format!(
+                    "✓ {} events this session ({:.1}/min)",
+                    watcher_events,
+                    watcher_events as f64 / minutes.max(1.0)
+                ));
+            }
+            ui.end_row();
+            
+            // Saved index
+            ui.label("Saved index:");
+            if self.saver.is_ephemeral() {
+                ui.colored_label(warn_color, "⚠ Not saved (ephemeral mode)");
+            } else if self.saver.is_dirty() {
+                ui.label(format!("Unsaved changes, last save {}", format_ago(self.saver.last_save().elapsed())));
+            } else {
+                ui.colored_label(good, format!("✓ Saved {}", format_ago(self.saver.last_save().elapsed())));
+            }
+            ui.end_row();
+        });
+        
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            if running {
+                ui.spinner();
+                ui.label(egui::RichText::new("Checking...").weak());
+            } else if let Some(r) = &report {
+                ui.label(egui::RichText::new(format!("Computed {}", format_ago(since(r.computed_at)))).weak().small());
+            }
+            
+            if ui.add_enabled(!running, egui::Button::new("🔄 Refresh")).clicked() {
+                self.refresh_health();
+            }
+            
+            let has_issues = report.as_ref().is_some_and(|r| !r.missing.is_empty())
+                || self.watcher.is_none()
+                || !unwatched.is_empty();
+            if ui.add_enabled(has_issues && !running, egui::Button::new("🩹 Fix issues"))
+                .on_hover_text("Prune dead entries, rescan drifted folders and restart the watcher")
+                .clicked()
+            {
+                self.fix_health_issues();
+            }
+        });
+    }
+    
+    /// Open the batch rename dialog for the current results
+    fn open_batch_rename(&mut self) {
+        let paths: Vec<PathBuf> = self.results.iter().take(MAX_BATCH_RENAME).map(|result| result.path.clone()).collect();
+        self.batch_rename = Some(BatchRenameDialog {
+            omitted: self.results.len().saturating_sub(paths.len()),
+            paths,
+            pattern: RenamePattern::default(),
+            preview: Vec::new(),
+            previewed: None,
+            summary: None,
+        });
+        self.modals.open(Modal::BatchRename);
+    }
+    
+    /// Rename the previewed files and keep the batch for undo
+    fn apply_batch_rename(&mut self) {
+        let Some(dialog) = &mut self.batch_rename else {
+            return;
+        };
+        
+        let index = self.index.clone();
+        let summary = apply_renames(&dialog.preview, |from, to| rename_in_index(&index, from, to));
+        if !summary.renamed.is_empty() {
+            self.rename_undo = summary.renamed.clone();
+        }
+        let renamed = summary.renamed.clone();
+        dialog.summary = Some(summary);
+        self.replace_result_paths(&renamed);
+    }
+    
+    /// Rename the files of the last batch back to their old names
+    fn undo_batch_rename(&mut self) {
+        let index = self.index.clone();
+        let summary = undo_renames(&self.rename_undo, |from, to| rename_in_index(&index, from, to));
+        self.rename_undo.clear();
+        self.replace_result_paths(&summary.renamed);
+        
+        self.last_error = Some(if summary.failed.is_empty() {
+            format!("✓ Renamed {} files back", summary.renamed.len())
+        } else {
+            format!("Renamed {} files back, {} failed", summary.renamed.len(), summary.failed.len())
+        });
+        if let Some(dialog) = &mut self.batch_rename {
+            dialog.summary = None;
+            dialog.previewed = None;
+            for path in &mut dialog.paths {
+                if let Some((_, old)) = summary.renamed.iter().find(|(new, _)| new == path) {
+                    *path = old.clone();
+                }
+            }
+        }
+    }
+    
+    /// Point results at the new paths of renamed files
+    fn replace_result_paths(&mut self, renamed: &[(PathBuf, PathBuf)]) {
+        if renamed.is_empty() {
+            return;
+        }
+        let renamed: AHashMap<&PathBuf, &PathBuf> = renamed.iter().map(|(from, to)| (from, to)).collect();
+        for result in &mut self.results {
+            if let Some(to) = renamed.get(&result.path) {
+                *result = result.renamed((*to).clone());
+            }
+        }
+    }
+    
+    /// Pattern editor, live preview and apply summary
+    fn render_batch_rename(&mut self, ui: &mut egui::Ui) {
+        let can_undo = !self.rename_undo.is_empty();
+        let Some(dialog) = &mut self.batch_rename else {
+            return;
+        };
+        let mut apply = false;
+        let mut undo = false;
+        
+        if let Some(summary) = &dialog.summary {
+            ui.label(egui::RichText::new(format!(
+                "✓ {} renamed, {} failed, {} skipped",
+                summary.renamed.len(),
+                summary.failed.len(),
+                summary.skipped
+            )).size(14.0).strong());
+            if !summary.failed.is_empty() {
+                ui.add_space(6.0);
+                egui::ScrollArea::vertical().id_source("rename_failures").max_height(200.0).show(ui, |ui| {
+                    for (path, reason) in &summary.failed {
+                        ui.label(egui::RichText::new(path.display().to_string()).size(11.5));
+                        ui.colored_label(egui::Color32::from_rgb(255, 100, 100), format!("   {}", reason));
+                    }
+                });
+            }
+            ui.add_space(8.0);
+            if ui.add_enabled(can_undo, egui::Button::new("↶ Undo rename"))
+                .on_hover_text("Rename these files back to their old names")
+                .clicked()
+            {
+                undo = true;
+            }
+        } else {
+            egui::Grid::new("rename_pattern").num_columns(2).spacing([12.0, 6.0]).show(ui, |ui| {
+                ui.label("Find:");
+                ui.text_edit_singleline(&mut dialog.pattern.find);
+                ui.end_row();
+                
+                ui.label("Replace with:");
+                ui.text_edit_singleline(&mut dialog.pattern.replace);
+                ui.end_row();
+                
+                ui.label("Prefix:");
+                ui.add(egui::TextEdit::singleline(&mut dialog.pattern.prefix).hint_text("2024_"));
+                ui.end_row();
+                
+                ui.label("Suffix:");
+                ui.add(egui::TextEdit::singleline(&mut dialog.pattern.suffix).hint_text("_{n}"));
+                ui.end_row();
+                
+                ui.label("Numbering:");
+                ui.horizontal(|ui| {
+                    ui.label("start");
+                    ui.add(egui::DragValue::new(&mut dialog.pattern.start_number).clamp_range(0..=1_000_000));
+                    ui.label("digits");
+                    ui.add(egui::DragValue::new(&mut dialog.pattern.number_width).clamp_range(1..=9));
+                });
+                ui.end_row();
+            });
+            ui.label(egui::RichText::new(format!("{} in the replacement, prefix or suffix is replaced by the file's number", NUMBER_TOKEN)).weak().size(11.5));
+            
+            if dialog.previewed.as_ref() != Some(&dialog.pattern) {
+                dialog.preview = plan_renames(&dialog.paths, &dialog.pattern, |path| {
+                    std::fs::symlink_metadata(to_long_path(path)).is_ok()
+                });
+                dialog.previewed = Some(dialog.pattern.clone());
+            }
+            
+            ui.add_space(8.0);
+            ui.separator();
+            let row_height = ui.text_style_height(&egui::TextStyle::Body);
+            egui::ScrollArea::vertical().id_source("rename_preview").max_height(300.0).show_rows(ui, row_height, dialog.preview.len(), |ui, rows| {
+                for plan in &dialog.preview[rows] {
+                    let name = |p: &Path| p.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                    ui.horizontal(|ui| {
+                        ui.label(egui::RichText::new(name(&plan.from)).size(12.0));
+                        ui.label(egui::RichText::new("→").weak());
+                        match plan.issue {
+                            None => {
+                                ui.label(egui::RichText::new(name(&plan.to)).size(12.0).strong());
+                            }
+                            Some(issue) => {
+                                ui.colored_label(egui::Color32::from_rgb(255, 150, 100), format!("{} ({})", name(&plan.to), issue.label()))
+                                    .on_hover_text(plan.from.display().to_string());
+                            }
+                        }
+                    });
+                }
+            });
+            ui.separator();
+            
+            let ready = dialog.preview.iter().filter(|p| p.issue.is_none()).count();
+            let mut status = format!("{} of {} files will be renamed", ready, dialog.preview.len());
+            if dialog.omitted > 0 {
+                status.push_str(&format!(", {} more results left out", dialog.omitted));
+            }
+            ui.label(egui::RichText::new(status).size(12.0));
+            ui.add_space(6.0);
+            ui.horizontal(|ui| {
+                apply = ui.add_enabled(ready > 0, egui::Button::new(format!("✏ Rename {} files", ready))).clicked();
+                if can_undo && ui.button("↶ Undo last batch rename").clicked() {
+                    undo = true;
+                }
+            });
+        }
+        
+        if apply {
+            self.apply_batch_rename();
+        }
+        if undo {
+            self.undo_batch_rename();
+        }
+    }
+    
+    /// Write a week's summary as Markdown to the current folder, then open it
+    fn export_week(&mut self, week: &WeekSummary) {
+        let filename = format!("flashfind_activity_{}.md", format_day(week.start));
+        let export_path = std::env::current_dir()
+            .unwrap_or_else(|_| std::path::PathBuf::from("."))
+            .join(&filename);
+        
+        if let Err(e) = std::fs::write(&export_path, week.to_markdown()) {
+            error!("Failed to write activity summary: {}", e);
+            self.last_error = Some(format!("Failed to export: {}", e));
+            return;
+        }
+        
+        info!("Exported weekly activity to {}", redact(&export_path));
+        self.last_error = Some(format!("✓ Exported to {}", filename));
+        
+        if let Some(parent) = export_path.parent() {
+            let _ = open::that(parent);
+        }
+    }
+    
+    /// Export search results, or every indexed entry, to a CSV file
+    fn export_to_csv(&mut self, whole_index: bool) {
+        use std::fs::File;
+        use std::io::BufWriter;
+        
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        
+        let filename = format!("flashfind_export_{}.csv", timestamp);
+        let export_path = std::env::current_dir()
+            .unwrap_or_else(|_| std::path::PathBuf::from("."))
+            .join(&filename);
+        
+        // The whole index can take a while, so it is written in the background
+        if whole_index {
+            self.export_job = Some(ExportJob::start(self.index.read(), export_path, self.config.csv_include_bom));
+            return;
+        }
+        
+        let file = match File::create(&export_path) {
+            Ok(file) => file,
+            Err(e) => {
+                error!("Failed to create CSV file: {}", e);
+                self.last_error = Some(format!("Failed to export: {}", e));
+                return;
+            }
+        };
+        
+        let mut writer = BufWriter::new(file);
+        if let Err(e) = export::write_csv(&mut writer, &self.results, self.config.csv_include_bom) {
+            error!("Failed to write CSV: {}", e);
+            self.last_error = Some(format!("Failed to write CSV: {}", e));
+            return;
+        }
+        
+        info!("Exported {} entries to {}", self.results.len(), redact(&export_path));
+        self.last_error = Some(format!("✓ Exported to {}", filename));
+        
+        // Open the folder containing the CSV
+        if let Some(parent) = export_path.parent() {
+            let _ = open::that(parent);
+        }
+    }
+    
+    /// Pick up a finished whole-index export
+    fn poll_export_job(&mut self, ctx: &egui::Context) {
+        let Some(job) = &self.export_job else {
+            return;
+        };
+        let Some(outcome) = job.take_outcome() else {
+            ctx.request_repaint_after(Duration::from_millis(100));
+            return;
+        };
+        let export_path = job.path().to_path_buf();
+        self.export_job = None;
+        
+        match outcome {
+            Ok(Some(exported)) => {
+                info!("Exported {} entries to {}", exported, redact(&export_path));
+                let filename = export_path.file_name().unwrap_or_default().to_string_lossy();
+                self.last_error = Some(format!("✓ Exported {} entries to {}", exported, filename));
+                if let Some(parent) = export_path.parent() {
+                    let _ = open::that(parent);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error!("Failed to export index: {}", e);
+                self.last_error = Some(format!("Failed to export: {}", e));
+            }
+        }
+    }
+    
+    /// Export a snapshot diff to a CSV file next to result exports
+    fn export_snapshot_diff(&mut self, diff: &SnapshotDiff) {
+        use std::fs::File;
+        use std::io::BufWriter;
+        
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        
+        let filename = format!("flashfind_changes_{}.csv", timestamp);
+        let export_path = std::env::current_dir()
+            .unwrap_or_else(|_| std::path::PathBuf::from("."))
+            .join(&filename);
+        
+        let written = File::create(&export_path)
+            .and_then(|file| export::write_diff_csv(&mut BufWriter::new(file), diff, self.config.csv_include_bom));
+        match written {
+            Ok(()) => {
+                info!("Exported snapshot diff to {}", redact(&export_path));
+                self.last_error = Some(format!("✓ Exported to {}", filename));
+            }
+            Err(e) => {
+                error!("Failed to export snapshot diff: {}", e);
+                self.last_error = Some(format!("Failed to export: {}", e));
+            }
+        }
+    }
+    
+    /// Render settings window
+    fn render_settings(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let previous_tab = self.settings_tab;
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.settings_tab, SettingsTab::Configuration, "⚙️ Configuration");
+            // Drive letters only exist on Windows; other platforms pick mount points
+            if cfg!(windows) {
+                ui.selectable_value(&mut self.settings_tab, SettingsTab::Drives, "💾 Drives");
+            } else {
+                ui.selectable_value(&mut self.settings_tab, SettingsTab::Locations, "📍 Locations");
+            }
+            ui.selectable_value(&mut self.settings_tab, SettingsTab::Statistics, "📊 Statistics");
+            ui.selectable_value(&mut self.settings_tab, SettingsTab::Health, "🩺 Health");
+            ui.selectable_value(&mut self.settings_tab, SettingsTab::Status, "⚙️ Status");
+            ui.selectable_value(&mut self.settings_tab, SettingsTab::SavedSearches, "⭐ Saved Searches");
+            ui.selectable_value(&mut self.settings_tab, SettingsTab::Directories, "👁 Directories");
+            ui.selectable_value(&mut self.settings_tab, SettingsTab::About, "ℹ About");
+        });
+        
+        // The health dashboard refreshes each time it is opened
+        if self.settings_tab == SettingsTab::Health && previous_tab != SettingsTab::Health {
+            self.refresh_health();
+        }
+        
+        ui.separator();
+        ui.add_space(10.0);
+        
+        egui::ScrollArea::vertical()
+            .max_height(400.0)
+            .show(ui, |ui| {
+                match self.settings_tab {
                     SettingsTab::Configuration => {
                         ui.heading("Configuration");
                         ui.add_space(10.0);
@@ -398,6 +2980,366 @@ impl FlashFindApp {
                         });
                         ui.label(egui::RichText::new("(0 = disabled)").weak().small());
                         
+                        ui.add_space(10.0);
+                        
+                        // CSV export encoding
+                        if ui.checkbox(&mut self.config.csv_include_bom, "Add UTF-8 BOM to CSV exports")
+                            .on_hover_text("Helps Excel detect non-ASCII filenames correctly")
+                            .changed()
+                        {
+                            if let Err(e) = self.config.save() {
+                                warn!("Failed to save config: {}", e);
+                            }
+                        }
+                        
+                        // Privacy: forget past queries
+                        ui.horizontal(|ui| {
+                            let count = self.config.search_history.len();
+                            ui.label(format!("Search history: {} {}", count, if count == 1 { "query" } else { "queries" }));
+                            if ui.add_enabled(count > 0, egui::Button::new("🗑 Clear history")).clicked() {
+                                self.config.search_history.clear();
+                                self.history_recall.reset();
+                                if let Err(e) = self.config.save() {
+                                    warn!("Failed to save config: {}", e);
+                                }
+                            }
+                        });
+                        
+                        // Privacy: hide personal folder and file names in flashfind.log
+                        if ui.checkbox(&mut self.config.log_redaction, "Redact file paths in logs")
+                            .on_hover_text("Logs keep only the drive and top-level folder, so they are safe to share")
+                            .changed()
+                        {
+                            redact::set_enabled(self.config.log_redaction);
+                            info!("Log path redaction: {}", if self.config.log_redaction { "on" } else { "off" });
+                            if let Err(e) = self.config.save() {
+                                warn!("Failed to save config: {}", e);
+                            }
+                        }
+                        
+                        ui.add_space(15.0);
+                        ui.separator();
+                        ui.add_space(10.0);
+                        
+                        // Exclusion settings
+                        ui.label(egui::RichText::new("🚫 Exclusions").size(14.0).strong());
+                        ui.add_space(8.0);
+                        
+                        let mut exclusions_changed = false;
+                        exclusions_changed |= ui.checkbox(&mut self.config.index_system_file_types, "Index system file types")
+                            .on_hover_text(".dll and .sys files under Windows and Program Files are skipped unless enabled")
+                            .changed();
+                        
+                        ui.add_space(6.0);
+                        ui.label(egui::RichText::new("Custom patterns (name, prefix*, *.glob, a/b; prefix with ! to always include):").weak().small());
+                        
+                        let mut remove_idx = None;
+                        for (i, pattern) in self.config.custom_exclusions.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                if ui.small_button("✖").on_hover_text("Remove pattern").clicked() {
+                                    remove_idx = Some(i);
+                                }
+                                ui.label(egui::RichText::new(pattern).monospace());
+                            });
+                        }
+                        if let Some(i) = remove_idx {
+                            self.config.custom_exclusions.remove(i);
+                            exclusions_changed = true;
+                        }
+                        
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut self.new_exclusion)
+                                .hint_text("e.g. *.iso or !obj")
+                                .desired_width(220.0));
+                            let pattern = self.new_exclusion.trim().to_string();
+                            if ui.add_enabled(!pattern.is_empty(), egui::Button::new("➕ Add")).clicked() {
+                                if !self.config.custom_exclusions.contains(&pattern) {
+                                    self.config.custom_exclusions.push(pattern);
+                                    exclusions_changed = true;
+                                }
+                                self.new_exclusion.clear();
+                            }
+                        });
+                        
+                        ui.add_space(6.0);
+                        exclusions_changed |= ui.checkbox(&mut self.config.index_archives, "Index contents of .zip archives")
+                            .on_hover_text("Lists archive members by name (nothing is extracted) and finds them as archive.zip::member")
+                            .changed();
+                        ui.add_enabled_ui(self.config.index_archives, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("Skip archives larger than");
+                                exclusions_changed |= ui.add(egui::DragValue::new(&mut self.config.max_archive_size_mb)
+                                    .clamp_range(1..=4096)
+                                    .suffix(" MB"))
+                                    .changed();
+                            });
+                        });
+                        exclusions_changed |= ui.checkbox(&mut self.config.index_directories, "Index folders")
+                            .on_hover_text("Find folders by name as well as files; the Folders filter shows only them")
+                            .changed();
+                        let prune = ui.checkbox(&mut self.config.prune_on_load, "Remove deleted files at startup")
+                            .on_hover_text("After loading the saved index, check indexed files against the disk in the background and drop those that are gone; offline drives are left alone");
+                        if prune.changed() {
+                            if let Err(e) = self.config.save() {
+                                warn!("Failed to save config: {}", e);
+                            }
+                        }
+                        
+                        if exclusions_changed {
+                            self.apply_exclusion_settings();
+                        }
+                        
+                        self.render_exclusions_rescan(ui);
+                        
+                        ui.add_space(15.0);
+                        ui.separator();
+                        ui.add_space(10.0);
+                        
+                        // Result settings
+                        ui.label(egui::RichText::new("🔎 Results").size(14.0).strong());
+                        ui.add_space(8.0);
+                        
+                        let merge = ui.checkbox(&mut self.config.merge_aliases, "Merge aliases of the same file")
+                            .on_hover_text("Show a file reached through a junction, link or second drive letter once; the other paths are listed in \"Why this match?\"");
+                        if merge.changed() {
+                            if let Err(e) = self.config.save() {
+                                warn!("Failed to save config: {}", e);
+                            }
+                            self.do_search();
+                        }
+                        
+                        ui.horizontal(|ui| {
+                            ui.label("Typo tolerance:");
+                            let mut tolerance_changed = false;
+                            for tolerance in TypoTolerance::ALL {
+                                tolerance_changed |= ui
+                                    .selectable_value(&mut self.config.typo_tolerance, tolerance, tolerance.label())
+                                    .on_hover_text(match tolerance {
+                                        TypoTolerance::Off => "Exact matches only, never a fuzzy fallback",
+                                        TypoTolerance::Low => "One typo per four characters",
+                                        TypoTolerance::High => "One typo per three characters, from three-letter words",
+                                    })
+                                    .clicked();
+                            }
+                            if tolerance_changed {
+                                if let Err(e) = self.config.save() {
+                                    warn!("Failed to save config: {}", e);
+                                }
+                                self.do_search();
+                            }
+                        });
+                        
+                        let accents = ui.checkbox(&mut self.config.ignore_accents, "Ignore accents")
+                            .on_hover_text("Match \"resume\" to \"résumé\"; changing this refiles every indexed name");
+                        if accents.changed() {
+                            fold::set_ignore_accents(self.config.ignore_accents);
+                            self.index.write().sync_key_folding();
+                            if let Err(e) = self.config.save() {
+                                warn!("Failed to save config: {}", e);
+                            }
+                            self.do_search();
+                        }
+                        
+                        let trigrams = ui.checkbox(&mut self.config.trigram_index, "Faster substring search")
+                            .on_hover_text("Keeps every three-letter run of each filename in memory, so searches of three or more characters skip most of the index");
+                        if trigrams.changed() {
+                            self.index.write().set_trigram_index(self.config.trigram_index);
+                            if let Err(e) = self.config.save() {
+                                warn!("Failed to save config: {}", e);
+                            }
+                        }
+                        if let Some(bytes) = self.index.read().trigram_memory() {
+                            ui.label(egui::RichText::new(format!("Uses about {} of memory", format_size(bytes as u64))).weak().small());
+                        }
+                        
+                        let acronyms = ui.checkbox(&mut self.config.acronym_matching, "Match initials")
+                            .on_hover_text("Typing fbr also finds financial_budget_report.xlsx, listed after names containing fbr");
+                        if acronyms.changed() {
+                            self.index.write().set_acronym_matching(self.config.acronym_matching);
+                            if let Err(e) = self.config.save() {
+                                warn!("Failed to save config: {}", e);
+                            }
+                            self.do_search();
+                        }
+                        
+                        let hidden = ui.checkbox(&mut self.config.show_hidden_files, "Show hidden files")
+                            .on_hover_text("List hidden and system files in results; is:hidden finds them either way");
+                        if hidden.changed() {
+                            self.index.write().set_show_hidden(self.config.show_hidden_files);
+                            if let Err(e) = self.config.save() {
+                                warn!("Failed to save config: {}", e);
+                            }
+                            self.do_search();
+                        }
+                        
+                        ui.horizontal(|ui| {
+                            ui.label("Stop a search after");
+                            let timeout = ui.add(egui::DragValue::new(&mut self.config.search_timeout_ms)
+                                .clamp_range(0..=10_000)
+                                .suffix(" ms"))
+                                .on_hover_text("A search taking longer shows the results found so far; 0 never stops one");
+                            if timeout.changed() {
+                                if let Err(e) = self.config.save() {
+                                    warn!("Failed to save config: {}", e);
+                                }
+                            }
+                        });
+                        
+                        ui.horizontal(|ui| {
+                            ui.label("Search after a typing pause of");
+                            let debounce = ui.add(egui::DragValue::new(&mut self.config.search_debounce_ms)
+                                .clamp_range(0..=1000)
+                                .suffix(" ms"))
+                                .on_hover_text("Longer pauses keep typing smooth on large indexes; 0 searches on every key");
+                            if debounce.changed() {
+                                if let Err(e) = self.config.save() {
+                                    warn!("Failed to save config: {}", e);
+                                }
+                            }
+                        });
+                        
+                        ui.horizontal(|ui| {
+                            ui.label("Search names from");
+                            let min_length = ui.add(egui::DragValue::new(&mut self.config.min_query_length)
+                                .clamp_range(1..=10)
+                                .suffix(" characters"))
+                                .on_hover_text("Shorter names show \"keep typing\" instead of matching most files; .ext and * always search");
+                            if min_length.changed() {
+                                if let Err(e) = self.config.save() {
+                                    warn!("Failed to save config: {}", e);
+                                }
+                                self.do_search();
+                            }
+                        });
+                        
+                        ui.horizontal(|ui| {
+                            ui.label("Recently modified files shown");
+                            let recent = ui.add(egui::DragValue::new(&mut self.config.recent_files).clamp_range(0..=RECENT_CAPACITY))
+                                .on_hover_text("Listed while the search box is empty; 0 shows the welcome splash instead");
+                            if recent.changed() {
+                                if let Err(e) = self.config.save() {
+                                    warn!("Failed to save config: {}", e);
+                                }
+                            }
+                        });
+                        
+                        ui.add_space(6.0);
+                        ui.horizontal(|ui| {
+                            ui.label("Clicking a result:");
+                            let mut activation_changed = false;
+                            egui::ComboBox::from_id_source("result_activation")
+                                .selected_text(self.config.result_activation.label())
+                                .show_ui(ui, |ui| {
+                                    for activation in ResultActivation::ALL {
+                                        activation_changed |= ui
+                                            .selectable_value(&mut self.config.result_activation, activation, activation.label())
+                                            .clicked();
+                                    }
+                                });
+                            if activation_changed {
+                                if let Err(e) = self.config.save() {
+                                    warn!("Failed to save config: {}", e);
+                                }
+                            }
+                        });
+                        ui.label(egui::RichText::new("Ctrl+click adds a result to the selection; Enter opens the selection").weak().small());
+                        
+                        ui.add_space(15.0);
+                        ui.separator();
+                        ui.add_space(10.0);
+                        
+                        // Kinds shared by kind: queries and the file type filter
+                        ui.label(egui::RichText::new("🗂 File Kinds").size(14.0).strong());
+                        ui.add_space(8.0);
+                        ui.label(egui::RichText::new("Search a kind with kind:name, e.g. kind:images vacation; extensions are separated by commas").weak().small());
+                        
+                        let mut kinds_changed = false;
+                        let mut remove_idx = None;
+                        egui::Grid::new("kind_groups").num_columns(4).spacing([6.0, 4.0]).show(ui, |ui| {
+                            for (i, group) in self.config.kind_groups.iter_mut().enumerate() {
+                                kinds_changed |= ui.add(egui::TextEdit::singleline(&mut group.icon).desired_width(24.0)).lost_focus();
+                                kinds_changed |= ui.add(egui::TextEdit::singleline(&mut group.name).desired_width(90.0)).lost_focus();
+                                // Joined without spaces so editing round-trips; applying tidies it
+                                let mut extensions = group.extensions.join(",");
+                                let edit = ui.add(egui::TextEdit::singleline(&mut extensions).desired_width(260.0));
+                                if edit.changed() {
+                                    group.extensions = extensions.split(',').map(str::to_string).collect();
+                                }
+                                kinds_changed |= edit.lost_focus();
+                                if ui.small_button("✖").on_hover_text("Remove kind").clicked() {
+                                    remove_idx = Some(i);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                        if let Some(i) = remove_idx {
+                            self.config.kind_groups.remove(i);
+                            kinds_changed = true;
+                        }
+                        ui.horizontal(|ui| {
+                            if ui.button("➕ Add kind").clicked() {
+                                self.config.kind_groups.push(KindGroup { name: "new".to_string(), icon: String::new(), extensions: Vec::new() });
+                                kinds_changed = true;
+                            }
+                            if ui.button("Reset to defaults").clicked() {
+                                self.config.kind_groups = default_kind_groups();
+                                kinds_changed = true;
+                            }
+                        });
+                        if kinds_changed {
+                            self.apply_kind_groups();
+                        }
+                        
+                        ui.add_space(15.0);
+                        ui.separator();
+                        ui.add_space(10.0);
+                        
+                        // Open safety settings
+                        ui.label(egui::RichText::new("🔐 Opening Files").size(14.0).strong());
+                        ui.add_space(8.0);
+                        
+                        let mut open_settings_changed = ui.checkbox(&mut self.config.strict_open_mode, "Strict mode")
+                            .on_hover_text("Refuse to open links or junctions that resolve outside the indexed folders")
+                            .changed();
+                        
+                        ui.add_space(6.0);
+                        ui.label(egui::RichText::new("Network locations allowed to open (\\\\server\\share):").weak().small());
+                        
+                        let mut remove_idx = None;
+                        for (i, location) in self.config.network_locations.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                if ui.small_button("✖").on_hover_text("Remove location").clicked() {
+                                    remove_idx = Some(i);
+                                }
+                                ui.label(egui::RichText::new(location.display().to_string()).monospace());
+                            });
+                        }
+                        if let Some(i) = remove_idx {
+                            self.config.network_locations.remove(i);
+                            open_settings_changed = true;
+                        }
+                        
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut self.new_network_location)
+                                .hint_text("\\\\nas\\shared")
+                                .desired_width(220.0));
+                            let location = PathBuf::from(self.new_network_location.trim());
+                            let valid = location.to_string_lossy().starts_with("\\\\");
+                            if ui.add_enabled(valid, egui::Button::new("➕ Add")).clicked() {
+                                if !self.config.network_locations.contains(&location) {
+                                    self.config.network_locations.push(location);
+                                    open_settings_changed = true;
+                                }
+                                self.new_network_location.clear();
+                            }
+                        });
+                        
+                        if open_settings_changed {
+                            if let Err(e) = self.config.save() {
+                                warn!("Failed to save config: {}", e);
+                            }
+                        }
+                        
                         ui.add_space(15.0);
                         ui.separator();
                         ui.add_space(10.0);
@@ -414,8 +3356,8 @@ impl FlashFindApp {
                                 ui.vertical(|ui| {
                                     ui.spacing_mut().item_spacing.y = 6.0;
                                     ui.label(egui::RichText::new("• Start typing to search instantly").size(12.0));
-                                    ui.label(egui::RichText::new("• Press Enter to open the first result").size(12.0));
-                                    ui.label(egui::RichText::new("• Press Esc to clear your search").size(12.0));
+                                    ui.label(egui::RichText::new("• Press Enter in the search box to open the first result").size(12.0));
+                                    ui.label(egui::RichText::new("• Press Esc to close this window, or to clear your search").size(12.0));
                                     ui.label(egui::RichText::new("• Use file type filters for specific searches").size(12.0));
                                     ui.label(egui::RichText::new("• Right-click results for more options").size(12.0));
                                 });
@@ -429,7 +3371,7 @@ impl FlashFindApp {
                         ui.label(egui::RichText::new("Select which drives to index:").weak());
                         ui.add_space(10.0);
                         
-                        let available_drives = crate::watcher::get_available_drives();
+                        let available_drives = platform::get_available_drives();
                         
                         for drive in &available_drives {
                             let mut is_enabled = self.config.enabled_drives.contains(drive);
@@ -476,73 +3418,216 @@ impl FlashFindApp {
                         
                         ui.add_space(10.0);
                         
-                        if ui.button("🔄 Apply & Re-index").on_hover_text("Save drive selection and rebuild index").clicked() {
-                            if !self.config.enabled_drives.is_empty() {
-                                if let Err(e) = self.config.save() {
-                                    warn!("Failed to save config: {}", e);
-                                    self.last_error = Some(format!("Failed to save config: {}", e));
-                                } else {
-                                    // Clear existing index before re-indexing with new drive selection
-                                    self.index.write().clear();
-                                    
-                                    // Trigger re-indexing
-                                    let dirs = crate::watcher::get_directories_for_drives(&self.config.enabled_drives);
-                                    if let Err(e) = self.indexer.start_scan(dirs.clone()) {
-                                        error!("Failed to start re-indexing: {}", e);
-                                        self.last_error = Some(e.user_message());
-                                    } else {
-                                        // Update watcher
-                                        if let Some(ref mut watcher) = self.watcher {
-                                            match watcher.watch_directories(dirs) {
-                                                Ok(errors) => {
-                                                    for err in errors {
-                                                        warn!("Watcher error: {}", err);
-                                                    }
-                                                }
-                                                Err(e) => error!("Failed to setup watchers: {}", e),
-                                            }
-                                        }
-                                        info!("Re-indexing started for drives: {:?}", self.config.enabled_drives);
-                                    }
-                                }
-                            } else {
-                                self.last_error = Some("Please select at least one drive".to_string());
+                        if ui.button("🔄 Apply & Re-index").on_hover_text("Save drive selection and index only the drives that changed").clicked() {
+                            if !self.config.enabled_drives.is_empty() {
+                                self.apply_drive_selection();
+                            } else {
+                                self.last_error = Some("Please select at least one drive".to_string());
+                            }
+                        }
+                        
+                        ui.add_space(5.0);
+                        ui.label(
+                            egui::RichText::new("ℹ Changes require clicking Apply to take effect")
+                            .weak()
+                            .small()
+                        );
+                    }
+                    
+                    SettingsTab::Locations => {
+                        ui.heading("Locations");
+                        ui.add_space(10.0);
+                        
+                        ui.label(
+                            egui::RichText::new("Your home folders (Documents, Downloads, Desktop, …) are always indexed.")
+                                .weak()
+                        );
+                        ui.add_space(10.0);
+                        ui.label("Also index these mounted volumes:");
+                        ui.add_space(5.0);
+                        
+                        for mount in platform::mount_points() {
+                            let mut is_enabled = self.config.enabled_locations.contains(&mount);
+                            if ui.checkbox(&mut is_enabled, mount.display().to_string()).changed() {
+                                if is_enabled {
+                                    self.config.enabled_locations.push(mount);
+                                } else {
+                                    self.config.enabled_locations.retain(|m| m != &mount);
+                                }
+                            }
+                        }
+                        
+                        ui.add_space(10.0);
+                        
+                        if ui.button("🔄 Apply & Re-index").on_hover_text("Save location selection and rebuild index").clicked() {
+                            self.apply_index_locations();
+                        }
+                        
+                        ui.add_space(5.0);
+                        ui.label(
+                            egui::RichText::new("ℹ Changes require clicking Apply to take effect")
+                            .weak()
+                            .small()
+                        );
+                    }
+                    
+                    SettingsTab::Statistics => {
+                        ui.heading("Index Statistics");
+                        ui.add_space(10.0);
+                        
+                        let stats = self.index.read();
+                        let report = stats.stats();
+                        let live_count = stats.len();
+                        let (tombstones, dead_ratio) = (stats.tombstones(), stats.dead_ratio());
+                        let generation = stats.generation();
+                        if self.memory_usage.is_none_or(|(at, _)| at != generation) {
+                            self.memory_usage = Some((generation, stats.memory_usage()));
+                        }
+                        if self.largest_folders.as_ref().is_none_or(|(at, _)| *at != generation) {
+                            self.largest_folders = Some((generation, stats.largest_folders(LARGEST_FOLDERS)));
+                        }
+                        if self.drive_counts.as_ref().is_none_or(|(at, _)| *at != generation) {
+                            self.drive_counts = Some((generation, stats.drive_counts()));
+                        }
+                        if self.top_extensions.as_ref().is_none_or(|(at, _)| *at != generation) {
+                            let mut counts = stats.extension_counts();
+                            counts.truncate(TOP_EXTENSIONS);
+                            self.top_extensions = Some((generation, counts));
+                        }
+                        drop(stats);
+                        
+                        ui.horizontal(|ui| {
+                            ui.label("Live files:");
+                            ui.label(egui::RichText::new(format!("{}", live_count)).strong());
+                        });
+                        if let Some((_, drives)) = self.drive_counts.as_ref().filter(|(_, drives)| !drives.is_empty()) {
+                            ui.horizontal(|ui| {
+                                ui.label("By drive:");
+                                let counts: Vec<String> = drives.iter().map(|(letter, files)| format!("{}: {}", letter, files)).collect();
+                                ui.label(egui::RichText::new(counts.join(", ")).strong());
+                            });
+                        }
+                        ui.add_space(8.0);
+                        
+                        let (lifetime, session) = (report.lifetime, report.session);
+                        let rows = [
+                            ("Insertions", lifetime.insertions, session.insertions),
+                            ("Duplicates skipped", lifetime.duplicates, session.duplicates),
+                            ("Removals", lifetime.removals, session.removals),
+                            ("Searches performed", lifetime.searches, session.searches),
+                            ("Watcher events", lifetime.watcher_events, session.watcher_events),
+                        ];
+                        
+                        egui::Grid::new("index_stats_grid")
+                            .num_columns(3)
+                            .spacing([20.0, 4.0])
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.label("");
+                                ui.label(egui::RichText::new("Lifetime").strong());
+                                ui.label(egui::RichText::new("This session").strong());
+                                ui.end_row();
+                                
+                                for (name, total, current) in rows {
+                                    ui.label(format!("{}:", name));
+                                    ui.label(format!("{}", total));
+                                    ui.label(format!("{}", current));
+                                    ui.end_row();
+                                }
+                            });
+                        
+                        ui.add_space(4.0);
+                        ui.label(
+                            egui::RichText::new("Lifetime counters are saved with the index; session counters reset on launch.")
+                                .size(11.0)
+                                .weak()
+                        );
+                        
+                        ui.add_space(8.0);
+                        let cache = report.cache;
+                        ui.horizontal(|ui| {
+                            ui.label("Result cache:");
+                            ui.label(
+                                egui::RichText::new(format!(
+                                    "{} hits, {} misses this session ({:.0}% served from cache)",
+                                    cache.hits,
+                                    cache.misses,
+                                    cache.hit_rate() * 100.0
+                                ))
+                                .strong(),
+                            );
+                        });
+                        
+                        if let Some((_, memory)) = self.memory_usage {
+                            ui.add_space(8.0);
+                            ui.horizontal(|ui| {
+                                ui.label("Memory:");
+                                ui.label(egui::RichText::new(format!("about {}", format_size(memory.total() as u64))).strong());
+                            });
+                            egui::CollapsingHeader::new("Memory by structure").show(ui, |ui| {
+                                egui::Grid::new("memory_grid").num_columns(2).spacing([20.0, 4.0]).striped(true).show(ui, |ui| {
+                                    for (label, bytes) in memory.parts() {
+                                        ui.label(format!("{}:", label));
+                                        ui.label(format_size(bytes as u64));
+                                        ui.end_row();
+                                    }
+                                });
+                                ui.label(egui::RichText::new("Estimated from allocated capacity; removed entries count until compaction.").size(11.0).weak());
+                            });
+                        }
+                        
+                        if let Some((_, folders)) = self.largest_folders.as_ref().filter(|(_, folders)| !folders.is_empty()) {
+                            egui::CollapsingHeader::new("Largest folders").show(ui, |ui| {
+                                egui::Grid::new("largest_folders_grid").num_columns(2).spacing([20.0, 4.0]).striped(true).show(ui, |ui| {
+                                    for (folder, files) in folders {
+                                        ui.label(folder.display().to_string());
+                                        ui.label(format!("{} entries", files));
+                                        ui.end_row();
+                                    }
+                                });
+                                ui.label(egui::RichText::new("Counts only entries directly inside each folder.").size(11.0).weak());
+                            });
+                        }
+                        
+                        if let Some((_, extensions)) = self.top_extensions.as_ref().filter(|(_, extensions)| !extensions.is_empty()) {
+                            egui::CollapsingHeader::new("Top file types").show(ui, |ui| {
+                                egui::Grid::new("top_extensions_grid").num_columns(2).spacing([20.0, 4.0]).striped(true).show(ui, |ui| {
+                                    for (ext, files) in extensions {
+                                        ui.label(format!(".{}", ext));
+                                        ui.label(format!("{} files", format_count(*files)));
+                                        ui.end_row();
+                                    }
+                                });
+                            });
+                        }
+                        
+                        ui.add_space(15.0);
+                        ui.separator();
+                        ui.add_space(10.0);
+                        
+                        ui.label(egui::RichText::new("📂 Space usage").size(14.0).strong());
+                        ui.add_space(8.0);
+                        ui.label(egui::RichText::new("Totals by folder, built from the indexed files without walking the disk.").size(12.0).weak());
+                        ui.add_space(8.0);
+                        let mut usage_root = None;
+                        for root in platform::configured_directories(&self.config) {
+                            if ui.button(format!("📂 {}", root.display())).on_hover_text("Show space usage by folder").clicked() {
+                                usage_root = Some(root);
                             }
                         }
+                        if let Some(root) = usage_root {
+                            self.open_space_usage(root);
+                        }
                         
-                        ui.add_space(5.0);
-                        ui.label(
-                            egui::RichText::new("ℹ Changes require clicking Apply to take effect")
-                            .weak()
-                            .small()
-                        );
-                    }
-                    
-                    SettingsTab::Statistics => {
-                        ui.heading("Index Statistics");
+                        ui.add_space(15.0);
+                        ui.separator();
                         ui.add_space(10.0);
+                        self.render_weekly_activity(ui);
                         
-                        let stats = self.index.read();
-                        let (insertions, duplicates, searches) = stats.stats();
-                        let live_count = stats.len();
-                        drop(stats);
-                        
-                        ui.horizontal(|ui| {
-                            ui.label("Live files:");
-                            ui.label(egui::RichText::new(format!("{}", live_count)).strong());
-                        });
-                        ui.horizontal(|ui| {
-                            ui.label("Insertions:");
-                            ui.label(format!("{}", insertions));
-                        });
-                        ui.horizontal(|ui| {
-                            ui.label("Duplicates skipped:");
-                            ui.label(format!("{}", duplicates));
-                        });
-                        ui.horizontal(|ui| {
-                            ui.label("Searches performed:");
-                            ui.label(format!("{}", searches));
-                        });
+                        ui.add_space(15.0);
+                        ui.separator();
+                        ui.add_space(10.0);
+                        self.render_snapshots(ui);
                         
                         ui.add_space(15.0);
                         ui.separator();
@@ -550,25 +3635,80 @@ impl FlashFindApp {
                         
                         ui.label(egui::RichText::new("🗜️ Index Maintenance").size(14.0).strong());
                         ui.add_space(8.0);
-                        ui.label(egui::RichText::new("Compaction removes deleted file entries and frees memory.").size(12.0).weak());
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "Compaction removes deleted file entries and frees memory. It runs in the background once they make up {:.0}% of the index.",
+                                COMPACTION_THRESHOLD * 100.0
+                            ))
+                            .size(12.0)
+                            .weak(),
+                        );
                         ui.add_space(8.0);
                         
-                        if ui.button("🗜️ Compact Index").on_hover_text("Remove tombstones and optimize memory").clicked() {
-                            match self.index.write().compact() {
-                                Ok(removed) => {
-                                    info!("Manual compaction: removed {} tombstones", removed);
-                                    if removed > 0 {
-                                        self.last_error = Some(format!("✓ Compacted: removed {} deleted entries", removed));
-                                    } else {
-                                        self.last_error = Some("✓ Index already compact".to_string());
-                                    }
-                                }
+                        ui.horizontal(|ui| {
+                            ui.label("Deleted entries awaiting compaction:");
+                            ui.label(egui::RichText::new(format!("{} ({:.1}%)", tombstones, dead_ratio * 100.0)).strong());
+                        });
+                        let compactions = self.indexer.compactions();
+                        ui.horizontal(|ui| {
+                            ui.label("Reclaimed this session:");
+                            let summary = match compactions.last {
+                                Some((when, last)) => format!(
+                                    "{} entries in {} compactions (last {}, {} entries)",
+                                    compactions.reclaimed,
+                                    compactions.runs,
+                                    format_ago(since(when)),
+                                    last
+                                ),
+                                None => "no compaction yet".to_string(),
+                            };
+                            ui.label(egui::RichText::new(summary).strong());
+                        });
+                        if let Some(prune) = self.indexer.last_prune() {
+                            ui.horizontal(|ui| {
+                                ui.label("Deleted files pruned:");
+                                let checked = if prune.complete { "all".to_string() } else { format!("stopped after {}", prune.checked) };
+                                ui.label(egui::RichText::new(format!("{} ({} entries checked)", prune.pruned, checked)).strong());
+                            });
+                        }
+                        ui.add_space(8.0);
+                        
+                        if ui.button("🗜️ Compact Index").on_hover_text("Remove deleted entries in the background").clicked() {
+                            match self.indexer.compact(true) {
+                                Ok(()) => self.last_error = Some("✓ Compacting index in the background".to_string()),
                                 Err(e) => {
                                     error!("Compaction failed: {}", e);
                                     self.last_error = Some(format!("Compaction failed: {}", e.user_message()));
                                 }
                             }
                         }
+                        
+                        if let Some(job) = &self.export_job {
+                            let (written, total) = job.progress();
+                            ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label(format!("Exporting {} of {} entries...", written, total));
+                                if ui.button("Cancel").clicked() {
+                                    job.cancel();
+                                }
+                            });
+                        } else if ui.button("📊 Export Index").on_hover_text("Export every indexed entry to CSV").clicked() {
+                            self.export_to_csv(true);
+                        }
+                        
+                        if ui.button("🩺 Check Integrity").on_hover_text("Verify index structures and repair inconsistencies").clicked() {
+                            let report = self.index.write().repair();
+                            info!("Integrity check: {:?}", report);
+                            if report.is_consistent() {
+                                self.last_error = Some("✓ Index is consistent".to_string());
+                            } else {
+                                self.last_error = Some(format!("✓ Repaired {} index inconsistencies", report.issues()));
+                            }
+                        }
+                    }
+                    
+                    SettingsTab::Health => {
+                        self.render_health(ui, ctx);
                     }
                     
                     SettingsTab::Status => {
@@ -582,6 +3722,12 @@ impl FlashFindApp {
                             IndexState::Scanning { progress } => {
                                 ui.colored_label(egui::Color32::from_rgb(255, 200, 100), format!("🔄 Scanning: {} files", progress));
                             }
+                            IndexState::Pruning { checked, total } => {
+                                ui.colored_label(egui::Color32::from_rgb(255, 200, 100), format!("🔍 Checking for deleted files: {} of {}", checked, total));
+                            }
+                            IndexState::Compacting => {
+                                ui.colored_label(egui::Color32::from_rgb(100, 200, 255), "🗜️ Compacting...");
+                            }
                             IndexState::Saving => {
                                 ui.colored_label(egui::Color32::from_rgb(100, 200, 255), "💾 Saving...");
                             }
@@ -589,6 +3735,90 @@ impl FlashFindApp {
                                 ui.colored_label(egui::Color32::from_rgb(255, 100, 100), format!("❌ Error: {}", message));
                             }
                         }
+                        
+                        // Folders skipped for permissions, with an opt-in elevated rescan
+                        let denied = self.indexer.denied_dirs();
+                        if !denied.is_empty() || self.elevated_scan.is_some() {
+                            ui.add_space(15.0);
+                            ui.separator();
+                            ui.add_space(10.0);
+                            ui.label(egui::RichText::new(format!("🔒 {} folders skipped (access denied)", denied.len())).size(14.0).strong());
+                            ui.add_space(6.0);
+                            
+                            egui::ScrollArea::vertical().max_height(120.0).show(ui, |ui| {
+                                for dir in &denied {
+                                    ui.label(egui::RichText::new(dir.display().to_string()).weak().size(11.5));
+                                }
+                            });
+                            ui.add_space(8.0);
+                            
+                            if self.elevated_scan.is_some() {
+                                let mut cancel = false;
+                                ui.horizontal(|ui| {
+                                    ui.spinner();
+                                    ui.label("Waiting for the elevated scan to finish...");
+                                    cancel = ui.button("Cancel").clicked();
+                                });
+                                if cancel {
+                                    if let Some(scan) = self.elevated_scan.take() {
+                                        scan.cancel();
+                                    }
+                                }
+                            } else {
+                                let retry = ui.add_enabled(cfg!(windows), egui::Button::new("🛡 Retry with administrator rights"))
+                                    .on_hover_text("Scans only these folders in a separate elevated helper; FlashFind itself keeps running without admin rights")
+                                    .on_disabled_hover_text("Only available on Windows");
+                                if retry.clicked() {
+                                    self.start_elevated_scan();
+                                }
+                            }
+                        }
+                        
+                        let corrupt = self.indexer.corrupt_archives();
+                        if !corrupt.is_empty() {
+                            ui.add_space(15.0);
+                            ui.separator();
+                            ui.add_space(10.0);
+                            ui.label(egui::RichText::new(format!("🗜 {} archives skipped (unreadable)", corrupt.len())).size(14.0).strong());
+                            ui.add_space(6.0);
+                            
+                            egui::ScrollArea::vertical().id_source("corrupt_archives").max_height(120.0).show(ui, |ui| {
+                                for archive in &corrupt {
+                                    ui.label(egui::RichText::new(archive.display().to_string()).weak().size(11.5));
+                                }
+                            });
+                        }
+                        
+                        // Watcher-driven changes, so a vanished file can be told apart from a trashed one
+                        let recent = self.watcher.as_ref().map(|w| w.recent_changes()).unwrap_or_default();
+                        if !recent.is_empty() {
+                            ui.add_space(15.0);
+                            ui.separator();
+                            ui.add_space(10.0);
+                            ui.label(egui::RichText::new("🕘 Recent changes").size(14.0).strong());
+                            ui.add_space(6.0);
+                            
+                            egui::ScrollArea::vertical().id_source("recent_changes").max_height(160.0).show(ui, |ui| {
+                                for change in &recent {
+                                    let text = format!("{} — {} ({})", change.path.display(), change.kind.label(), format_ago(since(change.at)));
+                                    let label = egui::RichText::new(text).size(11.5);
+                                    match change.kind {
+                                        ChangeKind::MovedToTrash => {
+                                            ui.label(label.color(egui::Color32::from_rgb(255, 180, 100)))
+                                                .on_hover_text("Restore it from the Recycle Bin to bring it back");
+                                        }
+                                        _ => {
+                                            ui.label(label.weak());
+                                        }
+                                    }
+                                }
+                            });
+                        }
+                        
+                        ui.add_space(15.0);
+                        ui.separator();
+                        ui.add_space(10.0);
+                        self.render_logging(ui);
                     }
                     
                     SettingsTab::Directories => {
@@ -607,6 +3837,16 @@ impl FlashFindApp {
                         } else {
                             ui.colored_label(egui::Color32::from_rgb(255, 150, 100), "⚠ File watcher disabled");
                         }
+                        
+                        ui.add_space(16.0);
+                        self.render_include_filters(ui);
+                        
+                        ui.add_space(16.0);
+                        self.render_favorite_folders(ui);
+                    }
+                    
+                    SettingsTab::SavedSearches => {
+                        self.render_saved_searches(ui);
                     }
                     
                     SettingsTab::About => {
@@ -629,7 +3869,7 @@ impl FlashFindApp {
                         });
                         
                         ui.add_space(10.0);
-                        ui.label("High-performance file search for Windows");
+                        ui.label("High-performance file search for Windows, macOS and Linux");
                         ui.label(egui::RichText::new("MIT License © 2026").weak().small());
                         
                         ui.add_space(10.0);
@@ -650,39 +3890,115 @@ impl eframe::App for FlashFindApp {
         
         // Auto-save check
         if self.config.auto_save_interval > 0 {
-            let elapsed = self.last_save.elapsed();
-            if elapsed >= Duration::from_secs(self.config.auto_save_interval) {
+            let elapsed = self.saver.last_save().elapsed();
+            if elapsed >= Duration::from_secs(self.config.auto_save_interval) && self.saver.is_dirty() {
                 debug!("Auto-save triggered after {}s", elapsed.as_secs());
                 self.handle_save();
-                self.last_save = Instant::now();
             }
         }
         
-        // Handle keyboard shortcuts
-        let escape_pressed = ctx.input(|i| i.key_pressed(egui::Key::Escape));
-        let enter_pressed = ctx.input(|i| i.key_pressed(egui::Key::Enter));
-        let first_result = if !self.results.is_empty() {
-            Some(self.results[0].clone())
-        } else {
-            None
-        };
+        // Compact on the indexer thread once removals leave enough dead slots
+        if !is_indexing && self.index.read().dead_ratio() >= COMPACTION_THRESHOLD {
+            if let Err(e) = self.indexer.compact(false) {
+                warn!("Failed to queue compaction: {}", e);
+            }
+        }
         
-        if escape_pressed {
-            self.query.clear();
-            self.results.clear();
-            self.last_error = None;
+        // Re-probe roots and resume watching any that came back online
+        self.handle_root_availability(ctx);
+        self.handle_elevated_scan(ctx);
+        
+        // The completion dropdown gets the arrow keys, Tab and Enter first
+        let suggesting = self.suggestions_open && !self.suggestions.is_empty();
+        if suggesting && self.focus == FocusTarget::Search && self.modals.top().is_none() {
+            self.handle_suggestion_keys(ctx);
+        } else if self.focus == FocusTarget::Search && self.modals.top().is_none() {
+            self.handle_history_keys(ctx);
         }
         
-        if enter_pressed {
-            if let Some(path) = first_result {
-                self.open_file(&path);
+        // Handle keyboard shortcuts based on modal and focus state
+        for key in [egui::Key::Escape, egui::Key::Enter] {
+            if !ctx.input(|i| i.key_pressed(key)) {
+                continue;
+            }
+            
+            let search_box = if self.suggestions_open && !self.suggestions.is_empty() {
+                SearchBoxState::Suggesting
+            } else if self.search_pending {
+                SearchBoxState::Unsearched
+            } else {
+                SearchBoxState::Searched
+            };
+            match resolve_shortcut(key, self.modals.top(), self.focus, search_box) {
+                Some(ShortcutAction::CloseModal(modal)) => self.close_modal(modal),
+                Some(ShortcutAction::CloseSuggestions) => self.close_suggestions(),
+                Some(ShortcutAction::RunSearch) => {
+                    self.remember_query(&self.query.clone());
+                    self.browse_page = 0;
+                    self.last_error = None;
+                    self.do_search();
+                }
+                Some(ShortcutAction::ClearSearch) => {
+                    if !self.results.is_empty() {
+                        self.remember_query(&self.query.clone());
+                    }
+                    self.history_recall.reset();
+                    self.query.clear();
+                    self.selection.clear();
+                    self.suggestions.clear();
+                    self.close_suggestions();
+                    self.search_pending = false;
+                    self.search_due = None;
+                    self.results.clear();
+                    self.search_hint = None;
+                    self.results_truncated = false;
+                    if let Some(job) = self.size_job.take() {
+                        job.cancel();
+                    }
+                    if let Some(job) = self.search_job.take() {
+                        job.cancel();
+                    }
+                    self.last_error = None;
+                }
+                Some(ShortcutAction::OpenResult(row)) => {
+                    // Consume Enter so the focused row's link does not open it a second time
+                    ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, key));
+                    self.remember_query(&self.query.clone());
+                    // Enter does not wait out the typing pause
+                    if self.search_due.is_some() {
+                        self.do_search();
+                    }
+                    self.wait_for_search();
+                    self.open_selection(row);
+                }
+                None => {}
             }
         }
         
+        if ctx.input_mut(|i| i.consume_shortcut(&egui::KeyboardShortcut::new(egui::Modifiers::CTRL | egui::Modifiers::SHIFT, egui::Key::N))) {
+            self.toggle_incognito();
+        }
+        
         // Header panel
+        let mut search_focused = false;
         let mut should_save = false;
         let mut should_reindex = false;
         
+        // Ephemeral mode banner, shown for the whole session
+        if let Some(warning) = &self.storage_warning {
+            egui::TopBottomPanel::top("storage_banner")
+                .frame(egui::Frame::none()
+                    .fill(egui::Color32::from_rgb(90, 60, 20))
+                    .inner_margin(egui::Margin::symmetric(16.0, 6.0)))
+                .show(ctx, |ui| {
+                    ui.label(egui::RichText::new(format!("⚠ {}", warning)).color(egui::Color32::WHITE));
+                    ui.label(egui::RichText::new(format!(
+                        "To keep data, set {} to a writable folder (portable mode) and restart.",
+                        platform::DATA_DIR_ENV
+                    )).small().color(egui::Color32::from_rgb(230, 220, 200)));
+                });
+        }
+        
         egui::TopBottomPanel::top("header")
             .frame(egui::Frame::none()
                 .fill(ctx.style().visuals.panel_fill)
@@ -702,6 +4018,13 @@ impl eframe::App for FlashFindApp {
                                 ui.add(egui::Spinner::new().size(14.0));
                                 ui.label(egui::RichText::new(format!("Indexing {} files", progress)).weak().size(13.0));
                             }
+                            IndexState::Pruning { checked, total } => {
+                                ui.add(egui::Spinner::new().size(14.0));
+                                ui.label(egui::RichText::new(format!("Checking {} of {} files", checked, total)).weak().size(13.0));
+                            }
+                            IndexState::Compacting => {
+                                ui.label(egui::RichText::new("🗜️ Compacting...").weak().size(13.0));
+                            }
                             IndexState::Saving => {
                                 ui.label(egui::RichText::new("💾 Saving...").weak().size(13.0));
                             }
@@ -716,7 +4039,40 @@ impl eframe::App for FlashFindApp {
                         ui.add_space(4.0);
                         
                         if !self.results.is_empty() && ui.button(egui::RichText::new("📊 Export").size(13.0)).on_hover_text("Export results to CSV").clicked() {
-                            self.export_to_csv();
+                            self.export_to_csv(false);
+                        }
+                        
+                        if !self.results.is_empty() && ui.button(egui::RichText::new("✏ Batch rename…").size(13.0))
+                            .on_hover_text("Rename these results with a pattern")
+                            .clicked()
+                        {
+                            self.open_batch_rename();
+                        }
+                        
+                        if ui.add_enabled(self.duplicate_job.is_none(), egui::Button::new(egui::RichText::new("🧬 Duplicates").size(13.0)))
+                            .on_hover_text("List indexed files that share a name")
+                            .clicked()
+                        {
+                            self.find_duplicates();
+                        }
+                        
+                        let saved = self.config.saved_searches.iter().any(|s| s.query == self.query.trim());
+                        if privacy::should_record_query(&self.query) && !saved && ui.button(egui::RichText::new("⭐ Save search").size(13.0))
+                            .on_hover_text("Keep this query under Settings → Saved Searches")
+                            .clicked()
+                        {
+                            self.save_current_search();
+                        }
+                        
+                        if !self.results.is_empty() {
+                            let can_open = self.result_windows.len() < MAX_RESULT_WINDOWS;
+                            if ui.add_enabled(can_open, egui::Button::new(egui::RichText::new("🗗 New window").size(13.0)))
+                                .on_hover_text("Keep these results in a separate window")
+                                .on_disabled_hover_text(format!("At most {} results windows can be open", MAX_RESULT_WINDOWS))
+                                .clicked()
+                            {
+                                self.open_results_window();
+                            }
                         }
                         
                         if ui.button(egui::RichText::new("💾 Save").size(13.0)).on_hover_text("Save index now").clicked() {
@@ -728,7 +4084,11 @@ impl eframe::App for FlashFindApp {
                         }
                         
                         if ui.button(egui::RichText::new("⚙ Settings").size(13.0)).clicked() {
-                            self.show_settings = !self.show_settings;
+                            if self.modals.is_open(Modal::Settings) {
+                                self.close_modal(Modal::Settings);
+                            } else {
+                                self.modals.open(Modal::Settings);
+                            }
                         }
                     });
                 });
@@ -741,43 +4101,161 @@ impl eframe::App for FlashFindApp {
                     let mut filter_changed = false;
                     egui::ComboBox::from_id_source("file_type_filter")
                         .selected_text(egui::RichText::new(self.file_type_filter.label()).size(13.0))
-                        .width(120.0)
+                        .width(150.0)
                         .show_ui(ui, |ui| {
+                            // Only runs while the dropdown is open
+                            let index = self.index.read();
+                            let generation = index.generation();
+                            if self.kind_counts.as_ref().is_none_or(|(at, _)| *at != generation) {
+                                let counts = self.config.kind_groups.iter().map(|group| index.count_with_extensions(&group.extensions)).collect();
+                                self.kind_counts = Some((generation, counts));
+                            }
+                            drop(index);
+                            let counts = self.kind_counts.as_ref().map(|(_, counts)| counts.as_slice()).unwrap_or_default();
+                            
                             filter_changed |= ui.selectable_value(&mut self.file_type_filter, FileTypeFilter::All, "📋 All Files").clicked();
-                            filter_changed |= ui.selectable_value(&mut self.file_type_filter, FileTypeFilter::Documents, "📄 Documents").clicked();
-                            filter_changed |= ui.selectable_value(&mut self.file_type_filter, FileTypeFilter::Images, "🖼️ Images").clicked();
-                            filter_changed |= ui.selectable_value(&mut self.file_type_filter, FileTypeFilter::Videos, "🎥 Videos").clicked();
-                            filter_changed |= ui.selectable_value(&mut self.file_type_filter, FileTypeFilter::Audio, "🎵 Audio").clicked();
-                            filter_changed |= ui.selectable_value(&mut self.file_type_filter, FileTypeFilter::Code, "💻 Code").clicked();
-                            filter_changed |= ui.selectable_value(&mut self.file_type_filter, FileTypeFilter::Archives, "📦 Archives").clicked();
+                            for (i, group) in self.config.kind_groups.iter().enumerate() {
+                                let filter = FileTypeFilter::Kind(group.name.clone());
+                                let mut text = format!("{} {}", group.icon, group.label());
+                                if let Some(files) = counts.get(i) {
+                                    text.push_str(&format!(" ({})", format_count(*files)));
+                                }
+                                filter_changed |= ui.selectable_value(&mut self.file_type_filter, filter, text.trim()).clicked();
+                            }
+                            filter_changed |= ui.selectable_value(&mut self.file_type_filter, FileTypeFilter::Folders, "📁 Folders").clicked();
+                        });
+                    
+                    
+                    ui.add_space(12.0);
+                    ui.label(egui::RichText::new("Rank:").size(13.0));
+                    let mut ranking_changed = false;
+                    egui::ComboBox::from_id_source("ranking_preset")
+                        .selected_text(egui::RichText::new(self.config.ranking_preset.label()).size(13.0))
+                        .width(140.0)
+                        .show_ui(ui, |ui| {
+                            for preset in RankingPreset::ALL {
+                                ranking_changed |= ui.selectable_value(&mut self.config.ranking_preset, preset, preset.label()).clicked();
+                            }
                         });
                     
-                    if filter_changed {
+                    ui.add_space(12.0);
+                    let fuzzy_changed = ui.checkbox(&mut self.config.fuzzy_search, egui::RichText::new("≈ Fuzzy").size(13.0))
+                        .on_hover_text("Also match names with typos, closest first. Searches with no exact matches fall back to this anyway")
+                        .changed();
+                    let whole_word_changed = ui.checkbox(&mut self.config.whole_word, egui::RichText::new("ab Whole word").size(13.0))
+                        .on_hover_text("Only match names holding the search as a whole word, between separators or case changes, as \\bword does")
+                        .changed();
+                    
+                    if filter_changed || ranking_changed || fuzzy_changed || whole_word_changed {
+                        self.browse_page = 0;
                         self.do_search();
                     }
+                    if ranking_changed || fuzzy_changed || whole_word_changed {
+                        if let Err(e) = self.config.save() {
+                            warn!("Failed to save config: {}", e);
+                        }
+                    }
                 });
                 
+                if self.config.ranking_preset == RankingPreset::Custom {
+                    self.render_custom_ranking(ui);
+                }
+                
                 ui.add_space(8.0);
                 
-                // Search box
-                let search = ui.add(
-                    egui::TextEdit::singleline(&mut self.query)
-                        .hint_text("🔍 Search files... (Enter to open, Esc to clear)")
-                        .desired_width(f32::INFINITY)
-                        .font(egui::TextStyle::Body)
-                        .margin(egui::vec2(8.0, 6.0))
-                        .lock_focus(true),
-                );
+                // Search box, with the incognito toggle and syntax help button at its end
+                let incognito = privacy::is_incognito();
+                let mut show_syntax = false;
+                let mut toggle_incognito = false;
+                let search = ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    show_syntax = ui.button("?").on_hover_text("Search syntax").clicked();
+                    let eye = if incognito {
+                        egui::RichText::new("👁").color(egui::Color32::from_rgb(200, 140, 255))
+                    } else {
+                        egui::RichText::new("👁")
+                    };
+                    toggle_incognito = ui.selectable_label(incognito, eye)
+                        .on_hover_text("Incognito search: queries are not saved or logged (Ctrl+Shift+N)")
+                        .clicked();
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.query)
+                            .id(search_box_id())
+                            .hint_text(if incognito {
+                                "🕶 Incognito search... (not saved or logged)"
+                            } else {
+                                "🔍 Search files... (Enter opens first result, Esc clears)"
+                            })
+                            .desired_width(f32::INFINITY)
+                            .font(egui::TextStyle::Body)
+                            .margin(egui::vec2(8.0, 6.0))
+                            .lock_focus(true),
+                    )
+                }).inner;
+                if show_syntax {
+                    self.modals.open(Modal::SearchSyntax);
+                }
+                if toggle_incognito {
+                    self.toggle_incognito();
+                }
+                if incognito {
+                    ui.label(egui::RichText::new("🕶 Incognito: searches are kept out of saved searches and logs")
+                        .size(12.0)
+                        .color(egui::Color32::from_rgb(200, 140, 255)));
+                }
                 
                 if search.changed() {
-                    self.do_search();
+                    self.history_recall.reset();
+                    self.browse_page = 0;
+                    self.last_error = None;
+                    // Each keystroke pushes the search back, so fast typing runs one search
+                    let debounce = Duration::from_millis(self.config.search_debounce_ms);
+                    match resolve_typed_search(&self.query, self.config.min_query_length, debounce) {
+                        TypedSearch::Now | TypedSearch::KeepTyping => self.do_search(),
+                        TypedSearch::After(pause) => self.search_due = Some(Instant::now() + pause),
+                    }
+                    self.update_suggestions();
                 }
+                if self.suggestions_open && !self.suggestions.is_empty() && search.has_focus() {
+                    self.render_suggestions(ctx, &search);
+                } else if self.query.is_empty() && !self.config.search_history.is_empty() && search.has_focus() {
+                    self.render_history(ctx, &search);
+                }
+                
+                // Keep typing in the search box unless a modal or another widget has focus
+                if self.modals.top().is_none() && ctx.memory(|m| m.focused().is_none()) {
+                    search.request_focus();
+                }
+                search_focused = search.has_focus();
                 
                 ui.add_space(4.0);
                 
                 // Show search stats and errors
                 ui.horizontal(|ui| {
-                    if !self.results.is_empty() {
+                    if let Some(total) = self.browse_total.filter(|&t| t > 0) {
+                        let first = self.browse_page * PATH_PAGE_SIZE;
+                        let last = (first + PATH_PAGE_SIZE).min(total);
+                        ui.label(
+                            egui::RichText::new(format!("📂 {}–{} of {} entries", first + 1, last, total))
+                                .color(egui::Color32::from_rgb(120, 200, 120))
+                                .size(12.0),
+                        );
+                        if ui.add_enabled(self.browse_page > 0, egui::Button::new("◀").small()).clicked() {
+                            self.browse_page -= 1;
+                            self.do_search();
+                        }
+                        if ui.add_enabled(last < total, egui::Button::new("▶").small()).clicked() {
+                            self.browse_page += 1;
+                            self.do_search();
+                        }
+                    } else if self.search_job.is_some() {
+                        ui.spinner();
+                        let status = if self.stream_replaces {
+                            "Searching…".to_string()
+                        } else {
+                            format!("Searching… {} so far", self.results.len())
+                        };
+                        ui.label(egui::RichText::new(status).size(12.0));
+                    } else if !self.results.is_empty() {
                         ui.label(
                             egui::RichText::new(format!(
                                 "✓ {} results in {:.1}ms",
@@ -789,86 +4267,399 @@ impl eframe::App for FlashFindApp {
                         );
                     }
                     
+                    if !self.results.is_empty() {
+                        self.render_size_total(ui, ctx);
+                    }
+                    
+                    // Browse pages stay in path order
+                    if !self.results.is_empty() && self.browse_total.is_none() {
+                        self.render_sort_selector(ui);
+                    }
+                    if !self.config.favorite_folders.is_empty() && self.browse_total.is_none() {
+                        self.render_favorites_toggle(ui);
+                    }
+                    
+                    let searched_names = !self.query.trim().is_empty()
+                        && self.search_job.is_none()
+                        && self.search_due.is_none()
+                        && self.search_hint.is_none()
+                        && self.query_error.is_none()
+                        && self.browse_total.is_none();
+                    if self.content_search.is_running() {
+                        ui.spinner();
+                        ui.label(
+                            egui::RichText::new(format!("Searching file contents… {} found", self.content_matches.len()))
+                                .size(12.0),
+                        );
+                        if ui.small_button("Stop").clicked() {
+                            self.content_search.cancel();
+                        }
+                    } else if let Some(summary) = self.content_summary {
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "📝 {} files contain \"{}\" ({} searched)",
+                                self.content_matches.len(),
+                                self.query.trim(),
+                                summary.files_searched
+                            ))
+                            .color(egui::Color32::from_rgb(255, 190, 120))
+                            .size(12.0),
+                        );
+                        if summary.timed_out {
+                            ui.label(
+                                egui::RichText::new(format!("⚠ Stopped after {}s", GREP_TIME_LIMIT.as_secs()))
+                                    .color(egui::Color32::from_rgb(255, 200, 100))
+                                    .size(12.0),
+                            );
+                        } else if summary.truncated {
+                            ui.label(
+                                egui::RichText::new("⚠ Too many matches, showing the first ones")
+                                    .color(egui::Color32::from_rgb(255, 200, 100))
+                                    .size(12.0),
+                            );
+                        }
+                    } else if self.results.is_empty()
+                        && searched_names
+                        && ui
+                            .button("📝 Search file contents")
+                            .on_hover_text("Look for the query inside indexed text files")
+                            .clicked()
+                    {
+                        self.start_content_search();
+                    }
+                    
+                    if self.fuzzy_fallback && !self.results.is_empty() {
+                        ui.label(
+                            egui::RichText::new("≈ No exact matches, showing similar names")
+                                .color(egui::Color32::from_rgb(200, 180, 255))
+                                .size(12.0),
+                        );
+                    }
+                    
+                    if self.results_timed_out {
+                        ui.label(
+                            egui::RichText::new("⏱ Showing partial results — query timed out")
+                                .color(egui::Color32::from_rgb(255, 200, 100))
+                                .size(12.0),
+                        );
+                    } else if self.results_truncated {
+                        ui.label(
+                            egui::RichText::new("⚠ Results truncated — refine your query")
+                                .color(egui::Color32::from_rgb(255, 200, 100))
+                                .size(12.0),
+                        );
+                    }
+                    
+                    if let Some(hint) = &self.search_hint {
+                        ui.label(egui::RichText::new(format!("💡 {}", hint)).weak().size(12.0));
+                    }
+                    
+                    if let Some(reason) = &self.query_error {
+                        ui.label(
+                            egui::RichText::new(format!("✖ {}", reason))
+                                .color(egui::Color32::from_rgb(255, 120, 120))
+                                .size(12.0),
+                        );
+                    }
+                    
                     if let Some(err) = &self.last_error {
                         ui.colored_label(egui::Color32::from_rgb(255, 120, 120), format!("⚠ {}", err));
                     }
                 });
             });
         
-        // Handle button actions after UI
-        if should_save {
-            self.handle_save();
+        // Handle button actions after UI
+        if should_save {
+            self.handle_save();
+        }
+        if should_reindex {
+            self.handle_reindex();
+        }
+        
+        // Settings window
+        if self.modals.is_open(Modal::Settings) {
+            let mut open = true;
+            egui::Window::new("⚙ Settings")
+                .open(&mut open)
+                .resizable(false)
+                .collapsible(false)
+                .fixed_size([600.0, 500.0])
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    self.render_settings(ui, ctx);
+                });
+            if !open {
+                self.close_modal(Modal::Settings);
+            }
+        }
+        
+        // Welcome window for first-time users
+        if self.modals.is_open(Modal::Welcome) {
+            let mut open = true;
+            egui::Window::new("👋 Welcome to FlashFind")
+                .open(&mut open)
+                .resizable(false)
+                .collapsible(false)
+                .fixed_size([520.0, 580.0])
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    render_welcome(ui);
+                });
+            if !open {
+                self.close_modal(Modal::Welcome);
+            }
+        }
+        
+        // Search syntax reference, generated from the parser's operator table
+        if self.modals.is_open(Modal::SearchSyntax) {
+            let mut open = true;
+            let mut example = None;
+            egui::Window::new("❓ Search syntax")
+                .open(&mut open)
+                .resizable(false)
+                .collapsible(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    example = render_search_syntax(ui);
+                });
+            if let Some(example) = example {
+                self.query = example.to_string();
+                self.browse_page = 0;
+                self.last_error = None;
+                self.do_search();
+                open = false;
+            }
+            if !open {
+                self.close_modal(Modal::SearchSyntax);
+            }
+        }
+        
+        // Match explanation for a single result
+        if self.modals.is_open(Modal::MatchInfo) {
+            let mut open = true;
+            if let Some((path, query)) = &self.match_info {
+                egui::Window::new("❓ Why this match?")
+                    .open(&mut open)
+                    .resizable(false)
+                    .collapsible(false)
+                    .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                    .show(ctx, |ui| {
+                        let aliases = self.aliases.get(path).map_or(&[][..], Vec::as_slice);
+                        render_match_info(ui, path, query, aliases);
+                    });
+            }
+            if !open || self.match_info.is_none() {
+                self.close_modal(Modal::MatchInfo);
+            }
         }
-        if should_reindex {
-            self.handle_reindex();
+        
+        // Batch rename over the current results
+        if self.modals.is_open(Modal::BatchRename) {
+            let mut open = true;
+            egui::Window::new("✏ Batch rename")
+                .open(&mut open)
+                .resizable(true)
+                .collapsible(false)
+                .default_width(560.0)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    self.render_batch_rename(ui);
+                });
+            if !open || self.batch_rename.is_none() {
+                self.batch_rename = None;
+                self.close_modal(Modal::BatchRename);
+            }
         }
         
-        // Settings window
-        let mut show_settings = self.show_settings;
-        if show_settings {
-            egui::Window::new("⚙ Settings")
-                .open(&mut show_settings)
-                .resizable(false)
+        // Space usage by folder for an indexed root
+        if self.modals.is_open(Modal::SpaceUsage) {
+            let mut open = true;
+            egui::Window::new("📂 Space usage")
+                .open(&mut open)
+                .resizable(true)
                 .collapsible(false)
-                .fixed_size([600.0, 500.0])
+                .default_width(620.0)
                 .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
                 .show(ctx, |ui| {
-                    self.render_settings(ui, ctx);
+                    self.render_space_usage(ui, ctx);
                 });
+            if !open {
+                if let Some(view) = self.space_usage.take() {
+                    view.job.cancel();
+                }
+                self.close_modal(Modal::SpaceUsage);
+            }
         }
-        self.show_settings = show_settings;
         
-        // Welcome window for first-time users
-        let mut show_welcome = self.show_welcome;
-        if show_welcome {
-            egui::Window::new("👋 Welcome to FlashFind")
-                .open(&mut show_welcome)
+        // Changes between a snapshot and the current index
+        if self.modals.is_open(Modal::SnapshotDiff) {
+            let mut open = true;
+            egui::Window::new("📸 Changes since snapshot")
+                .open(&mut open)
+                .resizable(true)
+                .collapsible(false)
+                .default_width(640.0)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    self.render_snapshot_diff(ui);
+                });
+            if !open || self.snapshot_diff.is_none() {
+                self.snapshot_diff = None;
+                self.close_modal(Modal::SnapshotDiff);
+            }
+        }
+        
+        // Confirm replacing a Desktop shortcut of the same name
+        if self.modals.is_open(Modal::ReplaceShortcut) {
+            let mut open = true;
+            let mut replace = false;
+            let mut cancel = false;
+            let name = self.pending_shortcut.as_ref().map(|s| shortcut_name(&s.name)).unwrap_or_default();
+            egui::Window::new("🔗 Replace shortcut?")
+                .open(&mut open)
                 .resizable(false)
                 .collapsible(false)
-                .fixed_size([520.0, 580.0])
                 .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
                 .show(ctx, |ui| {
-                    render_welcome(ui);
+                    ui.label(format!("A shortcut named \"{}\" is already on the Desktop.", name));
+                    ui.add_space(8.0);
+                    ui.horizontal(|ui| {
+                        replace = ui.button("Replace").clicked();
+                        cancel = ui.button("Cancel").clicked();
+                    });
                 });
-            
-            // If user closed welcome, mark first launch as false
-            if !show_welcome && self.show_welcome {
-                self.config.first_launch = false;
-                if let Err(e) = self.config.save() {
-                    warn!("Failed to save config after welcome: {}", e);
+            if replace {
+                if let Some(saved) = self.pending_shortcut.take() {
+                    self.create_search_shortcut(saved, true);
                 }
             }
+            if !open || replace || cancel || self.pending_shortcut.is_none() {
+                self.pending_shortcut = None;
+                self.close_modal(Modal::ReplaceShortcut);
+            }
         }
-        self.show_welcome = show_welcome;
         
         // Main results panel
         let results_clone = self.results.clone();
         let mut action_queue: Vec<(PathBuf, ResultAction)> = Vec::new();
+        let mut focused_row = None;
+        let mut meant = None;
         
         egui::CentralPanel::default().show(ctx, |ui| {
-            if results_clone.is_empty() && self.query.is_empty() {
-                render_empty_state(ui, total_files);
+            let parsed = parse_query(&self.query);
+            let nothing_searched = matches!(parsed, ParsedQuery::Empty | ParsedQuery::Hint(_));
+            let path_match = match &parsed {
+                ParsedQuery::FullPath(needle) => Some(needle.as_str()),
+                _ => None,
+            };
+            if self.duplicate_job.is_some() {
+                ui.centered_and_justified(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(egui::RichText::new("Looking for duplicate names…").weak());
+                    });
+                });
+            } else if let Some(report) = &self.duplicates {
+                match render_duplicates(ui, report, &mut self.duplicates_by_size, &mut action_queue) {
+                    Some(DuplicatesChange::Rescan) => self.find_duplicates(),
+                    Some(DuplicatesChange::Close) => self.duplicates = None,
+                    None => {}
+                }
+            } else if results_clone.is_empty() && (!self.content_matches.is_empty() || self.content_search.is_running()) {
+                render_content_matches(ui, &self.content_matches, &mut action_queue);
+            } else if results_clone.is_empty() && nothing_searched {
+                let card = self.weekly_card.as_ref().and_then(|week| render_weekly_card(ui, week));
+                match card {
+                    Some(WeeklyCardChange::Export) => {
+                        if let Some(week) = self.weekly_card.clone() {
+                            self.export_week(&week);
+                        }
+                    }
+                    Some(WeeklyCardChange::Dismiss) => self.weekly_card = None,
+                    None => {}
+                }
+                
+                // Kept current by the watcher, so reading it each frame is cheap
+                let recent = match parsed {
+                    ParsedQuery::Empty if self.config.recent_files > 0 => self.index.read().recent(self.config.recent_files),
+                    _ => Vec::new(),
+                };
+                if recent.is_empty() {
+                    render_empty_state(ui, total_files);
+                } else {
+                    ui.label(egui::RichText::new("🕒 Recently modified").size(15.0).strong());
+                    ui.add_space(4.0);
+                    render_results(
+                        ui,
+                        &recent,
+                        &self.roots,
+                        self.config.result_activation,
+                        &self.selection,
+                        None,
+                        &mut action_queue,
+                    );
+                }
+            } else if results_clone.is_empty() && self.query_error.is_some() {
+                ui.centered_and_justified(|ui| {
+                    ui.label(egui::RichText::new("Fix the query to see results").weak());
+                });
+            } else if results_clone.is_empty() && !self.did_you_mean.is_empty() && self.search_job.is_none() {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(60.0);
+                    ui.label(egui::RichText::new("No results found. Did you mean:").weak());
+                    ui.add_space(8.0);
+                    for name in &self.did_you_mean {
+                        if ui.link(egui::RichText::new(name).size(15.0)).clicked() {
+                            meant = Some(name.clone());
+                        }
+                    }
+                });
             } else if results_clone.is_empty() {
                 ui.centered_and_justified(|ui| {
                     ui.label(egui::RichText::new("No results found").weak());
                 });
             } else {
-                render_results(ui, &results_clone, &mut action_queue);
+                focused_row = render_results(
+                    ui,
+                    &results_clone,
+                    &self.roots,
+                    self.config.result_activation,
+                    &self.selection,
+                    path_match,
+                    &mut action_queue,
+                );
             }
         });
         
+        // Remember where focus ended up for next frame's shortcut routing
+        self.focus = if search_focused {
+            FocusTarget::Search
+        } else if let Some(row) = focused_row {
+            FocusTarget::Results(row)
+        } else {
+            FocusTarget::Other
+        };
+        
         // Process actions after UI rendering
+        let query = self.query.clone();
         for (path, action) in action_queue {
-            match action {
-                ResultAction::Open => self.open_file(&path),
-                ResultAction::OpenFolder => {
-                    if let Some(parent) = path.parent() {
-                        self.open_folder(parent);
-                    }
-                }
-                ResultAction::CopyPath => {},
-            }
+            self.handle_result_action(path, action, &query);
         }
+        if let Some(name) = meant {
+            self.set_query(ctx, name);
+        }
+        
+        self.show_results_windows(ctx);
+        self.poll_snapshot_job(ctx);
+        self.poll_export_job(ctx);
+        self.poll_token_job(ctx);
+        self.run_due_search(ctx);
+        self.poll_search_job(ctx);
+        self.poll_alias_job(ctx);
+        self.poll_content_search(ctx);
+        self.poll_duplicate_job(ctx);
+        self.collect_alerts(ctx);
+        self.show_alert_toasts(ctx);
         
         // Request repaint if indexing
         if is_indexing {
@@ -880,18 +4671,154 @@ impl eframe::App for FlashFindApp {
         info!("FlashFind shutting down");
         
         // Save index on exit
-        match save_index(&*self.index.read()) {
+        match self.saver.flush() {
             Ok(()) => info!("Index saved on exit"),
             Err(e) => error!("Failed to save index on exit: {}", e),
         }
+        if let Err(e) = self.activity.save() {
+            error!("Failed to save activity rollups on exit: {}", e);
+        }
     }
 }
 
 /// Actions that can be performed on results
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum ResultAction {
     Open,
+    /// Make the row the only selected one
+    Select,
+    /// Add the row to the selection or take it out
+    ToggleSelect,
     OpenFolder,
     CopyPath,
+    Explain,
+}
+
+/// A click on a result row, as seen in one frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RowClick {
+    /// The click landed on the filename link rather than the row around it
+    on_name: bool,
+    /// Second click of a double click
+    double: bool,
+    /// Ctrl (Cmd on macOS) was held
+    toggle: bool,
+}
+
+/// What a click on a result row does under the activation setting
+///
+/// A click never both changes the selection and opens: toggling clicks only
+/// toggle, and the second click of a double click only opens (double-click
+/// mode) or does nothing, since the first click already opened (single-click
+/// mode).
+fn resolve_row_click(activation: ResultActivation, click: RowClick) -> Option<ResultAction> {
+    match activation {
+        ResultActivation::SingleClick => {
+            if click.double {
+                None
+            } else if click.toggle {
+                Some(ResultAction::ToggleSelect)
+            } else if click.on_name {
+                Some(ResultAction::Open)
+            } else {
+                Some(ResultAction::Select)
+            }
+        }
+        ResultActivation::DoubleClick => match (click.toggle, click.double) {
+            (true, true) => None,
+            (true, false) => Some(ResultAction::ToggleSelect),
+            (false, true) => Some(ResultAction::Open),
+            (false, false) => Some(ResultAction::Select),
+        },
+    }
+}
+
+/// What the user asked of the weekly summary card
+enum WeeklyCardChange {
+    Export,
+    Dismiss,
+}
+
+/// Last week's summary above the empty state
+fn render_weekly_card(ui: &mut egui::Ui, week: &WeekSummary) -> Option<WeeklyCardChange> {
+    let mut change = None;
+    
+    egui::Frame::group(ui.style()).show(ui, |ui| {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("📅 Last week in your index").size(15.0).strong());
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("✖").on_hover_text("Dismiss until next week").clicked() {
+                    change = Some(WeeklyCardChange::Dismiss);
+                }
+                if ui.button("📝 Export as Markdown").clicked() {
+                    change = Some(WeeklyCardChange::Export);
+                }
+            });
+        });
+        ui.label(egui::RichText::new(week.title()).size(12.0).weak());
+        render_week_summary(ui, week);
+    });
+    ui.add_space(8.0);
+    
+    change
+}
+
+/// Totals, daily counts, busiest folders and biggest new files of a week
+fn render_week_summary(ui: &mut egui::Ui, week: &WeekSummary) {
+    if week.is_empty() {
+        ui.label(egui::RichText::new("No activity was recorded this week.").weak());
+        return;
+    }
+    
+    ui.horizontal(|ui| {
+        ui.label(egui::RichText::new(format!("{} files added", format_count(week.added as usize))).strong());
+        ui.label("·");
+        ui.label(egui::RichText::new(format!("{} removed", format_count(week.removed as usize))).strong());
+        if week.recorded_days() < 7 {
+            ui.label(egui::RichText::new(format!("({} of 7 days recorded)", week.recorded_days())).weak());
+        }
+    });
+    
+    egui::CollapsingHeader::new("By day").id_source(("week_days", week.start)).show(ui, |ui| {
+        egui::Grid::new(("week_days_grid", week.start)).num_columns(3).spacing([20.0, 4.0]).striped(true).show(ui, |ui| {
+            for (offset, day) in week.days.iter().enumerate() {
+                ui.label(week.day_label(offset));
+                match day {
+                    Some((added, removed)) => {
+                        ui.label(format!("+{}", added));
+                        ui.label(format!("−{}", removed));
+                    }
+                    None => {
+                        ui.label(egui::RichText::new("no data").weak());
+                        ui.label("");
+                    }
+                }
+                ui.end_row();
+            }
+        });
+    });
+    if !week.folders.is_empty() {
+        egui::CollapsingHeader::new("Busiest folders").id_source(("week_folders", week.start)).show(ui, |ui| {
+            egui::Grid::new(("week_folders_grid", week.start)).num_columns(2).spacing([20.0, 4.0]).striped(true).show(ui, |ui| {
+                for (folder, files) in &week.folders {
+                    ui.label(folder.display().to_string());
+                    ui.label(format!("{} files", format_count(*files as usize)));
+                    ui.end_row();
+                }
+            });
+        });
+    }
+    if !week.biggest.is_empty() {
+        egui::CollapsingHeader::new("Biggest new files").id_source(("week_biggest", week.start)).show(ui, |ui| {
+            egui::Grid::new(("week_biggest_grid", week.start)).num_columns(2).spacing([20.0, 4.0]).striped(true).show(ui, |ui| {
+                for (path, size) in &week.biggest {
+                    ui.label(path.display().to_string());
+                    ui.label(format_size(*size));
+                    ui.end_row();
+                }
+            });
+        });
+    }
 }
 
 /// Render the header bar
@@ -913,32 +4840,240 @@ fn render_empty_state(ui: &mut egui::Ui, total_files: usize) {
     });
 }
 
+/// A result row's path line, with the part matching a `path:` query highlighted
+fn path_line(ui: &egui::Ui, path_str: &str, path_match: Option<&str>) -> egui::text::LayoutJob {
+    let format = |color| egui::TextFormat { font_id: egui::FontId::proportional(11.5), color, ..Default::default() };
+    let weak = format(ui.visuals().weak_text_color());
+    let mut job = egui::text::LayoutJob::default();
+    
+    // `path:` queries match lowercased paths with `\` separators; offsets only
+    // carry over when lowercasing kept every byte length
+    let key = path_str.to_lowercase().replace('/', "\\");
+    let found = path_match
+        .filter(|_| key.len() == path_str.len())
+        .and_then(|needle| key.find(needle).map(|start| (start, start + needle.len())));
+    
+    match found {
+        Some((start, end)) => {
+            job.append(&path_str[..start], 0.0, weak.clone());
+            job.append(&path_str[start..end], 0.0, format(ui.visuals().strong_text_color()));
+            job.append(&path_str[end..], 0.0, weak);
+        }
+        None => job.append(path_str, 0.0, weak),
+    }
+    job
+}
+
+/// A result's filename in `color`, with the parts that matched the query in
+/// the accent color
+fn name_line(name: &str, matched: &[Range<usize>], color: egui::Color32) -> egui::text::LayoutJob {
+    let format = |color| egui::TextFormat { font_id: egui::FontId::proportional(14.0), color, ..Default::default() };
+    let mut job = egui::text::LayoutJob::default();
+    let mut shown = 0;
+    
+    for range in matched.iter().filter(|range| range.end <= name.len()) {
+        job.append(&name[shown..range.start], 0.0, format(color));
+        job.append(&name[range.clone()], 0.0, format(egui::Color32::from_rgb(100, 200, 255)));
+        shown = range.end;
+    }
+    job.append(&name[shown..], 0.0, format(color));
+    job
+}
+
+/// Render lines found by a content search, set apart from filename results
+/// by their icon and the matching line under each name
+fn render_content_matches(
+    ui: &mut egui::Ui,
+    matches: &[ContentMatch],
+    action_queue: &mut Vec<(PathBuf, ResultAction)>,
+) {
+    let row_height = 52.0;
+    let line_color = egui::Color32::from_rgb(255, 190, 120);
+    
+    egui::ScrollArea::vertical().show_rows(ui, row_height, matches.len(), |ui, range| {
+        ui.spacing_mut().item_spacing.y = 0.0;
+        
+        for i in range {
+            let found = &matches[i];
+            let path = &found.path;
+            let filename = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            
+            ui.push_id(format!("content_{}", i), |ui| {
+                let row_rect = egui::Rect::from_min_size(ui.cursor().min, egui::vec2(ui.available_width(), row_height));
+                let row = ui.interact(row_rect, ui.id().with("row"), egui::Sense::click());
+                let bg_color = if i % 2 == 0 { ui.visuals().faint_bg_color } else { egui::Color32::TRANSPARENT };
+                
+                egui::Frame::none()
+                    .fill(bg_color)
+                    .inner_margin(egui::Margin::symmetric(12.0, 8.0))
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.set_height(row_height - 16.0);
+                            ui.label(egui::RichText::new("📝").size(18.0));
+                            ui.add_space(4.0);
+                            
+                            ui.vertical(|ui| {
+                                ui.spacing_mut().item_spacing.y = 2.0;
+                                ui.horizontal(|ui| {
+                                    ui.add(egui::Label::new(egui::RichText::new(&filename).size(14.0)).selectable(false));
+                                    ui.label(egui::RichText::new(format!("line {}", found.line_number)).size(10.0).weak());
+                                });
+                                let mut line = name_line(&found.excerpt, &found.matched, line_color);
+                                for section in &mut line.sections {
+                                    section.format.font_id = egui::FontId::monospace(11.0);
+                                }
+                                ui.add(egui::Label::new(line).selectable(false).truncate(true));
+                            });
+                        });
+                    });
+                
+                if row.double_clicked() {
+                    action_queue.push((path.clone(), ResultAction::Open));
+                }
+                row.on_hover_text(path.display().to_string()).context_menu(|ui| {
+                    if ui.button("📂 Open Folder").clicked() {
+                        action_queue.push((path.clone(), ResultAction::OpenFolder));
+                        ui.close_menu();
+                    }
+                });
+            });
+        }
+    });
+}
+
+/// What the user asked of the duplicates view
+enum DuplicatesChange {
+    /// Scan again, after switching the size check
+    Rescan,
+    Close,
+}
+
+/// List groups of files that share a name, each row showing the folder
+/// the file is in
+fn render_duplicates(
+    ui: &mut egui::Ui,
+    report: &DuplicateReport,
+    by_size: &mut bool,
+    action_queue: &mut Vec<(PathBuf, ResultAction)>,
+) -> Option<DuplicatesChange> {
+    let mut change = None;
+    
+    ui.horizontal(|ui| {
+        ui.label(egui::RichText::new("🧬 Duplicate names").size(15.0).strong());
+        let shown = if report.groups.len() < report.total_groups {
+            format!("{} groups, largest {} shown", report.total_groups, report.groups.len())
+        } else {
+            format!("{} groups", report.total_groups)
+        };
+        ui.label(egui::RichText::new(shown).weak().size(12.0));
+        if ui.checkbox(by_size, "Same size only")
+            .on_hover_text("Group files by size as well as name; files of unknown size are left out")
+            .changed()
+        {
+            change = Some(DuplicatesChange::Rescan);
+        }
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if ui.button("✖ Close").clicked() {
+                change = Some(DuplicatesChange::Close);
+            }
+        });
+    });
+    ui.separator();
+    
+    if report.groups.is_empty() {
+        ui.centered_and_justified(|ui| {
+            ui.label(egui::RichText::new("No files share a name").weak());
+        });
+        return change;
+    }
+    
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for (i, group) in report.groups.iter().enumerate() {
+            let mut title = format!("{}  ·  {} files", group.name, group.count);
+            if let Some(size) = group.size {
+                title.push_str(&format!("  ·  {} each", format_size(size)));
+            }
+            // Hundreds of `index.js` would bury every other group
+            egui::CollapsingHeader::new(title)
+                .id_source(("duplicates", i))
+                .default_open(group.count <= COLLAPSED_GROUP_SIZE)
+                .show(ui, |ui| {
+                    for entry in &group.entries {
+                        let folder = entry.path.parent().map(|p| p.display().to_string()).unwrap_or_default();
+                        ui.horizontal(|ui| {
+                            if ui.link(&folder).on_hover_text(entry.path.display().to_string()).clicked() {
+                                action_queue.push((entry.path.clone(), ResultAction::Open));
+                            }
+                            if ui.small_button("📂").on_hover_text("Open the folder").clicked() {
+                                action_queue.push((entry.path.clone(), ResultAction::OpenFolder));
+                            }
+                        });
+                    }
+                    if group.count > group.entries.len() {
+                        ui.label(egui::RichText::new(format!("… and {} more", group.count - group.entries.len())).weak());
+                    }
+                });
+        }
+    });
+    change
+}
+
 /// Render search results with virtual scrolling
-fn render_results(ui: &mut egui::Ui, results: &[PathBuf], action_queue: &mut Vec<(PathBuf, ResultAction)>) {
+/// Returns the row that holds keyboard focus, if any
+fn render_results(
+    ui: &mut egui::Ui,
+    results: &[SearchResult],
+    roots: &RootMonitor,
+    activation: ResultActivation,
+    selection: &[PathBuf],
+    path_match: Option<&str>,
+    action_queue: &mut Vec<(PathBuf, ResultAction)>,
+) -> Option<usize> {
     let row_height = 52.0;
+    let mut focused = None;
+    let utc_offset = local_utc_offset();
     
     egui::ScrollArea::vertical().show_rows(ui, row_height, results.len(), |ui, range| {
         ui.spacing_mut().item_spacing.y = 0.0;
         
         for i in range {
-            let path = &results[i];
-            let filename = path
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
+            let result = &results[i];
+            let path = &result.path;
+            let filename = &result.file_name;
             let path_str = path.display().to_string();
+            // Stored by the indexer, so drawing a row never reads the disk
+            let details: Vec<String> = result
+                .size
+                .map(format_size)
+                .into_iter()
+                .chain(result.modified.map(|modified| format_date(modified, utc_offset)))
+                .collect();
+            let offline = !roots.is_available(path);
             
             // Use unique ID for each row based on full path and index
             ui.push_id(format!("result_{}", i), |ui| {
-                // Highlight alternate rows
-                let bg_color = if i % 2 == 0 {
+                // The whole row is a click target, registered before its contents so
+                // the filename link and menu button stay on top of it
+                let row_rect = egui::Rect::from_min_size(ui.cursor().min, egui::vec2(ui.available_width(), row_height));
+                let sense = egui::Sense {
+                    click: true,
+                    drag: false,
+                    focusable: activation == ResultActivation::DoubleClick,
+                };
+                let row = ui.interact(row_rect, ui.id().with("row"), sense);
+                let mut name_clicked = false;
+                let mut name_double_clicked = false;
+                
+                // Highlight selected and alternate rows
+                let bg_color = if selection.contains(path) {
+                    ui.visuals().selection.bg_fill.linear_multiply(0.4)
+                } else if i % 2 == 0 {
                     ui.visuals().faint_bg_color
                 } else {
                     egui::Color32::TRANSPARENT
                 };
                 
-                let response = egui::Frame::none()
+                egui::Frame::none()
                     .fill(bg_color)
                     .inner_margin(egui::Margin::symmetric(12.0, 8.0))
                     .show(ui, |ui| {
@@ -946,17 +5081,38 @@ fn render_results(ui: &mut egui::Ui, results: &[PathBuf], action_queue: &mut Vec
                             ui.set_height(row_height - 16.0);
                             
                             // Icon
-                            ui.label(egui::RichText::new(get_file_icon(path)).size(18.0));
+                            let icon = if result.is_dir { "📁" } else { get_file_icon(path) };
+                            ui.label(egui::RichText::new(icon).size(18.0));
                             ui.add_space(4.0);
                             
                             // Filename and path
                             ui.vertical(|ui| {
                                 ui.spacing_mut().item_spacing.y = 2.0;
-                                let link = ui.link(egui::RichText::new(&filename).size(14.0));
-                                if link.clicked() {
-                                    action_queue.push((path.clone(), ResultAction::Open));
+                                if offline {
+                                    // Greyed out until the drive or share is reachable again
+                                    ui.horizontal(|ui| {
+                                        ui.label(name_line(filename, &result.matched, ui.visuals().weak_text_color()));
+                                        ui.label(
+                                            egui::RichText::new("offline")
+                                                .size(10.0)
+                                                .color(egui::Color32::from_rgb(255, 150, 100)),
+                                        );
+                                    });
+                                } else if activation == ResultActivation::DoubleClick {
+                                    // Plain text, so clicks reach the row
+                                    ui.add(egui::Label::new(name_line(filename, &result.matched, ui.visuals().text_color())).selectable(false));
+                                } else {
+                                    let link = ui.link(name_line(filename, &result.matched, ui.visuals().hyperlink_color));
+                                    name_clicked = link.clicked();
+                                    name_double_clicked = link.double_clicked();
+                                    if link.gained_focus() {
+                                        action_queue.push((path.clone(), ResultAction::Select));
+                                    }
+                                    if link.has_focus() {
+                                        focused = Some(i);
+                                    }
                                 }
-                                ui.label(egui::RichText::new(&path_str).weak().size(11.5));
+                                ui.add(egui::Label::new(path_line(ui, &path_str, path_match)).selectable(false));
                             });
                             
                             // Spacer and menu
@@ -971,13 +5127,40 @@ fn render_results(ui: &mut egui::Ui, results: &[PathBuf], action_queue: &mut Vec
                                         action_queue.push((path.clone(), ResultAction::CopyPath));
                                         ui.close_menu();
                                     }
+                                    if ui.button("❓ Why this match?").clicked() {
+                                        action_queue.push((path.clone(), ResultAction::Explain));
+                                        ui.close_menu();
+                                    }
                                 });
+                                if !details.is_empty() {
+                                    ui.label(egui::RichText::new(details.join("  ·  ")).size(11.0).weak());
+                                }
                             });
                         });
-                    }).response;
+                    });
+                
+                if name_clicked || row.clicked() {
+                    let click = RowClick {
+                        on_name: name_clicked,
+                        double: row.double_clicked() || name_double_clicked,
+                        toggle: ui.input(|i| i.modifiers.command),
+                    };
+                    // Offline rows cannot be opened, only selected
+                    let action = resolve_row_click(activation, click)
+                        .filter(|action| !(offline && *action == ResultAction::Open));
+                    if let Some(action) = action {
+                        action_queue.push((path.clone(), action));
+                    }
+                }
+                if row.gained_focus() {
+                    action_queue.push((path.clone(), ResultAction::Select));
+                }
+                if row.has_focus() {
+                    focused = Some(i);
+                }
                 
                 // Context menu with unique ID
-                response.context_menu(|ui| {
+                row.context_menu(|ui| {
                     if ui.button("📂 Open Folder").clicked() {
                         action_queue.push((path.clone(), ResultAction::OpenFolder));
                         ui.close_menu();
@@ -987,14 +5170,103 @@ fn render_results(ui: &mut egui::Ui, results: &[PathBuf], action_queue: &mut Vec
                         action_queue.push((path.clone(), ResultAction::CopyPath));
                         ui.close_menu();
                     }
+                    if ui.button("❓ Why This Match?").clicked() {
+                        action_queue.push((path.clone(), ResultAction::Explain));
+                        ui.close_menu();
+                    }
                 });
             });
         }
     });
+    
+    focused
+}
+
+/// Render the query operator reference; returns the example the user clicked
+fn render_search_syntax(ui: &mut egui::Ui) -> Option<&'static str> {
+    let mut clicked = None;
+    
+    egui::Grid::new("search_syntax").num_columns(5).spacing([16.0, 8.0]).striped(true).show(ui, |ui| {
+        ui.label(egui::RichText::new("Operator").strong());
+        ui.label(egui::RichText::new("Syntax").strong());
+        ui.label(egui::RichText::new("Finds").strong());
+        ui.label(egui::RichText::new("Example").strong());
+        ui.label(egui::RichText::new("Since").strong());
+        ui.end_row();
+        
+        for op in QUERY_OPERATORS {
+            ui.label(op.name);
+            ui.label(egui::RichText::new(op.syntax).monospace());
+            ui.label(op.description);
+            if ui.link(egui::RichText::new(op.example).monospace())
+                .on_hover_text(format!("Search for {}", op.example))
+                .clicked()
+            {
+                clicked = Some(op.example);
+            }
+            ui.label(egui::RichText::new(format!("v{}", op.since)).weak());
+            ui.end_row();
+        }
+    });
+    
+    ui.add_space(6.0);
+    ui.label(egui::RichText::new("Click an example to try it.").weak().small());
+    clicked
+}
+
+/// Render the rule that matched `path` for `query` (also handy in bug reports)
+fn render_match_info(ui: &mut egui::Ui, path: &Path, query: &str, aliases: &[PathBuf]) {
+    let filename = path.file_name().unwrap_or_default().to_string_lossy();
+    ui.label(egui::RichText::new(filename).size(14.0).strong());
+    ui.label(egui::RichText::new(path.display().to_string()).weak().size(11.5));
+    ui.add_space(8.0);
+    
+    egui::Grid::new("match_info").num_columns(2).spacing([12.0, 6.0]).show(ui, |ui| {
+        ui.label("Query:");
+        ui.label(egui::RichText::new(query).monospace());
+        ui.end_row();
+        
+        ui.label("Operator:");
+        ui.label(query_operator(query).map_or("None", |op| op.name));
+        ui.end_row();
+        
+        ui.label("Matched by:");
+        match explain_match(query, path) {
+            Some(reason) => ui.label(reason.to_string()),
+            None => ui.label(egui::RichText::new("Not a match for this query").weak()),
+        };
+        ui.end_row();
+    });
+    
+    if !aliases.is_empty() {
+        ui.add_space(8.0);
+        ui.label(egui::RichText::new("Also indexed as (same file):").strong());
+        for alias in aliases {
+            ui.label(egui::RichText::new(alias.display().to_string()).weak().size(11.5));
+        }
+    }
+}
+
+/// Move a renamed file to its new path in the index (see
+/// `ShardedIndex::rename`); a file the index did not hold is added
+fn rename_in_index(index: &ShardedIndex, from: &Path, to: &Path) {
+    match index.rename(from, to.to_path_buf()) {
+        Ok(true) => {}
+        Ok(false) => {
+            if let Err(e) = index.insert_with_meta(to.to_path_buf(), EntryMeta::read(to)) {
+                warn!("Failed to index renamed file {}: {}", redact(to), e);
+            }
+        }
+        Err(e) => warn!("Failed to rename {} in index: {}", redact(to), e),
+    }
 }
 
 /// Get icon for file type
 fn get_file_icon(path: &Path) -> &'static str {
+    if entry_kind(path) == EntryKind::ArchiveMember {
+        return "🗜";
+    }
+    
     let ext = path
         .extension()
         .and_then(|e| e.to_str())
@@ -1017,6 +5289,19 @@ fn get_file_icon(path: &Path) -> &'static str {
     }
 }
 
+/// A count with thousands separated by commas, as in "12,431"
+fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut text = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            text.push(',');
+        }
+        text.push(digit);
+    }
+    text
+}
+
 /// Setup UI styling
 fn setup_ui_style(ctx: &egui::Context, theme: Theme) {
     let mut visuals = match theme {
@@ -1062,7 +5347,7 @@ fn render_welcome(ui: &mut egui::Ui) {
             ui.add_space(12.0);
             ui.label(egui::RichText::new("FlashFind").size(32.0).strong());
             ui.add_space(8.0);
-            ui.label(egui::RichText::new("Lightning-Fast File Search for Windows")
+            ui.label(egui::RichText::new("Lightning-Fast File Search")
                 .size(14.0)
                 .color(egui::Color32::from_rgb(150, 150, 150)));
             
@@ -1163,49 +5448,141 @@ fn render_welcome(ui: &mut egui::Ui) {
     });
 }
 
-/// Initialize logging system
-fn init_logging() {
-    use tracing::Level;
-    
-    let log_path = match crate::persistence::get_log_path() {
-        Ok(path) => path,
-        Err(_) => {
-            // Fallback: only show errors and warnings
-            eprintln!("Failed to get log path");
-            let _ = tracing_subscriber::fmt()
-                .with_max_level(Level::WARN)
-                .try_init();
-            return;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_group_thousands() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(12_431), "12,431");
+        assert_eq!(format_count(1_000_000), "1,000,000");
+    }
+
+    #[test]
+    fn test_escape_closes_topmost_modal_first() {
+        let mut modals = ModalStack::default();
+        modals.open(Modal::Welcome);
+        modals.open(Modal::Settings);
+
+        assert_eq!(
+            resolve_shortcut(egui::Key::Escape, modals.top(), FocusTarget::Search, SearchBoxState::Searched),
+            Some(ShortcutAction::CloseModal(Modal::Settings))
+        );
+        modals.close(Modal::Settings);
+        assert_eq!(
+            resolve_shortcut(egui::Key::Escape, modals.top(), FocusTarget::Search, SearchBoxState::Searched),
+            Some(ShortcutAction::CloseModal(Modal::Welcome))
+        );
+    }
+
+    #[test]
+    fn test_enter_ignored_while_modal_open() {
+        for focus in [FocusTarget::Search, FocusTarget::Results(3), FocusTarget::Other] {
+            assert_eq!(resolve_shortcut(egui::Key::Enter, Some(Modal::Settings), focus, SearchBoxState::Searched), None);
         }
-    };
-    
-    let file_appender = tracing_appender::rolling::daily(
-        log_path.parent().unwrap_or(Path::new(".")),
-        "flashfind.log",
-    );
-    
-    let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
-    
-    // In debug builds, all logs go to file, only warnings/errors to console
-    // In release builds, all logs go to file only (no console output)
-    #[cfg(debug_assertions)]
-    {
-        let _ = tracing_subscriber::fmt()
-            .with_writer(non_blocking)
-            .with_max_level(Level::DEBUG)
-            .try_init();
+    }
+
+    #[test]
+    fn test_shortcuts_follow_search_and_results_focus() {
+        assert_eq!(
+            resolve_shortcut(egui::Key::Enter, None, FocusTarget::Search, SearchBoxState::Searched),
+            Some(ShortcutAction::OpenResult(0))
+        );
+        assert_eq!(
+            resolve_shortcut(egui::Key::Enter, None, FocusTarget::Results(4), SearchBoxState::Searched),
+            Some(ShortcutAction::OpenResult(4))
+        );
+        assert_eq!(
+            resolve_shortcut(egui::Key::Escape, None, FocusTarget::Search, SearchBoxState::Searched),
+            Some(ShortcutAction::ClearSearch)
+        );
+        assert_eq!(resolve_shortcut(egui::Key::Escape, None, FocusTarget::Other, SearchBoxState::Searched), None);
+        assert_eq!(resolve_shortcut(egui::Key::Enter, None, FocusTarget::Other, SearchBoxState::Searched), None);
+    }
+
+    #[test]
+    fn test_completion_dropdown_takes_keys_before_search_box() {
+        // Esc closes the dropdown but keeps the query; a second Esc clears it
+        assert_eq!(
+            resolve_shortcut(egui::Key::Escape, None, FocusTarget::Search, SearchBoxState::Suggesting),
+            Some(ShortcutAction::CloseSuggestions)
+        );
+        assert_eq!(
+            resolve_shortcut(egui::Key::Escape, Some(Modal::Settings), FocusTarget::Search, SearchBoxState::Suggesting),
+            Some(ShortcutAction::CloseModal(Modal::Settings))
+        );
+        assert_eq!(
+            resolve_shortcut(egui::Key::Escape, None, FocusTarget::Results(2), SearchBoxState::Suggesting),
+            Some(ShortcutAction::ClearSearch)
+        );
         
-        info!("Debug mode: Full logging to file, warnings to console");
+        // Enter searches an accepted completion before opening anything
+        assert_eq!(
+            resolve_shortcut(egui::Key::Enter, None, FocusTarget::Search, SearchBoxState::Unsearched),
+            Some(ShortcutAction::RunSearch)
+        );
+        assert_eq!(
+            resolve_shortcut(egui::Key::Enter, None, FocusTarget::Search, SearchBoxState::Suggesting),
+            Some(ShortcutAction::OpenResult(0))
+        );
     }
-    
-    #[cfg(not(debug_assertions))]
-    {
-        let _ = tracing_subscriber::fmt()
-            .with_writer(non_blocking)
-            .with_max_level(Level::INFO)
-            .try_init();
+
+    #[test]
+    fn test_short_queries_wait_for_more_typing() {
+        let pause = Duration::from_millis(100);
+        assert_eq!(resolve_typed_search("a", 2, pause), TypedSearch::KeepTyping);
+        assert_eq!(resolve_typed_search("  é ", 2, Duration::ZERO), TypedSearch::KeepTyping);
+        assert_eq!(resolve_typed_search("ab", 2, pause), TypedSearch::After(pause));
+        assert_eq!(resolve_typed_search("ab", 2, Duration::ZERO), TypedSearch::Now);
+        assert_eq!(resolve_typed_search("a", 1, pause), TypedSearch::After(pause));
+        
+        // Extensions, browse-all and other operators search at any length
+        for query in [".c", "*", "^a", "", "."] {
+            assert_ne!(resolve_typed_search(query, 3, pause), TypedSearch::KeepTyping, "{}", query);
+        }
+    }
+
+    fn click(on_name: bool, double: bool, toggle: bool) -> RowClick {
+        RowClick { on_name, double, toggle }
+    }
+
+    #[test]
+    fn test_single_click_mode_opens_from_the_name() {
+        let mode = ResultActivation::SingleClick;
+        assert_eq!(resolve_row_click(mode, click(true, false, false)), Some(ResultAction::Open));
+        assert_eq!(resolve_row_click(mode, click(false, false, false)), Some(ResultAction::Select));
+        
+        // The first click of a double click already opened the file
+        assert_eq!(resolve_row_click(mode, click(true, true, false)), None);
+        assert_eq!(resolve_row_click(mode, click(false, true, false)), None);
+        
+        // Ctrl+click only toggles the selection, even on the name
+        assert_eq!(resolve_row_click(mode, click(true, false, true)), Some(ResultAction::ToggleSelect));
+        assert_eq!(resolve_row_click(mode, click(false, false, true)), Some(ResultAction::ToggleSelect));
+        assert_eq!(resolve_row_click(mode, click(true, true, true)), None);
+    }
+
+    #[test]
+    fn test_double_click_mode_selects_then_opens() {
+        let mode = ResultActivation::DoubleClick;
+        for on_name in [false, true] {
+            assert_eq!(resolve_row_click(mode, click(on_name, false, false)), Some(ResultAction::Select));
+            assert_eq!(resolve_row_click(mode, click(on_name, true, false)), Some(ResultAction::Open));
+            assert_eq!(resolve_row_click(mode, click(on_name, false, true)), Some(ResultAction::ToggleSelect));
+            // A Ctrl+double click toggled on its first click and must not open
+            assert_eq!(resolve_row_click(mode, click(on_name, true, true)), None);
+        }
+    }
+
+    #[test]
+    fn test_reopening_modal_moves_it_to_top() {
+        let mut modals = ModalStack::default();
+        modals.open(Modal::Settings);
+        modals.open(Modal::Welcome);
+        modals.open(Modal::Settings);
+        assert_eq!(modals.top(), Some(Modal::Settings));
+        assert!(modals.is_open(Modal::Welcome));
     }
-    
-    // Keep the file appender alive
-    std::mem::forget(_guard);
 }
@@ -0,0 +1,237 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::error::{FlashFindError, Result};
+use crate::paths::{path_key, to_long_path};
+use crate::redact::redact;
+
+/// Separates an archive from one of its members in a virtual path
+/// (`C:\Downloads\photos.zip::IMG_001.jpg`)
+pub const MEMBER_SEPARATOR: &str = "::";
+
+/// Default size limit for archives whose members are listed
+pub const DEFAULT_MAX_ARCHIVE_MB: u64 = 100;
+
+/// Folder under the temp dir that members are extracted into for opening
+const EXTRACT_DIR: &str = "flashfind_extract";
+
+/// What an index entry refers to, derived from the form of its path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    /// A regular file on disk
+    File,
+    /// A file inside a zip archive, addressed by a virtual path
+    ArchiveMember,
+}
+
+/// Classify an index entry
+pub fn entry_kind(path: &Path) -> EntryKind {
+    if split_member(path).is_some() {
+        EntryKind::ArchiveMember
+    } else {
+        EntryKind::File
+    }
+}
+
+/// Check for a real `.zip` file (not a member of one)
+pub fn is_archive(path: &Path) -> bool {
+    split_member(path).is_none()
+        && path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("zip"))
+}
+
+/// Virtual path of `member` inside `archive`
+pub fn member_path(archive: &Path, member: &str) -> PathBuf {
+    PathBuf::from(format!("{}{}{}", archive.display(), MEMBER_SEPARATOR, member))
+}
+
+/// Split a virtual path into its archive and member name
+pub fn split_member(path: &Path) -> Option<(PathBuf, String)> {
+    let path_str = path.to_string_lossy();
+    // ASCII lowercasing keeps byte offsets valid for the original string
+    let marker = format!(".zip{}", MEMBER_SEPARATOR);
+    let at = path_str.to_ascii_lowercase().find(&marker)?;
+    let (archive, rest) = path_str.split_at(at + ".zip".len());
+    let member = &rest[MEMBER_SEPARATOR.len()..];
+
+    if member.is_empty() {
+        return None;
+    }
+    Some((PathBuf::from(archive), member.to_string()))
+}
+
+/// Check whether `path` is a member of `archive`
+pub fn is_member_of(path: &Path, archive: &Path) -> bool {
+    split_member(path).is_some_and(|(owner, _)| path_key(&owner) == path_key(archive))
+}
+
+/// The file on disk an entry lives in: the archive for members, else the path itself
+pub fn backing_file(path: &Path) -> PathBuf {
+    match split_member(path) {
+        Some((archive, _)) => archive,
+        None => path.to_path_buf(),
+    }
+}
+
+/// List the file members of `archive` as virtual paths
+///
+/// Only the central directory is read; nothing is extracted. Archives larger
+/// than `max_bytes` yield no members. Unreadable archives are an error so the
+/// scanner can report them.
+pub fn list_members(archive: &Path, max_bytes: u64) -> Result<Vec<PathBuf>> {
+    let fs_path = to_long_path(archive);
+    let size = fs::metadata(&fs_path)
+        .map_err(|e| corrupt(archive, &e.to_string()))?
+        .len();
+    if size > max_bytes {
+        debug!("Archive over size limit, members not listed: {}", redact(archive));
+        return Ok(Vec::new());
+    }
+
+    let file = File::open(&fs_path).map_err(|e| corrupt(archive, &e.to_string()))?;
+    let zip = zip::ZipArchive::new(file).map_err(|e| corrupt(archive, &e.to_string()))?;
+
+    Ok(zip
+        .file_names()
+        .filter(|name| !name.ends_with('/'))
+        .map(|name| member_path(archive, name))
+        .collect())
+}
+
+/// Extract an archive member to a temp folder and return the extracted file
+///
+/// Only the member's filename is kept, so names with `..` components cannot
+/// escape the temp folder. Extracted files are left for the OS to clean up.
+pub fn extract_member(path: &Path) -> Result<PathBuf> {
+    let (archive, member) = split_member(path)
+        .ok_or_else(|| FlashFindError::InvalidPath(format!("{} is not an archive member", path.display())))?;
+    let filename = Path::new(&member)
+        .file_name()
+        .ok_or_else(|| corrupt(&archive, "member has no file name"))?
+        .to_owned();
+
+    let file = File::open(to_long_path(&archive)).map_err(|e| FlashFindError::FileReadError {
+        path: archive.display().to_string(),
+        source: e,
+    })?;
+    let mut zip = zip::ZipArchive::new(file).map_err(|e| corrupt(&archive, &e.to_string()))?;
+    let mut entry = zip.by_name(&member).map_err(|e| corrupt(&archive, &e.to_string()))?;
+
+    let mut hasher = DefaultHasher::new();
+    path_key(path).hash(&mut hasher);
+    let dir = std::env::temp_dir()
+        .join(EXTRACT_DIR)
+        .join(format!("{:016x}", hasher.finish()));
+    fs::create_dir_all(&dir).map_err(|e| FlashFindError::DirectoryCreationError {
+        path: dir.display().to_string(),
+        source: e,
+    })?;
+
+    let target = dir.join(filename);
+    let write_error = |e: io::Error| FlashFindError::FileWriteError {
+        path: target.display().to_string(),
+        source: e,
+    };
+    let mut out = File::create(&target).map_err(write_error)?;
+    io::copy(&mut entry, &mut out).map_err(write_error)?;
+
+    debug!("Extracted {} for opening", redact(path));
+    Ok(target)
+}
+
+fn corrupt(archive: &Path, reason: &str) -> FlashFindError {
+    FlashFindError::CorruptArchive {
+        path: archive.display().to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    /// Write a small fixture zip with the given members into `dir`
+    fn fixture_zip(dir: &Path, name: &str, members: &[(&str, &[u8])]) -> PathBuf {
+        let path = dir.join(name);
+
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for (member, data) in members {
+            if member.ends_with('/') {
+                zip.add_directory(*member, options).unwrap();
+            } else {
+                zip.start_file(*member, options).unwrap();
+                zip.write_all(data).unwrap();
+            }
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn test_lists_members_as_virtual_paths() {
+        let dir = TestDir::new("archive_list");
+        let archive = fixture_zip(&dir, "photos.zip", &[("IMG_001.jpg", b"a"), ("2019/", b""), ("2019/IMG_002.jpg", b"b")]);
+
+        let members = list_members(&archive, u64::MAX).unwrap();
+        assert_eq!(
+            members,
+            vec![member_path(&archive, "IMG_001.jpg"), member_path(&archive, "2019/IMG_002.jpg")]
+        );
+        assert!(members.iter().all(|m| entry_kind(m) == EntryKind::ArchiveMember));
+        assert!(members.iter().all(|m| is_member_of(m, &archive)));
+
+        // Over the size limit: skipped, not an error
+        assert!(list_members(&archive, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_split_member_round_trip() {
+        let virtual_path = PathBuf::from("C:\\Downloads\\Photos.ZIP::IMG_001.jpg");
+        assert_eq!(
+            split_member(&virtual_path),
+            Some((PathBuf::from("C:\\Downloads\\Photos.ZIP"), "IMG_001.jpg".to_string()))
+        );
+        assert_eq!(backing_file(&virtual_path), PathBuf::from("C:\\Downloads\\Photos.ZIP"));
+        assert_eq!(entry_kind(Path::new("C:\\Downloads\\photos.zip")), EntryKind::File);
+        assert!(is_archive(Path::new("C:\\Downloads\\photos.zip")));
+        assert!(!is_archive(&virtual_path));
+        assert!(split_member(Path::new("C:\\notes::draft.txt")).is_none());
+    }
+
+    #[test]
+    fn test_corrupt_archive_is_an_error() {
+        let dir = TestDir::new("archive_corrupt");
+        let broken = dir.join("broken.zip");
+        fs::write(&broken, b"definitely not a zip").unwrap();
+
+        assert!(matches!(
+            list_members(&broken, u64::MAX),
+            Err(FlashFindError::CorruptArchive { .. })
+        ));
+    }
+
+    #[test]
+    fn test_extract_member_keeps_only_filename() {
+        let dir = TestDir::new("archive_extract");
+        let archive = fixture_zip(&dir, "docs.zip", &[("reports/q1.txt", b"quarterly")]);
+
+        let extracted = extract_member(&member_path(&archive, "reports/q1.txt")).unwrap();
+        assert_eq!(extracted.file_name().unwrap(), "q1.txt");
+        assert!(extracted.starts_with(std::env::temp_dir().join(EXTRACT_DIR)));
+        assert_eq!(fs::read(&extracted).unwrap(), b"quarterly");
+
+        assert!(extract_member(&member_path(&archive, "missing.txt")).is_err());
+
+        fs::remove_dir_all(extracted.parent().unwrap()).unwrap();
+    }
+}
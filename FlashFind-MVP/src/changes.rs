@@ -0,0 +1,210 @@
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Entries kept in the recent-changes feed
+pub const MAX_RECENT_CHANGES: usize = 100;
+
+/// How far apart a removal and a recycle-bin arrival may be to count as one move
+const TRASH_PAIRING_WINDOW: Duration = Duration::from_secs(5);
+
+/// What happened to an indexed file, as reported by the watcher
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    /// Removed while a matching file appeared in a recycle bin
+    MovedToTrash,
+}
+
+impl ChangeKind {
+    /// Short description for the feed
+    pub fn label(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::MovedToTrash => "moved to Recycle Bin",
+        }
+    }
+}
+
+/// One entry in the recent-changes feed
+#[derive(Debug, Clone)]
+pub struct RecentChange {
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+    pub at: SystemTime,
+    /// Monotonic time for pairing, immune to clock changes
+    seen: Instant,
+}
+
+#[derive(Debug, Default)]
+struct FeedState {
+    /// Newest last
+    changes: VecDeque<RecentChange>,
+    /// Recycle-bin files not yet paired with a removal
+    trash_arrivals: VecDeque<(PathBuf, Instant)>,
+}
+
+/// Bounded feed of watcher-driven index changes
+///
+/// A removal and a file appearing in a recycle bin arrive as separate events
+/// in either order, so whichever comes second completes the pair.
+#[derive(Debug, Default)]
+pub struct ChangeFeed {
+    state: Mutex<FeedState>,
+}
+
+impl ChangeFeed {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a file the watcher added to the index
+    pub fn record_added(&self, path: PathBuf) {
+        self.push(path, ChangeKind::Added, Instant::now());
+    }
+
+    /// Record a file the watcher removed from the index
+    pub fn record_removed(&self, path: PathBuf) {
+        self.record_removed_at(path, Instant::now());
+    }
+
+    fn record_removed_at(&self, path: PathBuf, now: Instant) {
+        let mut state = self.state.lock();
+        state.trash_arrivals.retain(|(_, seen)| now.duration_since(*seen) <= TRASH_PAIRING_WINDOW);
+
+        let paired = state
+            .trash_arrivals
+            .iter()
+            .position(|(trashed, _)| is_trashed_copy(&path, trashed));
+        let kind = match paired {
+            Some(i) => {
+                state.trash_arrivals.remove(i);
+                ChangeKind::MovedToTrash
+            }
+            None => ChangeKind::Removed,
+        };
+        drop(state);
+
+        self.push(path, kind, now);
+    }
+
+    /// Note a file appearing in a recycle bin, upgrading a recent matching
+    /// removal to "moved to Recycle Bin"
+    pub fn record_trash_arrival(&self, trashed: PathBuf) {
+        self.record_trash_arrival_at(trashed, Instant::now());
+    }
+
+    fn record_trash_arrival_at(&self, trashed: PathBuf, now: Instant) {
+        let mut guard = self.state.lock();
+        let state = &mut *guard;
+        let removal = state.changes.iter_mut().rev().find(|change| {
+            change.kind == ChangeKind::Removed
+                && now.duration_since(change.seen) <= TRASH_PAIRING_WINDOW
+                && is_trashed_copy(&change.path, &trashed)
+        });
+
+        match removal {
+            Some(change) => change.kind = ChangeKind::MovedToTrash,
+            None if !state.trash_arrivals.iter().any(|(p, _)| *p == trashed) => {
+                state.trash_arrivals.push_back((trashed, now));
+                if state.trash_arrivals.len() > MAX_RECENT_CHANGES {
+                    state.trash_arrivals.pop_front();
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Recent changes, newest first
+    pub fn recent(&self) -> Vec<RecentChange> {
+        self.state.lock().changes.iter().rev().cloned().collect()
+    }
+
+    fn push(&self, path: PathBuf, kind: ChangeKind, seen: Instant) {
+        let mut state = self.state.lock();
+        state.changes.push_back(RecentChange { path, kind, at: SystemTime::now(), seen });
+        if state.changes.len() > MAX_RECENT_CHANGES {
+            state.changes.pop_front();
+        }
+    }
+}
+
+/// Check whether `trashed` looks like the recycle-bin copy of `original`
+///
+/// Matches an identical filename, or the `$R<id>.<ext>` name the Windows
+/// recycle bin gives files, when the extension agrees.
+fn is_trashed_copy(original: &Path, trashed: &Path) -> bool {
+    // Split on both separators so Windows paths compare the same everywhere
+    let name = |p: &Path| {
+        let path_str = p.to_string_lossy().to_lowercase();
+        path_str.rsplit(['/', '\\']).next().unwrap_or_default().to_string()
+    };
+    let (original_name, trashed_name) = (name(original), name(trashed));
+    if original_name.is_empty() || trashed_name.is_empty() {
+        return false;
+    }
+    if original_name == trashed_name {
+        return true;
+    }
+
+    let extension = |n: &str| n.rsplit_once('.').map(|(_, ext)| ext.to_string());
+    trashed_name.starts_with("$r") && extension(&original_name) == extension(&trashed_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_removal_then_arrival_is_moved_to_trash() {
+        let feed = ChangeFeed::new();
+        let now = Instant::now();
+        feed.record_removed_at(PathBuf::from("C:\\Users\\me\\report.pdf"), now);
+        feed.record_trash_arrival_at(PathBuf::from("C:\\$Recycle.Bin\\S-1-5-21-1\\$RAB12CD.pdf"), now);
+
+        let recent = feed.recent();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].kind, ChangeKind::MovedToTrash);
+    }
+
+    #[test]
+    fn test_arrival_then_removal_is_moved_to_trash() {
+        let feed = ChangeFeed::new();
+        let now = Instant::now();
+        feed.record_trash_arrival_at(PathBuf::from("D:\\$RECYCLE.BIN\\S-1-5-21-9\\notes.txt"), now);
+        feed.record_removed_at(PathBuf::from("D:\\work\\notes.txt"), now);
+        // The arrival is consumed by the first pairing
+        feed.record_removed_at(PathBuf::from("D:\\other\\notes.txt"), now);
+
+        let kinds: Vec<ChangeKind> = feed.recent().iter().map(|c| c.kind).collect();
+        assert_eq!(kinds, vec![ChangeKind::Removed, ChangeKind::MovedToTrash]);
+    }
+
+    #[test]
+    fn test_unrelated_or_stale_arrivals_stay_removals() {
+        let feed = ChangeFeed::new();
+        let start = Instant::now();
+        feed.record_removed_at(PathBuf::from("C:\\docs\\a.docx"), start);
+        feed.record_trash_arrival_at(PathBuf::from("C:\\$Recycle.Bin\\S-1\\$RZZ.pdf"), start);
+
+        let later = start + TRASH_PAIRING_WINDOW + Duration::from_secs(1);
+        feed.record_trash_arrival_at(PathBuf::from("C:\\$Recycle.Bin\\S-1\\b.txt"), start);
+        feed.record_removed_at(PathBuf::from("C:\\docs\\b.txt"), later);
+
+        assert!(feed.recent().iter().all(|c| c.kind == ChangeKind::Removed));
+    }
+
+    #[test]
+    fn test_feed_is_bounded() {
+        let feed = ChangeFeed::new();
+        for i in 0..MAX_RECENT_CHANGES + 10 {
+            feed.record_added(PathBuf::from(format!("C:\\data\\{}.txt", i)));
+        }
+        let recent = feed.recent();
+        assert_eq!(recent.len(), MAX_RECENT_CHANGES);
+        assert_eq!(recent[0].path, PathBuf::from(format!("C:\\data\\{}.txt", MAX_RECENT_CHANGES + 9)));
+    }
+}
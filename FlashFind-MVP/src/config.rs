@@ -2,8 +2,16 @@ use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use tracing::{debug, info, warn};
 
+use crate::archive::DEFAULT_MAX_ARCHIVE_MB;
 use crate::error::{FlashFindError, Result};
-use crate::persistence::get_app_data_dir;
+use crate::history::SearchHistory;
+use crate::index::{SortOrder, TypoTolerance, DEFAULT_CANDIDATE_LIMIT};
+use crate::kinds::{default_kind_groups, KindGroup};
+use crate::logging::LogLevel;
+use crate::platform;
+use crate::privacy;
+use crate::ranking::{RankingPreset, RankingProfile};
+use crate::redact::redact;
 
 /// Application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,18 +22,279 @@ pub struct Config {
     /// Theme preference
     pub theme: Theme,
     
-    /// Enabled drive letters (e.g., vec!['C', 'D'])
+    /// Enabled drive letters (e.g., vec!['C', 'D']), Windows only
     pub enabled_drives: Vec<char>,
     
+    /// Extra mount points to index besides the home folders (Linux/macOS)
+    #[serde(default)]
+    pub enabled_locations: Vec<PathBuf>,
+    
     /// First launch flag for welcome screen
     #[serde(default = "default_first_launch")]
     pub first_launch: bool,
+    
+    /// Prefix CSV exports with a UTF-8 BOM so Excel detects the encoding
+    #[serde(default)]
+    pub csv_include_bom: bool,
+    
+    /// Index .dll/.sys files inside Windows and Program Files as well
+    #[serde(default)]
+    pub index_system_file_types: bool,
+    
+    /// Extra exclusion patterns; a leading `!` marks a never-exclude override
+    #[serde(default)]
+    pub custom_exclusions: Vec<String>,
+    
+    /// Folders under which only some files are indexed
+    #[serde(default)]
+    pub include_filters: Vec<IncludeFilter>,
+    
+    /// Refuse to open links that resolve outside the indexed folders
+    #[serde(default)]
+    pub strict_open_mode: bool,
+    
+    /// UNC shares (e.g. `\\nas\shared`) that results may be opened from
+    #[serde(default)]
+    pub network_locations: Vec<PathBuf>,
+    
+    /// Hide personal path components in log files (default on in release builds)
+    #[serde(default = "default_log_redaction")]
+    pub log_redaction: bool,
+    
+    /// Cap on candidates collected per search; weaker matches beyond it are dropped
+    #[serde(default = "default_max_search_candidates")]
+    pub max_search_candidates: usize,
+    
+    /// Pause in typing before the query is searched, in milliseconds
+    /// (0 searches on every keystroke)
+    #[serde(default = "default_search_debounce_ms")]
+    pub search_debounce_ms: u64,
+    
+    /// Shortest filename query that is searched; shorter ones show a hint
+    /// instead (extension and `*` queries are always searched)
+    #[serde(default = "default_min_query_length")]
+    pub min_query_length: usize,
+    
+    /// Time a search may scan before it stops and shows what it found, in
+    /// milliseconds (0 for no limit)
+    #[serde(default = "default_search_timeout_ms")]
+    pub search_timeout_ms: u64,
+    
+    /// List the members of .zip files and index them as virtual paths
+    #[serde(default)]
+    pub index_archives: bool,
+    
+    /// Largest archive (in MB) whose members are listed
+    #[serde(default = "default_max_archive_size_mb")]
+    pub max_archive_size_mb: u64,
+    
+    /// Index folders as well as files, so they can be searched by name
+    #[serde(default)]
+    pub index_directories: bool,
+    
+    /// Ranking profile applied to search results
+    #[serde(default)]
+    pub ranking_preset: RankingPreset,
+    
+    /// Weights used by the Custom ranking profile
+    #[serde(default)]
+    pub custom_ranking: RankingProfile,
+    
+    /// Order of search results; anything but Name bypasses the ranking profile
+    #[serde(default)]
+    pub sort_order: SortOrder,
+    
+    /// Folders whose files are listed before other results
+    #[serde(default)]
+    pub favorite_folders: Vec<PathBuf>,
+    
+    /// Search only inside the favorite folders
+    #[serde(default)]
+    pub favorites_only: bool,
+    
+    /// Show one result per file when it is reachable under several paths
+    #[serde(default = "default_merge_aliases")]
+    pub merge_aliases: bool,
+    
+    /// Match filenames with typos on every search, not only when nothing matches exactly
+    #[serde(default)]
+    pub fuzzy_search: bool,
+    
+    /// How many typos fuzzy matches and the no-match fallback forgive
+    #[serde(default)]
+    pub typo_tolerance: TypoTolerance,
+    
+    /// Match plain filename queries only as whole words, as `\b` does
+    #[serde(default)]
+    pub whole_word: bool,
+    
+    /// Match filenames without regard to accents, so "resume" finds "résumé"
+    #[serde(default = "default_ignore_accents")]
+    pub ignore_accents: bool,
+    
+    /// Keep a trigram index of filenames in memory for faster substring search
+    #[serde(default)]
+    pub trigram_index: bool,
+    
+    /// Also match short filename queries against the initials of filename
+    /// words, so "fbr" finds financial_budget_report.xlsx
+    #[serde(default)]
+    pub acronym_matching: bool,
+    
+    /// After loading the saved index, drop entries whose files are gone
+    /// (see `health::verify_and_prune`)
+    #[serde(default = "default_prune_on_load")]
+    pub prune_on_load: bool,
+    
+    /// List hidden and system files (dot files outside Windows) in results;
+    /// they are indexed either way and `is:hidden` always finds them
+    #[serde(default)]
+    pub show_hidden_files: bool,
+    
+    /// Named extension groups for `kind:` queries and the file type filter
+    #[serde(default = "default_kind_groups")]
+    pub kind_groups: Vec<KindGroup>,
+    
+    /// Whether a single click opens a result or only selects it
+    #[serde(default)]
+    pub result_activation: ResultActivation,
+    
+    /// Most recently modified files listed while the search box is empty
+    /// (0 shows the splash screen instead)
+    #[serde(default = "default_recent_files")]
+    pub recent_files: usize,
+    
+    /// Count files added and removed each day for the weekly summary (see
+    /// `activity::ActivityLog`)
+    #[serde(default)]
+    pub weekly_summary: bool,
+    
+    /// Monday (days since 1970-01-01) of the week whose start last showed
+    /// the summary card, so it shows once a week
+    #[serde(default)]
+    pub weekly_summary_seen: i64,
+    
+    /// Named queries; watched ones raise an alert when a new file matches
+    #[serde(default)]
+    pub saved_searches: Vec<SavedSearch>,
+    
+    /// Recent queries for Up-arrow recall, newest first
+    #[serde(default)]
+    pub search_history: SearchHistory,
+    
+    /// Verbosity of the log file
+    #[serde(default = "LogLevel::default_for_build")]
+    pub log_level: LogLevel,
+    
+    /// Per-module log directives in `EnvFilter` syntax, e.g. `flashfind::watcher=debug`
+    #[serde(default)]
+    pub log_module_filter: String,
+    
+    /// Keep settings in memory only because app storage is unwritable
+    #[serde(skip)]
+    pub ephemeral: bool,
 }
 
 fn default_first_launch() -> bool {
     true
 }
 
+fn default_log_redaction() -> bool {
+    !cfg!(debug_assertions)
+}
+
+fn default_max_search_candidates() -> usize {
+    DEFAULT_CANDIDATE_LIMIT
+}
+
+fn default_search_debounce_ms() -> u64 {
+    100
+}
+
+fn default_min_query_length() -> usize {
+    2
+}
+
+fn default_search_timeout_ms() -> u64 {
+    500
+}
+
+fn default_merge_aliases() -> bool {
+    true
+}
+
+fn default_ignore_accents() -> bool {
+    true
+}
+
+fn default_prune_on_load() -> bool {
+    true
+}
+
+fn default_max_archive_size_mb() -> u64 {
+    DEFAULT_MAX_ARCHIVE_MB
+}
+
+fn default_recent_files() -> usize {
+    20
+}
+
+/// A query kept for reuse
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+    /// File type filter id the search was saved with; none means all files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    /// Alert when the watcher indexes a new file matching the query
+    #[serde(default)]
+    pub watch: bool,
+}
+
+/// Files to index under one folder, when not all of them are wanted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IncludeFilter {
+    pub root: PathBuf,
+    /// Extensions (`mp4`, `.mkv`), kind names (`videos`) or wildcard
+    /// patterns (`*.srt`); a file matching any of them is indexed
+    pub patterns: Vec<String>,
+}
+
+impl IncludeFilter {
+    /// A filter for `root` from comma- or space-separated patterns, or
+    /// `None` when there are none
+    pub fn parse(root: PathBuf, patterns: &str) -> Option<Self> {
+        let patterns: Vec<String> = patterns
+            .split([',', ' '])
+            .filter(|p| !p.is_empty())
+            .map(str::to_string)
+            .collect();
+        (!patterns.is_empty()).then_some(Self { root, patterns })
+    }
+}
+
+/// How clicks on a result row open the file
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum ResultActivation {
+    /// Clicking the filename opens it
+    #[default]
+    SingleClick,
+    /// A click selects the row and a double click opens it
+    DoubleClick,
+}
+
+impl ResultActivation {
+    pub const ALL: [ResultActivation; 2] = [ResultActivation::SingleClick, ResultActivation::DoubleClick];
+    
+    pub fn label(self) -> &'static str {
+        match self {
+            ResultActivation::SingleClick => "Single click opens",
+            ResultActivation::DoubleClick => "Click selects, double click opens",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum Theme {
     Dark,
@@ -39,7 +308,46 @@ impl Default for Config {
             auto_save_interval: 300, // 5 minutes
             theme: Theme::Dark,
             enabled_drives: vec!['C'], // Default: C drive only
+            enabled_locations: Vec::new(),
             first_launch: true,
+            csv_include_bom: false,
+            index_system_file_types: false,
+            custom_exclusions: Vec::new(),
+            include_filters: Vec::new(),
+            strict_open_mode: false,
+            network_locations: Vec::new(),
+            log_redaction: default_log_redaction(),
+            max_search_candidates: default_max_search_candidates(),
+            search_debounce_ms: default_search_debounce_ms(),
+            min_query_length: default_min_query_length(),
+            search_timeout_ms: default_search_timeout_ms(),
+            index_archives: false,
+            max_archive_size_mb: default_max_archive_size_mb(),
+            index_directories: false,
+            ranking_preset: RankingPreset::default(),
+            sort_order: SortOrder::default(),
+            favorite_folders: Vec::new(),
+            favorites_only: false,
+            custom_ranking: RankingProfile::default(),
+            merge_aliases: default_merge_aliases(),
+            fuzzy_search: false,
+            typo_tolerance: TypoTolerance::default(),
+            whole_word: false,
+            ignore_accents: true,
+            trigram_index: false,
+            acronym_matching: false,
+            prune_on_load: default_prune_on_load(),
+            show_hidden_files: false,
+            kind_groups: default_kind_groups(),
+            result_activation: ResultActivation::default(),
+            recent_files: default_recent_files(),
+            weekly_summary: false,
+            weekly_summary_seen: 0,
+            saved_searches: Vec::new(),
+            search_history: SearchHistory::default(),
+            log_level: LogLevel::default_for_build(),
+            log_module_filter: String::new(),
+            ephemeral: false,
         }
     }
 }
@@ -62,36 +370,76 @@ impl Config {
             }
         })?;
         
-        let config: Config = serde_json::from_str(&data).map_err(|e| {
+        let mut config: Config = serde_json::from_str(&data).map_err(|e| {
             warn!("Failed to parse config: {}", e);
             FlashFindError::InvalidConfig(format!("Parse error: {}", e))
         })?;
+        config.drop_missing_favorites();
         
-        debug!("Loaded config from {}", path.display());
+        debug!("Loaded config from {}", redact(&path));
         Ok(config)
     }
     
     /// Save configuration to file
     pub fn save(&self) -> Result<()> {
+        if self.ephemeral {
+            debug!("Ephemeral mode, config kept in memory");
+            return Ok(());
+        }
+        
         let path = Self::config_path()?;
         
         let data = serde_json::to_string_pretty(self).map_err(|e| {
             FlashFindError::InvalidConfig(format!("Serialization error: {}", e))
         })?;
         
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| FlashFindError::DirectoryCreationError {
+                path: dir.display().to_string(),
+                source: e,
+            })?;
+        }
+        
         std::fs::write(&path, data).map_err(|e| FlashFindError::FileWriteError {
             path: path.display().to_string(),
             source: e,
         })?;
         
-        info!("Saved config to {}", path.display());
+        info!("Saved config to {}", redact(&path));
         Ok(())
     }
     
+    /// Forget favorite folders that no longer exist (deleted, or on a drive
+    /// that is not attached)
+    pub fn drop_missing_favorites(&mut self) {
+        self.favorite_folders.retain(|folder| {
+            let exists = folder.is_dir();
+            if !exists {
+                warn!("Favorite folder {} no longer exists, dropping it", redact(folder));
+            }
+            exists
+        });
+    }
+    
+    /// Keep a query as a saved search named after it, unless it may not be recorded
+    pub fn add_saved_search(&mut self, query: &str, filter: Option<&str>) -> bool {
+        let query = query.trim();
+        if !privacy::should_record_query(query) || self.saved_searches.iter().any(|s| s.query == query) {
+            return false;
+        }
+        
+        self.saved_searches.push(SavedSearch {
+            name: query.to_string(),
+            query: query.to_string(),
+            filter: filter.map(str::to_string),
+            watch: false,
+        });
+        true
+    }
+    
     /// Get the configuration file path
     fn config_path() -> Result<PathBuf> {
-        let app_dir = get_app_data_dir()?;
-        Ok(app_dir.join("config.json"))
+        Ok(platform::config_dir()?.join("config.json"))
     }
 }
 
@@ -106,4 +454,38 @@ mod tests {
         assert_eq!(config.theme, Theme::Dark);
         assert_eq!(config.enabled_drives, vec!['C']);
     }
+
+    #[test]
+    fn test_sort_order_survives_save_and_defaults_when_missing() {
+        let config = Config { sort_order: SortOrder::ModifiedDesc, ..Config::default() };
+        let json = serde_json::to_string(&config).unwrap();
+        let loaded: Config = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.sort_order, SortOrder::ModifiedDesc);
+
+        // Configs saved before the sort selector existed
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value.as_object_mut().unwrap().remove("sort_order");
+        let old: Config = serde_json::from_value(value).unwrap();
+        assert_eq!(old.sort_order, SortOrder::Name);
+    }
+
+    #[test]
+    fn test_kind_groups_default_to_the_built_in_filters() {
+        let mut value = serde_json::to_value(Config::default()).unwrap();
+        value.as_object_mut().unwrap().remove("kind_groups");
+        let old: Config = serde_json::from_value(value).unwrap();
+        let names: Vec<&str> = old.kind_groups.iter().map(|group| group.name.as_str()).collect();
+        assert_eq!(names, ["documents", "images", "videos", "audio", "code", "archives"]);
+    }
+
+    #[test]
+    fn test_missing_favorite_folders_are_dropped() {
+        let existing = std::env::temp_dir();
+        let mut config = Config {
+            favorite_folders: vec![existing.clone(), existing.join("flashfind-no-such-folder")],
+            ..Config::default()
+        };
+        config.drop_missing_favorites();
+        assert_eq!(config.favorite_folders, [existing]);
+    }
 }
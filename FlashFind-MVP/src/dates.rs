@@ -0,0 +1,145 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Seconds since the Unix epoch, negative before 1970
+pub fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => after.as_secs() as i64,
+        Err(before) => -(before.duration().as_secs() as i64),
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+///
+/// Howard Hinnant's `days_from_civil`; `month` is 1-12.
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The `(year, month, day)` of a day count from `days_from_civil`
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Number of days in a month, or 0 if `month` is not 1-12
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+/// Local calendar day (days since 1970-01-01) containing a Unix time
+pub fn local_day(seconds: i64, utc_offset: i64) -> i64 {
+    (seconds + utc_offset).div_euclid(SECONDS_PER_DAY)
+}
+
+/// Unix time of local midnight starting `day`
+pub fn day_start(day: i64, utc_offset: i64) -> i64 {
+    day * SECONDS_PER_DAY - utc_offset
+}
+
+/// A Unix time as a local `YYYY-MM-DD` date
+pub fn format_date(seconds: i64, utc_offset: i64) -> String {
+    format_day(local_day(seconds, utc_offset))
+}
+
+/// A day count from `days_from_civil` as a `YYYY-MM-DD` date
+pub fn format_day(days: i64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// The local calendar day right now
+pub fn today() -> i64 {
+    local_day(unix_seconds(SystemTime::now()), local_utc_offset())
+}
+
+/// Seconds the local time zone is ahead of UTC right now
+///
+/// Read from the Windows time zone settings, including daylight saving.
+/// Elsewhere dates are in UTC.
+pub fn local_utc_offset() -> i64 {
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::System::Time::{GetTimeZoneInformation, TIME_ZONE_ID_INVALID, TIME_ZONE_INFORMATION};
+
+        // Return values of GetTimeZoneInformation (winnt.h)
+        const TIME_ZONE_ID_STANDARD: u32 = 1;
+        const TIME_ZONE_ID_DAYLIGHT: u32 = 2;
+
+        // SAFETY: the struct is plain data that the call fills in
+        let mut info: TIME_ZONE_INFORMATION = unsafe { std::mem::zeroed() };
+        let bias_minutes = match unsafe { GetTimeZoneInformation(&mut info) } {
+            TIME_ZONE_ID_INVALID => return 0,
+            TIME_ZONE_ID_STANDARD => info.Bias + info.StandardBias,
+            TIME_ZONE_ID_DAYLIGHT => info.Bias + info.DaylightBias,
+            _ => info.Bias,
+        };
+        // Bias is UTC minus local time
+        -(bias_minutes as i64) * 60
+    }
+
+    #[cfg(not(windows))]
+    {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_civil_round_trip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        for days in [-800_000, -1, 0, 59, 11_016, 19_723, 2_932_896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+        assert_eq!(civil_from_days(days_from_civil(2024, 2, 29)), (2024, 2, 29));
+        assert_eq!(days_in_month(2024, 2), 29);
+        assert_eq!(days_in_month(1900, 2), 28);
+        assert_eq!(days_in_month(2000, 2), 29);
+        assert_eq!(days_in_month(2023, 13), 0);
+    }
+
+    #[test]
+    fn test_local_days_and_formatting() {
+        let noon_utc = days_from_civil(2024, 6, 1) * SECONDS_PER_DAY + 12 * 3600;
+        assert_eq!(format_date(noon_utc, 0), "2024-06-01");
+        // 14:00 in UTC+2 and 03:00 the next day in UTC+15
+        assert_eq!(format_date(noon_utc, 2 * 3600), "2024-06-01");
+        assert_eq!(format_date(noon_utc, 15 * 3600), "2024-06-02");
+        assert_eq!(format_date(noon_utc, -13 * 3600), "2024-05-31");
+        assert_eq!(format_day(days_from_civil(2024, 6, 1)), "2024-06-01");
+
+        let day = local_day(noon_utc, -5 * 3600);
+        assert!(day_start(day, -5 * 3600) <= noon_utc && noon_utc < day_start(day + 1, -5 * 3600));
+
+        assert_eq!(unix_seconds(UNIX_EPOCH + Duration::from_secs(90)), 90);
+        assert_eq!(unix_seconds(UNIX_EPOCH - Duration::from_secs(90)), -90);
+    }
+}
@@ -0,0 +1,253 @@
+use ahash::AHashMap;
+use crossbeam_channel::{Receiver, TryRecvError};
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use crate::archive::{member_path, split_member};
+use crate::paths::{from_long_path, path_key, to_long_path};
+
+/// Leading results checked for aliases; later results are returned as-is
+pub const DEDUP_WINDOW: usize = 1_000;
+
+/// Results with aliases of the same file collapsed
+#[derive(Debug, Default)]
+pub struct Deduped<T = PathBuf> {
+    pub paths: Vec<T>,
+    /// Other paths of each kept result, for the result's info dialog
+    pub aliases: AHashMap<PathBuf, Vec<PathBuf>>,
+}
+
+/// Collapse results that are the same file under different paths
+///
+/// Aliases (a junction and its target, two drive letters on one volume)
+/// share a filename, so only results whose filename repeats within the
+/// window are resolved, keeping the pass cheap. The window extends past
+/// `window` while the filename ties with the last result in it. Of each
+/// group the shortest path is kept, at the position of the best-ranked one.
+pub fn dedup_results<T, F>(paths: Vec<T>, window: usize, identity: F) -> Deduped<T>
+where
+    T: AsRef<Path> + Clone + Sync,
+    F: Fn(&Path) -> Option<String> + Sync,
+{
+    // Resolve only the repeated names; everything else is its own identity
+    let repeated = repeated_names(&paths, window);
+    let identities: Vec<Option<String>> = paths[..repeated.len()]
+        .par_iter()
+        .zip(repeated.par_iter())
+        .map(|(path, &repeated)| repeated.then(|| identity(path.as_ref())).flatten())
+        .collect();
+
+    let mut groups: AHashMap<&str, Vec<usize>> = AHashMap::new();
+    for (i, id) in identities.iter().enumerate() {
+        if let Some(id) = id {
+            groups.entry(id.as_str()).or_default().push(i);
+        }
+    }
+
+    let mut keep = vec![true; paths.len()];
+    let mut replacement: AHashMap<usize, usize> = AHashMap::new();
+    let mut aliases = AHashMap::new();
+    for members in groups.into_values().filter(|m| m.len() > 1) {
+        let preferred = *members
+            .iter()
+            .min_by_key(|&&i| (paths[i].as_ref().as_os_str().len(), i))
+            .expect("group is not empty");
+        let first = members[0];
+        for &i in &members[1..] {
+            keep[i] = false;
+        }
+        replacement.insert(first, preferred);
+        aliases.insert(
+            paths[preferred].as_ref().to_path_buf(),
+            members.iter().filter(|&&i| i != preferred).map(|&i| paths[i].as_ref().to_path_buf()).collect(),
+        );
+    }
+
+    let kept = (0..paths.len())
+        .filter(|&i| keep[i])
+        .map(|i| paths[*replacement.get(&i).unwrap_or(&i)].clone())
+        .collect();
+    Deduped { paths: kept, aliases }
+}
+
+/// Results within the window whose filename repeats there: the only ones
+/// `dedup_results` resolves
+pub fn alias_candidates<T: AsRef<Path>>(paths: &[T], window: usize) -> Vec<PathBuf> {
+    paths
+        .iter()
+        .zip(repeated_names(paths, window))
+        .filter(|(_, repeated)| *repeated)
+        .map(|(path, _)| path.as_ref().to_path_buf())
+        .collect()
+}
+
+/// Whether each result in the window shares its filename with another
+fn repeated_names<T: AsRef<Path>>(paths: &[T], window: usize) -> Vec<bool> {
+    let name = |p: &T| -> String {
+        let path_str = p.as_ref().to_string_lossy().to_lowercase();
+        path_str.rsplit(['/', '\\']).next().unwrap_or_default().to_string()
+    };
+
+    let mut end = window.min(paths.len());
+    if end > 0 {
+        let last = name(&paths[end - 1]);
+        while end < paths.len() && name(&paths[end]) == last {
+            end += 1;
+        }
+    }
+
+    let names: Vec<String> = paths[..end].iter().map(name).collect();
+    let mut name_counts: AHashMap<&str, usize> = AHashMap::new();
+    for name in &names {
+        *name_counts.entry(name).or_default() += 1;
+    }
+    names.iter().map(|name| name_counts[name.as_str()] > 1).collect()
+}
+
+/// Identities of alias candidates resolved on their own thread
+///
+/// Resolving touches the filesystem, which can stall on a network drive, so
+/// it stays off the UI thread; `dedup_results` then looks them up.
+pub struct AliasJob {
+    outcome: Receiver<AHashMap<PathBuf, String>>,
+}
+
+impl AliasJob {
+    pub fn start(candidates: Vec<PathBuf>) -> Self {
+        let (sender, outcome) = crossbeam_channel::bounded(1);
+        thread::spawn(move || {
+            let resolved: Vec<(PathBuf, String)> = candidates
+                .into_par_iter()
+                .filter_map(|path| canonical_identity(&path).map(|id| (path, id)))
+                .collect();
+            let identities: AHashMap<PathBuf, String> = resolved.into_iter().collect();
+            // The receiver is gone if the app dropped the job; nothing to report
+            let _ = sender.send(identities);
+        });
+        Self { outcome }
+    }
+
+    /// The identities once resolved; none at all if the thread panicked
+    pub fn take_identities(&self) -> Option<AHashMap<PathBuf, String>> {
+        match self.outcome.try_recv() {
+            Ok(identities) => Some(identities),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(AHashMap::new()),
+        }
+    }
+}
+
+/// Identity of the file behind `path`: its fully resolved path
+///
+/// Junctions, symlinks and substituted drive letters resolve to the same
+/// target. Archive members resolve through their archive. Paths that no
+/// longer exist have no identity and are never merged.
+pub fn canonical_identity(path: &Path) -> Option<String> {
+    let resolve = |p: &Path| std::fs::canonicalize(to_long_path(p)).ok().map(|c| from_long_path(&c));
+    let resolved = match split_member(path) {
+        Some((archive, member)) => member_path(&resolve(&archive)?, &member),
+        None => resolve(path)?,
+    };
+    Some(path_key(&resolved))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use std::fs;
+
+    #[test]
+    fn test_only_repeated_names_are_resolved() {
+        let paths = vec![PathBuf::from("C:\\a\\one.txt"), PathBuf::from("C:\\b\\two.txt")];
+        let deduped = dedup_results(paths.clone(), DEDUP_WINDOW, |_| panic!("resolved a unique name"));
+        assert_eq!(deduped.paths, paths);
+        assert!(deduped.aliases.is_empty());
+    }
+
+    #[test]
+    fn test_window_extends_over_ties_only() {
+        let paths: Vec<PathBuf> = ["C:\\x\\a.txt", "C:\\y\\b.txt", "C:\\z\\b.txt", "C:\\w\\c.txt", "C:\\v\\c.txt"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        let same = |_: &Path| Some("same file".to_string());
+
+        // Window of 2 ends on b.txt, so the tied third result is included
+        let deduped = dedup_results(paths, 2, same);
+        assert_eq!(
+            deduped.paths,
+            vec![
+                PathBuf::from("C:\\x\\a.txt"),
+                PathBuf::from("C:\\y\\b.txt"),
+                PathBuf::from("C:\\w\\c.txt"),
+                PathBuf::from("C:\\v\\c.txt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_candidates_are_the_repeated_names() {
+        let paths: Vec<PathBuf> = ["C:\\x\\a.txt", "C:\\y\\B.txt", "C:\\z\\b.txt", "C:\\w\\c.txt", "C:\\v\\a.txt"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(alias_candidates(&paths, DEDUP_WINDOW), vec![paths[0].clone(), paths[1].clone(), paths[2].clone(), paths[4].clone()]);
+        // The second a.txt is outside a window of 3
+        assert_eq!(alias_candidates(&paths, 3), vec![paths[1].clone(), paths[2].clone()]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_junction_alias_collapses_to_shortest_real_path() {
+        let base = TestDir::new("dedup_junction");
+        let target = base.join("projects").join("2024").join("reports");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("summary.pdf"), b"x").unwrap();
+        let link = base.join("r");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let real = target.join("summary.pdf");
+        let via_link = link.join("summary.pdf");
+        let other = base.join("summary.pdf");
+        fs::write(&other, b"y").unwrap();
+
+        let paths = vec![real.clone(), other.clone(), via_link.clone()];
+        let deduped = dedup_results(paths.clone(), DEDUP_WINDOW, canonical_identity);
+        assert_eq!(deduped.paths, vec![via_link.clone(), other]);
+        assert_eq!(deduped.aliases[&via_link], vec![real]);
+
+        // Resolved in the background first, the merge comes out the same
+        let job = AliasJob::start(alias_candidates(&paths, DEDUP_WINDOW));
+        let identities = loop {
+            match job.take_identities() {
+                Some(identities) => break identities,
+                None => thread::sleep(std::time::Duration::from_millis(5)),
+            }
+        };
+        let resolved = dedup_results(paths, DEDUP_WINDOW, |path| identities.get(path).cloned());
+        assert_eq!(resolved.paths, deduped.paths);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shard_overlap_after_drive_letter_remap() {
+        // Two "drive letters" mounted on the same volume, as after a remap
+        // where an old shard and a new one both hold the files
+        let base = TestDir::new("dedup_remap");
+        let volume = base.join("volume");
+        fs::create_dir_all(volume.join("data")).unwrap();
+        fs::write(volume.join("data").join("ledger.xlsx"), b"x").unwrap();
+        std::os::unix::fs::symlink(&volume, base.join("D")).unwrap();
+        std::os::unix::fs::symlink(&volume, base.join("E")).unwrap();
+
+        let old_shard = base.join("D").join("data").join("ledger.xlsx");
+        let new_shard = base.join("E").join("data").join("ledger.xlsx");
+        let gone = base.join("F").join("data").join("ledger.xlsx");
+
+        let deduped = dedup_results(vec![old_shard.clone(), new_shard.clone(), gone.clone()], DEDUP_WINDOW, canonical_identity);
+        assert_eq!(deduped.paths, vec![old_shard.clone(), gone]);
+        assert_eq!(deduped.aliases[&old_shard], vec![new_shard]);
+    }
+}
@@ -0,0 +1,155 @@
+use crossbeam_channel::{Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use tracing::info;
+
+use crate::index::SearchResult;
+use crate::sharded::{IndexView, ShardedIndex};
+
+/// Groups listed at most, largest first
+pub const MAX_DUPLICATE_GROUPS: usize = 1_000;
+
+/// Files listed per group; the rest are only counted
+pub const MAX_GROUP_ROWS: usize = 100;
+
+/// Groups with more files than this start collapsed
+pub const COLLAPSED_GROUP_SIZE: usize = 10;
+
+/// Files that share a name (and size, when checked)
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateGroup {
+    /// Filename of the first file, as it is spelled on disk
+    pub name: String,
+    /// Every file's size, when the group was checked by size
+    pub size: Option<u64>,
+    /// Up to `MAX_GROUP_ROWS` of the files
+    pub entries: Vec<SearchResult>,
+    /// Files in the whole group
+    pub count: usize,
+}
+
+/// The outcome of one duplicate scan
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DuplicateReport {
+    /// Up to `MAX_DUPLICATE_GROUPS` groups, largest first
+    pub groups: Vec<DuplicateGroup>,
+    /// Groups found in all
+    pub total_groups: usize,
+    /// Files were grouped by size as well as name
+    pub verify_size: bool,
+}
+
+/// Group the indexed files that share a name, capped for display
+///
+/// Equal names share a shard, so each shard groups its own files.
+pub fn find_duplicates(index: &IndexView, verify_size: bool) -> DuplicateReport {
+    let shards: Vec<_> = index.shards().collect();
+    let mut groups: Vec<(usize, Vec<u32>)> = shards
+        .iter()
+        .enumerate()
+        .flat_map(|(shard, index)| index.duplicate_groups(2, verify_size).into_iter().map(move |ids| (shard, ids)))
+        .collect();
+    groups.sort_unstable_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.cmp(b)));
+    let total_groups = groups.len();
+    let groups: Vec<DuplicateGroup> = groups
+        .iter()
+        .take(MAX_DUPLICATE_GROUPS)
+        .map(|(shard, ids)| {
+            let entries = shards[*shard].entries(&ids[..ids.len().min(MAX_GROUP_ROWS)]);
+            DuplicateGroup {
+                name: entries[0].file_name.clone(),
+                size: if verify_size { entries[0].size } else { None },
+                entries,
+                count: ids.len(),
+            }
+        })
+        .collect();
+    info!("Found {} groups of duplicate names", total_groups);
+    DuplicateReport { groups, total_groups, verify_size }
+}
+
+/// A duplicate scan running on its own thread
+pub struct DuplicateJob {
+    outcome: Receiver<DuplicateReport>,
+}
+
+impl DuplicateJob {
+    pub fn start(index: Arc<ShardedIndex>, verify_size: bool) -> Self {
+        let (sender, outcome) = crossbeam_channel::bounded(1);
+        thread::spawn(move || {
+            // Slots are only valid under the lock that found them
+            let report = find_duplicates(&index.read(), verify_size);
+            // The receiver is gone if the app dropped the job; nothing to report
+            let _ = sender.send(report);
+        });
+        Self { outcome }
+    }
+
+    /// The report once the scan has finished; an empty one if it panicked
+    pub fn take_report(&self) -> Option<DuplicateReport> {
+        match self.outcome.try_recv() {
+            Ok(report) => Some(report),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(DuplicateReport::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::EntryMeta;
+    use std::path::PathBuf;
+
+    fn file(size: u64) -> EntryMeta {
+        EntryMeta { size, modified: 1_700_000_000, is_dir: false, attributes: 0 }
+    }
+
+    #[test]
+    fn test_duplicates_group_by_name_then_size() {
+        let index = ShardedIndex::new();
+        index.insert_with_meta(PathBuf::from("/a/Report.pdf"), file(10)).unwrap();
+        index.insert_with_meta(PathBuf::from("/b/report.pdf"), file(10)).unwrap();
+        index.insert_with_meta(PathBuf::from("/c/report.pdf"), file(99)).unwrap();
+        index.insert(PathBuf::from("/d/report.pdf")).unwrap();
+        index.insert_with_meta(PathBuf::from("/e/report.pdf"), EntryMeta { is_dir: true, ..file(10) }).unwrap();
+        index.insert_with_meta(PathBuf::from("/a/notes.txt"), file(1)).unwrap();
+        for i in 0..3 {
+            index.insert_with_meta(PathBuf::from(format!("/src/{}/index.js", i)), file(5)).unwrap();
+        }
+        index.publish();
+
+        let by_name = find_duplicates(&index.read(), false);
+        let summary: Vec<(&str, usize, Option<u64>)> =
+            by_name.groups.iter().map(|g| (g.name.as_str(), g.count, g.size)).collect();
+        assert_eq!(summary, [("Report.pdf", 4, None), ("index.js", 3, None)]);
+
+        // Unknown sizes cannot be confirmed, and a lone size is no duplicate
+        let by_size = find_duplicates(&index.read(), true);
+        let summary: Vec<(&str, usize, Option<u64>)> =
+            by_size.groups.iter().map(|g| (g.name.as_str(), g.count, g.size)).collect();
+        assert_eq!(summary, [("index.js", 3, Some(5)), ("Report.pdf", 2, Some(10))]);
+        assert_eq!(by_size.total_groups, 2);
+
+        let job = DuplicateJob::start(Arc::new(index), true);
+        let report = loop {
+            if let Some(report) = job.take_report() {
+                break report;
+            }
+            thread::yield_now();
+        };
+        assert_eq!(report, by_size);
+    }
+
+    #[test]
+    fn test_large_groups_are_capped() {
+        let index = ShardedIndex::new();
+        for i in 0..MAX_GROUP_ROWS + 5 {
+            index.insert_with_meta(PathBuf::from(format!("/node_modules/{}/index.js", i)), file(5)).unwrap();
+        }
+        index.publish();
+        let report = find_duplicates(&index.read(), true);
+        assert_eq!(report.groups[0].count, MAX_GROUP_ROWS + 5);
+        assert_eq!(report.groups[0].entries.len(), MAX_GROUP_ROWS);
+    }
+}
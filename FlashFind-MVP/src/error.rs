@@ -1,51 +1,98 @@
 use thiserror::Error;
 
+use crate::redact::redact;
+
 /// Error types for FlashFind operations
+///
+/// Paths are shown through `redact`, as these errors end up in the log;
+/// `user_message` spells them out in full for the UI.
 #[derive(Error, Debug)]
 pub enum FlashFindError {
     // Filesystem & I/O Errors
-    #[error("Failed to read file: {path}")]
+    #[error("Failed to read file: {}", redact(.path))]
     FileReadError {
         path: String,
         #[source]
         source: std::io::Error,
     },
 
-    #[error("Failed to write file: {path}")]
+    #[error("Failed to write file: {}", redact(.path))]
     FileWriteError {
         path: String,
         #[source]
         source: std::io::Error,
     },
 
-    #[error("Failed to create directory: {path}")]
+    #[error("Failed to create directory: {}", redact(.path))]
     DirectoryCreationError {
         path: String,
         #[source]
         source: std::io::Error,
     },
 
-    #[error("Invalid path: {0}")]
+    #[error("App data folder is not writable: {}", redact(.path))]
+    StorageUnavailable {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Cannot read archive {}: {reason}", redact(.path))]
+    CorruptArchive { path: String, reason: String },
+
+    #[error("Failed to rename {} to {}", redact(.from), redact(.to))]
+    RenameFailed {
+        from: String,
+        to: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Invalid path: {}", redact(.0))]
     InvalidPath(String),
 
+    #[error("Refusing to open {}: {reason}", redact(.path))]
+    UnsafePath { path: String, reason: String },
+
+    #[error("Location is offline: {}", redact(.0))]
+    RootUnavailable(String),
+
     // Index Errors
     #[error("Index is corrupted or in invalid format")]
     CorruptedIndex(#[from] bincode::Error),
 
+    #[error("Snapshot {} is unreadable: {reason}", redact(.path))]
+    CorruptSnapshot { path: String, reason: String },
+
     #[error("Index has reached maximum capacity of {0} files")]
     IndexFull(usize),
 
+    #[error("No such drive indexed: {0}:")]
+    DriveNotIndexed(char),
+
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+
+    #[error("Search timed out after {} ms", .0.as_millis())]
+    Timeout(std::time::Duration),
+
     // Watcher Errors
     #[error("Failed to initialize filesystem watcher")]
     WatcherInitError(#[from] notify::Error),
 
-    #[error("Failed to watch directory: {path}")]
+    #[error("Failed to watch directory: {}", redact(.path))]
     WatchError {
         path: String,
         #[source]
         source: notify::Error,
     },
 
+    #[error("Elevated scan failed: {0}")]
+    ElevationFailed(String),
+
+    #[error("Cannot create shortcut: {0}")]
+    ShortcutFailed(String),
+
     // Concurrency Errors
     #[error("Background thread panicked: {0}")]
     ThreadPanic(String),
@@ -89,9 +136,37 @@ impl FlashFindError {
             FlashFindError::CorruptedIndex(_) => {
                 "Index file is corrupted. It will be rebuilt.".to_string()
             }
+            FlashFindError::StorageUnavailable { path, .. } => {
+                format!(
+                    "Cannot write to {}. Running in ephemeral mode: the index and settings last only for this session.",
+                    path
+                )
+            }
+            FlashFindError::RootUnavailable(root) => {
+                format!("{} is offline. Reconnect the drive or share and try again.", root)
+            }
+            FlashFindError::RenameFailed { to, source, .. } => {
+                format!("Cannot rename to {}: {}", to, source)
+            }
+            FlashFindError::InvalidQuery(reason) => reason.clone(),
+            FlashFindError::Timeout(limit) => {
+                format!(
+                    "The search found nothing in {} ms. Try a more specific query, or raise the time limit in Settings.",
+                    limit.as_millis()
+                )
+            }
             FlashFindError::WatcherInitError(_) => {
                 "Cannot monitor file changes. Real-time updates disabled.".to_string()
             }
+            // The log form redacts these paths; the user sees them whole
+            FlashFindError::FileReadError { path, .. } => format!("Failed to read file: {}", path),
+            FlashFindError::FileWriteError { path, .. } => format!("Failed to write file: {}", path),
+            FlashFindError::DirectoryCreationError { path, .. } => format!("Failed to create directory: {}", path),
+            FlashFindError::CorruptArchive { path, reason } => format!("Cannot read archive {}: {}", path, reason),
+            FlashFindError::InvalidPath(path) => format!("Invalid path: {}", path),
+            FlashFindError::UnsafePath { path, reason } => format!("Refusing to open {}: {}", path, reason),
+            FlashFindError::CorruptSnapshot { path, reason } => format!("Snapshot {} is unreadable: {}", path, reason),
+            FlashFindError::WatchError { path, .. } => format!("Failed to watch directory: {}", path),
             _ => self.to_string(),
         }
     }
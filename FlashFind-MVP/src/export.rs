@@ -0,0 +1,332 @@
+use parking_lot::Mutex;
+use std::borrow::Borrow;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use crate::index::SearchResult;
+use crate::sharded::IndexView;
+#[cfg(test)]
+use crate::sharded::ShardedIndex;
+use crate::snapshot::SnapshotDiff;
+
+/// UTF-8 byte order mark, lets Excel detect the encoding of exported files
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Line terminator used for every record (RFC 4180 / Excel friendly)
+const CRLF: &str = "\r\n";
+
+/// Header row for search result exports
+const HEADER: [&str; 4] = ["Path", "Filename", "Extension", "Size"];
+
+/// Rows written between progress updates and cancel checks
+const ROW_BATCH: usize = 4_096;
+
+/// Header row for snapshot diff exports
+const DIFF_HEADER: [&str; 4] = ["Change", "Path", "Size before", "Size after"];
+
+/// Quote a single CSV field according to RFC 4180
+///
+/// Fields containing separators, quotes, or line breaks are wrapped in double
+/// quotes with embedded quotes doubled. `force` quotes the field regardless.
+pub fn escape_field(field: &str, force: bool) -> String {
+    let needs_quotes = force
+        || field.contains(',')
+        || field.contains('"')
+        || field.contains('\n')
+        || field.contains('\r');
+
+    if needs_quotes {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Serialize one search result into a CSV record (including the CRLF terminator)
+///
+/// A size the index never read is left empty.
+pub fn format_row(result: &SearchResult) -> String {
+    let filename = if result.file_name.is_empty() { "N/A" } else { &result.file_name };
+    let size = result.size.map(|s| s.to_string()).unwrap_or_default();
+
+    format!(
+        "{},{},{},{}{}",
+        escape_field(&result.path.to_string_lossy(), true),
+        escape_field(filename, true),
+        escape_field(result.extension.as_deref().unwrap_or("N/A"), true),
+        size,
+        CRLF
+    )
+}
+
+/// Write search results as CSV, optionally prefixed with a UTF-8 BOM
+///
+/// Sizes come from the index rather than the filesystem. Results may be
+/// borrowed or produced as they are written, as for a whole-index export.
+pub fn write_csv<W, I>(writer: &mut W, results: I, include_bom: bool) -> io::Result<()>
+where
+    W: Write,
+    I: IntoIterator,
+    I::Item: Borrow<SearchResult>,
+{
+    write_header(writer, &HEADER, include_bom)?;
+
+    for result in results {
+        writer.write_all(format_row(result.borrow()).as_bytes())?;
+    }
+
+    writer.flush()
+}
+
+/// Write every entry of the view as CSV, stopping early once `cancel` is set
+///
+/// Returns the number of rows written, or `None` if cancelled.
+fn write_index_csv<W: Write>(
+    writer: &mut W,
+    index: &IndexView,
+    include_bom: bool,
+    processed: &AtomicUsize,
+    cancel: &AtomicBool,
+) -> io::Result<Option<usize>> {
+    write_header(writer, &HEADER, include_bom)?;
+
+    let mut written = 0;
+    for (path, meta) in index.iter() {
+        writer.write_all(format_row(&SearchResult::new(path, meta)).as_bytes())?;
+        written += 1;
+        if written % ROW_BATCH == 0 {
+            if cancel.load(Ordering::SeqCst) {
+                return Ok(None);
+            }
+            processed.store(written, Ordering::Relaxed);
+        }
+    }
+    processed.store(written, Ordering::Relaxed);
+
+    writer.flush()?;
+    Ok(Some(written))
+}
+
+/// Whole-index CSV export running on a background thread
+pub struct ExportJob {
+    path: PathBuf,
+    processed: Arc<AtomicUsize>,
+    total: usize,
+    cancel: Arc<AtomicBool>,
+    outcome: Arc<Mutex<Option<io::Result<Option<usize>>>>>,
+}
+
+impl ExportJob {
+    /// Write every entry of `index` to `path`; a cancelled export leaves no file
+    pub fn start(index: IndexView, path: PathBuf, include_bom: bool) -> Self {
+        let job = Self {
+            path: path.clone(),
+            processed: Arc::new(AtomicUsize::new(0)),
+            total: index.len(),
+            cancel: Arc::new(AtomicBool::new(false)),
+            outcome: Arc::new(Mutex::new(None)),
+        };
+
+        let (processed, cancel, outcome) = (job.processed.clone(), job.cancel.clone(), job.outcome.clone());
+        thread::spawn(move || {
+            let result = File::create(&path).and_then(|file| {
+                write_index_csv(&mut BufWriter::new(file), &index, include_bom, &processed, &cancel)
+            });
+            if !matches!(result, Ok(Some(_))) {
+                let _ = fs::remove_file(&path);
+            }
+            *outcome.lock() = Some(result);
+        });
+        job
+    }
+
+    /// The file being written
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Entries written so far and the number the index held at the start
+    pub fn progress(&self) -> (usize, usize) {
+        (self.processed.load(Ordering::Relaxed), self.total)
+    }
+
+    /// Rows written once the job has finished, or `None` if it was cancelled
+    pub fn take_outcome(&self) -> Option<io::Result<Option<usize>>> {
+        self.outcome.lock().take()
+    }
+
+    /// Stop after the current batch of rows
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Write a snapshot diff as CSV: added, then removed, then resized files
+///
+/// Sizes the snapshot or the diff does not know are left empty.
+pub fn write_diff_csv<W: Write>(writer: &mut W, diff: &SnapshotDiff, include_bom: bool) -> io::Result<()> {
+    write_header(writer, &DIFF_HEADER, include_bom)?;
+
+    let size = |size: Option<u64>| size.map(|s| s.to_string()).unwrap_or_default();
+    let mut write_row = |change: &str, path: &Path, before: Option<u64>, after: Option<u64>| {
+        let row = format!(
+            "{},{},{},{}{}",
+            change,
+            escape_field(&path.to_string_lossy(), true),
+            size(before),
+            size(after),
+            CRLF
+        );
+        writer.write_all(row.as_bytes())
+    };
+
+    for path in &diff.added {
+        write_row("Added", path, None, None)?;
+    }
+    for (path, before) in &diff.removed {
+        write_row("Removed", path, *before, None)?;
+    }
+    for change in &diff.changed {
+        write_row("Size changed", &change.path, Some(change.before), Some(change.after))?;
+    }
+
+    writer.flush()
+}
+
+/// Write the optional BOM and a header row
+fn write_header<W: Write>(writer: &mut W, header: &[&str], include_bom: bool) -> io::Result<()> {
+    if include_bom {
+        writer.write_all(UTF8_BOM)?;
+    }
+
+    let header: Vec<String> = header.iter().map(|h| escape_field(h, false)).collect();
+    writer.write_all(header.join(",").as_bytes())?;
+    writer.write_all(CRLF.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::EntryMeta;
+    use crate::test_dir::TestDir;
+
+    fn result(path: &str, size: u64) -> SearchResult {
+        SearchResult::new(PathBuf::from(path), EntryMeta { size, modified: 0, is_dir: false, attributes: 0 })
+    }
+
+    #[test]
+    fn test_escape_plain_field() {
+        assert_eq!(escape_field("report", false), "report");
+        assert_eq!(escape_field("report", true), "\"report\"");
+    }
+
+    #[test]
+    fn test_escape_embedded_quote() {
+        assert_eq!(escape_field("my \"best\" file", false), "\"my \"\"best\"\" file\"");
+    }
+
+    #[test]
+    fn test_escape_comma_and_newline() {
+        assert_eq!(escape_field("a,b", false), "\"a,b\"");
+        assert_eq!(escape_field("line1\nline2", false), "\"line1\nline2\"");
+        assert_eq!(escape_field("line1\r\nline2", false), "\"line1\r\nline2\"");
+    }
+
+    #[test]
+    fn test_row_with_special_filenames() {
+        let row = format_row(&result("/data/say \"hi\", bob\n.txt", 42));
+        assert_eq!(
+            row,
+            "\"/data/say \"\"hi\"\", bob\n.txt\",\"say \"\"hi\"\", bob\n.txt\",\"txt\",42\r\n"
+        );
+    }
+
+    #[test]
+    fn test_row_without_extension() {
+        let row = format_row(&result("/data/Makefile", 0));
+        assert_eq!(row, "\"/data/Makefile\",\"Makefile\",\"N/A\",0\r\n");
+    }
+
+    #[test]
+    fn test_write_csv_crlf_and_bom() {
+        let mut out = Vec::new();
+        let missing = SearchResult::new(PathBuf::from("/missing/a,b.pdf"), EntryMeta::UNKNOWN);
+        write_csv(&mut out, &[missing, result("/data/notes.txt", 7)], true).unwrap();
+
+        assert!(out.starts_with(UTF8_BOM));
+        let text = String::from_utf8(out[UTF8_BOM.len()..].to_vec()).unwrap();
+        assert_eq!(
+            text,
+            "Path,Filename,Extension,Size\r\n\"/missing/a,b.pdf\",\"a,b.pdf\",\"pdf\",\r\n\"/data/notes.txt\",\"notes.txt\",\"txt\",7\r\n"
+        );
+    }
+
+    #[test]
+    fn test_write_diff_csv() {
+        let diff = SnapshotDiff {
+            added: vec![PathBuf::from("C:\\new.txt")],
+            removed: vec![(PathBuf::from("C:\\gone, old.txt"), Some(7)), (PathBuf::from("C:\\unsized"), None)],
+            changed: vec![crate::snapshot::SizeChange { path: PathBuf::from("C:\\grew.log"), before: 1, after: 2 }],
+        };
+        let mut out = Vec::new();
+        write_diff_csv(&mut out, &diff, false).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Change,Path,Size before,Size after\r\n\
+             Added,\"C:\\new.txt\",,\r\n\
+             Removed,\"C:\\gone, old.txt\",7,\r\n\
+             Removed,\"C:\\unsized\",,\r\n\
+             Size changed,\"C:\\grew.log\",1,2\r\n"
+        );
+    }
+
+    #[test]
+    fn test_write_csv_without_bom() {
+        let mut out = Vec::new();
+        write_csv(&mut out, &[] as &[SearchResult], false).unwrap();
+        assert_eq!(out, b"Path,Filename,Extension,Size\r\n");
+    }
+
+    #[test]
+    fn test_export_job_writes_every_entry() {
+        let index = ShardedIndex::new();
+        for i in 0..10_000 {
+            index.insert(PathBuf::from(format!("/data/export_{}.txt", i))).unwrap();
+        }
+        index.publish();
+
+        let dir = TestDir::new("export_job");
+        let job = ExportJob::start(index.read(), dir.join("index.csv"), false);
+        let written = loop {
+            if let Some(outcome) = job.take_outcome() {
+                break outcome.unwrap();
+            }
+            thread::sleep(std::time::Duration::from_millis(10));
+        };
+
+        assert_eq!(written, Some(10_000));
+        assert_eq!(job.progress(), (10_000, 10_000));
+        let text = fs::read_to_string(job.path()).unwrap();
+        assert_eq!(text.lines().count(), 10_001);
+        assert!(text.contains("\"/data/export_9999.txt\""));
+    }
+
+    #[test]
+    fn test_cancelled_index_export_stops_early() {
+        let index = ShardedIndex::new();
+        for i in 0..(ROW_BATCH * 3) {
+            index.insert(PathBuf::from(format!("/data/export_{}.txt", i))).unwrap();
+        }
+        index.publish();
+
+        let mut out = Vec::new();
+        let (processed, cancel) = (AtomicUsize::new(0), AtomicBool::new(true));
+        assert_eq!(write_index_csv(&mut out, &index.read(), false, &processed, &cancel).unwrap(), None);
+        assert_eq!(processed.load(Ordering::Relaxed), 0);
+        assert!(String::from_utf8(out).unwrap().lines().count() <= ROW_BATCH + 1);
+    }
+}
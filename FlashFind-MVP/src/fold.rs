@@ -0,0 +1,120 @@
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Whether filename keys and queries drop accents (on by default)
+static IGNORE_ACCENTS: AtomicBool = AtomicBool::new(true);
+
+/// Turn accent-insensitive matching on or off (from `Config::ignore_accents`)
+///
+/// Keys already in an index keep the old folding until
+/// `FileIndex::sync_key_folding` refiles them.
+pub fn set_ignore_accents(enabled: bool) {
+    IGNORE_ACCENTS.store(enabled, Ordering::Relaxed);
+}
+
+/// Check whether matching ignores accents
+pub fn ignores_accents() -> bool {
+    IGNORE_ACCENTS.load(Ordering::Relaxed)
+}
+
+/// The form of a filename or query that matching compares: NFC, lowercase,
+/// and without accents unless `set_ignore_accents(false)` was called
+///
+/// Composed (NFC) and decomposed (NFD, as macOS writes them) spellings of a
+/// name fold alike. Casing is Unicode's locale-independent mapping, so `I`
+/// lowercases to `i` as in English, never to the Turkish dotless `ı`.
+pub fn fold(text: &str) -> String {
+    fold_with(text, ignores_accents())
+}
+
+/// `fold` with an explicit accent setting
+pub fn fold_with(text: &str, strip_accents: bool) -> String {
+    if text.is_ascii() {
+        return text.to_ascii_lowercase();
+    }
+    // Lowercasing can itself decompose (İ becomes i + combining dot)
+    let lower = text.nfc().collect::<String>().to_lowercase();
+    if strip_accents {
+        lower.nfd().filter(|&c| !is_combining_mark(c)).nfc().collect()
+    } else {
+        lower.nfc().collect()
+    }
+}
+
+/// Byte ranges of `text` whose folded form is `needle` (already folded),
+/// for highlighting a match in a name as written
+///
+/// Each character is folded together with the accents that follow it, so
+/// ranges cover whole letters in composed and decomposed spellings alike.
+pub fn find_folded(text: &str, needle: &str) -> Vec<Range<usize>> {
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    if text.is_ascii() {
+        let lower = text.to_ascii_lowercase();
+        return lower.match_indices(needle).map(|(at, m)| at..at + m.len()).collect();
+    }
+
+    let mut folded = String::with_capacity(text.len());
+    // Original byte range of the letter each folded byte came from
+    let mut origin = Vec::with_capacity(text.len());
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let mut end = start + c.len_utf8();
+        while let Some((at, mark)) = chars.next_if(|&(_, mark)| is_combining_mark(mark)) {
+            end = at + mark.len_utf8();
+        }
+        let piece = fold(&text[start..end]);
+        origin.extend(std::iter::repeat_n((start, end), piece.len()));
+        folded.push_str(&piece);
+    }
+    folded
+        .match_indices(needle)
+        .map(|(at, m)| origin[at].0..origin[at + m.len() - 1].1)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAFE_NFC: &str = "Caf\u{e9}.txt";
+    const CAFE_NFD: &str = "Cafe\u{301}.txt";
+
+    #[test]
+    fn test_composed_and_decomposed_names_fold_alike() {
+        assert_ne!(CAFE_NFC, CAFE_NFD);
+        assert_eq!(fold_with(CAFE_NFC, false), fold_with(CAFE_NFD, false));
+        assert_eq!(fold_with(CAFE_NFD, false), "caf\u{e9}.txt");
+        assert_eq!(fold_with(CAFE_NFC, true), "cafe.txt");
+        assert_eq!(fold_with(CAFE_NFD, true), "cafe.txt");
+        assert_eq!(fold_with("RÉSUMÉ.docx", true), "resume.docx");
+        assert_eq!(fold_with("Report.PDF", true), "report.pdf");
+    }
+
+    #[test]
+    fn test_turkish_i_uses_locale_independent_casing() {
+        // Dotted capital İ loses its dot with accents ignored, and keeps it otherwise
+        assert_eq!(fold_with("İstanbul", true), "istanbul");
+        assert_eq!(fold_with("İstanbul", false), "i\u{307}stanbul");
+        // Dotless ı is its own letter, not an accented i
+        assert_eq!(fold_with("kırmızı", true), "kırmızı");
+        assert_ne!(fold_with("ISTANBUL", true), fold_with("ıstanbul", true));
+        assert_eq!(fold_with("ISTANBUL", true), "istanbul");
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_find_folded_maps_back_to_the_name_as_written() {
+        assert_eq!(find_folded("Invoice_INVOICE.pdf", "invoice"), [0..7, 8..15]);
+        assert!(find_folded("report.pdf", "").is_empty());
+        // The accent is part of the matched letter, composed or not
+        assert_eq!(find_folded(CAFE_NFC, "cafe"), [0..5]);
+        assert_eq!(find_folded(CAFE_NFD, "cafe"), [0..6]);
+        assert_eq!(find_folded("Mon CAFÉ préféré", "prefere"), [10..20]);
+        assert_eq!(find_folded("İstanbul.jpg", "istanbul"), [0..9]);
+        assert_eq!(find_folded("Straße", "straße"), [0..7]);
+    }
+}
@@ -0,0 +1,369 @@
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::Read;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::archive::{entry_kind, EntryKind};
+use crate::error::{FlashFindError, Result};
+use crate::fold::{find_folded, fold};
+use crate::paths::to_long_path;
+use crate::sharded::ShardedIndex;
+
+/// Extensions of the files whose contents are searched
+pub const TEXT_EXTENSIONS: &[&str] = &[
+    "txt", "md", "markdown", "log", "csv", "tsv", "json", "toml", "yaml", "yml", "ini", "cfg", "conf", "xml",
+    "html", "htm", "css", "js", "ts", "jsx", "tsx", "rs", "py", "go", "java", "c", "h", "cpp", "hpp", "cs", "rb",
+    "php", "sh", "ps1", "bat", "sql",
+];
+
+/// Bytes read from the start of each file; the rest is not searched
+pub const MAX_GREP_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Longest a content search runs before reporting what it found so far
+pub const GREP_TIME_LIMIT: Duration = Duration::from_secs(10);
+
+/// Matches reported before a content search stops early
+pub const MAX_CONTENT_MATCHES: usize = 1_000;
+
+/// Files read per batch between cancel and deadline checks
+const GREP_BATCH: usize = 256;
+
+/// Bytes of a matching line shown in the results
+const MAX_EXCERPT_BYTES: usize = 160;
+
+/// Bytes kept before the match when a long line is cut
+const EXCERPT_LEAD_BYTES: usize = 40;
+
+/// First line of a file holding the query
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentMatch {
+    pub path: PathBuf,
+    /// 1-based
+    pub line_number: usize,
+    /// The line, trimmed and cut around the match when long
+    pub excerpt: String,
+    /// Byte ranges of `excerpt` that matched
+    pub matched: Vec<Range<usize>>,
+}
+
+/// How a finished content search went
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GrepSummary {
+    /// Files read, whether or not they matched
+    pub files_searched: usize,
+    /// `GREP_TIME_LIMIT` ran out before every file was read
+    pub timed_out: bool,
+    /// `MAX_CONTENT_MATCHES` was reached
+    pub truncated: bool,
+}
+
+/// Messages streamed from the content search thread
+#[derive(Debug, Clone, PartialEq)]
+pub enum GrepEvent {
+    Match(ContentMatch),
+    Finished(GrepSummary),
+}
+
+/// Commands that can be sent to the content search thread
+pub enum GrepCommand {
+    /// Search indexed text files for `query`; `generation` lets the thread
+    /// skip searches a newer one has already replaced
+    Search { query: String, generation: u64 },
+}
+
+/// Background search of file contents, for when no filename matches
+///
+/// Runs on its own thread like the indexer, reading indexed files with a
+/// `TEXT_EXTENSIONS` extension and streaming each match back as it is found.
+pub struct ContentSearcher {
+    command_tx: Sender<GrepCommand>,
+    events: Receiver<(u64, GrepEvent)>,
+    cancel_flag: Arc<AtomicBool>,
+    /// Generation of the most recent search; events of older ones are dropped
+    generation: Arc<AtomicU64>,
+    /// A search was started and has not reported `Finished`
+    running: bool,
+    #[allow(dead_code)]
+    thread_handle: Option<JoinHandle<()>>,
+}
+
+impl ContentSearcher {
+    pub fn new(index: Arc<ShardedIndex>) -> Self {
+        let (command_tx, command_rx) = bounded::<GrepCommand>(10);
+        let (event_tx, events) = unbounded();
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        let generation = Arc::new(AtomicU64::new(0));
+
+        let thread_cancel = cancel_flag.clone();
+        let thread_generation = generation.clone();
+        let thread_handle = thread::spawn(move || {
+            grep_thread(index, thread_cancel, thread_generation, command_rx, event_tx);
+        });
+
+        Self {
+            command_tx,
+            events,
+            cancel_flag,
+            generation,
+            running: false,
+            thread_handle: Some(thread_handle),
+        }
+    }
+
+    /// Search file contents for `query`, replacing any search still running
+    pub fn search(&mut self, query: &str) -> Result<()> {
+        // Publish the generation before cancelling, as `Indexer::rebuild` does
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.cancel_flag.store(true, Ordering::SeqCst);
+
+        info!("Searching file contents");
+        self.command_tx
+            .send(GrepCommand::Search { query: query.to_string(), generation })
+            .map_err(|_| FlashFindError::ThreadPanic("Content search thread not responding".to_string()))?;
+        self.running = true;
+        Ok(())
+    }
+
+    /// Stop the running search; its remaining events are dropped
+    pub fn cancel(&mut self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.cancel_flag.store(true, Ordering::SeqCst);
+        self.running = false;
+    }
+
+    /// Events of the current search received since the last poll
+    pub fn poll(&mut self) -> Vec<GrepEvent> {
+        let current = self.generation.load(Ordering::SeqCst);
+        let events: Vec<GrepEvent> = self
+            .events
+            .try_iter()
+            .filter(|(generation, _)| *generation == current)
+            .map(|(_, event)| event)
+            .collect();
+        if events.iter().any(|event| matches!(event, GrepEvent::Finished(_))) {
+            self.running = false;
+        }
+        events
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+}
+
+/// Background thread that runs content searches one at a time
+fn grep_thread(
+    index: Arc<ShardedIndex>,
+    cancel_flag: Arc<AtomicBool>,
+    generation: Arc<AtomicU64>,
+    command_rx: Receiver<GrepCommand>,
+    event_tx: Sender<(u64, GrepEvent)>,
+) {
+    while let Ok(GrepCommand::Search { query, generation: search }) = command_rx.recv() {
+        // Reset the cancel flag before checking the generation: a newer
+        // search either shows up here or cancels this one while it runs
+        cancel_flag.store(false, Ordering::SeqCst);
+        if search < generation.load(Ordering::SeqCst) {
+            debug!("Skipping superseded content search #{}", search);
+            continue;
+        }
+
+        let paths: Vec<PathBuf> = index
+            .read()
+            .files_with_extensions(TEXT_EXTENSIONS)
+            .into_iter()
+            .filter(|path| entry_kind(path) == EntryKind::File)
+            .collect();
+        let deadline = Instant::now() + GREP_TIME_LIMIT;
+        let summary = grep_files(&paths, &fold(query.trim()), deadline, &cancel_flag, |found| {
+            // The receiver only goes away with the app
+            let _ = event_tx.send((search, GrepEvent::Match(found)));
+        });
+        info!("Content search read {} files", summary.files_searched);
+        if summary.timed_out {
+            warn!("Content search stopped after {:?}", GREP_TIME_LIMIT);
+        }
+        let _ = event_tx.send((search, GrepEvent::Finished(summary)));
+    }
+    debug!("Content search thread shutting down");
+}
+
+/// Search `paths` for `needle` (already folded) in batches, reporting each
+/// match as its batch finishes
+///
+/// Stops at `deadline`, once `cancel` is set, or after `MAX_CONTENT_MATCHES`.
+fn grep_files(
+    paths: &[PathBuf],
+    needle: &str,
+    deadline: Instant,
+    cancel: &AtomicBool,
+    mut report: impl FnMut(ContentMatch),
+) -> GrepSummary {
+    let mut summary = GrepSummary::default();
+    let mut found = 0;
+    if needle.is_empty() {
+        return summary;
+    }
+
+    for batch in paths.chunks(GREP_BATCH) {
+        if cancel.load(Ordering::SeqCst) {
+            break;
+        }
+        if Instant::now() >= deadline {
+            summary.timed_out = true;
+            break;
+        }
+
+        let matches: Vec<ContentMatch> = batch.par_iter().filter_map(|path| grep_file(path, needle)).collect();
+        summary.files_searched += batch.len();
+        for hit in matches {
+            if found == MAX_CONTENT_MATCHES {
+                summary.truncated = true;
+                return summary;
+            }
+            found += 1;
+            report(hit);
+        }
+    }
+    summary
+}
+
+/// First line of the file at `path` holding `needle` (already folded)
+///
+/// Reads at most `MAX_GREP_FILE_BYTES`, and skips files that turn out to be
+/// binary or cannot be read.
+fn grep_file(path: &Path, needle: &str) -> Option<ContentMatch> {
+    let mut bytes = Vec::new();
+    File::open(to_long_path(path)).ok()?.take(MAX_GREP_FILE_BYTES).read_to_end(&mut bytes).ok()?;
+    // A text extension does not guarantee text
+    if bytes[..bytes.len().min(8192)].contains(&0) {
+        return None;
+    }
+
+    let text = String::from_utf8_lossy(&bytes);
+    text.lines().enumerate().find_map(|(i, line)| {
+        let line = line.trim();
+        let first = find_folded(line, needle).into_iter().next()?;
+        let (excerpt, _) = excerpt(line, first.start);
+        Some(ContentMatch {
+            path: path.to_path_buf(),
+            line_number: i + 1,
+            excerpt: excerpt.to_string(),
+            matched: find_folded(excerpt, needle),
+        })
+    })
+}
+
+/// Up to `MAX_EXCERPT_BYTES` of `line` around byte `at`, and where the
+/// excerpt starts in `line`
+fn excerpt(line: &str, at: usize) -> (&str, usize) {
+    if line.len() <= MAX_EXCERPT_BYTES {
+        return (line, 0);
+    }
+    let mut start = at.saturating_sub(EXCERPT_LEAD_BYTES).min(line.len() - MAX_EXCERPT_BYTES);
+    while !line.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (start + MAX_EXCERPT_BYTES).min(line.len());
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    (&line[start..end], start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_grep_file_reports_the_first_matching_line() {
+        let dir = TestDir::new("grep_file");
+        let notes = dir.join("notes.md");
+        std::fs::write(&notes, "# Plans\n\n  Call the Plumber on Friday\nplumber again\n").unwrap();
+        let binary = dir.join("data.json");
+        std::fs::write(&binary, b"plumber\0\x01\x02").unwrap();
+
+        let found = grep_file(&notes, "plumber").unwrap();
+        assert_eq!(found.line_number, 3);
+        assert_eq!(found.excerpt, "Call the Plumber on Friday");
+        assert_eq!(found.matched, vec![9..16]);
+        assert!(grep_file(&notes, "electrician").is_none());
+        assert!(grep_file(&binary, "plumber").is_none());
+        assert!(grep_file(&dir.join("gone.txt"), "plumber").is_none());
+    }
+
+    #[test]
+    fn test_long_lines_are_cut_around_the_match() {
+        let line = format!("{}needle{}", "a".repeat(300), "b".repeat(300));
+        let (cut, shift) = excerpt(&line, 300);
+        assert_eq!(cut.len(), MAX_EXCERPT_BYTES);
+        assert_eq!(shift, 300 - EXCERPT_LEAD_BYTES);
+        assert_eq!(cut.find("needle"), Some(EXCERPT_LEAD_BYTES));
+
+        // Cuts land on character boundaries
+        let line = "é".repeat(200);
+        let (cut, shift) = excerpt(&line, 201);
+        assert!(line.is_char_boundary(shift) && cut.len() <= MAX_EXCERPT_BYTES);
+        assert_eq!(excerpt("short", 2), ("short", 0));
+    }
+
+    #[test]
+    fn test_grep_files_stops_at_cancel_deadline_and_cap() {
+        let dir = TestDir::new("grep_files");
+        let paths: Vec<PathBuf> = (0..5)
+            .map(|i| {
+                let path = dir.join(format!("{}.txt", i));
+                std::fs::write(&path, if i % 2 == 0 { "todo: ship it" } else { "done" }).unwrap();
+                path
+            })
+            .collect();
+        let later = Instant::now() + Duration::from_secs(60);
+
+        let mut found = Vec::new();
+        let summary = grep_files(&paths, "todo", later, &AtomicBool::new(false), |hit| found.push(hit.path));
+        found.sort();
+        assert_eq!(found, vec![paths[0].clone(), paths[2].clone(), paths[4].clone()]);
+        assert_eq!(summary, GrepSummary { files_searched: 5, timed_out: false, truncated: false });
+
+        let summary = grep_files(&paths, "todo", later, &AtomicBool::new(true), |_| panic!("cancelled"));
+        assert_eq!(summary.files_searched, 0);
+        let summary = grep_files(&paths, "todo", Instant::now(), &AtomicBool::new(false), |_| panic!("timed out"));
+        assert!(summary.timed_out);
+    }
+
+    #[test]
+    fn test_searcher_streams_matches_from_indexed_text_files() {
+        let dir = TestDir::new("grep_searcher");
+        let index = ShardedIndex::new();
+        for (name, text) in [("todo.txt", "buy milk"), ("photo.jpg", "milk"), ("list.md", "Milk, eggs")] {
+            let path = dir.join(name);
+            std::fs::write(&path, text).unwrap();
+            index.insert(path).unwrap();
+        }
+        index.publish();
+
+        let mut searcher = ContentSearcher::new(Arc::new(index));
+        searcher.search("MILK").unwrap();
+        assert!(searcher.is_running());
+        let mut found = Vec::new();
+        let summary = loop {
+            match searcher.events.recv_timeout(Duration::from_secs(10)).unwrap().1 {
+                GrepEvent::Match(hit) => found.push(hit.path.file_name().unwrap().to_string_lossy().into_owned()),
+                GrepEvent::Finished(summary) => break summary,
+            }
+        };
+        found.sort();
+        // Only text extensions are read
+        assert_eq!(found, ["list.md", "todo.txt"]);
+        assert_eq!(summary.files_searched, 2);
+    }
+}
@@ -0,0 +1,267 @@
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::archive::backing_file;
+use crate::paths::{is_within, to_long_path};
+use crate::roots::RootMonitor;
+use crate::sharded::ShardedIndex;
+
+/// Index entries checked against the disk per verification pass
+pub const SAMPLE_SIZE: usize = 500;
+
+/// Longest a prune pass spends checking the disk before it stops early
+pub const PRUNE_TIME_BUDGET: Duration = Duration::from_secs(120);
+
+/// Entries checked between progress reports and cancellation checks
+const PRUNE_CHUNK: usize = 4096;
+
+/// The outcome of `verify_and_prune`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PruneReport {
+    /// Entries checked against the disk
+    pub checked: usize,
+    /// Entries removed because their file is gone
+    pub pruned: usize,
+    /// Every entry under an available root was checked
+    pub complete: bool,
+}
+
+/// Remove the index entries under `roots` whose files no longer exist
+///
+/// Offline roots are skipped, so a disconnected drive keeps its entries.
+/// The disk is checked in parallel without holding the index, and each
+/// missing entry is removed under a short write lock of its shard, leaving
+/// tombstones for compaction. Stops early, keeping what it pruned, once
+/// `cancel` is set or `budget` has passed; `progress` gets the entries
+/// checked so far and the total.
+pub fn verify_and_prune<F: Fn(usize, usize)>(
+    index: &ShardedIndex,
+    roots: &[PathBuf],
+    budget: Duration,
+    cancel: &AtomicBool,
+    progress: F,
+) -> PruneReport {
+    let deadline = Instant::now() + budget;
+    let mut paths: Vec<PathBuf> = {
+        let index = index.read();
+        roots
+            .iter()
+            .filter(|root| to_long_path(root).is_dir())
+            .flat_map(|root| index.paths_within(root))
+            .collect()
+    };
+    // Nested roots list their shared entries twice
+    paths.par_sort_unstable();
+    paths.dedup();
+    
+    let mut report = PruneReport::default();
+    for chunk in paths.chunks(PRUNE_CHUNK) {
+        if cancel.load(Ordering::Relaxed) || Instant::now() >= deadline {
+            info!("Pruning stopped after checking {} of {} entries", report.checked, paths.len());
+            return report;
+        }
+        let missing: Vec<&PathBuf> = chunk.par_iter().filter(|path| !exists(path)).collect();
+        report.checked += chunk.len();
+        // A file recreated since the check is kept
+        for path in missing.into_iter().filter(|path| !exists(path)) {
+            if matches!(index.remove(path), Ok(true)) {
+                report.pruned += 1;
+            }
+        }
+        progress(report.checked, paths.len());
+    }
+    report.complete = true;
+    info!("Pruned {} of {} indexed entries whose files are gone", report.pruned, report.checked);
+    report
+}
+
+/// Whether an indexed entry still exists; archive members go by their archive
+fn exists(path: &Path) -> bool {
+    to_long_path(&backing_file(path)).exists()
+}
+
+/// A sampled index verification, computed in the background
+///
+/// Only a sample is checked, so the result is an estimate of how far the
+/// index has drifted from the disk, not a full audit.
+#[derive(Debug, Clone)]
+pub struct HealthReport {
+    pub computed_at: SystemTime,
+    /// Sampled entries checked against the disk
+    pub checked: usize,
+    /// Sampled entries that no longer exist
+    pub missing: Vec<PathBuf>,
+    /// Sampled entries skipped because their root is offline
+    pub offline: usize,
+    /// Roots holding missing entries, due an incremental rescan
+    pub drifted_roots: Vec<PathBuf>,
+}
+
+impl HealthReport {
+    /// Check a spread sample of index entries against the disk
+    ///
+    /// The index lock is held only while sampling, not during the disk checks.
+    pub fn verify(index: &ShardedIndex, monitor: &RootMonitor, roots: &[PathBuf], sample_size: usize) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        let sample = index.read().sample(sample_size, seed);
+
+        let mut report = Self {
+            computed_at: SystemTime::now(),
+            checked: 0,
+            missing: Vec::new(),
+            offline: 0,
+            drifted_roots: Vec::new(),
+        };
+
+        for path in sample {
+            if !monitor.is_available(&path) {
+                report.offline += 1;
+                continue;
+            }
+            report.checked += 1;
+            if exists(&path) {
+                continue;
+            }
+
+            if let Some(root) = roots.iter().find(|root| is_within(&path, root)) {
+                if !report.drifted_roots.contains(root) {
+                    report.drifted_roots.push(root.clone());
+                }
+            }
+            report.missing.push(path);
+        }
+
+        info!(
+            "Health check: {} of {} sampled entries missing",
+            report.missing.len(),
+            report.checked
+        );
+        report
+    }
+
+    /// Share of checked entries that still exist, in percent
+    pub fn existing_percent(&self) -> f64 {
+        if self.checked == 0 {
+            return 100.0;
+        }
+        (self.checked - self.missing.len()) as f64 * 100.0 / self.checked as f64
+    }
+}
+
+/// Describe an elapsed time for the dashboard ("just now", "5 min ago")
+pub fn format_ago(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{} min ago", secs / 60),
+        3600..=86_399 => format!("{} h ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86_400),
+    }
+}
+
+/// Time since `when`, treating clock skew as "just now"
+pub fn since(when: SystemTime) -> Duration {
+    SystemTime::now().duration_since(when).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::FileIndex;
+    use crate::test_dir::TestDir;
+    use std::fs;
+
+    #[test]
+    fn test_verify_finds_deleted_entries_and_drifted_root() {
+        let base = TestDir::new("health");
+        let (kept, drifted) = (base.join("kept"), base.join("drifted"));
+        fs::create_dir_all(&kept).unwrap();
+        fs::create_dir_all(&drifted).unwrap();
+
+        let index = ShardedIndex::new();
+        for i in 0..10 {
+            for dir in [&kept, &drifted] {
+                let file = dir.join(format!("file_{}.txt", i));
+                fs::write(&file, b"x").unwrap();
+                index.insert(file).unwrap();
+            }
+        }
+        for i in 0..5 {
+            fs::remove_file(drifted.join(format!("file_{}.txt", i))).unwrap();
+        }
+        index.publish();
+
+        let roots = vec![kept.clone(), drifted.clone()];
+        let report = HealthReport::verify(&index, &RootMonitor::new(), &roots, 100);
+        assert_eq!(report.checked, 20);
+        assert_eq!(report.missing.len(), 5);
+        assert_eq!(report.drifted_roots, vec![drifted.clone()]);
+        assert!((report.existing_percent() - 75.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_prune_drops_missing_entries_under_available_roots() {
+        let base = TestDir::new("prune");
+        let root = base.join("root");
+        fs::create_dir_all(&root).unwrap();
+        
+        let index = ShardedIndex::new();
+        for i in 0..10 {
+            let file = root.join(format!("file_{}.txt", i));
+            fs::write(&file, b"x").unwrap();
+            index.insert(file).unwrap();
+        }
+        for i in 0..4 {
+            fs::remove_file(root.join(format!("file_{}.txt", i))).unwrap();
+        }
+        // Entries of an offline root are never checked
+        let offline = base.join("unplugged");
+        index.insert(offline.join("photo.jpg")).unwrap();
+        index.publish();
+        
+        let roots = [root.clone(), offline.clone()];
+        let cancelled = verify_and_prune(&index, &roots, PRUNE_TIME_BUDGET, &AtomicBool::new(true), |_, _| {});
+        assert_eq!(cancelled, PruneReport::default());
+        
+        let seen = std::sync::Mutex::new(Vec::new());
+        let report = verify_and_prune(&index, &roots, PRUNE_TIME_BUDGET, &AtomicBool::new(false), |checked, total| {
+            seen.lock().unwrap().push((checked, total))
+        });
+        assert_eq!(report, PruneReport { checked: 10, pruned: 4, complete: true });
+        assert_eq!(seen.into_inner().unwrap(), [(10, 10)]);
+        
+        index.publish();
+        assert_eq!(index.read().len(), 7);
+        assert_eq!(index.read().tombstones(), 4);
+        assert!(index.search("file_0").is_empty());
+        assert_eq!(index.search("photo"), vec![offline.join("photo.jpg")]);
+    }
+
+    #[test]
+    fn test_sample_is_bounded_and_spread() {
+        let mut index = FileIndex::new();
+        for i in 0..1_000 {
+            index.insert(PathBuf::from(format!("C:\\data\\{}.txt", i))).unwrap();
+        }
+
+        let sample = index.sample(50, 7);
+        assert_eq!(sample.len(), 50);
+        assert!(sample.contains(&PathBuf::from("C:\\data\\7.txt")));
+        assert!(sample.contains(&PathBuf::from("C:\\data\\987.txt")));
+        assert!(FileIndex::new().sample(50, 7).is_empty());
+    }
+
+    #[test]
+    fn test_format_ago() {
+        assert_eq!(format_ago(Duration::from_secs(5)), "just now");
+        assert_eq!(format_ago(Duration::from_secs(300)), "5 min ago");
+        assert_eq!(format_ago(Duration::from_secs(7_200)), "2 h ago");
+        assert_eq!(format_ago(Duration::from_secs(3 * 86_400)), "3 days ago");
+    }
+}
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+
+/// Queries kept in the search history
+pub const MAX_HISTORY: usize = 50;
+
+/// Recent queries shown in the dropdown under an empty search box
+pub const HISTORY_DROPDOWN_ROWS: usize = 8;
+
+/// Past queries, newest first, persisted with the config
+///
+/// Callers decide what to record through `privacy::should_record_query`, so
+/// incognito searches never reach the history.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SearchHistory {
+    queries: Vec<String>,
+}
+
+impl SearchHistory {
+    /// Remember a query unless it repeats the newest entry; returns whether
+    /// the history changed
+    pub fn record(&mut self, query: &str) -> bool {
+        let query = query.trim();
+        if query.is_empty() || self.queries.first().is_some_and(|newest| newest == query) {
+            return false;
+        }
+        self.queries.insert(0, query.to_string());
+        self.queries.truncate(MAX_HISTORY);
+        true
+    }
+
+    /// The query `steps` back from the newest (0 is the newest)
+    pub fn get(&self, steps: usize) -> Option<&str> {
+        self.queries.get(steps).map(String::as_str)
+    }
+
+    /// Up to `count` distinct queries, newest first
+    pub fn recent(&self, count: usize) -> Vec<&str> {
+        let mut recent: Vec<&str> = Vec::new();
+        for query in &self.queries {
+            if recent.len() == count {
+                break;
+            }
+            if !recent.contains(&query.as_str()) {
+                recent.push(query);
+            }
+        }
+        recent
+    }
+
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.queries.clear();
+    }
+}
+
+/// Position while stepping through the history with the arrow keys
+///
+/// Up from an empty search box starts at the newest query; Down past the
+/// newest returns to an empty box. Typing ends the recall.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HistoryRecall {
+    steps: Option<usize>,
+}
+
+impl HistoryRecall {
+    /// Step to an older query, or `None` at the end of the history
+    pub fn older<'a>(&mut self, history: &'a SearchHistory) -> Option<&'a str> {
+        let next = self.steps.map_or(0, |steps| steps + 1);
+        let query = history.get(next)?;
+        self.steps = Some(next);
+        Some(query)
+    }
+
+    /// Step to a newer query; `Some("")` means back past the newest
+    pub fn newer<'a>(&mut self, history: &'a SearchHistory) -> Option<&'a str> {
+        match self.steps? {
+            0 => {
+                self.steps = None;
+                Some("")
+            }
+            steps => {
+                self.steps = Some(steps - 1);
+                history.get(steps - 1)
+            }
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.steps.is_some()
+    }
+
+    pub fn reset(&mut self) {
+        self.steps = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_skips_consecutive_duplicates_and_stays_bounded() {
+        let mut history = SearchHistory::default();
+        assert!(history.record("invoice"));
+        assert!(!history.record(" invoice "));
+        assert!(!history.record("   "));
+        assert!(history.record(".pdf"));
+        assert!(history.record("invoice"));
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.recent(5), ["invoice", ".pdf"]);
+
+        for i in 0..MAX_HISTORY + 10 {
+            history.record(&format!("query {}", i));
+        }
+        assert_eq!(history.len(), MAX_HISTORY);
+        assert_eq!(history.get(0), Some("query 59"));
+        assert_eq!(history.recent(2), ["query 59", "query 58"]);
+
+        history.clear();
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn test_recall_walks_older_then_back_to_empty() {
+        let mut history = SearchHistory::default();
+        for query in ["first", "second", "third"] {
+            history.record(query);
+        }
+
+        let mut recall = HistoryRecall::default();
+        assert_eq!(recall.newer(&history), None);
+        assert_eq!(recall.older(&history), Some("third"));
+        assert_eq!(recall.older(&history), Some("second"));
+        assert_eq!(recall.older(&history), Some("first"));
+        assert_eq!(recall.older(&history), None);
+        assert_eq!(recall.newer(&history), Some("second"));
+        assert_eq!(recall.newer(&history), Some("third"));
+        assert_eq!(recall.newer(&history), Some(""));
+        assert!(!recall.is_active());
+
+        recall.older(&history);
+        recall.reset();
+        assert_eq!(recall.older(&history), Some("third"));
+        assert_eq!(recall.older(&SearchHistory::default()), None);
+    }
+
+    #[test]
+    fn test_history_persists_as_a_plain_list() {
+        let mut history = SearchHistory::default();
+        history.record("a");
+        history.record("b");
+        let json = serde_json::to_string(&history).unwrap();
+        assert_eq!(json, r#"["b","a"]"#);
+        assert_eq!(serde_json::from_str::<SearchHistory>(&json).unwrap(), history);
+    }
+}
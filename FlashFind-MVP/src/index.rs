@@ -1,18 +1,1228 @@
 use ahash::AHashMap;
+use parking_lot::Mutex;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{debug, warn, info};
 
+use crate::dates::{format_date, local_utc_offset, unix_seconds};
 use crate::error::{FlashFindError, Result};
+use crate::fold::{find_folded, fold, ignores_accents};
+use crate::kinds::{find_kind, kind_names};
+use crate::path_pool::{PathKeys, PathPool};
+use crate::paths::{is_windows_style, is_within, path_key};
+use crate::privacy::logged_query;
+use crate::paths::to_long_path;
+use crate::query_cache::{CacheStats, QueryCache};
+use crate::query::{parse_boolean, split_folder_scopes, word_end, BoolQuery, DateFilter, FolderScope, RegexFilter, SizeFilter, MODIFIED_PREFIX, SIZE_PREFIX};
+use crate::redact::redact;
+use crate::size::format_size;
+use crate::trigram::TrigramIndex;
+use crate::words::{acronym, is_whole_word, starts_word, words, words_match};
 
 /// Maximum number of files that can be indexed
 pub const MAX_INDEX_SIZE: usize = 10_000_000;
 
+// Pool slots are addressed by `u32` ids, so a larger limit must not build
+const _: () = assert!(MAX_INDEX_SIZE <= u32::MAX as usize + 1);
+
 /// Serialization version for backwards compatibility
-pub const INDEX_VERSION: u32 = 1;
+/// v2 added persisted lifetime statistics
+/// v3 added per-entry file sizes
+/// v4 added per-entry modification times
+/// v5 folded keys with Unicode normalization
+/// v6 added the word index
+/// v7 added the compound extension index
+/// v8 added per-entry directory flags
+/// v9 added per-entry hidden and system attributes
+/// v10 stored each folder path once (see `PathPool`)
+/// v11 split the index into shards (see `ShardedIndex`)
+pub const INDEX_VERSION: u32 = 11;
+
+/// Stored size of an entry whose size was never read (size filters skip it)
+pub const UNKNOWN_SIZE: u64 = u64::MAX;
+
+/// Stored modification time of an entry never read (date filters skip it)
+pub const UNKNOWN_MTIME: i64 = i64::MIN;
+
+/// File metadata stored alongside each indexed path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryMeta {
+    /// Size in bytes, or `UNKNOWN_SIZE`
+    pub size: u64,
+    /// Last modification in seconds since the Unix epoch, or `UNKNOWN_MTIME`
+    pub modified: i64,
+    /// The entry is a folder (see `Config::index_directories`)
+    pub is_dir: bool,
+    /// `HIDDEN` and `SYSTEM` flags, as Windows reports them
+    pub attributes: u8,
+}
+
+impl EntryMeta {
+    pub const UNKNOWN: EntryMeta = EntryMeta { size: UNKNOWN_SIZE, modified: UNKNOWN_MTIME, is_dir: false, attributes: 0 };
+
+    /// Attribute flag of entries Explorer hides
+    pub const HIDDEN: u8 = 1;
+
+    /// Attribute flag of operating system files, hidden along with `HIDDEN` ones
+    pub const SYSTEM: u8 = 2;
+
+    /// A folder's size is left unknown, so size filters and sorts skip it
+    pub fn from_metadata(metadata: &std::fs::Metadata) -> Self {
+        Self {
+            size: if metadata.is_dir() { UNKNOWN_SIZE } else { metadata.len() },
+            modified: metadata.modified().map_or(UNKNOWN_MTIME, unix_seconds),
+            is_dir: metadata.is_dir(),
+            attributes: file_attributes(metadata),
+        }
+    }
+
+    /// Whether the entry is left out of results unless
+    /// `Config::show_hidden_files` is on
+    pub fn is_hidden(&self) -> bool {
+        self.attributes & (Self::HIDDEN | Self::SYSTEM) != 0
+    }
+
+    /// Read a file's current metadata, or `UNKNOWN` if it cannot be read
+    pub fn read(path: &Path) -> Self {
+        std::fs::metadata(to_long_path(path)).map_or(Self::UNKNOWN, |m| Self::from_metadata(&m))
+    }
+
+    /// The metadata of an entry moved from `old` to `new`; only the part of
+    /// the attributes implied by the name changes
+    pub fn renamed(self, old: &Path, new: &Path) -> Self {
+        Self { attributes: (self.attributes & !name_attributes(old)) | name_attributes(new), ..self }
+    }
+}
+
+/// `EntryMeta` attribute flags from the file's Windows attributes
+#[cfg(windows)]
+fn file_attributes(metadata: &std::fs::Metadata) -> u8 {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+    
+    let raw = metadata.file_attributes();
+    let mut attributes = 0;
+    if raw & FILE_ATTRIBUTE_HIDDEN != 0 {
+        attributes |= EntryMeta::HIDDEN;
+    }
+    if raw & FILE_ATTRIBUTE_SYSTEM != 0 {
+        attributes |= EntryMeta::SYSTEM;
+    }
+    attributes
+}
+
+/// Other systems have no hidden attribute; see `name_attributes`
+#[cfg(not(windows))]
+fn file_attributes(_metadata: &std::fs::Metadata) -> u8 {
+    0
+}
+
+/// Attribute flags implied by the name alone: elsewhere than Windows, a
+/// leading dot hides a file
+#[cfg(not(windows))]
+fn name_attributes(path: &Path) -> u8 {
+    let dotted = path.file_name().and_then(|n| n.to_str()).is_some_and(|name| name.starts_with('.'));
+    if dotted { EntryMeta::HIDDEN } else { 0 }
+}
+
+/// On Windows only the attributes hide a file, whatever its name
+#[cfg(windows)]
+fn name_attributes(_path: &Path) -> u8 {
+    0
+}
+
+/// Default cap on candidates collected per search (`Config::max_search_candidates`)
+pub const DEFAULT_CANDIDATE_LIMIT: usize = 500_000;
+
+/// Longest filename query also matched against filename acronyms
+/// (`Config::acronym_matching`); longer ones are rarely initials
+pub const MAX_ACRONYM_QUERY: usize = 6;
+
+/// Pool entries checked per parallel batch when scanning the whole pool
+const SUFFIX_SCAN_CHUNK: usize = 64 * 1024;
+
+/// Most recently modified files `FileIndex::recent` keeps track of
+pub const RECENT_CAPACITY: usize = 200;
+
+/// Maximum number of entries returned per page in path-browse mode
+pub const PATH_PAGE_SIZE: usize = 500;
+
+/// Outcome of a bounded search
+#[derive(Debug, Default)]
+pub struct SearchResults {
+    /// Matching paths, sorted by `compare_results` or the requested `SortOrder`
+    pub paths: Vec<PathBuf>,
+    /// The candidate cap was hit, so the weakest (or oldest, or smallest)
+    /// matches were dropped
+    pub truncated: bool,
+    /// The time limit cut the scan short, so only the matches found before
+    /// it are here (`truncated` is set too)
+    pub timed_out: bool,
+}
+
+/// Tells a running search to stop early: cancelled from another thread, or
+/// out of time
+#[derive(Debug)]
+pub struct SearchStop<'a> {
+    cancel: &'a AtomicBool,
+    deadline: Option<Instant>,
+    /// Latched by the first check past the deadline
+    timed_out: AtomicBool,
+}
+
+impl<'a> SearchStop<'a> {
+    /// Stop once `cancel` is set or, with a `timeout`, once it has passed
+    pub fn new(cancel: &'a AtomicBool, timeout: Option<Duration>) -> Self {
+        Self { cancel, deadline: timeout.map(|timeout| Instant::now() + timeout), timed_out: AtomicBool::new(false) }
+    }
+
+    /// Whether the search should stop now
+    pub fn is_set(&self) -> bool {
+        if self.cancel.load(Ordering::Relaxed) || self.timed_out.load(Ordering::Relaxed) {
+            return true;
+        }
+        let late = self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        if late {
+            self.timed_out.store(true, Ordering::Relaxed);
+        }
+        late
+    }
+
+    pub fn cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Whether some scan stopped early because time ran out
+    pub fn timed_out(&self) -> bool {
+        self.timed_out.load(Ordering::Relaxed)
+    }
+}
+
+/// A result row with the details the results list and CSV export show,
+/// taken from the index so showing it never touches the filesystem
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub path: PathBuf,
+    pub file_name: String,
+    /// Extension as it appears in the filename, without the dot
+    pub extension: Option<String>,
+    /// Size in bytes, if the indexer has read it
+    pub size: Option<u64>,
+    /// Last modification in seconds since the Unix epoch, if known
+    pub modified: Option<i64>,
+    /// Byte ranges of `file_name` that matched the query (see `match_ranges`)
+    pub matched: Vec<Range<usize>>,
+    /// The result is a folder rather than a file
+    pub is_dir: bool,
+}
+
+impl SearchResult {
+    pub fn new(path: PathBuf, meta: EntryMeta) -> Self {
+        Self {
+            file_name: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+            extension: path.extension().map(|e| e.to_string_lossy().into_owned()),
+            size: (meta.size != UNKNOWN_SIZE).then_some(meta.size),
+            modified: (meta.modified != UNKNOWN_MTIME).then_some(meta.modified),
+            matched: Vec::new(),
+            is_dir: meta.is_dir,
+            path,
+        }
+    }
+
+    /// The same file after a rename or move, keeping its size and date
+    pub fn renamed(&self, path: PathBuf) -> Self {
+        let meta = EntryMeta {
+            size: self.size.unwrap_or(UNKNOWN_SIZE),
+            modified: self.modified.unwrap_or(UNKNOWN_MTIME),
+            is_dir: self.is_dir,
+            attributes: 0,
+        };
+        Self::new(path, meta)
+    }
+}
+
+impl AsRef<Path> for SearchResult {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Order of search results, chosen in the results header
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortOrder {
+    /// By filename, then re-ranked by the ranking profile
+    #[default]
+    Name,
+    /// Most recently modified first
+    ModifiedDesc,
+    /// Largest first
+    SizeDesc,
+    /// By filename, A to Z, with no re-ranking
+    NameAsc,
+    /// By filename, Z to A
+    NameDesc,
+    /// Fewest path components first, so files near a drive's root lead
+    DepthAsc,
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 6] = [
+        SortOrder::Name,
+        SortOrder::NameAsc,
+        SortOrder::NameDesc,
+        SortOrder::SizeDesc,
+        SortOrder::ModifiedDesc,
+        SortOrder::DepthAsc,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortOrder::Name => "Relevance",
+            SortOrder::ModifiedDesc => "Newest first",
+            SortOrder::SizeDesc => "Largest first",
+            SortOrder::NameAsc => "Name A–Z",
+            SortOrder::NameDesc => "Name Z–A",
+            SortOrder::DepthAsc => "Shallowest first",
+        }
+    }
+}
+
+/// Ids matched by the last filename search, kept so a longer query can
+/// narrow them instead of scanning every filename again
+///
+/// Only complete (untruncated) result sets are kept, and only for the index
+/// generation they came from.
+#[derive(Debug, Clone)]
+pub struct SearchCache {
+    /// The lowercased filename needle that was searched
+    needle: String,
+    generation: u64,
+    /// Hidden entries were kept (see `FileIndex::set_show_hidden`)
+    show_hidden: bool,
+    /// Every live match that is shown, in no particular order
+    ids: Vec<u32>,
+}
+
+/// One page of a path-prefix (directory browse) query
+#[derive(Debug, Default)]
+pub struct PathPage {
+    /// Entries on this page, sorted by full path
+    pub paths: Vec<PathBuf>,
+    /// Total number of entries under the prefix
+    pub total: usize,
+}
+
+/// Hint shown when the query is a bare `.`
+pub const EXTENSION_HINT: &str = "Type an extension, e.g. .pdf";
+
+/// Spelled-out form of an extension query, as in `ext:pdf`
+pub const EXTENSION_PREFIX: &str = "ext:";
+
+/// Prefix that matches the rest of the query against whole paths
+pub const FULL_PATH_PREFIX: &str = "path:";
+
+/// Hint shown when the query is a bare `size:`
+pub const SIZE_HINT: &str = "Type a size, e.g. size:>100mb, size:<4kb or size:1gb..5gb";
+
+/// Hint shown when the query is a bare `modified:`
+pub const MODIFIED_HINT: &str = "Type a date, e.g. modified:today, modified:last7days or modified:>2024-01-01";
+
+/// Hint shown when the query is a bare `in:`
+pub const IN_HINT: &str = "Type part of a folder, e.g. in:Downloads installer or in:\"My Documents\"";
+
+/// `kind:` prefix naming a group of extensions, as in `kind:images`
+pub const KIND_PREFIX: &str = "kind:";
+
+/// Hint shown when the query is a bare `kind:`
+pub const KIND_HINT: &str = "Type a kind of file, e.g. kind:images vacation or kind:code";
+
+/// `is:` prefix of an attribute filter, as in `is:hidden`
+pub const IS_PREFIX: &str = "is:";
+
+/// Hint shown when the query is a bare `is:`
+pub const IS_HINT: &str = "Type an attribute, e.g. is:hidden";
+
+/// `words:` prefix matching the words of filenames (not `w:`, which is a drive)
+pub const WORDS_PREFIX: &str = "words:";
+
+/// Hint shown when the query is a bare `words:`
+pub const WORDS_HINT: &str = "Type the starts of words in a name, e.g. words:profile controller";
+
+/// `\b` prefix keeping a filename query to whole words
+pub const WHOLE_WORD_PREFIX: &str = "\\b";
+
+/// Hint shown when the query is a bare `\b`
+pub const WHOLE_WORD_HINT: &str = "Type a word to find between separators, e.g. \\bart";
+
+/// Hint shown when the query is a bare `^`
+pub const STARTS_WITH_HINT: &str = "Type the start of a filename, e.g. ^IMG_";
+
+/// Hint shown when the query is a bare `path:`
+pub const FULL_PATH_HINT: &str = "Type part of a folder or path, e.g. path:tax-2023";
+
+/// `re:` prefix matching the rest of the query as a regular expression
+pub const REGEX_PREFIX: &str = "re:";
+
+/// `repath:` prefix matching a regular expression against full paths
+pub const REGEX_PATH_PREFIX: &str = "repath:";
+
+/// Hint shown when the query is a bare `re:` or `repath:`
+pub const REGEX_HINT: &str = "Type a regular expression, e.g. re:^report_\\d{4}\\.xlsx$";
+
+/// How a raw search box query is executed
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParsedQuery {
+    /// Empty or whitespace-only: no results and no error
+    Empty,
+    /// Incomplete query: show the hint under the search box instead of results
+    Hint(&'static str),
+    /// Malformed boolean query: show why under the search box instead of results
+    Invalid(String),
+    /// Bare `*`: list every indexed file, paged like path browsing
+    BrowseAll,
+    /// Path-like query: browse entries under the prefix
+    PathPrefix(String),
+    /// `in:` clauses: results of `query` (every file if `None`) inside any
+    /// of the folders
+    InFolder { folders: Vec<FolderScope>, query: Option<Box<ParsedQuery>> },
+    /// `path:` query: substring match against whole paths, lowercased with
+    /// `\` separators
+    FullPath(String),
+    /// Several terms combined with AND, OR and NOT
+    Boolean(BoolQuery),
+    /// `size:` filter on stored file sizes
+    Size(SizeFilter),
+    /// `modified:` filter on stored modification times
+    Modified(DateFilter),
+    /// `^` query: folded filenames that begin with the text
+    StartsWith(String),
+    /// `words:` query: each folded word must start a word of the filename
+    Words(Vec<String>),
+    /// `\b` query (or `Config::whole_word`): folded text that must begin
+    /// and end at word boundaries of the filename
+    WholeWord(String),
+    /// Wildcard pattern matched against whole folded filenames
+    Glob(Glob),
+    /// `re:` or `/…/` query: regular expression found in filenames, or in
+    /// full paths for `repath:`
+    Regex(RegexFilter),
+    /// `.ext` lookup, stored folded without the leading dot
+    Extension(String),
+    /// `.jpg|.png` list: files with any of the extensions, each stored
+    /// folded without the leading dot
+    Extensions(Vec<String>),
+    /// `kind:` query: files with any extension of the named group, resolved
+    /// from `Config::kind_groups` when parsed
+    Kind { name: String, extensions: Vec<String> },
+    /// `is:hidden` query: entries with the hidden or system attribute,
+    /// listed even while `Config::show_hidden_files` is off
+    Hidden,
+    /// Case- and (by default) accent-insensitive substring match against
+    /// filenames, stored folded
+    Filename(String),
+}
+
+/// A query form the parser understands, with its in-app help entry
+///
+/// `QUERY_OPERATORS` is the single source of truth: `parse_query` tries the
+/// operators in table order and the "Search syntax" panel is generated from
+/// the same table, so a new operator is documented as soon as it parses.
+pub struct QueryOperator {
+    pub name: &'static str,
+    pub syntax: &'static str,
+    pub description: &'static str,
+    /// A query that exercises the operator (clickable in the help panel)
+    pub example: &'static str,
+    /// First version that understands the operator
+    pub since: &'static str,
+    /// Parse a trimmed, non-empty query, or `None` to try the next operator
+    parse: fn(&str) -> Option<ParsedQuery>,
+}
+
+/// Every operator, in parsing precedence order; the last one always matches
+pub const QUERY_OPERATORS: &[QueryOperator] = &[
+    QueryOperator {
+        name: "Browse all",
+        syntax: "*",
+        description: "List every indexed file, a page at a time",
+        example: "*",
+        since: "1.0",
+        parse: |q| (q == "*").then_some(ParsedQuery::BrowseAll),
+    },
+    // Ahead of the other operators, whose syntax a pattern's `\` or spaces
+    // would trigger
+    QueryOperator {
+        name: "Regular expression",
+        syntax: "re:pattern, /pattern/, repath:pattern",
+        description: "Filenames in which the regular expression matches, ignoring case; anchor with ^ and $ for whole names. repath: matches full paths instead",
+        example: "re:^report_\\d{4}\\.xlsx$",
+        since: "1.1",
+        parse: parse_regex,
+    },
+    QueryOperator {
+        name: "In folder",
+        syntax: "in:folder, in:\"two words\", in:C:\\path",
+        description: "Only files in folders whose path contains the text (or under a full path); several in: clauses widen the scope, and in: alone lists the folders' files",
+        example: "in:Downloads installer",
+        since: "1.1",
+        parse: parse_in,
+    },
+    QueryOperator {
+        name: "Full path",
+        syntax: "path:text, path:\"two words\"",
+        description: "Files whose full path contains the text, so folder names match too; combine it with other terms",
+        example: "path:tax-2023",
+        since: "1.1",
+        parse: parse_full_path,
+    },
+    QueryOperator {
+        name: "Size",
+        syntax: "size:>10mb, size:<4kb, size:1gb..5gb",
+        description: "Files by size in b, kb, mb or gb; combine with names as in video size:>1gb",
+        example: "size:>100mb",
+        since: "1.1",
+        parse: |q| {
+            let prefix = q.get(..SIZE_PREFIX.len())?;
+            if !prefix.eq_ignore_ascii_case(SIZE_PREFIX) {
+                return None;
+            }
+            let text = q[SIZE_PREFIX.len()..].trim();
+            // More terms follow: the boolean parser reads them all
+            if text.contains(char::is_whitespace) {
+                return None;
+            }
+            Some(match SizeFilter::parse(text) {
+                _ if text.is_empty() => ParsedQuery::Hint(SIZE_HINT),
+                Ok(filter) => ParsedQuery::Size(filter),
+                Err(e) => ParsedQuery::Invalid(e.user_message()),
+            })
+        },
+    },
+    QueryOperator {
+        name: "Modified",
+        syntax: "modified:today, modified:last7days, modified:>2024-01-01, modified:2024-01..2024-03",
+        description: "Files by last change, in local days; combine with names as in invoice modified:>2024-06-01",
+        example: "modified:last7days",
+        since: "1.1",
+        parse: |q| {
+            let prefix = q.get(..MODIFIED_PREFIX.len())?;
+            if !prefix.eq_ignore_ascii_case(MODIFIED_PREFIX) {
+                return None;
+            }
+            let text = q[MODIFIED_PREFIX.len()..].trim();
+            // More terms follow: the boolean parser reads them all
+            if text.contains(char::is_whitespace) {
+                return None;
+            }
+            if text.is_empty() {
+                return Some(ParsedQuery::Hint(MODIFIED_HINT));
+            }
+            // Boolean queries reparse each term, so `now` is per term
+            let now = unix_seconds(std::time::SystemTime::now());
+            Some(match DateFilter::parse(text, now, local_utc_offset()) {
+                Ok(filter) => ParsedQuery::Modified(filter),
+                Err(e) => ParsedQuery::Invalid(e.user_message()),
+            })
+        },
+    },
+    QueryOperator {
+        name: "Kind",
+        syntax: "kind:images, kind:video",
+        description: "Files with any extension of a kind set up in Settings (documents, images, videos, audio, code, archives by default); combine with names as in kind:image vacation",
+        example: "kind:images",
+        since: "1.1",
+        parse: parse_kind,
+    },
+    QueryOperator {
+        name: "Hidden",
+        syntax: "is:hidden",
+        description: "Hidden and system files, even while Settings leaves them out of results; combine with other terms as in is:hidden .ini",
+        example: "is:hidden",
+        since: "1.1",
+        parse: parse_is,
+    },
+    QueryOperator {
+        name: "Whole word",
+        syntax: "\\bword",
+        description: "Filenames holding the text as a whole word, bounded by the name's ends, _ - . spaces or case changes: \\bart finds ArtBook.pdf but not startup.txt. Add a trailing \\ (\\bin\\) to browse a folder instead",
+        example: "\\bart",
+        since: "1.1",
+        parse: |q| {
+            let word = q.strip_prefix(WHOLE_WORD_PREFIX)?;
+            // More terms, or a rooted path like \bin\tools
+            if word.contains(char::is_whitespace) || word.contains('\\') {
+                return None;
+            }
+            Some(if word.is_empty() { ParsedQuery::Hint(WHOLE_WORD_HINT) } else { ParsedQuery::WholeWord(fold(word)) })
+        },
+    },
+    QueryOperator {
+        name: "Path",
+        syntax: "C:\\folder",
+        description: "Browse files under a drive or folder",
+        example: "C:\\Users",
+        since: "1.0",
+        parse: |q| is_path_query(q).then(|| ParsedQuery::PathPrefix(q.to_string())),
+    },
+    QueryOperator {
+        name: "Words",
+        syntax: "words:word word",
+        description: "Filenames with words starting with each of these, split at _ - . and case changes: words:profile controller finds UserProfileController.cs",
+        example: "words:profile controller",
+        since: "1.1",
+        parse: |q| {
+            let prefix = q.get(..WORDS_PREFIX.len())?;
+            if !prefix.eq_ignore_ascii_case(WORDS_PREFIX) {
+                return None;
+            }
+            let query = words(&q[WORDS_PREFIX.len()..]);
+            Some(if query.is_empty() { ParsedQuery::Hint(WORDS_HINT) } else { ParsedQuery::Words(query) })
+        },
+    },
+    QueryOperator {
+        name: "Starts with",
+        syntax: "^text",
+        description: "Filenames beginning with the text, found without scanning every name; combine with other terms as in ^IMG_ .jpg",
+        example: "^IMG_",
+        since: "1.1",
+        parse: |q| {
+            let prefix = q.strip_prefix('^')?;
+            // More terms follow: the boolean parser reads them all
+            if prefix.contains(char::is_whitespace) {
+                return None;
+            }
+            Some(if prefix.is_empty() {
+                ParsedQuery::Hint(STARTS_WITH_HINT)
+            } else {
+                ParsedQuery::StartsWith(fold(prefix))
+            })
+        },
+    },
+    QueryOperator {
+        name: "Extension list",
+        syntax: ".jpg|.png|.webp",
+        description: "Files with any of the extensions; combine with a name as in .jpg|.png vacation, or leave them all out with !.tmp|.bak",
+        example: ".jpg|.png|.webp",
+        since: "1.1",
+        parse: parse_extension_list,
+    },
+    QueryOperator {
+        name: "Boolean",
+        syntax: "a AND b, a OR b, NOT a, !.ext",
+        description: "Combine terms; NOT binds tightest, then AND, then OR. Words with no operator must all match; quote a \"phrase\" to keep its spaces. !.log or -.log leaves out an extension",
+        example: "invoice AND 2024 NOT draft",
+        since: "1.1",
+        parse: |q| {
+            parse_boolean(q).map(|parsed| parsed.map_or_else(|e| ParsedQuery::Invalid(e.user_message()), ParsedQuery::Boolean))
+        },
+    },
+    QueryOperator {
+        name: "Wildcards",
+        syntax: "* ? [abc]",
+        description: "Whole filenames matching the pattern: * any run, ? one character, [abc] or [a-z] one of a set",
+        example: "IMG_????.jpg",
+        since: "1.1",
+        parse: |q| Glob::parse(&fold(q)).map(ParsedQuery::Glob),
+    },
+    QueryOperator {
+        name: "Extension",
+        syntax: ".ext, ext:ext",
+        description: "Files with this extension, including compound ones like .tar.gz; combine with a name as in report .pdf",
+        example: ".pdf",
+        since: "1.0",
+        parse: |q| {
+            let ext = match q.get(..EXTENSION_PREFIX.len()) {
+                Some(prefix) if prefix.eq_ignore_ascii_case(EXTENSION_PREFIX) => {
+                    let ext = &q[EXTENSION_PREFIX.len()..];
+                    ext.strip_prefix('.').unwrap_or(ext)
+                }
+                _ => q.strip_prefix('.')?,
+            };
+            Some(if ext.is_empty() {
+                ParsedQuery::Hint(EXTENSION_HINT)
+            } else if ext.starts_with('.') {
+                return None;
+            } else {
+                ParsedQuery::Extension(fold(ext))
+            })
+        },
+    },
+    QueryOperator {
+        name: "Filename",
+        syntax: "text",
+        description: "Filenames containing the text, ignoring case",
+        example: "invoice",
+        since: "1.0",
+        parse: |q| Some(ParsedQuery::Filename(fold(q))),
+    },
+];
+
+/// Parse a `re:`, `repath:` or slash-wrapped query; the whole rest of the
+/// query is the pattern, spaces included
+fn parse_regex(q: &str) -> Option<ParsedQuery> {
+    let strip = |prefix: &str| q.get(..prefix.len()).filter(|p| p.eq_ignore_ascii_case(prefix)).map(|_| &q[prefix.len()..]);
+    let (pattern, full_path) = if let Some(pattern) = strip(REGEX_PATH_PREFIX) {
+        (pattern, true)
+    } else if let Some(pattern) = strip(REGEX_PREFIX) {
+        (pattern, false)
+    } else if q.len() > 2 && q.starts_with('/') && q.ends_with('/') {
+        (&q[1..q.len() - 1], false)
+    } else {
+        return None;
+    };
+    Some(match RegexFilter::parse(pattern, full_path) {
+        _ if pattern.trim().is_empty() => ParsedQuery::Hint(REGEX_HINT),
+        Ok(filter) => ParsedQuery::Regex(filter),
+        Err(e) => ParsedQuery::Invalid(e.user_message()),
+    })
+}
+
+/// Parse a `path:` query: one word, or a quoted phrase that may hold spaces
+///
+/// An unquoted needle with spaces is left to the boolean parser, which reads
+/// `path:tax-2023 .pdf` as two terms.
+fn parse_full_path(q: &str) -> Option<ParsedQuery> {
+    let prefix = q.get(..FULL_PATH_PREFIX.len())?;
+    if !prefix.eq_ignore_ascii_case(FULL_PATH_PREFIX) {
+        return None;
+    }
+    
+    let rest = q[FULL_PATH_PREFIX.len()..].trim();
+    let needle = match rest.strip_prefix('"') {
+        // More terms follow the phrase, or the quote is unbalanced: both are
+        // for the boolean parser
+        Some(quoted) => match quoted.find('"') {
+            Some(close) if quoted[close + 1..].trim().is_empty() => &quoted[..close],
+            _ => return None,
+        },
+        None if rest.contains(char::is_whitespace) => return None,
+        None => rest,
+    };
+    Some(if needle.trim().is_empty() {
+        ParsedQuery::Hint(FULL_PATH_HINT)
+    } else {
+        ParsedQuery::FullPath(normalize_for_prefix(needle))
+    })
+}
+
+/// Parse a single `kind:` term against the configured kinds
+fn parse_kind(q: &str) -> Option<ParsedQuery> {
+    let prefix = q.get(..KIND_PREFIX.len())?;
+    if !prefix.eq_ignore_ascii_case(KIND_PREFIX) {
+        return None;
+    }
+    let kind = q[KIND_PREFIX.len()..].trim();
+    // More terms follow: the boolean parser reads them all
+    if kind.contains(char::is_whitespace) {
+        return None;
+    }
+    if kind.is_empty() {
+        return Some(ParsedQuery::Hint(KIND_HINT));
+    }
+    Some(match find_kind(kind) {
+        Some(group) => ParsedQuery::Kind { name: group.name, extensions: group.extensions },
+        None => ParsedQuery::Invalid(format!("Unknown kind \"{}\"; try one of: {}", kind, kind_names().join(", "))),
+    })
+}
+
+/// Parse a `.jpg|.png` extension list, spaces around the pipes allowed,
+/// or `None` if more terms follow
+fn parse_extension_list(q: &str) -> Option<ParsedQuery> {
+    if !q.starts_with('.') || !q.contains('|') || word_end(q) != q.len() {
+        return None;
+    }
+    let list: String = q.split_whitespace().collect();
+    let mut extensions = Vec::new();
+    for alternative in list.split('|') {
+        match parse_query(alternative) {
+            ParsedQuery::Extension(ext) => {
+                if !extensions.contains(&ext) {
+                    extensions.push(ext);
+                }
+            }
+            _ if alternative.is_empty() => {
+                return Some(ParsedQuery::Invalid(format!(
+                    "Empty alternative in \"{}\"; separate extensions with |, as in .jpg|.png",
+                    list
+                )))
+            }
+            _ => {
+                return Some(ParsedQuery::Invalid(format!(
+                    "\"{}\" in \"{}\" is not an extension; list extensions as in .jpg|.png",
+                    alternative, list
+                )))
+            }
+        }
+    }
+    Some(ParsedQuery::Extensions(extensions))
+}
+
+/// Parse an `is:` attribute filter; only `is:hidden` is known
+fn parse_is(q: &str) -> Option<ParsedQuery> {
+    let prefix = q.get(..IS_PREFIX.len())?;
+    if !prefix.eq_ignore_ascii_case(IS_PREFIX) {
+        return None;
+    }
+    let attribute = q[IS_PREFIX.len()..].trim();
+    // More terms follow: the boolean parser reads them all
+    if attribute.contains(char::is_whitespace) {
+        return None;
+    }
+    Some(match attribute.to_lowercase().as_str() {
+        "" => ParsedQuery::Hint(IS_HINT),
+        "hidden" => ParsedQuery::Hidden,
+        _ => ParsedQuery::Invalid(format!("Unknown attribute \"{}\"; try is:hidden", attribute)),
+    })
+}
+
+/// Whether a query asks for hidden entries, which it then lists whatever
+/// `Config::show_hidden_files` says
+fn wants_hidden(parsed: &ParsedQuery) -> bool {
+    match parsed {
+        ParsedQuery::Hidden => true,
+        ParsedQuery::Boolean(query) => query.any_of.iter().flat_map(|c| &c.include).any(wants_hidden),
+        ParsedQuery::InFolder { query: Some(query), .. } => wants_hidden(query),
+        _ => false,
+    }
+}
+
+/// Parse a query with `in:` clauses, or `None` if it has none
+///
+/// The rest of the query is parsed on its own, then scoped to the folders.
+fn parse_in(q: &str) -> Option<ParsedQuery> {
+    let (folders, rest) = match split_folder_scopes(q)? {
+        Ok(split) => split,
+        Err(e) => return Some(ParsedQuery::Invalid(e.user_message())),
+    };
+    if folders.is_empty() {
+        return Some(ParsedQuery::Hint(IN_HINT));
+    }
+    let query = match parse_query(&rest) {
+        ParsedQuery::Empty | ParsedQuery::BrowseAll => None,
+        hint @ (ParsedQuery::Hint(_) | ParsedQuery::Invalid(_)) => return Some(hint),
+        ParsedQuery::PathPrefix(_) | ParsedQuery::InFolder { .. } => {
+            return Some(ParsedQuery::Invalid("Put the folder in the in: clause, e.g. in:C:\\Users report".to_string()))
+        }
+        query => Some(Box::new(query)),
+    };
+    Some(ParsedQuery::InFolder { folders, query })
+}
+
+/// Classify a search box query, including degenerate symbol-only input
+///
+/// `..` and other dot runs are literal filename text; only a single leading
+/// dot followed by something else is an extension query.
+pub fn parse_query(query: &str) -> ParsedQuery {
+    let q = query.trim();
+    
+    if q.is_empty() {
+        return ParsedQuery::Empty;
+    }
+    if q == "." {
+        return ParsedQuery::Hint(EXTENSION_HINT);
+    }
+    
+    QUERY_OPERATORS
+        .iter()
+        .find_map(|op| (op.parse)(q))
+        .unwrap_or_else(|| ParsedQuery::Filename(fold(q)))
+}
+
+/// The operator that handles a query, if it is not empty or hint-only
+pub fn query_operator(query: &str) -> Option<&'static QueryOperator> {
+    let q = query.trim();
+    if q.is_empty() || q == "." {
+        return None;
+    }
+    QUERY_OPERATORS.iter().find(|op| (op.parse)(q).is_some())
+}
+
+/// One element of a compiled glob
+#[derive(Debug, Clone, PartialEq)]
+enum GlobToken {
+    /// `*`: any run of characters, including none
+    AnyRun,
+    /// `?`: exactly one character
+    AnyChar,
+    /// `[abc]`, `[a-z]`, or negated `[!abc]`
+    Class { negated: bool, ranges: Vec<(char, char)> },
+    Literal(char),
+}
+
+/// A filename wildcard pattern, matched against the whole name
+///
+/// Only `*` and `?` make a query a pattern; brackets then form character
+/// classes. Brackets alone stay literal text, so a search for `song [live]`
+/// still finds "song [live].mp3" by substring.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Glob {
+    pattern: String,
+    tokens: Vec<GlobToken>,
+}
+
+impl Glob {
+    /// Compile `pattern`, or `None` if it has no `*` or `?`
+    pub fn parse(pattern: &str) -> Option<Glob> {
+        if !pattern.contains(['*', '?']) {
+            return None;
+        }
+        
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let token = match chars[i] {
+                // Consecutive stars behave like one
+                '*' if tokens.last() == Some(&GlobToken::AnyRun) => {
+                    i += 1;
+                    continue;
+                }
+                '*' => GlobToken::AnyRun,
+                '?' => GlobToken::AnyChar,
+                '[' => match parse_class(&chars[i + 1..]) {
+                    Some((token, used)) => {
+                        i += used;
+                        token
+                    }
+                    // An unclosed bracket is a literal
+                    None => GlobToken::Literal('['),
+                },
+                c => GlobToken::Literal(c),
+            };
+            tokens.push(token);
+            i += 1;
+        }
+        
+        Some(Glob { pattern: pattern.to_string(), tokens })
+    }
+    
+    /// The pattern as typed (lowercased)
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+    
+    /// Check whether the whole `name` matches the pattern
+    pub fn matches(&self, name: &str) -> bool {
+        let text: Vec<char> = name.chars().collect();
+        let (mut ti, mut pi) = (0, 0);
+        // Position after the last `*` and the text position it resumes from
+        let mut backtrack: Option<(usize, usize)> = None;
+        
+        while ti < text.len() {
+            match self.tokens.get(pi) {
+                Some(GlobToken::AnyRun) => {
+                    pi += 1;
+                    backtrack = Some((pi, ti));
+                    continue;
+                }
+                Some(token) if token_matches(token, text[ti]) => {
+                    pi += 1;
+                    ti += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            // Let the last `*` swallow one more character and retry
+            let Some((star_pi, star_ti)) = backtrack else {
+                return false;
+            };
+            pi = star_pi;
+            ti = star_ti + 1;
+            backtrack = Some((star_pi, ti));
+        }
+        
+        self.tokens[pi..].iter().all(|token| *token == GlobToken::AnyRun)
+    }
+}
+
+/// Parse a class after its `[`; returns it and the characters used through `]`
+fn parse_class(chars: &[char]) -> Option<(GlobToken, usize)> {
+    let negated = matches!(chars.first(), Some('!' | '^'));
+    let mut i = usize::from(negated);
+    let mut ranges = Vec::new();
+    
+    // A `]` right after the opening bracket is a member, not the end
+    while i < chars.len() && (chars[i] != ']' || ranges.is_empty()) {
+        let start = chars[i];
+        if chars.get(i + 1) == Some(&'-') && chars.get(i + 2).is_some_and(|&end| end != ']') {
+            ranges.push((start, chars[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((start, start));
+            i += 1;
+        }
+    }
+    
+    (i < chars.len()).then_some((GlobToken::Class { negated, ranges }, i + 1))
+}
+
+fn token_matches(token: &GlobToken, c: char) -> bool {
+    match token {
+        GlobToken::AnyRun | GlobToken::AnyChar => true,
+        GlobToken::Class { negated, ranges } => ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated,
+        GlobToken::Literal(literal) => *literal == c,
+    }
+}
+
+/// Check whether a query looks like a path (`C:`, `C:\Users`, `docs\2024`)
+/// and should browse by prefix instead of matching filenames
+pub fn is_path_query(query: &str) -> bool {
+    let q = query.trim();
+    q.contains('\\') || drive_letter(q).is_some()
+}
+
+/// Extract the drive letter from a query starting with `X:`
+fn drive_letter(query: &str) -> Option<char> {
+    let mut chars = query.chars();
+    match (chars.next(), chars.next()) {
+        (Some(letter), Some(':')) if letter.is_ascii_alphabetic() => Some(letter.to_ascii_uppercase()),
+        _ => None,
+    }
+}
+
+/// Lowercase a path and use backslashes so prefixes compare uniformly
+pub fn normalize_for_prefix(path: &str) -> String {
+    path.to_lowercase().replace('/', "\\")
+}
+
+/// The folder holding `path`, normalized as `normalize_for_prefix` does and
+/// without a trailing separator; `None` for a path with no separator
+pub fn folder_key(path: &Path) -> Option<String> {
+    let full = normalize_for_prefix(&path.to_string_lossy());
+    full.rsplit_once('\\').map(|(folder, _)| folder.to_string())
+}
+
+/// Distinct words of a path's filename, as filed in the word index
+/// The acronym a path is filed under in the acronym map
+fn name_acronym(path: &Path) -> Option<String> {
+    path.file_name().and_then(|n| n.to_str()).and_then(acronym)
+}
+
+fn name_words(path: &Path) -> Vec<String> {
+    let mut found = path.file_name().and_then(|n| n.to_str()).map(words).unwrap_or_default();
+    found.sort_unstable();
+    found.dedup();
+    found
+}
+
+/// Dotted suffixes of a folded filename with two or more parts, as filed
+/// in the compound extension index (`tar.gz` and `backup.tar.gz` for
+/// `my.backup.tar.gz`)
+fn compound_extensions(name: &str) -> impl Iterator<Item = &str> {
+    name.match_indices('.')
+        .map(move |(dot, _)| &name[dot + 1..])
+        .filter(|suffix| suffix.contains('.'))
+}
+
+/// Folded filename and extension keys a path is filed under (see `fold::fold`)
+fn index_keys(path: &Path) -> (Option<String>, Option<String>) {
+    let name = path.file_name().and_then(|n| n.to_str()).map(fold);
+    let ext = path.extension().and_then(|e| e.to_str()).map(fold);
+    (name, ext)
+}
+
+/// Keys a path is filed under, computed once and moved into the maps
+#[derive(Clone)]
+struct EntryKeys {
+    name: Option<String>,
+    ext: Option<String>,
+    words: Vec<String>,
+    acronym: Option<String>,
+    folder: Option<String>,
+}
+
+impl EntryKeys {
+    fn of(path: &Path) -> Self {
+        let (name, ext) = index_keys(path);
+        Self { name, ext, words: name_words(path), acronym: name_acronym(path), folder: folder_key(path) }
+    }
+}
+
+/// A path to insert with everything derived from it alone worked out
+#[derive(Clone)]
+struct PreparedEntry {
+    path: PathBuf,
+    /// Attributes include those implied by the name
+    meta: EntryMeta,
+    /// `paths::path_key` of the path
+    key: String,
+    /// `None` for a path the index cannot hold (no filename, or not Unicode)
+    keys: Option<EntryKeys>,
+}
+
+impl PreparedEntry {
+    fn new(path: PathBuf, meta: EntryMeta) -> Self {
+        let valid = path.file_name().is_some() && path.to_str().is_some();
+        Self {
+            meta: EntryMeta { attributes: meta.attributes | name_attributes(&path), ..meta },
+            key: path_key(&path),
+            keys: valid.then(|| EntryKeys::of(&path)),
+            path,
+        }
+    }
+}
+
+/// Paths to insert with `FileIndex::insert_batch`
+///
+/// Folding names and splitting words needs no index, so it happens here,
+/// in parallel and before the write lock is taken; only the lookups and
+/// filing are left for the lock.
+#[derive(Clone)]
+pub struct PreparedBatch {
+    entries: Vec<PreparedEntry>,
+}
+
+impl PreparedBatch {
+    pub fn new(entries: Vec<(PathBuf, EntryMeta)>) -> Self {
+        let entries = entries.into_par_iter().map(|(path, meta)| PreparedEntry::new(path, meta)).collect();
+        Self { entries }
+    }
+
+    /// Split into `count` batches, each path going to the one `route` picks
+    pub fn split<F: Fn(&Path) -> usize>(self, count: usize, route: F) -> Vec<PreparedBatch> {
+        let mut batches: Vec<PreparedBatch> = (0..count).map(|_| PreparedBatch { entries: Vec::new() }).collect();
+        for entry in self.entries {
+            batches[route(&entry.path)].entries.push(entry);
+        }
+        batches
+    }
+
+    /// Move the entries whose path `pick` accepts into a batch of their own
+    pub fn take_where<F: Fn(&Path) -> bool>(&mut self, pick: F) -> PreparedBatch {
+        let (taken, kept) = std::mem::take(&mut self.entries).into_iter().partition(|entry| pick(&entry.path));
+        self.entries = kept;
+        PreparedBatch { entries: taken }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// What `FileIndex::insert_batch` did with each path of a batch
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BatchResult {
+    pub added: usize,
+    /// Already indexed; any changed size or date was taken
+    pub duplicates: usize,
+    /// Could not be indexed, and logged
+    pub invalid: usize,
+}
+
+/// Inconsistencies found by `FileIndex::check_invariants`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct InvariantReport {
+    /// Inverted-index ids past the end of the pool
+    pub dangling_ids: usize,
+    /// Inverted-index ids whose pool entry has a different name/extension
+    pub mismatched_ids: usize,
+    /// Inverted-index ids pointing at removed or superseded entries
+    pub stale_ids: usize,
+    /// Live entries missing from the filename or extension index
+    pub unindexed_entries: usize,
+    /// `seen_paths` entries that disagree with the pool
+    pub bad_seen_paths: usize,
+}
+
+impl InvariantReport {
+    /// Total number of problems found
+    pub fn issues(&self) -> usize {
+        self.dangling_ids
+            + self.mismatched_ids
+            + self.stale_ids
+            + self.unindexed_entries
+            + self.bad_seen_paths
+    }
+
+    /// True when the index structures fully agree
+    pub fn is_consistent(&self) -> bool {
+        self.issues() == 0
+    }
+}
+
+/// An index rebuilt from the live entries of another, ready to swap in
+/// (see `FileIndex::compaction`)
+pub struct Compaction {
+    /// Generation of the index it was built from
+    generation: u64,
+    /// Tombstones left out
+    removed: usize,
+    rebuilt: FileIndex,
+}
+
+/// Approximate heap bytes held by each part of the index, allocated
+/// capacity included (see `FileIndex::memory_usage`)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MemoryBreakdown {
+    /// Every slot's path, removed ones included
+    pub pool: usize,
+    /// Sizes, dates, folder flags and attributes of every slot
+    pub metadata: usize,
+    pub filename_index: usize,
+    /// Plain and compound extension indexes
+    pub extension_index: usize,
+    pub word_index: usize,
+    pub directory_index: usize,
+    /// Path keys of the live entries
+    pub seen_paths: usize,
+    /// Trigram index and filename acronyms, when enabled
+    pub optional: usize,
+}
+
+impl MemoryBreakdown {
+    pub fn total(&self) -> usize {
+        self.pool
+            + self.metadata
+            + self.filename_index
+            + self.extension_index
+            + self.word_index
+            + self.directory_index
+            + self.seen_paths
+            + self.optional
+    }
+
+    /// Each part with its label, in display order
+    pub fn parts(&self) -> [(&'static str, usize); 8] {
+        [
+            ("Paths", self.pool),
+            ("Path lookup", self.seen_paths),
+            ("Filename index", self.filename_index),
+            ("Extension index", self.extension_index),
+            ("Word index", self.word_index),
+            ("Folder index", self.directory_index),
+            ("Sizes and dates", self.metadata),
+            ("Trigrams and acronyms", self.optional),
+        ]
+    }
+}
+
+impl fmt::Display for MemoryBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "about {}", format_size(self.total() as u64))?;
+        for (label, bytes) in self.parts() {
+            write!(f, ", {} {}", label.to_lowercase(), format_size(bytes as u64))?;
+        }
+        Ok(())
+    }
+}
+
+/// Heap bytes of a lookup map: its buckets (plus a control byte each),
+/// key strings and id lists
+fn map_bytes(map: &AHashMap<String, Vec<u32>>) -> usize {
+    map.capacity() * (std::mem::size_of::<(String, Vec<u32>)>() + 1)
+        + map.iter().map(|(key, ids)| key.capacity() + ids.capacity() * std::mem::size_of::<u32>()).sum::<usize>()
+}
 
 /// Core file indexing data structure with memory-efficient path storage
 #[derive(Serialize, Deserialize)]
@@ -20,332 +1230,5246 @@ pub struct FileIndex {
     /// Serialization version for compatibility checking
     version: u32,
     
-    /// Central storage for all file paths (indexed by u32)
-    pool: Vec<PathBuf>,
+    /// Central storage for all file paths (indexed by u32), each folder
+    /// stored once (see `PathPool`)
+    pool: PathPool,
+    
+    /// File size in bytes of each pool slot (`UNKNOWN_SIZE` if never read)
+    sizes: Vec<u64>,
+    
+    /// Modification time of each pool slot (`UNKNOWN_MTIME` if never read)
+    mtimes: Vec<i64>,
+    
+    /// Whether each pool slot is a folder rather than a file
+    dirs: Vec<bool>,
+    
+    /// `EntryMeta` attribute flags of each pool slot
+    attributes: Vec<u8>,
+    
+    /// Filename to pool indices mapping
+    filename_index: AHashMap<String, Vec<u32>>,
+    
+    /// File extension to pool indices mapping
+    extension_index: AHashMap<String, Vec<u32>>,
+    
+    /// Compound extension (see `compound_extensions`) to pool indices mapping
+    compound_extension_index: AHashMap<String, Vec<u32>>,
+    
+    /// Word of a filename (see `words::words`) to pool indices mapping
+    word_index: AHashMap<String, Vec<u32>>,
+    
+    /// Whether the filename and extension keys were folded without accents
+    accent_free_keys: bool,
+    
+    /// Folder (see `folder_key`) to the live slots directly inside it, for
+    /// `in:` scopes and folder counts; rebuilt after loading rather than stored
+    #[serde(skip)]
+    directory_index: AHashMap<String, Vec<u32>>,
+    
+    /// Runtime-only map from canonical path key to its live pool slot
+    /// (case-insensitive for Windows paths, see `paths::path_key`)
+    #[serde(skip)]
+    seen_paths: PathKeys,
+    
+    /// Distinct filename keys in sorted order for `^` prefix queries; built
+    /// on the first one and dropped whenever a name is added or removed
+    #[serde(skip)]
+    sorted_names: Mutex<Option<Vec<String>>>,
+    
+    /// Newest files first, at most `RECENT_CAPACITY`; built on the first
+    /// `recent` call, then kept current as files are added and modified
+    #[serde(skip)]
+    recent: Mutex<Option<Vec<u32>>>,
+    
+    /// Results of recent searches, served again while the generation holds
+    #[serde(skip)]
+    query_cache: Mutex<QueryCache>,
+    
+    /// Optional trigrams of every filename for faster substring search,
+    /// rebuilt after loading rather than stored (see `set_trigram_index`)
+    #[serde(skip)]
+    trigrams: Option<TrigramIndex>,
+    
+    /// Optional map from filename acronyms (see `words::acronym`) to pool
+    /// slots, rebuilt after loading rather than stored (see
+    /// `set_acronym_matching`)
+    #[serde(skip)]
+    acronyms: Option<AHashMap<String, Vec<u32>>>,
+    
+    /// Whether searches list hidden and system entries (see `set_show_hidden`)
+    #[serde(skip)]
+    show_hidden: bool,
+    
+    /// Cumulative counters persisted with the index
+    lifetime_stats: IndexStats,
+    
+    /// Counters for the current session only (reset on every launch)
+    #[serde(skip)]
+    session_stats: IndexStats,
+    
+    /// Runtime-only counter bumped on every mutation (used for dirty tracking)
+    #[serde(skip)]
+    generation: u64,
+    
+    /// Most pool slots this index may take (see `set_capacity`)
+    #[serde(skip, default = "default_capacity")]
+    capacity: usize,
+}
+
+fn default_capacity() -> usize {
+    MAX_INDEX_SIZE
+}
+
+/// Plain-integer view of index activity counters (also the on-disk form)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatsSnapshot {
+    pub insertions: u64,
+    pub duplicates: u64,
+    pub removals: u64,
+    pub searches: u64,
+    pub watcher_events: u64,
+}
+
+/// Lifetime and session counters reported by `FileIndex::stats`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IndexStatsReport {
+    pub lifetime: StatsSnapshot,
+    pub session: StatsSnapshot,
+    /// Result cache lookups this session
+    pub cache: CacheStats,
+}
+
+/// Kinds of activity tracked by the index counters
+#[derive(Debug, Clone, Copy)]
+enum Counter {
+    Insertion,
+    Duplicate,
+    Removal,
+    Search,
+    WatcherEvent,
+}
+
+/// Thread-safe counters, serialized as a `StatsSnapshot`
+///
+/// Clones share the counts, so the copies of an index a `ShardedIndex`
+/// publishes all add to the same ones.
+#[derive(Default, Clone, Serialize, Deserialize)]
+#[serde(from = "StatsSnapshot", into = "StatsSnapshot")]
+struct IndexStats(Arc<Counters>);
+
+#[derive(Default)]
+struct Counters {
+    insertions: AtomicU64,
+    duplicates: AtomicU64,
+    removals: AtomicU64,
+    searches: AtomicU64,
+    watcher_events: AtomicU64,
+}
+
+impl IndexStats {
+    fn counter(&self, counter: Counter) -> &AtomicU64 {
+        let counts = &self.0;
+        match counter {
+            Counter::Insertion => &counts.insertions,
+            Counter::Duplicate => &counts.duplicates,
+            Counter::Removal => &counts.removals,
+            Counter::Search => &counts.searches,
+            Counter::WatcherEvent => &counts.watcher_events,
+        }
+    }
+    
+    fn snapshot(&self) -> StatsSnapshot {
+        let counts = &self.0;
+        StatsSnapshot {
+            insertions: counts.insertions.load(Ordering::Relaxed),
+            duplicates: counts.duplicates.load(Ordering::Relaxed),
+            removals: counts.removals.load(Ordering::Relaxed),
+            searches: counts.searches.load(Ordering::Relaxed),
+            watcher_events: counts.watcher_events.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl From<StatsSnapshot> for IndexStats {
+    fn from(snapshot: StatsSnapshot) -> Self {
+        Self(Arc::new(Counters {
+            insertions: AtomicU64::new(snapshot.insertions),
+            duplicates: AtomicU64::new(snapshot.duplicates),
+            removals: AtomicU64::new(snapshot.removals),
+            searches: AtomicU64::new(snapshot.searches),
+            watcher_events: AtomicU64::new(snapshot.watcher_events),
+        }))
+    }
+}
+
+impl From<IndexStats> for StatsSnapshot {
+    fn from(stats: IndexStats) -> Self {
+        stats.snapshot()
+    }
+}
+
+/// On-disk layout of version 1 indexes, which had no persisted statistics
+#[derive(Serialize, Deserialize)]
+struct FileIndexV1 {
+    version: u32,
+    pool: Vec<PathBuf>,
+    filename_index: AHashMap<String, Vec<u32>>,
+    extension_index: AHashMap<String, Vec<u32>>,
+}
+
+/// On-disk layout of version 2 indexes, which had no file sizes
+#[derive(Serialize, Deserialize)]
+struct FileIndexV2 {
+    version: u32,
+    pool: Vec<PathBuf>,
+    filename_index: AHashMap<String, Vec<u32>>,
+    extension_index: AHashMap<String, Vec<u32>>,
+    lifetime_stats: IndexStats,
+}
+
+/// On-disk layout of version 3 indexes, which had no modification times
+#[derive(Serialize, Deserialize)]
+struct FileIndexV3 {
+    version: u32,
+    pool: Vec<PathBuf>,
+    sizes: Vec<u64>,
+    filename_index: AHashMap<String, Vec<u32>>,
+    extension_index: AHashMap<String, Vec<u32>>,
+    lifetime_stats: IndexStats,
+}
+
+/// On-disk layout of version 4 indexes, whose keys were only lowercased
+#[derive(Serialize, Deserialize)]
+struct FileIndexV4 {
+    version: u32,
+    pool: Vec<PathBuf>,
+    sizes: Vec<u64>,
+    mtimes: Vec<i64>,
+    filename_index: AHashMap<String, Vec<u32>>,
+    extension_index: AHashMap<String, Vec<u32>>,
+    lifetime_stats: IndexStats,
+}
+
+/// On-disk layout of version 5 indexes, which had no word index
+#[derive(Serialize, Deserialize)]
+struct FileIndexV5 {
+    version: u32,
+    pool: Vec<PathBuf>,
+    sizes: Vec<u64>,
+    mtimes: Vec<i64>,
+    filename_index: AHashMap<String, Vec<u32>>,
+    extension_index: AHashMap<String, Vec<u32>>,
+    accent_free_keys: bool,
+    lifetime_stats: IndexStats,
+}
+
+/// On-disk layout of version 6 indexes, which had no compound extension index
+#[derive(Serialize, Deserialize)]
+struct FileIndexV6 {
+    version: u32,
+    pool: Vec<PathBuf>,
+    sizes: Vec<u64>,
+    mtimes: Vec<i64>,
+    filename_index: AHashMap<String, Vec<u32>>,
+    extension_index: AHashMap<String, Vec<u32>>,
+    word_index: AHashMap<String, Vec<u32>>,
+    accent_free_keys: bool,
+    lifetime_stats: IndexStats,
+}
+
+/// On-disk layout of version 7 indexes, which held only files
+#[derive(Serialize, Deserialize)]
+struct FileIndexV7 {
+    version: u32,
+    pool: Vec<PathBuf>,
+    sizes: Vec<u64>,
+    mtimes: Vec<i64>,
+    filename_index: AHashMap<String, Vec<u32>>,
+    extension_index: AHashMap<String, Vec<u32>>,
+    compound_extension_index: AHashMap<String, Vec<u32>>,
+    word_index: AHashMap<String, Vec<u32>>,
+    accent_free_keys: bool,
+    lifetime_stats: IndexStats,
+}
+
+/// On-disk layout of version 8 indexes, which had no attributes
+#[derive(Serialize, Deserialize)]
+struct FileIndexV8 {
+    version: u32,
+    pool: Vec<PathBuf>,
+    sizes: Vec<u64>,
+    mtimes: Vec<i64>,
+    dirs: Vec<bool>,
+    filename_index: AHashMap<String, Vec<u32>>,
+    extension_index: AHashMap<String, Vec<u32>>,
+    compound_extension_index: AHashMap<String, Vec<u32>>,
+    word_index: AHashMap<String, Vec<u32>>,
+    accent_free_keys: bool,
+    lifetime_stats: IndexStats,
+}
+
+/// On-disk layout of version 9 indexes, which stored every path in full
+#[derive(Serialize, Deserialize)]
+struct FileIndexV9 {
+    version: u32,
+    pool: Vec<PathBuf>,
+    sizes: Vec<u64>,
+    mtimes: Vec<i64>,
+    dirs: Vec<bool>,
+    attributes: Vec<u8>,
+    filename_index: AHashMap<String, Vec<u32>>,
+    extension_index: AHashMap<String, Vec<u32>>,
+    compound_extension_index: AHashMap<String, Vec<u32>>,
+    word_index: AHashMap<String, Vec<u32>>,
+    accent_free_keys: bool,
+    lifetime_stats: IndexStats,
+}
+
+/// Deserialize a version 1 index and upgrade it to the current layout
+pub fn migrate_v1(data: &[u8]) -> Result<FileIndex> {
+    let old: FileIndexV1 = bincode::deserialize(data)?;
+    info!("Migrating index from v{} to v{}", old.version, INDEX_VERSION);
+    
+    let mut index = FileIndex {
+        pool: old.pool.into(),
+        filename_index: old.filename_index,
+        extension_index: old.extension_index,
+        ..FileIndex::default()
+    };
+    // Sizes and dates start unknown and are filled in by the next scan
+    index.rebuild_cache();
+    
+    // Older indexes may carry ids for files removed before tombstones existed
+    let report = index.repair();
+    if !report.is_consistent() {
+        warn!("Repaired {} inconsistencies in migrated index", report.issues());
+    }
+    Ok(index)
+}
+
+/// Deserialize a version 2 index and upgrade it to the current layout
+///
+/// Sizes and dates start unknown and are filled in by the next scan.
+pub fn migrate_v2(data: &[u8]) -> Result<FileIndex> {
+    let old: FileIndexV2 = bincode::deserialize(data)?;
+    info!("Migrating index from v{} to v{}", old.version, INDEX_VERSION);
+    
+    let mut index = FileIndex {
+        pool: old.pool.into(),
+        filename_index: old.filename_index,
+        extension_index: old.extension_index,
+        lifetime_stats: old.lifetime_stats,
+        ..FileIndex::default()
+    };
+    // Only filed slots are live, as removals unfiled theirs; keys before
+    // v5 were only lowercased, so they are refiled folded
+    index.rebuild_cache();
+    index.refile();
+    Ok(index)
+}
+
+/// Deserialize a version 3 index and upgrade it to the current layout
+///
+/// Sizes are kept; dates start unknown and are filled in by the next scan.
+pub fn migrate_v3(data: &[u8]) -> Result<FileIndex> {
+    let old: FileIndexV3 = bincode::deserialize(data)?;
+    info!("Migrating index from v{} to v{}", old.version, INDEX_VERSION);
+    
+    let mut index = FileIndex {
+        pool: old.pool.into(),
+        sizes: old.sizes,
+        filename_index: old.filename_index,
+        extension_index: old.extension_index,
+        lifetime_stats: old.lifetime_stats,
+        ..FileIndex::default()
+    };
+    index.rebuild_cache();
+    index.refile();
+    Ok(index)
+}
+
+/// Deserialize a version 4 index and upgrade it to the current layout
+///
+/// Sizes and dates are kept; the keys are refiled with Unicode folding.
+pub fn migrate_v4(data: &[u8]) -> Result<FileIndex> {
+    let old: FileIndexV4 = bincode::deserialize(data)?;
+    info!("Migrating index from v{} to v{}", old.version, INDEX_VERSION);
+    
+    let mut index = FileIndex {
+        pool: old.pool.into(),
+        sizes: old.sizes,
+        mtimes: old.mtimes,
+        filename_index: old.filename_index,
+        extension_index: old.extension_index,
+        lifetime_stats: old.lifetime_stats,
+        ..FileIndex::default()
+    };
+    index.rebuild_cache();
+    index.refile();
+    Ok(index)
+}
+
+/// Deserialize a version 5 index and upgrade it to the current layout
+///
+/// Sizes and dates are kept; refiling the keys adds the word index.
+pub fn migrate_v5(data: &[u8]) -> Result<FileIndex> {
+    let old: FileIndexV5 = bincode::deserialize(data)?;
+    info!("Migrating index from v{} to v{}", old.version, INDEX_VERSION);
+    
+    let mut index = FileIndex {
+        pool: old.pool.into(),
+        sizes: old.sizes,
+        mtimes: old.mtimes,
+        filename_index: old.filename_index,
+        extension_index: old.extension_index,
+        lifetime_stats: old.lifetime_stats,
+        ..FileIndex::default()
+    };
+    index.rebuild_cache();
+    index.refile();
+    Ok(index)
+}
+
+/// Deserialize a version 6 index and upgrade it to the current layout
+///
+/// Sizes and dates are kept; refiling the keys adds the compound extension index.
+pub fn migrate_v6(data: &[u8]) -> Result<FileIndex> {
+    let old: FileIndexV6 = bincode::deserialize(data)?;
+    info!("Migrating index from v{} to v{}", old.version, INDEX_VERSION);
+    
+    let mut index = FileIndex {
+        pool: old.pool.into(),
+        sizes: old.sizes,
+        mtimes: old.mtimes,
+        filename_index: old.filename_index,
+        extension_index: old.extension_index,
+        lifetime_stats: old.lifetime_stats,
+        ..FileIndex::default()
+    };
+    index.rebuild_cache();
+    index.refile();
+    Ok(index)
+}
+
+/// Deserialize a version 7 index and upgrade it to the current layout
+///
+/// Every entry was a file, so the lookup maps are kept as they are.
+pub fn migrate_v7(data: &[u8]) -> Result<FileIndex> {
+    let old: FileIndexV7 = bincode::deserialize(data)?;
+    info!("Migrating index from v{} to v{}", old.version, INDEX_VERSION);
+    
+    let mut index = FileIndex {
+        pool: old.pool.into(),
+        sizes: old.sizes,
+        mtimes: old.mtimes,
+        filename_index: old.filename_index,
+        extension_index: old.extension_index,
+        compound_extension_index: old.compound_extension_index,
+        word_index: old.word_index,
+        accent_free_keys: old.accent_free_keys,
+        lifetime_stats: old.lifetime_stats,
+        ..FileIndex::default()
+    };
+    index.validate()?;
+    index.rebuild_cache();
+    Ok(index)
+}
+
+/// Deserialize a version 8 index and upgrade it to the current layout
+///
+/// Dot files are flagged hidden again; Windows attributes are read by the
+/// next scan.
+pub fn migrate_v8(data: &[u8]) -> Result<FileIndex> {
+    let old: FileIndexV8 = bincode::deserialize(data)?;
+    info!("Migrating index from v{} to v{}", old.version, INDEX_VERSION);
+    
+    let mut index = FileIndex {
+        pool: old.pool.into(),
+        sizes: old.sizes,
+        mtimes: old.mtimes,
+        dirs: old.dirs,
+        filename_index: old.filename_index,
+        extension_index: old.extension_index,
+        compound_extension_index: old.compound_extension_index,
+        word_index: old.word_index,
+        accent_free_keys: old.accent_free_keys,
+        lifetime_stats: old.lifetime_stats,
+        ..FileIndex::default()
+    };
+    index.validate()?;
+    index.rebuild_cache();
+    Ok(index)
+}
+
+/// Deserialize a version 9 index and upgrade it to the current layout
+pub fn migrate_v9(data: &[u8]) -> Result<FileIndex> {
+    let old: FileIndexV9 = bincode::deserialize(data)?;
+    info!("Migrating index from v{} to v{}", old.version, INDEX_VERSION);
+    
+    let mut index = FileIndex {
+        pool: old.pool.into(),
+        sizes: old.sizes,
+        mtimes: old.mtimes,
+        dirs: old.dirs,
+        attributes: old.attributes,
+        filename_index: old.filename_index,
+        extension_index: old.extension_index,
+        compound_extension_index: old.compound_extension_index,
+        word_index: old.word_index,
+        accent_free_keys: old.accent_free_keys,
+        lifetime_stats: old.lifetime_stats,
+        ..FileIndex::default()
+    };
+    index.validate()?;
+    index.rebuild_cache();
+    Ok(index)
+}
+
+impl Default for FileIndex {
+    fn default() -> Self {
+        Self {
+            version: INDEX_VERSION,
+            pool: PathPool::default(),
+            sizes: Vec::new(),
+            mtimes: Vec::new(),
+            dirs: Vec::new(),
+            attributes: Vec::new(),
+            filename_index: AHashMap::new(),
+            extension_index: AHashMap::new(),
+            compound_extension_index: AHashMap::new(),
+            word_index: AHashMap::new(),
+            accent_free_keys: ignores_accents(),
+            directory_index: AHashMap::new(),
+            seen_paths: PathKeys::default(),
+            sorted_names: Mutex::new(None),
+            recent: Mutex::new(None),
+            query_cache: Mutex::new(QueryCache::default()),
+            trigrams: None,
+            acronyms: None,
+            show_hidden: false,
+            lifetime_stats: IndexStats::default(),
+            session_stats: IndexStats::default(),
+            generation: 0,
+            capacity: MAX_INDEX_SIZE,
+        }
+    }
+}
+
+/// A copy to change while searches go on reading the original (see
+/// `ShardedIndex`)
+///
+/// The counters stay shared. Of the caches only the recent files carry
+/// over; the result cache starts empty, keeping its hit counts, and the
+/// sorted names are rebuilt when next needed.
+impl Clone for FileIndex {
+    fn clone(&self) -> Self {
+        Self {
+            version: self.version,
+            pool: self.pool.clone(),
+            sizes: self.sizes.clone(),
+            mtimes: self.mtimes.clone(),
+            dirs: self.dirs.clone(),
+            attributes: self.attributes.clone(),
+            filename_index: self.filename_index.clone(),
+            extension_index: self.extension_index.clone(),
+            compound_extension_index: self.compound_extension_index.clone(),
+            word_index: self.word_index.clone(),
+            accent_free_keys: self.accent_free_keys,
+            directory_index: self.directory_index.clone(),
+            seen_paths: self.seen_paths.clone(),
+            sorted_names: Mutex::new(None),
+            recent: Mutex::new(self.recent.lock().clone()),
+            query_cache: Mutex::new(self.query_cache.lock().emptied()),
+            trigrams: self.trigrams.clone(),
+            acronyms: self.acronyms.clone(),
+            show_hidden: self.show_hidden,
+            lifetime_stats: self.lifetime_stats.clone(),
+            session_stats: self.session_stats.clone(),
+            generation: self.generation,
+            capacity: self.capacity,
+        }
+    }
+}
+
+impl FileIndex {
+    /// Create a new empty index
+    pub fn new() -> Self {
+        info!("Creating new file index");
+        Self::default()
+    }
+
+    /// Get current index version
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Get the mutation generation; changes whenever the index contents change
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Bring this older copy of `current` up to date by making the changes
+    /// it lacks again with `replay`
+    ///
+    /// They were counted when first made, so the counters are left alone;
+    /// the recent list and cache counters are taken over as a clone would.
+    pub fn catch_up(&mut self, current: &FileIndex, replay: impl FnOnce(&mut FileIndex)) {
+        let lifetime = std::mem::take(&mut self.lifetime_stats);
+        let session = std::mem::take(&mut self.session_stats);
+        replay(self);
+        self.lifetime_stats = lifetime;
+        self.session_stats = session;
+        *self.recent.get_mut() = current.recent.lock().clone();
+        *self.query_cache.get_mut() = current.query_cache.lock().emptied();
+        debug_assert_eq!(self.generation, current.generation, "replayed changes should match");
+    }
+
+    /// Reject a deserialized index whose lookup maps point past the pool,
+    /// whose pool is larger than `MAX_INDEX_SIZE` or names missing folders
+    ///
+    /// Only a damaged or crafted file holds such ids; loading one would
+    /// panic on the first search that reads them.
+    pub fn validate(&self) -> Result<()> {
+        let corrupted = |reason: String| FlashFindError::CorruptedIndex(Box::new(bincode::ErrorKind::Custom(reason)));
+        if self.pool.len() > MAX_INDEX_SIZE {
+            return Err(corrupted(format!("{} entries exceed the limit of {}", self.pool.len(), MAX_INDEX_SIZE)));
+        }
+        if !self.pool.is_valid() {
+            return Err(corrupted("an entry names a folder past the last one".to_string()));
+        }
+        let maps = [&self.filename_index, &self.extension_index, &self.compound_extension_index, &self.word_index];
+        let dangling = maps.iter().flat_map(|map| map.values().flatten()).find(|&&idx| idx as usize >= self.pool.len());
+        if let Some(idx) = dangling {
+            return Err(corrupted(format!("entry id {} is past the last of {} entries", idx, self.pool.len())));
+        }
+        Ok(())
+    }
+
+    /// Rebuild the seen_paths cache from the pool (call after deserialization)
+    ///
+    /// Removed slots were unfiled from the filename index, so only filed
+    /// slots (and nameless ones like drive roots, which are never filed)
+    /// come back; a reload must not resurrect removed entries.
+    pub fn rebuild_cache(&mut self) {
+        let mut filed = vec![false; self.pool.len()];
+        for &idx in self.filename_index.values().flatten() {
+            if let Some(slot) = filed.get_mut(idx as usize) {
+                *slot = true;
+            }
+        }
+        debug!("Rebuilding seen_paths cache from {} paths", self.pool.len());
+        self.sizes.resize(self.pool.len(), UNKNOWN_SIZE);
+        self.mtimes.resize(self.pool.len(), UNKNOWN_MTIME);
+        self.dirs.resize(self.pool.len(), false);
+        let known = self.attributes.len().min(self.pool.len());
+        let missing: Vec<u8> = self.pool.iter().skip(known).map(|path| name_attributes(&path)).collect();
+        self.attributes.truncate(known);
+        self.attributes.extend(missing);
+        self.pool.rebuild();
+        self.seen_paths.clear();
+        self.directory_index.clear();
+        for (idx, path) in self.pool.iter().enumerate() {
+            if filed[idx] || index_keys(&path).0.is_none() {
+                self.seen_paths.insert(&path, idx as u32);
+                if let Some(folder) = folder_key(&path) {
+                    self.directory_index.entry(folder).or_default().push(idx as u32);
+                }
+            }
+        }
+    }
+
+    /// Get total number of indexed files, not counting removed ones
+    /// waiting for compaction
+    pub fn len(&self) -> usize {
+        self.seen_paths.len()
+    }
+
+    /// Check if index is empty
+    pub fn is_empty(&self) -> bool {
+        self.seen_paths.is_empty()
+    }
+    
+    /// Pool slots held, removed entries waiting for compaction included;
+    /// for diagnostics, as `len` is what is indexed
+    pub fn pool_size(&self) -> usize {
+        self.pool.len()
+    }
+
+    /// Hold at most `capacity` pool slots, `MAX_INDEX_SIZE` by default;
+    /// each shard of a `ShardedIndex` takes its share
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+    }
+    
+    /// Deal the live entries out to `count` fresh indexes, each to the one
+    /// `route` picks, keeping their order
+    ///
+    /// The optional indexes and the hidden files setting carry over. The
+    /// lifetime counters go to the first index alone, so their sum stays.
+    pub fn split<F: Fn(&Path) -> usize>(self, count: usize, route: F) -> Vec<FileIndex> {
+        let mut parts: Vec<FileIndex> = (0..count).map(|_| FileIndex::default()).collect();
+        for (idx, path) in self.iter() {
+            let meta = self.meta_of(idx);
+            // Each part can hold everything this index held
+            let _ = parts[route(&path)].insert_with_meta(path, meta);
+        }
+        for part in &mut parts {
+            part.set_trigram_index(self.trigrams.is_some());
+            part.set_acronym_matching(self.acronyms.is_some());
+            part.set_show_hidden(self.show_hidden);
+            part.lifetime_stats = IndexStats::default();
+            part.session_stats = IndexStats::default();
+        }
+        if let Some(first) = parts.first_mut() {
+            first.lifetime_stats = self.lifetime_stats;
+        }
+        parts
+    }
+
+    /// Clear all indexed data
+    pub fn clear(&mut self) {
+        info!("Clearing index with {} files", self.pool.len());
+        self.pool.clear();
+        self.sizes.clear();
+        self.mtimes.clear();
+        self.dirs.clear();
+        self.attributes.clear();
+        self.filename_index.clear();
+        self.extension_index.clear();
+        self.compound_extension_index.clear();
+        self.word_index.clear();
+        self.directory_index.clear();
+        self.seen_paths.clear();
+        *self.sorted_names.get_mut() = None;
+        *self.recent.get_mut() = None;
+        self.query_cache.get_mut().clear();
+        if let Some(trigrams) = &mut self.trigrams {
+            trigrams.clear();
+        }
+        self.generation += 1;
+    }
+    
+    /// Build or drop the trigram index used by substring searches
+    ///
+    /// Off by default: it speeds up filename queries of three or more
+    /// characters on large indexes at the cost of several bytes per
+    /// character of every filename. It lives in memory only, so the app
+    /// calls this again after each load.
+    pub fn set_trigram_index(&mut self, enabled: bool) {
+        if enabled == self.trigrams.is_some() {
+            return;
+        }
+        if enabled {
+            self.trigrams = Some(TrigramIndex::default());
+            self.rebuild_trigrams();
+            info!("Built trigram index for {} files", self.seen_paths.len());
+        } else {
+            self.trigrams = None;
+            info!("Dropped trigram index");
+        }
+    }
+    
+    /// Also match short all-letter filename queries against the initials of
+    /// filename words (`Config::acronym_matching`), listing those matches
+    /// after every name that contains the query
+    pub fn set_acronym_matching(&mut self, enabled: bool) {
+        if enabled == self.acronyms.is_some() {
+            return;
+        }
+        if enabled {
+            let mut acronyms: AHashMap<String, Vec<u32>> = AHashMap::new();
+            let mut live: Vec<u32> = self.seen_paths.slots().collect();
+            live.sort_unstable();
+            for idx in live {
+                if let Some(acronym) = name_acronym(&self.pool.path(idx as usize)) {
+                    acronyms.entry(acronym).or_default().push(idx);
+                }
+            }
+            info!("Built {} filename acronyms", acronyms.len());
+            self.acronyms = Some(acronyms);
+        } else {
+            self.acronyms = None;
+            info!("Dropped filename acronyms");
+        }
+    }
+    
+    /// Whether a folded filename query is also looked up as an acronym
+    fn matches_acronyms(&self, query: &str) -> bool {
+        self.acronyms.is_some()
+            && (2..=MAX_ACRONYM_QUERY).contains(&query.chars().count())
+            && query.chars().all(char::is_alphabetic)
+    }
+    
+    /// Approximate memory held by the trigram index, if it is enabled
+    pub fn trigram_memory(&self) -> Option<usize> {
+        self.trigrams.as_ref().map(TrigramIndex::memory_bytes)
+    }
+    
+    /// Estimate the heap held by the index, part by part
+    ///
+    /// Walks every key, so callers showing it repeatedly should keep the
+    /// result until the generation changes.
+    pub fn memory_usage(&self) -> MemoryBreakdown {
+        MemoryBreakdown {
+            pool: self.pool.heap_bytes(),
+            metadata: self.sizes.capacity() * std::mem::size_of::<u64>()
+                + self.mtimes.capacity() * std::mem::size_of::<i64>()
+                + self.dirs.capacity()
+                + self.attributes.capacity(),
+            filename_index: map_bytes(&self.filename_index),
+            extension_index: map_bytes(&self.extension_index) + map_bytes(&self.compound_extension_index),
+            word_index: map_bytes(&self.word_index),
+            directory_index: map_bytes(&self.directory_index),
+            seen_paths: self.seen_paths.heap_bytes(),
+            optional: self.trigram_memory().unwrap_or(0) + self.acronyms.as_ref().map_or(0, map_bytes),
+        }
+    }
+    
+    /// Refill the trigram index from the live slots, dropping removed ones
+    fn rebuild_trigrams(&mut self) {
+        let Some(trigrams) = &mut self.trigrams else {
+            return;
+        };
+        trigrams.clear();
+        let mut live: Vec<u32> = self.seen_paths.slots().collect();
+        live.sort_unstable();
+        for idx in live {
+            if let (Some(name), _) = index_keys(&self.pool.path(idx as usize)) {
+                trigrams.add(idx, &name);
+            }
+        }
+    }
+
+    /// Compact the index by removing tombstones and rebuilding all structures
+    /// This should be called periodically or when deletion count is high
+    ///
+    /// Holds the index exclusively throughout; the indexer thread builds a
+    /// `compaction` under a shared lock instead and only swaps it in here.
+    pub fn compact(&mut self) -> Result<usize> {
+        let Some(compaction) = self.compaction() else {
+            debug!("Index already compact: {} live entries", self.len());
+            return Ok(0);
+        };
+        // Built from this very state, so it always applies
+        Ok(self.apply_compaction(compaction).unwrap_or_default())
+    }
+    
+    /// Removed entries still holding pool slots until compaction
+    pub fn tombstones(&self) -> usize {
+        self.pool_size() - self.len()
+    }
+    
+    /// Share of pool slots held by removed entries, 0 for an empty pool
+    #[cfg(test)]
+    pub fn dead_ratio(&self) -> f64 {
+        if self.pool.is_empty() {
+            0.0
+        } else {
+            self.tombstones() as f64 / self.pool.len() as f64
+        }
+    }
+    
+    /// Rebuild the live entries into fresh structures off to the side,
+    /// leaving this index untouched; `None` if nothing was removed
+    ///
+    /// Needs only shared access, so searches keep running while it builds;
+    /// `apply_compaction` then swaps the result in.
+    pub fn compaction(&self) -> Option<Compaction> {
+        let removed = self.tombstones();
+        if removed == 0 {
+            return None;
+        }
+        info!("Compacting index: {} -> {} files (removing {} tombstones)", self.pool.len(), self.len(), removed);
+        
+        // Live slots keep their relative order
+        let mut live: Vec<usize> = self.seen_paths.slots().map(|idx| idx as usize).collect();
+        live.sort_unstable();
+        let mut rebuilt = FileIndex {
+            pool: self.pool.select(&live),
+            sizes: live.iter().map(|&idx| self.sizes[idx]).collect(),
+            mtimes: live.iter().map(|&idx| self.mtimes[idx]).collect(),
+            dirs: live.iter().map(|&idx| self.dirs[idx]).collect(),
+            attributes: live.iter().map(|&idx| self.attributes[idx]).collect(),
+            trigrams: self.trigrams.as_ref().map(|_| TrigramIndex::default()),
+            acronyms: self.acronyms.as_ref().map(|_| AHashMap::new()),
+            ..FileIndex::default()
+        };
+        for (idx, path) in rebuilt.pool.iter().enumerate() {
+            rebuilt.seen_paths.insert(&path, idx as u32);
+        }
+        // File every key under its new slot
+        rebuilt.refile();
+        debug_assert!(rebuilt.check_invariants().is_consistent());
+        Some(Compaction { generation: self.generation, removed, rebuilt })
+    }
+    
+    /// Swap in a `compaction` of this index, returning the tombstones it
+    /// dropped
+    ///
+    /// `None`, with nothing changed, if the index changed or its optional
+    /// indexes were switched since the compaction was built; its slots
+    /// would no longer match.
+    pub fn apply_compaction(&mut self, compaction: Compaction) -> Option<usize> {
+        let Compaction { generation, removed, rebuilt } = compaction;
+        if generation != self.generation
+            || rebuilt.trigrams.is_some() != self.trigrams.is_some()
+            || rebuilt.acronyms.is_some() != self.acronyms.is_some()
+        {
+            return None;
+        }
+        
+        self.pool = rebuilt.pool;
+        self.sizes = rebuilt.sizes;
+        self.mtimes = rebuilt.mtimes;
+        self.dirs = rebuilt.dirs;
+        self.attributes = rebuilt.attributes;
+        self.filename_index = rebuilt.filename_index;
+        self.extension_index = rebuilt.extension_index;
+        self.compound_extension_index = rebuilt.compound_extension_index;
+        self.word_index = rebuilt.word_index;
+        self.directory_index = rebuilt.directory_index;
+        self.accent_free_keys = rebuilt.accent_free_keys;
+        self.seen_paths = rebuilt.seen_paths;
+        self.trigrams = rebuilt.trigrams;
+        self.acronyms = rebuilt.acronyms;
+        // Cached slots refer to the old numbering
+        *self.sorted_names.get_mut() = None;
+        *self.recent.get_mut() = None;
+        self.query_cache.get_mut().clear();
+        self.generation += 1;
+        
+        info!("Compaction complete: removed {} tombstones, {} files remain", removed, self.len());
+        Some(removed)
+    }
+
+    /// Every live entry with its pool slot, in pool order
+    ///
+    /// Pool order is insertion order until compaction, which keeps the
+    /// surviving entries in their order. Removed entries are skipped, and
+    /// each path is rebuilt from the pool as it is yielded.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, PathBuf)> + '_ {
+        self.pool.iter().enumerate().filter_map(|(idx, path)| self.live_entry(idx, path))
+    }
+
+    /// `iter` for rayon consumers; order is not kept
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = (u32, PathBuf)> + '_ {
+        (0..self.pool.len()).into_par_iter().filter_map(|idx| self.live_entry(idx, self.pool.path(idx)))
+    }
+
+    /// Live entries in pool slots from `start` on, with their metadata;
+    /// with `start` taken from `pool_size`, the entries inserted since
+    pub fn entries_since(&self, start: usize) -> Vec<(PathBuf, EntryMeta)> {
+        (start..self.pool.len())
+            .filter_map(|idx| self.live_entry(idx, self.pool.path(idx)))
+            .map(|(idx, path)| (path, self.meta_of(idx)))
+            .collect()
+    }
+
+    fn live_entry(&self, idx: usize, path: PathBuf) -> Option<(u32, PathBuf)> {
+        let idx = idx as u32;
+        (self.seen_paths.get(&path) == Some(idx)).then_some((idx, path))
+    }
+
+    /// Check whether a pool slot holds a live (not removed or superseded) entry
+    fn is_live(&self, idx: u32) -> bool {
+        self.pool
+            .get(idx as usize)
+            .is_some_and(|path| self.seen_paths.get(&path) == Some(idx))
+    }
+
+    /// List hidden and system entries in results (`Config::show_hidden_files`)
+    ///
+    /// Entries are indexed either way and only results are filtered, so
+    /// changing this needs no rescan.
+    pub fn set_show_hidden(&mut self, show: bool) {
+        if self.show_hidden != show {
+            self.show_hidden = show;
+            *self.recent.get_mut() = None;
+        }
+    }
+
+    /// Whether a pool slot may be listed under the hidden files setting
+    fn is_shown(&self, idx: u32) -> bool {
+        self.show_hidden || !self.meta_of(idx).is_hidden()
+    }
+
+    /// Pick up to `count` live entries spread evenly across the pool
+    ///
+    /// `seed` shifts the starting slot so repeated samples cover different
+    /// entries. Costs O(`count`) path checks, not a full pass.
+    pub fn sample(&self, count: usize, seed: u64) -> Vec<PathBuf> {
+        if count == 0 || self.pool.is_empty() {
+            return Vec::new();
+        }
+        
+        let step = (self.pool.len() / count).max(1);
+        let start = (seed % step as u64) as usize;
+        (start..self.pool.len())
+            .step_by(step)
+            .filter(|&idx| self.is_live(idx as u32))
+            .take(count)
+            .map(|idx| self.pool.path(idx))
+            .collect()
+    }
+
+    /// Every live entry inside `root`, in no particular order
+    pub fn paths_within(&self, root: &Path) -> Vec<PathBuf> {
+        self.par_iter()
+            .map(|(_, path)| path)
+            .filter(|path| is_within(path, root))
+            .collect()
+    }
+
+    /// Every live file whose extension is one of `extensions` (lowercase,
+    /// without the dot), in no particular order
+    pub fn files_with_extensions(&self, extensions: &[&str]) -> Vec<PathBuf> {
+        extensions
+            .iter()
+            .filter_map(|ext| self.extension_index.get(*ext))
+            .flatten()
+            .filter(|&&idx| self.is_live(idx) && !self.dirs[idx as usize])
+            .map(|&idx| self.pool.path(idx as usize))
+            .collect()
+    }
+
+    /// Verify that the inverted indexes, `seen_paths` and the pool agree
+    ///
+    /// Every id in the filename and extension indexes must refer to a live
+    /// pool entry with that name/extension, and every live entry must be
+    /// reachable from both indexes. Tombstoned pool slots are allowed.
+    pub fn check_invariants(&self) -> InvariantReport {
+        let mut report = InvariantReport::default();
+        
+        for (key, idx) in self.seen_paths.iter() {
+            let Some(path) = self.pool.get(idx as usize).filter(|p| self.seen_paths.key(p).as_ref() == Some(key)) else {
+                report.bad_seen_paths += 1;
+                continue;
+            };
+            
+            let (name, ext) = index_keys(&path);
+            let filed = |map: &AHashMap<String, Vec<u32>>, key: Option<&str>| {
+                key.is_none_or(|k| map.get(k).is_some_and(|ids| ids.contains(&idx)))
+            };
+            let compounds_filed = name
+                .as_deref()
+                .is_none_or(|name| compound_extensions(name).all(|c| filed(&self.compound_extension_index, Some(c))));
+            let folder_filed = filed(&self.directory_index, folder_key(&path).as_deref());
+            if !filed(&self.filename_index, name.as_deref()) || !filed(&self.extension_index, ext.as_deref()) || !compounds_filed || !folder_filed {
+                report.unindexed_entries += 1;
+            }
+        }
+        
+        let mut check_ids = |map: &AHashMap<String, Vec<u32>>, filed_under: fn(&Path, &str) -> bool| {
+            for (key, ids) in map {
+                for &idx in ids {
+                    match self.pool.get(idx as usize) {
+                        None => report.dangling_ids += 1,
+                        Some(path) => {
+                            if !filed_under(&path, key) {
+                                report.mismatched_ids += 1;
+                            } else if !self.is_live(idx) {
+                                report.stale_ids += 1;
+                            }
+                        }
+                    }
+                }
+            }
+        };
+        check_ids(&self.filename_index, |p, key| index_keys(p).0.as_deref() == Some(key));
+        check_ids(&self.extension_index, |p, key| index_keys(p).1.as_deref() == Some(key));
+        check_ids(&self.compound_extension_index, |p, key| {
+            index_keys(p).0.is_some_and(|name| compound_extensions(&name).any(|c| c == key))
+        });
+        check_ids(&self.directory_index, |p, key| folder_key(p).as_deref() == Some(key));
+        
+        report
+    }
+
+    /// Check invariants and rebuild the lookup structures if any are broken
+    /// Returns the problems found before repairing
+    pub fn repair(&mut self) -> InvariantReport {
+        let report = self.check_invariants();
+        if report.is_consistent() {
+            return report;
+        }
+        
+        warn!("Repairing index: {:?}", report);
+        
+        let pool = &self.pool;
+        self.seen_paths.retain_consistent(|idx| pool.get(idx as usize));
+        
+        self.refile();
+        
+        report
+    }
+    
+    /// Refile every entry when its keys were folded with a different
+    /// accent setting than `fold::ignores_accents` now holds
+    pub fn sync_key_folding(&mut self) {
+        if self.accent_free_keys == ignores_accents() {
+            return;
+        }
+        info!("Refiling {} files with accents {}", self.seen_paths.len(), if ignores_accents() { "ignored" } else { "matched" });
+        self.refile();
+    }
+    
+    /// Rebuild the filename, extension, word, folder and trigram indexes
+    /// from the live slots
+    fn refile(&mut self) {
+        self.filename_index.clear();
+        self.extension_index.clear();
+        self.compound_extension_index.clear();
+        self.word_index.clear();
+        self.directory_index.clear();
+        *self.sorted_names.get_mut() = None;
+        // Compaction renumbers the slots it lists
+        *self.recent.get_mut() = None;
+        self.query_cache.get_mut().clear();
+        if let Some(trigrams) = &mut self.trigrams {
+            trigrams.clear();
+        }
+        if let Some(acronyms) = &mut self.acronyms {
+            acronyms.clear();
+        }
+        self.accent_free_keys = ignores_accents();
+        let mut live: Vec<u32> = self.seen_paths.slots().collect();
+        live.sort_unstable();
+        for idx in live {
+            self.link(idx);
+        }
+        self.generation += 1;
+    }
+
+    /// File a pool slot under its filename, extension, word and folder keys
+    /// (and trigrams and acronym)
+    fn link(&mut self, idx: u32) {
+        let keys = EntryKeys::of(&self.pool.path(idx as usize));
+        self.file_under(idx, keys);
+    }
+    
+    /// File a pool slot under keys computed from its path
+    fn file_under(&mut self, idx: u32, keys: EntryKeys) {
+        let EntryKeys { name, ext, words, acronym, folder } = keys;
+        if let (Some(acronyms), Some(acronym)) = (&mut self.acronyms, acronym) {
+            acronyms.entry(acronym).or_default().push(idx);
+        }
+        if let Some(name) = name {
+            if let Some(trigrams) = &mut self.trigrams {
+                trigrams.add(idx, &name);
+            }
+            for compound in compound_extensions(&name) {
+                self.compound_extension_index.entry(compound.to_string()).or_default().push(idx);
+            }
+            if !self.filename_index.contains_key(&name) {
+                *self.sorted_names.get_mut() = None;
+            }
+            self.filename_index.entry(name).or_default().push(idx);
+        }
+        if let Some(ext) = ext {
+            self.extension_index.entry(ext).or_default().push(idx);
+        }
+        for word in words {
+            self.word_index.entry(word).or_default().push(idx);
+        }
+        if let Some(folder) = folder {
+            self.directory_index.entry(folder).or_default().push(idx);
+        }
+    }
+
+    /// Drop a pool slot from the filename, extension, word, folder and
+    /// acronym indexes
+    ///
+    /// The trigram index keeps the slot until compaction; searches verify
+    /// its candidates against the filename index.
+    fn unlink(&mut self, idx: u32) {
+        let (name, ext) = index_keys(&self.pool.path(idx as usize));
+        let compounds: Vec<String> = name.iter().flat_map(|name| compound_extensions(name)).map(String::from).collect();
+        let words = name_words(&self.pool.path(idx as usize));
+        if let (Some(acronyms), Some(acronym)) = (&mut self.acronyms, name_acronym(&self.pool.path(idx as usize))) {
+            if let Some(ids) = acronyms.get_mut(&acronym) {
+                ids.retain(|&id| id != idx);
+                if ids.is_empty() {
+                    acronyms.remove(&acronym);
+                }
+            }
+        }
+        let filed = [
+            (&mut self.filename_index, Vec::from_iter(name.clone())),
+            (&mut self.extension_index, Vec::from_iter(ext)),
+            (&mut self.compound_extension_index, compounds),
+            (&mut self.word_index, words),
+            (&mut self.directory_index, Vec::from_iter(folder_key(&self.pool.path(idx as usize)))),
+        ];
+        for (map, keys) in filed {
+            for key in keys {
+                if let Some(ids) = map.get_mut(&key) {
+                    ids.retain(|&id| id != idx);
+                    if ids.is_empty() {
+                        map.remove(&key);
+                    }
+                }
+            }
+        }
+        // Sorted names only go stale when the last file of a name leaves
+        if name.is_some_and(|name| !self.filename_index.contains_key(&name)) {
+            *self.sorted_names.get_mut() = None;
+        }
+        self.unlist_recent(idx);
+    }
+
+    /// Move slot `idx` to its place in the recent files after it was added
+    /// or its date changed, without a pass over the pool
+    fn relist_recent(&mut self, idx: u32) {
+        let mtimes = &self.mtimes;
+        let shown = self.is_shown(idx);
+        let Some(recent) = self.recent.get_mut() else {
+            return;
+        };
+        let was_full = recent.len() >= RECENT_CAPACITY;
+        let listed = recent.iter().position(|&id| id == idx);
+        if let Some(pos) = listed {
+            recent.remove(pos);
+        }
+        let eligible = !self.dirs[idx as usize] && mtimes[idx as usize] != UNKNOWN_MTIME && shown;
+        let pos = recent.partition_point(|&id| by_recency(mtimes, id, idx).is_lt());
+        
+        if eligible && (pos < recent.len() || !was_full) {
+            recent.insert(pos, idx);
+            recent.truncate(RECENT_CAPACITY);
+        } else if listed.is_some() && was_full {
+            // A file outside the list may now belong in the freed place
+            *self.recent.get_mut() = None;
+        }
+    }
+
+    /// Drop slot `idx` from the recent files once it leaves the index
+    fn unlist_recent(&mut self, idx: u32) {
+        let Some(recent) = self.recent.get_mut() else {
+            return;
+        };
+        if let Some(pos) = recent.iter().position(|&id| id == idx) {
+            if recent.len() >= RECENT_CAPACITY {
+                // The next newest file was never listed; find it on the next call
+                *self.recent.get_mut() = None;
+            } else {
+                recent.remove(pos);
+            }
+        }
+    }
+
+    /// The `limit` most recently modified files, newest first
+    ///
+    /// At most `RECENT_CAPACITY`; folders and files with unknown dates are
+    /// left out. The first call picks them from every entry; later ones
+    /// read a list that inserts and modify events keep in order.
+    pub fn recent(&self, limit: usize) -> Vec<SearchResult> {
+        let mut recent = self.recent.lock();
+        let ids = recent.get_or_insert_with(|| {
+            let mut ids: Vec<u32> = self
+                .seen_paths
+                .slots()
+                .filter(|&idx| !self.dirs[idx as usize] && self.mtimes[idx as usize] != UNKNOWN_MTIME && self.is_shown(idx))
+                .collect();
+            let order = |a: &u32, b: &u32| by_recency(&self.mtimes, *a, *b);
+            if ids.len() > RECENT_CAPACITY {
+                ids.select_nth_unstable_by(RECENT_CAPACITY, order);
+                ids.truncate(RECENT_CAPACITY);
+            }
+            ids.sort_unstable_by(order);
+            ids
+        });
+        ids.iter()
+            .take(limit)
+            .map(|&idx| SearchResult::new(self.pool.path(idx as usize), self.meta_of(idx)))
+            .collect()
+    }
+
+    /// Files sharing a filename, as groups of pool slots of at least
+    /// `min_count` (and never fewer than two), largest first
+    ///
+    /// Names compare as the filename index folds them, and folders are left
+    /// out. With `verify_size`, each group is split by stored size and files
+    /// of unknown size dropped. A content hash could split the groups again
+    /// the same way through `split_groups`. Slots are only valid until the
+    /// index next changes; describe them with `entries` under the same lock.
+    pub fn duplicate_groups(&self, min_count: usize, verify_size: bool) -> Vec<Vec<u32>> {
+        let min_count = min_count.max(2);
+        let mut groups: Vec<Vec<u32>> = self
+            .filename_index
+            .par_iter()
+            .filter(|(_, ids)| ids.len() >= min_count)
+            .map(|(_, ids)| ids.iter().copied().filter(|&idx| !self.dirs[idx as usize] && self.is_shown(idx)).collect::<Vec<u32>>())
+            .filter(|ids| ids.len() >= min_count)
+            .collect();
+        if verify_size {
+            groups = split_groups(groups, min_count, |idx| Some(self.sizes[idx as usize]).filter(|&size| size != UNKNOWN_SIZE));
+        }
+        groups.par_sort_unstable_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        groups
+    }
+
+    /// Pool slots with their paths, sizes and dates, as `duplicate_groups`
+    /// returns them
+    pub fn entries(&self, ids: &[u32]) -> Vec<SearchResult> {
+        ids.iter().map(|&idx| SearchResult::new(self.pool.path(idx as usize), self.meta_of(idx))).collect()
+    }
+
+    /// Files per extension (folded, without the dot), most common first and
+    /// ties by name; folders are not counted
+    ///
+    /// Reads the extension index without touching paths, but callers
+    /// showing it repeatedly should keep the result until the generation
+    /// changes.
+    pub fn extension_counts(&self) -> Vec<(String, usize)> {
+        let mut counts: Vec<(String, usize)> = self
+            .extension_index
+            .par_iter()
+            .map(|(ext, ids)| (ext.clone(), ids.iter().filter(|&&idx| !self.dirs[idx as usize]).count()))
+            .filter(|(_, files)| *files > 0)
+            .collect();
+        counts.par_sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts
+    }
+    
+    /// Files with any of `extensions`, compound ones like `tar.gz` included,
+    /// as a `kind:` group of them matches
+    pub fn count_with_extensions(&self, extensions: &[String]) -> usize {
+        self.extension_ids(extensions.iter().map(String::as_str))
+            .into_iter()
+            .filter(|&idx| !self.dirs[idx as usize])
+            .count()
+    }
+    
+    /// Each distinct lowercased filename with its number of indexed files
+    pub fn filename_counts(&self) -> impl Iterator<Item = (&str, usize)> + '_ {
+        self.filename_index.iter().map(|(name, ids)| (name.as_str(), ids.len()))
+    }
+
+    /// Get lifetime (persisted) and session activity counters
+    /// Counters describe activity, so clearing the index does not reset them
+    pub fn stats(&self) -> IndexStatsReport {
+        IndexStatsReport {
+            lifetime: self.lifetime_stats.snapshot(),
+            session: self.session_stats.snapshot(),
+            cache: self.query_cache.lock().stats(),
+        }
+    }
+
+    /// Count a filesystem event delivered by the watcher
+    pub fn record_watcher_event(&self) {
+        self.record(Counter::WatcherEvent);
+    }
+
+    /// Bump a counter in both the lifetime and session statistics
+    fn record(&self, counter: Counter) {
+        self.lifetime_stats.counter(counter).fetch_add(1, Ordering::Relaxed);
+        self.session_stats.counter(counter).fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Insert a file path into the index, its size and date unknown
+    /// Returns Ok(true) if inserted, Ok(false) if duplicate, Err on failure
+    pub fn insert(&mut self, path: PathBuf) -> Result<bool> {
+        self.insert_with_meta(path, EntryMeta::UNKNOWN)
+    }
+
+    /// Insert a file path with its size and modification time
+    ///
+    /// A duplicate keeps its slot but takes any known metadata that changed,
+    /// so a rescan or a modify event refreshes it.
+    pub fn insert_with_meta(&mut self, path: PathBuf, meta: EntryMeta) -> Result<bool> {
+        self.insert_prepared(PreparedEntry::new(path, meta))
+    }
+    
+    /// Insert every path of a batch prepared without holding the index,
+    /// counting what was added, already indexed or could not be indexed
+    ///
+    /// Paths the index cannot hold are skipped; any other error (a full
+    /// index) stops the batch, keeping the paths inserted before it.
+    pub fn insert_batch(&mut self, batch: PreparedBatch) -> Result<BatchResult> {
+        let mut result = BatchResult::default();
+        for entry in batch.entries {
+            // Only kept to report a path that cannot be indexed
+            let invalid = entry.keys.is_none().then(|| entry.path.clone());
+            match self.insert_prepared(entry) {
+                Ok(true) => result.added += 1,
+                Ok(false) => result.duplicates += 1,
+                Err(e @ FlashFindError::InvalidPath(_)) => {
+                    if let Some(path) = invalid {
+                        warn!("Failed to insert {}: {}", redact(&path), e);
+                    }
+                    result.invalid += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(result)
+    }
+    
+    fn insert_prepared(&mut self, entry: PreparedEntry) -> Result<bool> {
+        // Check capacity limit
+        let next = next_slot(self.pool.len(), self.capacity)?;
+        let PreparedEntry { path, meta, key, keys } = entry;
+
+        // Check for duplicates (including the same file spelled with different case)
+        if let Some(idx) = self.seen_paths.get_key(&key) {
+            let idx = idx as usize;
+            let mut changed = false;
+            if meta.size != UNKNOWN_SIZE && self.sizes[idx] != meta.size {
+                self.sizes[idx] = meta.size;
+                changed = true;
+            }
+            if meta.modified != UNKNOWN_MTIME && self.mtimes[idx] != meta.modified {
+                self.mtimes[idx] = meta.modified;
+                self.relist_recent(idx as u32);
+                changed = true;
+            }
+            // Attributes are read along with the date; unread ones stay as they were
+            if meta.modified != UNKNOWN_MTIME && self.attributes[idx] != meta.attributes {
+                self.attributes[idx] = meta.attributes;
+                changed = true;
+            }
+            if changed {
+                self.generation += 1;
+            }
+            self.record(Counter::Duplicate);
+            return Ok(false);
+        }
+
+        // Filename is required for the filename index, and the pool stores text
+        let Some(keys) = keys else {
+            return Err(FlashFindError::InvalidPath(path.display().to_string()));
+        };
+
+        let idx = next;
+
+        // Update tracking structures, then file under filename and extension
+        self.seen_paths.insert_key(&key, idx);
+        self.attributes.push(meta.attributes);
+        self.pool.push(&path);
+        self.sizes.push(meta.size);
+        self.mtimes.push(meta.modified);
+        self.dirs.push(meta.is_dir);
+        self.file_under(idx, keys);
+        self.relist_recent(idx);
+        self.record(Counter::Insertion);
+        self.generation += 1;
+
+        debug!("Inserted file #{}: {}", idx, redact(&path));
+        Ok(true)
+    }
+
+    /// Remove a file path from the index
+    pub fn remove(&mut self, path: &Path) -> Result<bool> {
+        let Some(idx) = self.seen_paths.remove(path) else {
+            return Ok(false); // Not found
+        };
+        
+        // The pool slot stays as a tombstone until compaction, but searches
+        // must never see it, so unfile it from the inverted indexes now
+        self.unlink(idx);
+        self.record(Counter::Removal);
+        self.generation += 1;
+
+        debug!("Removed path: {}", redact(&path));
+        Ok(true)
+    }
+
+    /// Move the entry at `old` to `new`, keeping its pool slot, size, date
+    /// and kind; false if `old` is not indexed
+    ///
+    /// Slot ids stay valid, so anything holding them still names the file.
+    /// An entry already at `new` is replaced, as the file system replaces
+    /// the file. A folder takes the entries inside it along.
+    pub fn rename(&mut self, old: &Path, new: PathBuf) -> Result<bool> {
+        let Some(idx) = self.seen_paths.get(old) else {
+            return Ok(false);
+        };
+        let Some(new_text) = new.to_str().filter(|_| new.file_name().is_some()) else {
+            return Err(FlashFindError::InvalidPath(new.display().to_string()));
+        };
+        
+        let old = self.pool.path(idx as usize);
+        let moved = if self.dirs[idx as usize] { self.entries_inside(&old, new_text) } else { Vec::new() };
+        
+        self.relocate(idx, &new);
+        for (child, path) in &moved {
+            self.relocate(*child, path);
+        }
+        self.generation += 1;
+        
+        debug!("Renamed #{} ({} entries inside): {} -> {}", idx, moved.len(), redact(&old), redact(&new));
+        Ok(true)
+    }
+    
+    /// Move the entries inside folder `old` to the same places inside `new`,
+    /// as renaming the folder does, returning how many moved
+    ///
+    /// For a folder whose own entry is held elsewhere, such as another shard
+    /// of a `ShardedIndex`.
+    pub fn move_contents(&mut self, old: &Path, new: &Path) -> Result<usize> {
+        let Some(new_text) = new.to_str().filter(|_| new.file_name().is_some()) else {
+            return Err(FlashFindError::InvalidPath(new.display().to_string()));
+        };
+        let moved = self.entries_inside(old, new_text);
+        for (child, path) in &moved {
+            self.relocate(*child, path);
+        }
+        if !moved.is_empty() {
+            self.generation += 1;
+        }
+        Ok(moved.len())
+    }
+    
+    /// Slots inside folder `old`, found through the folder keys, with the
+    /// paths they take once it is renamed to `new_text`
+    fn entries_inside(&self, old: &Path, new_text: &str) -> Vec<(u32, PathBuf)> {
+        let old_text = old.to_string_lossy();
+        let key = normalize_for_prefix(&old_text);
+        let inside = self
+            .directory_index
+            .iter()
+            .filter(|(folder, _)| folder.strip_prefix(key.as_str()).is_some_and(|rest| rest.is_empty() || rest.starts_with('\\')))
+            .flat_map(|(_, ids)| ids.iter().copied());
+        let mut moved = Vec::new();
+        for child in inside {
+            let path = self.pool.path(child as usize);
+            let text = path.to_string_lossy();
+            let Some(head) = text.get(..old_text.len()) else {
+                continue;
+            };
+            let same = if is_windows_style(&text) { head.eq_ignore_ascii_case(&old_text) } else { head == old_text };
+            let rest = &text[old_text.len()..];
+            if same && rest.starts_with(['/', '\\']) {
+                moved.push((child, PathBuf::from(format!("{}{}", new_text, rest))));
+            }
+        }
+        moved
+    }
+    
+    /// Point slot `idx` at `new` and refile it under the new keys, first
+    /// removing any other live entry at `new`
+    fn relocate(&mut self, idx: u32, new: &Path) {
+        if let Some(existing) = self.seen_paths.get(new).filter(|&existing| existing != idx) {
+            self.seen_paths.remove(new);
+            self.unlink(existing);
+            self.record(Counter::Removal);
+        }
+        
+        let old = self.pool.path(idx as usize);
+        self.unlink(idx);
+        self.seen_paths.remove(&old);
+        self.pool.set(idx as usize, new);
+        self.seen_paths.insert(new, idx);
+        
+        self.attributes[idx as usize] = self.meta_of(idx).renamed(&old, new).attributes;
+        self.file_under(idx, EntryKeys::of(new));
+        self.relist_recent(idx);
+    }
+
+    /// Check whether `path` is indexed as a folder
+    pub fn is_directory(&self, path: &Path) -> bool {
+        self.seen_paths.get(path).is_some_and(|idx| self.dirs[idx as usize])
+    }
+
+    /// Remove every indexed folder, keeping the files inside them
+    pub fn remove_directories(&mut self) -> usize {
+        let dirs = std::mem::take(&mut self.dirs);
+        let removed = self.remove_slots_where(|idx, _| dirs[idx as usize]);
+        self.dirs = dirs;
+        removed
+    }
+
+    /// Remove every live file (not folder) whose path matches the predicate
+    pub fn remove_files_where<F: Fn(&Path) -> bool>(&mut self, predicate: F) -> usize {
+        let dirs = std::mem::take(&mut self.dirs);
+        let removed = self.remove_slots_where(|idx, path| !dirs[idx as usize] && predicate(path));
+        self.dirs = dirs;
+        removed
+    }
+    
+    /// Remove every live path matching the predicate
+    /// Returns the number of entries removed (pool slots become tombstones)
+    pub fn remove_where<F: Fn(&Path) -> bool>(&mut self, predicate: F) -> usize {
+        self.remove_slots_where(|_, path| predicate(path))
+    }
+
+    fn remove_slots_where<F: Fn(u32, &Path) -> bool>(&mut self, predicate: F) -> usize {
+        let pool = &self.pool;
+        let mut removed_ids = Vec::new();
+        self.seen_paths.retain(|idx| {
+            let keep = !predicate(idx, &pool.path(idx as usize));
+            if !keep {
+                removed_ids.push(idx);
+            }
+            keep
+        });
+        self.unlink_removed(removed_ids)
+    }
+    
+    /// Remove every live entry on drive `letter`, returning how many
+    ///
+    /// The folders on the drive come from the directory index, so only the
+    /// drive's own entries are visited; entries on other drives keep their
+    /// slots.
+    pub fn remove_drive(&mut self, letter: char) -> usize {
+        let letter = letter.to_ascii_uppercase();
+        let slots: Vec<u32> = self
+            .directory_index
+            .iter()
+            .filter(|(folder, _)| drive_letter(folder) == Some(letter))
+            .flat_map(|(_, ids)| ids.iter().copied())
+            .collect();
+        for &idx in &slots {
+            self.seen_paths.remove(&self.pool.path(idx as usize));
+        }
+        let removed = self.unlink_removed(slots);
+        info!("Removed {} entries on drive {}:", removed, letter);
+        removed
+    }
+    
+    /// Unfile slots already dropped from `seen_paths` and count them as removals
+    fn unlink_removed(&mut self, removed_ids: Vec<u32>) -> usize {
+        for &idx in &removed_ids {
+            self.unlink(idx);
+        }
+        let removed = removed_ids.len();
+        
+        if removed > 0 {
+            self.lifetime_stats.counter(Counter::Removal).fetch_add(removed as u64, Ordering::Relaxed);
+            self.session_stats.counter(Counter::Removal).fetch_add(removed as u64, Ordering::Relaxed);
+            self.generation += 1;
+            debug!("Removed {} paths", removed);
+        }
+        removed
+    }
+
+    /// List live entries whose full path starts with a path-like query
+    ///
+    /// A bare drive (`c:`) lists the whole drive. Results are sorted by path
+    /// and paged so huge folders stay responsive. Fails if the query names a
+    /// drive with nothing indexed on it.
+    pub fn search_path_prefix(&self, query: &str, offset: usize, limit: usize) -> Result<PathPage> {
+        self.record(Counter::Search);
+        
+        let mut prefix = normalize_for_prefix(query.trim());
+        if prefix.len() == 2 && drive_letter(&prefix).is_some() {
+            prefix.push('\\');
+        }
+        
+        let page = self.browse(&prefix, offset, limit);
+        
+        if page.total == 0 {
+            if let Some(letter) = drive_letter(&prefix) {
+                if !self.has_drive(letter) {
+                    return Err(FlashFindError::DriveNotIndexed(letter));
+                }
+            }
+        }
+        
+        debug!("Path query '{}' matched {} entries", logged_query(query), page.total);
+        Ok(page)
+    }
+    
+    /// List every live entry, sorted by path and paged (the `*` query)
+    pub fn browse_all(&self, offset: usize, limit: usize) -> PathPage {
+        self.record(Counter::Search);
+        self.browse("", offset, limit)
+    }
+    
+    /// Page through live entries whose normalized path starts with `prefix`
+    fn browse(&self, prefix: &str, offset: usize, limit: usize) -> PathPage {
+        let mut matches: Vec<(String, PathBuf)> = self
+            .seen_paths
+            .par_slots()
+            .filter(|&idx| self.is_shown(idx))
+            .map(|idx| self.pool.path(idx as usize))
+            .filter_map(|path| {
+                let key = normalize_for_prefix(&path.to_string_lossy());
+                key.starts_with(prefix).then_some((key, path))
+            })
+            .collect();
+        
+        matches.par_sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        
+        let total = matches.len();
+        let paths = matches
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|(_, path)| path)
+            .collect();
+        
+        PathPage { paths, total }
+    }
+    
+    /// Check whether any live entry is on the given drive letter
+    pub fn has_drive(&self, letter: char) -> bool {
+        let letter = letter.to_ascii_uppercase();
+        self.directory_index.keys().any(|folder| drive_letter(folder) == Some(letter))
+    }
+    
+    /// Number of live entries on each drive, by letter; paths without a
+    /// drive letter are not counted
+    pub fn drive_counts(&self) -> Vec<(char, usize)> {
+        let mut counts: Vec<(char, usize)> = Vec::new();
+        for (folder, files) in self.folder_counts() {
+            let Some(letter) = drive_letter(folder) else {
+                continue;
+            };
+            match counts.iter_mut().find(|(known, _)| *known == letter) {
+                Some((_, total)) => *total += files,
+                None => counts.push((letter, files)),
+            }
+        }
+        counts.sort_unstable();
+        counts
+    }
+
+    /// Search for files matching the query
+    /// - Queries starting with '.' perform O(1) extension lookup
+    /// - Other queries perform parallel substring search across filenames
+    /// - Empty, hint-only and browse queries (see `parse_query`) return nothing
+    ///
+    /// Unbounded; the app goes through `search_limited`.
+    #[cfg(test)]
+    pub fn search(&self, query: &str) -> Vec<PathBuf> {
+        self.search_limited(query, usize::MAX, SortOrder::Name).paths
+    }
+
+    /// Pair each path with its stored size and date; paths the index does
+    /// not hold get unknown ones
+    #[cfg(test)]
+    pub fn describe(&self, paths: impl IntoIterator<Item = PathBuf>) -> Vec<SearchResult> {
+        paths
+            .into_iter()
+            .map(|path| {
+                let meta = self.metadata_for(&path).unwrap_or(EntryMeta::UNKNOWN);
+                SearchResult::new(path, meta)
+            })
+            .collect()
+    }
+
+    /// Size, date and kind stored when `path` was indexed, or `None` if it
+    /// is not indexed
+    pub fn metadata_for(&self, path: &Path) -> Option<EntryMeta> {
+        self.seen_paths.get(path).map(|idx| self.meta_of(idx))
+    }
+
+    /// Search, returning at most `limit` results in `sort` order
+    ///
+    /// By name, filename matches are gathered best tier first (exact, prefix,
+    /// token, substring), so hitting the cap drops the weakest matches.
+    /// Memory per search is bounded by `limit` ids and paths plus one
+    /// reference per matching distinct filename. By date or size, every
+    /// matching id is gathered and the cap keeps the newest or largest, so
+    /// memory grows by 4 bytes per match instead.
+    ///
+    /// The last `QUERY_CACHE_CAPACITY` result sets are kept, so repeating a
+    /// query the index has not changed since returns without a scan.
+    ///
+    /// The app goes through `search_refining`.
+    #[cfg(test)]
+    pub fn search_limited(&self, query: &str, limit: usize, sort: SortOrder) -> SearchResults {
+        self.search_with_cache(query, limit, sort, None, &SearchStop::new(&AtomicBool::new(false), None))
+            .expect("an uncancelled search cannot fail")
+    }
+
+    /// `search_limited` for a query typed a character at a time
+    ///
+    /// When the filename query contains the previous one (the user typed
+    /// more), only the previous matches can still match, so those ids are
+    /// rechecked instead of every distinct filename. Anything else, such as
+    /// deleting characters or an index change since, runs a full search and
+    /// refills `cache`.
+    ///
+    /// Setting the cancel flag of `stop` from another thread abandons the
+    /// search, which then returns `FlashFindError::Cancelled` and leaves
+    /// `cache` as it was. Running past its timeout instead returns what was
+    /// found so far, marked `timed_out`, and is not cached. A malformed query
+    /// is a `FlashFindError::InvalidQuery` whose message points at the
+    /// offending token.
+    pub fn search_refining(
+        &self,
+        query: &str,
+        limit: usize,
+        sort: SortOrder,
+        cache: &mut Option<SearchCache>,
+        stop: &SearchStop,
+    ) -> Result<SearchResults> {
+        self.search_with_cache(query, limit, sort, Some(cache), stop)
+    }
+
+    fn search_with_cache(
+        &self,
+        query: &str,
+        limit: usize,
+        sort: SortOrder,
+        cache: Option<&mut Option<SearchCache>>,
+        stop: &SearchStop,
+    ) -> Result<SearchResults> {
+        self.record(Counter::Search);
+        
+        let mut candidates: Vec<u32> = Vec::new();
+        let cap = if sort == SortOrder::Name { limit } else { usize::MAX };
+        let parsed = parse_query(query);
+        
+        // The parsed form holds the folded text, resolved kinds and date
+        // bounds, so a changed setting or a new day is a different key
+        let cache_key = (!matches!(
+            parsed,
+            ParsedQuery::Empty | ParsedQuery::Hint(_) | ParsedQuery::Invalid(_) | ParsedQuery::BrowseAll | ParsedQuery::PathPrefix(_)
+        ))
+        .then(|| format!("{:?}|{:?}|{}|{}|{}", parsed, sort, limit, self.show_hidden, self.acronyms.is_some()));
+        if let Some(key) = &cache_key {
+            let cached = self.query_cache.lock().get(key, self.generation);
+            if let Some((ids, truncated)) = cached {
+                debug!("Search '{}' served {} cached results", logged_query(query), ids.len());
+                if let Some(cache) = cache {
+                    *cache = self.refinable(parsed, &ids, truncated);
+                }
+                let paths = ids.into_iter().map(|idx| self.pool.path(idx as usize)).collect();
+                return Ok(SearchResults { paths, truncated, timed_out: false });
+            }
+        }
+        
+        // Narrow the previous matches when the query only grew
+        let previous = match (&parsed, cache.as_deref()) {
+            // A cache filled for a date or size sort may hold more than this cap
+            (ParsedQuery::Filename(needle), Some(Some(cached))) => (cached.generation == self.generation
+                && cached.show_hidden == self.show_hidden
+                && cached.ids.len() <= cap
+                // Acronym matches of the shorter query are not narrowed by name
+                && !self.matches_acronyms(needle)
+                && needle.contains(cached.needle.as_str()))
+            .then_some(&cached.ids),
+            _ => None,
+        };
+        if let (Some(ids), ParsedQuery::Filename(needle)) = (previous, &parsed) {
+            candidates = ids
+                .par_iter()
+                .copied()
+                .filter(|&idx| {
+                    if stop.is_set() {
+                        return false;
+                    }
+                    let (name, _) = index_keys(&self.pool.path(idx as usize));
+                    name.is_some_and(|name| match_tier(&name, needle).is_some())
+                })
+                .collect();
+            debug!("Refined {} previous matches to {}", ids.len(), candidates.len());
+        }
+
+        let mut truncated = match &parsed {
+            _ if previous.is_some() => false,
+            ParsedQuery::Boolean(query) => self.boolean_candidates(query, cap, stop, &mut candidates),
+            ParsedQuery::InFolder { folders, query } => self.folder_candidates(folders, query.as_deref(), cap, stop, &mut candidates),
+            term @ (ParsedQuery::FullPath(_)
+            | ParsedQuery::Size(_)
+            | ParsedQuery::Modified(_)
+            | ParsedQuery::Extension(_)
+            | ParsedQuery::Extensions(_)
+            | ParsedQuery::Kind { .. }
+            | ParsedQuery::Hidden
+            | ParsedQuery::StartsWith(_)
+            | ParsedQuery::Words(_)
+            | ParsedQuery::WholeWord(_)
+            | ParsedQuery::Glob(_)
+            | ParsedQuery::Regex(_)
+            | ParsedQuery::Filename(_)) => self.term_candidates(term, cap, stop, &mut candidates),
+            ParsedQuery::Invalid(reason) => {
+                if let Some(cache) = cache {
+                    *cache = None;
+                }
+                return Err(FlashFindError::InvalidQuery(reason.clone()));
+            }
+            ParsedQuery::Empty
+            | ParsedQuery::Hint(_)
+            | ParsedQuery::BrowseAll
+            | ParsedQuery::PathPrefix(_) => {
+                if let Some(cache) = cache {
+                    *cache = None;
+                }
+                return Ok(SearchResults::default());
+            }
+        };
+        // A cancelled scan stopped early, so its ids must not be cached
+        if stop.cancelled() {
+            debug!("Search '{}' cancelled", logged_query(query));
+            return Err(FlashFindError::Cancelled);
+        }
+        // Every check drops what it has not confirmed, so a stopped scan
+        // holds only true matches
+        let timed_out = stop.timed_out();
+        if timed_out {
+            warn!("Search '{}' timed out, returning partial results", logged_query(query));
+            truncated = true;
+        }
+        // Hidden entries count toward the cap, so a capped search may list fewer
+        let listed = wants_hidden(&parsed);
+        candidates.retain(|&idx| self.is_live(idx) && (listed || self.is_shown(idx)));
+        
+        if let Some(cache) = cache {
+            *cache = self.refinable(parsed, &candidates, truncated);
+        }
+        if sort == SortOrder::Name {
+            self.sort_by_name(&mut candidates);
+        } else {
+            candidates.sort_unstable();
+            candidates.dedup();
+            truncated |= self.sort_ids(&mut candidates, sort, limit);
+        }
+        // Partial results would hide matches the next search could find
+        if let (Some(key), false) = (cache_key, timed_out) {
+            self.query_cache.lock().put(key, self.generation, &candidates, truncated);
+        }
+
+        // Convert indices to paths
+        let paths: Vec<PathBuf> = candidates
+            .into_iter()
+            .map(|idx| self.pool.path(idx as usize))
+            .collect();
+
+        if truncated {
+            warn!("Search '{}' hit the {} candidate cap, results truncated", logged_query(query), limit);
+        }
+        debug!("Search '{}' returned {} results", logged_query(query), paths.len());
+        Ok(SearchResults { paths, truncated, timed_out })
+    }
+
+    /// What a later, longer filename query may narrow: every match of this
+    /// one, so only a complete filename result set
+    fn refinable(&self, parsed: ParsedQuery, ids: &[u32], truncated: bool) -> Option<SearchCache> {
+        match parsed {
+            ParsedQuery::Filename(needle) if !truncated => Some(SearchCache {
+                needle,
+                generation: self.generation,
+                show_hidden: self.show_hidden,
+                ids: ids.to_vec(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Collect candidates for one full path, size, date, extension, wildcard or filename term
+    ///
+    /// Returns whether the cap was hit. Filename candidates come best tier
+    /// first, so the cap drops the weakest matches. Once `cancel` is set the
+    /// scan stops early with whatever it has; the caller discards it.
+    fn term_candidates(&self, term: &ParsedQuery, limit: usize, stop: &SearchStop, candidates: &mut Vec<u32>) -> bool {
+        let mut truncated = false;
+        
+        match term {
+            // Extension search (e.g., ".pdf"); compound ones like ".tar.gz"
+            // have their own index of every dotted suffix
+            ParsedQuery::Extension(ext) => {
+                let index = if ext.contains('.') { &self.compound_extension_index } else { &self.extension_index };
+                if let Some(indices) = index.get(ext) {
+                    truncated |= take_candidates(candidates, indices, limit);
+                }
+            }
+            // Every extension of the list or kind, gathered into one sorted list
+            ParsedQuery::Extensions(extensions) | ParsedQuery::Kind { extensions, .. } => {
+                let ids = self.extension_ids(extensions.iter().map(String::as_str));
+                truncated |= take_candidates(candidates, &ids, limit);
+            }
+            // Full path search scans the pool a chunk at a time so the cap stops it early
+            ParsedQuery::FullPath(needle) => {
+                for base in (0..self.pool.len()).step_by(SUFFIX_SCAN_CHUNK) {
+                    if stop.is_set() {
+                        break;
+                    }
+                    let end = (base + SUFFIX_SCAN_CHUNK).min(self.pool.len());
+                    let matches: Vec<u32> = (base..end)
+                        .into_par_iter()
+                        .filter(|&idx| normalize_for_prefix(&self.pool.path(idx).to_string_lossy()).contains(needle.as_str()))
+                        .map(|idx| idx as u32)
+                        .collect();
+                    if take_candidates(candidates, &matches, limit) {
+                        truncated = true;
+                        break;
+                    }
+                }
+            }
+            // Sizes sit in their own array, so the scan never touches the paths
+            ParsedQuery::Size(filter) => {
+                for (chunk_no, chunk) in self.sizes.chunks(SUFFIX_SCAN_CHUNK).enumerate() {
+                    if stop.is_set() {
+                        break;
+                    }
+                    let base = chunk_no * SUFFIX_SCAN_CHUNK;
+                    let matches: Vec<u32> = chunk
+                        .par_iter()
+                        .enumerate()
+                        .filter(|&(_, &size)| size != UNKNOWN_SIZE && filter.contains(size))
+                        .map(|(idx, _)| (base + idx) as u32)
+                        .collect();
+                    if take_candidates(candidates, &matches, limit) {
+                        truncated = true;
+                        break;
+                    }
+                }
+            }
+            // Dates likewise scan their own array
+            ParsedQuery::Modified(filter) => {
+                for (chunk_no, chunk) in self.mtimes.chunks(SUFFIX_SCAN_CHUNK).enumerate() {
+                    if stop.is_set() {
+                        break;
+                    }
+                    let base = chunk_no * SUFFIX_SCAN_CHUNK;
+                    let matches: Vec<u32> = chunk
+                        .par_iter()
+                        .enumerate()
+                        .filter(|&(_, &modified)| modified != UNKNOWN_MTIME && filter.contains(modified))
+                        .map(|(idx, _)| (base + idx) as u32)
+                        .collect();
+                    if take_candidates(candidates, &matches, limit) {
+                        truncated = true;
+                        break;
+                    }
+                }
+            }
+            // Attributes too
+            ParsedQuery::Hidden => {
+                let hidden = EntryMeta::HIDDEN | EntryMeta::SYSTEM;
+                for (chunk_no, chunk) in self.attributes.chunks(SUFFIX_SCAN_CHUNK).enumerate() {
+                    if stop.is_set() {
+                        break;
+                    }
+                    let base = chunk_no * SUFFIX_SCAN_CHUNK;
+                    let matches: Vec<u32> = chunk
+                        .par_iter()
+                        .enumerate()
+                        .filter(|&(_, &attributes)| attributes & hidden != 0)
+                        .map(|(idx, _)| (base + idx) as u32)
+                        .collect();
+                    if take_candidates(candidates, &matches, limit) {
+                        truncated = true;
+                        break;
+                    }
+                }
+            }
+            // Names beginning with the prefix sit together in sorted order
+            ParsedQuery::StartsWith(prefix) => {
+                let mut sorted = self.sorted_names.lock();
+                let names = sorted.get_or_insert_with(|| {
+                    let mut names: Vec<String> = self.filename_index.keys().cloned().collect();
+                    names.par_sort_unstable();
+                    debug!("Sorted {} filenames for prefix search", names.len());
+                    names
+                });
+                let start = names.partition_point(|name| name.as_str() < prefix.as_str());
+                let len = names[start..].partition_point(|name| name.starts_with(prefix.as_str()));
+                let in_range = &names[start..start + len];
+                
+                // Exact names (`img` or `img.png` for ^img) first, then the rest in name order
+                let is_exact = |name: &String| name.rsplit_once('.').map_or(name.as_str(), |(stem, _)| stem) == prefix;
+                let ordered = in_range.iter().filter(|name| is_exact(name)).chain(in_range.iter().filter(|name| !is_exact(name)));
+                for name in ordered {
+                    if stop.is_set() {
+                        break;
+                    }
+                    let Some(indices) = self.filename_index.get(name) else { continue };
+                    if take_candidates(candidates, indices, limit) {
+                        truncated = true;
+                        break;
+                    }
+                }
+            }
+            // Each query word narrows the files with a name word it starts
+            ParsedQuery::Words(query) => {
+                let mut found: Option<Vec<u32>> = None;
+                for word in query {
+                    if stop.is_set() {
+                        break;
+                    }
+                    let mut ids: Vec<u32> = self
+                        .word_index
+                        .par_iter()
+                        .filter(|(name_word, _)| name_word.starts_with(word.as_str()))
+                        .flat_map_iter(|(_, ids)| ids.iter().copied())
+                        .collect();
+                    ids.par_sort_unstable();
+                    ids.dedup();
+                    let ids = match found {
+                        Some(so_far) => intersect_sorted(&so_far, &ids),
+                        None => ids,
+                    };
+                    let exhausted = ids.is_empty();
+                    found = Some(ids);
+                    if exhausted {
+                        break;
+                    }
+                }
+                truncated |= take_candidates(candidates, &found.unwrap_or_default(), limit);
+            }
+            ParsedQuery::Glob(glob) => {
+                let mut matches: Vec<(&String, &Vec<u32>)> = self
+                    .filename_index
+                    .par_iter()
+                    .filter(|(name, _)| !stop.is_set() && glob.matches(name))
+                    .collect();
+                matches.par_sort_unstable_by(|a, b| a.0.cmp(b.0));
+                
+                for (_, indices) in matches {
+                    if take_candidates(candidates, indices, limit) {
+                        truncated = true;
+                        break;
+                    }
+                }
+            }
+            // Patterns see names as written, which folded keys may not spell,
+            // so the pool is scanned a chunk at a time like `path:` queries
+            ParsedQuery::Regex(filter) => {
+                for base in (0..self.pool.len()).step_by(SUFFIX_SCAN_CHUNK) {
+                    if stop.is_set() {
+                        break;
+                    }
+                    let end = (base + SUFFIX_SCAN_CHUNK).min(self.pool.len());
+                    let matches: Vec<u32> = (base..end)
+                        .into_par_iter()
+                        .filter(|&idx| {
+                            if filter.full_path {
+                                filter.matches(&self.pool.path(idx))
+                            } else {
+                                filter.is_match(self.pool.name(idx))
+                            }
+                        })
+                        .map(|idx| idx as u32)
+                        .collect();
+                    if take_candidates(candidates, &matches, limit) {
+                        truncated = true;
+                        break;
+                    }
+                }
+            }
+            // Folded keys lose case changes, so each file's own name is checked
+            ParsedQuery::WholeWord(word) => {
+                for indices in self.names_containing(word, stop) {
+                    let ids: Vec<u32> = indices.iter().copied().filter(|&idx| self.entry_matches(term, idx)).collect();
+                    if take_candidates(candidates, &ids, limit) {
+                        truncated = true;
+                        break;
+                    }
+                }
+            }
+            ParsedQuery::Filename(q) => {
+                for indices in self.names_containing(q, stop) {
+                    if take_candidates(candidates, indices, limit) {
+                        truncated = true;
+                        break;
+                    }
+                }
+                
+                // Names whose word initials start with the query come after
+                // every name containing it
+                if !truncated && !stop.is_set() && self.matches_acronyms(q) {
+                    let mut ids: Vec<u32> = self
+                        .acronyms
+                        .iter()
+                        .flatten()
+                        .filter(|(acronym, _)| acronym.starts_with(q.as_str()))
+                        .flat_map(|(_, ids)| ids.iter().copied())
+                        .filter(|&idx| index_keys(&self.pool.path(idx as usize)).0.is_some_and(|name| match_tier(&name, q).is_none()))
+                        .collect();
+                    ids.par_sort_unstable();
+                    truncated |= take_candidates(candidates, &ids, limit);
+                }
+            }
+            _ => {}
+        }
+        
+        truncated
+    }
+    
+    /// Ids filed under each folded filename containing `q`, best tiers
+    /// first and in name order within a tier
+    fn names_containing(&self, q: &str, stop: &SearchStop) -> Vec<&Vec<u32>> {
+        // Parallel classification, then the sort by tier
+        let mut matches: Vec<(MatchTier, &String, &Vec<u32>)> = match self.trigram_names(q) {
+            Some(names) => names
+                .par_iter()
+                .filter(|_| !stop.is_set())
+                .filter_map(|name| self.filename_index.get_key_value(name))
+                .filter_map(|(name, indices)| match_tier(name, q).map(|tier| (tier, name, indices)))
+                .collect(),
+            None => self
+                .filename_index
+                .par_iter()
+                .filter(|_| !stop.is_set())
+                .filter_map(|(name, indices)| match_tier(name, q).map(|tier| (tier, name, indices)))
+                .collect(),
+        };
+        matches.par_sort_unstable_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+        matches.into_iter().map(|(_, _, indices)| indices).collect()
+    }
+    
+    /// Collect candidates for a boolean query, returning whether the cap was hit
+    ///
+    /// Each alternative starts from its cheapest term (an extension lookup
+    /// before a filename scan before a full path scan) and narrows the ids
+    /// term by term, so no paths are built per term and memory stays within
+    /// `limit` ids per term. Once fewer ids remain than distinct filenames,
+    /// the next term is checked against each candidate instead of scanning
+    /// for it. A term with no matches ends its alternative early. Excluded
+    /// extensions are then dropped by id, straight from the extension index,
+    /// and other NOT terms are checked against each remaining candidate.
+    fn boolean_candidates(&self, query: &BoolQuery, limit: usize, stop: &SearchStop, candidates: &mut Vec<u32>) -> bool {
+        let mut truncated = false;
+        
+        for conjunction in &query.any_of {
+            let (by_extension, by_entry): (Vec<&ParsedQuery>, Vec<&ParsedQuery>) = conjunction
+                .exclude
+                .iter()
+                .partition(|term| matches!(term, ParsedQuery::Extension(_) | ParsedQuery::Extensions(_)));
+            let excluded = self.extension_ids(by_extension.iter().flat_map(|term| match term {
+                ParsedQuery::Extension(ext) => std::slice::from_ref(ext),
+                ParsedQuery::Extensions(extensions) => extensions.as_slice(),
+                _ => &[],
+            }).map(String::as_str));
+
+            let mut terms: Vec<&ParsedQuery> = conjunction.include.iter().collect();
+            terms.sort_by_key(|term| term_cost(term));
+            
+            let mut found: Option<Vec<u32>> = None;
+            for term in terms {
+                let ids = match found {
+                    Some(so_far) if so_far.len() < self.filename_index.len() => so_far
+                        .into_par_iter()
+                        .filter(|&idx| !stop.is_set() && self.entry_matches(term, idx))
+                        .collect(),
+                    so_far => {
+                        let mut ids = Vec::new();
+                        truncated |= self.term_candidates(term, limit, stop, &mut ids);
+                        ids.sort_unstable();
+                        ids.dedup();
+                        match so_far {
+                            Some(so_far) => intersect_sorted(&so_far, &ids),
+                            None => ids,
+                        }
+                    }
+                };
+                let exhausted = ids.is_empty();
+                found = Some(ids);
+                if exhausted {
+                    break;
+                }
+            }
+            
+            let matching: Vec<u32> = found
+                .unwrap_or_default()
+                .into_par_iter()
+                .filter(|&idx| {
+                    !stop.is_set()
+                        && self.is_live(idx)
+                        && excluded.binary_search(&idx).is_err()
+                        && !by_entry.iter().any(|term| self.entry_matches(term, idx))
+                })
+                .collect();
+            candidates.extend(matching);
+        }
+        
+        candidates.sort_unstable();
+        candidates.dedup();
+        if candidates.len() > limit {
+            candidates.truncate(limit);
+            truncated = true;
+        }
+        truncated
+    }
+
+    /// Sorted ids of every file with any of these extensions, compound ones
+    /// like `tar.gz` included
+    fn extension_ids<'e>(&self, extensions: impl Iterator<Item = &'e str>) -> Vec<u32> {
+        let mut ids: Vec<u32> = extensions
+            .filter_map(|ext| {
+                let index = if ext.contains('.') { &self.compound_extension_index } else { &self.extension_index };
+                index.get(ext)
+            })
+            .flatten()
+            .copied()
+            .collect();
+        ids.par_sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Distinct lowercased names that may contain `query`, from the trigram index
+    ///
+    /// `None` when the index is off or the query is too short for a trigram,
+    /// in which case every name must be scanned.
+    fn trigram_names(&self, query: &str) -> Option<Vec<String>> {
+        let ids = self.trigrams.as_ref()?.candidates(query)?;
+        let mut names: Vec<String> = ids
+            .par_iter()
+            .filter_map(|&idx| index_keys(&self.pool.path(idx as usize)).0)
+            .collect();
+        names.par_sort_unstable();
+        names.dedup();
+        Some(names)
+    }
+
+    /// Collect candidates for `in:` clauses, returning whether the cap was hit
+    ///
+    /// Each folder in the directory index is checked against the scopes
+    /// once, rather than every path. With no query its files are the
+    /// candidates, in pool order; otherwise the query's matches are gathered
+    /// uncapped, in their usual order, and kept if they are among them.
+    fn folder_candidates(
+        &self,
+        folders: &[FolderScope],
+        query: Option<&ParsedQuery>,
+        limit: usize,
+        stop: &SearchStop,
+        candidates: &mut Vec<u32>,
+    ) -> bool {
+        let mut in_scope: Vec<u32> = self
+            .directory_index
+            .par_iter()
+            .filter(|(folder, _)| !stop.is_set() && folders.iter().any(|scope| scope.contains_folder(folder)))
+            .flat_map_iter(|(_, ids)| ids.iter().copied())
+            .collect();
+        in_scope.par_sort_unstable();
+        
+        let Some(query) = query else {
+            return take_candidates(candidates, &in_scope, limit);
+        };
+        
+        let mut ids = Vec::new();
+        match query {
+            ParsedQuery::Boolean(query) => self.boolean_candidates(query, usize::MAX, stop, &mut ids),
+            term => self.term_candidates(term, usize::MAX, stop, &mut ids),
+        };
+        let matches: Vec<u32> = ids.into_par_iter().filter(|idx| in_scope.binary_search(idx).is_ok()).collect();
+        take_candidates(candidates, &matches, limit)
+    }
+    
+    /// Number of live entries directly inside each folder, keyed as
+    /// `folder_key` spells it
+    pub fn folder_counts(&self) -> impl Iterator<Item = (&str, usize)> + '_ {
+        self.directory_index.iter().map(|(folder, ids)| (folder.as_str(), ids.len()))
+    }
+    
+    /// A folder of `folder_counts` as the entries inside it spell it, if
+    /// any are indexed here
+    pub fn folder_path(&self, folder: &str) -> Option<PathBuf> {
+        let ids = self.directory_index.get(folder)?;
+        Some(self.pool.path(ids[0] as usize).parent()?.to_path_buf())
+    }
+
+    /// Check one boolean term against a pool slot, using its stored metadata
+    fn entry_matches(&self, term: &ParsedQuery, idx: u32) -> bool {
+        term_matches(term, &self.pool.path(idx as usize), &|| self.meta_of(idx))
+    }
+
+    /// Order live ids by `sort` (see `compare_sorted`), keeping the first
+    /// `limit`; returns whether any ids were dropped
+    fn sort_ids(&self, ids: &mut Vec<u32>, sort: SortOrder, limit: usize) -> bool {
+        // Paths are rebuilt from the pool, so build each once, not per comparison
+        let mut keyed: Vec<(u32, PathBuf)> = ids.par_iter().map(|&idx| (idx, self.pool.path(idx as usize))).collect();
+        let order = |(a, a_path): &(u32, PathBuf), (b, b_path): &(u32, PathBuf)| {
+            compare_sorted(sort, (a_path, self.meta_of(*a)), (b_path, self.meta_of(*b)))
+        };
+        
+        let truncated = keyed.len() > limit;
+        if truncated {
+            keyed.select_nth_unstable_by(limit, order);
+            keyed.truncate(limit);
+        }
+        keyed.par_sort_unstable_by(order);
+        *ids = keyed.into_iter().map(|(idx, _)| idx).collect();
+        truncated
+    }
+
+    /// Order ids by `compare_results`, building each path's key once
+    /// instead of twice per comparison
+    fn sort_by_name(&self, ids: &mut Vec<u32>) {
+        let mut keyed: Vec<((Option<String>, String), PathBuf, u32)> = ids
+            .par_iter()
+            .map(|&idx| {
+                let path = self.pool.path(idx as usize);
+                (result_key(&path), path, idx)
+            })
+            .collect();
+        keyed.par_sort_unstable_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+        *ids = keyed.into_iter().map(|(_, _, idx)| idx).collect();
+    }
+
+    /// Stored size, modification time and kind of a pool slot
+    pub fn meta_of(&self, idx: u32) -> EntryMeta {
+        let idx = idx as usize;
+        EntryMeta {
+            size: self.sizes.get(idx).copied().unwrap_or(UNKNOWN_SIZE),
+            modified: self.mtimes.get(idx).copied().unwrap_or(UNKNOWN_MTIME),
+            is_dir: self.dirs.get(idx).copied().unwrap_or(false),
+            attributes: self.attributes.get(idx).copied().unwrap_or(0),
+        }
+    }
+
+    /// Search filenames allowing typos, collecting at most `limit` candidates
+    ///
+    /// By name, results are ordered by the typos needed (exact matches
+    /// first), then by `compare_results`; by date or size, the cap keeps the
+    /// newest or largest as in `search_limited`. Only plain filename queries
+    /// match fuzzily, with as many typos as `tolerance` allows for their
+    /// length (see `TypoTolerance::max_typos`).
+    pub fn search_fuzzy(&self, query: &str, limit: usize, sort: SortOrder, tolerance: TypoTolerance) -> SearchResults {
+        self.record(Counter::Search);
+        
+        let ParsedQuery::Filename(q) = parse_query(query) else {
+            return SearchResults::default();
+        };
+        let max = tolerance.max_typos(q.chars().count());
+        
+        let mut matches: Vec<(usize, &String, &Vec<u32>)> = self
+            .filename_index
+            .par_iter()
+            .filter_map(|(name, indices)| fuzzy_distance(name, &q, max).map(|typos| (typos, name, indices)))
+            .collect();
+        matches.par_sort_unstable_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+        
+        let mut candidates: Vec<(usize, u32)> = Vec::new();
+        let mut truncated = false;
+        let cap = if sort == SortOrder::Name { limit } else { usize::MAX };
+        for (typos, _, indices) in matches {
+            let room = cap.saturating_sub(candidates.len());
+            candidates.extend(indices.iter().take(room).map(|&idx| (typos, idx)));
+            if indices.len() > room {
+                truncated = true;
+                break;
+            }
+        }
+        
+        if sort != SortOrder::Name {
+            let mut ids: Vec<u32> = candidates.into_iter().map(|(_, idx)| idx).filter(|&idx| self.is_live(idx) && self.is_shown(idx)).collect();
+            truncated |= self.sort_ids(&mut ids, sort, limit);
+            debug!("Fuzzy search '{}' returned {} results", logged_query(query), ids.len());
+            return SearchResults {
+                paths: ids.into_iter().map(|idx| self.pool.path(idx as usize)).collect(),
+                truncated,
+                timed_out: false,
+            };
+        }
+        
+        let mut scored: Vec<(usize, PathBuf)> = candidates
+            .into_iter()
+            .filter(|&(_, idx)| self.is_live(idx) && self.is_shown(idx))
+            .map(|(typos, idx)| (typos, self.pool.path(idx as usize)))
+            .collect();
+        scored.sort_unstable_by(|a, b| a.0.cmp(&b.0).then_with(|| compare_results(&a.1, &b.1)));
+        
+        if truncated {
+            warn!("Fuzzy search '{}' hit the {} candidate cap, results truncated", logged_query(query), limit);
+        }
+        debug!("Fuzzy search '{}' returned {} results", logged_query(query), scored.len());
+        SearchResults { paths: scored.into_iter().map(|(_, path)| path).collect(), truncated, timed_out: false }
+    }
+}
+
+/// How many typos fuzzy matching forgives (`Config::typo_tolerance`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TypoTolerance {
+    /// Exact matches only; a search with none finds nothing
+    Off,
+    /// One typo per four characters
+    #[default]
+    Low,
+    /// One typo per three characters
+    High,
+}
+
+impl TypoTolerance {
+    pub const ALL: [TypoTolerance; 3] = [TypoTolerance::Off, TypoTolerance::Low, TypoTolerance::High];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TypoTolerance::Off => "Off",
+            TypoTolerance::Low => "Low",
+            TypoTolerance::High => "High",
+        }
+    }
+
+    /// Most typos a fuzzy match may contain for a query of `len` characters
+    ///
+    /// Under `Low`, queries shorter than four never match fuzzily, so short
+    /// words do not drown in noise; `High` forgives a typo from three.
+    pub fn max_typos(self, len: usize) -> usize {
+        match self {
+            TypoTolerance::Off => 0,
+            TypoTolerance::Low => len / 4,
+            TypoTolerance::High => len / 3,
+        }
+    }
+}
+
+/// Typos needed for `query` to appear somewhere in `name`, if at most `max`
+///
+/// A typo is one inserted, deleted, substituted or swapped adjacent
+/// character (optimal string alignment distance), with the match free to
+/// start and end anywhere in the name. Both strings should be lowercased.
+pub fn fuzzy_distance(name: &str, query: &str, max: usize) -> Option<usize> {
+    let q: Vec<char> = query.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    if q.is_empty() {
+        return Some(0);
+    }
+    if n.len() + max < q.len() {
+        return None;
+    }
+    
+    // One column per query prefix; a match may start anywhere in the name,
+    // so row 0 of every column is free
+    let mut before: Vec<usize> = (0..=q.len()).collect();
+    let mut previous: Vec<usize> = (0..=q.len()).collect();
+    let mut current = vec![0; q.len() + 1];
+    let mut best = previous[q.len()];
+    
+    for j in 1..=n.len() {
+        current[0] = 0;
+        for i in 1..=q.len() {
+            let cost = usize::from(q[i - 1] != n[j - 1]);
+            let mut d = (previous[i - 1] + cost).min(previous[i] + 1).min(current[i - 1] + 1);
+            if i > 1 && j > 1 && q[i - 1] == n[j - 2] && q[i - 2] == n[j - 1] {
+                d = d.min(before[i - 2] + 1);
+            }
+            current[i] = d;
+        }
+        best = best.min(current[q.len()]);
+        
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    
+    (best <= max).then_some(best)
+}
+
+/// Newest slot first, ties going to the earlier slot
+fn by_recency(mtimes: &[i64], a: u32, b: u32) -> std::cmp::Ordering {
+    mtimes[b as usize].cmp(&mtimes[a as usize]).then(a.cmp(&b))
+}
+
+/// Split each group into the slots that share a `key`, dropping slots
+/// without one and parts smaller than `min_count`
+pub fn split_groups<K, F>(groups: Vec<Vec<u32>>, min_count: usize, key: F) -> Vec<Vec<u32>>
+where
+    K: Eq + std::hash::Hash,
+    F: Fn(u32) -> Option<K> + Sync,
+{
+    groups
+        .into_par_iter()
+        .flat_map_iter(|group| {
+            let mut parts: AHashMap<K, Vec<u32>> = AHashMap::new();
+            for idx in group {
+                if let Some(key) = key(idx) {
+                    parts.entry(key).or_default().push(idx);
+                }
+            }
+            parts.into_values().filter(|part| part.len() >= min_count)
+        })
+        .collect()
+}
+
+/// The id of a slot appended to a pool of `len` slots, or `IndexFull` once
+/// the pool holds `max` slots or the id would not fit a `u32`
+fn next_slot(len: usize, max: usize) -> Result<u32> {
+    match u32::try_from(len) {
+        Ok(idx) if len < max => Ok(idx),
+        _ => {
+            let max = max.min(u32::MAX as usize + 1);
+            warn!("Index full at {} files", max);
+            Err(FlashFindError::IndexFull(max))
+        }
+    }
+}
+
+/// Append ids until `candidates` holds `limit`; returns true if some were dropped
+fn take_candidates(candidates: &mut Vec<u32>, ids: &[u32], limit: usize) -> bool {
+    let room = limit.saturating_sub(candidates.len());
+    candidates.extend(ids.iter().take(room));
+    ids.len() > room
+}
+
+/// Relative cost of gathering a boolean term's ids; cheapest goes first
+fn term_cost(term: &ParsedQuery) -> u8 {
+    match term {
+        ParsedQuery::Extension(_) | ParsedQuery::Extensions(_) | ParsedQuery::Kind { .. } | ParsedQuery::StartsWith(_) => 0,
+        ParsedQuery::Glob(_) | ParsedQuery::Words(_) | ParsedQuery::WholeWord(_) | ParsedQuery::Filename(_) => 1,
+        ParsedQuery::Size(_) | ParsedQuery::Modified(_) | ParsedQuery::Hidden => 2,
+        // Full paths scan every path in the pool
+        _ => 3,
+    }
+}
+
+/// Ids present in both sorted, deduplicated lists
+fn intersect_sorted(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (small, large) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    small.iter().copied().filter(|id| large.binary_search(id).is_ok()).collect()
+}
+
+/// How well a filename matches a query; better tiers are collected first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchTier {
+    /// The whole name or its stem equals the query
+    Exact,
+    /// The name starts with the query
+    Prefix,
+    /// The query starts a word inside the name (after a space, `_`, `-`, ...)
+    Token,
+    /// The query appears anywhere else in the name
+    Substring,
+}
+
+/// Classify how a lowercased `name` matches `query`, or `None` if it does not
+pub fn match_tier(name: &str, query: &str) -> Option<MatchTier> {
+    if !name.contains(query) {
+        return None;
+    }
+    
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    let tier = if name == query || stem == query {
+        MatchTier::Exact
+    } else if name.starts_with(query) {
+        MatchTier::Prefix
+    } else if name
+        .match_indices(query)
+        .any(|(pos, _)| name[..pos].chars().next_back().is_some_and(|c| !c.is_alphanumeric()))
+    {
+        MatchTier::Token
+    } else {
+        MatchTier::Substring
+    };
+    Some(tier)
+}
+
+/// The rule that put a path into the results of a query
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatchReason {
+    /// Filed under the extension in the extension index
+    Extension(String),
+    /// Filename ends with a compound extension such as `tar.gz`
+    CompoundExtension(String),
+    /// Filename contains the query; `tier` says how closely
+    Filename { query: String, tier: MatchTier },
+    /// Entry lives under a browsed folder or drive
+    PathPrefix(String),
+    /// Entry is in an `in:` folder, and matched the rest of the query if any
+    InFolder { folder: String, within: Option<Box<MatchReason>> },
+    /// Listed by the `*` browse-all query
+    BrowseAll,
+    /// Each word starts a word of the filename (`words:` query)
+    Words(Vec<String>),
+    /// Filename holds the text as a whole word (`\b` query)
+    WholeWord(String),
+    /// Whole filename matches a wildcard pattern
+    Glob(String),
+    /// Full path contains the `path:` query
+    FullPath(String),
+    /// File size is within a `size:` filter
+    Size { size: u64, filter: String },
+    /// Modification time (Unix seconds) is within a `modified:` filter
+    Modified { modified: i64, filter: String },
+    /// Filename contains the query with a few typos (fuzzy search)
+    Fuzzy { query: String, typos: usize },
+    /// Filename, or full path for `repath:`, matches a regular expression
+    Regex { pattern: String, full_path: bool },
+    /// Extension belongs to a `kind:` group
+    Kind { kind: String, extension: String },
+    /// Initials of the filename's words start with the query
+    Acronym { query: String, acronym: String },
+    /// Entry is hidden or a system file (`is:hidden` query)
+    Hidden,
+}
+
+impl fmt::Display for MatchReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatchReason::Extension(ext) => write!(f, "Extension lookup: file type .{}", ext),
+            MatchReason::CompoundExtension(ext) => write!(f, "Filename ends with .{}", ext),
+            MatchReason::Filename { query, tier } => match tier {
+                MatchTier::Exact => write!(f, "Filename is exactly \"{}\"", query),
+                MatchTier::Prefix => write!(f, "Filename starts with \"{}\"", query),
+                MatchTier::Token => write!(f, "A word in the filename starts with \"{}\"", query),
+                MatchTier::Substring => write!(f, "Filename contains \"{}\"", query),
+            },
+            MatchReason::PathPrefix(prefix) => write!(f, "Path starts with \"{}\"", prefix),
+            MatchReason::InFolder { folder, within: Some(within) } => write!(f, "{}, in a folder matching in:{}", within, folder),
+            MatchReason::InFolder { folder, within: None } => write!(f, "In a folder matching in:{}", folder),
+            MatchReason::BrowseAll => write!(f, "Listed by * (browse all files)"),
+            MatchReason::Words(words) => {
+                let quoted: Vec<String> = words.iter().map(|word| format!("\"{}\"", word)).collect();
+                write!(f, "Filename has words starting with {}", quoted.join(", "))
+            }
+            MatchReason::WholeWord(word) => write!(f, "Filename has \"{}\" as a whole word", word),
+            MatchReason::Glob(pattern) => write!(f, "Filename matches the pattern \"{}\"", pattern),
+            MatchReason::FullPath(needle) => write!(f, "Full path contains \"{}\"", needle),
+            MatchReason::Size { size, filter } => write!(f, "Size {} is within size:{}", format_size(*size), filter),
+            MatchReason::Modified { modified, filter } => {
+                write!(f, "Modified {} is within modified:{}", format_date(*modified, local_utc_offset()), filter)
+            }
+            MatchReason::Fuzzy { query, typos: 1 } => write!(f, "Filename contains \"{}\" with 1 typo", query),
+            MatchReason::Fuzzy { query, typos } => write!(f, "Filename contains \"{}\" with {} typos", query, typos),
+            MatchReason::Regex { pattern, full_path: false } => write!(f, "Filename matches the regular expression /{}/", pattern),
+            MatchReason::Regex { pattern, full_path: true } => write!(f, "Full path matches the regular expression /{}/", pattern),
+            MatchReason::Kind { kind, extension } => write!(f, "File type .{} is one of kind:{}", extension, kind),
+            MatchReason::Acronym { query, acronym } => {
+                write!(f, "Initials of the filename's words, \"{}\", start with \"{}\"", acronym, query)
+            }
+            MatchReason::Hidden => write!(f, "Hidden or system file (is:hidden)"),
+        }
+    }
+}
+
+/// Explain why `path` is a result for `query`, or `None` if it would not match
+///
+/// Uses the same parsing and tier classification as the search itself, so
+/// the explanation cannot drift from the actual matching rules.
+pub fn explain_match(query: &str, path: &Path) -> Option<MatchReason> {
+    explain_parsed(&parse_query(query), path)
+}
+
+/// Byte ranges of `name`, a filename as written, that the query's filename,
+/// `^` prefix, `words:`, extension and `re:` terms match, sorted and merged
+///
+/// Every occurrence of a filename term is included, and every included term
+/// of a boolean query. Other kinds of terms match no part of the name.
+pub fn match_ranges(parsed: &ParsedQuery, name: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    collect_ranges(parsed, name, &mut ranges);
+    ranges.sort_unstable_by_key(|range| (range.start, range.end));
+    
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+fn collect_ranges(parsed: &ParsedQuery, name: &str, ranges: &mut Vec<Range<usize>>) {
+    match parsed {
+        ParsedQuery::Filename(q) => ranges.extend(find_folded(name, q)),
+        ParsedQuery::StartsWith(prefix) => ranges.extend(find_folded(name, prefix).into_iter().take(1).filter(|r| r.start == 0)),
+        ParsedQuery::Words(words) => {
+            for word in words {
+                ranges.extend(find_folded(name, word).into_iter().filter(|r| starts_word(name, r.start)));
+            }
+        }
+        ParsedQuery::WholeWord(word) => ranges.extend(find_folded(name, word).into_iter().filter(|r| is_whole_word(name, r.clone()))),
+        ParsedQuery::Regex(filter) if !filter.full_path => ranges.extend(filter.find_ranges(name)),
+        // The extension without its dot, at the very end of the name
+        ParsedQuery::Extension(ext) => ranges.extend(
+            find_folded(name, &format!(".{}", ext))
+                .pop()
+                .filter(|r| r.end == name.len())
+                .map(|r| r.start + 1..r.end),
+        ),
+        ParsedQuery::Extensions(extensions) | ParsedQuery::Kind { extensions, .. } => {
+            for ext in extensions {
+                collect_ranges(&ParsedQuery::Extension(ext.clone()), name, ranges);
+            }
+        }
+        ParsedQuery::Boolean(query) => {
+            for term in query.any_of.iter().flat_map(|conjunction| &conjunction.include) {
+                collect_ranges(term, name, ranges);
+            }
+        }
+        ParsedQuery::InFolder { query: Some(query), .. } => collect_ranges(query, name, ranges),
+        _ => {}
+    }
+}
+
+/// `explain_match` for a query parsed once up front, e.g. a watched saved search
+///
+/// A `size:` or `modified:` filter reads the file's current metadata from disk.
+pub fn explain_parsed(parsed: &ParsedQuery, path: &Path) -> Option<MatchReason> {
+    explain_entry(parsed, path, &|| EntryMeta::read(path))
+}
+
+/// `explain_parsed` with the entry's metadata supplied by the caller, and
+/// only asked for when the query has a `size:`, `modified:` or `is:` filter
+fn explain_entry(parsed: &ParsedQuery, path: &Path, meta: &dyn Fn() -> EntryMeta) -> Option<MatchReason> {
+    let (name, ext) = index_keys(path);
+    
+    match parsed {
+        ParsedQuery::Extension(wanted) => {
+            if ext.as_deref() == Some(wanted.as_str()) {
+                Some(MatchReason::Extension(wanted.clone()))
+            } else if wanted.contains('.') && name?.ends_with(&format!(".{}", wanted)) {
+                Some(MatchReason::CompoundExtension(wanted.clone()))
+            } else {
+                None
+            }
+        }
+        // The longest matching extension explains itself, as for a kind
+        ParsedQuery::Extensions(extensions) => extensions
+            .iter()
+            .filter_map(|wanted| explain_entry(&ParsedQuery::Extension(wanted.clone()), path, meta))
+            .max_by_key(|reason| match reason {
+                MatchReason::Extension(ext) | MatchReason::CompoundExtension(ext) => ext.len(),
+                _ => 0,
+            }),
+        ParsedQuery::Kind { name: kind, extensions } => {
+            // The longest suffix first, so a .tar.gz kind explains itself over .gz
+            let name = name?;
+            let extension = extensions
+                .iter()
+                .filter(|wanted| ext.as_deref() == Some(wanted.as_str()) || name.ends_with(&format!(".{}", wanted)))
+                .max_by_key(|wanted| wanted.len())?;
+            Some(MatchReason::Kind { kind: kind.clone(), extension: extension.clone() })
+        }
+        ParsedQuery::Filename(q) => {
+            let name = name?;
+            if let Some(tier) = match_tier(&name, q) {
+                return Some(MatchReason::Filename { query: q.clone(), tier });
+            }
+            if let Some(acronym) = acronym(&name).filter(|acronym| acronym.starts_with(q.as_str())) {
+                return Some(MatchReason::Acronym { query: q.clone(), acronym });
+            }
+            // Explains a match under any tolerance setting
+            let typos = fuzzy_distance(&name, q, TypoTolerance::High.max_typos(q.chars().count()))?;
+            Some(MatchReason::Fuzzy { query: q.clone(), typos })
+        }
+        ParsedQuery::PathPrefix(prefix) => normalize_for_prefix(&path.to_string_lossy())
+            .starts_with(&normalize_for_prefix(prefix))
+            .then(|| MatchReason::PathPrefix(prefix.clone())),
+        ParsedQuery::FullPath(needle) => normalize_for_prefix(&path.to_string_lossy())
+            .contains(needle.as_str())
+            .then(|| MatchReason::FullPath(needle.clone())),
+        ParsedQuery::Size(filter) => {
+            let size = meta().size;
+            (size != UNKNOWN_SIZE && filter.contains(size))
+                .then(|| MatchReason::Size { size, filter: filter.as_str().to_string() })
+        }
+        ParsedQuery::Modified(filter) => {
+            let modified = meta().modified;
+            (modified != UNKNOWN_MTIME && filter.contains(modified))
+                .then(|| MatchReason::Modified { modified, filter: filter.as_str().to_string() })
+        }
+        ParsedQuery::Hidden => {
+            (meta().is_hidden() || name_attributes(path) != 0).then_some(MatchReason::Hidden)
+        }
+        ParsedQuery::StartsWith(prefix) => {
+            let name = name?;
+            let tier = match_tier(&name, prefix).filter(|_| name.starts_with(prefix.as_str()))?;
+            Some(MatchReason::Filename { query: prefix.clone(), tier })
+        }
+        ParsedQuery::Words(query) => words_match(&name_words(path), query).then(|| MatchReason::Words(query.clone())),
+        ParsedQuery::WholeWord(word) => {
+            let name = path.file_name()?.to_str()?;
+            find_folded(name, word)
+                .into_iter()
+                .any(|r| is_whole_word(name, r))
+                .then(|| MatchReason::WholeWord(word.clone()))
+        }
+        ParsedQuery::InFolder { folders, query } => {
+            let folder = folders.iter().find(|folder| folder.contains(path))?;
+            let within = match query {
+                Some(query) => Some(Box::new(explain_entry(query, path, meta)?)),
+                None => None,
+            };
+            Some(MatchReason::InFolder { folder: folder.as_str().to_string(), within })
+        }
+        ParsedQuery::BrowseAll => Some(MatchReason::BrowseAll),
+        ParsedQuery::Glob(glob) => glob.matches(&name?).then(|| MatchReason::Glob(glob.as_str().to_string())),
+        ParsedQuery::Regex(filter) => filter.matches(path).then(|| MatchReason::Regex {
+            pattern: filter.as_str().to_string(),
+            full_path: filter.full_path,
+        }),
+        // The first alternative that matches explains itself by its first term
+        ParsedQuery::Boolean(query) => query
+            .any_of
+            .iter()
+            .find(|c| {
+                c.include.iter().all(|term| term_matches(term, path, meta))
+                    && !c.exclude.iter().any(|term| term_matches(term, path, meta))
+            })
+            .and_then(|c| explain_entry(c.include.first()?, path, meta)),
+        ParsedQuery::Empty | ParsedQuery::Hint(_) | ParsedQuery::Invalid(_) => None,
+    }
+}
+
+/// Check one term of a boolean query against a path, without typo tolerance
+fn term_matches(term: &ParsedQuery, path: &Path, meta: &dyn Fn() -> EntryMeta) -> bool {
+    match term {
+        ParsedQuery::Filename(q) => index_keys(path).0.is_some_and(|name| match_tier(&name, q).is_some()),
+        term => explain_entry(term, path, meta).is_some(),
+    }
+}
+
+/// Total order for search results: filename (case-insensitive), then full path
+///
+/// The path tiebreak keeps identically named files (dozens of `invoice.pdf`)
+/// in the same order on every search, whatever the hash set iteration order.
+pub fn compare_results(a: &Path, b: &Path) -> std::cmp::Ordering {
+    result_key(a).cmp(&result_key(b)).then_with(|| a.cmp(b))
+}
+
+/// Order of two results under `sort`, by their stored metadata
+///
+/// Entries whose date or size was never read come last; ties fall back to
+/// `compare_results`.
+pub fn compare_sorted(sort: SortOrder, (a, a_meta): (&Path, EntryMeta), (b, b_meta): (&Path, EntryMeta)) -> std::cmp::Ordering {
+    let known_size = |meta: EntryMeta| Some(meta.size).filter(|&size| size != UNKNOWN_SIZE);
+    match sort {
+        SortOrder::Name | SortOrder::NameAsc => std::cmp::Ordering::Equal,
+        SortOrder::NameDesc => compare_results(b, a),
+        // UNKNOWN_MTIME is the oldest possible time
+        SortOrder::ModifiedDesc => b_meta.modified.cmp(&a_meta.modified),
+        SortOrder::SizeDesc => known_size(b_meta).cmp(&known_size(a_meta)),
+        SortOrder::DepthAsc => a.components().count().cmp(&b.components().count()),
+    }
+    .then_with(|| compare_results(a, b))
+}
+
+/// The parts of a path `compare_results` orders by, before the path itself
+fn result_key(path: &Path) -> (Option<String>, String) {
+    (path.file_name().map(|n| n.to_string_lossy().to_lowercase()), path_key(path))
+}
+
+/// Sort by `compare_results`, building each path's key once instead of
+/// twice per comparison
+pub fn sort_results(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut keyed: Vec<((Option<String>, String), PathBuf)> =
+        paths.into_par_iter().map(|path| (result_key(&path), path)).collect();
+    keyed.par_sort_unstable();
+    keyed.into_iter().map(|(_, path)| path).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static NEVER_CANCELLED: AtomicBool = AtomicBool::new(false);
+
+    /// A stop for searches that run to the end
+    fn unstopped() -> SearchStop<'static> {
+        SearchStop::new(&NEVER_CANCELLED, None)
+    }
+
+    #[test]
+    fn test_basic_insert() {
+        let mut index = FileIndex::new();
+        let path = PathBuf::from("C:\\test\\file.txt");
+        
+        assert!(index.insert(path.clone()).unwrap());
+        assert_eq!(index.len(), 1);
+        
+        // Duplicate insert
+        assert!(!index.insert(path).unwrap());
+        assert_eq!(index.len(), 1);
+    }
+
+    #[test]
+    fn test_extension_search() {
+        let mut index = FileIndex::new();
+        index.insert(PathBuf::from("C:\\test\\doc.pdf")).unwrap();
+        index.insert(PathBuf::from("C:\\test\\notes.txt")).unwrap();
+        
+        let results = index.search(".pdf");
+        assert_eq!(results.len(), 1);
+        assert!(results[0].to_string_lossy().contains("doc.pdf"));
+    }
+
+    #[test]
+    fn test_regex_search() {
+        let mut index = FileIndex::new();
+        for path in [
+            "/finance/report_2023.xlsx",
+            "/finance/Report_2024.XLSX",
+            "/finance/report_2024_old.xlsx",
+            "/finance/report_24.xlsx",
+            "/archive/2023/summary.pdf",
+            "/notes/café_2023.txt",
+        ] {
+            index.insert(PathBuf::from(path)).unwrap();
+        }
+        
+        // Anchored and case-insensitive
+        let mut found = index.search(r"re:^report_\d{4}\.xlsx$");
+        found.sort();
+        assert_eq!(found, vec![PathBuf::from("/finance/Report_2024.XLSX"), PathBuf::from("/finance/report_2023.xlsx")]);
+        // Unanchored patterns match anywhere, slashes work like re:
+        assert_eq!(index.search(r"/_\d{2}\./"), vec![PathBuf::from("/finance/report_24.xlsx")]);
+        // Classes, checked against names as written rather than lowercased keys
+        assert_eq!(index.search("re:^caf[é]_"), vec![PathBuf::from("/notes/café_2023.txt")]);
+        assert_eq!(index.search("re:2023").len(), 2);
+        // repath: sees the folders too
+        assert_eq!(index.search(r"repath:/archive/\d+/"), vec![PathBuf::from("/archive/2023/summary.pdf")]);
+        
+        let invalid = parse_query(r"re:report_(\d{4}");
+        assert!(matches!(invalid, ParsedQuery::Invalid(reason) if reason.starts_with("Invalid regular expression: unclosed group")));
+        assert_eq!(parse_query("re:"), ParsedQuery::Hint(REGEX_HINT));
+        // A backslash in a pattern does not make it a path
+        assert!(matches!(parse_query(r"re:\d+"), ParsedQuery::Regex(_)));
+        
+        let reason = explain_match(r"re:_\d{2}\.", Path::new("/finance/report_24.xlsx")).unwrap();
+        assert_eq!(reason.to_string(), r"Filename matches the regular expression /_\d{2}\./");
+        assert_eq!(match_ranges(&parse_query(r"re:\d+"), "Report_2024.XLSX"), vec![7..11]);
+    }
+
+    #[test]
+    fn test_substring_search() {
+        let mut index = FileIndex::new();
+        index.insert(PathBuf::from("C:\\test\\budget_2024.xlsx")).unwrap();
+        index.insert(PathBuf::from("C:\\test\\budget_report.pdf")).unwrap();
+        index.insert(PathBuf::from("C:\\test\\invoice.pdf")).unwrap();
+        
+        let results = index.search("budget");
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn test_fuzzy_distance_counts_typos() {
+        assert_eq!(fuzzy_distance("document.pdf", "docmuent", 2), Some(1));
+        assert_eq!(fuzzy_distance("budget_2024.xlsx", "bugdet", 1), Some(1));
+        assert_eq!(fuzzy_distance("my_budget.xlsx", "budget", 1), Some(0));
+        assert_eq!(fuzzy_distance("budget.xlsx", "budgt", 1), Some(1));
+        assert_eq!(fuzzy_distance("budget.xlsx", "bxdgxt", 1), None);
+        assert_eq!(fuzzy_distance("ab", "abcdef", 1), None);
+        assert_eq!(TypoTolerance::Low.max_typos(3), 0);
+        assert_eq!(TypoTolerance::Low.max_typos(8), 2);
+        assert_eq!(TypoTolerance::High.max_typos(3), 1);
+        assert_eq!(TypoTolerance::Off.max_typos(30), 0);
+    }
+
+    #[test]
+    fn test_fuzzy_search_orders_by_typos() {
+        let mut index = FileIndex::new();
+        index.insert(PathBuf::from("C:\\docs\\document.pdf")).unwrap();
+        index.insert(PathBuf::from("C:\\docs\\docmuent_draft.txt")).unwrap();
+        index.insert(PathBuf::from("C:\\docs\\budget.xlsx")).unwrap();
+        
+        assert_eq!(index.search("docmuent").len(), 1);
+        let fuzzy = index.search_fuzzy("docmuent", usize::MAX, SortOrder::Name, TypoTolerance::Low);
+        assert_eq!(
+            fuzzy.paths,
+            vec![PathBuf::from("C:\\docs\\docmuent_draft.txt"), PathBuf::from("C:\\docs\\document.pdf")]
+        );
+        
+        assert_eq!(index.search_fuzzy("bugdet", usize::MAX, SortOrder::Name, TypoTolerance::Low).paths, vec![PathBuf::from("C:\\docs\\budget.xlsx")]);
+        assert!(index.search_fuzzy("xyzzy", usize::MAX, SortOrder::Name, TypoTolerance::Low).paths.is_empty());
+        assert!(index.search_fuzzy(".pdf", usize::MAX, SortOrder::Name, TypoTolerance::Low).paths.is_empty());
+        
+        let reason = explain_match("bugdet", Path::new("C:\\docs\\budget.xlsx")).unwrap();
+        assert_eq!(reason, MatchReason::Fuzzy { query: "bugdet".to_string(), typos: 1 });
+    }
+
+    #[test]
+    fn test_typo_tolerance_levels() {
+        let mut index = FileIndex::new();
+        for path in ["/docs/report.pdf", "/docs/repot.txt", "/docs/rapport.doc", "/docs/summary.md"] {
+            index.insert(PathBuf::from(path)).unwrap();
+        }
+        let fuzzy = |tolerance| {
+            let found = index.search_fuzzy("report", usize::MAX, SortOrder::Name, tolerance);
+            found.paths.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect::<Vec<String>>()
+        };
+        
+        // Each level keeps the exact match first and adds farther names after it
+        assert_eq!(fuzzy(TypoTolerance::Off), ["report.pdf"]);
+        assert_eq!(fuzzy(TypoTolerance::Low), ["report.pdf", "repot.txt"]);
+        assert_eq!(fuzzy(TypoTolerance::High), ["report.pdf", "repot.txt", "rapport.doc"]);
+    }
+
+    fn glob_matches(pattern: &str, name: &str) -> bool {
+        Glob::parse(pattern).unwrap().matches(name)
+    }
+
+    #[test]
+    fn test_glob_wildcards_and_classes() {
+        assert!(glob_matches("*.tar.*", "backup.tar.gz"));
+        assert!(!glob_matches("*.tar.*", "backup.tar"));
+        assert!(glob_matches("img_????.jpg", "img_0042.jpg"));
+        assert!(!glob_matches("img_????.jpg", "img_042.jpg"));
+        assert!(!glob_matches("img_????.jpg", "img_00042.jpg"));
+        assert!(glob_matches("202[34]_report*", "2023_report.pdf"));
+        assert!(glob_matches("202[34]_report*", "2024_report"));
+        assert!(!glob_matches("202[34]_report*", "2022_report.pdf"));
+        assert!(glob_matches("file[0-9]?", "file7a"));
+        assert!(glob_matches("[!a]*", "bcd"));
+        assert!(!glob_matches("[^a]*", "abc"));
+        assert!(glob_matches("[]x]*", "]"));
+        assert!(glob_matches("a[b*", "a[bcd"));
+    }
+
+    #[test]
+    fn test_glob_edge_cases() {
+        // Leading and trailing stars
+        assert!(glob_matches("*report", "annual_report"));
+        assert!(glob_matches("*report", "report"));
+        assert!(!glob_matches("*report", "report.pdf"));
+        assert!(glob_matches("report*", "report"));
+        assert!(!glob_matches("report*", "my_report"));
+        // Only wildcards
+        assert!(glob_matches("**", ""));
+        assert!(glob_matches("**", "anything.txt"));
+        assert!(glob_matches("???", "abc"));
+        assert!(!glob_matches("???", "ab"));
+        assert!(glob_matches("?*", "a"));
+        assert!(!glob_matches("?*", ""));
+        // Order of the pieces matters
+        assert!(glob_matches("foo*bar", "foo_middle_bar"));
+        assert!(!glob_matches("foo*bar", "barfoo"));
+        assert!(glob_matches("*a*b*", "xxaxxbxx"));
+        assert!(glob_matches("é?", "éa"));
+        
+        assert_eq!(Glob::parse("song [live]"), None);
+        assert_eq!(parse_query("*"), ParsedQuery::BrowseAll);
+        assert!(matches!(parse_query("IMG_????.JPG"), ParsedQuery::Glob(g) if g.as_str() == "img_????.jpg"));
+    }
+
+    #[test]
+    fn test_glob_search() {
+        let mut index = FileIndex::new();
+        index.insert(PathBuf::from("/photos/IMG_0001.jpg")).unwrap();
+        index.insert(PathBuf::from("/photos/IMG_12.jpg")).unwrap();
+        index.insert(PathBuf::from("/backup/site.tar.gz")).unwrap();
+        index.insert(PathBuf::from("/docs/2023_report.pdf")).unwrap();
+        index.insert(PathBuf::from("/docs/2022_report.pdf")).unwrap();
+        
+        assert_eq!(index.search("IMG_????.jpg"), vec![PathBuf::from("/photos/IMG_0001.jpg")]);
+        assert_eq!(index.search("*.tar.*"), vec![PathBuf::from("/backup/site.tar.gz")]);
+        assert_eq!(index.search("202[34]_report*"), vec![PathBuf::from("/docs/2023_report.pdf")]);
+        assert_eq!(index.search("*.jpg").len(), 2);
+        
+        let reason = explain_match("*.tar.*", Path::new("/backup/site.tar.gz")).unwrap();
+        assert_eq!(reason, MatchReason::Glob("*.tar.*".to_string()));
+    }
+
+    #[test]
+    fn test_boolean_search() {
+        let mut index = FileIndex::new();
+        for path in [
+            "/docs/invoice_2024.pdf",
+            "/docs/invoice_2024_draft.pdf",
+            "/docs/invoice_2023.pdf",
+            "/docs/budget.xlsx",
+            "/docs/forecast.xlsx",
+            "/docs/annual report.docx",
+        ] {
+            index.insert(PathBuf::from(path)).unwrap();
+        }
+        
+        assert_eq!(index.search("invoice AND 2024 NOT draft"), vec![PathBuf::from("/docs/invoice_2024.pdf")]);
+        assert_eq!(index.search("invoice 2024").len(), 2);
+        assert_eq!(index.search("budget OR forecast").len(), 2);
+        assert_eq!(index.search("forecast OR invoice 2023").len(), 2);
+        assert_eq!(index.search("invoice NOT .pdf"), Vec::<PathBuf>::new());
+        assert_eq!(index.search("\"annual report\""), vec![PathBuf::from("/docs/annual report.docx")]);
+        // Malformed queries are errors rather than empty results
+        let refined = index.search_refining("NOT draft", usize::MAX, SortOrder::Name, &mut None, &unstopped());
+        assert!(matches!(refined, Err(FlashFindError::InvalidQuery(reason)) if reason.starts_with("NOT only excludes")));
+        assert!(matches!(parse_query("NOT draft"), ParsedQuery::Invalid(_)));
+        
+        // Boolean terms never match by typo
+        assert!(index.search("invoice AND 2O24").is_empty());
+        
+        let reason = explain_match("2024 invoice NOT draft", Path::new("/docs/invoice_2024.pdf")).unwrap();
+        assert_eq!(reason, MatchReason::Filename { query: "2024".to_string(), tier: MatchTier::Token });
+        assert_eq!(explain_match("invoice NOT draft", Path::new("/docs/invoice_2024_draft.pdf")), None);
+    }
+
+    #[test]
+    fn test_excluded_extensions() {
+        let mut index = FileIndex::new();
+        for path in ["/app/error.log", "/app/error.js", "/app/error.js.map", "/app/error_page.html", "/app/old/error.tar.gz"] {
+            index.insert(PathBuf::from(path)).unwrap();
+        }
+        let search = |query: &str| {
+            index.search_refining(query, usize::MAX, SortOrder::Name, &mut None, &unstopped()).map(|found| found.paths)
+        };
+        
+        assert_eq!(
+            search("error !.log -.map").unwrap(),
+            [
+                PathBuf::from("/app/error.js"),
+                PathBuf::from("/app/old/error.tar.gz"),
+                PathBuf::from("/app/error_page.html"),
+            ]
+        );
+        assert_eq!(search("error !.tar.gz").unwrap().len(), 4);
+        // An extension no file has leaves the results alone
+        assert_eq!(search("error !.xyz").unwrap(), search("error").unwrap());
+        // Leaving out every matching extension is an empty result, not an error
+        assert_eq!(search(".log !.log").unwrap(), Vec::<PathBuf>::new());
+        assert_eq!(search("error !.log !.js !.map !.html !.gz").unwrap(), Vec::<PathBuf>::new());
+    }
+
+    #[test]
+    fn test_extension_lists() {
+        let mut index = FileIndex::new();
+        for path in ["/photos/beach.JPG", "/photos/beach.png", "/photos/beach.raw", "/photos/city.webp", "/notes/beach.txt"] {
+            index.insert(PathBuf::from(path)).unwrap();
+        }
+        let search = |query: &str| {
+            index.search_refining(query, usize::MAX, SortOrder::Name, &mut None, &unstopped()).map(|found| found.paths)
+        };
+        
+        assert_eq!(search(".jpg|.png|.webp").unwrap().len(), 3);
+        assert_eq!(
+            search("beach .jpg | .png").unwrap(),
+            [PathBuf::from("/photos/beach.JPG"), PathBuf::from("/photos/beach.png")]
+        );
+        assert_eq!(
+            search("beach !.jpg|.png").unwrap(),
+            [PathBuf::from("/photos/beach.raw"), PathBuf::from("/notes/beach.txt")]
+        );
+        assert_eq!(search("beach NOT .raw|.txt OR city").unwrap().len(), 3);
+        assert!(matches!(search(".jpg|"), Err(FlashFindError::InvalidQuery(_))));
+        
+        let reason = explain_match(".txt|.tar.gz", Path::new("/backups/site.tar.gz")).unwrap();
+        assert_eq!(reason, MatchReason::CompoundExtension("tar.gz".to_string()));
+    }
+
+    #[test]
+    fn test_acronyms_match_after_names_containing_the_query() {
+        let mut index = FileIndex::new();
+        for path in [
+            "/finance/financial_budget_report_2024.xlsx",
+            "/finance/Fiscal-Budget-Review.pdf",
+            "/src/FooBarRenderer.ts",
+            "/notes/fbr_checklist.txt",
+            "/notes/fibre.txt",
+        ] {
+            index.insert(PathBuf::from(path)).unwrap();
+        }
+        assert_eq!(index.search("fbr"), vec![PathBuf::from("/notes/fbr_checklist.txt")]);
+        
+        index.set_acronym_matching(true);
+        let found = index.search("fbr");
+        assert_eq!(found.len(), 4);
+        assert_eq!(found[0], PathBuf::from("/notes/fbr_checklist.txt"));
+        assert_eq!(index.search("FooBar").len(), 1);
+        // Only short all-letter queries are read as initials
+        assert_eq!(index.search("fb2").len(), 0);
+        
+        // Kept current as files come and go
+        index.remove(Path::new("/src/FooBarRenderer.ts")).unwrap();
+        index.insert(PathBuf::from("/src/fast_batch_runner.py")).unwrap();
+        assert!(index.search("fbr").contains(&PathBuf::from("/src/fast_batch_runner.py")));
+        assert!(!index.search("fbr").contains(&PathBuf::from("/src/FooBarRenderer.ts")));
+        
+        let reason = explain_match("fbr", Path::new("/finance/Fiscal-Budget-Review.pdf")).unwrap();
+        assert_eq!(reason, MatchReason::Acronym { query: "fbr".to_string(), acronym: "fbr".to_string() });
+        
+        index.set_acronym_matching(false);
+        assert_eq!(index.search("fbr").len(), 1);
+    }
+    
+    #[test]
+    fn test_full_path_search() {
+        let mut index = FileIndex::new();
+        index.insert(PathBuf::from("/home/me/tax-2023/receipts/scan_01.jpg")).unwrap();
+        index.insert(PathBuf::from("/home/me/tax-2023/summary.pdf")).unwrap();
+        index.insert(PathBuf::from("/home/me/tax-2024/summary.pdf")).unwrap();
+        index.insert(PathBuf::from("/home/me/notes/tax-2023.txt")).unwrap();
+        
+        assert_eq!(parse_query("PATH:Tax-2023/Receipts"), ParsedQuery::FullPath("tax-2023\\receipts".into()));
+        assert_eq!(parse_query("path:  "), ParsedQuery::Hint(FULL_PATH_HINT));
+        assert_eq!(parse_query("path:\"Tax 2023\""), ParsedQuery::FullPath("tax 2023".into()));
+        assert!(matches!(parse_query("path:\"Tax 2023\" .pdf"), ParsedQuery::Boolean(_)));
+        assert!(index.search("tax-2023/receipts").is_empty());
+        assert_eq!(
+            index.search("path:tax-2023/receipts"),
+            vec![PathBuf::from("/home/me/tax-2023/receipts/scan_01.jpg")]
+        );
+        assert_eq!(
+            index.search("path:tax-2023"),
+            vec![
+                PathBuf::from("/home/me/tax-2023/receipts/scan_01.jpg"),
+                PathBuf::from("/home/me/tax-2023/summary.pdf"),
+                PathBuf::from("/home/me/notes/tax-2023.txt"),
+            ]
+        );
+        assert_eq!(index.search("path:tax-2023 .pdf"), vec![PathBuf::from("/home/me/tax-2023/summary.pdf")]);
+        
+        let limited = index.search_limited("path:/home/me", 2, SortOrder::Name);
+        assert!(limited.truncated);
+        assert_eq!(limited.paths.len(), 2);
+        
+        let reason = explain_match("path:tax-2023", Path::new("/home/me/tax-2023/summary.pdf")).unwrap();
+        assert_eq!(reason, MatchReason::FullPath("tax-2023".to_string()));
+    }
+
+    #[test]
+    fn test_multi_word_queries_match_all_words_in_any_order() {
+        let mut index = FileIndex::new();
+        index.insert(PathBuf::from("/work/project_plan_v2.docx")).unwrap();
+        index.insert(PathBuf::from("/work/Plan for the project.txt")).unwrap();
+        index.insert(PathBuf::from("/work/project_budget.xlsx")).unwrap();
+        index.insert(PathBuf::from("/work/floor plan.pdf")).unwrap();
+        
+        let forward = index.search("project plan");
+        assert_eq!(forward.len(), 2);
+        assert_eq!(index.search("plan project"), forward);
+        assert_eq!(index.search("  PROJECT   plan "), forward);
+        
+        // A word nothing matches empties the result, wherever it appears
+        assert!(index.search("zzz project plan").is_empty());
+        assert!(index.search("project zzz plan").is_empty());
+        assert_eq!(intersect_sorted(&[1, 3, 5, 7], &[3, 4, 7]), vec![3, 7]);
+    }
+
+    #[test]
+    fn test_quoted_phrase_search() {
+        let mut index = FileIndex::new();
+        index.insert(PathBuf::from("/docs/Annual Report 2023.pdf")).unwrap();
+        index.insert(PathBuf::from("/docs/annual_report.pdf")).unwrap();
+        index.insert(PathBuf::from("/docs/report annual.txt")).unwrap();
+        index.insert(PathBuf::from("/docs/release v1.2-rc.tar.gz")).unwrap();
+        
+        assert_eq!(index.search("\"annual report\""), vec![PathBuf::from("/docs/Annual Report 2023.pdf")]);
+        assert_eq!(index.search("annual report").len(), 3);
+        assert_eq!(index.search("\"V1.2-RC.tar\""), vec![PathBuf::from("/docs/release v1.2-rc.tar.gz")]);
+        
+        assert!(matches!(parse_query("\"annual report"), ParsedQuery::Invalid(m) if m.contains("Unbalanced quote")));
+        assert!(matches!(parse_query("path:\"my docs"), ParsedQuery::Invalid(_)));
+        let refined = index.search_refining("\"annual report", usize::MAX, SortOrder::Name, &mut None, &unstopped());
+        assert!(matches!(refined, Err(FlashFindError::InvalidQuery(reason)) if reason.contains("at character 1")));
+    }
+
+    #[test]
+    fn test_name_and_extension_queries() {
+        let mut index = FileIndex::new();
+        index.insert(PathBuf::from("/docs/report_q1.pdf")).unwrap();
+        index.insert(PathBuf::from("/docs/report_q1.docx")).unwrap();
+        index.insert(PathBuf::from("/docs/summary.pdf")).unwrap();
+        index.insert(PathBuf::from("/backups/backup_monday.tar.gz")).unwrap();
+        index.insert(PathBuf::from("/backups/backup_monday.zip")).unwrap();
+        index.insert(PathBuf::from("/backups/site.tar.gz")).unwrap();
+        
+        assert_eq!(parse_query("ext:PDF"), ParsedQuery::Extension("pdf".into()));
+        assert_eq!(parse_query("EXT:.tar.gz"), ParsedQuery::Extension("tar.gz".into()));
+        assert_eq!(parse_query("ext:"), ParsedQuery::Hint(EXTENSION_HINT));
+        
+        let pdf_report = vec![PathBuf::from("/docs/report_q1.pdf")];
+        assert_eq!(index.search("report .pdf"), pdf_report);
+        assert_eq!(index.search("report ext:pdf"), pdf_report);
+        assert_eq!(index.search(".PDF Report"), pdf_report);
+        assert_eq!(index.search("backup .tar.gz"), vec![PathBuf::from("/backups/backup_monday.tar.gz")]);
+        assert_eq!(index.search("backup ext:tar.gz"), vec![PathBuf::from("/backups/backup_monday.tar.gz")]);
+        assert!(index.search("summary .docx").is_empty());
+        
+        let reason = explain_match("report ext:pdf", Path::new("/docs/report_q1.pdf")).unwrap();
+        assert_eq!(reason, MatchReason::Filename { query: "report".to_string(), tier: MatchTier::Prefix });
+    }
+
+    #[test]
+    fn test_max_capacity() {
+        // Filling a real pool would take too long, so the boundary is checked
+        // on pool lengths alone
+        assert_eq!(next_slot(0, MAX_INDEX_SIZE).unwrap(), 0);
+        assert_eq!(next_slot(MAX_INDEX_SIZE - 1, MAX_INDEX_SIZE).unwrap(), MAX_INDEX_SIZE as u32 - 1);
+        assert!(matches!(next_slot(MAX_INDEX_SIZE, MAX_INDEX_SIZE), Err(FlashFindError::IndexFull(MAX_INDEX_SIZE))));
+        
+        // Past u32 ids the limit itself no longer matters
+        let slots = u32::MAX as usize + 1;
+        assert_eq!(next_slot(slots - 1, usize::MAX).unwrap(), u32::MAX);
+        assert!(matches!(next_slot(slots, usize::MAX), Err(FlashFindError::IndexFull(max)) if max == slots));
+    }
+
+    #[test]
+    fn test_out_of_range_ids_are_rejected_on_load() {
+        let mut index = FileIndex::new();
+        index.insert(PathBuf::from("/docs/report.pdf")).unwrap();
+        assert!(index.validate().is_ok());
+        
+        let dir = crate::test_dir::TestDir::new("crafted");
+        let file = dir.join("index.bin");
+        index.filename_index.get_mut("report.pdf").unwrap().push(40);
+        // As a v10 file held it, before the shards
+        index.version = 10;
+        std::fs::write(&file, bincode::serialize(&index).unwrap()).unwrap();
+        assert!(matches!(crate::persistence::load_index_from(&file), Err(FlashFindError::CorruptedIndex(_))));
+    }
+
+    #[test]
+    fn test_unsharded_v10_index_is_split_on_load() {
+        let dir = crate::test_dir::TestDir::new("index_v10_split");
+        let path = dir.join("index.bin");
+        let mut index = FileIndex::new();
+        for name in ["a", "b", "c", "d", "e", "f"] {
+            index.insert(PathBuf::from(format!("/docs/{}.txt", name))).unwrap();
+        }
+        index.version = 10;
+        std::fs::write(&path, bincode::serialize(&index).unwrap()).unwrap();
+
+        let loaded = crate::persistence::load_index_from(&path).unwrap();
+        assert_eq!(loaded.read().len(), 6);
+        assert_eq!(loaded.search("c.txt"), vec![PathBuf::from("/docs/c.txt")]);
+        assert!(loaded.read().check_invariants().is_consistent());
+
+        // Saved again, it comes back as shards
+        crate::persistence::save_index_to(&loaded.read(), &path).unwrap();
+        assert_eq!(bincode::deserialize::<u32>(&std::fs::read(&path).unwrap()).unwrap(), INDEX_VERSION);
+        assert_eq!(crate::persistence::load_index_from(&path).unwrap().read().len(), 6);
+    }
+
+    #[test]
+    fn test_remove_where() {
+        let mut index = FileIndex::new();
+        index.insert(PathBuf::from("C:\\test\\a.dll")).unwrap();
+        index.insert(PathBuf::from("C:\\test\\b.txt")).unwrap();
+
+        let removed = index.remove_where(|p| p.to_string_lossy().ends_with(".dll"));
+        assert_eq!(removed, 1);
+        assert!(index.search(".dll").is_empty());
+        assert_eq!(index.search(".txt").len(), 1);
+    }
+
+    fn browse_index() -> FileIndex {
+        let mut index = FileIndex::new();
+        index.insert(PathBuf::from("C:\\Users\\me\\Documents\\report.pdf")).unwrap();
+        index.insert(PathBuf::from("C:\\Users\\me\\Downloads\\setup.exe")).unwrap();
+        index.insert(PathBuf::from("C:\\Users\\me\\docs.txt")).unwrap();
+        index.insert(PathBuf::from("D:\\Media\\movie.mkv")).unwrap();
+        index
+    }
+
+    #[test]
+    fn test_is_path_query() {
+        assert!(is_path_query("c:"));
+        assert!(is_path_query("C:\\"));
+        assert!(is_path_query("users\\me"));
+        assert!(!is_path_query("report"));
+        assert!(!is_path_query(".pdf"));
+    }
+
+    #[test]
+    fn test_parse_degenerate_queries() {
+        assert_eq!(parse_query(""), ParsedQuery::Empty);
+        assert_eq!(parse_query("   \t "), ParsedQuery::Empty);
+        assert_eq!(parse_query("."), ParsedQuery::Hint(EXTENSION_HINT));
+        assert_eq!(parse_query(" . "), ParsedQuery::Hint(EXTENSION_HINT));
+        assert_eq!(parse_query("*"), ParsedQuery::BrowseAll);
+        assert_eq!(parse_query(".."), ParsedQuery::Filename("..".into()));
+        assert_eq!(parse_query("..."), ParsedQuery::Filename("...".into()));
+    }
+
+    #[test]
+    fn test_parse_regular_queries() {
+        assert_eq!(parse_query(".PDF"), ParsedQuery::Extension("pdf".into()));
+        assert_eq!(parse_query(".tar.gz"), ParsedQuery::Extension("tar.gz".into()));
+        assert_eq!(parse_query(" Report "), ParsedQuery::Filename("report".into()));
+        assert_eq!(parse_query("c:\\users"), ParsedQuery::PathPrefix("c:\\users".into()));
+        assert_eq!(parse_query("^IMG_"), ParsedQuery::StartsWith("img_".into()));
+        assert_eq!(parse_query("^"), ParsedQuery::Hint(STARTS_WITH_HINT));
+        assert!(matches!(parse_query("^IMG_ .jpg"), ParsedQuery::Boolean(_)));
+    }
+
+    #[test]
+    fn test_degenerate_queries_search() {
+        let mut index = FileIndex::new();
+        index.insert(PathBuf::from("C:\\a\\report.pdf")).unwrap();
+        index.insert(PathBuf::from("C:\\a\\archive..old")).unwrap();
+
+        assert!(index.search("   ").is_empty());
+        assert!(index.search(".").is_empty());
+        assert!(index.search("*").is_empty());
+        assert_eq!(index.search("..").len(), 1);
+        assert_eq!(index.browse_all(0, PATH_PAGE_SIZE).total, 2);
+    }
+
+    #[test]
+    fn test_drive_only_query() {
+        let index = browse_index();
+        assert_eq!(index.search_path_prefix("c:", 0, PATH_PAGE_SIZE).unwrap().total, 3);
+        assert_eq!(index.search_path_prefix("C:\\", 0, PATH_PAGE_SIZE).unwrap().total, 3);
+        assert_eq!(index.search_path_prefix("d:", 0, PATH_PAGE_SIZE).unwrap().total, 1);
+    }
+
+    #[test]
+    fn test_nested_prefix_query() {
+        let index = browse_index();
+        let page = index.search_path_prefix("c:\\users\\me\\doc", 0, PATH_PAGE_SIZE).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.paths[0], PathBuf::from("C:\\Users\\me\\docs.txt"));
+
+        let page = index.search_path_prefix("C:\\Users\\me\\Documents\\", 0, PATH_PAGE_SIZE).unwrap();
+        assert_eq!(page.total, 1);
+        let page = index.search_path_prefix("c:/users/me/", 0, PATH_PAGE_SIZE).unwrap();
+        assert_eq!(page.total, 3);
+    }
+
+    #[test]
+    fn test_paths_within_matches_whole_components() {
+        let mut index = browse_index();
+        index.remove(Path::new("C:\\Users\\me\\docs.txt")).unwrap();
+        let mut within = index.paths_within(Path::new("C:\\Users\\me"));
+        within.sort();
+        assert_eq!(within, vec![
+            PathBuf::from("C:\\Users\\me\\Documents\\report.pdf"),
+            PathBuf::from("C:\\Users\\me\\Downloads\\setup.exe"),
+        ]);
+        assert!(index.paths_within(Path::new("C:\\Users\\me\\Doc")).is_empty());
+    }
+
+    #[test]
+    fn test_unindexed_drive_query() {
+        let index = browse_index();
+        assert!(matches!(
+            index.search_path_prefix("q:", 0, PATH_PAGE_SIZE),
+            Err(FlashFindError::DriveNotIndexed('Q'))
+        ));
+        // Known drive with no match is just an empty page
+        assert_eq!(index.search_path_prefix("c:\\nothing", 0, PATH_PAGE_SIZE).unwrap().total, 0);
+    }
+
+    #[test]
+    fn test_path_query_paging() {
+        let index = browse_index();
+        let first = index.search_path_prefix("c:", 0, 2).unwrap();
+        let second = index.search_path_prefix("c:", 2, 2).unwrap();
+        assert_eq!(first.paths.len(), 2);
+        assert_eq!(second.paths.len(), 1);
+        assert_eq!(second.total, 3);
+        assert!(!first.paths.contains(&second.paths[0]));
+    }
+
+    #[test]
+    fn test_case_differing_duplicates() {
+        let mut index = FileIndex::new();
+        assert!(index.insert(PathBuf::from("C:\\Docs\\File.txt")).unwrap());
+        assert!(!index.insert(PathBuf::from("c:\\docs\\file.txt")).unwrap());
+        assert!(!index.insert(PathBuf::from("C:/DOCS/FILE.TXT")).unwrap());
+
+        // Original casing is preserved for display and opening
+        let results = index.search("file");
+        assert_eq!(results, vec![PathBuf::from("C:\\Docs\\File.txt")]);
+    }
+
+    #[test]
+    fn test_remove_by_either_casing() {
+        let mut index = FileIndex::new();
+        index.insert(PathBuf::from("C:\\Docs\\File.txt")).unwrap();
+        assert!(index.remove(&PathBuf::from("c:\\docs\\FILE.txt")).unwrap());
+        assert!(index.search("file").is_empty());
+
+        index.insert(PathBuf::from("c:\\docs\\file.txt")).unwrap();
+        assert!(index.remove(&PathBuf::from("C:\\Docs\\File.txt")).unwrap());
+        assert!(index.search("file").is_empty());
+    }
+
+    #[test]
+    fn test_reinsert_after_remove_single_result() {
+        let mut index = FileIndex::new();
+        let path = PathBuf::from("C:\\Docs\\File.txt");
+        index.insert(path.clone()).unwrap();
+        index.remove(&path).unwrap();
+        index.insert(path.clone()).unwrap();
+        assert_eq!(index.search("file"), vec![path]);
+    }
+
+    #[test]
+    fn test_removed_entries_stay_gone() {
+        let mut index = FileIndex::new();
+        let report = PathBuf::from("C:\\Docs\\report.pdf");
+        let notes = PathBuf::from("C:\\Docs\\notes.txt");
+        index.insert(report.clone()).unwrap();
+        index.insert(notes.clone()).unwrap();
+        
+        assert!(index.remove(&report).unwrap());
+        assert!(!index.remove(&report).unwrap());
+        assert_eq!(index.len(), 1);
+        assert!(index.search("report").is_empty());
+        assert!(index.search(".pdf").is_empty());
+        
+        // A reload neither resurrects nor recounts the removed entry
+        let mut loaded: FileIndex = bincode::deserialize(&bincode::serialize(&index).unwrap()).unwrap();
+        loaded.rebuild_cache();
+        assert_eq!(loaded.len(), 1);
+        assert!(loaded.search("report").is_empty());
+        
+        // Reinserting takes a fresh slot that searches find exactly once
+        assert!(loaded.insert(report.clone()).unwrap());
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.search("report"), vec![report.clone()]);
+        assert!(loaded.remove(&notes).unwrap());
+        assert_eq!(loaded.len(), 1);
+        assert!(!loaded.is_empty());
+    }
+
+    #[test]
+    fn test_compaction_keeps_search_results() {
+        let mut index = FileIndex::new();
+        for i in 0..50 {
+            index.insert(PathBuf::from(format!("/data/file_{}.{}", i, if i % 2 == 0 { "txt" } else { "log" }))).unwrap();
+        }
+        index.remove_where(|path| path.to_string_lossy().contains("_1"));
+        let queries = ["file", ".txt", "file_2 OR file_3", "path:data .log", "^file_4", "*_3?.txt"];
+        let before: Vec<Vec<PathBuf>> = queries.iter().map(|q| index.search(q)).collect();
+        let live = index.len();
+        
+        assert_eq!(index.compact().unwrap(), 50 - live);
+        assert_eq!(index.len(), live);
+        let after: Vec<Vec<PathBuf>> = queries.iter().map(|q| index.search(q)).collect();
+        assert_eq!(after, before);
+        assert!(index.search("file_1").is_empty());
+    }
+
+    #[test]
+    fn test_compaction_built_aside_applies_only_to_an_unchanged_index() {
+        let mut index = FileIndex::new();
+        index.set_trigram_index(true);
+        for i in 0..10 {
+            index.insert(PathBuf::from(format!("/docs/report_{}.pdf", i))).unwrap();
+        }
+        index.remove_where(|path| path.to_string_lossy().ends_with("_3.pdf") || path.to_string_lossy().ends_with("_7.pdf"));
+        assert_eq!(index.tombstones(), 2);
+        assert!((index.dead_ratio() - 0.2).abs() < 1e-9);
+        let before = index.search("report");
+        
+        // A change after building makes the compaction stale
+        let stale = index.compaction().unwrap();
+        index.insert(PathBuf::from("/docs/report_new.pdf")).unwrap();
+        assert_eq!(index.apply_compaction(stale), None);
+        assert_eq!(index.tombstones(), 2);
+        
+        let compaction = index.compaction().unwrap();
+        let generation = index.generation();
+        assert_eq!(index.apply_compaction(compaction), Some(2));
+        assert_ne!(index.generation(), generation);
+        assert_eq!(index.tombstones(), 0);
+        assert_eq!(index.dead_ratio(), 0.0);
+        assert!(index.compaction().is_none());
+        assert_eq!(index.search("report").len(), before.len() + 1);
+        assert_eq!(index.search("report_1"), vec![PathBuf::from("/docs/report_1.pdf")]);
+        assert!(index.trigram_memory().is_some());
+        assert!(index.check_invariants().is_consistent());
+    }
+    
+    #[test]
+    fn test_iteration_skips_tombstones_in_pool_order() {
+        let mut index = FileIndex::new();
+        for name in ["a", "b", "c", "d"] {
+            index.insert(PathBuf::from(format!("/docs/{}.txt", name))).unwrap();
+        }
+        index.remove(Path::new("/docs/b.txt")).unwrap();
+        index.remove(Path::new("/docs/a.txt")).unwrap();
+        // Re-adding takes a new slot; the old one stays a tombstone
+        index.insert(PathBuf::from("/docs/a.txt")).unwrap();
+        assert_eq!(index.tombstones(), 2);
+        
+        let entries: Vec<(u32, PathBuf)> = index.iter().collect();
+        let expected = [(2, "/docs/c.txt"), (3, "/docs/d.txt"), (4, "/docs/a.txt")];
+        assert_eq!(entries, expected.map(|(idx, path)| (idx, PathBuf::from(path))));
+        
+        let mut parallel: Vec<(u32, PathBuf)> = index.par_iter().collect();
+        parallel.sort();
+        assert_eq!(parallel, entries);
+        
+        index.compact().unwrap();
+        let paths: Vec<PathBuf> = index.iter().map(|(_, path)| path).collect();
+        assert_eq!(paths, ["/docs/c.txt", "/docs/d.txt", "/docs/a.txt"].map(PathBuf::from));
+    }
+    
+    #[test]
+    fn test_directory_index_follows_moved_files() {
+        let mut index = FileIndex::new();
+        for name in ["plan.txt", "budget.xlsx", "notes.txt"] {
+            index.insert(PathBuf::from(format!("/docs/Work/{}", name))).unwrap();
+        }
+        index.insert(PathBuf::from("/docs/Archive/old.txt")).unwrap();
+        let counts = |index: &FileIndex| {
+            let mut counts: Vec<(String, usize)> = index.folder_counts().map(|(folder, n)| (folder.to_string(), n)).collect();
+            counts.sort();
+            counts
+        };
+        assert_eq!(counts(&index), [("\\docs\\archive".to_string(), 1), ("\\docs\\work".to_string(), 3)]);
+        
+        // A move is a removal and an insertion under the new path
+        index.remove(Path::new("/docs/Work/plan.txt")).unwrap();
+        index.insert(PathBuf::from("/docs/Archive/plan.txt")).unwrap();
+        assert_eq!(counts(&index), [("\\docs\\archive".to_string(), 2), ("\\docs\\work".to_string(), 2)]);
+        assert_eq!(index.search("in:archive plan"), vec![PathBuf::from("/docs/Archive/plan.txt")]);
+        assert!(index.search("in:work plan").is_empty());
+        assert_eq!(index.folder_path("\\docs\\archive"), Some(PathBuf::from("/docs/Archive")));
+        
+        // The last file leaving a folder drops it
+        index.remove(Path::new("/docs/Archive/old.txt")).unwrap();
+        index.remove(Path::new("/docs/Archive/plan.txt")).unwrap();
+        assert_eq!(counts(&index), [("\\docs\\work".to_string(), 2)]);
+        assert!(index.check_invariants().is_consistent());
+        
+        // Rebuilt after loading, and renumbered by compaction
+        let mut loaded: FileIndex = bincode::deserialize(&bincode::serialize(&index).unwrap()).unwrap();
+        loaded.rebuild_cache();
+        assert_eq!(counts(&loaded), counts(&index));
+        loaded.compact().unwrap();
+        assert_eq!(loaded.search("in:work"), vec![PathBuf::from("/docs/Work/budget.xlsx"), PathBuf::from("/docs/Work/notes.txt")]);
+        assert!(loaded.check_invariants().is_consistent());
+    }
+    
+    #[test]
+    fn test_removing_a_drive_leaves_other_drives_untouched() {
+        let mut index = FileIndex::new();
+        for path in ["C:\\Users\\me\\report.pdf", "C:\\Users\\me\\Docs\\notes.txt", "c:\\Temp\\report.tmp"] {
+            index.insert(PathBuf::from(path)).unwrap();
+        }
+        let before = index.search("report");
+        
+        // Enabling D: adds its entries alongside C:'s
+        for path in ["D:\\Media\\report.mp4", "D:\\Media\\Music\\song.mp3"] {
+            index.insert(PathBuf::from(path)).unwrap();
+        }
+        assert_eq!(index.drive_counts(), [('C', 3), ('D', 2)]);
+        assert!(index.has_drive('d'));
+        
+        // Disabling it again removes only its entries
+        assert_eq!(index.remove_drive('d'), 2);
+        assert_eq!(index.remove_drive('E'), 0);
+        assert_eq!(index.drive_counts(), [('C', 3)]);
+        assert!(!index.has_drive('D'));
+        assert_eq!(index.search("report"), before);
+        assert_eq!(index.tombstones(), 2);
+        assert!(index.check_invariants().is_consistent());
+    }
+    
+    #[test]
+    fn test_extension_counts_most_common_first() {
+        let mut index = FileIndex::new();
+        let files = [
+            "/docs/a.pdf", "/docs/b.PDF", "/docs/c.pdf", "/pics/a.jpg", "/pics/b.png", "/pics/c.jpg",
+            "/src/backup.tar.gz", "/src/main.rs", "/src/notes.txt", "/src/README",
+        ];
+        for path in files {
+            index.insert(PathBuf::from(path)).unwrap();
+        }
+        let folder = EntryMeta { is_dir: true, ..EntryMeta::UNKNOWN };
+        index.insert_with_meta(PathBuf::from("/src/site.jpg"), folder).unwrap();
+        index.remove(Path::new("/docs/c.pdf")).unwrap();
+        
+        let counts = index.extension_counts();
+        let expected = [("jpg", 2), ("pdf", 2), ("gz", 1), ("png", 1), ("rs", 1), ("txt", 1)];
+        assert_eq!(counts, expected.map(|(ext, files)| (ext.to_string(), files)));
+        
+        let images = ["jpg", "png", "gif"].map(String::from);
+        assert_eq!(index.count_with_extensions(&images), 3);
+        assert_eq!(index.count_with_extensions(&["tar.gz".to_string(), "gz".to_string()]), 1);
+        assert_eq!(index.count_with_extensions(&[]), 0);
+    }
+    
+    #[test]
+    fn test_rename_keeps_slot_and_metadata() {
+        let mut index = FileIndex::new();
+        index.set_trigram_index(true);
+        let dated = EntryMeta { size: 2048, modified: 1_700_000_000, is_dir: false, attributes: 0 };
+        index.insert_with_meta(PathBuf::from("/docs/draft.tmp"), dated).unwrap();
+        index.insert(PathBuf::from("/docs/other.txt")).unwrap();
+        let slot = |index: &FileIndex, path: &str| index.iter().find(|(_, p)| p == Path::new(path)).map(|(idx, _)| idx);
+        
+        // Only the extension
+        assert!(index.rename(Path::new("/docs/draft.tmp"), PathBuf::from("/docs/draft.docx")).unwrap());
+        assert_eq!(slot(&index, "/docs/draft.docx"), Some(0));
+        assert!(index.search(".tmp").is_empty());
+        assert_eq!(index.search(".docx"), vec![PathBuf::from("/docs/draft.docx")]);
+        
+        // Only the folder
+        index.rename(Path::new("/docs/draft.docx"), PathBuf::from("/final/draft.docx")).unwrap();
+        assert_eq!(index.search("in:final draft"), vec![PathBuf::from("/final/draft.docx")]);
+        assert!(index.search("in:docs draft").is_empty());
+        
+        // Both, to a name found through the trigrams
+        index.rename(Path::new("/final/draft.docx"), PathBuf::from("/archive/report.pdf")).unwrap();
+        assert_eq!(slot(&index, "/archive/report.pdf"), Some(0));
+        assert_eq!(index.search("eport"), vec![PathBuf::from("/archive/report.pdf")]);
+        assert!(index.search("draft").is_empty());
+        assert_eq!(index.metadata_for(Path::new("/archive/report.pdf")), Some(dated));
+        assert_eq!((index.len(), index.pool_size()), (2, 2));
+        
+        // Renaming over an indexed file replaces it
+        index.rename(Path::new("/docs/other.txt"), PathBuf::from("/archive/report.pdf")).unwrap();
+        assert_eq!(slot(&index, "/archive/report.pdf"), Some(1));
+        assert_eq!(index.len(), 1);
+        
+        assert!(!index.rename(Path::new("/docs/missing.txt"), PathBuf::from("/docs/found.txt")).unwrap());
+        assert!(index.rename(Path::new("/archive/report.pdf"), PathBuf::from("/")).is_err());
+        assert!(index.check_invariants().is_consistent());
+    }
+    
+    #[test]
+    fn test_renamed_folder_takes_its_entries_along() {
+        let mut index = FileIndex::new();
+        let folder = EntryMeta { is_dir: true, ..EntryMeta::UNKNOWN };
+        index.insert_with_meta(PathBuf::from("/docs/New folder"), folder).unwrap();
+        index.insert(PathBuf::from("/docs/New folder/a.txt")).unwrap();
+        index.insert(PathBuf::from("/docs/New folder/sub/b.txt")).unwrap();
+        index.insert(PathBuf::from("/docs/New folder (2)/c.txt")).unwrap();
+        
+        index.rename(Path::new("/docs/New folder"), PathBuf::from("/docs/Taxes")).unwrap();
+        let mut paths: Vec<PathBuf> = index.iter().map(|(_, path)| path).collect();
+        paths.sort();
+        let expected = ["/docs/New folder (2)/c.txt", "/docs/Taxes", "/docs/Taxes/a.txt", "/docs/Taxes/sub/b.txt"];
+        assert_eq!(paths, expected.map(PathBuf::from));
+        assert!(index.check_invariants().is_consistent());
+    }
+    
+    #[test]
+    fn test_batch_insert_counts_each_outcome() {
+        let mut index = FileIndex::new();
+        index.insert(PathBuf::from("/docs/Report.pdf")).unwrap();
+        let dated = EntryMeta { size: 10, modified: 1_700_000_000, is_dir: false, attributes: 0 };
+        let batch = PreparedBatch::new(vec![
+            (PathBuf::from("/docs/Report.pdf"), dated),
+            (PathBuf::from("/docs/notes.txt"), EntryMeta::UNKNOWN),
+            (PathBuf::from("/docs/.hidden_notes.txt"), EntryMeta::UNKNOWN),
+            (PathBuf::from("/docs/notes.txt"), EntryMeta::UNKNOWN),
+            (PathBuf::from("/"), EntryMeta::UNKNOWN),
+        ]);
+        let result = index.insert_batch(batch).unwrap();
+        assert_eq!(result, BatchResult { added: 2, duplicates: 2, invalid: 1 });
+        
+        // The same outcome as inserting one at a time
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.metadata_for(Path::new("/docs/Report.pdf")), Some(dated));
+        assert_eq!(index.search("notes"), vec![PathBuf::from("/docs/notes.txt")]);
+        assert!(index.search("is:hidden").contains(&PathBuf::from("/docs/.hidden_notes.txt")));
+        assert!(index.check_invariants().is_consistent());
+    }
+    
+    #[test]
+    fn test_memory_usage_grows_linearly() {
+        // Same-length paths, each in its own folder so nothing is shared
+        let fill = |index: &mut FileIndex, range: Range<usize>| {
+            for i in range {
+                index.insert(PathBuf::from(format!("/data/{:06}/file_{:06}.txt", i, i))).unwrap();
+            }
+        };
+        let mut index = FileIndex::new();
+        let empty = index.memory_usage().total();
+        fill(&mut index, 0..20_000);
+        let first = index.memory_usage();
+        fill(&mut index, 20_000..40_000);
+        let second = index.memory_usage();
+        
+        // Every part grows, and twice the files take about twice the memory;
+        // hash maps and vectors growing by doubling allow some slack
+        assert!(first.pool > 0 && first.seen_paths > 0 && first.filename_index > 0 && first.extension_index > 0);
+        assert_eq!(first.optional, 0);
+        let growth = (second.total() - empty) as f64 / (first.total() - empty) as f64;
+        assert!((1.5..=2.5).contains(&growth), "grew {:.2}x", growth);
+        // At least the path text itself is counted
+        assert!(second.pool > 40_000 * "file_000000.txt".len());
+        
+        index.set_trigram_index(true);
+        assert!(index.memory_usage().optional > 0);
+        assert_eq!(index.memory_usage().total(), index.memory_usage().parts().iter().map(|(_, bytes)| bytes).sum::<usize>());
+    }
+
+    #[test]
+    fn test_lifetime_stats_survive_round_trip() {
+        let mut index = FileIndex::new();
+        let path = PathBuf::from("C:\\test\\file.txt");
+        index.insert(path.clone()).unwrap();
+        index.insert(path.clone()).unwrap();
+        index.search("file");
+        index.record_watcher_event();
+        index.remove(&path).unwrap();
+
+        let data = bincode::serialize(&index).unwrap();
+        let mut loaded: FileIndex = bincode::deserialize(&data).unwrap();
+        loaded.rebuild_cache();
+
+        let expected = StatsSnapshot {
+            insertions: 1,
+            duplicates: 1,
+            removals: 1,
+            searches: 1,
+            watcher_events: 1,
+        };
+        assert_eq!(index.stats().session, expected);
+        assert_eq!(loaded.stats().lifetime, expected);
+        assert_eq!(loaded.stats().session, StatsSnapshot::default());
+
+        // Lifetime counters keep accumulating after reload
+        loaded.search("file");
+        assert_eq!(loaded.stats().lifetime.searches, 2);
+        assert_eq!(loaded.stats().session.searches, 1);
+    }
+
+    #[test]
+    fn test_migrate_v1_index() {
+        let old = FileIndexV1 {
+            version: 1,
+            pool: vec![PathBuf::from("C:\\test\\doc.pdf")],
+            filename_index: [("doc.pdf".to_string(), vec![0])].into_iter().collect(),
+            extension_index: [("pdf".to_string(), vec![0])].into_iter().collect(),
+        };
+        let data = bincode::serialize(&old).unwrap();
+
+        let index = migrate_v1(&data).unwrap();
+        assert_eq!(index.version(), INDEX_VERSION);
+        assert_eq!(index.search(".pdf").len(), 1);
+        assert_eq!(index.stats().lifetime.searches, 1);
+    }
+
+    #[test]
+    fn test_migrate_v2_index_keeps_stats_with_unknown_sizes() {
+        let old = FileIndexV2 {
+            version: 2,
+            pool: vec![PathBuf::from("C:\\test\\doc.pdf")],
+            filename_index: [("doc.pdf".to_string(), vec![0])].into_iter().collect(),
+            extension_index: [("pdf".to_string(), vec![0])].into_iter().collect(),
+            lifetime_stats: StatsSnapshot { searches: 7, ..StatsSnapshot::default() }.into(),
+        };
+        let data = bincode::serialize(&old).unwrap();
+
+        let index = migrate_v2(&data).unwrap();
+        assert_eq!(index.version(), INDEX_VERSION);
+        assert_eq!(index.sizes, vec![UNKNOWN_SIZE]);
+        assert_eq!(index.mtimes, vec![UNKNOWN_MTIME]);
+        assert_eq!(index.search(".pdf").len(), 1);
+        assert!(index.search("size:<1gb").is_empty());
+        assert_eq!(index.stats().lifetime.searches, 9);
+    }
+
+    fn sized(size: u64) -> EntryMeta {
+        EntryMeta { size, ..EntryMeta::UNKNOWN }
+    }
+
+    #[test]
+    fn test_size_filters_compose_with_other_terms() {
+        const MB: u64 = 1 << 20;
+        let mut index = FileIndex::new();
+        index.insert_with_meta(PathBuf::from("/media/movie.mkv"), sized(4000 * MB)).unwrap();
+        index.insert_with_meta(PathBuf::from("/media/trailer.mkv"), sized(80 * MB)).unwrap();
+        index.insert_with_meta(PathBuf::from("/media/poster.jpg"), sized(2 * MB)).unwrap();
+        index.insert_with_meta(PathBuf::from("/docs/notes.txt"), sized(3000)).unwrap();
+        index.insert(PathBuf::from("/docs/unknown.bin")).unwrap();
+        
+        assert_eq!(index.search("size:>1gb"), vec![PathBuf::from("/media/movie.mkv")]);
+        assert_eq!(index.search("size:<4kb"), vec![PathBuf::from("/docs/notes.txt")]);
+        assert_eq!(index.search("size:1mb..100mb").len(), 2);
+        assert_eq!(index.search(".mkv size:<100mb"), vec![PathBuf::from("/media/trailer.mkv")]);
+        assert_eq!(index.search("path:media size:>=2mb NOT .jpg").len(), 2);
+        assert_eq!(index.search("size:0b..").len(), 4);
+        assert_eq!(index.search("size:>1gb mkv"), vec![PathBuf::from("/media/movie.mkv")]);
+        assert!(matches!(parse_query("size:lots"), ParsedQuery::Invalid(_)));
+        assert_eq!(parse_query("SIZE:"), ParsedQuery::Hint(SIZE_HINT));
+        
+        // A rescan or modify event refreshes the size of an indexed file
+        assert!(!index.insert_with_meta(PathBuf::from("/docs/notes.txt"), sized(5 * MB)).unwrap());
+        assert!(index.search("size:<4kb").is_empty());
+        assert!(!index.insert(PathBuf::from("/docs/notes.txt")).unwrap());
+        assert_eq!(index.search("notes size:5mb"), vec![PathBuf::from("/docs/notes.txt")]);
+        
+        // Sizes follow their entries through removal, compaction and reload
+        index.remove(Path::new("/media/trailer.mkv")).unwrap();
+        index.compact().unwrap();
+        let mut loaded: FileIndex = bincode::deserialize(&bincode::serialize(&index).unwrap()).unwrap();
+        loaded.rebuild_cache();
+        assert_eq!(loaded.search("size:>1mb").len(), 3);
+        assert_eq!(loaded.search("size:>1gb"), vec![PathBuf::from("/media/movie.mkv")]);
+    }
+
+    #[test]
+    fn test_describe_uses_stored_metadata() {
+        let mut index = FileIndex::new();
+        let report = PathBuf::from("/docs/Q3 Report.PDF");
+        index.insert_with_meta(report.clone(), EntryMeta { size: 4096, modified: 1_700_000_000, is_dir: false, attributes: 0 }).unwrap();
+        index.insert(PathBuf::from("/docs/Makefile")).unwrap();
+        
+        let found = index.describe(index.search("report"));
+        assert_eq!(
+            found,
+            vec![SearchResult {
+                path: report,
+                file_name: "Q3 Report.PDF".to_string(),
+                extension: Some("PDF".to_string()),
+                size: Some(4096),
+                modified: Some(1_700_000_000),
+                matched: Vec::new(),
+                is_dir: false,
+            }]
+        );
+        // Unknown metadata, and a path the index does not hold
+        let found = index.describe(vec![PathBuf::from("/docs/Makefile"), PathBuf::from("/gone.txt")]);
+        assert_eq!((found[0].size, found[0].modified, found[0].extension.as_deref()), (None, None, None));
+        assert_eq!((found[1].size, found[1].file_name.as_str()), (None, "gone.txt"));
+        
+        let moved = found[0].renamed(PathBuf::from("/docs/GNUmakefile.old"));
+        assert_eq!((moved.file_name.as_str(), moved.extension.as_deref()), ("GNUmakefile.old", Some("old")));
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_match_ranges_cover_each_matched_term() {
+        let ranges = |query: &str, name: &str| match_ranges(&parse_query(query), name);
+        assert_eq!(ranges("report", "Report_final_REPORT.pdf"), [0..6, 13..19]);
+        assert_eq!(ranges("final report", "Report_final_REPORT.pdf"), [0..6, 7..12, 13..19]);
+        assert_eq!(ranges("\"port_f\"", "Report_final.pdf"), [2..8]);
+        assert_eq!(ranges("rep OR .pdf", "Report.pdf"), [0..3, 7..10]);
+        assert_eq!(ranges("report NOT draft", "report draft.txt"), [0..6]);
+        assert_eq!(ranges("^rep", "Report rep.txt"), [0..3]);
+        assert!(ranges("^port", "Report.txt").is_empty());
+        assert_eq!(ranges("words:con", "ControllerConfig.cs"), [0..3, 10..13]);
+        assert!(ranges("words:roll", "Controller.cs").is_empty());
+        assert_eq!(ranges(".tar.gz", "site.tar.gz"), [5..11]);
+        assert_eq!(ranges("in:docs plan", "plan.txt"), [0..4]);
+        // Offsets are bytes of the name as written, accents and all
+        assert_eq!(ranges("resume", "Mon Re\u{301}sume\u{301}.docx"), [4..14]);
+        assert_eq!(ranges("über", "Grüße über.txt"), [8..13]);
+        assert!(ranges("*.pdf", "report.pdf").is_empty());
+        assert!(ranges("size:>1mb", "report.pdf").is_empty());
+    }
+
+    #[test]
+    fn test_modified_filters_compose_with_names() {
+        let day = |y, m, d| crate::dates::days_from_civil(y, m, d) * crate::dates::SECONDS_PER_DAY + 12 * 3600;
+        let dated = |modified| EntryMeta { modified, ..EntryMeta::UNKNOWN };
+        let mut index = FileIndex::new();
+        index.insert_with_meta(PathBuf::from("/bills/invoice-may.pdf"), dated(day(2024, 5, 20))).unwrap();
+        index.insert_with_meta(PathBuf::from("/bills/invoice-june.pdf"), dated(day(2024, 6, 1))).unwrap();
+        index.insert_with_meta(PathBuf::from("/bills/invoice-july.pdf"), dated(day(2024, 7, 3))).unwrap();
+        index.insert_with_meta(PathBuf::from("/bills/receipt-july.pdf"), dated(day(2024, 7, 4))).unwrap();
+        index.insert(PathBuf::from("/bills/invoice-undated.pdf")).unwrap();
+        
+        assert_eq!(index.search("invoice modified:>2024-06-01"), vec![PathBuf::from("/bills/invoice-july.pdf")]);
+        assert_eq!(index.search("invoice modified:>=2024-06-01").len(), 2);
+        assert_eq!(index.search("modified:2024-07"), vec![
+            PathBuf::from("/bills/invoice-july.pdf"),
+            PathBuf::from("/bills/receipt-july.pdf"),
+        ]);
+        assert_eq!(index.search("modified:..2024-06 NOT june"), vec![PathBuf::from("/bills/invoice-may.pdf")]);
+        assert_eq!(index.search("modified:2000..").len(), 4);
+        assert!(matches!(parse_query("modified:someday"), ParsedQuery::Invalid(_)));
+        assert_eq!(parse_query("Modified:"), ParsedQuery::Hint(MODIFIED_HINT));
+        assert!(matches!(
+            explain_entry(&parse_query("modified:2024"), Path::new("/x.pdf"), &|| dated(day(2024, 6, 1))),
+            Some(MatchReason::Modified { .. })
+        ));
+        
+        // A modify event refreshes the date but keeps a size read earlier
+        let path = PathBuf::from("/bills/invoice-may.pdf");
+        index.insert_with_meta(path.clone(), EntryMeta { size: 900, modified: day(2024, 5, 20), is_dir: false, attributes: 0 }).unwrap();
+        assert!(!index.insert_with_meta(path.clone(), dated(day(2024, 8, 9))).unwrap());
+        assert!(index.search("invoice modified:2024-05").is_empty());
+        assert_eq!(index.search("modified:2024-08 size:900b"), vec![path]);
+        
+        // Dates follow their entries through removal, compaction and reload
+        index.remove(Path::new("/bills/invoice-june.pdf")).unwrap();
+        index.compact().unwrap();
+        let mut loaded: FileIndex = bincode::deserialize(&bincode::serialize(&index).unwrap()).unwrap();
+        loaded.rebuild_cache();
+        assert_eq!(loaded.search("modified:2024-07").len(), 2);
+        assert_eq!(loaded.search("invoice modified:>2024-06-01").len(), 2);
+        assert!(loaded.search("undated modified:2000..").is_empty());
+    }
+
+    #[test]
+    fn test_sorted_search_keeps_newest_and_largest_before_the_cap() {
+        let mut index = FileIndex::new();
+        // Alphabetical order is the reverse of both date and size order
+        for (i, name) in ["a", "b", "c", "d", "e"].iter().enumerate() {
+            let meta = EntryMeta { size: 100 * i as u64, modified: 1_700_000_000 + i as i64, is_dir: false, attributes: 0 };
+            index.insert_with_meta(PathBuf::from(format!("/shots/screenshot_{}.png", name)), meta).unwrap();
+        }
+        index.insert(PathBuf::from("/shots/screenshot_unknown.png")).unwrap();
+        let names = |found: SearchResults| -> Vec<String> {
+            found.paths.iter().map(|p| p.file_stem().unwrap().to_string_lossy().replace("screenshot_", "")).collect()
+        };
+        
+        let newest = index.search_limited("screenshot", 2, SortOrder::ModifiedDesc);
+        assert!(newest.truncated);
+        assert_eq!(names(newest), ["e", "d"]);
+        assert_eq!(names(index.search_limited("screenshot", 2, SortOrder::SizeDesc)), ["e", "d"]);
+        assert_eq!(names(index.search_limited("screenshot", 2, SortOrder::Name)), ["a", "b"]);
+        
+        // Entries never measured come last, and a cap that fits everything is not truncation
+        let largest = index.search_limited("screenshot .png", 10, SortOrder::SizeDesc);
+        assert!(!largest.truncated);
+        assert_eq!(names(largest), ["e", "d", "c", "b", "a", "unknown"]);
+        assert_eq!(names(index.search_fuzzy("screenshto", 3, SortOrder::ModifiedDesc, TypoTolerance::Low)), ["e", "d", "c"]);
+        
+        index.remove(Path::new("/shots/screenshot_e.png")).unwrap();
+        assert_eq!(names(index.search_limited("screenshot", 1, SortOrder::ModifiedDesc)), ["d"]);
+    }
+
+    #[test]
+    fn test_every_sort_order() {
+        let mut index = FileIndex::new();
+        for (path, size, modified) in [
+            ("/a/b/c/Report.pdf", 300, 1_700_000_300),
+            ("/a/report_2.pdf", 100, 1_700_000_100),
+            ("/report_old.pdf", 200, 1_700_000_000),
+            ("/a/b/report_new.pdf", 50, 1_700_000_900),
+        ] {
+            index.insert_with_meta(PathBuf::from(path), EntryMeta { size, modified, is_dir: false, attributes: 0 }).unwrap();
+        }
+        let sorted = |sort: SortOrder| -> Vec<String> {
+            let found = index.search_limited("report", 10, sort);
+            found.paths.iter().map(|p| p.to_string_lossy().into_owned()).collect()
+        };
+        
+        assert_eq!(
+            sorted(SortOrder::NameAsc),
+            ["/a/b/c/Report.pdf", "/a/report_2.pdf", "/a/b/report_new.pdf", "/report_old.pdf"]
+        );
+        assert_eq!(
+            sorted(SortOrder::NameDesc),
+            ["/report_old.pdf", "/a/b/report_new.pdf", "/a/report_2.pdf", "/a/b/c/Report.pdf"]
+        );
+        assert_eq!(
+            sorted(SortOrder::SizeDesc),
+            ["/a/b/c/Report.pdf", "/report_old.pdf", "/a/report_2.pdf", "/a/b/report_new.pdf"]
+        );
+        assert_eq!(
+            sorted(SortOrder::ModifiedDesc),
+            ["/a/b/report_new.pdf", "/a/b/c/Report.pdf", "/a/report_2.pdf", "/report_old.pdf"]
+        );
+        assert_eq!(
+            sorted(SortOrder::DepthAsc),
+            ["/report_old.pdf", "/a/report_2.pdf", "/a/b/report_new.pdf", "/a/b/c/Report.pdf"]
+        );
+        // The cap keeps the head of the chosen order, not of the name order
+        assert_eq!(index.search_limited("report", 1, SortOrder::NameDesc).paths, [PathBuf::from("/report_old.pdf")]);
+        assert_eq!(index.search_limited("report", 1, SortOrder::DepthAsc).paths, [PathBuf::from("/report_old.pdf")]);
+    }
+
+    #[test]
+    fn test_migrate_v3_index_keeps_sizes_with_unknown_dates() {
+        let old = FileIndexV3 {
+            version: 3,
+            pool: vec![PathBuf::from("C:\\test\\doc.pdf")],
+            sizes: vec![4096],
+            filename_index: [("doc.pdf".to_string(), vec![0])].into_iter().collect(),
+            extension_index: [("pdf".to_string(), vec![0])].into_iter().collect(),
+            lifetime_stats: StatsSnapshot { insertions: 1, ..StatsSnapshot::default() }.into(),
+        };
+        let data = bincode::serialize(&old).unwrap();
+
+        let index = migrate_v3(&data).unwrap();
+        assert_eq!(index.version(), INDEX_VERSION);
+        assert_eq!(index.mtimes, vec![UNKNOWN_MTIME]);
+        assert_eq!(index.search("size:4kb").len(), 1);
+        assert!(index.search("modified:1970..").is_empty());
+        assert_eq!(index.stats().lifetime.insertions, 1);
+    }
+
+    #[test]
+    fn test_migrate_v4_index_refiles_keys_folded() {
+        let nfd = PathBuf::from("/docs/Cafe\u{301}.TXT");
+        let old = FileIndexV4 {
+            version: 4,
+            pool: vec![nfd.clone()],
+            sizes: vec![10],
+            mtimes: vec![1_700_000_000],
+            filename_index: [("cafe\u{301}.txt".to_string(), vec![0])].into_iter().collect(),
+            extension_index: [("txt".to_string(), vec![0])].into_iter().collect(),
+            lifetime_stats: StatsSnapshot::default().into(),
+        };
+        let data = bincode::serialize(&old).unwrap();
+        
+        let index = migrate_v4(&data).unwrap();
+        assert_eq!(index.version(), INDEX_VERSION);
+        assert_eq!(index.mtimes, vec![1_700_000_000]);
+        assert_eq!(index.search("café"), vec![nfd.clone()]);
+        assert_eq!(index.search("cafe"), vec![nfd]);
+        assert!(index.check_invariants().is_consistent());
+    }
+    
+    #[test]
+    fn test_accents_and_unicode_forms_match_alike() {
+        let nfc = PathBuf::from("/docs/Caf\u{e9} menu.pdf");
+        let nfd = PathBuf::from("/docs/cafe\u{301}.txt");
+        let resume = PathBuf::from("/docs/Résumé.DOCX");
+        let mut index = FileIndex::new();
+        for p in [&nfc, &nfd, &resume] {
+            index.insert(p.clone()).unwrap();
+        }
+        
+        // Composed and decomposed queries both find both spellings
+        for query in ["caf\u{e9}", "cafe\u{301}", "cafe", "CAFÉ"] {
+            assert_eq!(index.search(query), vec![nfd.clone(), nfc.clone()], "{:?}", query);
+        }
+        for query in ["resume", "résumé", "RESUME .docx", "r?sum?.docx", "^resu"] {
+            assert_eq!(index.search(query), vec![resume.clone()], "{:?}", query);
+        }
+        // Results keep the original spelling for display and opening
+        assert_eq!(index.search(".txt")[0].to_str(), Some("/docs/cafe\u{301}.txt"));
+        assert_eq!(
+            explain_match("resume", &resume),
+            Some(MatchReason::Filename { query: "resume".into(), tier: MatchTier::Exact })
+        );
+    }
+    
+    #[test]
+    fn test_turkish_i_follows_locale_independent_casing() {
+        let izmir = PathBuf::from("/photos/İzmir.jpg");
+        let isparta = PathBuf::from("/photos/ısparta.jpg");
+        let mut index = FileIndex::new();
+        index.insert(izmir.clone()).unwrap();
+        index.insert(isparta.clone()).unwrap();
+        
+        // Dotted İ folds to plain i, as accents do
+        assert_eq!(index.search("izmir"), vec![izmir.clone()]);
+        assert_eq!(index.search("IZMIR"), vec![izmir]);
+        // Dotless ı is a separate letter that I and i never match
+        assert!(index.search("isparta").is_empty());
+        assert!(index.search("ISPARTA").is_empty());
+        assert_eq!(index.search("ısparta"), vec![isparta]);
+    }
+    
+    #[test]
+    fn test_migrate_v5_index_adds_word_index() {
+        let path = PathBuf::from("/src/UserProfileController.cs");
+        let old = FileIndexV5 {
+            version: 5,
+            pool: vec![path.clone()],
+            sizes: vec![UNKNOWN_SIZE],
+            mtimes: vec![UNKNOWN_MTIME],
+            filename_index: [("userprofilecontroller.cs".to_string(), vec![0])].into_iter().collect(),
+            extension_index: [("cs".to_string(), vec![0])].into_iter().collect(),
+            accent_free_keys: true,
+            lifetime_stats: StatsSnapshot::default().into(),
+        };
+        let data = bincode::serialize(&old).unwrap();
+        
+        let index = migrate_v5(&data).unwrap();
+        assert_eq!(index.version(), INDEX_VERSION);
+        assert_eq!(index.search("words:profile controller"), vec![path]);
+        assert!(index.check_invariants().is_consistent());
+    }
+    
+    #[test]
+    fn test_migrate_v6_index_adds_compound_extension_index() {
+        let path = PathBuf::from("/backups/site.tar.gz");
+        let old = FileIndexV6 {
+            version: 6,
+            pool: vec![path.clone()],
+            sizes: vec![UNKNOWN_SIZE],
+            mtimes: vec![UNKNOWN_MTIME],
+            filename_index: [("site.tar.gz".to_string(), vec![0])].into_iter().collect(),
+            extension_index: [("gz".to_string(), vec![0])].into_iter().collect(),
+            word_index: ["site", "tar", "gz"].iter().map(|w| (w.to_string(), vec![0])).collect(),
+            accent_free_keys: true,
+            lifetime_stats: StatsSnapshot::default().into(),
+        };
+        let data = bincode::serialize(&old).unwrap();
+        
+        let index = migrate_v6(&data).unwrap();
+        assert_eq!(index.version(), INDEX_VERSION);
+        assert_eq!(index.search(".tar.gz"), vec![path]);
+        assert!(index.check_invariants().is_consistent());
+    }
+    
+    #[test]
+    fn test_migrations_keep_removed_entries_removed() {
+        let mut index = FileIndex::new();
+        for name in ["kept.txt", "gone.txt"] {
+            index.insert_with_meta(PathBuf::from(format!("/docs/{}", name)), sized(10)).unwrap();
+        }
+        index.remove(Path::new("/docs/gone.txt")).unwrap();
+        let pool: Vec<PathBuf> = index.pool.iter().collect();
+        assert_eq!(pool.len(), 2);
+
+        let v2 = FileIndexV2 {
+            version: 2,
+            pool: pool.clone(),
+            filename_index: index.filename_index.clone(),
+            extension_index: index.extension_index.clone(),
+            lifetime_stats: StatsSnapshot::default().into(),
+        };
+        let v6 = FileIndexV6 {
+            version: 6,
+            pool,
+            sizes: index.sizes.clone(),
+            mtimes: index.mtimes.clone(),
+            filename_index: index.filename_index.clone(),
+            extension_index: index.extension_index.clone(),
+            word_index: index.word_index.clone(),
+            accent_free_keys: true,
+            lifetime_stats: StatsSnapshot::default().into(),
+        };
+        let migrated = [
+            migrate_v2(&bincode::serialize(&v2).unwrap()).unwrap(),
+            migrate_v6(&bincode::serialize(&v6).unwrap()).unwrap(),
+        ];
+        for index in migrated {
+            assert_eq!(index.len(), 1);
+            assert_eq!(index.search(".txt"), vec![PathBuf::from("/docs/kept.txt")]);
+            assert!(index.search("gone").is_empty());
+            assert!(index.check_invariants().is_consistent());
+        }
+    }
+    
+    #[test]
+    fn test_migrate_v7_index_keeps_every_entry_a_file() {
+        let path = PathBuf::from("/backups/site.tar.gz");
+        let old = FileIndexV7 {
+            version: 7,
+            pool: vec![path.clone()],
+            sizes: vec![UNKNOWN_SIZE],
+            mtimes: vec![UNKNOWN_MTIME],
+            filename_index: [("site.tar.gz".to_string(), vec![0])].into_iter().collect(),
+            extension_index: [("gz".to_string(), vec![0])].into_iter().collect(),
+            compound_extension_index: [("tar.gz".to_string(), vec![0])].into_iter().collect(),
+            word_index: ["site", "tar", "gz"].iter().map(|w| (w.to_string(), vec![0])).collect(),
+            accent_free_keys: true,
+            lifetime_stats: StatsSnapshot::default().into(),
+        };
+        let data = bincode::serialize(&old).unwrap();
+        
+        let index = migrate_v7(&data).unwrap();
+        assert_eq!(index.version(), INDEX_VERSION);
+        assert_eq!(index.search(".tar.gz"), vec![path.clone()]);
+        assert!(!index.is_directory(&path));
+        assert!(index.check_invariants().is_consistent());
+    }
+    
+    #[test]
+    fn test_migrate_v8_index_flags_dot_files() {
+        let dotfile = PathBuf::from("/home/me/.profile");
+        let old = FileIndexV8 {
+            version: 8,
+            pool: vec![dotfile.clone()],
+            sizes: vec![UNKNOWN_SIZE],
+            mtimes: vec![UNKNOWN_MTIME],
+            dirs: vec![false],
+            filename_index: [(".profile".to_string(), vec![0])].into_iter().collect(),
+            extension_index: AHashMap::new(),
+            compound_extension_index: AHashMap::new(),
+            word_index: [("profile".to_string(), vec![0])].into_iter().collect(),
+            accent_free_keys: true,
+            lifetime_stats: StatsSnapshot::default().into(),
+        };
+        let data = bincode::serialize(&old).unwrap();
+        
+        let mut index = migrate_v8(&data).unwrap();
+        assert_eq!(index.version(), INDEX_VERSION);
+        assert_eq!(index.search("is:hidden"), vec![dotfile.clone()]);
+        index.set_show_hidden(true);
+        assert_eq!(index.search("profile"), vec![dotfile]);
+        assert!(index.check_invariants().is_consistent());
+    }
+    
+    #[test]
+    fn test_migrate_v9_index_interns_folders() {
+        let report = PathBuf::from("/docs/work/report.pdf");
+        let removed = PathBuf::from("/docs/work/old.pdf");
+        let old = FileIndexV9 {
+            version: 9,
+            pool: vec![report.clone(), removed.clone()],
+            sizes: vec![10, 20],
+            mtimes: vec![1_700_000_000, 1_600_000_000],
+            dirs: vec![false, false],
+            attributes: vec![0, 0],
+            filename_index: [("report.pdf".to_string(), vec![0])].into_iter().collect(),
+            extension_index: [("pdf".to_string(), vec![0])].into_iter().collect(),
+            compound_extension_index: AHashMap::new(),
+            word_index: [("report".to_string(), vec![0]), ("pdf".to_string(), vec![0])].into_iter().collect(),
+            accent_free_keys: true,
+            lifetime_stats: StatsSnapshot::default().into(),
+        };
+        let data = bincode::serialize(&old).unwrap();
+        
+        let mut index = migrate_v9(&data).unwrap();
+        assert_eq!(index.version(), INDEX_VERSION);
+        assert_eq!(index.search(".pdf"), vec![report.clone()]);
+        assert_eq!(index.metadata_for(&report).map(|meta| meta.size), Some(10));
+        // The removed slot stays a tombstone rather than coming back
+        assert_eq!(index.len(), 1);
+        assert!(index.check_invariants().is_consistent());
+        
+        // A reload of the new layout keeps the shared folder working
+        let mut reloaded: FileIndex = bincode::deserialize(&bincode::serialize(&index).unwrap()).unwrap();
+        reloaded.rebuild_cache();
+        assert!(reloaded.insert(removed.clone()).unwrap());
+        assert_eq!(reloaded.search(".pdf"), vec![removed, report]);
+        assert_eq!(index.compact().unwrap(), 1);
+    }
+    
+    #[test]
+    fn test_hidden_entries_are_listed_on_request() {
+        let mut index = FileIndex::new();
+        let hidden = EntryMeta { attributes: EntryMeta::HIDDEN, ..EntryMeta::UNKNOWN };
+        let system = EntryMeta { attributes: EntryMeta::SYSTEM, modified: 1_700_000_000, ..EntryMeta::UNKNOWN };
+        index.insert_with_meta(PathBuf::from("C:\\Users\\me\\desktop.ini"), hidden).unwrap();
+        index.insert_with_meta(PathBuf::from("C:\\pagefile.sys"), system).unwrap();
+        index.insert(PathBuf::from("/home/me/.bashrc")).unwrap();
+        index.insert_with_meta(PathBuf::from("/home/me/notes.ini"), EntryMeta { modified: 1_600_000_000, ..EntryMeta::UNKNOWN }).unwrap();
+        
+        // Outside Windows a leading dot is what hides a file
+        let expected_hidden = if cfg!(windows) { 2 } else { 3 };
+        assert_eq!(index.search(".ini"), vec![PathBuf::from("/home/me/notes.ini")]);
+        assert_eq!(index.search("bashrc").is_empty(), !cfg!(windows));
+        assert_eq!(index.search("is:hidden").len(), expected_hidden);
+        assert_eq!(index.search("is:hidden .ini"), vec![PathBuf::from("C:\\Users\\me\\desktop.ini")]);
+        assert_eq!(index.search("is:HIDDEN NOT .ini").len(), expected_hidden - 1);
+        let recent: Vec<PathBuf> = index.recent(10).into_iter().map(|result| result.path).collect();
+        assert_eq!(recent, [PathBuf::from("/home/me/notes.ini")]);
+        
+        index.set_show_hidden(true);
+        assert_eq!(index.search(".ini").len(), 2);
+        assert_eq!(index.recent(10).len(), 2);
+        assert_eq!(index.browse_all(0, 10).total, 4);
+        
+        // A rescan that reads the attributes again updates them
+        index.insert_with_meta(PathBuf::from("C:\\pagefile.sys"), EntryMeta { modified: 1_700_000_000, ..EntryMeta::UNKNOWN }).unwrap();
+        index.set_show_hidden(false);
+        assert_eq!(index.browse_all(0, 10).total, 4 - expected_hidden + 1);
+        
+        let reason = explain_parsed(&ParsedQuery::Hidden, Path::new("/home/me/.bashrc"));
+        assert_eq!(reason.is_some(), !cfg!(windows));
+        assert_eq!(parse_query("is:"), ParsedQuery::Hint(IS_HINT));
+        assert!(matches!(parse_query("is:secret"), ParsedQuery::Invalid(_)));
+    }
     
-    /// Filename to pool indices mapping
-    filename_index: AHashMap<String, Vec<u32>>,
+    #[test]
+    fn test_directories_are_searchable_and_removable() {
+        let mut index = FileIndex::new();
+        let folder = PathBuf::from("/projects/invoices");
+        let file = PathBuf::from("/projects/invoices/invoice-may.pdf");
+        index.insert_with_meta(folder.clone(), EntryMeta { is_dir: true, ..EntryMeta::UNKNOWN }).unwrap();
+        index.insert(file.clone()).unwrap();
+        
+        assert!(index.is_directory(&folder));
+        assert!(!index.is_directory(&file));
+        assert!(!index.is_directory(Path::new("/projects")));
+        let found = index.describe(index.search("invoices"));
+        assert_eq!(found.iter().map(|r| (r.path.clone(), r.is_dir)).collect::<Vec<_>>(), vec![(folder.clone(), true)]);
+        assert_eq!(found[0].size, None);
+        
+        // The flag follows its entry through compaction and reload
+        index.insert(PathBuf::from("/projects/old.txt")).unwrap();
+        index.remove(Path::new("/projects/old.txt")).unwrap();
+        index.compact().unwrap();
+        let mut loaded: FileIndex = bincode::deserialize(&bincode::serialize(&index).unwrap()).unwrap();
+        loaded.rebuild_cache();
+        assert!(loaded.is_directory(&folder));
+        
+        assert_eq!(loaded.remove_directories(), 1);
+        assert_eq!(loaded.search("invoice"), vec![file]);
+        assert!(!loaded.is_directory(&folder));
+        assert!(loaded.check_invariants().is_consistent());
+        
+        // Removing files by path keeps the folder the predicate also matches
+        assert_eq!(index.remove_files_where(|path| path.starts_with(&folder)), 1);
+        assert!(index.is_directory(&folder));
+        assert!(index.check_invariants().is_consistent());
+    }
     
-    /// File extension to pool indices mapping
-    extension_index: AHashMap<String, Vec<u32>>,
+    #[test]
+    fn test_recent_files_follow_inserts_modifications_and_removals() {
+        let mut index = FileIndex::new();
+        let path = |i: usize| PathBuf::from(format!("/work/file_{}.txt", i));
+        let file = |modified: i64| EntryMeta { size: 1, modified, is_dir: false, attributes: 0 };
+        let names = |index: &FileIndex, limit: usize| -> Vec<PathBuf> {
+            index.recent(limit).into_iter().map(|result| result.path).collect()
+        };
+        for i in 0..RECENT_CAPACITY + 50 {
+            index.insert_with_meta(path(i), file(1_000 + i as i64)).unwrap();
+        }
+        index.insert(PathBuf::from("/work/undated.txt")).unwrap();
+        index.insert_with_meta(PathBuf::from("/work/newest"), EntryMeta { is_dir: true, ..file(9_999) }).unwrap();
+        
+        let newest = RECENT_CAPACITY + 49;
+        assert_eq!(names(&index, 3), [path(newest), path(newest - 1), path(newest - 2)]);
+        assert_eq!(index.recent(usize::MAX).len(), RECENT_CAPACITY);
+        
+        // A modify event moves a file to the top, and a new file lands in place
+        index.insert_with_meta(path(0), file(5_000)).unwrap();
+        index.insert_with_meta(path(1_000), file(1_000 + newest as i64)).unwrap();
+        assert_eq!(names(&index, 3), [path(0), path(newest), path(1_000)]);
+        assert_eq!(index.recent(1)[0].modified, Some(5_000));
+        
+        // Removals and a file dated back never leave a gap
+        index.remove(&path(0)).unwrap();
+        index.insert_with_meta(path(newest), file(1)).unwrap();
+        let listed = names(&index, usize::MAX);
+        let mut expected: Vec<PathBuf> = (1..newest).rev().map(path).collect();
+        expected.insert(0, path(1_000));
+        expected.truncate(RECENT_CAPACITY);
+        assert_eq!(listed, expected);
+        
+        index.compact().unwrap();
+        assert_eq!(names(&index, usize::MAX), expected);
+        index.clear();
+        assert!(index.recent(10).is_empty());
+    }
     
-    /// Runtime-only cache for fast duplicate detection
-    #[serde(skip)]
-    seen_paths: HashSet<PathBuf>,
+    #[test]
+    fn test_words_query_matches_camel_case_and_separated_names() {
+        let camel = PathBuf::from("/src/UserProfileController.cs");
+        let snake = PathBuf::from("/src/user_profile_controller.rs");
+        let other = PathBuf::from("/src/ProfileView.tsx");
+        let mut index = FileIndex::new();
+        for p in [&camel, &snake, &other] {
+            index.insert(p.clone()).unwrap();
+        }
+        
+        assert_eq!(parse_query("words:Profile controller"), ParsedQuery::Words(vec!["profile".into(), "controller".into()]));
+        assert_eq!(parse_query("words: "), ParsedQuery::Hint(WORDS_HINT));
+        assert_eq!(parse_query("\\bArt"), ParsedQuery::WholeWord("art".into()));
+        assert_eq!(parse_query("\\b"), ParsedQuery::Hint(WHOLE_WORD_HINT));
+        assert_eq!(parse_query("\\bin\\tools"), ParsedQuery::PathPrefix("\\bin\\tools".into()));
+        // Whole words end at separators and case changes
+        assert_eq!(index.search("\\bprofile"), vec![other.clone(), snake.clone(), camel.clone()]);
+        assert!(index.search("\\broller").is_empty());
+        assert_eq!(index.search("\\bprofilecontroller"), vec![camel.clone()]);
+        assert_eq!(index.search("\\bprofile_controller"), vec![snake.clone()]);
+        assert_eq!(
+            explain_match("\\bcontroller", &camel).map(|reason| reason.to_string()),
+            Some("Filename has \"controller\" as a whole word".to_string())
+        );
+        assert_eq!(index.search("words:profile controller"), vec![snake.clone(), camel.clone()]);
+        assert_eq!(index.search("WORDS:prof contr"), vec![snake.clone(), camel.clone()]);
+        assert_eq!(index.search("words:profile"), vec![other.clone(), snake.clone(), camel.clone()]);
+        // Word starts only: "roller" sits inside "controller"
+        assert!(index.search("words:roller").is_empty());
+        assert_eq!(index.search("roller").len(), 2);
+        // A single words: term combines with other terms
+        assert_eq!(index.search("words:contr .rs"), vec![snake.clone()]);
+        assert_eq!(
+            explain_match("words:profile controller", &camel).map(|reason| reason.to_string()),
+            Some("Filename has words starting with \"profile\", \"controller\"".to_string())
+        );
+        
+        index.remove(&snake).unwrap();
+        assert_eq!(index.search("words:profile controller"), vec![camel.clone()]);
+        index.compact().unwrap();
+        assert_eq!(index.search("words:profile controller"), vec![camel]);
+        assert_eq!(index.search("words:view"), vec![other]);
+    }
     
-    /// Statistics counter
-    #[serde(skip)]
-    stats: IndexStats,
-}
-
-#[derive(Default)]
-struct IndexStats {
-    insertions: AtomicUsize,
-    duplicates: AtomicUsize,
-    searches: AtomicUsize,
-}
-
-impl Default for FileIndex {
-    fn default() -> Self {
-        Self {
-            version: INDEX_VERSION,
-            pool: Vec::new(),
-            filename_index: AHashMap::new(),
-            extension_index: AHashMap::new(),
-            seen_paths: HashSet::new(),
-            stats: IndexStats::default(),
+    #[test]
+    fn test_in_folder_scopes_results() {
+        let setup = PathBuf::from("C:\\Users\\ana\\Downloads\\installer.exe");
+        let nested = PathBuf::from("C:\\Users\\ana\\Downloads\\old\\installer_v1.msi");
+        let work = PathBuf::from("D:\\Work Files\\installer notes.txt");
+        let other = PathBuf::from("C:\\Program Files\\Tool\\installer.log");
+        let mut index = FileIndex::new();
+        for p in [&setup, &nested, &work, &other] {
+            index.insert(p.clone()).unwrap();
         }
+        
+        assert_eq!(parse_query("in:"), ParsedQuery::Hint(IN_HINT));
+        assert!(matches!(parse_query("in:Downloads C:\\Users"), ParsedQuery::Invalid(_)));
+        assert!(matches!(parse_query("in:Downloads size:huge"), ParsedQuery::Invalid(_)));
+        assert!(matches!(parse_query("in:\"Work"), ParsedQuery::Invalid(_)));
+        
+        assert_eq!(index.search("in:Downloads installer"), vec![setup.clone(), nested.clone()]);
+        assert_eq!(index.search("installer in:downloads\\old"), vec![nested.clone()]);
+        // Several in: clauses widen the scope
+        assert_eq!(index.search("in:Downloads in:\"work files\" installer"), vec![setup.clone(), nested.clone(), work.clone()]);
+        assert_eq!(index.search("in:C:\\Users .msi"), vec![nested.clone()]);
+        assert!(index.search("in:C:\\User installer").is_empty());
+        assert_eq!(index.search("in:Downloads installer NOT v1"), vec![setup.clone()]);
+        // in: alone lists everything under the folders
+        assert_eq!(index.search("in:Program"), vec![other.clone()]);
+        assert_eq!(index.search("in:Downloads *"), vec![setup.clone(), nested.clone()]);
+        
+        let found = index.search_limited("in:Downloads", 1, SortOrder::Name);
+        assert_eq!((found.paths.len(), found.truncated), (1, true));
+        
+        assert_eq!(
+            explain_match("in:docs report", Path::new("/home/ana/docs/report.pdf")).map(|reason| reason.to_string()),
+            Some("Filename is exactly \"report\", in a folder matching in:docs".to_string())
+        );
+        assert_eq!(
+            explain_match("in:Program", &other).map(|reason| reason.to_string()),
+            Some("In a folder matching in:Program".to_string())
+        );
+        assert_eq!(explain_match("in:Downloads installer", &work), None);
     }
-}
+    
+    #[test]
+    fn test_remove_unfiles_extension_ids() {
+        let mut index = FileIndex::new();
+        let path = PathBuf::from("C:\\docs\\report.pdf");
+        index.insert(path.clone()).unwrap();
+        index.insert(PathBuf::from("C:\\docs\\notes.txt")).unwrap();
+        index.remove(&path).unwrap();
 
-impl FileIndex {
-    /// Create a new empty index
-    pub fn new() -> Self {
-        info!("Creating new file index");
-        Self::default()
-    }
+        assert!(index.search(".pdf").is_empty());
+        assert!(!index.extension_index.contains_key("pdf"));
+        assert!(!index.filename_index.contains_key("report.pdf"));
+        assert!(index.check_invariants().is_consistent());
 
-    /// Get current index version
-    pub fn version(&self) -> u32 {
-        self.version
+        index.remove_where(|p| p.to_string_lossy().ends_with(".txt"));
+        assert!(index.extension_index.is_empty());
+        assert!(index.check_invariants().is_consistent());
     }
 
-    /// Rebuild the seen_paths cache from the pool (call after deserialization)
-    pub fn rebuild_cache(&mut self) {
-        debug!("Rebuilding seen_paths cache from {} paths", self.pool.len());
-        self.seen_paths = self.pool.iter().cloned().collect();
-    }
+    #[test]
+    fn test_migrated_v1_stale_ids_are_repaired() {
+        // A v1 index with a superseded duplicate slot and a dangling id
+        let old = FileIndexV1 {
+            version: 1,
+            pool: vec![
+                PathBuf::from("C:\\docs\\Report.pdf"),
+                PathBuf::from("c:\\docs\\report.pdf"),
+            ],
+            filename_index: [("report.pdf".to_string(), vec![0, 1, 7])].into_iter().collect(),
+            extension_index: [("pdf".to_string(), vec![0, 1])].into_iter().collect(),
+        };
+        let data = bincode::serialize(&old).unwrap();
 
-    /// Get total number of indexed files
-    pub fn len(&self) -> usize {
-        self.pool.len()
+        let index = migrate_v1(&data).unwrap();
+        assert!(index.check_invariants().is_consistent());
+        assert_eq!(index.search(".pdf"), vec![PathBuf::from("C:\\docs\\Report.pdf")]);
+        assert_eq!(index.search("report").len(), 1);
     }
 
-    /// Check if index is empty
-    pub fn is_empty(&self) -> bool {
-        self.pool.is_empty()
+    /// Small deterministic xorshift generator for randomized operation sequences
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as usize
+        }
     }
 
-    /// Clear all indexed data
-    pub fn clear(&mut self) {
-        info!("Clearing index with {} files", self.pool.len());
-        self.pool.clear();
-        self.filename_index.clear();
-        self.extension_index.clear();
-        self.seen_paths.clear();
-        self.stats.insertions.store(0, Ordering::Relaxed);
-        self.stats.duplicates.store(0, Ordering::Relaxed);
-        self.stats.searches.store(0, Ordering::Relaxed);
+    #[test]
+    fn test_random_operations_keep_invariants() {
+        const NAMES: [&str; 6] = ["a.pdf", "b.PDF", "c.txt", "d.tar.gz", "e", "f.rs"];
+        const DIRS: [&str; 3] = ["C:\\x", "C:\\y", "D:\\z"];
+
+        for seed in 1..=20u64 {
+            let mut rng = XorShift(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+            let mut index = FileIndex::new();
+            let mut live: std::collections::HashSet<String> = Default::default();
+            let random_path = |rng: &mut XorShift| {
+                PathBuf::from(format!("{}\\{}", DIRS[rng.next(DIRS.len())], NAMES[rng.next(NAMES.len())]))
+            };
+
+            for step in 0..300 {
+                let path = random_path(&mut rng);
+                match rng.next(4) {
+                    0 | 1 => {
+                        index.insert(path.clone()).unwrap();
+                        live.insert(path_key(&path));
+                    }
+                    2 => {
+                        index.remove(&path).unwrap();
+                        live.remove(&path_key(&path));
+                    }
+                    _ => {
+                        // Rename: the watcher sees a remove followed by a create
+                        let target = random_path(&mut rng);
+                        if index.remove(&path).unwrap() {
+                            live.remove(&path_key(&path));
+                            index.insert(target.clone()).unwrap();
+                            live.insert(path_key(&target));
+                        }
+                    }
+                }
+                if step % 50 == 0 {
+                    index.compact().unwrap();
+                }
+
+                let report = index.check_invariants();
+                assert!(report.is_consistent(), "seed {} step {}: {:?}", seed, step, report);
+            }
+
+            for query in [".pdf", ".txt", ".tar.gz", ".gz", "a", "e", "f.rs"] {
+                for found in index.search(query) {
+                    assert!(live.contains(&path_key(&found)), "seed {}: dead path {:?}", seed, found);
+                }
+            }
+        }
     }
 
-    /// Compact the index by removing tombstones and rebuilding all structures
-    /// This should be called periodically or when deletion count is high
-    pub fn compact(&mut self) -> Result<usize> {
-        let original_size = self.pool.len();
-        let live_count = self.seen_paths.len();
-        
-        if live_count == original_size {
-            debug!("Index already compact: {} live entries", live_count);
-            return Ok(0);
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_kind_query_unions_the_extensions_of_a_group() {
+        let beach = PathBuf::from("/photos/vacation/beach.JPG");
+        let sunset = PathBuf::from("/photos/vacation/sunset.png");
+        let clip = PathBuf::from("/photos/vacation/surf.mp4");
+        let notes = PathBuf::from("/photos/vacation notes.txt");
+        let mut index = FileIndex::new();
+        for path in [&beach, &sunset, &clip, &notes] {
+            index.insert(path.clone()).unwrap();
         }
         
-        info!("Compacting index: {} -> {} files (removing {} tombstones)", 
-              original_size, live_count, original_size - live_count);
+        let images = parse_query("kind:Image");
+        assert!(matches!(&images, ParsedQuery::Kind { name, extensions } if name == "images" && extensions.contains(&"png".to_string())));
+        assert_eq!(parse_query("kind:"), ParsedQuery::Hint(KIND_HINT));
+        assert!(matches!(parse_query("kind:spreadsheets"), ParsedQuery::Invalid(reason) if reason.contains("documents, images")));
         
-        // Build new pool from seen_paths only
-        let new_pool: Vec<PathBuf> = self.seen_paths.iter().cloned().collect();
+        assert_eq!(index.search("kind:images"), vec![beach.clone(), sunset.clone()]);
+        assert_eq!(index.search("kind:video"), vec![clip.clone()]);
+        // Combined with a name, and in boolean queries
+        assert_eq!(index.search("kind:image sun"), vec![sunset.clone()]);
+        assert_eq!(index.search("vacation kind:documents"), vec![notes.clone()]);
+        assert_eq!(index.search("kind:videos OR kind:documents"), vec![clip.clone(), notes]);
+        assert_eq!(index.search("kind:images NOT beach"), vec![sunset.clone()]);
         
-        // Rebuild filename and extension indices
-        let mut new_filename_index = AHashMap::new();
-        let mut new_extension_index = AHashMap::new();
+        assert_eq!(
+            explain_match("kind:images", &beach).map(|reason| reason.to_string()),
+            Some("File type .jpg is one of kind:images".to_string())
+        );
+        assert_eq!(match_ranges(&images, "beach.JPG"), vec![6..9]);
+    }
+    
+    #[test]
+    fn test_compound_extension() {
+        let mut index = FileIndex::new();
+        index.insert(PathBuf::from("C:\\test\\archive.tar.gz")).unwrap();
         
-        for (idx, path) in new_pool.iter().enumerate() {
-            let idx_u32 = idx as u32;
-            
-            // Add to filename index
-            if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-                let lower_name = filename.to_lowercase();
-                new_filename_index
-                    .entry(lower_name)
-                    .or_insert_with(Vec::new)
-                    .push(idx_u32);
-            }
-            
-            // Add to extension index
-            if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                new_extension_index
-                    .entry(ext.to_lowercase())
-                    .or_insert_with(Vec::new)
-                    .push(idx_u32);
-            }
-        }
+        let results = index.search(".tar.gz");
+        assert_eq!(results.len(), 1);
+        // Found by lookup rather than by scanning the pool
+        assert_eq!(index.compound_extension_index.get("tar.gz"), Some(&vec![0]));
+    }
+    
+    #[test]
+    fn test_compound_extensions_are_indexed_suffixes() {
+        assert_eq!(compound_extensions("my.backup.tar.gz").collect::<Vec<_>>(), ["backup.tar.gz", "tar.gz"]);
+        assert_eq!(compound_extensions(".eslintrc.json").collect::<Vec<_>>(), ["eslintrc.json"]);
+        assert_eq!(compound_extensions("report.pdf").count(), 0);
+        assert_eq!(compound_extensions("README").count(), 0);
         
-        // Replace old structures
-        self.pool = new_pool;
-        self.filename_index = new_filename_index;
-        self.extension_index = new_extension_index;
+        let archive = PathBuf::from("/backups/site.v2.tar.gz");
+        let types = PathBuf::from("/src/Index.D.TS");
+        let script = PathBuf::from("/www/app.min.js");
+        let plain = PathBuf::from("/www/app.js");
+        let mut index = FileIndex::new();
+        for p in [&archive, &types, &script, &plain] {
+            index.insert(p.clone()).unwrap();
+        }
         
-        let removed = original_size - live_count;
-        info!("Compaction complete: removed {} tombstones, {} files remain", removed, live_count);
+        assert_eq!(index.search(".tar.gz"), vec![archive.clone()]);
+        assert_eq!(index.search(".v2.tar.gz"), vec![archive.clone()]);
+        assert_eq!(index.search(".gz"), vec![archive.clone()]);
+        assert_eq!(index.search(".d.ts"), vec![types.clone()]);
+        assert_eq!(index.search(".min.js"), vec![script.clone()]);
+        assert_eq!(index.search(".js"), vec![plain.clone(), script.clone()]);
+        assert!(index.search(".ar.gz").is_empty());
+        assert_eq!(index.search("site .tar.gz"), vec![archive.clone()]);
         
-        Ok(removed)
-    }
-
-    /// Get statistics about the index
-    pub fn stats(&self) -> (usize, usize, usize) {
-        (
-            self.stats.insertions.load(Ordering::Relaxed),
-            self.stats.duplicates.load(Ordering::Relaxed),
-            self.stats.searches.load(Ordering::Relaxed),
-        )
+        index.remove(&archive).unwrap();
+        assert!(index.search(".tar.gz").is_empty());
+        assert!(!index.compound_extension_index.contains_key("v2.tar.gz"));
+        assert!(index.check_invariants().is_consistent());
+        index.compact().unwrap();
+        assert_eq!(index.search(".min.js"), vec![script]);
+        assert!(index.check_invariants().is_consistent());
     }
 
-    /// Insert a file path into the index
-    /// Returns Ok(true) if inserted, Ok(false) if duplicate, Err on failure
-    pub fn insert(&mut self, path: PathBuf) -> Result<bool> {
-        // Check capacity limit
-        if self.pool.len() >= MAX_INDEX_SIZE {
-            warn!("Index full at {} files", MAX_INDEX_SIZE);
-            return Err(FlashFindError::IndexFull(MAX_INDEX_SIZE));
+    #[test]
+    fn test_identical_names_sort_by_path() {
+        let dirs: Vec<String> = (0..60).map(|i| format!("C:\\clients\\{:02}", (i * 37) % 60)).collect();
+        let mut forward = FileIndex::new();
+        let mut backward = FileIndex::new();
+        for dir in &dirs {
+            forward.insert(PathBuf::from(format!("{}\\invoice.pdf", dir))).unwrap();
+        }
+        for dir in dirs.iter().rev() {
+            backward.insert(PathBuf::from(format!("{}\\Invoice.PDF", dir))).unwrap();
         }
 
-        // Check for duplicates
-        if self.seen_paths.contains(&path) {
-            self.stats.duplicates.fetch_add(1, Ordering::Relaxed);
-            return Ok(false);
+        let first = forward.search("invoice");
+        assert_eq!(first.len(), 60);
+        assert!(first.windows(2).all(|w| path_key(&w[0]) < path_key(&w[1])));
+        for _ in 0..5 {
+            assert_eq!(forward.search("invoice"), first);
+            assert_eq!(forward.search(".pdf"), first);
         }
 
-        // Extract filename
-        let filename = path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .ok_or_else(|| FlashFindError::InvalidPath(path.display().to_string()))?;
+        // Insertion order does not leak into the result order
+        let keys = |results: Vec<PathBuf>| results.iter().map(|p| path_key(p)).collect::<Vec<_>>();
+        assert_eq!(keys(backward.search("invoice")), keys(first));
+    }
+
+    #[test]
+    fn test_match_tiers() {
+        assert_eq!(match_tier("invoice.pdf", "invoice"), Some(MatchTier::Exact));
+        assert_eq!(match_tier("invoice_2024.pdf", "invoice"), Some(MatchTier::Prefix));
+        assert_eq!(match_tier("march invoice.pdf", "invoice"), Some(MatchTier::Token));
+        assert_eq!(match_tier("preinvoice.pdf", "invoice"), Some(MatchTier::Substring));
+        assert_eq!(match_tier("receipt.pdf", "invoice"), None);
+    }
 
-        let idx = self.pool.len() as u32;
-        let lower_name = filename.to_lowercase();
+    #[test]
+    fn test_pathological_query_is_capped() {
+        // Worst case: every entry matches a single-letter query
+        let mut index = FileIndex::new();
+        for i in 0..20_000 {
+            index.insert(PathBuf::from(format!("C:\\data\\x{}a.txt", i))).unwrap();
+        }
+        index.insert(PathBuf::from("C:\\data\\a.txt")).unwrap();
+        index.insert(PathBuf::from("C:\\data\\a_notes.txt")).unwrap();
+        index.insert(PathBuf::from("C:\\data\\old a.txt")).unwrap();
 
-        // Add to filename index
-        self.filename_index
-            .entry(lower_name)
-            .or_default()
-            .push(idx);
+        let start = std::time::Instant::now();
+        let results = index.search_limited("a", 1_000, SortOrder::Name);
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        assert!(results.truncated);
+        assert_eq!(results.paths.len(), 1_000);
 
-        // Add to extension index
-        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-            self.extension_index
-                .entry(ext.to_lowercase())
-                .or_default()
-                .push(idx);
+        // The cap keeps the exact, prefix and token matches
+        for name in ["a.txt", "a_notes.txt", "old a.txt"] {
+            let wanted = PathBuf::from(format!("C:\\data\\{}", name));
+            assert!(results.paths.contains(&wanted), "{} dropped", name);
         }
 
-        // Update tracking structures
-        let path_display = path.display().to_string();
-        self.seen_paths.insert(path.clone());
-        self.pool.push(path);
-        self.stats.insertions.fetch_add(1, Ordering::Relaxed);
+        // Truncated results are still repeatable
+        assert_eq!(index.search_limited("a", 1_000, SortOrder::Name).paths, results.paths);
 
-        debug!("Inserted file #{}: {}", idx, path_display);
-        Ok(true)
+        let all = index.search_limited("a", DEFAULT_CANDIDATE_LIMIT, SortOrder::Name);
+        assert!(!all.truncated);
+        assert_eq!(all.paths.len(), 20_003);
+        assert!(index.search_limited(".txt", 10, SortOrder::Name).truncated);
     }
 
-    /// Remove a file path from the index
-    pub fn remove(&mut self, path: &PathBuf) -> Result<bool> {
-        if !self.seen_paths.remove(path) {
-            return Ok(false); // Not found
+    #[test]
+    fn test_refining_search_agrees_with_full_search() {
+        let mut index = FileIndex::new();
+        for name in ["report.pdf", "reports_2024.xlsx", "my report.txt", "repo.zip", "deport.doc", "notes.txt"] {
+            index.insert(PathBuf::from(format!("/docs/{}", name))).unwrap();
         }
+        
+        // Typing, deleting and retyping, each step checked against a full search
+        let mut cache = None;
+        for query in ["r", "re", "rep", "repor", "report", "report ", "repor", "epor", "report.pdf", "notes", "*.txt", "report"] {
+            let refined = index.search_refining(query, 100, SortOrder::Name, &mut cache, &unstopped()).unwrap();
+            assert_eq!(refined.paths, index.search(query), "{:?}", query);
+        }
+        assert_eq!(cache.as_ref().map(|c| c.needle.as_str()), Some("report"));
+        
+        // Growing the query narrows the cached ids
+        index.search_refining("report", 100, SortOrder::Name, &mut cache, &unstopped()).unwrap();
+        index.search_refining("reports", 100, SortOrder::Name, &mut cache, &unstopped()).unwrap();
+        assert_eq!(cache.as_ref().map(|c| c.ids.len()), Some(1));
+        
+        // An index change since the last search forces a full search
+        index.insert(PathBuf::from("/docs/reports_2025.xlsx")).unwrap();
+        assert_eq!(index.search_refining("reports_", 100, SortOrder::Name, &mut cache, &unstopped()).unwrap().paths.len(), 2);
+        index.remove(Path::new("/docs/reports_2024.xlsx")).unwrap();
+        assert_eq!(
+            index.search_refining("reports_20", 100, SortOrder::SizeDesc, &mut cache, &unstopped()).unwrap().paths,
+            vec![PathBuf::from("/docs/reports_2025.xlsx")]
+        );
+        
+        // A truncated result set is incomplete, so it is never refined
+        assert!(index.search_refining("r", 2, SortOrder::Name, &mut cache, &unstopped()).unwrap().truncated);
+        assert!(cache.is_none());
+        assert_eq!(index.search_refining("report", 2, SortOrder::Name, &mut cache, &unstopped()).unwrap().paths.len(), 2);
+        
+        // Ids gathered for a size sort are uncapped, so a capped search starts over
+        index.search_refining("rep", 100, SortOrder::SizeDesc, &mut cache, &unstopped()).unwrap();
+        assert!(index.search_refining("repo", 2, SortOrder::Name, &mut cache, &unstopped()).unwrap().truncated);
+    }
 
-        // Find and mark as deleted in pool (we don't actually remove to keep indices valid)
-        // In a production version, you'd implement compaction here
-        debug!("Removed path: {}", path.display());
-        Ok(true)
+    #[test]
+    fn test_repeated_searches_are_cached_until_the_index_changes() {
+        let mut index = FileIndex::new();
+        for name in ["report.pdf", "report_draft.pdf", "notes.txt"] {
+            index.insert(PathBuf::from(format!("/docs/{}", name))).unwrap();
+        }
+        let cache = |index: &FileIndex| index.stats().cache;
+        
+        let first = index.search("report");
+        assert_eq!(cache(&index), CacheStats { hits: 0, misses: 1 });
+        assert_eq!(index.search("report"), first);
+        assert_eq!(index.search("  report "), first);
+        assert_eq!(cache(&index), CacheStats { hits: 2, misses: 1 });
+        
+        // Another order or cap is another result set
+        index.search_limited("report", 1, SortOrder::Name);
+        index.search_limited("report", usize::MAX, SortOrder::SizeDesc);
+        assert_eq!(cache(&index).misses, 3);
+        
+        // Any change to the index makes every cached set stale
+        index.insert(PathBuf::from("/docs/report_final.pdf")).unwrap();
+        assert_eq!(index.search("report").len(), 3);
+        index.remove(Path::new("/docs/report.pdf")).unwrap();
+        assert_eq!(index.search("report").len(), 2);
+        index.clear();
+        assert!(index.search("report").is_empty());
+        assert_eq!(cache(&index), CacheStats { hits: 2, misses: 6 });
+        
+        // Malformed and empty queries are never cached, nor counted
+        assert!(index.search("").is_empty());
+        assert!(index.search_refining("a AND", 10, SortOrder::Name, &mut None, &unstopped()).is_err());
+        assert_eq!(cache(&index), CacheStats { hits: 2, misses: 6 });
     }
 
-    /// Search for files matching the query
-    /// - Queries starting with '.' perform O(1) extension lookup
-    /// - Other queries perform parallel substring search across filenames
-    pub fn search(&self, query: &str) -> Vec<PathBuf> {
-        self.stats.searches.fetch_add(1, Ordering::Relaxed);
+    #[test]
+    fn test_timed_out_search_keeps_only_confirmed_matches() {
+        let mut index = FileIndex::new();
+        for i in 0..50_000 {
+            index.insert(PathBuf::from(format!("/archive/{}/scan_{}.pdf", i % 100, i))).unwrap();
+        }
+        let full = index.search_limited("path:archive\\4", usize::MAX, SortOrder::Name);
+        assert!(!full.timed_out);
+        // Cached results come back at once, with no scan to time out
+        index.query_cache.get_mut().clear();
         
-        let q = query.trim().to_lowercase();
-        if q.is_empty() {
-            return vec![];
+        let mut cache = None;
+        let cancel = AtomicBool::new(false);
+        for query in ["path:archive\\4", "scan_1", "scan NOT 7", "in:archive scan"] {
+            let stop = SearchStop::new(&cancel, Some(Duration::ZERO));
+            let found = index.search_refining(query, usize::MAX, SortOrder::Name, &mut cache, &stop).unwrap();
+            assert!(found.timed_out && found.truncated, "{:?} did not time out", query);
+            let every = index.search(query);
+            assert!(found.paths.iter().all(|path| every.contains(path)), "{:?}", query);
+            assert!(cache.is_none());
         }
+        
+        // Plenty of time leaves the search untouched
+        index.query_cache.get_mut().clear();
+        let stop = SearchStop::new(&cancel, Some(Duration::from_secs(60)));
+        let found = index.search_refining("path:archive\\4", usize::MAX, SortOrder::Name, &mut cache, &stop).unwrap();
+        assert!(!found.timed_out);
+        assert_eq!(found.paths, full.paths);
+    }
 
-        let mut matched_indices = HashSet::new();
+    /// Timing comparison; run with
+    /// `cargo test --release bench_refining -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_refining_vs_full_search() {
+        const FILES: usize = 1_000_000;
+        let words = ["report", "invoice", "photo", "notes", "budget", "draft", "scan", "backup"];
+        let mut index = FileIndex::new();
+        for i in 0..FILES {
+            let name = format!("{}_{}_{}.dat", words[i % words.len()], words[(i / 8) % words.len()], i);
+            index.insert(PathBuf::from(format!("/data/{}/{}", i % 1000, name))).unwrap();
+        }
+        
+        let typed = ["in", "inv", "invo", "invoi", "invoic", "invoice", "invoice_", "invoice_b", "invoice_bu"];
+        let time = |refine: bool| {
+            // Both runs repeat the same queries, which must not come from the result cache
+            index.query_cache.lock().clear();
+            let start = std::time::Instant::now();
+            let mut cache = None;
+            let mut found = 0;
+            for query in typed {
+                if !refine {
+                    cache = None;
+                }
+                let keystroke = std::time::Instant::now();
+                found = index.search_refining(query, usize::MAX, SortOrder::Name, &mut cache, &unstopped()).unwrap().paths.len();
+                println!("  {:<8} {:<12} {:>7} results {:?}", if refine { "refined" } else { "full" }, query, found, keystroke.elapsed());
+            }
+            (start.elapsed(), found)
+        };
+        
+        let (full, full_found) = time(false);
+        let (refined, refined_found) = time(true);
+        assert_eq!(full_found, refined_found);
+        println!(
+            "{} files, {} keystrokes: full {:?}, refined {:?} ({:.1}x)",
+            FILES,
+            typed.len(),
+            full,
+            refined,
+            full.as_secs_f64() / refined.as_secs_f64()
+        );
+    }
 
-        // Extension search (e.g., ".pdf")
-        if q.starts_with('.') {
-            let ext = q.trim_start_matches('.');
-            
-            // Support compound extensions like ".tar.gz"
-            if let Some(indices) = self.extension_index.get(ext) {
-                matched_indices.extend(indices);
+    #[test]
+    fn test_trigram_search_agrees_with_full_scan() {
+        let names = ["Invoice 2024.pdf", "invoice_draft.docx", "voice memo.m4a", "résumé.pdf", "aaaa.txt", "notes.txt"];
+        let mut plain = FileIndex::new();
+        let mut fast = FileIndex::new();
+        fast.set_trigram_index(true);
+        for (i, name) in names.iter().enumerate() {
+            for path in [format!("/docs/{}/{}", i, name), format!("/backup/{}", name)] {
+                plain.insert(PathBuf::from(&path)).unwrap();
+                fast.insert(PathBuf::from(&path)).unwrap();
             }
-            
-            // Also try matching the full extension for compound cases
-            if ext.contains('.') {
-                // For ".tar.gz", also search for files ending with full extension
-                let results: Vec<u32> = self.pool
-                    .par_iter()
-                    .enumerate()
-                    .filter(|(_, path)| {
-                        path.to_string_lossy()
-                            .to_lowercase()
-                            .ends_with(&q)
-                    })
-                    .map(|(idx, _)| idx as u32)
-                    .collect();
-                matched_indices.extend(results);
+        }
+        
+        let queries = ["in", "invoice", "voice", "oice 2", "sumé", "aaa", "txt", "xyz", "invoice .pdf", "voice NOT memo"];
+        let agree = |fast: &FileIndex, plain: &FileIndex| {
+            for query in queries {
+                assert_eq!(fast.search(query), plain.search(query), "{:?}", query);
             }
-        } else {
-            // Parallel substring search across all filenames
-            let results: Vec<u32> = self
-                .filename_index
-                .par_iter()
-                .filter(|(name, _)| name.contains(&q))
-                .flat_map(|(_, indices)| indices.clone())
-                .collect();
-            matched_indices.extend(results);
+        };
+        agree(&fast, &plain);
+        
+        // Removed slots linger in the trigram index but never reach the results
+        for index in [&mut fast, &mut plain] {
+            index.remove(Path::new("/backup/invoice_draft.docx")).unwrap();
+            index.remove(Path::new("/docs/2/voice memo.m4a")).unwrap();
         }
-
-        // Convert indices to paths, filter out deleted files, and sort
-        let mut results: Vec<PathBuf> = matched_indices
-            .into_iter()
-            .filter(|&idx| (idx as usize) < self.pool.len()) // Safety check
-            .map(|idx| self.pool[idx as usize].clone())
-            .filter(|path| self.seen_paths.contains(path)) // Filter out deleted files
-            .collect();
-
-        results.sort_unstable_by(|a, b| {
-            // Sort by filename, case-insensitive
-            let a_name = a.file_name().map(|n| n.to_string_lossy().to_lowercase());
-            let b_name = b.file_name().map(|n| n.to_string_lossy().to_lowercase());
-            a_name.cmp(&b_name)
-        });
-
-        debug!("Search '{}' returned {} results", query, results.len());
-        results
+        agree(&fast, &plain);
+        assert!(fast.search("voice memo").iter().all(|p| p.starts_with("/backup")));
+        
+        fast.compact().unwrap();
+        plain.compact().unwrap();
+        agree(&fast, &plain);
+        assert!(fast.check_invariants().is_consistent());
+        
+        fast.set_trigram_index(false);
+        assert_eq!(fast.trigram_memory(), None);
+        agree(&fast, &plain);
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
+    
+    /// Times substring queries with and without the trigram index; run with
+    /// `cargo test --release bench_trigram -- --ignored --nocapture`
     #[test]
-    fn test_basic_insert() {
+    #[ignore]
+    fn bench_trigram_vs_full_scan() {
+        const FILES: usize = 1_000_000;
+        let words = ["report", "invoice", "photo", "notes", "budget", "draft", "scan", "backup"];
         let mut index = FileIndex::new();
-        let path = PathBuf::from("C:\\test\\file.txt");
+        for i in 0..FILES {
+            let name = format!("{}_{}_{}.dat", words[i % words.len()], words[(i / 8) % words.len()], i);
+            index.insert(PathBuf::from(format!("/data/{}/{}", i % 1000, name))).unwrap();
+        }
         
-        assert!(index.insert(path.clone()).unwrap());
-        assert_eq!(index.len(), 1);
+        let queries = ["invoice_b", "draft_scan", "otes_bud", "123456", "99999", "ckup_re", "zzz"];
+        let time = |index: &FileIndex, label: &str| {
+            index.query_cache.lock().clear();
+            let start = std::time::Instant::now();
+            let mut found = Vec::new();
+            for query in queries {
+                let keystroke = std::time::Instant::now();
+                let count = index.search_limited(query, usize::MAX, SortOrder::Name).paths.len();
+                println!("  {:<8} {:<12} {:>7} results {:?}", label, query, count, keystroke.elapsed());
+                found.push(count);
+            }
+            (start.elapsed(), found)
+        };
         
-        // Duplicate insert
-        assert!(!index.insert(path).unwrap());
-        assert_eq!(index.len(), 1);
+        let (scan, scan_found) = time(&index, "scan");
+        let build = std::time::Instant::now();
+        index.set_trigram_index(true);
+        let build = build.elapsed();
+        let (trigram, trigram_found) = time(&index, "trigram");
+        assert_eq!(scan_found, trigram_found);
+        println!(
+            "{} files, {} queries: scan {:?}, trigram {:?} ({:.1}x); built in {:?}, {} MB",
+            FILES,
+            queries.len(),
+            scan,
+            trigram,
+            scan.as_secs_f64() / trigram.as_secs_f64(),
+            build,
+            index.trigram_memory().unwrap_or(0) / (1024 * 1024)
+        );
     }
 
+    /// Every live path whose lowercased filename starts with `prefix`
+    fn brute_force_prefix(paths: &[&str], prefix: &str) -> Vec<PathBuf> {
+        sort_results(
+            paths
+                .iter()
+                .map(PathBuf::from)
+                .filter(|p| index_keys(p).0.is_some_and(|name| name.starts_with(prefix)))
+                .collect(),
+        )
+    }
+    
     #[test]
-    fn test_extension_search() {
+    fn test_prefix_search_agrees_with_brute_force() {
+        let mut live = vec!["/photos/IMG_0001.jpg", "/photos/img_0002.JPG", "/photos/IMG.png", "/docs/2024-03 report.pdf", "/docs/2024.txt", "/docs/notes IMG_1.txt"];
         let mut index = FileIndex::new();
-        index.insert(PathBuf::from("C:\\test\\doc.pdf")).unwrap();
-        index.insert(PathBuf::from("C:\\test\\notes.txt")).unwrap();
+        for p in &live {
+            index.insert(PathBuf::from(p)).unwrap();
+        }
+        let queries = ["img", "img_", "img_000", "2024", "2024-", "notes", "zzz", "i"];
+        let agree = |index: &FileIndex, live: &[&str]| {
+            for prefix in queries {
+                assert_eq!(index.search(&format!("^{}", prefix)), brute_force_prefix(live, prefix), "^{}", prefix);
+            }
+        };
+        agree(&index, &live);
         
-        let results = index.search(".pdf");
-        assert_eq!(results.len(), 1);
-        assert!(results[0].to_string_lossy().contains("doc.pdf"));
+        // Adding and removing names after the first prefix query rebuilds the order
+        for p in ["/new/IMG_0003.jpg", "/new/2024-12.csv", "/new/img.png"] {
+            index.insert(PathBuf::from(p)).unwrap();
+            live.push(p);
+        }
+        agree(&index, &live);
+        index.remove(Path::new("/photos/IMG.png")).unwrap();
+        index.remove(Path::new("/docs/2024.txt")).unwrap();
+        live.retain(|p| !["/photos/IMG.png", "/docs/2024.txt"].contains(p));
+        agree(&index, &live);
+        index.compact().unwrap();
+        agree(&index, &live);
+        
+        // The exact name comes first even when the cap cuts the rest
+        assert_eq!(index.search_limited("^img", 1, SortOrder::Name).paths, vec![PathBuf::from("/new/img.png")]);
+        assert_eq!(index.search("^img_ .txt"), Vec::<PathBuf>::new());
+        assert_eq!(index.search("^notes .txt"), vec![PathBuf::from("/docs/notes IMG_1.txt")]);
+        assert_eq!(
+            explain_match("^IMG_", Path::new("/x/img_9.jpg")),
+            Some(MatchReason::Filename { query: "img_".into(), tier: MatchTier::Prefix })
+        );
+        assert_eq!(explain_match("^IMG_", Path::new("/x/notes IMG_1.txt")), None);
     }
-
+    
+    /// Times prefix queries against a full scan of every name; run with
+    /// `cargo test --release bench_prefix -- --ignored --nocapture`
     #[test]
-    fn test_substring_search() {
+    #[ignore]
+    fn bench_prefix_vs_full_scan() {
+        const FILES: usize = 1_000_000;
+        let words = ["report", "invoice", "photo", "notes", "budget", "draft", "scan", "backup"];
         let mut index = FileIndex::new();
-        index.insert(PathBuf::from("C:\\test\\budget_2024.xlsx")).unwrap();
-        index.insert(PathBuf::from("C:\\test\\budget_report.pdf")).unwrap();
-        index.insert(PathBuf::from("C:\\test\\invoice.pdf")).unwrap();
+        for i in 0..FILES {
+            let name = format!("{}_{}_{}.dat", words[i % words.len()], words[(i / 8) % words.len()], i);
+            index.insert(PathBuf::from(format!("/data/{}/{}", i % 1000, name))).unwrap();
+        }
         
-        let results = index.search("budget");
-        assert_eq!(results.len(), 2);
+        let prefixes = ["invoice_b", "draft_scan_1", "notes_budget_99", "photo_", "zzz"];
+        let start = std::time::Instant::now();
+        let scanned: Vec<usize> = prefixes
+            .iter()
+            .map(|prefix| index.filename_index.par_iter().filter(|(name, _)| name.starts_with(prefix)).map(|(_, ids)| ids.len()).sum())
+            .collect();
+        let scan = start.elapsed();
+        
+        let first = std::time::Instant::now();
+        index.search_limited("^zzz", usize::MAX, SortOrder::Name);
+        let build = first.elapsed();
+        let start = std::time::Instant::now();
+        let mut found = Vec::new();
+        for prefix in prefixes {
+            let keystroke = std::time::Instant::now();
+            let mut candidates = Vec::new();
+            index.term_candidates(&ParsedQuery::StartsWith(prefix.to_string()), usize::MAX, &unstopped(), &mut candidates);
+            println!("  ^{:<16} {:>7} ids {:?}", prefix, candidates.len(), keystroke.elapsed());
+            found.push(candidates.len());
+        }
+        let sorted = start.elapsed();
+        assert_eq!(scanned, found);
+        println!(
+            "{} files, {} prefixes: scan {:?}, sorted {:?} ({:.1}x); first query sorted the names in {:?}",
+            FILES,
+            prefixes.len(),
+            scan,
+            sorted,
+            scan.as_secs_f64() / sorted.as_secs_f64(),
+            build
+        );
     }
 
     #[test]
-    fn test_max_capacity() {
+    fn test_explain_match_agrees_with_search() {
         let mut index = FileIndex::new();
-        // This would take too long in real test, so we just test the error
-        for i in 0..MAX_INDEX_SIZE {
-            if i >= MAX_INDEX_SIZE {
-                let path = PathBuf::from(format!("C:\\test\\file_{}.txt", i));
-                assert!(index.insert(path).is_err());
-                break;
+        let paths = [
+            "C:\\docs\\budget.xlsx",
+            "C:\\docs\\march budget.pdf",
+            "C:\\docs\\nobudgets.txt",
+            "C:\\backup\\site.tar.gz",
+        ];
+        for p in paths {
+            index.insert(PathBuf::from(p)).unwrap();
+        }
+
+        for query in ["budget", ".pdf", ".tar.gz", "site"] {
+            let results = index.search(query);
+            for p in paths.iter().map(PathBuf::from) {
+                let explained = explain_match(query, &p).is_some();
+                assert_eq!(explained, results.contains(&p), "{} / {}", query, p.display());
             }
         }
+
+        let march = Path::new("C:\\docs\\march budget.pdf");
+        assert_eq!(
+            explain_match("Budget", march),
+            Some(MatchReason::Filename { query: "budget".into(), tier: MatchTier::Token })
+        );
+        assert_eq!(explain_match(".PDF", march), Some(MatchReason::Extension("pdf".into())));
+        assert_eq!(
+            explain_match("c:\\docs", march),
+            Some(MatchReason::PathPrefix("c:\\docs".into()))
+        );
+        assert_eq!(
+            explain_match(".tar.gz", Path::new("C:\\backup\\site.tar.gz")).unwrap().to_string(),
+            "Filename ends with .tar.gz"
+        );
     }
 
     #[test]
-    fn test_compound_extension() {
+    fn test_every_operator_example_parses_with_its_operator() {
+        assert_eq!(QUERY_OPERATORS.last().map(|op| op.name), Some("Filename"));
+        for op in QUERY_OPERATORS {
+            assert!(!op.example.is_empty() && !op.since.is_empty(), "{} lacks docs", op.name);
+            let parsed = parse_query(op.example);
+            assert!(
+                !matches!(parsed, ParsedQuery::Empty | ParsedQuery::Hint(_)),
+                "{} example does not parse",
+                op.name
+            );
+            assert_eq!(query_operator(op.example).map(|o| o.name), Some(op.name));
+        }
+    }
+
+    /// Heap held by paths and path keys on 1M files in 1000 deep folders,
+    /// stored in full as before v10 and interned now; run with
+    /// `cargo test --release bench_interned -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_interned_pool_memory() {
+        const FILES: usize = 1_000_000;
         let mut index = FileIndex::new();
-        index.insert(PathBuf::from("C:\\test\\archive.tar.gz")).unwrap();
+        for i in 0..FILES {
+            index.insert(PathBuf::from(format!("/data/projects/client/archive/{}/report_{}.dat", i % 1000, i))).unwrap();
+        }
         
-        let results = index.search(".tar.gz");
-        assert_eq!(results.len(), 1);
+        // Before v10: a `PathBuf` per slot plus a `String` key per live entry
+        let paths: Vec<PathBuf> = index.pool.iter().collect();
+        let full: usize = paths
+            .iter()
+            .map(|path| std::mem::size_of::<PathBuf>() + path.as_os_str().len())
+            .sum::<usize>()
+            + paths.iter().map(|path| std::mem::size_of::<(String, u32)>() + path_key(path).len()).sum::<usize>();
+        let interned = index.pool.heap_bytes() + index.seen_paths.heap_bytes();
+        println!(
+            "{} files: full paths {} MB, interned {} MB ({:.1}x smaller)",
+            FILES,
+            full / 1_000_000,
+            interned / 1_000_000,
+            full as f64 / interned as f64
+        );
+        assert!(interned < full);
+    }
+
+    /// Inserting 100k paths one at a time against prepared batches of the
+    /// scanner's size; run with
+    /// `cargo test --release bench_batch_insert -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_batch_insert_vs_single() {
+        const FILES: usize = 100_000;
+        const BATCH: usize = 1000;
+        let entries: Vec<(PathBuf, EntryMeta)> = (0..FILES)
+            .map(|i| (PathBuf::from(format!("/data/{}/Quarterly Report_{}.final.pdf", i % 1000, i)), EntryMeta::UNKNOWN))
+            .collect();
+        
+        // Time spent holding the index, as a lock would be held
+        let mut single = FileIndex::new();
+        let mut single_held = Duration::ZERO;
+        for chunk in entries.chunks(BATCH) {
+            let start = Instant::now();
+            for (path, meta) in chunk {
+                single.insert_with_meta(path.clone(), *meta).unwrap();
+            }
+            single_held += start.elapsed();
+        }
+        
+        let mut batched = FileIndex::new();
+        let (mut prepare, mut batched_held) = (Duration::ZERO, Duration::ZERO);
+        let mut added = 0;
+        for chunk in entries.chunks(BATCH) {
+            let start = Instant::now();
+            let batch = PreparedBatch::new(chunk.to_vec());
+            prepare += start.elapsed();
+            let start = Instant::now();
+            added += batched.insert_batch(batch).unwrap().added;
+            batched_held += start.elapsed();
+        }
+        
+        assert_eq!(added, FILES);
+        assert_eq!(batched.search("report_99").len(), single.search("report_99").len());
+        println!(
+            "{} files: one at a time held {:?}; batched held {:?} ({:.1}x less) after {:?} preparing unlocked",
+            FILES,
+            single_held,
+            batched_held,
+            single_held.as_secs_f64() / batched_held.as_secs_f64(),
+            prepare
+        );
     }
 }
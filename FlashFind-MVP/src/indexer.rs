@@ -1,46 +1,156 @@
 use crossbeam_channel::{bounded, Sender};
 use parking_lot::RwLock;
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
-use std::time::Instant;
+use std::time::{Instant, SystemTime};
 use tracing::{debug, error, info, warn};
 use walkdir::WalkDir;
 
+use crate::activity::ActivityLog;
+use crate::archive::{is_archive, list_members};
 use crate::error::{FlashFindError, Result};
-use crate::index::FileIndex;
-use crate::persistence::save_index;
-use crate::watcher::is_excluded;
+use crate::health::{verify_and_prune, PruneReport, PRUNE_TIME_BUDGET};
+use crate::index::{EntryMeta, PreparedBatch};
+use crate::sharded::{Shard, ShardedIndex};
+use crate::paths::{from_long_path, is_within, to_long_path};
+use crate::persistence::SaveCoordinator;
+use crate::redact::redact;
+use crate::shard::import_shard;
+use crate::watcher::Exclusions;
 
 /// Indexing state and progress information
 #[derive(Clone, Debug)]
 pub enum IndexState {
     Idle,
     Scanning { progress: usize },
+    /// Checking indexed files against the disk (see `IndexCommand::Prune`)
+    Pruning { checked: usize, total: usize },
+    /// Dropping removed entries (see `IndexCommand::Compact`)
+    Compacting,
     Saving,
     Error { message: String },
 }
 
 /// Commands that can be sent to the indexer thread
+///
+/// Commands are processed one at a time in the order they were sent, so a
+/// rebuild can never interleave with another scan.
 pub enum IndexCommand {
     StartScan(Vec<PathBuf>),
+    /// Clear the index, then scan; `generation` lets the thread skip
+    /// rebuilds that a newer rebuild request has already superseded
+    Rebuild {
+        directories: Vec<PathBuf>,
+        generation: u64,
+    },
+    /// Merge the shard written by an elevated helper scan of `roots`
+    ImportShard { shard: PathBuf, roots: Vec<PathBuf> },
+    /// Drop removed entries once they hold `COMPACTION_THRESHOLD` of the
+    /// pool, or whenever there are any with `force`
+    Compact { force: bool },
+    /// Remove entries under `roots` whose files are gone (see
+    /// `health::verify_and_prune`)
+    Prune { roots: Vec<PathBuf> },
+    /// Remove the entries of drives no longer indexed, then scan the
+    /// folders of newly enabled ones
+    ChangeDrives { removed: Vec<char>, added: Vec<PathBuf> },
 }
 
+/// Share of pool slots held by removed entries that triggers compaction
+pub const COMPACTION_THRESHOLD: f64 = 0.2;
+
+/// Compactions built before falling back to one under the write lock, as
+/// each is discarded if the index changed while it was being built
+const COMPACTION_ATTEMPTS: usize = 3;
+
 /// Result of indexing operation
 pub struct IndexResult {
     pub files_added: usize,
     pub duration_ms: u64,
+    /// Problems met while walking
+    pub report: ScanReport,
+}
+
+/// Entries a walk had to skip, surfaced in the Status view
+#[derive(Debug, Clone, Default)]
+pub struct ScanReport {
+    /// Directories skipped because reading them was not permitted
+    pub denied: Vec<PathBuf>,
+    /// Archives whose members could not be listed
+    pub corrupt_archives: Vec<PathBuf>,
+}
+
+/// What recent scans found, kept for the Status and Health views
+#[derive(Debug, Clone, Default)]
+pub struct ScanHistory {
+    /// Directories skipped because reading them was not permitted
+    pub denied: Vec<PathBuf>,
+    /// Archives skipped because they could not be read
+    pub corrupt_archives: Vec<PathBuf>,
+    /// When each root last finished a full scan (this session)
+    pub completed: Vec<(PathBuf, SystemTime)>,
+    /// Compactions run this session
+    pub compaction: CompactionReport,
+    /// The last pass removing entries whose files are gone
+    pub prune: Option<PruneReport>,
+}
+
+/// Compactions run this session, for the Statistics view
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompactionReport {
+    pub runs: usize,
+    /// Removed entries reclaimed in all
+    pub reclaimed: usize,
+    /// When the last one finished and the entries it reclaimed
+    pub last: Option<(SystemTime, usize)>,
+}
+
+impl CompactionReport {
+    fn record(&mut self, reclaimed: usize) {
+        self.runs += 1;
+        self.reclaimed += reclaimed;
+        self.last = Some((SystemTime::now(), reclaimed));
+    }
+}
+
+impl ScanHistory {
+    /// Replace the denied directories found under `roots`
+    fn replace_denied(&mut self, roots: &[PathBuf], denied: Vec<PathBuf>) {
+        self.denied.retain(|dir| !roots.iter().any(|root| is_within(dir, root)));
+        self.denied.extend(denied);
+    }
+    
+    /// Record a finished scan of `roots`
+    fn record_scan(&mut self, roots: &[PathBuf], report: ScanReport) {
+        self.replace_denied(roots, report.denied);
+        self.corrupt_archives.retain(|zip| !roots.iter().any(|root| is_within(zip, root)));
+        self.corrupt_archives.extend(report.corrupt_archives);
+        
+        let now = SystemTime::now();
+        for root in roots.iter().filter(|r| to_long_path(r).is_dir()) {
+            match self.completed.iter_mut().find(|(done, _)| done == root) {
+                Some(entry) => entry.1 = now,
+                None => self.completed.push((root.clone(), now)),
+            }
+        }
+    }
 }
 
 /// Background indexer that scans directories without blocking the UI
 pub struct Indexer {
     #[allow(dead_code)]
-    index: Arc<RwLock<FileIndex>>,
+    index: Arc<ShardedIndex>,
     state: Arc<RwLock<IndexState>>,
-    is_running: Arc<AtomicBool>,
-    #[allow(dead_code)]
+    /// Commands sent but not yet finished (queued or running)
+    pending: Arc<AtomicUsize>,
     cancel_flag: Arc<AtomicBool>,
+    /// Generation of the most recent rebuild request
+    rebuild_generation: Arc<AtomicU64>,
+    /// Denied directories and scan completion times
+    history: Arc<RwLock<ScanHistory>>,
     command_tx: Sender<IndexCommand>,
     #[allow(dead_code)]
     thread_handle: Option<JoinHandle<()>>,
@@ -48,26 +158,42 @@ pub struct Indexer {
 
 impl Indexer {
     /// Create a new background indexer
-    pub fn new(index: Arc<RwLock<FileIndex>>) -> Result<Self> {
+    ///
+    /// Files later scans add and pruning removes are counted in `activity`.
+    pub fn new(
+        index: Arc<ShardedIndex>,
+        exclusions: Arc<RwLock<Exclusions>>,
+        saver: Arc<SaveCoordinator>,
+        activity: Arc<ActivityLog>,
+    ) -> Result<Self> {
         let (command_tx, command_rx) = bounded::<IndexCommand>(10);
         
         let state = Arc::new(RwLock::new(IndexState::Idle));
-        let is_running = Arc::new(AtomicBool::new(false));
+        let pending = Arc::new(AtomicUsize::new(0));
         let cancel_flag = Arc::new(AtomicBool::new(false));
+        let rebuild_generation = Arc::new(AtomicU64::new(0));
+        let history = Arc::new(RwLock::new(ScanHistory::default()));
         
         // Clone Arc references for the thread
         let thread_index = index.clone();
         let thread_state = state.clone();
-        let thread_running = is_running.clone();
+        let thread_pending = pending.clone();
         let thread_cancel = cancel_flag.clone();
+        let thread_generation = rebuild_generation.clone();
+        let thread_history = history.clone();
         
         // Spawn background thread
         let thread_handle = thread::spawn(move || {
             indexer_thread(
                 thread_index,
+                exclusions,
+                saver,
+                activity,
                 thread_state,
-                thread_running,
+                thread_pending,
                 thread_cancel,
+                thread_generation,
+                thread_history,
                 command_rx,
             );
         });
@@ -75,8 +201,10 @@ impl Indexer {
         Ok(Self {
             index,
             state,
-            is_running,
+            pending,
             cancel_flag,
+            rebuild_generation,
+            history,
             command_tx,
             thread_handle: Some(thread_handle),
         })
@@ -84,17 +212,96 @@ impl Indexer {
     
     /// Start scanning directories
     pub fn start_scan(&self, directories: Vec<PathBuf>) -> Result<()> {
-        if self.is_running.load(Ordering::Relaxed) {
+        if self.is_running() {
             warn!("Indexing already in progress");
             return Ok(());
         }
         
         info!("Starting scan of {} directories", directories.len());
-        self.command_tx
-            .send(IndexCommand::StartScan(directories))
-            .map_err(|_| FlashFindError::ThreadPanic("Indexer thread not responding".to_string()))?;
+        self.send(IndexCommand::StartScan(directories))
+    }
+    
+    /// Clear the index and rebuild it from `directories`
+    ///
+    /// Any running scan is cancelled, then the clear and exactly one new scan
+    /// run on the indexer thread in order. Rapid repeated requests collapse
+    /// into the latest one.
+    pub fn rebuild(&self, directories: Vec<PathBuf>) -> Result<()> {
+        // Publish the generation before cancelling, so the thread either sees
+        // the cancel or skips the superseded command (see `indexer_thread`)
+        let generation = self.rebuild_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.cancel_flag.store(true, Ordering::SeqCst);
         
-        Ok(())
+        info!("Rebuilding index from {} directories", directories.len());
+        self.send(IndexCommand::Rebuild { directories, generation })
+    }
+    
+    /// Drop the entries of `removed` drives and scan `added` folders,
+    /// leaving every other drive's entries in place
+    ///
+    /// Queued behind any running scan, unlike `start_scan`.
+    pub fn change_drives(&self, removed: Vec<char>, added: Vec<PathBuf>) -> Result<()> {
+        info!("Changing drives: {} removed, {} folders added", removed.len(), added.len());
+        self.send(IndexCommand::ChangeDrives { removed, added })
+    }
+    
+    /// Merge files found by an elevated helper scan of `roots`
+    ///
+    /// Runs on the indexer thread after any queued scan; the shard file is
+    /// deleted whether or not the import succeeds.
+    pub fn import_shard(&self, shard: PathBuf, roots: Vec<PathBuf>) -> Result<()> {
+        info!("Importing elevated scan of {} directories", roots.len());
+        self.send(IndexCommand::ImportShard { shard, roots })
+    }
+    
+    /// Drop removed entries on the indexer thread, once they hold
+    /// `COMPACTION_THRESHOLD` of the pool or, with `force`, whenever there
+    /// are any
+    ///
+    /// Searches keep running while it works; see `compact_index`.
+    pub fn compact(&self, force: bool) -> Result<()> {
+        self.send(IndexCommand::Compact { force })
+    }
+    
+    /// Remove entries under `roots` whose files are gone, on the indexer
+    /// thread after any queued scan
+    pub fn prune(&self, roots: Vec<PathBuf>) -> Result<()> {
+        info!("Pruning missing files under {} directories", roots.len());
+        self.send(IndexCommand::Prune { roots })
+    }
+    
+    /// The last pass removing entries whose files are gone, this session
+    pub fn last_prune(&self) -> Option<PruneReport> {
+        self.history.read().prune
+    }
+    
+    /// Directories skipped by the last scans because access was denied
+    pub fn denied_dirs(&self) -> Vec<PathBuf> {
+        self.history.read().denied.clone()
+    }
+    
+    /// Archives the last scans could not read
+    pub fn corrupt_archives(&self) -> Vec<PathBuf> {
+        self.history.read().corrupt_archives.clone()
+    }
+    
+    /// Compactions run this session
+    pub fn compactions(&self) -> CompactionReport {
+        self.history.read().compaction
+    }
+    
+    /// When each root last finished a full scan this session
+    pub fn last_scans(&self) -> Vec<(PathBuf, SystemTime)> {
+        self.history.read().completed.clone()
+    }
+    
+    /// Queue a command for the indexer thread
+    fn send(&self, command: IndexCommand) -> Result<()> {
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        self.command_tx.send(command).map_err(|_| {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            FlashFindError::ThreadPanic("Indexer thread not responding".to_string())
+        })
     }
     
     /// Get current indexing state
@@ -102,84 +309,250 @@ impl Indexer {
         self.state.read().clone()
     }
     
-    /// Check if indexing is currently running
+    /// Check if indexing is currently running or queued
     pub fn is_running(&self) -> bool {
-        self.is_running.load(Ordering::Relaxed)
+        self.pending.load(Ordering::SeqCst) > 0
+    }
+}
+
+/// Drop removed entries, returning how many were reclaimed
+///
+/// Each shard is compacted in turn (see `compact_shard`), so only one is
+/// ever locked for it, and searches move to the compacted shards at once.
+fn compact_index(index: &ShardedIndex) -> usize {
+    let reclaimed = index.shards().iter().map(compact_shard).sum();
+    index.publish();
+    reclaimed
+}
+
+/// Drop a shard's removed entries, returning how many were reclaimed
+///
+/// The compacted structures are built under a read lock, so the watcher's
+/// lookups run meanwhile, and swapped in under a short write lock. Changes made while
+/// building make it stale; after `COMPACTION_ATTEMPTS` such retries the
+/// shard is compacted under the write lock.
+fn compact_shard(index: &Shard) -> usize {
+    for _ in 0..COMPACTION_ATTEMPTS {
+        let Some(compaction) = index.read().compaction() else {
+            return 0;
+        };
+        if let Some(reclaimed) = index.write().apply_compaction(compaction) {
+            return reclaimed;
+        }
+        debug!("Index changed during compaction, rebuilding it");
     }
+    warn!("Index kept changing during compaction, compacting a shard in place");
+    index.write().compact().unwrap_or_default()
+}
+
+/// Compact the index under the Compacting state and record the run,
+/// returning the entries reclaimed
+fn compact_recorded(index: &ShardedIndex, state: &RwLock<IndexState>, history: &RwLock<ScanHistory>) -> usize {
+    *state.write() = IndexState::Compacting;
+    let reclaimed = compact_index(index);
+    history.write().compaction.record(reclaimed);
+    reclaimed
 }
 
 /// Background thread that handles indexing operations
+#[allow(clippy::too_many_arguments)]
 fn indexer_thread(
-    index: Arc<RwLock<FileIndex>>,
+    index: Arc<ShardedIndex>,
+    exclusions: Arc<RwLock<Exclusions>>,
+    saver: Arc<SaveCoordinator>,
+    activity: Arc<ActivityLog>,
     state: Arc<RwLock<IndexState>>,
-    is_running: Arc<AtomicBool>,
+    pending: Arc<AtomicUsize>,
     cancel_flag: Arc<AtomicBool>,
+    rebuild_generation: Arc<AtomicU64>,
+    history: Arc<RwLock<ScanHistory>>,
     command_rx: crossbeam_channel::Receiver<IndexCommand>,
 ) {
     info!("Indexer thread started");
     
     loop {
-        match command_rx.recv() {
+        let directories = match command_rx.recv() {
             Ok(IndexCommand::StartScan(directories)) => {
-                is_running.store(true, Ordering::Relaxed);
-                cancel_flag.store(false, Ordering::Relaxed);
-                *state.write() = IndexState::Scanning { progress: 0 };
-                
-                let result = scan_directories(
-                    directories,
-                    &index,
-                    &state,
-                    &cancel_flag,
-                );
+                cancel_flag.store(false, Ordering::SeqCst);
+                directories
+            }
+            Ok(IndexCommand::Rebuild { directories, generation }) => {
+                // Reset the cancel flag before checking the generation: a newer
+                // rebuild either shows up here or cancels the scan below
+                cancel_flag.store(false, Ordering::SeqCst);
+                if generation < rebuild_generation.load(Ordering::SeqCst) {
+                    debug!("Skipping superseded rebuild #{}", generation);
+                    pending.fetch_sub(1, Ordering::SeqCst);
+                    continue;
+                }
                 
-                match result {
-                    Ok(stats) => {
-                        info!(
-                            "Scan completed: {} files added in {}ms",
-                            stats.files_added, stats.duration_ms
-                        );
+                info!("Clearing index for rebuild #{}", generation);
+                index.write().clear();
+                {
+                    let mut history = history.write();
+                    history.denied.clear();
+                    history.corrupt_archives.clear();
+                }
+                directories
+            }
+            Ok(IndexCommand::ImportShard { shard, roots }) => {
+                *state.write() = IndexState::Saving;
+                match import_shard(&index, &shard, &roots) {
+                    Ok(imported) => {
+                        info!("Imported {} files from elevated scan", imported.files_added);
+                        history.write().replace_denied(&roots, imported.denied);
                         
-                        // Auto-save after successful scan
-                        *state.write() = IndexState::Saving;
-                        if let Err(e) = save_index(&*index.read()) {
-                            error!("Failed to auto-save index: {}", e);
-                            *state.write() = IndexState::Error {
-                                message: e.user_message(),
-                            };
-                        } else {
-                            *state.write() = IndexState::Idle;
+                        if let Err(e) = saver.save() {
+                            error!("Failed to save after shard import: {}", e);
                         }
+                        *state.write() = IndexState::Idle;
                     }
                     Err(e) => {
-                        error!("Scan failed: {}", e);
+                        error!("Shard import failed: {}", e);
                         *state.write() = IndexState::Error {
                             message: e.user_message(),
                         };
                     }
                 }
-                
-                is_running.store(false, Ordering::Relaxed);
+                pending.fetch_sub(1, Ordering::SeqCst);
+                continue;
+            }
+            Ok(IndexCommand::Compact { force }) => {
+                let dead = index.read().dead_ratio();
+                if force || dead >= COMPACTION_THRESHOLD {
+                    let reclaimed = compact_recorded(&index, &state, &history);
+                    if reclaimed > 0 {
+                        *state.write() = IndexState::Saving;
+                        if let Err(e) = saver.save() {
+                            error!("Failed to save after compaction: {}", e);
+                        }
+                    }
+                    *state.write() = IndexState::Idle;
+                } else {
+                    debug!("Skipping compaction with {:.0}% of entries removed", dead * 100.0);
+                }
+                pending.fetch_sub(1, Ordering::SeqCst);
+                continue;
+            }
+            Ok(IndexCommand::ChangeDrives { removed, added }) => {
+                cancel_flag.store(false, Ordering::SeqCst);
+                if !removed.is_empty() {
+                    let mut lock = index.write();
+                    for letter in removed {
+                        lock.remove_drive(letter);
+                    }
+                    drop(lock);
+                    if index.read().dead_ratio() >= COMPACTION_THRESHOLD {
+                        compact_recorded(&index, &state, &history);
+                    }
+                }
+                // Scanning saves the result, even with no folders to scan
+                added
+            }
+            Ok(IndexCommand::Prune { roots }) => {
+                cancel_flag.store(false, Ordering::SeqCst);
+                *state.write() = IndexState::Pruning { checked: 0, total: 0 };
+                let report = verify_and_prune(&index, &roots, PRUNE_TIME_BUDGET, &cancel_flag, |checked, total| {
+                    *state.write() = IndexState::Pruning { checked, total };
+                });
+                index.publish();
+                history.write().prune = Some(report);
+                activity.record_removed(None, report.pruned as u64);
+                if report.pruned > 0 && index.read().is_empty() {
+                    // Nothing indexed is left, so scan afresh as on first launch
+                    info!("Pruning emptied the index, scanning {} directories", roots.len());
+                    roots
+                } else {
+                    if report.pruned > 0 {
+                        // Pruned entries are tombstones like any removal
+                        if index.read().dead_ratio() >= COMPACTION_THRESHOLD {
+                            compact_recorded(&index, &state, &history);
+                        }
+                        *state.write() = IndexState::Saving;
+                        if let Err(e) = saver.save() {
+                            error!("Failed to save after pruning: {}", e);
+                        }
+                    }
+                    *state.write() = IndexState::Idle;
+                    pending.fetch_sub(1, Ordering::SeqCst);
+                    continue;
+                }
             }
-            
             Err(_) => {
                 warn!("Command channel closed, shutting down");
                 break;
             }
+        };
+        
+        *state.write() = IndexState::Scanning { progress: 0 };
+        
+        // Snapshot rules so settings changes don't wait on the scan
+        let rules = exclusions.read().clone();
+        let scanned = directories.clone();
+        let result = scan_directories(
+            directories,
+            &index,
+            &rules,
+            &activity,
+            &state,
+            &cancel_flag,
+        );
+        
+        match result {
+            Ok(stats) => {
+                info!(
+                    "Scan completed: {} files added in {}ms",
+                    stats.files_added, stats.duration_ms
+                );
+                info!("Index memory: {}", index.read().memory_usage());
+                
+                history.write().record_scan(&scanned, stats.report);
+                
+                // Auto-save after successful scan
+                *state.write() = IndexState::Saving;
+                if let Err(e) = saver.save() {
+                    error!("Failed to auto-save index: {}", e);
+                    *state.write() = IndexState::Error {
+                        message: e.user_message(),
+                    };
+                } else {
+                    *state.write() = IndexState::Idle;
+                }
+            }
+            Err(FlashFindError::Cancelled) => {
+                // Superseded by a rebuild, which is already queued
+                *state.write() = IndexState::Idle;
+            }
+            Err(e) => {
+                error!("Scan failed: {}", e);
+                *state.write() = IndexState::Error {
+                    message: e.user_message(),
+                };
+            }
         }
+        
+        pending.fetch_sub(1, Ordering::SeqCst);
     }
     
     info!("Indexer thread stopped");
 }
 
 /// Scan directories and add files to index
+///
+/// Files added to an index that already held some are counted in
+/// `activity`; the first build of an index is not activity.
 fn scan_directories(
     directories: Vec<PathBuf>,
-    index: &Arc<RwLock<FileIndex>>,
+    index: &Arc<ShardedIndex>,
+    exclusions: &Exclusions,
+    activity: &ActivityLog,
     state: &Arc<RwLock<IndexState>>,
     cancel_flag: &Arc<AtomicBool>,
 ) -> Result<IndexResult> {
     let start_time = Instant::now();
     let mut total_added = 0;
+    let mut report = ScanReport::default();
+    let record = activity.is_enabled() && !index.read().is_empty();
     
     for dir in directories {
         if cancel_flag.load(Ordering::Relaxed) {
@@ -187,19 +560,17 @@ fn scan_directories(
             return Err(FlashFindError::Cancelled);
         }
         
-        debug!("Scanning directory: {}", dir.display());
+        if !to_long_path(&dir).is_dir() {
+            info!("Skipping unavailable directory: {}", redact(&dir));
+            continue;
+        }
+        
+        debug!("Scanning directory: {}", redact(&dir));
         
         // Collect all file paths without holding lock
-        let entries: Vec<PathBuf> = WalkDir::new(&dir)
-            .follow_links(false)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-            .filter(|e| !is_excluded(e.path()))
-            .map(|e| e.into_path())
-            .collect();
+        let entries = walk_files(&dir, exclusions, &mut report);
         
-        debug!("Found {} files in {}", entries.len(), dir.display());
+        debug!("Found {} files in {}", entries.len(), redact(&dir));
         
         // Batch insert with periodic lock releases
         const BATCH_SIZE: usize = 1000;
@@ -209,59 +580,456 @@ fn scan_directories(
                 return Err(FlashFindError::Cancelled);
             }
             
-            let mut lock = index.write();
-            
-            for path in chunk {
-                match lock.insert(path.clone()) {
-                    Ok(true) => total_added += 1,
-                    Ok(false) => {}, // Duplicate
-                    Err(e) => {
-                        if !e.is_recoverable() {
-                            return Err(e);
-                        }
-                        // Log but continue on recoverable errors
-                        warn!("Failed to insert {}: {}", path.display(), e);
-                    }
-                }
-            }
+            // Keys are worked out before taking any lock; each shard is
+            // then locked only while its part goes in
+            let batch = PreparedBatch::new(chunk.to_vec());
+            let (inserted, added) = index.insert_batch(batch, record)?;
+            total_added += inserted.added;
             
             // Update progress
             *state.write() = IndexState::Scanning {
-                progress: lock.len(),
+                progress: index.read().len(),
             };
-            
-            // Explicit drop to release lock between batches
-            drop(lock);
+            activity.record_added(&added);
         }
     }
+    // Searches see the batches as they are published; the last ones now
+    index.publish();
     
     let duration_ms = start_time.elapsed().as_millis() as u64;
     
+    if !report.denied.is_empty() {
+        warn!("{} directories skipped: permission denied", report.denied.len());
+    }
+    if !report.corrupt_archives.is_empty() {
+        warn!("{} archives skipped: unreadable", report.corrupt_archives.len());
+    }
+    
     Ok(IndexResult {
         files_added: total_added,
         duration_ms,
+        report,
     })
 }
 
+/// Walk `dir` and return the files to index with their metadata, recording
+/// directories that could not be read because access was denied
+///
+/// Walks the extended-length form so deep trees beyond MAX_PATH are
+/// reachable, but returns the normal form for the index. When archive
+/// indexing is enabled, zip members follow their archive as virtual paths
+/// (with `EntryMeta::UNKNOWN`). Folders below `dir` are included when
+/// `Exclusions::indexes_directories` is set.
+pub fn walk_files(dir: &Path, exclusions: &Exclusions, report: &mut ScanReport) -> Vec<(PathBuf, EntryMeta)> {
+    let mut files = Vec::new();
+    
+    for entry in WalkDir::new(to_long_path(dir)).follow_links(false) {
+        match entry {
+            Ok(entry) if entry.file_type().is_dir() && entry.depth() > 0 && exclusions.indexes_directories() => {
+                let path = from_long_path(entry.path());
+                if !exclusions.is_excluded(&path) {
+                    let meta = entry.metadata().map_or(EntryMeta::UNKNOWN, |m| EntryMeta::from_metadata(&m));
+                    files.push((path, EntryMeta { is_dir: true, ..meta }));
+                }
+            }
+            Ok(entry) if entry.file_type().is_file() => {
+                let path = from_long_path(entry.path());
+                if exclusions.excludes(&path, false) {
+                    continue;
+                }
+                
+                if let Some(limit) = exclusions.archive_limit().filter(|_| is_archive(&path)) {
+                    match list_members(&path, limit) {
+                        Ok(members) => files.extend(
+                            members
+                                .into_iter()
+                                .filter(|m| !exclusions.excludes(m, false))
+                                .map(|m| (m, EntryMeta::UNKNOWN)),
+                        ),
+                        Err(e) => {
+                            warn!("Skipping archive {}: {}", redact(&path), e);
+                            report.corrupt_archives.push(path.clone());
+                        }
+                    }
+                }
+                // Read from the directory listing, so no extra call per file on Windows
+                let meta = entry.metadata().map_or(EntryMeta::UNKNOWN, |m| EntryMeta::from_metadata(&m));
+                files.push((path, meta));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                let permission_denied = e
+                    .io_error()
+                    .is_some_and(|io| io.kind() == ErrorKind::PermissionDenied);
+                if let Some(path) = e.path().filter(|_| permission_denied) {
+                    let path = from_long_path(path);
+                    if !exclusions.is_excluded(&path) {
+                        debug!("Permission denied: {}", redact(&path));
+                        report.denied.push(path);
+                    }
+                }
+            }
+        }
+    }
+    
+    files
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::index::{SearchStop, SortOrder};
+    use crate::sharded::ShardedCache;
+    use crate::test_dir::TestDir;
 
     #[test]
     fn test_indexer_creation() {
-        let index = Arc::new(RwLock::new(FileIndex::new()));
-        let indexer = Indexer::new(index);
+        let index = Arc::new(ShardedIndex::new());
+        let indexer = Indexer::new(
+            index.clone(),
+            Arc::new(RwLock::new(Exclusions::default())),
+            Arc::new(SaveCoordinator::new(index)),
+            Arc::new(ActivityLog::default()),
+        );
         assert!(indexer.is_ok());
     }
 
     #[test]
     fn test_state_transitions() {
-        let index = Arc::new(RwLock::new(FileIndex::new()));
-        let indexer = Indexer::new(index).unwrap();
+        let index = Arc::new(ShardedIndex::new());
+        let indexer = Indexer::new(
+            index.clone(),
+            Arc::new(RwLock::new(Exclusions::default())),
+            Arc::new(SaveCoordinator::new(index)),
+            Arc::new(ActivityLog::default()),
+        ).unwrap();
         
         match indexer.state() {
             IndexState::Idle => {},
             _ => panic!("Should start in Idle state"),
         }
     }
+
+    fn wait_until_idle(indexer: &Indexer) {
+        let deadline = Instant::now() + std::time::Duration::from_secs(30);
+        while indexer.is_running() {
+            assert!(Instant::now() < deadline, "indexer did not finish");
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_rapid_rebuilds_match_single_clean_scan() {
+        let base = TestDir::new("rebuild");
+        let (kept, dropped) = (base.join("kept"), base.join("dropped"));
+        std::fs::create_dir_all(&kept).unwrap();
+        std::fs::create_dir_all(&dropped).unwrap();
+        for i in 0..200 {
+            std::fs::write(kept.join(format!("rebuild_file_{}.txt", i)), b"x").unwrap();
+            std::fs::write(dropped.join(format!("rebuild_other_{}.txt", i)), b"x").unwrap();
+        }
+
+        let index = Arc::new(ShardedIndex::new());
+        index.insert(PathBuf::from("/stale/rebuild_stale.txt")).unwrap();
+        let saver = Arc::new(SaveCoordinator::with_path(index.clone(), base.join("index.bin")));
+        let indexer = Indexer::new(index.clone(), Arc::new(RwLock::new(Exclusions::default())), saver, Arc::new(ActivityLog::default())).unwrap();
+
+        indexer.start_scan(vec![dropped.clone()]).unwrap();
+        for _ in 0..5 {
+            indexer.rebuild(vec![dropped.clone(), kept.clone()]).unwrap();
+        }
+        indexer.rebuild(vec![kept.clone()]).unwrap();
+        wait_until_idle(&indexer);
+
+        let expected = Arc::new(ShardedIndex::new());
+        let state = Arc::new(RwLock::new(IndexState::Idle));
+        let cancel = Arc::new(AtomicBool::new(false));
+        scan_directories(vec![kept.clone()], &expected, &Exclusions::default(), &ActivityLog::default(), &state, &cancel).unwrap();
+
+        let mut actual = index.search("rebuild_");
+        let mut wanted = expected.search("rebuild_");
+        actual.sort();
+        wanted.sort();
+        assert_eq!(actual.len(), 200);
+        assert_eq!(actual, wanted);
+        assert_eq!(index.read().len(), expected.read().len());
+    }
+
+    #[test]
+    fn test_searches_stay_responsive_during_scan() {
+        let base = TestDir::new("stress");
+        for folder in 0..10 {
+            let dir = base.join(format!("folder_{}", folder));
+            std::fs::create_dir_all(&dir).unwrap();
+            for i in 0..500 {
+                std::fs::write(dir.join(format!("stress_{}_{}.txt", folder, i)), b"x").unwrap();
+            }
+        }
+
+        let index = Arc::new(ShardedIndex::new());
+        let scanning = Arc::new(AtomicBool::new(true));
+        let searchers: Vec<_> = (0..4)
+            .map(|_| {
+                let (index, scanning) = (index.clone(), scanning.clone());
+                thread::spawn(move || {
+                    let (mut seen, mut slowest, mut searches) = (0, std::time::Duration::ZERO, 0);
+                    let (mut cache, cancel) = (ShardedCache::default(), AtomicBool::new(false));
+                    while scanning.load(Ordering::Relaxed) {
+                        let started = Instant::now();
+                        let stop = SearchStop::new(&cancel, None);
+                        let results = index.search_refining("stress_", usize::MAX, SortOrder::Name, &mut cache, &stop).unwrap().paths;
+                        slowest = slowest.max(started.elapsed());
+                        searches += 1;
+                        // Each snapshot holds all the last one did, so a search
+                        // never sees fewer files than before
+                        assert!(results.len() >= seen, "results went from {} to {}", seen, results.len());
+                        assert!(results.iter().all(|path| path.to_string_lossy().contains("stress_")));
+                        seen = results.len();
+                    }
+                    (slowest, searches)
+                })
+            })
+            .collect();
+
+        let state = Arc::new(RwLock::new(IndexState::Idle));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let scanned = scan_directories(vec![base.to_path_buf()], &index, &Exclusions::default(), &ActivityLog::default(), &state, &cancel).unwrap();
+        scanning.store(false, Ordering::Relaxed);
+
+        for searcher in searchers {
+            let (slowest, searches) = searcher.join().unwrap();
+            assert!(searches > 0);
+            assert!(slowest < std::time::Duration::from_secs(2), "a search took {:?}", slowest);
+        }
+        assert_eq!(scanned.files_added, 5000);
+        assert_eq!(index.search("stress_").len(), 5000);
+    }
+
+    #[test]
+    fn test_scans_count_new_files_as_activity() {
+        use crate::activity::week_start;
+        use crate::dates::today;
+
+        let base = TestDir::new("scan_activity");
+        std::fs::write(base.join("first.txt"), b"x").unwrap();
+
+        let index = Arc::new(ShardedIndex::new());
+        let activity = ActivityLog::default();
+        activity.set_enabled(true);
+        let state = Arc::new(RwLock::new(IndexState::Idle));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let scan = || scan_directories(vec![base.to_path_buf()], &index, &Exclusions::default(), &activity, &state, &cancel).unwrap();
+
+        // Building the index is not activity
+        scan();
+        assert!(activity.week(week_start(today())).is_empty());
+
+        std::fs::write(base.join("second.txt"), b"four").unwrap();
+        scan();
+        let week = activity.week(week_start(today()));
+        assert_eq!((week.added, week.removed), (1, 0));
+        assert_eq!(week.biggest, [(base.join("second.txt"), 4)]);
+    }
+
+    #[test]
+    fn test_changing_drives_only_touches_changed_drives() {
+        let added = TestDir::new("drives");
+        std::fs::write(added.join("drive_added.txt"), b"x").unwrap();
+        
+        let index = Arc::new(ShardedIndex::new());
+        for path in ["C:\\Users\\me\\drive_kept.txt", "D:\\Media\\drive_dropped.txt"] {
+            index.insert(PathBuf::from(path)).unwrap();
+        }
+        let saver = Arc::new(SaveCoordinator::with_path(index.clone(), added.join("index.bin")));
+        let indexer = Indexer::new(index.clone(), Arc::new(RwLock::new(Exclusions::default())), saver, Arc::new(ActivityLog::default())).unwrap();
+        
+        indexer.change_drives(vec!['D'], vec![added.to_path_buf()]).unwrap();
+        wait_until_idle(&indexer);
+        
+        let mut found = index.search("drive_");
+        found.sort();
+        assert_eq!(found, [added.join("drive_added.txt"), PathBuf::from("C:\\Users\\me\\drive_kept.txt")]);
+        assert!(added.join("index.bin").exists());
+    }
+    
+    #[test]
+    fn test_fully_pruned_index_is_scanned_again() {
+        let root = TestDir::new("repopulate");
+        std::fs::write(root.join("repopulate_new.txt"), b"x").unwrap();
+        
+        let index = Arc::new(ShardedIndex::new());
+        index.insert(root.join("repopulate_gone.txt")).unwrap();
+        index.publish();
+        let saver = Arc::new(SaveCoordinator::with_path(index.clone(), root.join("index.bin")));
+        let indexer = Indexer::new(index.clone(), Arc::new(RwLock::new(Exclusions::default())), saver, Arc::new(ActivityLog::default())).unwrap();
+        
+        indexer.prune(vec![root.to_path_buf()]).unwrap();
+        wait_until_idle(&indexer);
+        
+        assert_eq!(indexer.last_prune().map(|report| report.pruned), Some(1));
+        assert_eq!(index.search("repopulate_"), vec![root.join("repopulate_new.txt")]);
+        assert_eq!(index.read().len(), 1);
+    }
+    
+    #[test]
+    fn test_compaction_waits_for_threshold_unless_forced() {
+        let base = TestDir::new("compact");
+        let index = Arc::new(ShardedIndex::new());
+        for i in 0..10 {
+            index.insert(PathBuf::from(format!("/docs/notes_{}.txt", i))).unwrap();
+        }
+        index.remove(Path::new("/docs/notes_0.txt")).unwrap();
+        index.publish();
+        let saver = Arc::new(SaveCoordinator::with_path(index.clone(), base.join("index.bin")));
+        let indexer = Indexer::new(index.clone(), Arc::new(RwLock::new(Exclusions::default())), saver, Arc::new(ActivityLog::default())).unwrap();
+        
+        // One removed entry in ten is under the threshold
+        indexer.compact(false).unwrap();
+        wait_until_idle(&indexer);
+        assert_eq!(index.read().tombstones(), 1);
+        assert_eq!(indexer.compactions().runs, 0);
+        
+        index.remove(Path::new("/docs/notes_1.txt")).unwrap();
+        index.publish();
+        indexer.compact(false).unwrap();
+        wait_until_idle(&indexer);
+        assert_eq!(index.read().tombstones(), 0);
+        assert_eq!(index.read().len(), 8);
+        
+        index.remove(Path::new("/docs/notes_2.txt")).unwrap();
+        indexer.compact(true).unwrap();
+        wait_until_idle(&indexer);
+        let report = indexer.compactions();
+        assert_eq!((report.runs, report.reclaimed), (2, 3));
+        assert_eq!(report.last.map(|(_, reclaimed)| reclaimed), Some(1));
+        assert_eq!(index.search("notes_").len(), 7);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_long_path_indexing() {
+        let root = TestDir::new("long_path");
+        let mut dir = root.to_path_buf();
+        while dir.to_string_lossy().len() < 300 {
+            dir.push("deeply_nested_directory_segment");
+        }
+        let file = dir.join("long_path_report.txt");
+        std::fs::create_dir_all(to_long_path(&dir)).unwrap();
+        std::fs::write(to_long_path(&file), b"hello").unwrap();
+        assert!(file.to_string_lossy().len() > 300);
+
+        let index = Arc::new(ShardedIndex::new());
+        let state = Arc::new(RwLock::new(IndexState::Idle));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let result = scan_directories(vec![root.to_path_buf()], &index, &Exclusions::default(), &ActivityLog::default(), &state, &cancel).unwrap();
+        assert_eq!(result.files_added, 1);
+
+        // Stored and searchable in the normal (display) form
+        let results = index.search("long_path_report");
+        assert_eq!(results, vec![file.clone()]);
+        assert!(!results[0].to_string_lossy().starts_with("\\\\?\\"));
+        assert_eq!(std::fs::metadata(to_long_path(&results[0])).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_scan_indexes_archive_members_and_reports_corrupt() {
+        use crate::archive::member_path;
+        use crate::config::Config;
+        use std::io::Write;
+        
+        let root = TestDir::new("archive_scan");
+        
+        let archive = root.join("photos.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&archive).unwrap());
+        zip.start_file("IMG_001.jpg", zip::write::SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"jpeg").unwrap();
+        zip.finish().unwrap();
+        let broken = root.join("broken.zip");
+        std::fs::write(&broken, b"not a zip").unwrap();
+        
+        let config = Config { index_archives: true, ..Config::default() };
+        let mut report = ScanReport::default();
+        let files: Vec<PathBuf> = walk_files(&root, &Exclusions::from_config(&config), &mut report)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert!(files.contains(&member_path(&archive, "IMG_001.jpg")));
+        assert!(files.contains(&archive) && files.contains(&broken));
+        assert_eq!(report.corrupt_archives, vec![broken.clone()]);
+        
+        // Off by default: archives are indexed as plain files only
+        let mut report = ScanReport::default();
+        let files = walk_files(&root, &Exclusions::default(), &mut report);
+        assert_eq!(files.len(), 2);
+        assert!(report.corrupt_archives.is_empty());
+    }
+
+    #[test]
+    fn test_scan_indexes_folders_only_when_enabled() {
+        use crate::config::Config;
+        
+        let root = TestDir::new("folder_scan");
+        let folder = root.join("Invoices");
+        std::fs::create_dir_all(&folder).unwrap();
+        std::fs::write(folder.join("may.pdf"), b"pdf").unwrap();
+        
+        let config = Config { index_directories: true, ..Config::default() };
+        let mut report = ScanReport::default();
+        let mut found = walk_files(&root, &Exclusions::from_config(&config), &mut report);
+        found.sort_by(|a, b| a.0.cmp(&b.0));
+        // The scanned root itself is not an entry
+        let flags: Vec<(PathBuf, bool)> = found.into_iter().map(|(path, meta)| (path, meta.is_dir)).collect();
+        assert_eq!(flags, vec![(folder.clone(), true), (folder.join("may.pdf"), false)]);
+        
+        let mut report = ScanReport::default();
+        let found = walk_files(&root, &Exclusions::default(), &mut report);
+        assert_eq!(found.len(), 1);
+    }
+
+    #[test]
+    fn test_include_filter_limits_files_under_its_folder() {
+        use crate::config::{Config, IncludeFilter};
+        
+        let root = TestDir::new("include_scan");
+        let media = root.join("Media");
+        let shows = media.join("Shows");
+        std::fs::create_dir_all(&shows).unwrap();
+        for file in [media.join("movie.MKV"), media.join("movie.srt"), media.join("thumb.jpg"), shows.join("ep1.mp4"), shows.join("ep1.nfo")] {
+            std::fs::write(file, b"data").unwrap();
+        }
+        std::fs::write(root.join("notes.txt"), b"text").unwrap();
+        
+        // A kind name, a wildcard and a plain extension under Media; the
+        // rest of the tree is indexed as before
+        let config = Config {
+            index_directories: true,
+            include_filters: vec![IncludeFilter::parse(media.clone(), "videos, *.srt").unwrap()],
+            ..Config::default()
+        };
+        let mut report = ScanReport::default();
+        let mut found: Vec<PathBuf> = walk_files(&root, &Exclusions::from_config(&config), &mut report)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        found.sort();
+        let mut expected = vec![media.clone(), media.join("movie.MKV"), media.join("movie.srt"), shows.clone(), shows.join("ep1.mp4"), root.join("notes.txt")];
+        expected.sort();
+        assert_eq!(found, expected);
+        
+        // A narrower filter on a subfolder takes over from its parent's
+        let config = Config {
+            include_filters: vec![
+                IncludeFilter::parse(media.clone(), "videos").unwrap(),
+                IncludeFilter::parse(shows.clone(), ".nfo").unwrap(),
+            ],
+            ..Config::default()
+        };
+        let mut report = ScanReport::default();
+        let mut found: Vec<PathBuf> = walk_files(&shows, &Exclusions::from_config(&config), &mut report)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        found.sort();
+        assert_eq!(found, vec![shows.join("ep1.nfo")]);
+    }
 }
@@ -0,0 +1,143 @@
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+use crate::fold::fold;
+
+/// A named set of extensions, behind both `kind:` queries and the file
+/// type filter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KindGroup {
+    /// Lowercase name, used in `kind:` queries and saved searches
+    pub name: String,
+    /// Shown before the name in the filter dropdown
+    #[serde(default)]
+    pub icon: String,
+    /// Lowercase extensions without the leading dot
+    pub extensions: Vec<String>,
+}
+
+impl KindGroup {
+    fn new(name: &str, icon: &str, extensions: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            icon: icon.to_string(),
+            extensions: extensions.iter().map(|ext| ext.to_string()).collect(),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        kind_label(&self.name)
+    }
+
+    /// Whether `kind` names this group; the plural's `s` may be left off,
+    /// so `kind:image` finds "images"
+    pub fn is_named(&self, kind: &str) -> bool {
+        let kind = fold(kind);
+        kind == self.name || self.name.strip_suffix('s') == Some(kind.as_str())
+    }
+
+    /// Whether a file named `file_name` has one of the group's extensions,
+    /// compound ones like `tar.gz` included
+    pub fn matches(&self, file_name: &str) -> bool {
+        let name = fold(file_name);
+        self.extensions.iter().any(|ext| has_extension(&name, ext))
+    }
+
+    /// Tidy a group edited in Settings: fold the name and extensions, drop
+    /// leading dots, blanks and repeats
+    pub fn normalize(&mut self) {
+        self.name = fold(self.name.trim());
+        let mut extensions: Vec<String> = Vec::new();
+        for ext in &self.extensions {
+            let ext = fold(ext.trim().trim_start_matches('.'));
+            if !ext.is_empty() && !extensions.contains(&ext) {
+                extensions.push(ext);
+            }
+        }
+        self.extensions = extensions;
+    }
+}
+
+/// Whether the folded filename `name` ends in `.ext`, with something
+/// before the dot; `ext` is folded and may be compound like `tar.gz`
+pub fn has_extension(name: &str, ext: &str) -> bool {
+    name.len() > ext.len() + 1 && name.ends_with(ext) && name[..name.len() - ext.len()].ends_with('.')
+}
+
+/// The groups the file type filter offered before they were configurable
+pub fn default_kind_groups() -> Vec<KindGroup> {
+    vec![
+        KindGroup::new("documents", "📄", &["pdf", "doc", "docx", "txt", "rtf", "odt", "md"]),
+        KindGroup::new("images", "🖼️", &["jpg", "jpeg", "png", "gif", "bmp", "svg", "webp", "ico"]),
+        KindGroup::new("videos", "🎥", &["mp4", "avi", "mkv", "mov", "wmv", "flv", "webm"]),
+        KindGroup::new("audio", "🎵", &["mp3", "wav", "flac", "aac", "ogg", "m4a", "wma"]),
+        KindGroup::new(
+            "code",
+            "💻",
+            &[
+                "rs", "py", "js", "ts", "java", "c", "cpp", "h", "cs", "go", "rb", "php", "html", "css", "json", "xml",
+                "yaml", "toml",
+            ],
+        ),
+        KindGroup::new("archives", "📦", &["zip", "rar", "7z", "tar", "gz", "bz2", "xz"]),
+    ]
+}
+
+/// Groups `kind:` queries resolve against, from `Config::kind_groups`
+static KIND_GROUPS: LazyLock<RwLock<Vec<KindGroup>>> = LazyLock::new(|| RwLock::new(default_kind_groups()));
+
+/// Replace the groups `kind:` queries resolve against
+pub fn set_kind_groups(groups: &[KindGroup]) {
+    *KIND_GROUPS.write() = groups.to_vec();
+}
+
+/// The configured group `kind` names, if any
+pub fn find_kind(kind: &str) -> Option<KindGroup> {
+    find_kind_in(&KIND_GROUPS.read(), kind).cloned()
+}
+
+/// The group in `groups` that `kind` names, if any
+pub fn find_kind_in<'a>(groups: &'a [KindGroup], kind: &str) -> Option<&'a KindGroup> {
+    groups.iter().find(|group| group.is_named(kind))
+}
+
+/// A kind's name with a capital first letter, as the filter dropdown shows it
+pub fn kind_label(name: &str) -> String {
+    let mut chars = name.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+/// Names of the configured groups, for hints and errors
+pub fn kind_names() -> Vec<String> {
+    KIND_GROUPS.read().iter().map(|group| group.name.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kinds_are_found_by_name_or_singular() {
+        let groups = default_kind_groups();
+        let name = |kind: &str| find_kind_in(&groups, kind).map(|group| group.name.as_str());
+        assert_eq!(name("images"), Some("images"));
+        assert_eq!(name("Image"), Some("images"));
+        assert_eq!(name("VIDEO"), Some("videos"));
+        assert_eq!(name("audio"), Some("audio"));
+        assert_eq!(name("imag"), None);
+        assert_eq!(name(""), None);
+        assert_eq!(find_kind_in(&groups, "image").unwrap().label(), "Images");
+        assert!(groups[1].matches("Holiday.PNG") && !groups[1].matches("report.pdf"));
+        assert!(!groups[1].matches("png") && !groups[1].matches(".png") && !groups[1].matches("screenshot_png"));
+        assert!(KindGroup::new("backups", "", &["tar.gz"]).matches("site.tar.gz"));
+    }
+
+    #[test]
+    fn test_normalize_tidies_edited_groups() {
+        let mut group = KindGroup::new(" Scans ", "", &[".TIFF", "tif", " ", "tiff", ".tar.gz"]);
+        group.normalize();
+        assert_eq!(group.name, "scans");
+        assert_eq!(group.extensions, ["tiff", "tif", "tar.gz"]);
+    }
+}
@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::filter::EnvFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, Registry};
+
+use crate::error::{FlashFindError, Result};
+
+/// Verbosity of the log file, adjustable at runtime from the Status tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 5] = [
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Trace,
+    ];
+
+    /// Debug builds log more by default than release builds
+    pub fn default_for_build() -> Self {
+        if cfg!(debug_assertions) {
+            LogLevel::Debug
+        } else {
+            LogLevel::Info
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LogLevel::Error => "Error",
+            LogLevel::Warn => "Warn",
+            LogLevel::Info => "Info",
+            LogLevel::Debug => "Debug",
+            LogLevel::Trace => "Trace",
+        }
+    }
+
+    fn directive(self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+}
+
+/// Build the filter for a global level plus optional per-module directives
+///
+/// `module_filter` uses `EnvFilter` syntax, comma-separated
+/// (`flashfind::watcher=debug,flashfind::index=trace`).
+pub fn build_filter(level: LogLevel, module_filter: &str) -> Result<EnvFilter> {
+    let mut directives = level.directive().to_string();
+    for directive in module_filter.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+        directives.push(',');
+        directives.push_str(directive);
+    }
+
+    EnvFilter::builder()
+        .parse(&directives)
+        .map_err(|e| FlashFindError::InvalidConfig(format!("log filter: {}", e)))
+}
+
+/// The installed log subscriber: its reloadable filter and file writer guard
+///
+/// Keep it alive for the whole session; dropping it flushes buffered log
+/// lines to disk.
+pub struct LogControl {
+    handle: Option<reload::Handle<EnvFilter, Registry>>,
+    _guard: Option<WorkerGuard>,
+}
+
+impl LogControl {
+    /// Install the global subscriber, logging to the daily log file
+    ///
+    /// Without a writable log folder, warnings go to the console instead.
+    pub fn init(level: LogLevel, module_filter: &str) -> Self {
+        let filter = build_filter(level, module_filter).unwrap_or_else(|e| {
+            eprintln!("{}, using the {} level only", e, level.label());
+            EnvFilter::new(level.directive())
+        });
+
+        let log_path = match crate::persistence::get_log_path() {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("Failed to get log path ({}), logging warnings to the console", e);
+                let (filter, handle) = reload::Layer::new(EnvFilter::new("warn"));
+                let installed = tracing_subscriber::registry()
+                    .with(filter)
+                    .with(fmt::layer().with_writer(std::io::stderr))
+                    .try_init()
+                    .is_ok();
+                return Self { handle: installed.then_some(handle), _guard: None };
+            }
+        };
+
+        let file_appender = tracing_appender::rolling::daily(
+            log_path.parent().unwrap_or(Path::new(".")),
+            "flashfind.log",
+        );
+        let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
+
+        let (filter, handle) = reload::Layer::new(filter);
+        let installed = tracing_subscriber::registry()
+            .with(filter)
+            .with(fmt::layer().with_writer(non_blocking).with_ansi(false))
+            .try_init()
+            .is_ok();
+
+        Self { handle: installed.then_some(handle), _guard: Some(guard) }
+    }
+
+    /// Swap in a new level and module filter without restarting
+    pub fn apply(&self, level: LogLevel, module_filter: &str) -> Result<()> {
+        let filter = build_filter(level, module_filter)?;
+        if let Some(handle) = &self.handle {
+            handle
+                .reload(filter)
+                .map_err(|e| FlashFindError::InvalidConfig(format!("cannot reload log filter: {}", e)))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_filters_are_validated() {
+        assert!(build_filter(LogLevel::Info, "").is_ok());
+        assert!(build_filter(LogLevel::Warn, "flashfind::watcher=debug").is_ok());
+        assert!(build_filter(LogLevel::Warn, " flashfind::watcher=debug , flashfind::index=trace ,").is_ok());
+        assert!(build_filter(LogLevel::Info, "flashfind::watcher=loud").is_err());
+        assert!(build_filter(LogLevel::Info, "flashfind::watcher=debug=trace").is_err());
+    }
+
+    #[test]
+    fn test_level_directives_cover_every_level() {
+        for level in LogLevel::ALL {
+            let filter = build_filter(level, "").unwrap();
+            assert_eq!(filter.to_string(), level.directive());
+        }
+    }
+}
@@ -8,19 +8,73 @@
 //! - Sub-millisecond search response times
 //! - Production-grade error handling and logging
 
+mod activity;
+mod alerts;
 mod app;
+mod archive;
+mod changes;
 mod config;
+mod dates;
+mod dedup;
+mod duplicates;
 mod error;
+mod export;
+mod fold;
+mod grep;
+mod health;
+mod history;
 mod index;
 mod indexer;
+mod kinds;
+mod logging;
+mod path_pool;
+mod paths;
 mod persistence;
+mod platform;
+mod privacy;
+mod query;
+mod query_cache;
+mod ranking;
+mod rename;
+mod redact;
+mod roots;
+mod search;
+mod shard;
+mod sharded;
+mod shortcut;
+mod size;
+mod snapshot;
+mod suggest;
+#[cfg(test)]
+mod test_dir;
+#[cfg(test)]
+mod test_log;
+mod trigram;
+mod usage;
 mod watcher;
+mod words;
 
 use app::FlashFindApp;
 use eframe::egui;
 use tracing::info;
 
 fn main() -> eframe::Result<()> {
+    // Elevated helper mode: scan the denied folders, write a shard, exit
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(helper) = shard::helper_args(&args) {
+        let config = config::Config::load().unwrap_or_default();
+        let exclusions = watcher::Exclusions::from_config(&config);
+        let scanned = helper.and_then(|(shard_path, roots)| shard::run_helper(&shard_path, &roots, &exclusions));
+        let code = match scanned {
+            Ok(()) => 0,
+            Err(_) => 1,
+        };
+        std::process::exit(code);
+    }
+    
+    // Desktop shortcuts and flashfind:// links start with a search
+    let launch = shortcut::LaunchSearch::from_args(&args);
+    
     info!("FlashFind v1.0.0-phase1 starting");
     
     let options = eframe::NativeOptions {
@@ -33,6 +87,6 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "FlashFind",
         options,
-        Box::new(|cc| Box::new(FlashFindApp::new(cc))),
+        Box::new(move |cc| Box::new(FlashFindApp::new(cc, launch))),
     )
 }
\ No newline at end of file
@@ -0,0 +1,318 @@
+use ahash::AHashMap;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::paths::path_key;
+
+/// Indexed paths, each stored as the id of its folder and its filename
+///
+/// Deep trees repeat the same long folder paths for thousands of files, so
+/// each folder is stored once and a slot's path is rebuilt when asked for.
+/// Paths must be valid Unicode, as the saved index always needed them to be.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PathPool {
+    /// Every distinct folder as its paths spell it, trailing separator included
+    folders: Vec<Box<str>>,
+    /// Each slot's folder and the rest of its path
+    entries: Vec<(u32, Box<str>)>,
+    /// Id of each folder in `folders`, rebuilt after loading
+    #[serde(skip)]
+    folder_ids: AHashMap<Box<str>, u32>,
+}
+
+impl PathPool {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Append a slot for `path`; false (and nothing stored) if the path is
+    /// not valid Unicode
+    pub fn push(&mut self, path: &Path) -> bool {
+        let Some(text) = path.to_str() else {
+            return false;
+        };
+        let (folder, name) = split(text);
+        let id = self.folder_id(folder);
+        self.entries.push((id, name.into()));
+        true
+    }
+
+    /// Point slot `idx` at `path`; false (and the slot unchanged) if the
+    /// path is not valid Unicode
+    ///
+    /// A folder no slot uses any more stays stored until compaction builds
+    /// a new pool.
+    pub fn set(&mut self, idx: usize, path: &Path) -> bool {
+        let Some(text) = path.to_str() else {
+            return false;
+        };
+        let (folder, name) = split(text);
+        let id = self.folder_id(folder);
+        self.entries[idx] = (id, name.into());
+        true
+    }
+
+    /// Id of `folder`, stored now if it is new
+    fn folder_id(&mut self, folder: &str) -> u32 {
+        if let Some(&id) = self.folder_ids.get(folder) {
+            return id;
+        }
+        let id = self.folders.len() as u32;
+        self.folders.push(folder.into());
+        self.folder_ids.insert(folder.into(), id);
+        id
+    }
+
+    /// The path in slot `idx`; panics past the end, like indexing a `Vec`
+    pub fn path(&self, idx: usize) -> PathBuf {
+        let (folder, name) = &self.entries[idx];
+        let folder = &self.folders[*folder as usize];
+        let mut text = String::with_capacity(folder.len() + name.len());
+        text.push_str(folder);
+        text.push_str(name);
+        PathBuf::from(text)
+    }
+
+    /// The filename in slot `idx`, empty for a drive root; panics past the end
+    pub fn name(&self, idx: usize) -> &str {
+        &self.entries[idx].1
+    }
+
+    /// The path in slot `idx`, if there is such a slot
+    pub fn get(&self, idx: usize) -> Option<PathBuf> {
+        (idx < self.entries.len()).then(|| self.path(idx))
+    }
+
+    /// Every slot's path, in slot order
+    pub fn iter(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        (0..self.entries.len()).map(|idx| self.path(idx))
+    }
+
+    pub fn clear(&mut self) {
+        self.folders.clear();
+        self.entries.clear();
+        self.folder_ids.clear();
+    }
+
+    /// A pool holding only `slots`, in that order, and the folders they use
+    pub fn select(&self, slots: &[usize]) -> PathPool {
+        let mut pool = PathPool::default();
+        for &idx in slots {
+            pool.push(&self.path(idx));
+        }
+        pool
+    }
+
+    /// Rebuild the folder lookup (call after deserialization)
+    pub fn rebuild(&mut self) {
+        self.folder_ids = self.folders.iter().enumerate().map(|(id, folder)| (folder.clone(), id as u32)).collect();
+    }
+
+    /// Whether every slot names a stored folder; only a damaged or crafted
+    /// index file breaks this
+    pub fn is_valid(&self) -> bool {
+        self.entries.iter().all(|(folder, _)| (*folder as usize) < self.folders.len())
+    }
+
+    /// Approximate bytes on the heap, allocated capacity included
+    pub fn heap_bytes(&self) -> usize {
+        let folders: usize = self.folders.iter().map(|folder| folder.len()).sum();
+        let names: usize = self.entries.iter().map(|(_, name)| name.len()).sum();
+        self.folders.capacity() * std::mem::size_of::<Box<str>>()
+            + self.entries.capacity() * std::mem::size_of::<(u32, Box<str>)>()
+            + self.folder_ids.capacity() * (std::mem::size_of::<(Box<str>, u32)>() + 1)
+            + folders * 2
+            + names
+    }
+}
+
+impl From<Vec<PathBuf>> for PathPool {
+    /// Pools of older index layouts, whose paths were always valid Unicode
+    fn from(paths: Vec<PathBuf>) -> Self {
+        let mut pool = PathPool::default();
+        for path in paths {
+            if !pool.push(&path) {
+                pool.push(Path::new(path.to_string_lossy().as_ref()));
+            }
+        }
+        pool
+    }
+}
+
+/// Split a path's text into its folder, trailing separator included, and
+/// its filename; a path not ending in a filename (a drive root) is all folder
+fn split(text: &str) -> (&str, &str) {
+    let name = Path::new(text)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|name| text.ends_with(name))
+        .unwrap_or("");
+    text.split_at(text.len() - name.len())
+}
+
+/// The `path_key` of a live entry, split like `PathPool` splits paths
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PathKey {
+    folder: u32,
+    name: Box<str>,
+}
+
+/// Slot of each live entry by its `path_key`, so the same file spelled
+/// with different case is found as a duplicate
+///
+/// Folder keys are stored once, as in `PathPool`, instead of one full
+/// lowercased path per entry.
+#[derive(Debug, Clone, Default)]
+pub struct PathKeys {
+    /// Id of each distinct folder key
+    folders: AHashMap<Box<str>, u32>,
+    slots: AHashMap<PathKey, u32>,
+}
+
+impl PathKeys {
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// The key of `path`, if its folder key has been seen
+    pub fn key(&self, path: &Path) -> Option<PathKey> {
+        key_in(&self.folders, path)
+    }
+
+    /// Slot of the live entry at `path`
+    pub fn get(&self, path: &Path) -> Option<u32> {
+        self.get_key(&path_key(path))
+    }
+
+    /// Slot of the live entry whose `path_key` is `key`
+    pub fn get_key(&self, key: &str) -> Option<u32> {
+        let (folder, name) = split_key(key);
+        let folder = *self.folders.get(folder)?;
+        self.slots.get(&PathKey { folder, name: name.into() }).copied()
+    }
+
+    /// Map `path` to `idx` unless it is mapped already; returns the slot it
+    /// maps to
+    pub fn insert(&mut self, path: &Path, idx: u32) -> u32 {
+        self.insert_key(&path_key(path), idx)
+    }
+
+    /// Map the `path_key` `key` to `idx`, as `insert` does
+    pub fn insert_key(&mut self, key: &str, idx: u32) -> u32 {
+        let (folder, name) = split_key(key);
+        let next = self.folders.len() as u32;
+        let folder = *self.folders.entry(folder.into()).or_insert(next);
+        *self.slots.entry(PathKey { folder, name: name.into() }).or_insert(idx)
+    }
+
+    /// Unmap `path`, returning the slot it mapped to
+    pub fn remove(&mut self, path: &Path) -> Option<u32> {
+        let key = self.key(path)?;
+        self.slots.remove(&key)
+    }
+
+    /// Every live slot, in no particular order
+    pub fn slots(&self) -> impl Iterator<Item = u32> + '_ {
+        self.slots.values().copied()
+    }
+
+    /// Every live slot, in no particular order, in parallel
+    pub fn par_slots(&self) -> impl ParallelIterator<Item = u32> + '_ {
+        self.slots.par_iter().map(|(_, &idx)| idx)
+    }
+
+    /// Every key with its slot
+    pub fn iter(&self) -> impl Iterator<Item = (&PathKey, u32)> + '_ {
+        self.slots.iter().map(|(key, &idx)| (key, idx))
+    }
+
+    /// Keep only the slots `keep` accepts
+    pub fn retain<F: FnMut(u32) -> bool>(&mut self, mut keep: F) {
+        self.slots.retain(|_, idx| keep(*idx));
+    }
+
+    /// Keep only keys whose slot still holds a path with that key
+    pub fn retain_consistent<F: Fn(u32) -> Option<PathBuf>>(&mut self, path_at: F) {
+        let folders = &self.folders;
+        self.slots.retain(|key, idx| path_at(*idx).is_some_and(|path| key_in(folders, &path).as_ref() == Some(key)));
+    }
+
+    /// Drop every key; folder keys are dropped too, as nothing refers to them
+    pub fn clear(&mut self) {
+        self.folders.clear();
+        self.slots.clear();
+    }
+
+    /// Approximate bytes on the heap, allocated capacity included
+    pub fn heap_bytes(&self) -> usize {
+        let folders: usize = self.folders.keys().map(|folder| folder.len()).sum();
+        let names: usize = self.slots.keys().map(|key| key.name.len()).sum();
+        self.folders.capacity() * (std::mem::size_of::<(Box<str>, u32)>() + 1)
+            + self.slots.capacity() * (std::mem::size_of::<(PathKey, u32)>() + 1)
+            + folders
+            + names
+    }
+}
+
+fn key_in(folders: &AHashMap<Box<str>, u32>, path: &Path) -> Option<PathKey> {
+    let key = path_key(path);
+    let (folder, name) = split_key(&key);
+    folders.get(folder).map(|&folder| PathKey { folder, name: name.into() })
+}
+
+/// Split a `path_key` after its last separator
+fn split_key(key: &str) -> (&str, &str) {
+    key.split_at(key.rfind(['\\', '/']).map_or(0, |at| at + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths_come_back_as_pushed() {
+        let mut pool = PathPool::default();
+        let paths = ["/data/a/report.pdf", "/data/a/notes.txt", "/data/b/report.pdf", "/", "relative.txt"];
+        for path in paths {
+            assert!(pool.push(Path::new(path)));
+        }
+        let rebuilt: Vec<PathBuf> = pool.iter().collect();
+        assert_eq!(rebuilt, paths.map(PathBuf::from));
+        // The two files in /data/a/ share their folder
+        assert_eq!(pool.folders.len(), 4);
+        assert_eq!(pool.get(5), None);
+
+        let mut loaded: PathPool = bincode::deserialize(&bincode::serialize(&pool).unwrap()).unwrap();
+        loaded.rebuild();
+        assert!(loaded.is_valid());
+        loaded.push(Path::new("/data/b/photo.jpg"));
+        assert_eq!(loaded.folders.len(), 4);
+        assert_eq!((loaded.name(1), loaded.name(3)), ("notes.txt", ""));
+        assert_eq!(loaded.select(&[5, 0]).iter().collect::<Vec<_>>(), [PathBuf::from("/data/b/photo.jpg"), PathBuf::from("/data/a/report.pdf")]);
+        assert!(loaded.set(1, Path::new("/data/b/notes.txt")));
+        assert_eq!(loaded.path(1), PathBuf::from("/data/b/notes.txt"));
+        assert_eq!(loaded.folders.len(), 4);
+    }
+
+    #[test]
+    fn test_keys_find_windows_paths_in_any_case() {
+        let mut keys = PathKeys::default();
+        assert_eq!(keys.insert(Path::new("C:\\Docs\\File.txt"), 0), 0);
+        assert_eq!(keys.insert(Path::new("c:/docs/FILE.TXT"), 1), 0);
+        assert_eq!(keys.insert(Path::new("C:\\Docs\\Other.txt"), 2), 2);
+        assert_eq!(keys.get(Path::new("c:\\DOCS\\file.txt")), Some(0));
+        assert_eq!(keys.get(Path::new("D:\\Docs\\file.txt")), None);
+        assert_eq!(keys.remove(Path::new("C:\\docs\\file.txt")), Some(0));
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys.folders.len(), 1);
+    }
+}
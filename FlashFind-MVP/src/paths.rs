@@ -0,0 +1,294 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::{FlashFindError, Result};
+
+/// Windows MAX_PATH including the terminating NUL
+const MAX_PATH: usize = 260;
+
+/// Directories must leave room for an 8.3 filename, so they hit the limit earlier
+const MAX_DIR_PATH: usize = MAX_PATH - 12;
+
+/// Extended-length prefix for local paths
+const LONG_PREFIX: &str = r"\\?\";
+
+/// Extended-length prefix for UNC paths (`\\server\share` -> `\\?\UNC\server\share`)
+const LONG_UNC_PREFIX: &str = r"\\?\UNC\";
+
+/// Convert a path to its extended-length form when it would exceed MAX_PATH
+///
+/// Use this for every filesystem call (metadata, walking, watching, opening).
+/// Paths stored in the index and shown in the UI stay in their normal form.
+/// On non-Windows platforms the path is returned unchanged.
+pub fn to_long_path(path: &Path) -> PathBuf {
+    if !cfg!(windows) {
+        return path.to_path_buf();
+    }
+
+    match extend_path(&path.to_string_lossy()) {
+        Some(extended) => PathBuf::from(extended),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Strip an extended-length prefix, returning the normal display form
+pub fn from_long_path(path: &Path) -> PathBuf {
+    let path_str = path.to_string_lossy();
+
+    if let Some(unc) = path_str.strip_prefix(LONG_UNC_PREFIX) {
+        PathBuf::from(format!(r"\\{}", unc))
+    } else if let Some(local) = path_str.strip_prefix(LONG_PREFIX) {
+        PathBuf::from(local)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Canonical key used to detect the same file under different spellings
+///
+/// Windows paths (drive-letter or UNC) are case-insensitive and accept either
+/// separator, so they are lowercased with backslashes. Other paths are exact.
+pub fn path_key(path: &Path) -> String {
+    let path_str = from_long_path(path).to_string_lossy().into_owned();
+    if cfg!(windows) || is_windows_style(&path_str) {
+        path_str.to_lowercase().replace('/', "\\")
+    } else {
+        path_str
+    }
+}
+
+/// Check for a drive-letter (`C:`) or UNC (`\\server`) path
+pub fn is_windows_style(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':')
+        || path.starts_with(r"\\")
+}
+
+/// Rules applied before handing a path to the OS shell
+///
+/// Paths are passed to `open::that` as `&Path`, never interpolated into a
+/// shell command, so characters like `&`, `|` or `;` are harmless and allowed.
+pub struct OpenPolicy<'a> {
+    /// Indexed roots a strict-mode target must resolve into
+    pub roots: &'a [PathBuf],
+    /// Network shares (`\\server\share`) that may be opened
+    pub network_locations: &'a [PathBuf],
+    /// Reject targets whose symlinks/junctions resolve outside `roots`
+    pub strict: bool,
+}
+
+impl OpenPolicy<'_> {
+    /// Check that `path` may be opened; `allow_missing` permits revealing
+    /// the folder of a file that no longer exists
+    pub fn check(&self, path: &Path, allow_missing: bool) -> Result<()> {
+        let unsafe_path = |reason: &str| FlashFindError::UnsafePath {
+            path: path.display().to_string(),
+            reason: reason.to_string(),
+        };
+        
+        let is_unc = is_unc_path(path);
+        if is_unc && !self.network_locations.iter().any(|loc| is_within(path, loc)) {
+            return Err(unsafe_path("network path is not a configured network location"));
+        }
+        
+        if !is_unc && !path.is_absolute() {
+            return Err(unsafe_path("path is not absolute"));
+        }
+        
+        let fs_path = to_long_path(path);
+        if !fs_path.exists() {
+            return if allow_missing {
+                Ok(())
+            } else {
+                Err(unsafe_path("file no longer exists"))
+            };
+        }
+        
+        if self.strict {
+            let resolved = std::fs::canonicalize(&fs_path)
+                .map_err(|_| unsafe_path("path cannot be resolved"))?;
+            let inside_root = self
+                .roots
+                .iter()
+                .chain(self.network_locations)
+                .filter_map(|root| std::fs::canonicalize(to_long_path(root)).ok())
+                .any(|root| is_within(&resolved, &root));
+            
+            if !inside_root {
+                return Err(unsafe_path("link resolves outside the indexed folders"));
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+/// Check for a UNC path (`\\server\share`), excluding extended-length local paths
+fn is_unc_path(path: &Path) -> bool {
+    let path_str = path.to_string_lossy();
+    path_str.starts_with(LONG_UNC_PREFIX)
+        || (path_str.starts_with(r"\\") && !path_str.starts_with(LONG_PREFIX))
+}
+
+/// Component-wise prefix check that ignores separator style and, on Windows, case
+pub fn is_within(path: &Path, root: &Path) -> bool {
+    let normalize = |p: &Path| -> Vec<String> {
+        let s = from_long_path(p).to_string_lossy().into_owned();
+        let s = if cfg!(windows) { s.to_lowercase() } else { s };
+        s.split(['/', '\\'])
+            .filter(|c| !c.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    
+    let path_parts = normalize(path);
+    let root_parts = normalize(root);
+    !root_parts.is_empty() && path_parts.starts_with(&root_parts)
+}
+
+/// Add the `\\?\` prefix to an absolute Windows path string if it is too long
+///
+/// Extended-length paths skip Win32 normalization, so forward slashes are
+/// converted here. Relative and already-prefixed paths are left alone.
+fn extend_path(path: &str) -> Option<String> {
+    if path.starts_with(LONG_PREFIX) || path.len() < MAX_DIR_PATH {
+        return None;
+    }
+
+    let normalized = path.replace('/', "\\");
+
+    if let Some(unc) = normalized.strip_prefix(r"\\") {
+        return Some(format!("{}{}", LONG_UNC_PREFIX, unc));
+    }
+
+    let bytes = normalized.as_bytes();
+    let is_drive_absolute = bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && bytes[2] == b'\\';
+
+    if is_drive_absolute {
+        Some(format!("{}{}", LONG_PREFIX, normalized))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    fn long_tail() -> String {
+        "very_long_directory_name\\".repeat(14)
+    }
+
+    #[test]
+    fn test_short_paths_unchanged() {
+        assert_eq!(extend_path("C:\\Users\\me\\file.txt"), None);
+    }
+
+    #[test]
+    fn test_extend_local_path() {
+        let path = format!("C:\\{}file.txt", long_tail());
+        assert_eq!(extend_path(&path), Some(format!("\\\\?\\{}", path)));
+    }
+
+    #[test]
+    fn test_extend_unc_path() {
+        let path = format!("\\\\server\\share\\{}file.txt", long_tail());
+        let extended = extend_path(&path).unwrap();
+        assert!(extended.starts_with("\\\\?\\UNC\\server\\share\\"));
+    }
+
+    #[test]
+    fn test_extend_normalizes_slashes() {
+        let path = format!("C:/{}file.txt", long_tail().replace('\\', "/"));
+        let extended = extend_path(&path).unwrap();
+        assert!(!extended.contains('/'));
+    }
+
+    #[test]
+    fn test_extend_skips_relative_and_prefixed() {
+        assert_eq!(extend_path(&format!("relative\\{}", long_tail())), None);
+        assert_eq!(extend_path(&format!("\\\\?\\C:\\{}", long_tail())), None);
+    }
+
+    fn lenient() -> OpenPolicy<'static> {
+        OpenPolicy { roots: &[], network_locations: &[], strict: false }
+    }
+
+    #[test]
+    fn test_open_policy_allows_legal_filename_characters() {
+        let dir = TestDir::new("open_chars");
+        for name in ["AT&T bill.pdf", "a;b.txt", "Tom & Jerry.mkv", "résumé 履歴書.pdf", "trailing space .txt "] {
+            let file = dir.join(name);
+            std::fs::write(&file, b"x").unwrap();
+            assert!(lenient().check(&file, false).is_ok(), "rejected {}", name);
+        }
+    }
+
+    #[test]
+    fn test_open_policy_rejects_relative_and_missing() {
+        assert!(lenient().check(Path::new("relative/file.txt"), false).is_err());
+
+        let missing = std::env::temp_dir().join("flashfind_definitely_missing.txt");
+        assert!(lenient().check(&missing, false).is_err());
+        assert!(lenient().check(&missing, true).is_ok());
+    }
+
+    #[test]
+    fn test_open_policy_network_locations() {
+        let share = PathBuf::from(r"\\nas\shared");
+        let policy = OpenPolicy { roots: &[], network_locations: std::slice::from_ref(&share), strict: false };
+
+        assert!(lenient().check(Path::new(r"\\nas\shared\doc.pdf"), true).is_err());
+        if cfg!(windows) {
+            assert!(policy.check(Path::new(r"\\NAS\shared\doc.pdf"), true).is_ok());
+        }
+        assert!(policy.check(Path::new(r"\\nas\shared\doc.pdf"), true).is_ok());
+        assert!(policy.check(Path::new(r"\\other\shared\doc.pdf"), true).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_strict_mode_rejects_links_outside_roots() {
+        let root = TestDir::new("strict_root");
+        let outside = TestDir::new("strict_outside");
+        let target = outside.join("secret.txt");
+        std::fs::write(&target, b"x").unwrap();
+        let inside = root.join("inside.txt");
+        std::fs::write(&inside, b"x").unwrap();
+        let link = root.join("link.txt");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let roots = [root.to_path_buf()];
+        let policy = OpenPolicy { roots: &roots, network_locations: &[], strict: true };
+        assert!(policy.check(&inside, false).is_ok());
+        assert!(policy.check(&link, false).is_err());
+        assert!(lenient().check(&link, false).is_ok());
+    }
+
+    #[test]
+    fn test_path_key_windows_paths_ignore_case() {
+        assert_eq!(path_key(Path::new("C:\\Docs\\File.txt")), "c:\\docs\\file.txt");
+        assert_eq!(path_key(Path::new("c:/docs/file.txt")), "c:\\docs\\file.txt");
+        assert_eq!(path_key(Path::new("\\\\NAS\\Share\\a.txt")), "\\\\nas\\share\\a.txt");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_path_key_unix_paths_keep_case() {
+        assert_ne!(path_key(Path::new("/home/me/File.txt")), path_key(Path::new("/home/me/file.txt")));
+    }
+
+    #[test]
+    fn test_round_trip_display_form() {
+        let local = format!("C:\\{}file.txt", long_tail());
+        let extended = PathBuf::from(extend_path(&local).unwrap());
+        assert_eq!(from_long_path(&extended), PathBuf::from(&local));
+
+        let unc = format!("\\\\server\\share\\{}file.txt", long_tail());
+        let extended = PathBuf::from(extend_path(&unc).unwrap());
+        assert_eq!(from_long_path(&extended), PathBuf::from(&unc));
+    }
+}
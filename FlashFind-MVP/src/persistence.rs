@@ -1,30 +1,21 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 use tracing::{debug, error, info, warn};
 
 use crate::error::{FlashFindError, Result};
-use crate::index::{FileIndex, INDEX_VERSION};
+use crate::index::{migrate_v1, migrate_v2, migrate_v3, migrate_v4, migrate_v5, migrate_v6, migrate_v7, migrate_v8, migrate_v9, FileIndex, INDEX_VERSION};
+use crate::platform;
+use crate::redact::redact;
+use crate::sharded::{IndexView, ShardedIndex};
 
-/// Get the application data directory
+/// Get the application data directory (see `platform::data_dir`)
 pub fn get_app_data_dir() -> Result<PathBuf> {
-    #[cfg(target_os = "windows")]
-    {
-        use known_folders::{get_known_folder_path, KnownFolder};
-        
-        let roaming_appdata = get_known_folder_path(KnownFolder::RoamingAppData)
-            .ok_or_else(|| FlashFindError::SystemFolderError("APPDATA".to_string()))?;
-        
-        let app_dir = roaming_appdata.join("FlashFind");
-        Ok(app_dir)
-    }
-    
-    #[cfg(not(target_os = "windows"))]
-    {
-        // Fallback for non-Windows (though this is a Windows-focused app)
-        let home = std::env::var("HOME")
-            .map_err(|_| FlashFindError::SystemFolderError("HOME".to_string()))?;
-        Ok(PathBuf::from(home).join(".flashfind"))
-    }
+    platform::data_dir()
 }
 
 /// Get the path to the index file
@@ -33,11 +24,8 @@ pub fn get_index_path() -> Result<PathBuf> {
     
     // Ensure directory exists
     if !app_dir.exists() {
-        fs::create_dir_all(&app_dir).map_err(|e| FlashFindError::DirectoryCreationError {
-            path: app_dir.display().to_string(),
-            source: e,
-        })?;
-        info!("Created application data directory: {}", app_dir.display());
+        create_storage_dir(&app_dir)?;
+        info!("Created application data directory: {}", redact(&app_dir));
     }
     
     Ok(app_dir.join("index.bin"))
@@ -45,73 +33,201 @@ pub fn get_index_path() -> Result<PathBuf> {
 
 /// Get the path to the log file
 pub fn get_log_path() -> Result<PathBuf> {
-    let app_dir = get_app_data_dir()?;
+    let log_dir = platform::log_dir()?;
     
-    if !app_dir.exists() {
-        fs::create_dir_all(&app_dir).map_err(|e| FlashFindError::DirectoryCreationError {
-            path: app_dir.display().to_string(),
-            source: e,
-        })?;
+    if !log_dir.exists() {
+        create_storage_dir(&log_dir)?;
     }
     
-    Ok(app_dir.join("flashfind.log"))
+    Ok(log_dir.join("flashfind.log"))
 }
 
-/// Load the index from disk with version checking
-pub fn load_index() -> Result<FileIndex> {
-    let path = get_index_path()?;
+/// Check once at startup that the data folder can be created and written
+///
+/// Fails with `StorageUnavailable` on locked-down machines, so the app can
+/// switch to ephemeral mode instead of failing every save.
+pub fn check_storage() -> Result<PathBuf> {
+    let app_dir = get_app_data_dir()?;
+    ensure_writable(&app_dir)?;
+    Ok(app_dir)
+}
+
+/// Create `dir` if needed and prove it is writable with a probe file
+fn ensure_writable(dir: &Path) -> Result<()> {
+    create_storage_dir(dir)?;
     
+    let probe = dir.join(format!(".write_test.{}", std::process::id()));
+    fs::write(&probe, b"").map_err(|e| FlashFindError::StorageUnavailable {
+        path: dir.display().to_string(),
+        source: e,
+    })?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+/// Create an app storage folder, reporting failure as `StorageUnavailable`
+fn create_storage_dir(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir).map_err(|e| FlashFindError::StorageUnavailable {
+        path: dir.display().to_string(),
+        source: e,
+    })
+}
+
+/// The index file since v11: the version, then each shard in order
+#[derive(Deserialize)]
+struct ShardedFile {
+    version: u32,
+    shards: Vec<FileIndex>,
+}
+
+/// `ShardedFile` as written, borrowing the locked shards
+#[derive(Serialize)]
+struct ShardedFileRef<'a> {
+    version: u32,
+    shards: Vec<&'a FileIndex>,
+}
+
+/// Load the index from disk with version checking
+pub fn load_index() -> Result<ShardedIndex> {
+    load_index_from(&get_index_path()?)
+}
+
+/// Load an index from a specific file
+pub fn load_index_from(path: &Path) -> Result<ShardedIndex> {
     if !path.exists() {
-        info!("No existing index found at {}", path.display());
-        return Ok(FileIndex::new());
+        info!("No existing index found at {}", redact(&path));
+        return Ok(ShardedIndex::new());
     }
     
-    debug!("Loading index from {}", path.display());
+    debug!("Loading index from {}", redact(&path));
     
-    let data = fs::read(&path).map_err(|e| FlashFindError::FileReadError {
+    let data = fs::read(path).map_err(|e| FlashFindError::FileReadError {
         path: path.display().to_string(),
         source: e,
     })?;
     
-    let mut index: FileIndex = bincode::deserialize(&data)
-        .map_err(|e| {
-            error!("Failed to deserialize index: {}", e);
-            FlashFindError::CorruptedIndex(e)
-        })?;
-    
-    // Version compatibility check
-    if index.version() != INDEX_VERSION {
-        warn!(
-            "Index version mismatch: found {}, expected {}",
-            index.version(),
-            INDEX_VERSION
-        );
-        return Err(FlashFindError::VersionMismatch {
-            found: index.version(),
-            expected: INDEX_VERSION,
-        });
-    }
+    // The version is the leading field in every layout, so peek at it first
+    let version: u32 = bincode::deserialize(&data).map_err(|e| {
+        error!("Failed to read index version: {}", e);
+        FlashFindError::CorruptedIndex(e)
+    })?;
+    
+    let index = match version {
+        INDEX_VERSION => {
+            let file: ShardedFile = bincode::deserialize(&data).map_err(|e| {
+                error!("Failed to deserialize index: {}", e);
+                FlashFindError::CorruptedIndex(e)
+            })?;
+            let mut shards = file.shards;
+            for shard in &mut shards {
+                if shard.version() != file.version {
+                    let reason = format!("v{} shard in a v{} index", shard.version(), file.version);
+                    return Err(FlashFindError::CorruptedIndex(Box::new(bincode::ErrorKind::Custom(reason))));
+                }
+                prepare_loaded(shard)?;
+            }
+            let index = ShardedIndex::from_shards(shards)?;
+            info!("Loaded index v{} with {} files", version, index.read().len());
+            return Ok(index);
+        }
+        // v10 saved one unsharded index
+        10 => {
+            let mut index: FileIndex = bincode::deserialize(&data).map_err(|e| {
+                error!("Failed to deserialize index: {}", e);
+                FlashFindError::CorruptedIndex(e)
+            })?;
+            prepare_loaded(&mut index)?;
+            index
+        }
+        1 => migrate_v1(&data).map_err(|e| {
+            error!("Failed to migrate v1 index: {}", e);
+            e
+        })?,
+        2 => migrate_v2(&data).map_err(|e| {
+            error!("Failed to migrate v2 index: {}", e);
+            e
+        })?,
+        3 => migrate_v3(&data).map_err(|e| {
+            error!("Failed to migrate v3 index: {}", e);
+            e
+        })?,
+        4 => migrate_v4(&data).map_err(|e| {
+            error!("Failed to migrate v4 index: {}", e);
+            e
+        })?,
+        5 => migrate_v5(&data).map_err(|e| {
+            error!("Failed to migrate v5 index: {}", e);
+            e
+        })?,
+        6 => migrate_v6(&data).map_err(|e| {
+            error!("Failed to migrate v6 index: {}", e);
+            e
+        })?,
+        7 => migrate_v7(&data).map_err(|e| {
+            error!("Failed to migrate v7 index: {}", e);
+            e
+        })?,
+        8 => migrate_v8(&data).map_err(|e| {
+            error!("Failed to migrate v8 index: {}", e);
+            e
+        })?,
+        9 => migrate_v9(&data).map_err(|e| {
+            error!("Failed to migrate v9 index: {}", e);
+            e
+        })?,
+        found => {
+            warn!(
+                "Index version mismatch: found {}, expected {}",
+                found,
+                INDEX_VERSION
+            );
+            return Err(FlashFindError::VersionMismatch {
+                found,
+                expected: INDEX_VERSION,
+            });
+        }
+    };
+    
+    info!("Loaded index v{} with {} files, splitting it into shards", version, index.len());
+    Ok(ShardedIndex::from_index(index))
+}
+
+/// Check a deserialized index and rebuild what it doesn't store
+fn prepare_loaded(index: &mut FileIndex) -> Result<()> {
+    index.validate().inspect_err(|e| error!("Rejected loaded index: {:?}", e))?;
     
     // Rebuild runtime cache
     index.rebuild_cache();
     
-    info!("Loaded index with {} files", index.len());
-    Ok(index)
+    if cfg!(debug_assertions) {
+        let report = index.check_invariants();
+        if !report.is_consistent() {
+            warn!("Loaded index is inconsistent: {:?}", report);
+        }
+    }
+    Ok(())
 }
 
-/// Save the index to disk atomically
+/// Save an index to a specific file atomically
 /// 
 /// This performs an atomic write by:
-/// 1. Writing to a temporary file
+/// 1. Writing to a uniquely named temporary file next to the target
 /// 2. Renaming the temp file to the target (atomic operation on same filesystem)
-pub fn save_index(index: &FileIndex) -> Result<()> {
-    let path = get_index_path()?;
-    let temp_path = path.with_extension("tmp");
+pub fn save_index_to(index: &IndexView, path: &Path) -> Result<()> {
+    static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+    
+    let suffix = format!(
+        "{}.{}.tmp",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    let temp_path = path.with_extension(suffix);
     
     debug!("Saving index with {} files", index.len());
     
     // Serialize to bytes
-    let data = bincode::serialize(index).map_err(|e| {
+    let file = ShardedFileRef { version: INDEX_VERSION, shards: index.shards().collect() };
+    let data = bincode::serialize(&file).map_err(|e| {
         error!("Failed to serialize index: {}", e);
         FlashFindError::CorruptedIndex(e)
     })?;
@@ -123,25 +239,157 @@ pub fn save_index(index: &FileIndex) -> Result<()> {
     })?;
     
     // Atomic rename (overwrites existing file)
-    fs::rename(&temp_path, &path).map_err(|e| FlashFindError::FileWriteError {
-        path: path.display().to_string(),
-        source: e,
+    fs::rename(&temp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        FlashFindError::FileWriteError {
+            path: path.display().to_string(),
+            source: e,
+        }
     })?;
     
-    info!("Index saved successfully to {}", path.display());
+    info!("Index saved successfully to {}", redact(&path));
     Ok(())
 }
 
+/// Single entry point for every index save (indexer, auto-save, manual, exit)
+///
+/// Writes are serialized; a request arriving while another save is running is
+/// coalesced into at most one follow-up write instead of overlapping it.
+pub struct SaveCoordinator {
+    index: Arc<ShardedIndex>,
+    /// Target file; `None` resolves the default index path at save time
+    path: Option<PathBuf>,
+    write_lock: Mutex<()>,
+    pending: AtomicBool,
+    saved_generation: AtomicU64,
+    last_save: Mutex<Instant>,
+    /// Ephemeral mode: storage is unwritable, so never attempt a write
+    disabled: bool,
+}
+
+impl SaveCoordinator {
+    /// Create a coordinator saving to the default index path
+    pub fn new(index: Arc<ShardedIndex>) -> Self {
+        let generation = index.generation();
+        Self {
+            index,
+            path: None,
+            write_lock: Mutex::new(()),
+            pending: AtomicBool::new(false),
+            saved_generation: AtomicU64::new(generation),
+            last_save: Mutex::new(Instant::now()),
+            disabled: false,
+        }
+    }
+    
+    /// Create a coordinator that keeps the index in memory only
+    pub fn ephemeral(index: Arc<ShardedIndex>) -> Self {
+        Self {
+            disabled: true,
+            ..Self::new(index)
+        }
+    }
+    
+    /// Check whether saves are disabled (ephemeral mode)
+    pub fn is_ephemeral(&self) -> bool {
+        self.disabled
+    }
+    
+    /// Create a coordinator saving to a specific file
+    #[cfg(test)]
+    pub fn with_path(index: Arc<ShardedIndex>, path: PathBuf) -> Self {
+        Self {
+            path: Some(path),
+            ..Self::new(index)
+        }
+    }
+    
+    /// Check whether the index changed since the last successful save
+    /// (never true in ephemeral mode, so auto-save stays quiet)
+    pub fn is_dirty(&self) -> bool {
+        !self.disabled
+            && self.index.generation() != self.saved_generation.load(Ordering::SeqCst)
+    }
+    
+    /// Time of the last save attempt (or creation), used to pace auto-save
+    pub fn last_save(&self) -> Instant {
+        *self.last_save.lock()
+    }
+    
+    /// Request a save without waiting behind one already in progress
+    ///
+    /// Returns `Ok(true)` if this call wrote the file and `Ok(false)` if the
+    /// request was handed to the save already running.
+    pub fn save(&self) -> Result<bool> {
+        if self.disabled {
+            debug!("Ephemeral mode, index save skipped");
+            return Ok(false);
+        }
+        
+        self.pending.store(true, Ordering::SeqCst);
+        let mut result = Ok(false);
+        
+        loop {
+            let Some(guard) = self.write_lock.try_lock() else {
+                debug!("Save already in progress, request coalesced");
+                return result;
+            };
+            
+            while self.pending.swap(false, Ordering::SeqCst) {
+                result = self.write().map(|()| true);
+            }
+            drop(guard);
+            
+            // A request may have arrived between the last check and the unlock
+            if !self.pending.load(Ordering::SeqCst) {
+                return result;
+            }
+        }
+    }
+    
+    /// Save and wait for any in-flight write (used on exit)
+    pub fn flush(&self) -> Result<()> {
+        if self.disabled {
+            return Ok(());
+        }
+        
+        let _guard = self.write_lock.lock();
+        self.pending.store(false, Ordering::SeqCst);
+        self.write()
+    }
+    
+    /// Perform one write while holding the write lock
+    fn write(&self) -> Result<()> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => get_index_path()?,
+        };
+        
+        self.index.publish();
+        let index = self.index.read();
+        let generation = index.generation();
+        let result = save_index_to(&index, &path);
+        drop(index);
+        
+        // Failed attempts still count so auto-save doesn't retry every frame
+        *self.last_save.lock() = Instant::now();
+        result?;
+        self.saved_generation.store(generation, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_dir::TestDir;
 
     #[test]
     fn test_get_app_data_dir() {
         let result = get_app_data_dir();
         assert!(result.is_ok());
         let path = result.unwrap();
-        assert!(path.to_string_lossy().contains("FlashFind"));
+        assert!(path.ends_with(platform::APP_DIR_NAME));
     }
 
     #[test]
@@ -151,4 +399,147 @@ mod tests {
         let path = result.unwrap();
         assert!(path.to_string_lossy().ends_with("index.bin"));
     }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let dir = TestDir::new("persist_round_trip");
+        let path = dir.join("index.bin");
+        let index = ShardedIndex::new();
+        index.insert(PathBuf::from("C:\\test\\file.txt")).unwrap();
+        index.publish();
+
+        save_index_to(&index.read(), &path).unwrap();
+        let loaded = load_index_from(&path).unwrap();
+        assert_eq!(loaded.read().len(), 1);
+    }
+
+    #[test]
+    fn test_len_counts_live_entries_across_removal_and_reload() {
+        let dir = TestDir::new("persist_live_count");
+        let path = dir.join("index.bin");
+        let index = ShardedIndex::new();
+        for name in ["a", "b", "c"] {
+            index.insert(PathBuf::from(format!("/docs/{}.txt", name))).unwrap();
+        }
+        let counts = |index: &ShardedIndex| {
+            index.publish();
+            let view = index.read();
+            (view.len(), view.tombstones())
+        };
+        assert_eq!(counts(&index), (3, 0));
+
+        index.remove(Path::new("/docs/b.txt")).unwrap();
+        assert_eq!(counts(&index), (2, 1));
+        // Re-inserting takes a new slot; the old one is a tombstone
+        index.insert(PathBuf::from("/docs/b.txt")).unwrap();
+        assert_eq!(counts(&index), (3, 1));
+        index.remove(Path::new("/docs/a.txt")).unwrap();
+        index.publish();
+
+        save_index_to(&index.read(), &path).unwrap();
+        let loaded = load_index_from(&path).unwrap();
+        assert_eq!(counts(&loaded), (2, 2));
+
+        // Removing everything leaves an empty index, tombstones or not
+        loaded.write().remove_where(|_| true);
+        assert_eq!(counts(&loaded), (0, 4));
+        save_index_to(&loaded.read(), &path).unwrap();
+        assert!(load_index_from(&path).unwrap().read().is_empty());
+    }
+
+    #[test]
+    fn test_wrong_shard_count_is_rejected() {
+        let dir = TestDir::new("persist_shard_count");
+        let path = dir.join("index.bin");
+        let shard = FileIndex::new();
+        let file = ShardedFileRef { version: INDEX_VERSION, shards: vec![&shard; 3] };
+        fs::write(&path, bincode::serialize(&file).unwrap()).unwrap();
+
+        assert!(matches!(load_index_from(&path), Err(FlashFindError::CorruptedIndex(_))));
+    }
+
+    #[test]
+    fn test_coordinator_dirty_tracking() {
+        let dir = TestDir::new("persist_dirty");
+        let path = dir.join("index.bin");
+        let index = Arc::new(ShardedIndex::new());
+        let saver = SaveCoordinator::with_path(index.clone(), path.clone());
+        assert!(!saver.is_dirty());
+
+        index.insert(PathBuf::from("C:\\test\\file.txt")).unwrap();
+        assert!(saver.is_dirty());
+
+        assert!(saver.save().unwrap());
+        assert!(!saver.is_dirty());
+    }
+
+    #[test]
+    fn test_unwritable_storage_is_reported() {
+        // A file where the data folder should be makes it uncreatable
+        let dir = TestDir::new("persist_unwritable");
+        let blocker = dir.join("blocker");
+        fs::write(&blocker, b"x").unwrap();
+
+        let err = ensure_writable(&blocker.join("FlashFind")).unwrap_err();
+        assert!(matches!(err, FlashFindError::StorageUnavailable { .. }));
+        assert!(ensure_writable(&dir).is_ok());
+    }
+
+    #[test]
+    fn test_ephemeral_coordinator_never_writes() {
+        let dir = TestDir::new("persist_ephemeral");
+        let path = dir.join("index.bin");
+        let index = Arc::new(ShardedIndex::new());
+        let saver = SaveCoordinator {
+            path: Some(path.clone()),
+            ..SaveCoordinator::ephemeral(index.clone())
+        };
+
+        index.insert(PathBuf::from("C:\\test\\file.txt")).unwrap();
+        assert!(saver.is_ephemeral());
+        assert!(!saver.is_dirty());
+        assert!(!saver.save().unwrap());
+        saver.flush().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_concurrent_saves_leave_valid_file() {
+        let dir = TestDir::new("persist_stress");
+        let path = dir.join("index.bin");
+        let index = Arc::new(ShardedIndex::new());
+        let saver = Arc::new(SaveCoordinator::with_path(index.clone(), path.clone()));
+
+        let handles: Vec<_> = (0..8)
+            .map(|t| {
+                let index = index.clone();
+                let saver = saver.clone();
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for i in 0..25 {
+                        index
+                            .insert(PathBuf::from(format!("C:\\t{}\\file_{}.txt", t, i)))
+                            .unwrap();
+                        saver.save().unwrap();
+                        load_index_from(&path).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        saver.flush().unwrap();
+
+        let loaded = load_index_from(&path).unwrap();
+        assert_eq!(loaded.read().len(), 200);
+        assert!(!saver.is_dirty());
+
+        // No orphaned temp files left behind
+        let leftovers = fs::read_dir(&dir)
+            .unwrap()
+            .filter(|e| e.as_ref().unwrap().path() != path)
+            .count();
+        assert_eq!(leftovers, 0);
+    }
 }
@@ -0,0 +1,389 @@
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::error::{FlashFindError, Result};
+use crate::paths::is_within;
+use crate::redact::redact;
+
+/// Folder name under the platform data, config and log locations
+///
+/// XDG systems conventionally use lowercase names (`~/.local/share/flashfind`).
+pub const APP_DIR_NAME: &str = if cfg!(any(windows, target_os = "macos")) {
+    "FlashFind"
+} else {
+    "flashfind"
+};
+
+/// Environment variable that relocates all app data (portable mode)
+pub const DATA_DIR_ENV: &str = "FLASHFIND_DATA_DIR";
+
+/// Filesystem types that never hold user files
+const PSEUDO_FILESYSTEMS: &[&str] = &[
+    "autofs", "binfmt_misc", "bpf", "cgroup", "cgroup2", "configfs", "debugfs",
+    "devpts", "devtmpfs", "efivarfs", "fusectl", "hugetlbfs", "mqueue", "nsfs",
+    "overlay", "proc", "pstore", "ramfs", "rpc_pipefs", "securityfs", "squashfs",
+    "sysfs", "tmpfs", "tracefs",
+];
+
+/// Parents under which removable and extra volumes are mounted
+const MOUNT_PARENTS: &[&str] = &["/media", "/run/media", "/mnt", "/Volumes"];
+
+/// Data folder chosen through `FLASHFIND_DATA_DIR`, if set
+fn portable_dir() -> Option<PathBuf> {
+    std::env::var_os(DATA_DIR_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Directory holding the index (Roaming AppData, XDG data home, Application Support)
+///
+/// `FLASHFIND_DATA_DIR` overrides this and the config and log locations.
+pub fn data_dir() -> Result<PathBuf> {
+    if let Some(dir) = portable_dir() {
+        return Ok(dir);
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        use known_folders::{get_known_folder_path, KnownFolder};
+
+        let roaming_appdata = get_known_folder_path(KnownFolder::RoamingAppData)
+            .ok_or_else(|| FlashFindError::SystemFolderError("APPDATA".to_string()))?;
+        Ok(roaming_appdata.join(APP_DIR_NAME))
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        dirs::data_dir()
+            .map(|dir| dir.join(APP_DIR_NAME))
+            .ok_or_else(|| FlashFindError::SystemFolderError("data directory".to_string()))
+    }
+}
+
+/// Directory holding config.json (XDG config home on Linux, data dir elsewhere)
+pub fn config_dir() -> Result<PathBuf> {
+    if cfg!(any(windows, target_os = "macos")) || portable_dir().is_some() {
+        return data_dir();
+    }
+
+    dirs::config_dir()
+        .map(|dir| dir.join(APP_DIR_NAME))
+        .ok_or_else(|| FlashFindError::SystemFolderError("config directory".to_string()))
+}
+
+/// Directory holding flashfind.log (XDG state home, ~/Library/Logs, or the data dir)
+pub fn log_dir() -> Result<PathBuf> {
+    if cfg!(windows) || portable_dir().is_some() {
+        return data_dir();
+    }
+
+    if cfg!(target_os = "macos") {
+        return dirs::home_dir()
+            .map(|home| home.join("Library").join("Logs").join(APP_DIR_NAME))
+            .ok_or_else(|| FlashFindError::SystemFolderError("HOME".to_string()));
+    }
+
+    match dirs::state_dir() {
+        Some(dir) => Ok(dir.join(APP_DIR_NAME)),
+        None => data_dir(),
+    }
+}
+
+/// Default folders to index: user folders on C: (Windows) or in the home directory
+pub fn get_default_directories() -> Vec<PathBuf> {
+    if cfg!(windows) {
+        get_directories_for_drives(&['C'])
+    } else {
+        get_home_directories()
+    }
+}
+
+/// Folders to index for the current configuration
+///
+/// Windows uses the enabled drive letters. Elsewhere the home folders are
+/// always indexed, plus any mount points enabled in the Locations tab.
+pub fn configured_directories(config: &Config) -> Vec<PathBuf> {
+    if cfg!(windows) {
+        return get_directories_for_drives(&config.enabled_drives);
+    }
+
+    let mut dirs = get_home_directories();
+    dirs.extend(config.enabled_locations.iter().filter(|p| p.is_dir()).cloned());
+    remove_nested(dirs)
+}
+
+/// Drop folders already covered by another folder in the list
+fn remove_nested(dirs: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut kept: Vec<PathBuf> = Vec::new();
+    for dir in dirs {
+        if kept.iter().any(|k| is_within(&dir, k)) {
+            continue;
+        }
+        kept.retain(|k| !is_within(k, &dir));
+        kept.push(dir);
+    }
+    kept
+}
+
+/// Standard user folders under the home directory (XDG user dirs on Linux)
+///
+/// Falls back to the home directory itself when none of them exist.
+fn get_home_directories() -> Vec<PathBuf> {
+    let candidates = [
+        ("Documents", dirs::document_dir()),
+        ("Downloads", dirs::download_dir()),
+        ("Desktop", dirs::desktop_dir()),
+        ("Pictures", dirs::picture_dir()),
+        ("Videos", dirs::video_dir()),
+        ("Music", dirs::audio_dir()),
+    ];
+
+    let mut found = Vec::new();
+    for (name, dir) in candidates {
+        match dir {
+            Some(path) if path.is_dir() => {
+                info!("Added default directory: {} ({})", name, redact(&path));
+                found.push(path);
+            }
+            _ => {}
+        }
+    }
+
+    if found.is_empty() {
+        if let Some(home) = dirs::home_dir() {
+            info!("No standard user folders found, indexing home: {}", redact(&home));
+            found.push(home);
+        }
+    }
+
+    remove_nested(found)
+}
+
+/// Mounted volumes the user can choose to index (drive roots on Windows)
+pub fn mount_points() -> Vec<PathBuf> {
+    if cfg!(windows) {
+        return get_available_drives()
+            .into_iter()
+            .map(|letter| PathBuf::from(format!("{}:\\", letter)))
+            .collect();
+    }
+
+    match std::fs::read_to_string("/proc/self/mounts") {
+        Ok(mounts) => parse_mounts(&mounts),
+        Err(_) => {
+            // No procfs (macOS, BSD): the root plus whatever is under /Volumes etc.
+            let mut points = vec![PathBuf::from("/")];
+            for parent in MOUNT_PARENTS {
+                if let Ok(entries) = std::fs::read_dir(parent) {
+                    points.extend(entries.filter_map(|e| e.ok()).map(|e| e.path()));
+                }
+            }
+            points
+        }
+    }
+}
+
+/// Extract user-facing mount points from `/proc/self/mounts` content
+fn parse_mounts(content: &str) -> Vec<PathBuf> {
+    let mut points: Vec<PathBuf> = content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (_device, mount, fs_type) = (fields.next()?, fields.next()?, fields.next()?);
+
+            if PSEUDO_FILESYSTEMS.contains(&fs_type) {
+                return None;
+            }
+
+            let mount = unescape_mount(mount);
+            let user_facing = mount == "/"
+                || MOUNT_PARENTS.iter().any(|parent| is_within(Path::new(&mount), Path::new(parent)));
+            user_facing.then(|| PathBuf::from(mount))
+        })
+        .collect();
+
+    points.sort();
+    points.dedup();
+    points
+}
+
+/// Decode the octal escapes (`\040` for space) used in the mounts table
+fn unescape_mount(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut chars = field.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            let digits: String = chars.clone().take(3).collect();
+            if digits.len() == 3 {
+                if let Ok(code) = u8::from_str_radix(&digits, 8) {
+                    out.push(code as char);
+                    chars.nth(2);
+                    continue;
+                }
+            }
+        }
+        out.push(c);
+    }
+
+    out
+}
+
+/// Get available Windows drive letters (empty on other platforms)
+pub fn get_available_drives() -> Vec<char> {
+    #[allow(unused_mut)]
+    let mut drives = Vec::new();
+
+    #[cfg(target_os = "windows")]
+    {
+        // Check common drive letters A-Z
+        for letter in 'A'..='Z' {
+            let drive_path = format!("{}:\\", letter);
+            if std::path::Path::new(&drive_path).exists() {
+                drives.push(letter);
+            }
+        }
+    }
+
+    drives
+}
+
+/// Get directories for specified drives
+pub fn get_directories_for_drives(drive_letters: &[char]) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    
+    #[cfg(target_os = "windows")]
+    {
+        use known_folders::{get_known_folder_path, KnownFolder};
+        
+        // Only add user folders if C: drive is enabled
+        if drive_letters.contains(&'C') {
+            let folders = vec![
+                (KnownFolder::Documents, "Documents"),
+                (KnownFolder::Downloads, "Downloads"),
+                (KnownFolder::Desktop, "Desktop"),
+                (KnownFolder::Pictures, "Pictures"),
+                (KnownFolder::Videos, "Videos"),
+                (KnownFolder::Music, "Music"),
+            ];
+            
+            for (folder, name) in folders {
+                if let Some(path) = get_known_folder_path(folder) {
+                    if path.exists() {
+                        info!("Added default directory: {} ({})", name, redact(&path));
+                        dirs.push(path);
+                    } else {
+                        warn!("Known folder {} does not exist: {}", name, redact(&path));
+                    }
+                } else {
+                    warn!("Could not get path for known folder: {}", name);
+                }
+            }
+        }
+        
+        // Add root of other enabled drives (excluding C:)
+        for &drive in drive_letters {
+            if drive != 'C' {
+                let drive_root = PathBuf::from(format!("{}:\\", drive));
+                if drive_root.exists() {
+                    info!("Added drive root: {}", redact(&drive_root));
+                    dirs.push(drive_root);
+                }
+            }
+        }
+    }
+    
+    #[cfg(not(target_os = "windows"))]
+    {
+        // Drive letters don't apply; the home folders stand in for C:
+        let _ = drive_letters;
+        dirs.extend(get_home_directories());
+    }
+    
+    if dirs.is_empty() {
+        warn!("No default directories found!");
+    }
+    
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOUNTS: &str = "\
+proc /proc proc rw,nosuid,nodev,noexec,relatime 0 0
+sysfs /sys sysfs rw,nosuid,nodev,noexec,relatime 0 0
+/dev/nvme0n1p2 / ext4 rw,relatime 0 0
+/dev/nvme0n1p1 /boot/efi vfat rw,relatime 0 0
+tmpfs /run/user/1000 tmpfs rw,nosuid,nodev,relatime 0 0
+/dev/sdb1 /media/jane/USB\\040Stick vfat rw,nosuid,nodev 0 0
+/dev/sdc1 /mnt/backup ext4 rw,relatime 0 0
+/dev/loop3 /snap/core/1234 squashfs ro,nodev,relatime 0 0
+";
+
+    #[test]
+    fn test_parse_mounts_keeps_user_volumes() {
+        assert_eq!(
+            parse_mounts(MOUNTS),
+            vec![
+                PathBuf::from("/"),
+                PathBuf::from("/media/jane/USB Stick"),
+                PathBuf::from("/mnt/backup"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unescape_mount() {
+        assert_eq!(unescape_mount("/media/a\\040b"), "/media/a b");
+        assert_eq!(unescape_mount("/mnt/tab\\011x"), "/mnt/tab\tx");
+        assert_eq!(unescape_mount("/mnt/plain"), "/mnt/plain");
+        assert_eq!(unescape_mount("/mnt/odd\\9"), "/mnt/odd\\9");
+    }
+
+    #[test]
+    fn test_remove_nested_directories() {
+        let dirs = vec![
+            PathBuf::from("/home/me/Documents"),
+            PathBuf::from("/mnt/data"),
+            PathBuf::from("/home/me"),
+            PathBuf::from("/mnt/data/photos"),
+        ];
+        assert_eq!(remove_nested(dirs), vec![PathBuf::from("/mnt/data"), PathBuf::from("/home/me")]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_linux_locations_follow_xdg() {
+        let data = data_dir().unwrap();
+        let log = log_dir().unwrap();
+        assert!(data.ends_with("flashfind") && data.is_absolute());
+        assert!(log.ends_with("flashfind") && log.is_absolute());
+        assert!(config_dir().unwrap().ends_with("flashfind"));
+        assert_ne!(data, log);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_no_fake_drive_letters_off_windows() {
+        assert!(get_available_drives().is_empty());
+        assert!(!mount_points().is_empty());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_configured_directories_include_locations() {
+        use crate::test_dir::TestDir;
+
+        let location = TestDir::new("locations");
+        let config = Config {
+            enabled_locations: vec![location.to_path_buf(), PathBuf::from("/definitely/not/mounted")],
+            ..Config::default()
+        };
+        let dirs = configured_directories(&config);
+        assert!(dirs.iter().any(|d| location.starts_with(d)));
+        assert!(!dirs.contains(&PathBuf::from("/definitely/not/mounted")));
+    }
+}
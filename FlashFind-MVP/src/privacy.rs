@@ -0,0 +1,130 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether searches are currently incognito (toggled from the search box)
+static INCOGNITO: AtomicBool = AtomicBool::new(false);
+
+/// Placeholder written to logs in place of an incognito query
+const HIDDEN_QUERY: &str = "<incognito>";
+
+/// Turn incognito searching on or off
+pub fn set_incognito(enabled: bool) {
+    INCOGNITO.store(enabled, Ordering::Relaxed);
+}
+
+/// Check whether incognito searching is active
+pub fn is_incognito() -> bool {
+    INCOGNITO.load(Ordering::Relaxed)
+}
+
+/// Decide whether a query may outlive the search that ran it
+///
+/// Every feature that keeps a query around (saved searches, history, usage
+/// statistics, session restore) must ask here first, so incognito mode covers
+/// new features without touching each of them.
+pub fn should_record_query(query: &str) -> bool {
+    record_allowed(query, is_incognito())
+}
+
+fn record_allowed(query: &str, incognito: bool) -> bool {
+    !incognito && !query.trim().is_empty()
+}
+
+/// Wrap a query for logging; use at every log site that mentions a query
+pub fn logged_query(query: &str) -> LoggedQuery<'_> {
+    LoggedQuery(query)
+}
+
+/// Display adapter returned by `logged_query`
+pub struct LoggedQuery<'a>(&'a str);
+
+impl fmt::Display for LoggedQuery<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(format_query(self.0, is_incognito()))
+    }
+}
+
+fn format_query(query: &str, incognito: bool) -> &str {
+    if incognito {
+        HIDDEN_QUERY
+    } else {
+        query
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::index::{SortOrder, TypoTolerance};
+    use crate::ranking::RankingProfile;
+    use crate::search::{SearchBatch, SearchJob, SearchRequest};
+    use crate::sharded::{ShardedCache, ShardedIndex};
+    use crate::test_log::CapturedLog;
+    use parking_lot::Mutex;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    const SECRET: &str = "divorce_agreement";
+
+    #[test]
+    fn test_incognito_blocks_recording_and_hides_query() {
+        assert!(record_allowed(SECRET, false));
+        assert!(!record_allowed(SECRET, true));
+        assert!(!record_allowed("   ", false));
+
+        assert_eq!(format_query(SECRET, false), SECRET);
+        assert_eq!(format_query(SECRET, true), HIDDEN_QUERY);
+    }
+
+    #[test]
+    fn test_incognito_query_leaves_no_trace() {
+        let index = ShardedIndex::new();
+        index.insert(PathBuf::from("/home/me/divorce_agreement.pdf")).unwrap();
+        index.insert(PathBuf::from("/home/me/notes.txt")).unwrap();
+        index.publish();
+        let index = Arc::new(index);
+
+        let log = CapturedLog::start();
+        let mut config = Config::default();
+        set_incognito(true);
+
+        // The search and its shard scans log from their own threads
+        let request = SearchRequest {
+            query: SECRET.to_string(),
+            limit: usize::MAX,
+            sort: SortOrder::Name,
+            profile: RankingProfile::default(),
+            fuzzy: false,
+            typo_tolerance: TypoTolerance::Low,
+            offset: 0,
+            timeout: None,
+            favorites: Vec::new(),
+            favorites_only: false,
+            whole_word: false,
+        };
+        let batches = SearchJob::start(index.clone(), request, Arc::new(Mutex::new(ShardedCache::default()))).wait();
+        let found: usize = batches
+            .iter()
+            .map(|batch| match batch {
+                SearchBatch::Results(results) => results.len(),
+                SearchBatch::Finished(summary) => {
+                    assert!(summary.is_ok());
+                    0
+                }
+            })
+            .sum();
+        assert_eq!(found, 1);
+        index.search_path_prefix("/home/me/divorce", 0, 10).unwrap();
+        assert!(!config.add_saved_search(SECRET, None));
+
+        let logged = log.text();
+        drop(log);
+        assert!(logged.contains(HIDDEN_QUERY));
+        assert!(!logged.contains("divorce"));
+
+        assert!(config.saved_searches.is_empty());
+        let persisted = serde_json::to_string(&config).unwrap();
+        assert!(!persisted.contains("divorce"));
+    }
+}
@@ -0,0 +1,806 @@
+use crate::dates::{day_start, days_from_civil, days_in_month, local_day};
+use crate::error::{FlashFindError, Result};
+use crate::fold::fold;
+use crate::index::{folder_key, normalize_for_prefix, parse_query, ParsedQuery, FULL_PATH_PREFIX};
+use regex::{Regex, RegexBuilder};
+use std::ops::Range;
+use std::path::Path;
+
+/// Prefix of a file size filter, as in `size:>100mb`
+pub const SIZE_PREFIX: &str = "size:";
+
+/// Prefix of a modification date filter, as in `modified:>2024-01-01`
+pub const MODIFIED_PREFIX: &str = "modified:";
+
+/// Prefix of a folder scope, as in `in:Downloads installer`
+pub const IN_PREFIX: &str = "in:";
+
+/// Size units, binary like `format_size`; a bare number is bytes
+const SIZE_UNITS: [(&str, u64); 5] = [("tb", 1 << 40), ("gb", 1 << 30), ("mb", 1 << 20), ("kb", 1 << 10), ("b", 1)];
+
+/// Operator keywords; only these exact uppercase words are operators, so
+/// "salt and pepper" still searches for the word "and"
+const AND: &str = "AND";
+const OR: &str = "OR";
+const NOT: &str = "NOT";
+
+/// A boolean query, in OR-of-ANDs form
+///
+/// Precedence is NOT, then AND, then OR, so `a OR b AND NOT c` reads as
+/// `a OR (b AND (NOT c))`. Terms next to each other with no operator are
+/// ANDed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoolQuery {
+    /// A file matches if it matches any one of these
+    pub any_of: Vec<Conjunction>,
+}
+
+/// Terms that must all match (`include`) while none of `exclude` does
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Conjunction {
+    /// Never empty: a conjunction of only NOT terms is rejected when parsing
+    pub include: Vec<ParsedQuery>,
+    pub exclude: Vec<ParsedQuery>,
+}
+
+impl Conjunction {
+    fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+}
+
+/// A folder an `in:` clause restricts results to
+///
+/// An absolute path (`C:\Users`, `/home`) matches files under it; any
+/// other text matches files whose folder path contains it, so `in:Downloads`
+/// covers every Downloads folder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FolderScope {
+    /// Lowercased with `\` separators, without a trailing separator
+    needle: String,
+    anchored: bool,
+    /// The folder as typed after `in:`, for explanations
+    text: String,
+}
+
+impl FolderScope {
+    pub fn new(text: &str) -> FolderScope {
+        let needle = normalize_for_prefix(text.trim());
+        let anchored = needle.starts_with('\\') || needle.as_bytes().get(1) == Some(&b':');
+        FolderScope {
+            needle: needle.trim_end_matches('\\').to_string(),
+            anchored,
+            text: text.trim().to_string(),
+        }
+    }
+
+    /// Whether the folder holding `path` is in scope
+    pub fn contains(&self, path: &Path) -> bool {
+        folder_key(path).is_some_and(|folder| self.contains_folder(&folder))
+    }
+
+    /// Whether a folder, given as its `folder_key`, is in scope
+    pub fn contains_folder(&self, folder: &str) -> bool {
+        if !self.anchored {
+            return folder.contains(self.needle.as_str());
+        }
+        folder
+            .strip_prefix(self.needle.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('\\'))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+/// Pull every `in:` clause out of a query, returning the folders and the
+/// remaining query text, or `None` if there are no `in:` clauses
+///
+/// A quoted folder may hold spaces, as in `in:"My Documents"`. A bare `in:`
+/// adds no folder. Quoted phrases and `path:"..."` words are kept whole.
+pub fn split_folder_scopes(query: &str) -> Option<Result<(Vec<FolderScope>, String)>> {
+    let mut folders = Vec::new();
+    let mut rest = Vec::new();
+    let mut found = false;
+    let mut text = query.trim_start();
+
+    while !text.is_empty() {
+        let end = if let Some(quoted) = text.strip_prefix('"') {
+            // An unbalanced quote is left for the boolean parser to report
+            quoted.find('"').map_or(text.len(), |close| close + 2)
+        } else if let Some(end) = quoted_operand(query, text, IN_PREFIX) {
+            match end {
+                Ok(end) => end,
+                Err(e) => return Some(Err(e)),
+            }
+        } else {
+            match quoted_operand(query, text, FULL_PATH_PREFIX) {
+                Some(end) => end.unwrap_or(text.len()),
+                None => text.find(char::is_whitespace).unwrap_or(text.len()),
+            }
+        };
+        let word = &text[..end];
+        match word.get(..IN_PREFIX.len()) {
+            Some(prefix) if prefix.eq_ignore_ascii_case(IN_PREFIX) => {
+                found = true;
+                let folder = word[IN_PREFIX.len()..].trim_matches('"');
+                if !folder.trim().is_empty() {
+                    folders.push(FolderScope::new(folder));
+                }
+            }
+            _ => rest.push(word),
+        }
+        text = text[end..].trim_start();
+    }
+    found.then(|| Ok((folders, rest.join(" "))))
+}
+
+/// An inclusive file size range from a `size:` term
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeFilter {
+    pub min: u64,
+    pub max: u64,
+    /// The filter as typed after `size:`, lowercased, for explanations
+    text: String,
+}
+
+impl SizeFilter {
+    /// Parse the text after `size:`: `>X`, `>=X`, `<X`, `<=X`, `X` or `A..B`
+    ///
+    /// Either end of a range may be left open, as in `1gb..`.
+    pub fn parse(text: &str) -> Result<SizeFilter> {
+        let text = text.trim().to_lowercase();
+        let bad = || invalid(&format!("Cannot read size \"{}\"; try size:>10mb, size:<4kb or size:1gb..5gb", text));
+        let value = |v: &str| parse_size(v).ok_or_else(bad);
+
+        let (min, max) = if let Some(v) = text.strip_prefix(">=") {
+            (value(v)?, u64::MAX)
+        } else if let Some(v) = text.strip_prefix('>') {
+            (value(v)?.checked_add(1).ok_or_else(bad)?, u64::MAX)
+        } else if let Some(v) = text.strip_prefix("<=") {
+            (0, value(v)?)
+        } else if let Some(v) = text.strip_prefix('<') {
+            (0, value(v)?.checked_sub(1).ok_or_else(bad)?)
+        } else if let Some((low, high)) = text.split_once("..") {
+            if low.trim().is_empty() && high.trim().is_empty() {
+                return Err(bad());
+            }
+            let low = if low.trim().is_empty() { 0 } else { value(low)? };
+            let high = if high.trim().is_empty() { u64::MAX } else { value(high)? };
+            (low, high)
+        } else {
+            let exact = value(text.strip_prefix('=').unwrap_or(&text))?;
+            (exact, exact)
+        };
+
+        if min > max {
+            return Err(invalid(&format!("size:{} matches no file size", text)));
+        }
+        Ok(SizeFilter { min, max, text })
+    }
+
+    pub fn contains(&self, size: u64) -> bool {
+        (self.min..=self.max).contains(&size)
+    }
+
+    /// The filter as typed after `size:`
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+/// Bytes in a size such as `100mb`, `1.5gb` or `512`
+fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let (number, unit) = SIZE_UNITS
+        .iter()
+        .find_map(|&(suffix, unit)| text.strip_suffix(suffix).map(|number| (number.trim_end(), unit)))
+        .unwrap_or((text, 1));
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+
+    let bytes = number.parse::<f64>().ok()? * unit as f64;
+    (bytes.is_finite() && bytes < u64::MAX as f64).then_some(bytes.round() as u64)
+}
+
+/// An inclusive range of modification times (Unix seconds) from a
+/// `modified:` term
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateFilter {
+    pub from: i64,
+    pub to: i64,
+    /// The filter as typed after `modified:`, lowercased, for explanations
+    text: String,
+}
+
+impl DateFilter {
+    /// Parse the text after `modified:`: a period, `>P`, `>=P`, `<P`, `<=P`
+    /// or `P..Q`, where either end of a range may be left open
+    ///
+    /// A period is `today`, `yesterday`, `lastNdays` (today and the N-1 days
+    /// before it), or a `YYYY`, `YYYY-MM` or `YYYY-MM-DD` date. Days run
+    /// midnight to midnight in the time zone `utc_offset` seconds ahead of
+    /// UTC, and relative periods count back from `now`.
+    pub fn parse(text: &str, now: i64, utc_offset: i64) -> Result<DateFilter> {
+        let text = text.trim().to_lowercase();
+        let bad = || {
+            invalid(&format!(
+                "Cannot read date \"{}\"; try modified:today, modified:last7days or modified:>2024-01-01",
+                text
+            ))
+        };
+        let period = |v: &str| parse_period(v.trim(), now, utc_offset).ok_or_else(bad);
+
+        let (from, to) = if let Some(v) = text.strip_prefix(">=") {
+            (period(v)?.0, i64::MAX)
+        } else if let Some(v) = text.strip_prefix('>') {
+            (period(v)?.1 + 1, i64::MAX)
+        } else if let Some(v) = text.strip_prefix("<=") {
+            (i64::MIN, period(v)?.1)
+        } else if let Some(v) = text.strip_prefix('<') {
+            (i64::MIN, period(v)?.0 - 1)
+        } else if let Some((low, high)) = text.split_once("..") {
+            if low.trim().is_empty() && high.trim().is_empty() {
+                return Err(bad());
+            }
+            let from = if low.trim().is_empty() { i64::MIN } else { period(low)?.0 };
+            let to = if high.trim().is_empty() { i64::MAX } else { period(high)?.1 };
+            (from, to)
+        } else {
+            period(&text)?
+        };
+
+        if from > to {
+            return Err(invalid(&format!("modified:{} matches no date", text)));
+        }
+        Ok(DateFilter { from, to, text })
+    }
+
+    pub fn contains(&self, modified: i64) -> bool {
+        (self.from..=self.to).contains(&modified)
+    }
+
+    /// The filter as typed after `modified:`
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+/// First and last second of a named period or calendar date
+fn parse_period(text: &str, now: i64, utc_offset: i64) -> Option<(i64, i64)> {
+    let today = local_day(now, utc_offset);
+    let days = |first: i64, end: i64| (day_start(first, utc_offset), day_start(end, utc_offset) - 1);
+
+    match text {
+        "today" => return Some(days(today, today + 1)),
+        "yesterday" => return Some(days(today - 1, today)),
+        _ => {}
+    }
+    if let Some(count) = text.strip_prefix("last") {
+        let count = count.strip_suffix("days").or_else(|| count.strip_suffix("day"))?;
+        let count: i64 = count.parse().ok().filter(|&n| (1..=100_000).contains(&n))?;
+        return Some(days(today - (count - 1), today + 1));
+    }
+
+    let parts: Vec<&str> = text.split('-').collect();
+    let number = |part: &str, digits: std::ops::RangeInclusive<usize>| {
+        (digits.contains(&part.len()) && part.chars().all(|c| c.is_ascii_digit())).then(|| part.parse::<u32>().ok())?
+    };
+    let year = i64::from(number(parts[0], 4..=4)?);
+    match parts[1..] {
+        [] => Some(days(days_from_civil(year, 1, 1), days_from_civil(year + 1, 1, 1))),
+        [month] => {
+            let month = number(month, 1..=2).filter(|m| (1..=12).contains(m))?;
+            let first = days_from_civil(year, month, 1);
+            Some(days(first, first + i64::from(days_in_month(year, month))))
+        }
+        [month, day] => {
+            let month = number(month, 1..=2).filter(|m| (1..=12).contains(m))?;
+            let day = number(day, 1..=2).filter(|&d| d >= 1 && d <= days_in_month(year, month))?;
+            let first = days_from_civil(year, month, day);
+            Some(days(first, first + 1))
+        }
+        _ => None,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    And,
+    Or,
+    Not,
+    /// A bare word, parsed like a single-term query
+    Word(String),
+    /// A `"quoted phrase"`, matched literally as filename text
+    Phrase(String),
+}
+
+/// Split a query into words, quoted phrases and operators, each with the
+/// character position it starts at
+fn tokenize(query: &str) -> Result<Vec<(usize, Token)>> {
+    let mut tokens = Vec::new();
+    let mut rest = query.trim_start();
+
+    while !rest.is_empty() {
+        let at = column(query, rest);
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').ok_or_else(|| unbalanced_quote(at))?;
+            if !quoted[..end].trim().is_empty() {
+                tokens.push((at, Token::Phrase(quoted[..end].to_string())));
+            }
+            rest = &quoted[end + 1..];
+        } else {
+            let (end, word) = match quoted_operand(query, rest, FULL_PATH_PREFIX) {
+                Some(end) => {
+                    let end = end?;
+                    (end, rest[..end].to_string())
+                }
+                // `.jpg | .png` is one word, spaces dropped
+                None => {
+                    let end = word_end(rest);
+                    (end, rest[..end].split_whitespace().collect::<String>())
+                }
+            };
+            let token = match word.as_str() {
+                AND => Token::And,
+                OR => Token::Or,
+                NOT => Token::Not,
+                word => match excluded_extension(word) {
+                    // `!.log` reads as `NOT .log`
+                    Some(ext) => {
+                        tokens.push((at, Token::Not));
+                        Token::Word(ext.to_string())
+                    }
+                    None => Token::Word(word.to_string()),
+                },
+            };
+            tokens.push((at, token));
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+/// End of the word `rest` starts with; an extension list spaced out
+/// around its pipes, as in `.jpg | .png`, runs on to its last extension
+pub fn word_end(rest: &str) -> usize {
+    let mut end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    if !rest.trim_start_matches(['!', '-']).starts_with('.') {
+        return end;
+    }
+    loop {
+        let after = rest[end..].trim_start();
+        if after.is_empty() || !(rest[..end].ends_with('|') || after.starts_with('|')) {
+            return end;
+        }
+        end = rest.len() - after.len() + after.find(char::is_whitespace).unwrap_or(after.len());
+    }
+}
+
+/// The `.ext` (or `.ext|.ext` list) of a `!.ext` or `-.ext` word, which
+/// excludes those extensions
+fn excluded_extension(word: &str) -> Option<&str> {
+    let ext = word.strip_prefix(['!', '-'])?;
+    (ext.starts_with('.') && matches!(parse_query(ext), ParsedQuery::Extension(_) | ParsedQuery::Extensions(_))).then_some(ext)
+}
+
+/// 1-based character position in `query` where its suffix `rest` starts,
+/// as error messages point at it
+fn column(query: &str, rest: &str) -> usize {
+    query[..query.len() - rest.len()].chars().count() + 1
+}
+
+/// End of a leading `path:"quoted phrase"` (or other `operator:"..."`)
+/// word of `rest`, spaces and all; `rest` is a suffix of `query`
+fn quoted_operand(query: &str, rest: &str, operator: &str) -> Option<Result<usize>> {
+    let prefix = rest.get(..operator.len())?;
+    let quoted = rest[operator.len()..].strip_prefix('"')?;
+    if !prefix.eq_ignore_ascii_case(operator) {
+        return None;
+    }
+    let start = operator.len() + 1;
+    let quote_at = column(query, rest) + operator.chars().count();
+    Some(quoted.find('"').map(|close| start + close + 1).ok_or_else(|| unbalanced_quote(quote_at)))
+}
+
+/// Parse `query` as a boolean query, or `None` if it is a single plain term
+///
+/// A query becomes boolean once it has more than one term, an operator or a
+/// quoted phrase. Malformed queries (an unbalanced quote, a dangling
+/// operator, a term that cannot be combined, only NOT terms) are an
+/// `InvalidQuery` error.
+pub fn parse_boolean(query: &str) -> Option<Result<BoolQuery>> {
+    let tokens = match tokenize(query) {
+        Ok(tokens) => tokens,
+        Err(e) => return Some(Err(e)),
+    };
+    if matches!(tokens.as_slice(), [] | [(_, Token::Word(_))]) {
+        return None;
+    }
+    Some(build(tokens))
+}
+
+fn build(tokens: Vec<(usize, Token)>) -> Result<BoolQuery> {
+    let mut any_of = Vec::new();
+    let mut current = Conjunction::default();
+    // Position of the NOT still waiting for its term
+    let mut negate: Option<usize> = None;
+    // The AND or OR still waiting for its right-hand term, and its position
+    let mut dangling: Option<(&str, usize)> = None;
+
+    for (at, token) in tokens {
+        let term = match token {
+            Token::And | Token::Or | Token::Not if negate.is_some() => return Err(dangling_not(negate.unwrap_or(at))),
+            Token::And if current.is_empty() || dangling.is_some() => return Err(needs_terms(AND, at)),
+            Token::Or if current.is_empty() || dangling.is_some() => return Err(needs_terms(OR, at)),
+            Token::And => {
+                dangling = Some((AND, at));
+                continue;
+            }
+            Token::Or => {
+                any_of.push(std::mem::take(&mut current));
+                dangling = Some((OR, at));
+                continue;
+            }
+            Token::Not => {
+                negate = Some(at);
+                continue;
+            }
+            Token::Word(word) => parse_term(&word, at)?,
+            Token::Phrase(phrase) => ParsedQuery::Filename(fold(&phrase)),
+        };
+
+        if negate.take().is_some() {
+            current.exclude.push(term);
+        } else {
+            current.include.push(term);
+        }
+        dangling = None;
+    }
+
+    if let Some(at) = negate {
+        return Err(dangling_not(at));
+    }
+    if let Some((operator, at)) = dangling {
+        return Err(needs_terms(operator, at));
+    }
+    any_of.push(current);
+
+    if any_of.iter().any(|c| c.include.is_empty()) {
+        return Err(invalid("NOT only excludes files; add a term to search for, e.g. invoice NOT draft"));
+    }
+    Ok(BoolQuery { any_of })
+}
+
+/// One bare word, which may itself be a `path:`, `size:`, `modified:`,
+/// `kind:`, `^` prefix, single `words:` word, extension (or extension
+/// list) or wildcard query,
+/// starting at character `at`
+fn parse_term(word: &str, at: usize) -> Result<ParsedQuery> {
+    match parse_query(word) {
+        term @ (ParsedQuery::FullPath(_)
+        | ParsedQuery::Size(_)
+        | ParsedQuery::Modified(_)
+        | ParsedQuery::Extension(_)
+        | ParsedQuery::Extensions(_)
+        | ParsedQuery::Kind { .. }
+        | ParsedQuery::Hidden
+        | ParsedQuery::StartsWith(_)
+        | ParsedQuery::Words(_)
+        | ParsedQuery::Glob(_)
+        | ParsedQuery::Filename(_)) => Ok(term),
+        ParsedQuery::Invalid(reason) => Err(invalid(&format!("\"{}\" at character {}: {}", word, at, reason))),
+        _ => Err(invalid(&format!("\"{}\" at character {} cannot be combined with other terms", word, at))),
+    }
+}
+
+fn unbalanced_quote(at: usize) -> FlashFindError {
+    invalid(&format!("Unbalanced quote at character {}: close the phrase with another \"", at))
+}
+
+fn needs_terms(operator: &str, at: usize) -> FlashFindError {
+    invalid(&format!("{} at character {} needs a term on each side", operator, at))
+}
+
+fn dangling_not(at: usize) -> FlashFindError {
+    invalid(&format!("NOT at character {} must be followed by a term", at))
+}
+
+/// A regular expression from a `re:`, `repath:` or `/…/` query, matched
+/// ignoring case anywhere in the filename or, for `repath:`, the full path
+#[derive(Debug, Clone)]
+pub struct RegexFilter {
+    regex: Regex,
+    pub full_path: bool,
+}
+
+impl RegexFilter {
+    /// Compile `pattern`; a syntax error is an `InvalidQuery` naming the problem
+    pub fn parse(pattern: &str, full_path: bool) -> Result<RegexFilter> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| match e {
+                regex::Error::Syntax(detail) => {
+                    // The detail draws a caret under the pattern; its last line says what is wrong
+                    let reason = detail.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or_default();
+                    invalid(&format!("Invalid regular expression: {}", reason.trim().trim_start_matches("error: ")))
+                }
+                e => invalid(&format!("Invalid regular expression: {}", e)),
+            })?;
+        Ok(RegexFilter { regex, full_path })
+    }
+
+    /// Check a path's filename, or the whole path for `repath:`
+    pub fn matches(&self, path: &Path) -> bool {
+        if self.full_path {
+            self.regex.is_match(&path.to_string_lossy())
+        } else {
+            path.file_name().is_some_and(|name| self.is_match(&name.to_string_lossy()))
+        }
+    }
+
+    /// Check a filename, or any text, as written
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+
+    /// Byte ranges of each non-empty match in `text`
+    pub fn find_ranges<'a>(&'a self, text: &'a str) -> impl Iterator<Item = Range<usize>> + 'a {
+        self.regex.find_iter(text).filter(|m| !m.is_empty()).map(|m| m.range())
+    }
+
+    /// The pattern as typed
+    pub fn as_str(&self) -> &str {
+        self.regex.as_str()
+    }
+}
+
+impl PartialEq for RegexFilter {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str() && self.full_path == other.full_path
+    }
+}
+
+fn invalid(reason: &str) -> FlashFindError {
+    FlashFindError::InvalidQuery(reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(q: &str) -> ParsedQuery {
+        ParsedQuery::Filename(q.to_string())
+    }
+
+    fn and(include: &[&str], exclude: &[&str]) -> Conjunction {
+        Conjunction {
+            include: include.iter().map(|q| name(q)).collect(),
+            exclude: exclude.iter().map(|q| name(q)).collect(),
+        }
+    }
+
+    fn parsed(query: &str) -> BoolQuery {
+        parse_boolean(query).expect("boolean query").expect("valid query")
+    }
+
+    fn error(query: &str) -> String {
+        parse_boolean(query).expect("boolean query").unwrap_err().to_string()
+    }
+
+    fn size(text: &str) -> (u64, u64) {
+        let filter = SizeFilter::parse(text).unwrap();
+        (filter.min, filter.max)
+    }
+
+    #[test]
+    fn test_size_filter_operators_and_units() {
+        const KB: u64 = 1024;
+        const MB: u64 = 1024 * KB;
+        const GB: u64 = 1024 * MB;
+
+        assert_eq!(size(">100mb"), (100 * MB + 1, u64::MAX));
+        assert_eq!(size(">=100MB"), (100 * MB, u64::MAX));
+        assert_eq!(size("<4kb"), (0, 4 * KB - 1));
+        assert_eq!(size("<=4kb"), (0, 4 * KB));
+        assert_eq!(size("1gb..5gb"), (GB, 5 * GB));
+        assert_eq!(size("1gb.."), (GB, u64::MAX));
+        assert_eq!(size("..512b"), (0, 512));
+        assert_eq!(size("512"), (512, 512));
+        assert_eq!(size("=1.5kb"), (1536, 1536));
+        assert_eq!(size(" 2 mb "), (2 * MB, 2 * MB));
+
+        let filter = SizeFilter::parse(">1kb").unwrap();
+        assert!(filter.contains(1025) && !filter.contains(1024));
+        assert_eq!(filter.as_str(), ">1kb");
+    }
+
+    #[test]
+    fn test_bad_size_filters_are_rejected() {
+        for text in ["", "..", ">", "big", "10xb", "-5mb", "1e3", "<0", "5gb..1gb"] {
+            assert!(SizeFilter::parse(text).is_err(), "{:?} should not parse", text);
+        }
+        assert!(SizeFilter::parse("huge").unwrap_err().to_string().contains("try size:>10mb"));
+    }
+
+    /// 2024-06-15 14:30 UTC, a Saturday
+    const NOW: i64 = 1_718_461_800;
+    const DAY: i64 = 86_400;
+
+    fn dates(text: &str, utc_offset: i64) -> (i64, i64) {
+        let filter = DateFilter::parse(text, NOW, utc_offset).unwrap();
+        (filter.from, filter.to)
+    }
+
+    #[test]
+    fn test_date_filter_periods() {
+        let june_15 = days_from_civil(2024, 6, 15) * DAY;
+        assert_eq!(june_15 + 14 * 3600 + 30 * 60, NOW);
+
+        assert_eq!(dates("today", 0), (june_15, june_15 + DAY - 1));
+        assert_eq!(dates("Yesterday", 0), (june_15 - DAY, june_15 - 1));
+        assert_eq!(dates("last7days", 0), (june_15 - 6 * DAY, june_15 + DAY - 1));
+        assert_eq!(dates("last1day", 0), dates("today", 0));
+        assert_eq!(dates("2024-06-01", 0), (june_15 - 14 * DAY, june_15 - 13 * DAY - 1));
+        assert_eq!(dates("2024-2", 0).1 - dates("2024-2", 0).0 + 1, 29 * DAY);
+        assert_eq!(dates("2023", 0).1 + 1, days_from_civil(2024, 1, 1) * DAY);
+
+        // Ten hours ahead of UTC it is already late on the 16th, local time
+        let local_june_16 = june_15 + DAY - 10 * 3600;
+        assert_eq!(dates("today", 10 * 3600), (local_june_16, local_june_16 + DAY - 1));
+    }
+
+    #[test]
+    fn test_date_filter_comparisons_and_ranges() {
+        let june_1 = days_from_civil(2024, 6, 1) * DAY;
+        assert_eq!(dates(">2024-06-01", 0), (june_1 + DAY, i64::MAX));
+        assert_eq!(dates(">=2024-06-01", 0), (june_1, i64::MAX));
+        assert_eq!(dates("<2024-06-01", 0), (i64::MIN, june_1 - 1));
+        assert_eq!(dates("<=2024-06", 0), (i64::MIN, june_1 + 30 * DAY - 1));
+        assert_eq!(dates("2024-01-01..2024-03-31", 0), (days_from_civil(2024, 1, 1) * DAY, days_from_civil(2024, 4, 1) * DAY - 1));
+        assert_eq!(dates("2024..", 0).1, i64::MAX);
+        assert_eq!(dates("..yesterday", 0), (i64::MIN, days_from_civil(2024, 6, 15) * DAY - 1));
+
+        let filter = DateFilter::parse(">=yesterday", NOW, 0).unwrap();
+        assert!(filter.contains(NOW) && !filter.contains(NOW - 2 * DAY));
+        assert_eq!(filter.as_str(), ">=yesterday");
+    }
+
+    #[test]
+    fn test_bad_date_filters_are_rejected() {
+        for text in ["", "..", "soon", "last0days", "lastweek", "24-01-01", "2024-13", "2023-02-29", "2024-06-01-01", "2025..2024"] {
+            assert!(DateFilter::parse(text, NOW, 0).is_err(), "{:?} should not parse", text);
+        }
+        assert!(DateFilter::parse("soon", NOW, 0).unwrap_err().to_string().contains("try modified:today"));
+    }
+
+    #[test]
+    fn test_single_terms_are_not_boolean() {
+        assert!(parse_boolean("invoice").is_none());
+        assert!(parse_boolean("  .pdf ").is_none());
+        assert!(parse_boolean("").is_none());
+    }
+
+    #[test]
+    fn test_precedence_not_then_and_then_or() {
+        assert_eq!(parsed("invoice AND 2024 NOT draft").any_of, vec![and(&["invoice", "2024"], &["draft"])]);
+        assert_eq!(parsed("budget OR forecast").any_of, vec![and(&["budget"], &[]), and(&["forecast"], &[])]);
+        assert_eq!(parsed("a OR b AND c").any_of, vec![and(&["a"], &[]), and(&["b", "c"], &[])]);
+        assert_eq!(parsed("a b OR NOT c d").any_of, vec![and(&["a", "b"], &[]), and(&["d"], &["c"])]);
+    }
+
+    #[test]
+    fn test_implicit_and_phrases_and_lowercase_words() {
+        assert_eq!(parsed("Invoice 2024").any_of, vec![and(&["invoice", "2024"], &[])]);
+        assert_eq!(parsed("salt and pepper").any_of, vec![and(&["salt", "and", "pepper"], &[])]);
+        assert_eq!(parsed("\"Annual Report\" NOT \"draft\"").any_of, vec![and(&["annual report"], &["draft"])]);
+        assert_eq!(parsed("\"v1.2 - final.tar\"").any_of, vec![and(&["v1.2 - final.tar"], &[])]);
+        assert_eq!(parsed("\"  two  spaces \"").any_of, vec![and(&["  two  spaces "], &[])]);
+        assert_eq!(parsed("\"AND\"").any_of, vec![and(&["and"], &[])]);
+        assert_eq!(
+            parsed("path:\"My Docs\" NOT path:Old").any_of,
+            vec![Conjunction {
+                include: vec![ParsedQuery::FullPath("my docs".into())],
+                exclude: vec![ParsedQuery::FullPath("old".into())],
+            }]
+        );
+        assert_eq!(
+            parsed("report .PDF").any_of,
+            vec![Conjunction { include: vec![name("report"), ParsedQuery::Extension("pdf".into())], exclude: vec![] }]
+        );
+    }
+
+    #[test]
+    fn test_not_only_and_malformed_queries_are_rejected() {
+        assert!(error("NOT draft").contains("NOT only excludes files"));
+        assert!(error("invoice OR NOT draft").contains("NOT only excludes files"));
+        assert_eq!(error("invoice AND"), "Invalid query: AND at character 9 needs a term on each side");
+        assert_eq!(error("OR invoice"), "Invalid query: OR at character 1 needs a term on each side");
+        assert_eq!(error("a AND OR b"), "Invalid query: OR at character 7 needs a term on each side");
+        assert_eq!(error("a NOT NOT b"), "Invalid query: NOT at character 3 must be followed by a term");
+        assert_eq!(error("a NOT"), "Invalid query: NOT at character 3 must be followed by a term");
+        assert_eq!(error("a NOT OR b"), "Invalid query: NOT at character 3 must be followed by a term");
+        assert!(error("\"annual report").contains("Unbalanced quote at character 1"));
+        assert!(error("report \"draft").contains("Unbalanced quote at character 8"));
+        assert!(error("résumé path:\"my docs").contains("Unbalanced quote at character 13"));
+        assert!(error("report C:").contains("\"C:\" at character 8 cannot be combined"));
+        assert!(error("report size:big").contains("\"size:big\" at character 8: Cannot read size"));
+    }
+
+    #[test]
+    fn test_bang_or_dash_extension_excludes_it() {
+        let ext = |e: &str| ParsedQuery::Extension(e.to_string());
+        assert_eq!(
+            parsed("error !.log -.MAP").any_of,
+            vec![Conjunction { include: vec![name("error")], exclude: vec![ext("log"), ext("map")] }]
+        );
+        assert_eq!(
+            parsed("backup -.tar.gz").any_of,
+            vec![Conjunction { include: vec![name("backup")], exclude: vec![ext("tar.gz")] }]
+        );
+        // Only an extension is excluded this way; other words keep their dash
+        assert_eq!(parsed("x-ray -draft").any_of, vec![and(&["x-ray", "-draft"], &[])]);
+        assert_eq!(parsed("error !.").any_of, vec![and(&["error", "!."], &[])]);
+        assert!(error("!.log").contains("NOT only excludes files"));
+        assert_eq!(error("error NOT !.log"), "Invalid query: NOT at character 7 must be followed by a term");
+    }
+
+    #[test]
+    fn test_extension_lists_are_one_term() {
+        let list = |exts: &[&str]| ParsedQuery::Extensions(exts.iter().map(|e| e.to_string()).collect());
+        assert_eq!(
+            parsed("vacation .JPG|.png|.webp").any_of,
+            vec![Conjunction { include: vec![name("vacation"), list(&["jpg", "png", "webp"])], exclude: vec![] }]
+        );
+        assert_eq!(
+            parsed(".jpg | .png |.jpg  vacation").any_of,
+            vec![Conjunction { include: vec![list(&["jpg", "png"]), name("vacation")], exclude: vec![] }]
+        );
+        assert_eq!(
+            parsed("report !.tmp | .bak").any_of,
+            vec![Conjunction { include: vec![name("report")], exclude: vec![list(&["tmp", "bak"])] }]
+        );
+        assert_eq!(parse_query(" .jpg |  .png "), list(&["jpg", "png"]));
+        assert!(error("photo .jpg||.png").contains("Empty alternative in \".jpg||.png\""));
+        assert!(error("photo .jpg |").contains("Empty alternative"));
+        assert!(error("photo .jpg|beach").contains("\"beach\" in \".jpg|beach\" is not an extension"));
+    }
+
+    #[test]
+    fn test_in_clauses_are_split_from_the_query() {
+        let split = |q: &str| {
+            let (folders, rest) = split_folder_scopes(q).expect("in: clause").expect("valid query");
+            (folders.iter().map(|f| f.as_str().to_string()).collect::<Vec<_>>(), rest)
+        };
+        assert_eq!(split("in:Downloads installer"), (vec!["Downloads".to_string()], "installer".to_string()));
+        assert_eq!(
+            split("report IN:\"My Documents\" in:work \"draft one\""),
+            (vec!["My Documents".to_string(), "work".to_string()], "report \"draft one\"".to_string())
+        );
+        assert_eq!(split("in: invoice"), (vec![], "invoice".to_string()));
+        // Only a word starting with in: is a clause
+        assert!(split_folder_scopes("login.txt main:rs").is_none());
+        assert!(split_folder_scopes("path:\"in:x\" report").is_none());
+        assert!(split_folder_scopes("in:\"My Documents").unwrap().unwrap_err().to_string().contains("Unbalanced quote at character 4"));
+    }
+
+    #[test]
+    fn test_folder_scope_contains_or_anchors() {
+        let path = Path::new("C:\\Users\\ana\\Downloads\\Setup\\installer.exe");
+        assert!(FolderScope::new("downloads").contains(path));
+        assert!(FolderScope::new("loads\\set").contains(path));
+        assert!(FolderScope::new("C:\\Users").contains(path));
+        assert!(FolderScope::new("c:/users/ana/downloads/setup/").contains(path));
+        // Anchored folders match whole components from the root
+        assert!(!FolderScope::new("C:\\User").contains(path));
+        assert!(!FolderScope::new("D:\\Users").contains(path));
+        // The filename itself is not part of the folder
+        assert!(!FolderScope::new("installer").contains(path));
+        assert!(FolderScope::new("/home/ana").contains(Path::new("/home/ana/notes.txt")));
+        assert!(!FolderScope::new("/home/ana").contains(Path::new("/home/anabel/notes.txt")));
+    }
+}
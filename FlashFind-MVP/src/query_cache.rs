@@ -0,0 +1,126 @@
+use std::collections::VecDeque;
+
+/// Distinct queries whose results are kept
+pub const QUERY_CACHE_CAPACITY: usize = 16;
+
+/// Larger result sets are not kept; copying them back costs about as much
+/// as the memory they would hold
+pub const MAX_CACHED_IDS: usize = 200_000;
+
+/// Hits and misses of the result cache since launch
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Share of lookups answered from the cache, 0 before the first lookup
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// Final result ids of recent searches, least recently used dropped first
+///
+/// Each entry remembers the index generation it was computed at; once the
+/// index has changed it is stale and a lookup drops it. Keys must capture
+/// everything that shapes the results besides the index contents.
+#[derive(Debug, Default)]
+pub struct QueryCache {
+    /// Most recently used first
+    entries: VecDeque<CachedQuery>,
+    stats: CacheStats,
+}
+
+#[derive(Debug)]
+struct CachedQuery {
+    key: String,
+    generation: u64,
+    /// Result ids in display order
+    ids: Vec<u32>,
+    truncated: bool,
+}
+
+impl QueryCache {
+    /// The ids and truncation of `key`'s results at `generation`, counted
+    /// as a hit or a miss
+    pub fn get(&mut self, key: &str, generation: u64) -> Option<(Vec<u32>, bool)> {
+        let found = self.entries.iter().position(|entry| entry.key == key);
+        let Some(entry) = found.and_then(|at| self.entries.remove(at)) else {
+            self.stats.misses += 1;
+            return None;
+        };
+        if entry.generation != generation {
+            self.stats.misses += 1;
+            return None;
+        }
+        self.stats.hits += 1;
+        let result = (entry.ids.clone(), entry.truncated);
+        self.entries.push_front(entry);
+        Some(result)
+    }
+
+    /// Keep `key`'s results, unless there are more than `MAX_CACHED_IDS`
+    pub fn put(&mut self, key: String, generation: u64, ids: &[u32], truncated: bool) {
+        if ids.len() > MAX_CACHED_IDS {
+            return;
+        }
+        self.entries.retain(|entry| entry.key != key);
+        self.entries.push_front(CachedQuery { key, generation, ids: ids.to_vec(), truncated });
+        self.entries.truncate(QUERY_CACHE_CAPACITY);
+    }
+
+    /// Drop every entry, keeping the counters
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// An empty cache carrying on these counters
+    pub fn emptied(&self) -> Self {
+        Self { entries: VecDeque::new(), stats: self.stats }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_least_recently_used_entry_is_dropped() {
+        let mut cache = QueryCache::default();
+        for i in 0..QUERY_CACHE_CAPACITY as u32 {
+            cache.put(format!("q{}", i), 1, &[i], false);
+        }
+        // Using the oldest entry saves it from eviction
+        assert_eq!(cache.get("q0", 1), Some((vec![0], false)));
+        cache.put("new".to_string(), 1, &[99], true);
+        assert_eq!(cache.get("q1", 1), None);
+        assert_eq!(cache.get("q0", 1), Some((vec![0], false)));
+        assert_eq!(cache.get("new", 1), Some((vec![99], true)));
+        assert_eq!(cache.stats(), CacheStats { hits: 3, misses: 1 });
+    }
+
+    #[test]
+    fn test_stale_and_oversized_results_are_not_served() {
+        let mut cache = QueryCache::default();
+        cache.put("report".to_string(), 1, &[1, 2], false);
+        assert_eq!(cache.get("report", 2), None);
+        // The stale entry is gone, not just skipped
+        assert_eq!(cache.get("report", 1), None);
+
+        cache.put("big".to_string(), 1, &vec![0; MAX_CACHED_IDS + 1], false);
+        assert_eq!(cache.get("big", 1), None);
+        assert_eq!(cache.stats(), CacheStats { hits: 0, misses: 3 });
+        assert_eq!(cache.stats().hit_rate(), 0.0);
+    }
+}
@@ -0,0 +1,329 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::fold::fold;
+use crate::index::{match_tier, parse_query, MatchTier, ParsedQuery};
+use crate::query::FolderScope;
+
+/// Age at which the recency score has halved
+const RECENCY_HALF_LIFE_DAYS: f32 = 7.0;
+
+/// Weights combined into a result's relevance score
+///
+/// The match tier contributes one of the four tier weights, recency scales a
+/// score that halves after a week, and path depth scales `1 / components`, so a
+/// positive depth weight lists shallow paths first.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RankingProfile {
+    pub exact: f32,
+    pub prefix: f32,
+    pub token: f32,
+    pub substring: f32,
+    pub recency: f32,
+    pub path_depth: f32,
+}
+
+/// Match tier first, deepest paths last within a tier: exact stems, then
+/// prefixes, word starts and substrings
+pub const FILENAME_FOCUSED: RankingProfile = RankingProfile {
+    exact: 100.0,
+    prefix: 60.0,
+    token: 40.0,
+    substring: 10.0,
+    recency: 0.0,
+    path_depth: 20.0,
+};
+
+/// Shallow paths first: a match near the top of a tree outranks a better
+/// filename match buried deep inside it
+pub const PATH_FOCUSED: RankingProfile = RankingProfile {
+    exact: 30.0,
+    prefix: 25.0,
+    token: 20.0,
+    substring: 15.0,
+    recency: 0.0,
+    path_depth: 80.0,
+};
+
+/// Newest files first; the match tier only separates files of similar age
+pub const RECENCY_FOCUSED: RankingProfile = RankingProfile {
+    exact: 6.0,
+    prefix: 4.0,
+    token: 2.0,
+    substring: 1.0,
+    recency: 100.0,
+    path_depth: 0.0,
+};
+
+impl Default for RankingProfile {
+    fn default() -> Self {
+        FILENAME_FOCUSED
+    }
+}
+
+impl RankingProfile {
+    fn tier_weight(&self, tier: MatchTier) -> f32 {
+        match tier {
+            MatchTier::Exact => self.exact,
+            MatchTier::Prefix => self.prefix,
+            MatchTier::Token => self.token,
+            MatchTier::Substring => self.substring,
+        }
+    }
+}
+
+/// Ranking profile chosen in the results toolbar
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RankingPreset {
+    #[default]
+    Filename,
+    Path,
+    Recency,
+    /// User weights from `Config::custom_ranking`
+    Custom,
+}
+
+impl RankingPreset {
+    pub const ALL: [RankingPreset; 4] = [
+        RankingPreset::Filename,
+        RankingPreset::Path,
+        RankingPreset::Recency,
+        RankingPreset::Custom,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RankingPreset::Filename => "Filename first",
+            RankingPreset::Path => "Shallow paths first",
+            RankingPreset::Recency => "Newest first",
+            RankingPreset::Custom => "Custom",
+        }
+    }
+
+    /// Weights for this preset; `custom` backs the Custom choice
+    pub fn profile(self, custom: &RankingProfile) -> RankingProfile {
+        match self {
+            RankingPreset::Filename => FILENAME_FOCUSED,
+            RankingPreset::Path => PATH_FOCUSED,
+            RankingPreset::Recency => RECENCY_FOCUSED,
+            RankingPreset::Custom => *custom,
+        }
+    }
+}
+
+/// Order search results by relevance under `profile`, files under any of
+/// `favorites` first
+///
+/// Modification times are looked up only when the profile weighs recency:
+/// `stored` gives the indexed time (Unix seconds) if known. Nothing is
+/// stat'ed, so results without one count as old.
+pub fn rank_results(
+    paths: Vec<PathBuf>,
+    query: &str,
+    profile: &RankingProfile,
+    favorites: &[PathBuf],
+    stored: &(dyn Fn(&Path) -> Option<i64> + Sync),
+) -> Vec<PathBuf> {
+    let weighs_recency = profile.recency != 0.0;
+    let now = SystemTime::now();
+    let aged = paths
+        .into_par_iter()
+        .map(|path| {
+            let age = weighs_recency
+                .then(|| stored(&path))
+                .flatten()
+                .map(|seconds| now.duration_since(UNIX_EPOCH + Duration::from_secs(seconds.max(0) as u64)).unwrap_or_default());
+            (path, age)
+        })
+        .collect();
+    rank_aged(aged, query, profile, favorites)
+}
+
+/// Rank paths paired with their age; ties keep their incoming order
+fn rank_aged(aged: Vec<(PathBuf, Option<Duration>)>, query: &str, profile: &RankingProfile, favorites: &[PathBuf]) -> Vec<PathBuf> {
+    let query_name = filename_query(query);
+    let favorites: Vec<FolderScope> = favorites.iter().map(|folder| FolderScope::new(&folder.to_string_lossy())).collect();
+    let mut scored: Vec<(bool, bool, f32, PathBuf)> = aged
+        .into_par_iter()
+        .map(|(path, age)| {
+            let favorite = favorites.iter().any(|folder| folder.contains(&path));
+            let (named, score) = score(&path, query_name.as_deref(), profile, age);
+            (favorite, named, score, path)
+        })
+        .collect();
+    // A favorite outranks any score, whatever the weights, and a name holding
+    // the query outranks one matched otherwise (by its initials); stable, so
+    // equal scores keep the deterministic `compare_results` order
+    scored.par_sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)).then(b.2.total_cmp(&a.2)));
+    scored.into_iter().map(|(_, _, _, path)| path).collect()
+}
+
+/// The lowercased filename query, if the query matches on filenames at all
+///
+/// Boolean queries rank by their first filename term, `in:` queries by the
+/// query they scope.
+fn filename_query(query: &str) -> Option<String> {
+    parsed_filename_query(parse_query(query))
+}
+
+fn parsed_filename_query(query: ParsedQuery) -> Option<String> {
+    match query {
+        ParsedQuery::Filename(q) | ParsedQuery::WholeWord(q) => Some(q),
+        ParsedQuery::Boolean(query) => query.any_of.into_iter().flat_map(|c| c.include).find_map(|term| match term {
+            ParsedQuery::Filename(q) => Some(q),
+            _ => None,
+        }),
+        ParsedQuery::InFolder { query: Some(query), .. } => parsed_filename_query(*query),
+        _ => None,
+    }
+}
+
+/// Whether the filename contains the query (always, with no filename
+/// query), and the result's relevance; higher ranks first
+fn score(path: &Path, query: Option<&str>, profile: &RankingProfile, age: Option<Duration>) -> (bool, f32) {
+    // Split on both separators so Windows paths rank the same everywhere
+    let path_str = path.to_string_lossy().to_lowercase();
+    let components: Vec<&str> = path_str.split(['/', '\\']).filter(|c| !c.is_empty()).collect();
+    let name = fold(components.last().copied().unwrap_or_default());
+
+    let tier = query.and_then(|q| match_tier(&name, q));
+    let text = tier.map_or(0.0, |tier| profile.tier_weight(tier));
+    let recency = age.map_or(0.0, |age| {
+        let days = age.as_secs_f32() / 86_400.0;
+        1.0 / (1.0 + days / RECENCY_HALF_LIFE_DAYS)
+    });
+    let depth = 1.0 / components.len().max(1) as f32;
+
+    (query.is_none() || tier.is_some(), text + profile.recency * recency + profile.path_depth * depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86_400;
+
+    /// (path, age in days) shared by every profile test
+    fn fixtures() -> Vec<(PathBuf, u64)> {
+        [
+            ("C:\\src\\app\\modules\\deep\\report.rs", 300),
+            ("C:\\src\\report.rs", 200),
+            ("C:\\src\\reporting_tool.rs", 100),
+            ("C:\\archive\\2019\\old\\yearly_report_final.pdf", 1),
+            ("C:\\notes\\monthly report.txt", 30),
+        ]
+        .into_iter()
+        .map(|(path, days)| (PathBuf::from(path), days))
+        .collect()
+    }
+
+    fn rank_fixtures(profile: &RankingProfile) -> Vec<String> {
+        let aged = fixtures()
+            .into_iter()
+            .map(|(path, days)| (path, Some(Duration::from_secs(days * DAY))))
+            .collect();
+        rank_aged(aged, "report", profile, &[])
+            .into_iter()
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_filename_focused_ranks_exact_stems_first_deepest_last() {
+        assert_eq!(
+            rank_fixtures(&FILENAME_FOCUSED),
+            vec![
+                "C:\\src\\report.rs",
+                "C:\\src\\app\\modules\\deep\\report.rs",
+                "C:\\src\\reporting_tool.rs",
+                "C:\\notes\\monthly report.txt",
+                "C:\\archive\\2019\\old\\yearly_report_final.pdf",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_path_focused_ranks_shallow_paths_first() {
+        assert_eq!(
+            rank_fixtures(&PATH_FOCUSED),
+            vec![
+                "C:\\src\\report.rs",
+                "C:\\src\\reporting_tool.rs",
+                "C:\\notes\\monthly report.txt",
+                "C:\\src\\app\\modules\\deep\\report.rs",
+                "C:\\archive\\2019\\old\\yearly_report_final.pdf",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recency_focused_ranks_newest_first() {
+        assert_eq!(
+            rank_fixtures(&RECENCY_FOCUSED),
+            vec![
+                "C:\\archive\\2019\\old\\yearly_report_final.pdf",
+                "C:\\notes\\monthly report.txt",
+                "C:\\src\\reporting_tool.rs",
+                "C:\\src\\report.rs",
+                "C:\\src\\app\\modules\\deep\\report.rs",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ties_keep_incoming_order() {
+        let paths = vec![PathBuf::from("C:\\a\\x.pdf"), PathBuf::from("C:\\b\\x.pdf")];
+        assert_eq!(rank_results(paths.clone(), ".pdf", &FILENAME_FOCUSED, &[], &|_| None), paths);
+    }
+
+    #[test]
+    fn test_recency_uses_stored_dates() {
+        // Neither file exists, so only the stored dates can tell them apart
+        let old = PathBuf::from("C:\\gone\\report_2019.pdf");
+        let new = PathBuf::from("C:\\gone\\report_2024.pdf");
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+        let stored = |path: &Path| Some(if path == old.as_path() { now - 900 * DAY as i64 } else { now - DAY as i64 });
+        let ranked = rank_results(vec![old.clone(), new.clone()], "report", &RECENCY_FOCUSED, &[], &stored);
+        assert_eq!(ranked, [new, old]);
+    }
+
+    #[test]
+    fn test_favorite_folders_rank_first() {
+        let aged = fixtures().into_iter().map(|(path, _)| (path, None)).collect();
+        let favorites = [PathBuf::from("C:\\archive"), PathBuf::from("C:\\notes\\")];
+        let ranked: Vec<String> = rank_aged(aged, "report", &FILENAME_FOCUSED, &favorites)
+            .into_iter()
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            ranked,
+            vec![
+                "C:\\notes\\monthly report.txt",
+                "C:\\archive\\2019\\old\\yearly_report_final.pdf",
+                "C:\\src\\report.rs",
+                "C:\\src\\app\\modules\\deep\\report.rs",
+                "C:\\src\\reporting_tool.rs",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_names_without_the_query_rank_last() {
+        // Matched by its initials only, so even a shallow path comes after
+        let aged = vec![
+            (PathBuf::from("C:\\Really_Old_Ledger.xlsx"), None),
+            (PathBuf::from("C:\\archive\\2019\\old\\yearly_rol_final.pdf"), None),
+        ];
+        let ranked = rank_aged(aged, "rol", &PATH_FOCUSED, &[]);
+        assert_eq!(ranked[0], PathBuf::from("C:\\archive\\2019\\old\\yearly_rol_final.pdf"));
+    }
+
+    #[test]
+    fn test_custom_preset_uses_user_weights() {
+        let custom = RankingProfile { recency: 5.0, ..PATH_FOCUSED };
+        assert_eq!(RankingPreset::Custom.profile(&custom), custom);
+        assert_eq!(RankingPreset::Recency.profile(&custom), RECENCY_FOCUSED);
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether log output hides personal path components (on by default in release builds)
+static ENABLED: AtomicBool = AtomicBool::new(!cfg!(debug_assertions));
+
+/// Turn log path redaction on or off (from `Config::log_redaction`)
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Check whether log path redaction is active
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Wrap a path for logging; use at every log site instead of `path.display()`
+///
+/// With redaction on, only the drive (or root) and the top-level folder are
+/// kept. The rest becomes a short stable hash plus the extension, so the same
+/// file can still be correlated across log lines.
+pub fn redact<P: AsRef<Path> + ?Sized>(path: &P) -> Redacted<'_> {
+    Redacted(path.as_ref())
+}
+
+/// Display adapter returned by `redact`
+pub struct Redacted<'a>(&'a Path);
+
+impl fmt::Display for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_path(self.0, is_enabled()))
+    }
+}
+
+/// Render a path for logs, redacting it when `enabled`
+fn format_path(path: &Path, enabled: bool) -> String {
+    let path_str = path.to_string_lossy();
+    if !enabled {
+        return path_str.into_owned();
+    }
+
+    let separator = if path_str.contains('\\') { '\\' } else { '/' };
+    let parts: Vec<&str> = path_str.split(['/', '\\']).collect();
+
+    // Keep leading empty parts (root, UNC), a drive, and one top-level folder
+    let mut keep = 0;
+    while keep < parts.len() && parts[keep].is_empty() {
+        keep += 1;
+    }
+    if parts.get(keep).is_some_and(|p| is_drive(p)) {
+        keep += 1;
+    }
+    keep = (keep + 1).min(parts.len());
+
+    let hidden = &parts[keep..];
+    if hidden.iter().all(|p| p.is_empty()) {
+        return path_str.into_owned();
+    }
+
+    let mut hasher = DefaultHasher::new();
+    hidden.hash(&mut hasher);
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    let mut kept = parts[..keep].join(&separator.to_string());
+    kept.push(separator);
+    format!("{}…#{:08x}{}", kept, hasher.finish() as u32, extension)
+}
+
+/// Check for a drive component such as `C:`
+fn is_drive(part: &str) -> bool {
+    let bytes = part.as_bytes();
+    bytes.len() == 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::activity::ActivityLog;
+    use crate::archive::{extract_member, member_path};
+    use crate::config::Config;
+    use crate::error::FlashFindError;
+    use crate::index::{EntryMeta, FileIndex, PreparedBatch};
+    use crate::indexer::Indexer;
+    use crate::paths::OpenPolicy;
+    use crate::persistence::SaveCoordinator;
+    use crate::sharded::ShardedIndex;
+    use crate::test_dir::TestDir;
+    use crate::test_log::CapturedLog;
+    use crate::watcher::Exclusions;
+    use parking_lot::RwLock;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_redacts_windows_user_path() {
+        let path = Path::new("C:\\Users\\jane\\Documents\\divorce_agreement.pdf");
+        let redacted = format_path(path, true);
+
+        assert!(redacted.starts_with("C:\\Users\\…#"));
+        assert!(redacted.ends_with(".pdf"));
+        assert!(!redacted.contains("jane"));
+        assert!(!redacted.contains("divorce"));
+        assert!(!redacted.contains("Documents"));
+    }
+
+    #[test]
+    fn test_redacts_unix_and_unc_paths() {
+        let unix = format_path(Path::new("/home/jane/notes/secret.txt"), true);
+        assert!(unix.starts_with("/home/…#"));
+        assert!(!unix.contains("jane"));
+
+        let unc = format_path(Path::new("\\\\nas\\jane\\taxes.xlsx"), true);
+        assert!(unc.starts_with("\\\\nas\\…#"));
+        assert!(!unc.contains("jane"));
+    }
+
+    #[test]
+    fn test_redaction_is_stable_and_distinguishes_files() {
+        let a = Path::new("C:\\Users\\jane\\a.txt");
+        let b = Path::new("C:\\Users\\jane\\b.txt");
+        assert_eq!(format_path(a, true), format_path(a, true));
+        assert_ne!(format_path(a, true), format_path(b, true));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_logged_errors_keep_home_paths_out() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        const HOME: &str = "/home/jane/Documents";
+        let log = CapturedLog::start();
+        set_enabled(true);
+
+        // An unindexable name is logged by the index itself
+        let mut index = FileIndex::new();
+        let invalid = Path::new(HOME).join(OsStr::from_bytes(b"bad\xff.txt"));
+        let batch = PreparedBatch::new(vec![(invalid, EntryMeta::UNKNOWN)]);
+        assert_eq!(index.insert_batch(batch).unwrap().invalid, 1);
+
+        // Refused opens are logged next to the redacted path
+        let path = Path::new(HOME).join("divorce_agreement.pdf");
+        let policy = OpenPolicy { roots: &[], network_locations: &[], strict: true };
+        let refused = policy.check(&path, false).unwrap_err();
+        tracing::warn!("Refused to open {}: {}", redact(&path), refused);
+        let offline = FlashFindError::RootUnavailable(HOME.to_string());
+        tracing::warn!("Refused to open {}: {}", redact(&path), offline);
+        let unreadable = extract_member(&member_path(&Path::new(HOME).join("taxes.zip"), "2024.pdf")).unwrap_err();
+        tracing::error!("Failed to extract {}: {}", redact(&path), unreadable);
+
+        let logged = log.text();
+        drop(log);
+        assert!(logged.contains("Refusing to open /home/…#"));
+        assert!(!logged.contains("jane"));
+        assert!(!logged.contains("Documents"));
+    }
+
+    #[test]
+    fn test_scan_logs_keep_folder_names_out() {
+        let root = TestDir::new("redacted_scan");
+        let private = root.join("jane_divorce");
+        std::fs::create_dir_all(&private).unwrap();
+        std::fs::write(private.join("settlement.zip"), b"not a zip").unwrap();
+
+        let log = CapturedLog::start();
+        set_enabled(true);
+
+        // The scan and its warnings run on the indexer's own threads
+        let index = Arc::new(ShardedIndex::new());
+        let config = Config { index_archives: true, ..Config::default() };
+        let exclusions = Arc::new(RwLock::new(Exclusions::from_config(&config)));
+        let saver = Arc::new(SaveCoordinator::with_path(index.clone(), root.join("index.bin")));
+        let indexer = Indexer::new(index.clone(), exclusions, saver, Arc::new(ActivityLog::default())).unwrap();
+        indexer.start_scan(vec![root.to_path_buf()]).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while indexer.is_running() {
+            assert!(Instant::now() < deadline, "indexer did not finish");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let logged = log.text();
+        drop(log);
+        assert!(logged.contains("Skipping archive"));
+        assert!(!logged.contains("jane"));
+        assert!(!logged.contains("settlement"));
+    }
+
+    #[test]
+    fn test_short_paths_and_opt_out_unchanged() {
+        assert_eq!(format_path(Path::new("C:\\Users"), true), "C:\\Users");
+        let full = Path::new("C:\\Users\\jane\\a.txt");
+        assert_eq!(format_path(full, false), "C:\\Users\\jane\\a.txt");
+    }
+}
@@ -0,0 +1,407 @@
+use ahash::AHashMap;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::archive::{entry_kind, EntryKind};
+use crate::error::{FlashFindError, Result};
+use crate::paths::{path_key, to_long_path};
+use crate::redact::redact;
+
+/// Placeholder replaced by each file's sequence number
+pub const NUMBER_TOKEN: &str = "{n}";
+
+/// Most results a single batch rename covers
+pub const MAX_BATCH_RENAME: usize = 1_000;
+
+/// How new filenames are derived from the current ones
+///
+/// Find/replace runs on the whole filename; the prefix goes in front and the
+/// suffix before the extension. `{n}` in the replacement, prefix or suffix
+/// becomes the file's number in the batch, counting from `start_number`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenamePattern {
+    pub find: String,
+    pub replace: String,
+    pub prefix: String,
+    pub suffix: String,
+    pub start_number: usize,
+    /// Zero-padded width of `{n}`
+    pub number_width: usize,
+}
+
+impl Default for RenamePattern {
+    fn default() -> Self {
+        Self {
+            find: String::new(),
+            replace: String::new(),
+            prefix: String::new(),
+            suffix: String::new(),
+            start_number: 1,
+            number_width: 1,
+        }
+    }
+}
+
+impl RenamePattern {
+    /// New filename for `name`, the `position`-th file of the batch
+    pub fn apply(&self, name: &str, position: usize) -> String {
+        let number = format!("{:0width$}", self.start_number + position, width = self.number_width);
+        let expand = |text: &str| text.replace(NUMBER_TOKEN, &number);
+
+        let replaced = if self.find.is_empty() {
+            name.to_string()
+        } else {
+            name.replace(&self.find, &expand(&self.replace))
+        };
+        let (stem, extension) = split_extension(&replaced);
+        format!("{}{}{}{}", expand(&self.prefix), stem, expand(&self.suffix), extension)
+    }
+}
+
+/// Why a planned rename will not be performed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenameIssue {
+    /// The pattern leaves the name as it is
+    Unchanged,
+    InvalidName(&'static str),
+    /// Members of an indexed archive cannot be renamed in place
+    InArchive,
+    /// Another file in the batch gets the same new name
+    Duplicate,
+    /// A file with the new name already exists
+    TargetExists,
+}
+
+impl RenameIssue {
+    pub fn label(self) -> &'static str {
+        match self {
+            RenameIssue::Unchanged => "unchanged",
+            RenameIssue::InvalidName(reason) => reason,
+            RenameIssue::InArchive => "inside an archive",
+            RenameIssue::Duplicate => "same new name as another file",
+            RenameIssue::TargetExists => "a file with this name exists",
+        }
+    }
+}
+
+/// One file of a batch rename, with the problem that blocks it, if any
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedRename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub issue: Option<RenameIssue>,
+}
+
+/// Work out the new name of every file, flagging invalid names and collisions
+///
+/// `exists` reports whether a path is already taken on disk. A target that
+/// only differs from its own source in case is not a collision.
+pub fn plan_renames<F>(paths: &[PathBuf], pattern: &RenamePattern, exists: F) -> Vec<PlannedRename>
+where
+    F: Fn(&Path) -> bool,
+{
+    let mut plans: Vec<PlannedRename> = paths
+        .iter()
+        .enumerate()
+        .map(|(position, from)| {
+            let Some((dir, name)) = split_name(from) else {
+                let issue = Some(RenameIssue::InvalidName("name is not valid Unicode"));
+                return PlannedRename { from: from.clone(), to: from.clone(), issue };
+            };
+            let new_name = pattern.apply(name, position);
+            let to = PathBuf::from(format!("{}{}", dir, new_name));
+
+            let issue = if entry_kind(from) == EntryKind::ArchiveMember {
+                Some(RenameIssue::InArchive)
+            } else if new_name == name {
+                Some(RenameIssue::Unchanged)
+            } else if let Some(reason) = invalid_name(&new_name) {
+                Some(RenameIssue::InvalidName(reason))
+            } else if path_key(&to) != path_key(from) && exists(&to) {
+                Some(RenameIssue::TargetExists)
+            } else {
+                None
+            };
+            PlannedRename { from: from.clone(), to, issue }
+        })
+        .collect();
+
+    // Files keeping their name still occupy it, so they count as targets too
+    let mut targets: AHashMap<String, Vec<usize>> = AHashMap::new();
+    for (i, plan) in plans.iter().enumerate() {
+        if matches!(plan.issue, None | Some(RenameIssue::Unchanged)) {
+            targets.entry(path_key(&plan.to)).or_default().push(i);
+        }
+    }
+    for indices in targets.into_values().filter(|indices| indices.len() > 1) {
+        for i in indices {
+            if plans[i].issue.is_none() {
+                plans[i].issue = Some(RenameIssue::Duplicate);
+            }
+        }
+    }
+
+    plans
+}
+
+/// Result of applying a batch rename
+#[derive(Debug, Clone, Default)]
+pub struct RenameSummary {
+    /// (old path, new path) of every file renamed, in order
+    pub renamed: Vec<(PathBuf, PathBuf)>,
+    /// Files that could not be renamed, with the reason
+    pub failed: Vec<(PathBuf, String)>,
+    /// Files left alone because of a planning issue
+    pub skipped: usize,
+}
+
+/// Rename every file without an issue, one at a time
+///
+/// `on_renamed` runs after each successful rename so the caller can update
+/// the index. Failures are collected and the rest of the batch continues.
+pub fn apply_renames<F>(plans: &[PlannedRename], mut on_renamed: F) -> RenameSummary
+where
+    F: FnMut(&Path, &Path),
+{
+    let mut summary = RenameSummary::default();
+    for plan in plans {
+        if plan.issue.is_some() {
+            summary.skipped += 1;
+            continue;
+        }
+        match rename_file(&plan.from, &plan.to) {
+            Ok(()) => {
+                on_renamed(&plan.from, &plan.to);
+                summary.renamed.push((plan.from.clone(), plan.to.clone()));
+            }
+            Err(e) => {
+                warn!("Rename of {} failed: {}", redact(&plan.from), e);
+                summary.failed.push((plan.from.clone(), e.user_message()));
+            }
+        }
+    }
+    info!("Batch rename: {} renamed, {} failed, {} skipped", summary.renamed.len(), summary.failed.len(), summary.skipped);
+    summary
+}
+
+/// Rename the files of an earlier batch back, newest first
+pub fn undo_renames<F>(renamed: &[(PathBuf, PathBuf)], on_renamed: F) -> RenameSummary
+where
+    F: FnMut(&Path, &Path),
+{
+    let plans: Vec<PlannedRename> = renamed
+        .iter()
+        .rev()
+        .map(|(from, to)| PlannedRename { from: to.clone(), to: from.clone(), issue: None })
+        .collect();
+    apply_renames(&plans, on_renamed)
+}
+
+/// Rename one file, refusing to replace an existing one
+fn rename_file(from: &Path, to: &Path) -> Result<()> {
+    let failed = |source| FlashFindError::RenameFailed {
+        from: from.display().to_string(),
+        to: to.display().to_string(),
+        source,
+    };
+
+    // Checked again here: the file may have appeared since the preview
+    if path_key(from) != path_key(to) && std::fs::symlink_metadata(to_long_path(to)).is_ok() {
+        return Err(failed(std::io::Error::new(std::io::ErrorKind::AlreadyExists, "a file with this name exists")));
+    }
+    std::fs::rename(to_long_path(from), to_long_path(to)).map_err(failed)
+}
+
+/// Split a path into its folder (with trailing separator) and filename
+///
+/// Splits on both separators so Windows paths behave the same everywhere.
+/// Paths that are not valid Unicode cannot be split.
+fn split_name(path: &Path) -> Option<(&str, &str)> {
+    let path_str = path.to_str()?;
+    Some(match path_str.rfind(['/', '\\']) {
+        Some(i) => path_str.split_at(i + 1),
+        None => ("", path_str),
+    })
+}
+
+/// Split `report.final.pdf` into `report.final` and `.pdf`; dotfiles have no extension
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(i) if i > 0 => name.split_at(i),
+        _ => (name, ""),
+    }
+}
+
+/// Reason a filename cannot be used on Windows, if any
+fn invalid_name(name: &str) -> Option<&'static str> {
+    const RESERVED: [&str; 22] = [
+        "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9",
+        "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+    ];
+
+    if name.trim().is_empty() || name == "." || name == ".." {
+        return Some("name is empty");
+    }
+    if name.chars().any(|c| c.is_control() || matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*')) {
+        return Some("contains a character that is not allowed");
+    }
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Some("ends with a dot or space");
+    }
+    let device = name.split('.').next().unwrap_or_default().trim_end().to_lowercase();
+    if RESERVED.contains(&device.as_str()) {
+        return Some("reserved device name");
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use std::fs;
+
+    fn paths(list: &[&str]) -> Vec<PathBuf> {
+        list.iter().map(PathBuf::from).collect()
+    }
+
+    fn targets(plans: &[PlannedRename]) -> Vec<String> {
+        plans.iter().map(|p| p.to.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn test_prefix_suffix_and_replace() {
+        let pattern = RenamePattern {
+            find: "invoice".to_string(),
+            replace: "INV".to_string(),
+            prefix: "2024_".to_string(),
+            suffix: "_paid".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(pattern.apply("invoice_0042.pdf", 0), "2024_INV_0042_paid.pdf");
+        assert_eq!(pattern.apply("archive.tar.gz", 0), "2024_archive.tar_paid.gz");
+        assert_eq!(pattern.apply(".gitignore", 0), "2024_.gitignore_paid");
+    }
+
+    #[test]
+    fn test_numbering_token_is_padded() {
+        let pattern = RenamePattern {
+            prefix: "{n} - ".to_string(),
+            start_number: 9,
+            number_width: 3,
+            ..Default::default()
+        };
+        let plans = plan_renames(&paths(&["C:\\scans\\a.jpg", "C:\\scans\\b.jpg"]), &pattern, |_| false);
+        assert_eq!(targets(&plans), vec!["C:\\scans\\009 - a.jpg", "C:\\scans\\010 - b.jpg"]);
+        assert!(plans.iter().all(|p| p.issue.is_none()));
+    }
+
+    #[test]
+    fn test_collisions_within_batch_are_flagged() {
+        let pattern = RenamePattern { find: "draft".to_string(), replace: "final".to_string(), ..Default::default() };
+        let plans = plan_renames(
+            &paths(&["C:\\docs\\report draft.docx", "C:\\docs\\REPORT FINAL.docx", "C:\\docs\\report final.docx", "C:\\other\\report draft.docx"]),
+            &pattern,
+            |_| false,
+        );
+        let issues: Vec<Option<RenameIssue>> = plans.iter().map(|p| p.issue).collect();
+        assert_eq!(
+            issues,
+            vec![
+                Some(RenameIssue::Duplicate),
+                Some(RenameIssue::Unchanged),
+                Some(RenameIssue::Unchanged),
+                None,
+            ]
+        );
+
+        // Names differing only in case collide on Windows
+        let pattern = RenamePattern { find: "a".to_string(), replace: "X".to_string(), ..Default::default() };
+        let plans = plan_renames(&paths(&["C:\\d\\a.txt", "C:\\d\\x.txt"]), &pattern, |_| false);
+        assert_eq!(plans[0].issue, Some(RenameIssue::Duplicate));
+        let pattern = RenamePattern { suffix: "_1".to_string(), ..Default::default() };
+        let plans = plan_renames(&paths(&["C:\\d\\A.txt", "C:\\d\\a.txt"]), &pattern, |_| false);
+        assert!(plans.iter().all(|p| p.issue == Some(RenameIssue::Duplicate)));
+    }
+
+    #[test]
+    fn test_existing_targets_and_invalid_names() {
+        let taken = PathBuf::from("C:\\docs\\b.txt");
+        let pattern = RenamePattern { find: "a".to_string(), replace: "b".to_string(), ..Default::default() };
+        let plans = plan_renames(&paths(&["C:\\docs\\a.txt"]), &pattern, |p| p == taken);
+        assert_eq!(plans[0].issue, Some(RenameIssue::TargetExists));
+
+        // A case-only rename finds its own file, which is not a collision
+        let pattern = RenamePattern { find: "a".to_string(), replace: "A".to_string(), ..Default::default() };
+        let plans = plan_renames(&paths(&["C:\\docs\\a.txt"]), &pattern, |_| true);
+        assert_eq!(plans[0].issue, None);
+
+        for (find, replace, reason) in [
+            ("a", "a:b", "contains a character that is not allowed"),
+            ("a.txt", "", "name is empty"),
+            ("a.txt", "con.txt", "reserved device name"),
+            (".txt", ".", "ends with a dot or space"),
+        ] {
+            let pattern = RenamePattern { find: find.to_string(), replace: replace.to_string(), ..Default::default() };
+            let plans = plan_renames(&paths(&["C:\\docs\\a.txt"]), &pattern, |_| false);
+            assert_eq!(plans[0].issue, Some(RenameIssue::InvalidName(reason)), "{} -> {}", find, replace);
+        }
+
+        let plans = plan_renames(&paths(&["C:\\docs\\bundle.zip::a.txt"]), &RenamePattern { prefix: "x".to_string(), ..Default::default() }, |_| false);
+        assert_eq!(plans[0].issue, Some(RenameIssue::InArchive));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_unicode_name_is_invalid() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let from = PathBuf::from(std::ffi::OsStr::from_bytes(b"/docs/caf\xe9.txt"));
+        let plans = plan_renames(std::slice::from_ref(&from), &RenamePattern { prefix: "x".to_string(), ..Default::default() }, |_| false);
+        assert_eq!(plans[0].issue, Some(RenameIssue::InvalidName("name is not valid Unicode")));
+        assert_eq!(plans[0].to, from);
+    }
+
+    #[test]
+    fn test_apply_reports_failures_and_undo_restores() {
+        let dir = TestDir::new("rename_apply");
+        for name in ["one.txt", "two.txt", "2024_three.txt", "three.txt"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        let files = vec![dir.join("one.txt"), dir.join("missing.txt"), dir.join("two.txt"), dir.join("three.txt")];
+        let pattern = RenamePattern { prefix: "2024_".to_string(), ..Default::default() };
+        let plans = plan_renames(&files, &pattern, |p| p.exists());
+        assert_eq!(plans[3].issue, Some(RenameIssue::TargetExists));
+
+        let mut updates = Vec::new();
+        let summary = apply_renames(&plans, |from, to| updates.push((from.to_path_buf(), to.to_path_buf())));
+        assert_eq!(summary.renamed, updates);
+        assert_eq!(summary.renamed.len(), 2);
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].0, dir.join("missing.txt"));
+        assert_eq!(summary.skipped, 1);
+        assert!(dir.join("2024_one.txt").exists() && !dir.join("one.txt").exists());
+        assert_eq!(fs::read_to_string(dir.join("2024_three.txt")).unwrap(), "2024_three.txt");
+
+        let undone = undo_renames(&summary.renamed, |_, _| {});
+        assert_eq!(undone.renamed.len(), 2);
+        assert!(undone.failed.is_empty());
+        assert!(dir.join("one.txt").exists() && dir.join("two.txt").exists());
+        assert!(!dir.join("2024_one.txt").exists());
+    }
+
+    #[test]
+    fn test_apply_never_overwrites_a_file_created_after_planning() {
+        let dir = TestDir::new("rename_race");
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        let pattern = RenamePattern { find: "a".to_string(), replace: "b".to_string(), ..Default::default() };
+        let plans = plan_renames(&[dir.join("a.txt")], &pattern, |p| p.exists());
+        assert_eq!(plans[0].issue, None);
+
+        fs::write(dir.join("b.txt"), "b").unwrap();
+        let summary = apply_renames(&plans, |_, _| {});
+        assert!(summary.renamed.is_empty());
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(fs::read_to_string(dir.join("b.txt")).unwrap(), "b");
+    }
+}
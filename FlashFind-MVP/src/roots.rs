@@ -0,0 +1,184 @@
+use parking_lot::RwLock;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::paths::{is_within, to_long_path};
+use crate::redact::redact;
+
+/// How often an available root is re-checked
+const HEALTHY_PROBE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// First retry delay after a root goes offline
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Upper bound for the retry delay of an offline root
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Availability state of a single indexed root
+#[derive(Debug, Clone)]
+struct RootState {
+    path: PathBuf,
+    available: bool,
+    failures: u32,
+    next_probe: Instant,
+}
+
+/// Tracks whether indexed roots (drives, folders, shares) are reachable
+///
+/// Probing is a cheap existence check. Offline roots are retried with
+/// exponential backoff so a sleeping or unplugged drive does not flood the
+/// log, and paths under them are treated as "offline" rather than deleted.
+#[derive(Debug, Default)]
+pub struct RootMonitor {
+    roots: RwLock<Vec<RootState>>,
+}
+
+impl RootMonitor {
+    /// Create a monitor with no roots
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the tracked roots, keeping the state of roots already known
+    pub fn set_roots(&self, roots: &[PathBuf]) {
+        let now = Instant::now();
+        let mut states = self.roots.write();
+
+        let updated = roots
+            .iter()
+            .map(|path| {
+                states
+                    .iter()
+                    .find(|s| &s.path == path)
+                    .cloned()
+                    .unwrap_or_else(|| RootState {
+                        path: path.clone(),
+                        available: true,
+                        failures: 0,
+                        next_probe: now,
+                    })
+            })
+            .collect();
+
+        *states = updated;
+    }
+
+    /// Check whether a path can be reached (paths outside every root count as available)
+    pub fn is_available(&self, path: &Path) -> bool {
+        self.offline_root(path).is_none()
+    }
+
+    /// The offline root containing `path`, if its closest root is unreachable
+    pub fn offline_root(&self, path: &Path) -> Option<PathBuf> {
+        self.roots
+            .read()
+            .iter()
+            .filter(|s| is_within(path, &s.path))
+            .max_by_key(|s| s.path.as_os_str().len())
+            .filter(|s| !s.available)
+            .map(|s| s.path.clone())
+    }
+
+    /// Roots that are currently unreachable
+    pub fn unavailable_roots(&self) -> Vec<PathBuf> {
+        self.roots
+            .read()
+            .iter()
+            .filter(|s| !s.available)
+            .map(|s| s.path.clone())
+            .collect()
+    }
+
+    /// Probe every root that is due and return the roots that came back online
+    pub fn probe(&self) -> Vec<PathBuf> {
+        self.probe_at(Instant::now())
+    }
+
+    /// Probe with an explicit clock (used by tests to skip the backoff)
+    fn probe_at(&self, now: Instant) -> Vec<PathBuf> {
+        let mut restored = Vec::new();
+        let mut states = self.roots.write();
+
+        for state in states.iter_mut().filter(|s| s.next_probe <= now) {
+            let reachable = to_long_path(&state.path).is_dir();
+
+            if reachable {
+                if !state.available {
+                    info!("Root is available again: {}", redact(&state.path));
+                    restored.push(state.path.clone());
+                }
+                state.available = true;
+                state.failures = 0;
+                state.next_probe = now + HEALTHY_PROBE_INTERVAL;
+            } else {
+                if state.available {
+                    // Warn once per outage, not once per probe
+                    warn!("Root is unavailable: {}", redact(&state.path));
+                }
+                state.available = false;
+                state.failures += 1;
+                state.next_probe = now + backoff(state.failures);
+            }
+        }
+
+        restored
+    }
+}
+
+/// Retry delay after `failures` consecutive failed probes
+fn backoff(failures: u32) -> Duration {
+    let shift = failures.saturating_sub(1).min(16);
+    (INITIAL_BACKOFF * (1u32 << shift)).min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        assert_eq!(backoff(1), Duration::from_secs(1));
+        assert_eq!(backoff(2), Duration::from_secs(2));
+        assert_eq!(backoff(4), Duration::from_secs(8));
+        assert_eq!(backoff(30), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_root_deleted_mid_session() {
+        let root = TestDir::new("roots_deleted");
+        let file = root.join("notes.txt");
+        let monitor = RootMonitor::new();
+        monitor.set_roots(&[root.to_path_buf()]);
+
+        let start = Instant::now();
+        assert!(monitor.probe_at(start).is_empty());
+        assert!(monitor.is_available(&file));
+
+        std::fs::remove_dir_all(&root).unwrap();
+        let offline_at = start + HEALTHY_PROBE_INTERVAL;
+        assert!(monitor.probe_at(offline_at).is_empty());
+        assert!(!monitor.is_available(&file));
+        assert_eq!(monitor.unavailable_roots(), vec![root.to_path_buf()]);
+
+        // Root returns, but is not re-probed until the backoff elapses
+        std::fs::create_dir_all(&root).unwrap();
+        assert!(monitor.probe_at(offline_at + INITIAL_BACKOFF / 2).is_empty());
+        assert!(!monitor.is_available(&file));
+
+        let restored = monitor.probe_at(offline_at + INITIAL_BACKOFF);
+        assert_eq!(restored, vec![root.to_path_buf()]);
+        assert!(monitor.is_available(&file));
+    }
+
+    #[test]
+    fn test_paths_outside_roots_are_available() {
+        let monitor = RootMonitor::new();
+        monitor.set_roots(&[PathBuf::from("/definitely/missing/root")]);
+        monitor.probe();
+
+        assert!(!monitor.is_available(Path::new("/definitely/missing/root/a.txt")));
+        assert!(monitor.is_available(Path::new("/elsewhere/a.txt")));
+    }
+}
@@ -0,0 +1,552 @@
+use crossbeam_channel::{Receiver, Sender, TryRecvError};
+use parking_lot::Mutex;
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::{FlashFindError, Result};
+use crate::index::{
+    match_ranges, parse_query, ParsedQuery, SearchResult, SearchStop, SortOrder, TypoTolerance,
+    PATH_PAGE_SIZE, UNKNOWN_MTIME, WHOLE_WORD_PREFIX,
+};
+use crate::query::IN_PREFIX;
+use crate::ranking::{rank_results, RankingProfile};
+use crate::sharded::{ShardedCache, ShardedIndex};
+use crate::suggest::{did_you_mean, MAX_DID_YOU_MEAN};
+
+/// Results are sent to the app this many at a time
+pub const STREAM_BATCH: usize = 5_000;
+
+/// Larger result sets stream in name order instead of waiting to be ranked
+pub const MAX_RANKED: usize = 100_000;
+
+/// Batches a streamed search may queue before it waits for the app
+const STREAM_QUEUE: usize = 8;
+
+/// What a background search runs, copied from the search box and config
+#[derive(Debug, Clone)]
+pub struct SearchRequest {
+    pub query: String,
+    /// `Config::max_search_candidates`
+    pub limit: usize,
+    pub sort: SortOrder,
+    /// Weights applied when sorting by relevance
+    pub profile: RankingProfile,
+    /// Match filenames with typos on every search (`Config::fuzzy_search`)
+    pub fuzzy: bool,
+    /// Typos a fuzzy match may hold (`Config::typo_tolerance`); `Off`
+    /// turns fuzzy matching and its fallback off
+    pub typo_tolerance: TypoTolerance,
+    /// First entry of the page, for `*` and path-prefix browsing
+    pub offset: usize,
+    /// Time after which the scan stops and keeps what it found
+    /// (`Config::search_timeout_ms`)
+    pub timeout: Option<Duration>,
+    /// Folders whose files rank first (`Config::favorite_folders`)
+    pub favorites: Vec<PathBuf>,
+    /// Search only inside `favorites` (`Config::favorites_only`)
+    pub favorites_only: bool,
+    /// Match a plain filename query as a whole word (`Config::whole_word`)
+    pub whole_word: bool,
+}
+
+/// Results of a finished background search, before the file type filter
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct SearchOutcome {
+    /// Matches with their stored sizes and dates, best first
+    pub results: Vec<SearchResult>,
+    /// The candidate cap was hit
+    pub truncated: bool,
+    /// The scan ran out of time, so these are only the matches found first
+    pub timed_out: bool,
+    /// Nothing matched exactly, so the results come from the fuzzy search
+    pub fuzzy_fallback: bool,
+    /// Entries in the whole listing, when the results are one browse page
+    pub total: Option<usize>,
+    /// Close filenames to offer when nothing matched, even with typos
+    pub suggestions: Vec<String>,
+}
+
+/// What a streamed search reports once every batch has been sent
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchSummary {
+    /// Results sent across every batch
+    pub count: usize,
+    /// The candidate cap was hit
+    pub truncated: bool,
+    /// The scan ran out of time, so these are only the matches found first
+    pub timed_out: bool,
+    /// Nothing matched exactly, so the results come from the fuzzy search
+    pub fuzzy_fallback: bool,
+    /// Entries in the whole listing, when the results are one browse page
+    pub total: Option<usize>,
+    /// Close filenames to offer when nothing matched, even with typos
+    pub suggestions: Vec<String>,
+}
+
+/// One message from a streamed search
+#[derive(Debug)]
+pub enum SearchBatch {
+    /// More results, in their final order after the ones already sent
+    Results(Vec<SearchResult>),
+    /// The last message of the search
+    Finished(Result<SearchSummary>),
+}
+
+/// `search_streaming`, with every batch gathered into one outcome
+///
+/// The app shows batches as they arrive through `SearchJob`.
+#[cfg(test)]
+pub fn run_search(
+    index: &ShardedIndex,
+    request: &SearchRequest,
+    cache: &Mutex<ShardedCache>,
+    cancel: &AtomicBool,
+) -> Result<SearchOutcome> {
+    let (tx, batches) = crossbeam_channel::unbounded();
+    let summary = search_streaming(index, request, cache, cancel, &tx)?;
+    drop(tx);
+    let mut results = Vec::with_capacity(summary.count);
+    for batch in batches {
+        if let SearchBatch::Results(batch) = batch {
+            results.extend(batch);
+        }
+    }
+    Ok(SearchOutcome {
+        results,
+        truncated: summary.truncated,
+        timed_out: summary.timed_out,
+        fuzzy_fallback: summary.fuzzy_fallback,
+        total: summary.total,
+        suggestions: summary.suggestions,
+    })
+}
+
+/// Run a filename, extension, wildcard, boolean, path, size or date query,
+/// or fetch one page of a `*` or path-prefix listing, sending the results
+/// to `tx` in batches of `STREAM_BATCH`
+///
+/// A plain filename query with no exact matches falls back to the fuzzy
+/// search; exact matches are then ranked by `request.profile` unless a date
+/// or size sort was chosen or there are more than `MAX_RANKED` of them, and
+/// carry the parts of their names that matched. A filename query that finds
+/// nothing at all gets "did you mean" names instead. Checks `cancel` between
+/// steps and batches (and the index checks it while scanning), returning
+/// `FlashFindError::Cancelled` once set or once `tx` has no receiver. A scan
+/// past `request.timeout` sends the matches it found, marked `timed_out`;
+/// having found none is a `FlashFindError::Timeout`. With
+/// `request.favorites_only`, a search is run as if `in:` each favorite
+/// folder had been typed; with `request.whole_word`, a plain filename query
+/// as if it started with `\b`.
+pub fn search_streaming(
+    index: &ShardedIndex,
+    request: &SearchRequest,
+    cache: &Mutex<ShardedCache>,
+    cancel: &AtomicBool,
+    tx: &Sender<SearchBatch>,
+) -> Result<SearchSummary> {
+    let check = || if cancel.load(Ordering::Relaxed) { Err(FlashFindError::Cancelled) } else { Ok(()) };
+    let worded = if request.whole_word { whole_word_query(&request.query) } else { None };
+    let query = worded.as_deref().unwrap_or(&request.query);
+    let scoped = if request.favorites_only { scoped_query(query, &request.favorites) } else { None };
+    let query = scoped.as_deref().unwrap_or(query);
+    let parsed = parse_query(query);
+    let is_filename = matches!(parsed, ParsedQuery::Filename(_));
+    let suggestions = |count: usize| -> Result<Vec<String>> {
+        if count > 0 {
+            return Ok(Vec::new());
+        }
+        let names = did_you_mean(&index.read(), query, MAX_DID_YOU_MEAN);
+        check()?;
+        Ok(names)
+    };
+    
+    // Shard locks are taken per result, so the watcher can update them in between
+    let send = |paths: Vec<PathBuf>, highlight: bool| -> Result<usize> {
+        let count = paths.len();
+        let mut paths = paths.into_iter();
+        loop {
+            check()?;
+            let mut results = index.describe(paths.by_ref().take(STREAM_BATCH));
+            if results.is_empty() {
+                return Ok(count);
+            }
+            if highlight {
+                results.par_iter_mut().for_each(|result| result.matched = match_ranges(&parsed, &result.file_name));
+            }
+            tx.send(SearchBatch::Results(results)).map_err(|_| FlashFindError::Cancelled)?;
+        }
+    };
+    
+    // Listings sort every matching path, so even one page takes a while on a large index
+    let page = match &parsed {
+        ParsedQuery::BrowseAll => Some(index.browse_all(request.offset, PATH_PAGE_SIZE)),
+        ParsedQuery::PathPrefix(prefix) => Some(index.search_path_prefix(prefix, request.offset, PATH_PAGE_SIZE)?),
+        _ => None,
+    };
+    if let Some(page) = page {
+        let count = send(page.paths, false)?;
+        return Ok(SearchSummary { count, total: Some(page.total), ..SearchSummary::default() });
+    }
+
+    // Fuzzy results keep their closest-first order instead of being ranked
+    let tolerant = is_filename && request.typo_tolerance != TypoTolerance::Off;
+    if tolerant && request.fuzzy {
+        let found = index.search_fuzzy(query, request.limit, request.sort, request.typo_tolerance);
+        let count = send(found.paths, false)?;
+        return Ok(SearchSummary { count, truncated: found.truncated, suggestions: suggestions(count)?, ..SearchSummary::default() });
+    }
+
+    let stop = SearchStop::new(cancel, request.timeout);
+    let found = index.search_refining(query, request.limit, request.sort, &mut cache.lock(), &stop)?;
+    check()?;
+    if found.timed_out && found.paths.is_empty() {
+        return Err(FlashFindError::Timeout(request.timeout.unwrap_or_default()));
+    }
+    if found.paths.is_empty() && tolerant {
+        // Nothing matched exactly, so the query may hold a typo
+        let fuzzy = index.search_fuzzy(query, request.limit, request.sort, request.typo_tolerance);
+        let count = send(fuzzy.paths, false)?;
+        return Ok(SearchSummary {
+            count,
+            truncated: fuzzy.truncated,
+            timed_out: false,
+            fuzzy_fallback: count > 0,
+            total: None,
+            suggestions: suggestions(count)?,
+        });
+    }
+
+    // Ranking a huge result set would hold back the first batch, so those
+    // keep the index's name order
+    let paths = if request.sort == SortOrder::Name && found.paths.len() <= MAX_RANKED {
+        // Recency reads the dates stored in the index; no result is stat'ed
+        let stored = |path: &Path| index.metadata_for(path).map(|meta| meta.modified).filter(|&modified| modified != UNKNOWN_MTIME);
+        rank_results(found.paths, query, &request.profile, &request.favorites, &stored)
+    } else {
+        // Already in the chosen order, straight from the index
+        found.paths
+    };
+    let count = send(paths, true)?;
+    Ok(SearchSummary { count, truncated: found.truncated, timed_out: found.timed_out, ..SearchSummary::default() })
+}
+
+/// `query` behind `\b` if it is a plain filename query, so it only matches
+/// whole words
+fn whole_word_query(query: &str) -> Option<String> {
+    matches!(parse_query(query), ParsedQuery::Filename(_)).then(|| format!("{}{}", WHOLE_WORD_PREFIX, query.trim()))
+}
+
+/// `query` with an `in:` clause for each of `folders`, or `None` when there
+/// are none or the query is a listing or already has `in:` clauses of its own
+fn scoped_query(query: &str, folders: &[PathBuf]) -> Option<String> {
+    if folders.is_empty() {
+        return None;
+    }
+    match parse_query(query) {
+        ParsedQuery::Empty
+        | ParsedQuery::Hint(_)
+        | ParsedQuery::Invalid(_)
+        | ParsedQuery::BrowseAll
+        | ParsedQuery::PathPrefix(_)
+        | ParsedQuery::InFolder { .. } => None,
+        _ => {
+            let scopes: Vec<String> = folders.iter().map(|folder| format!("{}\"{}\"", IN_PREFIX, folder.display())).collect();
+            Some(format!("{} {}", scopes.join(" "), query.trim()))
+        }
+    }
+}
+
+/// A search running on its own thread, so typing never waits for it
+pub struct SearchJob {
+    batches: Receiver<SearchBatch>,
+    cancel: Arc<AtomicBool>,
+    started: Instant,
+}
+
+impl SearchJob {
+    pub fn start(index: Arc<ShardedIndex>, request: SearchRequest, cache: Arc<Mutex<ShardedCache>>) -> Self {
+        let (sender, batches) = crossbeam_channel::bounded(STREAM_QUEUE);
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let thread_cancel = cancel.clone();
+        thread::spawn(move || {
+            let summary = search_streaming(&index, &request, &cache, &thread_cancel, &sender);
+            // The receiver is gone if the app dropped the job; nothing to report
+            let _ = sender.send(SearchBatch::Finished(summary));
+        });
+
+        Self { batches, cancel, started: Instant::now() }
+    }
+
+    /// Abandon the search at its next check; a newer query replaces it
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+
+    /// Batches sent since the last call, ending with `Finished` once the
+    /// search is done
+    pub fn take_batches(&self) -> Vec<SearchBatch> {
+        let mut batches = Vec::new();
+        loop {
+            match self.batches.try_recv() {
+                Ok(batch) => batches.push(batch),
+                Err(TryRecvError::Empty) => return batches,
+                // The thread ended without reporting (it panicked)
+                Err(TryRecvError::Disconnected) => {
+                    batches.push(SearchBatch::Finished(Err(FlashFindError::Cancelled)));
+                    return batches;
+                }
+            }
+        }
+    }
+
+    /// Block until the search finishes, returning the batches not yet taken
+    pub fn wait(&self) -> Vec<SearchBatch> {
+        let mut batches = Vec::new();
+        loop {
+            let batch = self.batches.recv().unwrap_or(SearchBatch::Finished(Err(FlashFindError::Cancelled)));
+            let finished = matches!(batch, SearchBatch::Finished(_));
+            batches.push(batch);
+            if finished {
+                return batches;
+            }
+        }
+    }
+
+    /// Time since the search started
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::EntryMeta;
+
+    fn request(query: &str) -> SearchRequest {
+        SearchRequest {
+            query: query.to_string(),
+            limit: usize::MAX,
+            sort: SortOrder::Name,
+            profile: RankingProfile::default(),
+            fuzzy: false,
+            typo_tolerance: TypoTolerance::Low,
+            offset: 0,
+            timeout: None,
+            favorites: Vec::new(),
+            favorites_only: false,
+            whole_word: false,
+        }
+    }
+
+    /// The results and summary of a job's batches
+    fn outcome(batches: Vec<SearchBatch>) -> SearchOutcome {
+        let mut found = SearchOutcome::default();
+        for batch in batches {
+            match batch {
+                SearchBatch::Results(results) => found.results.extend(results),
+                SearchBatch::Finished(summary) => {
+                    let summary = summary.unwrap();
+                    assert_eq!(summary.count, found.results.len());
+                    found.truncated = summary.truncated;
+                    found.timed_out = summary.timed_out;
+                    found.fuzzy_fallback = summary.fuzzy_fallback;
+                    found.total = summary.total;
+                    found.suggestions = summary.suggestions;
+                }
+            }
+        }
+        found
+    }
+
+    fn sample_index() -> Arc<ShardedIndex> {
+        let index = ShardedIndex::new();
+        for i in 0..5_000 {
+            index.insert(PathBuf::from(format!("/data/entry_{}.txt", i))).unwrap();
+        }
+        let meta = EntryMeta { size: 2_048, modified: 1_700_000_000, is_dir: false, attributes: 0 };
+        index.insert_with_meta(PathBuf::from("/docs/budget.xlsx"), meta).unwrap();
+        index.publish();
+        Arc::new(index)
+    }
+
+    #[test]
+    fn test_cancelled_search_returns_cancelled_and_keeps_the_cache() {
+        let index = sample_index();
+        let cache = Mutex::default();
+
+        let found = run_search(&index, &request("entry"), &cache, &AtomicBool::new(false)).unwrap();
+        assert_eq!(found.results.len(), 5_000);
+        assert!(!cache.lock().is_empty());
+
+        // Cancelled before it starts, the search stops at its first check
+        let cancelled = AtomicBool::new(true);
+        for query in ["e", "entry_1", ".txt", "*.txt", "entry NOT 1", "path:data", "budget"] {
+            let result = run_search(&index, &request(query), &cache, &cancelled);
+            assert!(matches!(result, Err(FlashFindError::Cancelled)), "{:?} was not cancelled", query);
+        }
+        let refined = index.search_refining("entry_1", usize::MAX, SortOrder::Name, &mut cache.lock(), &SearchStop::new(&cancelled, None));
+        assert!(matches!(refined, Err(FlashFindError::Cancelled)));
+        assert!(!cache.lock().is_empty());
+        let found = run_search(&index, &request("entry_1"), &cache, &AtomicBool::new(false)).unwrap();
+        assert_eq!(found.results.len(), 1_111);
+    }
+
+    #[test]
+    fn test_browse_queries_return_one_page_with_the_total() {
+        let index = sample_index();
+        let cache = Mutex::default();
+        let cancel = AtomicBool::new(false);
+        
+        let first = run_search(&index, &request("*"), &cache, &cancel).unwrap();
+        assert_eq!(first.total, Some(5_001));
+        assert_eq!(first.results.len(), PATH_PAGE_SIZE);
+        assert_eq!(first.results[0].path, PathBuf::from("/data/entry_0.txt"));
+        
+        let last = run_search(&index, &SearchRequest { offset: 5_000, ..request("*") }, &cache, &cancel).unwrap();
+        assert_eq!(last.total, Some(5_001));
+        assert_eq!(last.results.iter().map(|r| r.size).collect::<Vec<_>>(), vec![Some(2_048)]);
+        
+        // Plain searches are not pages
+        assert_eq!(run_search(&index, &request("budget"), &cache, &cancel).unwrap().total, None);
+        assert!(matches!(run_search(&index, &request("*"), &cache, &AtomicBool::new(true)), Err(FlashFindError::Cancelled)));
+    }
+    
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_search_job_reports_results_and_falls_back_to_fuzzy() {
+        let index = sample_index();
+        let cache = Arc::new(Mutex::default());
+
+        let job = SearchJob::start(index.clone(), request("budget"), cache.clone());
+        let found = outcome(job.wait());
+        let budget = SearchResult {
+            path: PathBuf::from("/docs/budget.xlsx"),
+            file_name: "budget.xlsx".to_string(),
+            extension: Some("xlsx".to_string()),
+            size: Some(2_048),
+            modified: Some(1_700_000_000),
+            matched: vec![0..6],
+            is_dir: false,
+        };
+        assert_eq!(found.results, vec![budget.clone()]);
+        assert!(!found.fuzzy_fallback);
+
+        let job = SearchJob::start(index.clone(), request("bugdet"), cache);
+        let found = outcome(job.wait());
+        // Typo matches have nothing to highlight
+        assert!(found.fuzzy_fallback);
+        assert_eq!(found.results, vec![SearchResult { matched: Vec::new(), ..budget }]);
+        assert!(found.suggestions.is_empty());
+        
+        // Too far off for the fuzzy search, but close to a whole name
+        let found = run_search(&index, &request("bdugte"), &Mutex::default(), &AtomicBool::new(false)).unwrap();
+        assert!(found.results.is_empty());
+        assert_eq!(found.suggestions, ["budget.xlsx"]);
+    }
+
+    #[test]
+    fn test_search_out_of_time_with_nothing_found_is_a_timeout() {
+        let index = sample_index();
+        let cache = Mutex::default();
+        let cancel = AtomicBool::new(false);
+        
+        let instant = SearchRequest { timeout: Some(Duration::ZERO), ..request("path:data") };
+        let result = run_search(&index, &instant, &cache, &cancel);
+        assert!(matches!(result, Err(FlashFindError::Timeout(limit)) if limit.is_zero()));
+        
+        let relaxed = SearchRequest { timeout: Some(Duration::from_secs(60)), ..request("path:data") };
+        let found = run_search(&index, &relaxed, &cache, &cancel).unwrap();
+        assert_eq!(found.results.len(), 5_000);
+        assert!(!found.timed_out);
+    }
+    
+    #[test]
+    fn test_large_result_sets_stream_in_batches() {
+        let index = sample_index();
+        let cache = Mutex::default();
+        let (tx, rx) = crossbeam_channel::unbounded();
+        
+        let summary = search_streaming(&index, &request("entry"), &cache, &AtomicBool::new(false), &tx).unwrap();
+        assert_eq!(summary, SearchSummary { count: 5_000, ..SearchSummary::default() });
+        let sizes: Vec<usize> = rx
+            .try_iter()
+            .map(|batch| match batch {
+                SearchBatch::Results(results) => results.len(),
+                SearchBatch::Finished(_) => panic!("only the job sends Finished"),
+            })
+            .collect();
+        assert_eq!(sizes, [STREAM_BATCH]);
+        
+        let many = Arc::new(ShardedIndex::new());
+        for i in 0..12_000 {
+            many.insert(PathBuf::from(format!("/data/log_{}.txt", i))).unwrap();
+        }
+        many.publish();
+        let job = SearchJob::start(many.clone(), request("log"), Arc::new(Mutex::default()));
+        let batches = job.wait();
+        assert_eq!(batches.len(), 4);
+        assert_eq!(outcome(batches).results.len(), 12_000);
+        
+        // A dropped receiver stops the producer
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        drop(rx);
+        let dropped = search_streaming(&many, &request("log"), &Mutex::default(), &AtomicBool::new(false), &tx);
+        assert!(matches!(dropped, Err(FlashFindError::Cancelled)));
+    }
+    
+    #[test]
+    fn test_favorites_rank_first_or_scope_the_search() {
+        let index = ShardedIndex::new();
+        for path in ["/work/acme/budget.xlsx", "/home/budget.xlsx", "/work/acme/old/budget_2019.xlsx", "/tmp/budget_copy.xlsx"] {
+            index.insert(PathBuf::from(path)).unwrap();
+        }
+        index.publish();
+        let (cache, cancel) = (Mutex::default(), AtomicBool::new(false));
+        let paths = |request: &SearchRequest| -> Vec<String> {
+            let found = run_search(&index, request, &cache, &cancel).unwrap();
+            found.results.iter().map(|r| r.path.to_string_lossy().into_owned()).collect()
+        };
+        let favorites = vec![PathBuf::from("/work/acme")];
+        
+        let boosted = SearchRequest { favorites: favorites.clone(), ..request("budget") };
+        assert_eq!(paths(&boosted), ["/work/acme/budget.xlsx", "/work/acme/old/budget_2019.xlsx", "/home/budget.xlsx", "/tmp/budget_copy.xlsx"]);
+        
+        let scoped = SearchRequest { favorites_only: true, ..boosted };
+        assert_eq!(paths(&scoped), ["/work/acme/budget.xlsx", "/work/acme/old/budget_2019.xlsx"]);
+        // The query's own in: clause wins, and listings are never scoped
+        assert_eq!(paths(&SearchRequest { query: "in:tmp budget".into(), ..scoped.clone() }), ["/tmp/budget_copy.xlsx"]);
+        assert_eq!(paths(&SearchRequest { query: "*".into(), ..scoped.clone() }).len(), 4);
+        // With no favorites the toggle does nothing
+        assert_eq!(paths(&SearchRequest { favorites: Vec::new(), ..scoped }).len(), 4);
+    }
+    
+    #[test]
+    fn test_whole_word_toggle_drops_names_merely_containing_the_query() {
+        let index = ShardedIndex::new();
+        for path in ["/pics/art_deco.png", "/pics/ArtBook.pdf", "/notes/startup_notes.txt", "/pics/particle.png"] {
+            index.insert(PathBuf::from(path)).unwrap();
+        }
+        index.publish();
+        let (cache, cancel) = (Mutex::default(), AtomicBool::new(false));
+        let names = |request: &SearchRequest| -> Vec<String> {
+            let found = run_search(&index, request, &cache, &cancel).unwrap();
+            found.results.iter().map(|r| r.file_name.clone()).collect()
+        };
+        
+        assert_eq!(names(&request("art")).len(), 4);
+        let whole = SearchRequest { whole_word: true, ..request("art") };
+        assert_eq!(names(&whole), ["art_deco.png", "ArtBook.pdf"]);
+        let found = run_search(&index, &whole, &cache, &cancel).unwrap();
+        assert_eq!(found.results[1].matched, vec![0..3]);
+        // Other query forms are left alone
+        assert_eq!(names(&SearchRequest { query: "*.png".into(), ..whole.clone() }).len(), 2);
+        assert_eq!(names(&SearchRequest { whole_word: false, ..request("\\bart") }).len(), 2);
+    }
+}
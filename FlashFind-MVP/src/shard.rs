@@ -0,0 +1,409 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+
+use crate::error::{FlashFindError, Result};
+use crate::indexer::{walk_files, ScanReport};
+use crate::paths::is_within;
+use crate::redact::redact;
+use crate::sharded::ShardedIndex;
+use crate::watcher::Exclusions;
+
+/// Command-line flag that runs FlashFind as an elevated scan helper
+pub const HELPER_FLAG: &str = "--elevated-scan";
+
+/// Shard file format version
+const SHARD_VERSION: u32 = 1;
+
+/// Prefix of shard, temp and cancel-marker files in the temp directory
+const SHARD_PREFIX: &str = "flashfind_shard_";
+
+/// How long to wait for the helper before giving up on a shard
+const HELPER_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Files found by an elevated helper scan, handed back to the main app
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScanShard {
+    version: u32,
+    /// Roots the helper was asked to scan
+    pub roots: Vec<PathBuf>,
+    /// Files found under `roots`
+    pub files: Vec<PathBuf>,
+    /// Directories still unreadable with administrator rights
+    pub denied: Vec<PathBuf>,
+}
+
+impl ScanShard {
+    /// Create a shard for the given scan results
+    pub fn new(roots: Vec<PathBuf>, files: Vec<PathBuf>, denied: Vec<PathBuf>) -> Self {
+        Self { version: SHARD_VERSION, roots, files, denied }
+    }
+}
+
+/// Outcome of merging a shard into the index
+#[derive(Debug, Default)]
+pub struct ShardImport {
+    pub files_added: usize,
+    /// Files dropped because they were outside the shard's roots
+    pub rejected: usize,
+    pub denied: Vec<PathBuf>,
+}
+
+/// Write a shard atomically, so the app never imports a half-written file
+pub fn write_shard(path: &Path, shard: &ScanShard) -> Result<()> {
+    let write_error = |e| FlashFindError::FileWriteError {
+        path: path.display().to_string(),
+        source: e,
+    };
+
+    let temp = path.with_extension("tmp");
+    let data = bincode::serialize(shard)?;
+    fs::write(&temp, data).map_err(write_error)?;
+    fs::rename(&temp, path).map_err(write_error)
+}
+
+/// Read a shard written by `write_shard`
+pub fn read_shard(path: &Path) -> Result<ScanShard> {
+    let data = fs::read(path).map_err(|e| FlashFindError::FileReadError {
+        path: path.display().to_string(),
+        source: e,
+    })?;
+
+    let shard: ScanShard = bincode::deserialize(&data)?;
+    if shard.version != SHARD_VERSION {
+        return Err(FlashFindError::VersionMismatch {
+            found: shard.version,
+            expected: SHARD_VERSION,
+        });
+    }
+    Ok(shard)
+}
+
+/// Merge the shard at `path` into the index, then delete it
+///
+/// The shard must cover only the `expected` roots the app asked for, and
+/// files outside those roots are dropped, so a stray or tampered temp file
+/// cannot inject arbitrary paths.
+pub fn import_shard(index: &ShardedIndex, path: &Path, expected: &[PathBuf]) -> Result<ShardImport> {
+    let shard = read_shard(path);
+    remove_shard_files(path);
+    let shard = shard?;
+
+    if let Some(root) = shard.roots.iter().find(|r| !expected.contains(r)) {
+        return Err(FlashFindError::ElevationFailed(format!(
+            "shard covers an unexpected folder: {}",
+            root.display()
+        )));
+    }
+
+    let mut import = ShardImport {
+        denied: shard.denied,
+        ..ShardImport::default()
+    };
+    for file in shard.files {
+        if !file.is_absolute() || !shard.roots.iter().any(|root| is_within(&file, root)) {
+            import.rejected += 1;
+            continue;
+        }
+        match index.insert(file) {
+            Ok(true) => import.files_added += 1,
+            Ok(false) => {}
+            Err(e) if e.is_recoverable() => warn!("Failed to import shard entry: {}", e),
+            Err(e) => return Err(e),
+        }
+    }
+
+    if import.rejected > 0 {
+        warn!("Rejected {} shard entries outside the scanned roots", import.rejected);
+    }
+    Ok(import)
+}
+
+/// Parse `<exe> --elevated-scan <shard>` and read the roots written next
+/// to the shard; `None` when not started as a helper
+pub fn helper_args(args: &[String]) -> Option<Result<(PathBuf, Vec<PathBuf>)>> {
+    match args {
+        [_, flag, shard] if flag == HELPER_FLAG => {
+            let shard = PathBuf::from(shard);
+            Some(read_roots(&shard).map(|roots| (shard, roots)))
+        }
+        _ => None,
+    }
+}
+
+/// Write the roots a helper is to scan next to its shard
+///
+/// Only the shard path goes on the command line, which stays short however
+/// many folders were denied.
+fn write_roots(shard: &Path, roots: &[PathBuf]) -> Result<()> {
+    let path = roots_file(shard);
+    fs::write(&path, bincode::serialize(roots)?).map_err(|e| FlashFindError::FileWriteError {
+        path: path.display().to_string(),
+        source: e,
+    })
+}
+
+/// Read the roots written by `write_roots`
+fn read_roots(shard: &Path) -> Result<Vec<PathBuf>> {
+    let path = roots_file(shard);
+    let data = fs::read(&path).map_err(|e| FlashFindError::FileReadError {
+        path: path.display().to_string(),
+        source: e,
+    })?;
+    let roots: Vec<PathBuf> = bincode::deserialize(&data)?;
+    if roots.is_empty() {
+        return Err(FlashFindError::ElevationFailed("no folders to scan".to_string()));
+    }
+    Ok(roots)
+}
+
+/// Body of the elevated helper process: scan `roots` and write the shard
+///
+/// Honors the cancel marker left by `ElevatedScan::cancel`, in which case
+/// nothing is written.
+pub fn run_helper(shard: &Path, roots: &[PathBuf], exclusions: &Exclusions) -> Result<()> {
+    let mut files = Vec::new();
+    let mut report = ScanReport::default();
+    // Shards carry file paths only; sizes and folders come from the next regular scan
+    for root in roots.iter().filter(|r| r.is_dir()) {
+        files.extend(
+            walk_files(root, exclusions, &mut report)
+                .into_iter()
+                .filter(|(_, meta)| !meta.is_dir)
+                .map(|(path, _)| path),
+        );
+    }
+
+    let marker = cancel_marker(shard);
+    if marker.exists() {
+        let _ = fs::remove_file(&marker);
+        return Err(FlashFindError::Cancelled);
+    }
+
+    info!("Elevated scan found {} files", files.len());
+    write_shard(shard, &ScanShard::new(roots.to_vec(), files, report.denied))
+}
+
+/// Delete leftover shards, temp files and cancel markers from earlier sessions
+pub fn cleanup_stale_shards() {
+    let Ok(entries) = fs::read_dir(std::env::temp_dir()) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with(SHARD_PREFIX) {
+            debug!("Removing stale shard file: {}", redact(&entry.path()));
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Remove a shard with its temp and roots files
+fn remove_shard_files(shard: &Path) {
+    let _ = fs::remove_file(shard);
+    let _ = fs::remove_file(shard.with_extension("tmp"));
+    let _ = fs::remove_file(roots_file(shard));
+}
+
+/// Roots the helper for `shard` is to scan
+fn roots_file(shard: &Path) -> PathBuf {
+    shard.with_extension("roots")
+}
+
+/// Marker file asking the helper to discard its results
+fn cancel_marker(shard: &Path) -> PathBuf {
+    shard.with_extension("cancel")
+}
+
+/// Progress of an elevated helper scan
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShardStatus {
+    Pending,
+    Ready,
+    TimedOut,
+}
+
+/// An elevated helper scan the app is waiting on
+///
+/// Only the helper process runs elevated; the app itself never does. The
+/// helper writes its shard with an atomic rename, so the file appearing
+/// means the scan is complete.
+#[derive(Debug)]
+pub struct ElevatedScan {
+    pub shard: PathBuf,
+    pub roots: Vec<PathBuf>,
+    started: Instant,
+}
+
+impl ElevatedScan {
+    /// Launch the helper for `roots` behind an administrator prompt
+    pub fn start(roots: Vec<PathBuf>) -> Result<Self> {
+        let stamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let shard = std::env::temp_dir().join(format!(
+            "{}{}_{}.bin",
+            SHARD_PREFIX,
+            std::process::id(),
+            stamp
+        ));
+        remove_shard_files(&shard);
+        write_roots(&shard, &roots)?;
+        launch_elevated(&shard).inspect_err(|_| remove_shard_files(&shard))?;
+
+        info!("Elevated scan started for {} directories", roots.len());
+        Ok(Self { shard, roots, started: Instant::now() })
+    }
+
+    /// Check whether the shard is ready to import
+    pub fn poll(&self) -> ShardStatus {
+        if self.shard.exists() {
+            ShardStatus::Ready
+        } else if self.started.elapsed() >= HELPER_TIMEOUT {
+            ShardStatus::TimedOut
+        } else {
+            ShardStatus::Pending
+        }
+    }
+
+    /// Abandon the scan: tell the helper to discard results and clean up
+    pub fn cancel(self) {
+        info!("Elevated scan cancelled");
+        let _ = fs::write(cancel_marker(&self.shard), b"");
+        remove_shard_files(&self.shard);
+    }
+}
+
+/// Relaunch this executable as an elevated helper via ShellExecute "runas"
+#[cfg(windows)]
+fn launch_elevated(shard: &Path) -> Result<()> {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::UI::Shell::ShellExecuteW;
+    use windows_sys::Win32::UI::WindowsAndMessaging::SW_HIDE;
+
+    let wide = |s: &OsStr| s.encode_wide().chain(std::iter::once(0)).collect::<Vec<u16>>();
+    let exe = std::env::current_exe().map_err(|e| FlashFindError::ElevationFailed(e.to_string()))?;
+
+    // Paths cannot contain quotes on Windows, and a shard path never ends
+    // in a backslash, so quoting it is enough
+    let params = format!("{} \"{}\"", HELPER_FLAG, shard.display());
+
+    let verb = wide(OsStr::new("runas"));
+    let file = wide(exe.as_os_str());
+    let params = wide(OsStr::new(&params));
+
+    // SAFETY: all strings are NUL-terminated UTF-16 buffers that outlive the call
+    let result = unsafe {
+        ShellExecuteW(0, verb.as_ptr(), file.as_ptr(), params.as_ptr(), std::ptr::null(), SW_HIDE)
+    };
+
+    // Values of 32 or less are errors, including a declined UAC prompt
+    if result <= 32 {
+        return Err(FlashFindError::ElevationFailed(
+            "the administrator prompt was declined or could not be shown".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn launch_elevated(_shard: &Path) -> Result<()> {
+    Err(FlashFindError::ElevationFailed(
+        "elevated rescans are only available on Windows".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn test_helper_scan_shard_imports_and_is_removed() {
+        let base = TestDir::new("shard_import");
+        let root = base.join("restricted");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a.txt"), b"x").unwrap();
+        fs::write(root.join("sub").join("b.txt"), b"x").unwrap();
+
+        let shard = base.join("scan.bin");
+        let roots = vec![root.clone()];
+        run_helper(&shard, &roots, &Exclusions::default()).unwrap();
+        assert!(shard.exists());
+        assert!(!shard.with_extension("tmp").exists());
+
+        let index = ShardedIndex::new();
+        let import = import_shard(&index, &shard, &roots).unwrap();
+        assert_eq!(import.files_added, 2);
+        assert_eq!(import.rejected, 0);
+        assert_eq!(index.search("b.txt"), vec![root.join("sub").join("b.txt")]);
+        assert!(!shard.exists());
+    }
+
+    #[test]
+    fn test_import_rejects_paths_outside_roots() {
+        let base = TestDir::new("shard_reject");
+        let root = base.join("root");
+        let shard = base.join("scan.bin");
+        let files = vec![root.join("ok.txt"), base.join("elsewhere.txt"), PathBuf::from("relative.txt")];
+        write_shard(&shard, &ScanShard::new(vec![root.clone()], files, Vec::new())).unwrap();
+
+        let index = ShardedIndex::new();
+        let import = import_shard(&index, &shard, std::slice::from_ref(&root)).unwrap();
+        assert_eq!(import.files_added, 1);
+        assert_eq!(import.rejected, 2);
+
+        // Roots the app did not ask for are refused outright
+        write_shard(&shard, &ScanShard::new(vec![base.to_path_buf()], Vec::new(), Vec::new())).unwrap();
+        assert!(import_shard(&index, &shard, std::slice::from_ref(&root)).is_err());
+        assert!(!shard.exists());
+    }
+
+    #[test]
+    fn test_corrupt_shard_is_removed() {
+        let base = TestDir::new("shard_corrupt");
+        let shard = base.join("scan.bin");
+        fs::write(&shard, b"not a shard").unwrap();
+
+        let index = ShardedIndex::new();
+        assert!(import_shard(&index, &shard, &[base.to_path_buf()]).is_err());
+        assert!(!shard.exists());
+        assert!(index.read().is_empty());
+    }
+
+    #[test]
+    fn test_cancelled_helper_writes_nothing() {
+        let base = TestDir::new("shard_cancel");
+        let shard = base.join("scan.bin");
+        fs::write(cancel_marker(&shard), b"").unwrap();
+
+        let result = run_helper(&shard, &[base.to_path_buf()], &Exclusions::default());
+        assert!(matches!(result, Err(FlashFindError::Cancelled)));
+        assert!(!shard.exists());
+        assert!(!cancel_marker(&shard).exists());
+    }
+
+    #[test]
+    fn test_helper_args_read_roots_beside_shard() {
+        let base = TestDir::new("shard_args");
+        let shard = base.join("scan.bin");
+        let roots: Vec<PathBuf> = (0..200).map(|n| base.join(format!("Private folder {}", n))).collect();
+        write_roots(&shard, &roots).unwrap();
+
+        let args = vec!["flashfind.exe".to_string(), HELPER_FLAG.to_string(), shard.display().to_string()];
+        let (parsed, read) = helper_args(&args).unwrap().unwrap();
+        assert_eq!(parsed, shard);
+        assert_eq!(read, roots);
+
+        // Roots never travel on the command line
+        let mut extra = args.clone();
+        extra.push(base.display().to_string());
+        assert!(helper_args(&extra).is_none());
+        assert!(helper_args(&args[..2]).is_none());
+        assert!(helper_args(&["flashfind.exe".to_string()]).is_none());
+
+        // Without its roots file the helper fails instead of scanning nothing
+        remove_shard_files(&shard);
+        assert!(helper_args(&args).unwrap().is_err());
+    }
+}
@@ -0,0 +1,1484 @@
+use ahash::AHashMap;
+use parking_lot::{MappedRwLockReadGuard, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use rayon::prelude::*;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::{FlashFindError, Result};
+use crate::fold::{fold, fold_with};
+use crate::index::{
+    compare_results, compare_sorted, fuzzy_distance, match_tier, normalize_for_prefix, parse_query, sort_results, BatchResult,
+    EntryMeta, FileIndex, IndexStatsReport, InvariantReport, MemoryBreakdown, ParsedQuery, PathPage, PreparedBatch,
+    SearchCache, SearchResult, SearchResults, SearchStop, SortOrder, StatsSnapshot, TypoTolerance, MAX_INDEX_SIZE,
+};
+use crate::paths::path_key;
+
+/// Shards every index is split into; saved indexes hold exactly this many
+pub const SHARD_COUNT: usize = 16;
+
+/// Pool slots each shard may take, so the whole index holds `MAX_INDEX_SIZE`
+const SHARD_CAPACITY: usize = MAX_INDEX_SIZE.div_ceil(SHARD_COUNT);
+
+/// The shard a new entry at `path` goes to, picked by its last component
+/// (either separator counting) folded without accents
+///
+/// Files sharing a filename key, under either accent setting, share a shard
+/// unless one was renamed (see `Routes`). FNV-1a keeps the pick the same
+/// across releases, as saved indexes depend on it.
+pub fn shard_of(path: &Path) -> usize {
+    let text = path.to_string_lossy();
+    let trimmed = text.trim_end_matches(['/', '\\']);
+    let name = trimmed.rsplit(['/', '\\']).next().unwrap_or(trimmed);
+    let hash = fold_with(name, true)
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3));
+    (hash % SHARD_COUNT as u64) as usize
+}
+
+/// Shards holding entries away from the one `shard_of` picks, by `path_key`
+///
+/// A renamed entry stays in its shard, keeping its pool slot, so its new
+/// path may lead elsewhere. Routes change only while the shards holding the
+/// entries concerned are locked. Those of removed entries linger until the
+/// next load; they still send their path to a single shard, which is all
+/// lookups need.
+#[derive(Debug, Default)]
+pub struct Routes {
+    shards: AHashMap<String, usize>,
+    /// Bumped on every change, so a writer can tell its routes still hold
+    version: u64,
+}
+
+impl Routes {
+    /// Routes to the entries of `shards` that `shard_of` places elsewhere
+    fn of(shards: &[FileIndex]) -> Self {
+        let strays: Vec<(String, usize)> = shards
+            .par_iter()
+            .enumerate()
+            .flat_map(|(shard, index)| {
+                index.par_iter().filter_map(move |(_, path)| (shard_of(&path) != shard).then(|| (path_key(&path), shard)))
+            })
+            .collect();
+        Self { shards: strays.into_iter().collect(), version: 0 }
+    }
+
+    /// The shard holding `path`, or that would take it
+    fn shard(&self, path: &Path) -> usize {
+        if self.shards.is_empty() {
+            return shard_of(path);
+        }
+        self.shards.get(&path_key(path)).copied().unwrap_or_else(|| shard_of(path))
+    }
+
+    /// Note that the entry at `old`, held by `shard`, moved to `new`, along
+    /// with everything inside it if it is a folder
+    fn moved(&mut self, old: &Path, new: &Path, shard: usize, folder: bool) {
+        let (old_key, new_key) = (path_key(old), path_key(new));
+        self.shards.remove(&old_key);
+        if folder {
+            let inside: Vec<String> = self
+                .shards
+                .keys()
+                .filter(|key| key.strip_prefix(old_key.as_str()).is_some_and(|rest| rest.starts_with(['/', '\\'])))
+                .cloned()
+                .collect();
+            for key in inside {
+                if let Some(held) = self.shards.remove(&key) {
+                    self.shards.insert(format!("{}{}", new_key, &key[old_key.len()..]), held);
+                }
+            }
+        }
+        if shard == shard_of(new) {
+            self.shards.remove(&new_key);
+        } else {
+            self.shards.insert(new_key, shard);
+        }
+        self.version += 1;
+    }
+
+    fn clear(&mut self) {
+        self.shards.clear();
+        self.version += 1;
+    }
+}
+
+/// A shard's `IndexFull` names the limit of the whole index
+fn whole_index(error: FlashFindError) -> FlashFindError {
+    match error {
+        FlashFindError::IndexFull(_) => FlashFindError::IndexFull(MAX_INDEX_SIZE),
+        error => error,
+    }
+}
+
+/// The file index split into `SHARD_COUNT` shards by filename
+///
+/// Writers change each shard's live copy under its own lock, which holds
+/// up only the writers of that shard. Searches never wait on them: they
+/// read the snapshot each shard last published, fanning out to every shard
+/// at once and merging the results into the order a single index gives.
+/// `read` takes every shard's snapshot and `write` locks every live copy,
+/// in order, for work spanning them. The routes to renamed entries are
+/// locked only for a lookup or an update, never while waiting on a shard.
+pub struct ShardedIndex {
+    shards: Box<[Shard]>,
+    routes: RwLock<Routes>,
+}
+
+impl Default for ShardedIndex {
+    fn default() -> Self {
+        Self::assemble((0..SHARD_COUNT).map(|_| FileIndex::new()).collect(), Routes::default())
+    }
+}
+
+impl ShardedIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Split a single index, as versions before v11 saved it
+    pub fn from_index(index: FileIndex) -> Self {
+        Self::assemble(index.split(SHARD_COUNT, shard_of), Routes::default())
+    }
+
+    /// Put loaded shards back together; they must be `SHARD_COUNT`, in order
+    pub fn from_shards(shards: Vec<FileIndex>) -> Result<Self> {
+        if shards.len() != SHARD_COUNT {
+            let reason = format!("{} shards instead of {}", shards.len(), SHARD_COUNT);
+            return Err(FlashFindError::CorruptedIndex(Box::new(bincode::ErrorKind::Custom(reason))));
+        }
+        let routes = Routes::of(&shards);
+        Ok(Self::assemble(shards, routes))
+    }
+
+    fn assemble(mut shards: Vec<FileIndex>, routes: Routes) -> Self {
+        for shard in &mut shards {
+            shard.set_capacity(SHARD_CAPACITY);
+        }
+        Self { shards: shards.into_iter().map(Shard::new).collect(), routes: RwLock::new(routes) }
+    }
+
+    /// Every shard, in order
+    pub fn shards(&self) -> &[Shard] {
+        &self.shards
+    }
+
+    /// The shard holding `path`, or that would take it
+    fn shard(&self, path: &Path) -> &Shard {
+        let shard = self.routes.read().shard(path);
+        &self.shards[shard]
+    }
+
+    /// Write-lock the shard holding `path`, or that would take it
+    fn shard_for(&self, path: &Path) -> ShardWriter<'_> {
+        loop {
+            let shard = self.routes.read().shard(path);
+            let writer = self.shards[shard].write();
+            // A rename may have moved the route while this waited
+            if self.routes.read().shard(path) == shard {
+                return writer;
+            }
+        }
+    }
+
+    /// Every shard's snapshot, publishing the changes of those due first
+    pub fn read(&self) -> IndexView {
+        Shards { guards: self.shards.iter().map(Shard::snapshot).collect(), routes: () }
+    }
+
+    /// Write-lock every shard's live copy, in order; the changes are
+    /// published as the lock is released
+    pub fn write(&self) -> IndexWriter<'_> {
+        let guards = self.shards.iter().map(|shard| shard.write().publishing()).collect();
+        Shards { guards, routes: &self.routes }
+    }
+
+    /// Publish every shard's changes now, so `read` and searches see them
+    pub fn publish(&self) {
+        for shard in self.shards.iter() {
+            shard.publish(true);
+        }
+    }
+
+    /// Sum of the live copies' generations, which changes whenever any shard
+    /// does, published or not; each shard is locked in turn, never all at once
+    pub fn generation(&self) -> u64 {
+        self.shards.iter().map(|shard| shard.read().generation()).sum()
+    }
+
+    /// `FileIndex::insert`, locking only the path's shard
+    pub fn insert(&self, path: PathBuf) -> Result<bool> {
+        self.shard_for(&path).insert(path).map_err(whole_index)
+    }
+
+    /// `FileIndex::insert_with_meta`, locking only the path's shard
+    pub fn insert_with_meta(&self, path: PathBuf, meta: EntryMeta) -> Result<bool> {
+        self.shard_for(&path).insert_with_meta(path, meta).map_err(whole_index)
+    }
+
+    /// `FileIndex::insert_batch`, locking each shard only for its own paths
+    ///
+    /// With `list_added`, the entries it added come back too, with their
+    /// metadata. A full shard stops the batch, keeping what went in before.
+    pub fn insert_batch(&self, batch: PreparedBatch, list_added: bool) -> Result<(BatchResult, Vec<(PathBuf, EntryMeta)>)> {
+        let mut result = BatchResult::default();
+        let mut added = Vec::new();
+        let mut rerouted = Vec::new();
+        let (batches, version) = {
+            let routes = self.routes.read();
+            (batch.split(SHARD_COUNT, |path| routes.shard(path)), routes.version)
+        };
+        for (index, (shard, mut batch)) in self.shards.iter().zip(batches).enumerate() {
+            if batch.is_empty() {
+                continue;
+            }
+            let mut shard = shard.write();
+            // Paths a rename moved while this waited go round again
+            let routes = self.routes.read();
+            if routes.version != version {
+                rerouted.push(batch.take_where(|path| routes.shard(path) != index));
+            }
+            drop(routes);
+            let first_slot = shard.pool_size();
+            let inserted = shard.insert_batch(batch).map_err(whole_index)?;
+            if list_added && inserted.added > 0 {
+                added.extend(shard.entries_since(first_slot));
+            }
+            result.added += inserted.added;
+            result.duplicates += inserted.duplicates;
+            result.invalid += inserted.invalid;
+        }
+        for batch in rerouted.into_iter().filter(|batch| !batch.is_empty()) {
+            let (inserted, more) = self.insert_batch(batch, list_added)?;
+            added.extend(more);
+            result.added += inserted.added;
+            result.duplicates += inserted.duplicates;
+            result.invalid += inserted.invalid;
+        }
+        Ok((result, added))
+    }
+
+    /// `FileIndex::remove`, locking only the path's shard
+    pub fn remove(&self, path: &Path) -> Result<bool> {
+        self.shard_for(path).remove(path)
+    }
+
+    /// `IndexWriter::rename`, locking only the shard holding `old` and the
+    /// one `new` leads to; a folder locks every shard, as what it holds is
+    /// spread over them
+    pub fn rename(&self, old: &Path, new: PathBuf) -> Result<bool> {
+        loop {
+            let routes_now = || {
+                let routes = self.routes.read();
+                (routes.shard(old), routes.shard(&new))
+            };
+            let (from, to) = routes_now();
+            let mut low = self.shards[from.min(to)].write();
+            let mut high = (from != to).then(|| self.shards[from.max(to)].write());
+            // A rename may have moved either route while this waited
+            if routes_now() != (from, to) {
+                continue;
+            }
+
+            let holder = match &high {
+                Some(high) if from > to => high,
+                _ => &low,
+            };
+            let Some(meta) = holder.metadata_for(old) else {
+                return Ok(false);
+            };
+            if meta.is_dir {
+                drop((low, high));
+                return self.write().rename(old, new);
+            }
+            if new.to_str().is_none() || new.file_name().is_none() {
+                return Err(FlashFindError::InvalidPath(new.display().to_string()));
+            }
+
+            let (holder, replaced) = match high.as_mut() {
+                Some(high) if from > to => (high, Some(&mut low)),
+                high => (&mut low, high),
+            };
+            if let Some(replaced) = replaced {
+                replaced.remove(&new)?;
+            }
+            holder.rename(old, new.clone())?;
+            self.routes.write().moved(old, &new, from, false);
+            return Ok(true);
+        }
+    }
+
+    /// Whether `path` is an indexed folder, by the live copy of its shard
+    pub fn is_directory(&self, path: &Path) -> bool {
+        self.shard(path).read().is_directory(path)
+    }
+
+    /// The metadata of `path` as searches see it
+    pub fn metadata_for(&self, path: &Path) -> Option<EntryMeta> {
+        self.shard(path).snapshot().metadata_for(path)
+    }
+
+    /// `FileIndex::describe` on the snapshots
+    pub fn describe(&self, paths: impl IntoIterator<Item = PathBuf>) -> Vec<SearchResult> {
+        paths
+            .into_iter()
+            .map(|path| {
+                let meta = self.metadata_for(&path).unwrap_or(EntryMeta::UNKNOWN);
+                SearchResult::new(path, meta)
+            })
+            .collect()
+    }
+
+    /// Count a filesystem event delivered by the watcher
+    pub fn record_watcher_event(&self) {
+        self.shards[0].snapshot().record_watcher_event();
+    }
+
+    /// Run `search` on every shard's snapshot at once, each on a thread of
+    /// its own
+    ///
+    /// Plain threads rather than rayon tasks, so a shard's search never
+    /// queues behind rayon work such as a scan's.
+    fn fan_out<S, T, F>(&self, states: impl IntoIterator<Item = S>, search: F) -> Vec<T>
+    where
+        S: Send,
+        T: Send,
+        F: Fn(&FileIndex, S) -> T + Sync,
+    {
+        let view = self.read();
+        let found = thread::scope(|scope| {
+            let search = &search;
+            let searches: Vec<_> = view
+                .shards()
+                .zip(states)
+                .map(|(shard, state)| scope.spawn(move || search(shard, state)))
+                .collect();
+            searches
+                .into_iter()
+                .map(|search| search.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+                .collect()
+        });
+        // The last reader of a replaced snapshot frees it, which can take
+        // longer than the search did; that happens off the search's path
+        if view.guards.iter().any(|shard| Arc::strong_count(shard) == 1) {
+            thread::spawn(move || drop(view));
+        }
+        found
+    }
+
+    /// `FileIndex::search_refining` across the shards, each narrowing its
+    /// own part of `cache`
+    ///
+    /// The merged results keep the order and cap of a single index: by name,
+    /// the best matching tiers when more than `limit` matched; by date or
+    /// size, the newest or largest.
+    pub fn search_refining(
+        &self,
+        query: &str,
+        limit: usize,
+        sort: SortOrder,
+        cache: &mut ShardedCache,
+        stop: &SearchStop,
+    ) -> Result<SearchResults> {
+        cache.shards.resize_with(SHARD_COUNT, || None);
+        let found = self.fan_out(cache.shards.iter_mut(), |shard, cache| {
+            shard.search_refining(query, limit, sort, cache, stop).map(|found| with_meta(shard, found, sort))
+        });
+        let found = found.into_iter().collect::<Result<Vec<_>>>()?;
+        if sort != SortOrder::Name {
+            return Ok(merge_sorted(found, limit, sort));
+        }
+
+        let (mut truncated, timed_out) = flags(&found);
+        let mut paths: Vec<PathBuf> = found.into_iter().flat_map(|(found, _)| found.paths).collect();
+        if paths.len() > limit {
+            paths = best_tiers(paths, &parse_query(query), limit);
+            truncated = true;
+        }
+        Ok(SearchResults { paths: sort_results(paths), truncated, timed_out })
+    }
+
+    /// `FileIndex::search_fuzzy` across the shards; by name, the fewest
+    /// typos still come first
+    pub fn search_fuzzy(&self, query: &str, limit: usize, sort: SortOrder, tolerance: TypoTolerance) -> SearchResults {
+        let found = self.fan_out(std::iter::repeat(()), |shard, ()| {
+            with_meta(shard, shard.search_fuzzy(query, limit, sort, tolerance), sort)
+        });
+        if sort != SortOrder::Name {
+            return merge_sorted(found, limit, sort);
+        }
+        let ParsedQuery::Filename(needle) = parse_query(query) else {
+            return SearchResults::default();
+        };
+
+        let max = tolerance.max_typos(needle.chars().count());
+        let (mut truncated, _) = flags(&found);
+        let mut scored: Vec<(usize, PathBuf)> = found
+            .into_iter()
+            .flat_map(|(found, _)| found.paths)
+            .map(|path| {
+                let name = path.file_name().map(|name| fold(&name.to_string_lossy())).unwrap_or_default();
+                (fuzzy_distance(&name, &needle, max).unwrap_or(max), path)
+            })
+            .collect();
+        scored.par_sort_unstable_by(|a, b| a.0.cmp(&b.0).then_with(|| compare_results(&a.1, &b.1)));
+        truncated |= scored.len() > limit;
+        scored.truncate(limit);
+        SearchResults { paths: scored.into_iter().map(|(_, path)| path).collect(), truncated, timed_out: false }
+    }
+
+    /// `FileIndex::browse_all` across the shards
+    pub fn browse_all(&self, offset: usize, limit: usize) -> PathPage {
+        // Each shard lists its share of everything up to the page's end
+        let pages = self.fan_out(std::iter::repeat(()), |shard, ()| shard.browse_all(0, offset.saturating_add(limit)));
+        merge_pages(pages, offset, limit)
+    }
+
+    /// `FileIndex::search_path_prefix` across the shards; a drive is only
+    /// missing if no shard holds anything on it
+    pub fn search_path_prefix(&self, query: &str, offset: usize, limit: usize) -> Result<PathPage> {
+        let pages = self.fan_out(std::iter::repeat(()), |shard, ()| {
+            shard.search_path_prefix(query, 0, offset.saturating_add(limit))
+        });
+        let mut found = Vec::new();
+        let mut missing = None;
+        for page in pages {
+            match page {
+                Ok(page) => found.push(page),
+                Err(e @ FlashFindError::DriveNotIndexed(_)) => missing = Some(e),
+                Err(e) => return Err(e),
+            }
+        }
+        match missing {
+            Some(e) if found.is_empty() => Err(e),
+            _ => Ok(merge_pages(found, offset, limit)),
+        }
+    }
+
+    /// Every match of `query`, by name, once every change is published;
+    /// see `FileIndex::search`
+    #[cfg(test)]
+    pub fn search(&self, query: &str) -> Vec<PathBuf> {
+        self.publish();
+        let cancel = std::sync::atomic::AtomicBool::new(false);
+        self.search_refining(query, usize::MAX, SortOrder::Name, &mut ShardedCache::default(), &SearchStop::new(&cancel, None))
+            .expect("an uncancelled search cannot fail")
+            .paths
+    }
+}
+
+/// How long a shard's changes may wait before searches see them
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Entries a shard's changes may touch between publishes and still be
+/// replayed on its spare copy; past that, copying the live copy is cheaper
+const REPLAY_LIMIT: usize = 1_024;
+
+/// One shard: the live copy writers change, and the snapshot searches read
+///
+/// Publishing makes the live copy the snapshot, sharing it, so a snapshot
+/// never changes while searches read it. The next write takes over the
+/// snapshot published before, once no search holds it, and makes the few
+/// changes it lacks again, rather than copying the whole shard (see
+/// `ShardWriter`). Writers publish once `PUBLISH_INTERVAL` has passed since
+/// the last time, and so do readers that find the live copy unlocked.
+pub struct Shard {
+    live: RwLock<Live>,
+    published: Mutex<Published>,
+}
+
+struct Published {
+    index: Arc<FileIndex>,
+    at: Instant,
+}
+
+/// A shard's live copy, with what catches up its spare copy
+struct Live {
+    index: Arc<FileIndex>,
+    /// Changes since the last publish; `None` once one was made that is not
+    /// logged, or they touched more than `REPLAY_LIMIT` entries
+    changes: Option<Vec<Change>>,
+    /// Entries `changes` touch
+    logged: usize,
+    /// The snapshot published before the last one, with the changes it lacks
+    spare: Option<(Arc<FileIndex>, Vec<Change>)>,
+    /// Times a write had to copy the whole shard
+    #[cfg(test)]
+    copies: usize,
+}
+
+impl Live {
+    fn new(index: Arc<FileIndex>) -> Self {
+        Self {
+            index,
+            changes: Some(Vec::new()),
+            logged: 0,
+            spare: None,
+            #[cfg(test)]
+            copies: 0,
+        }
+    }
+
+    /// Log a change touching `entries` entries, unless too many were
+    fn log(&mut self, entries: usize, change: impl FnOnce() -> Change) {
+        self.logged += entries;
+        if self.logged > REPLAY_LIMIT {
+            self.changes = None;
+        }
+        if let Some(changes) = &mut self.changes {
+            changes.push(change());
+        }
+    }
+
+    /// The live copy to change, unshared from the snapshot first: the spare
+    /// copy caught up if no search holds it, or else a copy
+    fn index_mut(&mut self) -> &mut FileIndex {
+        let spare = self.spare.take();
+        if Arc::get_mut(&mut self.index).is_none() {
+            let current = &self.index;
+            let caught_up = spare.and_then(|(mut index, changes)| {
+                Arc::get_mut(&mut index)?.catch_up(current, |index| changes.into_iter().for_each(|change| change.apply(index)));
+                Some(index)
+            });
+            if let Some(index) = caught_up {
+                self.index = index;
+            } else {
+                #[cfg(test)]
+                {
+                    self.copies += 1;
+                }
+            }
+        }
+        Arc::make_mut(&mut self.index)
+    }
+}
+
+/// A change a writer made to a shard, logged to make it again on the spare
+/// copy
+enum Change {
+    Insert(PathBuf, EntryMeta),
+    Batch(PreparedBatch),
+    Remove(PathBuf),
+    Rename(PathBuf, PathBuf),
+    MoveContents(PathBuf, PathBuf),
+}
+
+impl Change {
+    /// Make the change on a copy lacking it, which fails where it did first
+    fn apply(self, index: &mut FileIndex) {
+        let _ = match self {
+            Change::Insert(path, meta) => index.insert_with_meta(path, meta).map(drop),
+            Change::Batch(batch) => index.insert_batch(batch).map(drop),
+            Change::Remove(path) => index.remove(&path).map(drop),
+            Change::Rename(old, new) => index.rename(&old, new).map(drop),
+            Change::MoveContents(old, new) => index.move_contents(&old, &new).map(drop),
+        };
+    }
+}
+
+impl Shard {
+    fn new(index: FileIndex) -> Self {
+        let index = Arc::new(index);
+        Self {
+            live: RwLock::new(Live::new(index.clone())),
+            published: Mutex::new(Published { index, at: Instant::now() }),
+        }
+    }
+
+    /// Read-lock the live copy
+    pub fn read(&self) -> MappedRwLockReadGuard<'_, FileIndex> {
+        RwLockReadGuard::map(self.live.read(), |live| &*live.index)
+    }
+
+    /// Write-lock the live copy; its changes are published once due
+    pub fn write(&self) -> ShardWriter<'_> {
+        ShardWriter { shard: self, live: self.live.write(), force: false }
+    }
+
+    /// The last published snapshot, publishing first if that is due and no
+    /// one holds the live copy
+    pub fn snapshot(&self) -> Arc<FileIndex> {
+        if let Some(mut live) = self.live.try_write() {
+            self.publish_from(&mut live, false);
+        }
+        self.published.lock().index.clone()
+    }
+
+    /// Publish the live copy, waiting for its writer; with `force`, even if
+    /// the last publish was under `PUBLISH_INTERVAL` ago
+    fn publish(&self, force: bool) {
+        self.publish_from(&mut self.live.write(), force);
+    }
+
+    fn publish_from(&self, live: &mut Live, force: bool) {
+        let mut published = self.published.lock();
+        if Arc::ptr_eq(&published.index, &live.index) || !(force || published.at.elapsed() >= PUBLISH_INTERVAL) {
+            return;
+        }
+        let previous = std::mem::replace(&mut published.index, live.index.clone());
+        published.at = Instant::now();
+        drop(published);
+        // Kept for the next write, or freed if it cannot be caught up
+        live.spare = live.changes.replace(Vec::new()).map(|changes| (previous, changes));
+        live.logged = 0;
+    }
+
+    /// Times a write copied the whole shard
+    #[cfg(test)]
+    fn copies(&self) -> usize {
+        self.live.read().copies
+    }
+}
+
+/// A shard's live copy, write-locked
+///
+/// The changes made through its own methods are logged to catch up the
+/// spare copy; any other change through `DerefMut` stops that until the
+/// next publish, so the write after it copies the shard.
+pub struct ShardWriter<'a> {
+    shard: &'a Shard,
+    live: RwLockWriteGuard<'a, Live>,
+    /// Publish on release even if the last publish was recent
+    force: bool,
+}
+
+impl ShardWriter<'_> {
+    fn publishing(mut self) -> Self {
+        self.force = true;
+        self
+    }
+
+    pub fn insert(&mut self, path: PathBuf) -> Result<bool> {
+        self.live.log(1, || Change::Insert(path.clone(), EntryMeta::UNKNOWN));
+        self.live.index_mut().insert(path)
+    }
+
+    pub fn insert_with_meta(&mut self, path: PathBuf, meta: EntryMeta) -> Result<bool> {
+        self.live.log(1, || Change::Insert(path.clone(), meta));
+        self.live.index_mut().insert_with_meta(path, meta)
+    }
+
+    pub fn insert_batch(&mut self, batch: PreparedBatch) -> Result<BatchResult> {
+        self.live.log(batch.len(), || Change::Batch(batch.clone()));
+        self.live.index_mut().insert_batch(batch)
+    }
+
+    pub fn remove(&mut self, path: &Path) -> Result<bool> {
+        self.live.log(1, || Change::Remove(path.to_path_buf()));
+        self.live.index_mut().remove(path)
+    }
+
+    pub fn rename(&mut self, old: &Path, new: PathBuf) -> Result<bool> {
+        self.live.log(1, || Change::Rename(old.to_path_buf(), new.clone()));
+        self.live.index_mut().rename(old, new)
+    }
+
+    pub fn move_contents(&mut self, old: &Path, new: &Path) -> Result<usize> {
+        self.live.log(1, || Change::MoveContents(old.to_path_buf(), new.to_path_buf()));
+        self.live.index_mut().move_contents(old, new)
+    }
+}
+
+impl Deref for ShardWriter<'_> {
+    type Target = FileIndex;
+
+    fn deref(&self) -> &FileIndex {
+        &self.live.index
+    }
+}
+
+impl DerefMut for ShardWriter<'_> {
+    fn deref_mut(&mut self) -> &mut FileIndex {
+        self.live.changes = None;
+        self.live.index_mut()
+    }
+}
+
+impl Drop for ShardWriter<'_> {
+    fn drop(&mut self) {
+        self.shard.publish_from(&mut self.live, self.force);
+    }
+}
+
+/// What each shard's last search left for the next one to narrow (see
+/// `FileIndex::search_refining`)
+#[derive(Debug, Default)]
+pub struct ShardedCache {
+    shards: Vec<Option<SearchCache>>,
+}
+
+impl ShardedCache {
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(Option::is_none)
+    }
+}
+
+/// A shard's results, with their metadata when `sort` orders by it
+fn with_meta(shard: &FileIndex, found: SearchResults, sort: SortOrder) -> (SearchResults, Vec<EntryMeta>) {
+    let metas = match sort {
+        SortOrder::Name => Vec::new(),
+        _ => found.paths.iter().map(|path| shard.metadata_for(path).unwrap_or(EntryMeta::UNKNOWN)).collect(),
+    };
+    (found, metas)
+}
+
+/// Whether any shard's results were truncated, and whether any timed out
+fn flags(found: &[(SearchResults, Vec<EntryMeta>)]) -> (bool, bool) {
+    (found.iter().any(|(found, _)| found.truncated), found.iter().any(|(found, _)| found.timed_out))
+}
+
+/// The shards' results in `sort` order, keeping the first `limit`
+///
+/// Each shard kept its own first `limit`, so these are the first of all.
+fn merge_sorted(found: Vec<(SearchResults, Vec<EntryMeta>)>, limit: usize, sort: SortOrder) -> SearchResults {
+    let (mut truncated, timed_out) = flags(&found);
+    let mut entries: Vec<(PathBuf, EntryMeta)> =
+        found.into_iter().flat_map(|(found, metas)| found.paths.into_iter().zip(metas)).collect();
+    entries.par_sort_unstable_by(|a, b| compare_sorted(sort, (&a.0, a.1), (&b.0, b.1)));
+    truncated |= entries.len() > limit;
+    entries.truncate(limit);
+    SearchResults { paths: entries.into_iter().map(|(path, _)| path).collect(), truncated, timed_out }
+}
+
+/// The `limit` best matches of a filename query, best tier first as one
+/// index gathers them (acronym matches last), then by name; any other
+/// query keeps the first by name
+fn best_tiers(paths: Vec<PathBuf>, parsed: &ParsedQuery, limit: usize) -> Vec<PathBuf> {
+    let mut paths = sort_results(paths);
+    if let ParsedQuery::Filename(needle) = parsed {
+        let tier = |path: &PathBuf| {
+            let tier = path.file_name().and_then(|name| name.to_str()).and_then(|name| match_tier(&fold(name), needle));
+            (tier.is_none(), tier)
+        };
+        // Stable, so each tier stays in name order
+        paths.par_sort_by_cached_key(tier);
+    }
+    paths.truncate(limit);
+    paths
+}
+
+/// One page of the shards' listings, each sorted by path and covering the
+/// page's end
+fn merge_pages(pages: Vec<PathPage>, offset: usize, limit: usize) -> PathPage {
+    let total = pages.iter().map(|page| page.total).sum();
+    let mut keyed: Vec<(String, PathBuf)> = pages
+        .into_iter()
+        .flat_map(|page| page.paths)
+        .map(|path| (normalize_for_prefix(&path.to_string_lossy()), path))
+        .collect();
+    keyed.par_sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    let paths = keyed.into_iter().skip(offset).take(limit).map(|(_, path)| path).collect();
+    PathPage { paths, total }
+}
+
+/// Every shard, as one kind of snapshot or lock guard, with the routes
+/// to renamed entries where they may change
+pub struct Shards<G, R = ()> {
+    guards: Vec<G>,
+    routes: R,
+}
+
+/// Every shard's snapshot (see `ShardedIndex::read`)
+pub type IndexView = Shards<Arc<FileIndex>>;
+
+/// Every shard's live copy write-locked (see `ShardedIndex::write`)
+pub type IndexWriter<'a> = Shards<ShardWriter<'a>, &'a RwLock<Routes>>;
+
+impl<G: Deref<Target = FileIndex> + Sync, R> Shards<G, R> {
+    /// Each shard, in order
+    pub fn shards(&self) -> impl Iterator<Item = &FileIndex> {
+        self.guards.iter().map(|guard| &**guard)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards().map(FileIndex::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards().all(FileIndex::is_empty)
+    }
+
+    /// See `ShardedIndex::generation`
+    pub fn generation(&self) -> u64 {
+        self.shards().map(FileIndex::generation).sum()
+    }
+
+    pub fn tombstones(&self) -> usize {
+        self.shards().map(FileIndex::tombstones).sum()
+    }
+
+    /// Share of all pool slots held by removed entries
+    pub fn dead_ratio(&self) -> f64 {
+        let slots: usize = self.shards().map(FileIndex::pool_size).sum();
+        if slots == 0 {
+            0.0
+        } else {
+            self.tombstones() as f64 / slots as f64
+        }
+    }
+
+    /// Counters of every shard together
+    ///
+    /// Every search runs on every shard, so searches and cache lookups are
+    /// the most any shard counted rather than the sum.
+    pub fn stats(&self) -> IndexStatsReport {
+        let mut report = IndexStatsReport::default();
+        for stats in self.shards().map(FileIndex::stats) {
+            add_counts(&mut report.lifetime, stats.lifetime);
+            add_counts(&mut report.session, stats.session);
+            report.cache.hits = report.cache.hits.max(stats.cache.hits);
+            report.cache.misses = report.cache.misses.max(stats.cache.misses);
+        }
+        report
+    }
+
+    pub fn trigram_memory(&self) -> Option<usize> {
+        self.shards().map(FileIndex::trigram_memory).sum()
+    }
+
+    pub fn memory_usage(&self) -> MemoryBreakdown {
+        let mut total = MemoryBreakdown::default();
+        for part in self.shards().map(FileIndex::memory_usage) {
+            total.pool += part.pool;
+            total.metadata += part.metadata;
+            total.filename_index += part.filename_index;
+            total.extension_index += part.extension_index;
+            total.word_index += part.word_index;
+            total.directory_index += part.directory_index;
+            total.seen_paths += part.seen_paths;
+            total.optional += part.optional;
+        }
+        total
+    }
+
+    /// Every live entry with its metadata, shard by shard
+    pub fn iter(&self) -> impl Iterator<Item = (PathBuf, EntryMeta)> + '_ {
+        self.shards().flat_map(|shard| shard.iter().map(move |(idx, path)| (path, shard.meta_of(idx))))
+    }
+
+    /// `iter` for rayon consumers
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = (PathBuf, EntryMeta)> + '_ {
+        self.guards
+            .par_iter()
+            .flat_map(|shard| shard.par_iter().map(move |(idx, path)| (path, shard.meta_of(idx))))
+    }
+
+    /// `FileIndex::sample`, an equal share from each shard
+    pub fn sample(&self, count: usize, seed: u64) -> Vec<PathBuf> {
+        let share = count.div_ceil(self.guards.len());
+        let mut sample: Vec<PathBuf> = self.shards().flat_map(|shard| shard.sample(share, seed)).collect();
+        sample.truncate(count);
+        sample
+    }
+
+    pub fn paths_within(&self, root: &Path) -> Vec<PathBuf> {
+        self.shards().flat_map(|shard| shard.paths_within(root)).collect()
+    }
+
+    pub fn files_with_extensions(&self, extensions: &[&str]) -> Vec<PathBuf> {
+        self.shards().flat_map(|shard| shard.files_with_extensions(extensions)).collect()
+    }
+
+    /// `FileIndex::recent` across the shards, newest first
+    pub fn recent(&self, limit: usize) -> Vec<SearchResult> {
+        let mut recent: Vec<SearchResult> = self.shards().flat_map(|shard| shard.recent(limit)).collect();
+        recent.sort_unstable_by(|a, b| b.modified.cmp(&a.modified).then_with(|| compare_results(&a.path, &b.path)));
+        recent.truncate(limit);
+        recent
+    }
+
+    /// `FileIndex::extension_counts` across the shards
+    pub fn extension_counts(&self) -> Vec<(String, usize)> {
+        let mut totals: AHashMap<String, usize> = AHashMap::new();
+        for (ext, files) in self.shards().flat_map(FileIndex::extension_counts) {
+            *totals.entry(ext).or_default() += files;
+        }
+        let mut counts: Vec<(String, usize)> = totals.into_iter().collect();
+        counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts
+    }
+
+    pub fn count_with_extensions(&self, extensions: &[String]) -> usize {
+        self.shards().map(|shard| shard.count_with_extensions(extensions)).sum()
+    }
+
+    /// `FileIndex::drive_counts` across the shards
+    pub fn drive_counts(&self) -> Vec<(char, usize)> {
+        let mut counts: Vec<(char, usize)> = Vec::new();
+        for (letter, files) in self.shards().flat_map(FileIndex::drive_counts) {
+            match counts.iter_mut().find(|(known, _)| *known == letter) {
+                Some((_, total)) => *total += files,
+                None => counts.push((letter, files)),
+            }
+        }
+        counts.sort_unstable();
+        counts
+    }
+
+    /// The `count` folders holding the most entries directly, largest first,
+    /// each as its entries spell it
+    pub fn largest_folders(&self, count: usize) -> Vec<(PathBuf, usize)> {
+        let mut totals: AHashMap<&str, usize> = AHashMap::new();
+        for (folder, entries) in self.shards().flat_map(FileIndex::folder_counts) {
+            *totals.entry(folder).or_default() += entries;
+        }
+        let mut largest: Vec<(&str, usize)> = totals.into_iter().collect();
+        largest.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        largest
+            .into_iter()
+            .take(count)
+            .filter_map(|(folder, entries)| Some((self.shards().find_map(|shard| shard.folder_path(folder))?, entries)))
+            .collect()
+    }
+
+    #[cfg(test)]
+    pub fn check_invariants(&self) -> InvariantReport {
+        self.shards().map(FileIndex::check_invariants).fold(InvariantReport::default(), add_issues)
+    }
+}
+
+impl<'a> IndexWriter<'a> {
+    fn shard_mut(&mut self, path: &Path) -> &mut ShardWriter<'a> {
+        let shard = self.routes.read().shard(path);
+        &mut self.guards[shard]
+    }
+
+    pub fn insert(&mut self, path: PathBuf) -> Result<bool> {
+        self.shard_mut(&path).insert(path).map_err(whole_index)
+    }
+
+    /// Move the entry at `old` to `new` (see `FileIndex::rename`)
+    ///
+    /// The entry stays in its shard, keeping its slot, wherever `shard_of`
+    /// would put a new entry at `new`; an entry already at `new` is replaced
+    /// from whichever shard held it. The entries inside a folder move within
+    /// their own shards.
+    pub fn rename(&mut self, old: &Path, new: PathBuf) -> Result<bool> {
+        let (from, to) = {
+            let routes = self.routes.read();
+            (routes.shard(old), routes.shard(&new))
+        };
+        let Some(meta) = self.guards[from].metadata_for(old) else {
+            return Ok(false);
+        };
+        if new.to_str().is_none() || new.file_name().is_none() {
+            return Err(FlashFindError::InvalidPath(new.display().to_string()));
+        }
+
+        if from != to {
+            self.guards[to].remove(&new)?;
+        }
+        self.guards[from].rename(old, new.clone())?;
+        if meta.is_dir {
+            for (shard, guard) in self.guards.iter_mut().enumerate() {
+                // The rename took its own shard's entries along
+                if shard != from {
+                    guard.move_contents(old, &new)?;
+                }
+            }
+        }
+        self.routes.write().moved(old, &new, from, meta.is_dir);
+        Ok(true)
+    }
+
+    pub fn remove_where<F: Fn(&Path) -> bool>(&mut self, predicate: F) -> usize {
+        self.guards.iter_mut().map(|shard| shard.remove_where(&predicate)).sum()
+    }
+
+    pub fn remove_files_where<F: Fn(&Path) -> bool>(&mut self, predicate: F) -> usize {
+        self.guards.iter_mut().map(|shard| shard.remove_files_where(&predicate)).sum()
+    }
+
+    pub fn remove_directories(&mut self) -> usize {
+        self.guards.iter_mut().map(|shard| shard.remove_directories()).sum()
+    }
+
+    pub fn remove_drive(&mut self, letter: char) -> usize {
+        self.guards.iter_mut().map(|shard| shard.remove_drive(letter)).sum()
+    }
+
+    pub fn clear(&mut self) {
+        for shard in &mut self.guards {
+            shard.clear();
+        }
+        self.routes.write().clear();
+    }
+
+    /// `FileIndex::repair` on every shard, with the problems of all
+    pub fn repair(&mut self) -> InvariantReport {
+        self.guards.iter_mut().map(|shard| shard.repair()).fold(InvariantReport::default(), add_issues)
+    }
+
+    pub fn sync_key_folding(&mut self) {
+        for shard in &mut self.guards {
+            shard.sync_key_folding();
+        }
+    }
+
+    pub fn set_trigram_index(&mut self, enabled: bool) {
+        for shard in &mut self.guards {
+            shard.set_trigram_index(enabled);
+        }
+    }
+
+    pub fn set_acronym_matching(&mut self, enabled: bool) {
+        for shard in &mut self.guards {
+            shard.set_acronym_matching(enabled);
+        }
+    }
+
+    pub fn set_show_hidden(&mut self, show: bool) {
+        for shard in &mut self.guards {
+            shard.set_show_hidden(show);
+        }
+    }
+}
+
+fn add_counts(total: &mut StatsSnapshot, part: StatsSnapshot) {
+    total.insertions += part.insertions;
+    total.duplicates += part.duplicates;
+    total.removals += part.removals;
+    total.searches = total.searches.max(part.searches);
+    total.watcher_events += part.watcher_events;
+}
+
+fn add_issues(total: InvariantReport, part: InvariantReport) -> InvariantReport {
+    InvariantReport {
+        dangling_ids: total.dangling_ids + part.dangling_ids,
+        mismatched_ids: total.mismatched_ids + part.mismatched_ids,
+        stale_ids: total.stale_ids + part.stale_ids,
+        unindexed_entries: total.unindexed_entries + part.unindexed_entries,
+        bad_seen_paths: total.bad_seen_paths + part.bad_seen_paths,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::{Duration, Instant};
+
+    fn meta(size: u64, modified: i64) -> EntryMeta {
+        EntryMeta { size, modified, is_dir: false, attributes: 0 }
+    }
+
+    /// Two names landing in different shards, starting with `prefix`
+    fn apart(prefix: &str) -> (String, String) {
+        let first = format!("{}_0.txt", prefix);
+        let second = (1..)
+            .map(|i| format!("{}_{}.txt", prefix, i))
+            .find(|name| shard_of(Path::new(name)) != shard_of(Path::new(&first)))
+            .unwrap();
+        (first, second)
+    }
+
+    /// The same entries in one index and split into shards
+    fn both() -> (FileIndex, ShardedIndex) {
+        let mut single = FileIndex::new();
+        let sharded = ShardedIndex::new();
+        for i in 0..3_000_i64 {
+            let path = match i % 4 {
+                0 => format!("/data/{}/report_{}.pdf", i % 7, i),
+                1 => format!("C:\\Users\\me\\Quarterly Report {}.docx", i),
+                2 => format!("/archive/{}/rpt-{}.txt", i % 11, i),
+                _ => format!("/data/{}/Reprot {}.md", i % 5, i),
+            };
+            let meta = meta(((i * 7_919) % 5_000) as u64, 1_600_000_000 + (i * 104_729) % 50_000);
+            single.insert_with_meta(PathBuf::from(&path), meta).unwrap();
+            sharded.insert_with_meta(PathBuf::from(path), meta).unwrap();
+        }
+        sharded.publish();
+        (single, sharded)
+    }
+
+    fn search_limited(index: &ShardedIndex, query: &str, limit: usize, sort: SortOrder) -> SearchResults {
+        let cancel = AtomicBool::new(false);
+        index
+            .search_refining(query, limit, sort, &mut ShardedCache::default(), &SearchStop::new(&cancel, None))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_equal_filename_keys_share_a_shard() {
+        let shard = shard_of(Path::new("/docs/Report.pdf"));
+        for path in ["C:\\Work\\report.pdf", "/old/RÉPORT.pdf", "report.pdf"] {
+            assert_eq!(shard_of(Path::new(path)), shard, "{}", path);
+        }
+        assert_eq!(shard_of(Path::new("/docs/Work/")), shard_of(Path::new("C:\\Work")));
+
+        // Names spread over every shard
+        let used: ahash::AHashSet<usize> = (0..1_000).map(|i| shard_of(Path::new(&format!("/f/{}.txt", i)))).collect();
+        assert_eq!(used.len(), SHARD_COUNT);
+
+        let index = ShardedIndex::new();
+        assert!(index.insert(PathBuf::from("C:\\Docs\\Report.pdf")).unwrap());
+        assert!(!index.insert(PathBuf::from("c:\\docs\\report.pdf")).unwrap());
+        assert!(index.insert(PathBuf::from("/old/report.pdf")).unwrap());
+        index.publish();
+        let view = index.read();
+        assert_eq!(view.shards().filter(|shard| !shard.is_empty()).count(), 1);
+        assert_eq!(view.len(), 2);
+    }
+
+    #[test]
+    fn test_merged_searches_match_a_single_index() {
+        let (single, sharded) = both();
+        let sorts = [
+            SortOrder::Name,
+            SortOrder::NameAsc,
+            SortOrder::NameDesc,
+            SortOrder::ModifiedDesc,
+            SortOrder::SizeDesc,
+            SortOrder::DepthAsc,
+        ];
+        for query in ["report", "rep", "qr", "*.pdf", "report_1", "path:data", "report NOT 1", "in:archive rpt"] {
+            for sort in sorts {
+                for limit in [usize::MAX, 50, 7] {
+                    let expected = single.search_limited(query, limit, sort);
+                    let found = search_limited(&sharded, query, limit, sort);
+                    assert_eq!(found.truncated, expected.truncated, "{:?} by {:?} up to {}", query, sort, limit);
+                    let by_tier = matches!(parse_query(query), ParsedQuery::Filename(_));
+                    if sort == SortOrder::Name && expected.truncated && !by_tier {
+                        // A capped scan keeps whichever matches each shard met first
+                        let all = single.search(query);
+                        assert!(found.paths.len() <= limit, "{:?}", query);
+                        assert!(found.paths.iter().all(|path| all.contains(path)), "{:?} up to {}", query, limit);
+                        assert_eq!(found.paths, sort_results(found.paths.clone()));
+                    } else {
+                        assert_eq!(found.paths, expected.paths, "{:?} by {:?} up to {}", query, sort, limit);
+                    }
+                }
+            }
+        }
+
+        for tolerance in [TypoTolerance::Low, TypoTolerance::High] {
+            for sort in sorts {
+                for limit in [usize::MAX, 20] {
+                    let expected = single.search_fuzzy("reoprt", limit, sort, tolerance);
+                    let found = sharded.search_fuzzy("reoprt", limit, sort, tolerance);
+                    assert_eq!(found.paths, expected.paths, "{:?} by {:?} up to {}", tolerance, sort, limit);
+                }
+            }
+        }
+
+        let page = |page: PathPage| (page.paths, page.total);
+        for offset in [0, 10, 2_995, 5_000] {
+            assert_eq!(page(sharded.browse_all(offset, 10)), page(single.browse_all(offset, 10)));
+            assert_eq!(
+                page(sharded.search_path_prefix("/data/", offset, 10).unwrap()),
+                page(single.search_path_prefix("/data/", offset, 10).unwrap())
+            );
+        }
+        assert_eq!(sharded.read().recent(25), single.recent(25));
+        assert_eq!(sharded.read().extension_counts(), single.extension_counts());
+    }
+
+    #[test]
+    fn test_refined_searches_narrow_every_shard() {
+        let (single, sharded) = both();
+        let cancel = AtomicBool::new(false);
+        let stop = SearchStop::new(&cancel, None);
+        let mut cache = ShardedCache::default();
+        for query in ["r", "re", "rep", "repo", "report_2"] {
+            let found = sharded.search_refining(query, usize::MAX, SortOrder::Name, &mut cache, &stop).unwrap();
+            assert_eq!(found.paths, single.search(query), "{:?}", query);
+            assert!(!cache.is_empty());
+        }
+
+        // A fanned-out search counts once
+        assert_eq!(sharded.read().stats().session.searches, 5);
+    }
+
+    #[test]
+    fn test_unindexed_drive_is_reported_once_no_shard_has_it() {
+        let index = ShardedIndex::new();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            index.insert(PathBuf::from(format!("C:\\Docs\\{}", name))).unwrap();
+        }
+        index.publish();
+        assert_eq!(index.search_path_prefix("C:\\", 0, 10).unwrap().total, 3);
+        assert!(matches!(index.search_path_prefix("D:\\", 0, 10), Err(FlashFindError::DriveNotIndexed('D'))));
+    }
+
+    /// The shard and pool slot holding `path`, once every change is published
+    fn id_of(index: &ShardedIndex, path: &Path) -> Option<(usize, u32)> {
+        index.publish();
+        let view = index.read();
+        let found = view.shards().enumerate().find_map(|(shard, held)| {
+            held.iter().find(|(_, held)| held == path).map(|(idx, _)| (shard, idx))
+        });
+        found
+    }
+
+    #[test]
+    fn test_renames_keep_entries_in_their_shards() {
+        let (old, new) = apart("draft");
+        let index = ShardedIndex::new();
+        let dated = meta(4, 1_700_000_000);
+        let (from, to) = (PathBuf::from(format!("/docs/{}", old)), PathBuf::from(format!("/docs/{}", new)));
+        index.insert_with_meta(from.clone(), dated).unwrap();
+        index.insert_with_meta(to.clone(), meta(9, 1)).unwrap();
+        let id = id_of(&index, &from).unwrap();
+
+        // Named for another shard, replacing the file already there, yet
+        // locking only those two
+        let (done, renamed) = std::sync::mpsc::channel();
+        let busy = (0..SHARD_COUNT).find(|&shard| shard != shard_of(&from) && shard != shard_of(&to)).unwrap();
+        thread::scope(|scope| {
+            let _held = index.shards()[busy].write();
+            let (index, from, to) = (&index, &from, &to);
+            scope.spawn(move || done.send(index.rename(from, to.clone())).unwrap());
+            let renamed = renamed.recv_timeout(Duration::from_secs(10)).expect("the rename waited on an unrelated shard");
+            assert!(renamed.unwrap());
+        });
+        assert_eq!(id_of(&index, &to), Some(id));
+        assert_eq!(index.metadata_for(&to), Some(dated));
+        assert_eq!(index.search("draft"), vec![to.clone()]);
+        assert!(!index.rename(&from, PathBuf::from("/docs/other.txt")).unwrap());
+
+        // Later writes find it where it stayed
+        assert!(!index.insert(to.clone()).unwrap());
+        let batch = PreparedBatch::new(vec![(to.clone(), dated), (from.clone(), dated)]);
+        let (inserted, _) = index.insert_batch(batch, false).unwrap();
+        assert_eq!((inserted.added, inserted.duplicates), (1, 1));
+        assert!(index.remove(&from).unwrap());
+        let reloaded = ShardedIndex::from_shards(index.read().shards().cloned().collect()).unwrap();
+        assert!(!reloaded.insert(to.clone()).unwrap());
+        assert_eq!(id_of(&reloaded, &to), Some(id));
+
+        // A hidden name hides the moved entry, wherever its name leads
+        let hidden = PathBuf::from("/docs/.draft");
+        assert!(index.rename(&to, hidden.clone()).unwrap());
+        assert_eq!(id_of(&index, &hidden), Some(id));
+        assert!(index.metadata_for(&hidden).unwrap().is_hidden());
+
+        // A folder takes what it holds along, in every shard, including an
+        // entry renamed away from its name's shard
+        let folder = EntryMeta { is_dir: true, ..EntryMeta::UNKNOWN };
+        index.insert_with_meta(PathBuf::from("/work/Reports"), folder).unwrap();
+        let (first, second) = apart("q");
+        for name in [&first, &second] {
+            index.insert(PathBuf::from(format!("/work/Reports/{}", name))).unwrap();
+        }
+        let stray = PathBuf::from(format!("/work/Reports/{}", second));
+        let renamed = (0..).map(|i| format!("moved_{}.txt", i)).find(|name| shard_of(Path::new(name)) != shard_of(&stray)).unwrap();
+        assert!(index.rename(&stray, PathBuf::from(format!("/work/Reports/{}", renamed))).unwrap());
+        let ids: Vec<(usize, u32)> = [&first, &renamed]
+            .iter()
+            .map(|name| id_of(&index, Path::new(&format!("/work/Reports/{}", name))).unwrap())
+            .collect();
+        assert!(index.rename(Path::new("/work/Reports"), PathBuf::from("/work/Old Reports")).unwrap());
+        let mut moved = index.read().paths_within(Path::new("/work/Old Reports"));
+        moved.sort();
+        let mut expected: Vec<PathBuf> = ["".to_string(), format!("/{}", first), format!("/{}", renamed)]
+            .iter()
+            .map(|name| PathBuf::from(format!("/work/Old Reports{}", name)))
+            .collect();
+        expected.sort();
+        assert_eq!(moved, expected);
+        for (name, id) in [&first, &renamed].iter().zip(ids) {
+            assert_eq!(id_of(&index, Path::new(&format!("/work/Old Reports/{}", name))), Some(id));
+        }
+        assert!(!index.insert(PathBuf::from(format!("/work/Old Reports/{}", renamed))).unwrap());
+        assert!(index.read().paths_within(Path::new("/work/Reports")).is_empty());
+        assert!(index.is_directory(Path::new("/work/Old Reports")));
+        assert!(index.read().check_invariants().is_consistent());
+    }
+
+    #[test]
+    fn test_folder_totals_add_up_across_shards() {
+        let index = ShardedIndex::new();
+        for i in 0..40 {
+            index.insert(PathBuf::from(format!("/music/Albums/track_{}.mp3", i))).unwrap();
+        }
+        for i in 0..3 {
+            index.insert(PathBuf::from(format!("/docs/note_{}.txt", i))).unwrap();
+        }
+        index.publish();
+        let view = index.read();
+        assert!(view.shards().filter(|shard| !shard.is_empty()).count() > 1);
+        assert_eq!(view.largest_folders(1), [(PathBuf::from("/music/Albums"), 40)]);
+        assert_eq!(view.extension_counts(), [("mp3".to_string(), 40), ("txt".to_string(), 3)]);
+        assert_eq!(view.sample(10, 3).len(), 10);
+    }
+
+    #[test]
+    fn test_splitting_keeps_the_lifetime_counts_once() {
+        let mut single = FileIndex::new();
+        for i in 0..100 {
+            single.insert(PathBuf::from(format!("/docs/{}.txt", i))).unwrap();
+        }
+        single.remove(Path::new("/docs/0.txt")).unwrap();
+        let lifetime = single.stats().lifetime;
+
+        let sharded = ShardedIndex::from_index(single);
+        let view = sharded.read();
+        assert_eq!(view.len(), 99);
+        assert_eq!(view.tombstones(), 0);
+        assert_eq!(view.stats().lifetime.insertions, lifetime.insertions);
+        assert_eq!(view.stats().lifetime.removals, lifetime.removals);
+        assert_eq!(view.stats().session.insertions, 0);
+        assert!(view.check_invariants().is_consistent());
+    }
+
+    #[test]
+    fn test_searches_never_wait_on_writers() {
+        let index = ShardedIndex::new();
+        index.insert(PathBuf::from("/docs/report.pdf")).unwrap();
+        index.publish();
+        let published = index.read().generation();
+        let found = |index: &ShardedIndex| search_limited(index, "report", 10, SortOrder::Name).paths;
+
+        // Every shard is write-locked, yet searches go on over the snapshots
+        let mut writer = index.write();
+        writer.insert(PathBuf::from("/docs/report_2.pdf")).unwrap();
+        writer.remove_where(|path| path.ends_with("report.pdf"));
+        assert_eq!(found(&index), [PathBuf::from("/docs/report.pdf")]);
+        assert_eq!(index.read().generation(), published);
+
+        // Releasing the writer publishes what it changed
+        drop(writer);
+        assert_eq!(found(&index), [PathBuf::from("/docs/report_2.pdf")]);
+        assert!(index.read().check_invariants().is_consistent());
+    }
+
+    #[test]
+    fn test_lone_watcher_events_never_copy_a_shard() {
+        let (mut single, sharded) = both();
+        let shard = shard_of(Path::new("event.txt"));
+        let copies = || sharded.shards()[shard].copies();
+        let matches = |single: &FileIndex| {
+            let mut found = sharded.search("event");
+            found.sort();
+            let mut expected = single.search("event");
+            expected.sort();
+            assert_eq!(found, expected);
+        };
+
+        // One change at a time, published and searched before the next, as
+        // watcher events arriving apart are; the first has no spare copy yet
+        let mut event = |i: i64| {
+            let path = PathBuf::from(format!("/watched/{}/event.txt", i));
+            match i % 3 {
+                0 => {
+                    sharded.insert_with_meta(path.clone(), meta(i as u64, 1_700_000_000 + i)).unwrap();
+                    single.insert_with_meta(path, meta(i as u64, 1_700_000_000 + i)).unwrap();
+                }
+                1 => {
+                    let old = PathBuf::from(format!("/watched/{}/event.txt", i - 1));
+                    assert!(sharded.rename(&old, path.clone()).unwrap());
+                    single.rename(&old, path).unwrap();
+                }
+                _ => {
+                    assert!(sharded.remove(&PathBuf::from(format!("/watched/{}/event.txt", i - 1))).unwrap());
+                    single.remove(&PathBuf::from(format!("/watched/{}/event.txt", i - 1))).unwrap();
+                }
+            }
+            sharded.publish();
+            matches(&single);
+        };
+        event(0);
+        let before = copies();
+        for i in 1..100 {
+            event(i);
+        }
+        assert_eq!(copies(), before);
+
+        // A search still holding the spare copy leaves a write to copy
+        let held = sharded.read();
+        event(100);
+        event(101);
+        assert_eq!(copies(), before + 1);
+        drop(held);
+        event(102);
+        event(103);
+        assert_eq!(copies(), before + 1);
+
+        let view = sharded.read();
+        assert!(view.check_invariants().is_consistent());
+        let (counted, expected) = (view.stats().lifetime, single.stats().lifetime);
+        assert_eq!((counted.insertions, counted.removals), (expected.insertions, expected.removals));
+    }
+
+    /// Search latency with and without a scan writing batches alongside,
+    /// under one index lock and over the shards' snapshots
+    ///
+    /// `cargo test --release bench_search_latency_during_scan -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_search_latency_during_scan() {
+        use std::sync::Arc;
+
+        const INDEXED: usize = 200_000;
+        const SCANNED: usize = 100_000;
+        const BATCH: usize = 1000;
+        let entry = |i: usize| (PathBuf::from(format!("/data/{}/Quarterly Report_{}.final.pdf", i % 1000, i)), EntryMeta::UNKNOWN);
+
+        // Percentiles of single searches until `done` is set or `count` ran;
+        // `None` if the scan finished before one did
+        let measure = |search: &dyn Fn(usize) -> usize, done: &AtomicBool, count: usize| {
+            let mut took = Vec::new();
+            while took.len() < count && !done.load(Ordering::Relaxed) {
+                let start = Instant::now();
+                let found = search(took.len());
+                took.push(start.elapsed());
+                assert!(found > 0);
+            }
+            took.sort();
+            let slowest = *took.last()?;
+            let at = |q: f64| took[((took.len() - 1) as f64 * q) as usize];
+            Some((took.len(), at(0.5), at(0.99), slowest))
+        };
+        let report = |label: &str, timings: Option<(usize, Duration, Duration, Duration)>| match timings {
+            Some((runs, p50, p99, max)) => println!("{} searches {}: p50 {:?}, p99 {:?}, max {:?}", runs, label, p50, p99, max),
+            None => println!("no searches {}: the scan finished first", label),
+        };
+
+        let indexed: Vec<_> = (0..INDEXED).map(entry).collect();
+        let mut filled = FileIndex::new();
+        filled.insert_batch(PreparedBatch::new(indexed.clone())).unwrap();
+        let single = Arc::new(RwLock::new(filled));
+        let sharded = Arc::new(ShardedIndex::new());
+        sharded.insert_batch(PreparedBatch::new(indexed), false).unwrap();
+        sharded.publish();
+
+        let single_search = |i: usize| single.read().search_limited(&format!("report_{}", i % 1000), 100, SortOrder::Name).paths.len();
+        let sharded_search = |i: usize| search_limited(&sharded, &format!("report_{}", i % 1000), 100, SortOrder::Name).paths.len();
+        report("idle, one lock", measure(&single_search, &AtomicBool::new(false), 500));
+        report("idle, snapshots", measure(&sharded_search, &AtomicBool::new(false), 500));
+
+        let entries: Vec<_> = (INDEXED..INDEXED + SCANNED).map(entry).collect();
+        let done = Arc::new(AtomicBool::new(false));
+        let scan = {
+            let (index, done, entries) = (single.clone(), done.clone(), entries.clone());
+            std::thread::spawn(move || {
+                for chunk in entries.chunks(BATCH) {
+                    index.write().insert_batch(PreparedBatch::new(chunk.to_vec())).unwrap();
+                }
+                done.store(true, Ordering::Relaxed);
+            })
+        };
+        report("during scan, one lock", measure(&single_search, &done, usize::MAX));
+        scan.join().unwrap();
+
+        let done = Arc::new(AtomicBool::new(false));
+        let scan = {
+            let (index, done) = (sharded.clone(), done.clone());
+            std::thread::spawn(move || {
+                for chunk in entries.chunks(BATCH) {
+                    index.insert_batch(PreparedBatch::new(chunk.to_vec()), false).unwrap();
+                }
+                done.store(true, Ordering::Relaxed);
+            })
+        };
+        report("during scan, snapshots", measure(&sharded_search, &done, usize::MAX));
+        scan.join().unwrap();
+
+        assert_eq!(single.read().len(), INDEXED + SCANNED);
+        sharded.publish();
+        assert_eq!(sharded.read().len(), INDEXED + SCANNED);
+    }
+}
@@ -0,0 +1,462 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::error::{FlashFindError, Result};
+use crate::redact::redact;
+
+/// Command-line flag carrying the query a shortcut opens
+pub const QUERY_FLAG: &str = "--query";
+
+/// Command-line flag carrying the file type filter id a shortcut opens
+pub const FILTER_FLAG: &str = "--filter";
+
+/// URL scheme handled by FlashFind once registered with the shell
+const URL_PREFIX: &str = "flashfind://search?";
+
+/// A search to run at startup, from a desktop shortcut or a flashfind:// link
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LaunchSearch {
+    pub query: String,
+    /// File type filter id (`documents`, `images`, ...), if not all files
+    pub filter: Option<String>,
+}
+
+impl LaunchSearch {
+    /// Read `--query`/`--filter` or a flashfind:// URL from the process arguments
+    pub fn from_args(args: &[String]) -> Option<Self> {
+        if let Some(url) = args.iter().skip(1).find(|a| a.starts_with(URL_PREFIX)) {
+            return Self::from_url(url);
+        }
+
+        let mut search = LaunchSearch::default();
+        let mut found = false;
+        let mut rest = args.iter().skip(1);
+        while let Some(arg) = rest.next() {
+            match arg.as_str() {
+                QUERY_FLAG => {
+                    search.query = rest.next()?.clone();
+                    found = true;
+                }
+                FILTER_FLAG => search.filter = Some(rest.next()?.clone()),
+                _ => {}
+            }
+        }
+        found.then_some(search)
+    }
+
+    /// Arguments for the shortcut target, quoted for the Windows command line
+    pub fn arguments(&self) -> String {
+        let mut args = vec![QUERY_FLAG.to_string(), quote_argument(&self.query)];
+        if let Some(filter) = &self.filter {
+            args.push(FILTER_FLAG.to_string());
+            args.push(quote_argument(filter));
+        }
+        args.join(" ")
+    }
+
+    /// The flashfind:// URL for this search
+    pub fn url(&self) -> String {
+        let mut url = format!("{}query={}", URL_PREFIX, percent_encode(&self.query));
+        if let Some(filter) = &self.filter {
+            url.push_str("&filter=");
+            url.push_str(&percent_encode(filter));
+        }
+        url
+    }
+
+    fn from_url(url: &str) -> Option<Self> {
+        let mut search = LaunchSearch::default();
+        let mut found = false;
+        for pair in url.strip_prefix(URL_PREFIX)?.split('&') {
+            let (key, value) = pair.split_once('=')?;
+            match key {
+                "query" => {
+                    search.query = percent_decode(value)?;
+                    found = true;
+                }
+                "filter" => search.filter = Some(percent_decode(value)?),
+                _ => {}
+            }
+        }
+        found.then_some(search)
+    }
+}
+
+/// Quote one argument so `CommandLineToArgvW` (and Rust's `env::args`) read it back unchanged
+///
+/// Quotes become `\"`, and backslashes are doubled only where they precede a
+/// quote, including the closing one.
+pub fn quote_argument(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '\x0b', '"']) {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => {
+                backslashes += 1;
+                continue;
+            }
+            '"' => quoted.push_str(&"\\".repeat(backslashes * 2 + 1)),
+            _ => quoted.push_str(&"\\".repeat(backslashes)),
+        }
+        quoted.push(c);
+        backslashes = 0;
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.bytes();
+    while let Some(b) = rest.next() {
+        if b == b'%' {
+            let hex = [rest.next()?, rest.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Make a saved search name usable as a file name
+pub fn shortcut_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if c.is_control() || r#"<>:"/\|?*"#.contains(c) { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.trim().trim_end_matches('.').to_string();
+    if cleaned.is_empty() {
+        "FlashFind search".to_string()
+    } else {
+        cleaned
+    }
+}
+
+/// The user's Desktop folder
+pub fn desktop_dir() -> Result<PathBuf> {
+    dirs::desktop_dir().ok_or_else(|| FlashFindError::SystemFolderError("Desktop".to_string()))
+}
+
+/// A `.lnk` or `.url` shortcut named `name` already in `dir`, if any
+pub fn existing_shortcut(dir: &Path, name: &str) -> Option<PathBuf> {
+    ["lnk", "url"]
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", shortcut_name(name), ext)))
+        .find(|path| path.exists())
+}
+
+/// Write a shortcut named `name` in `dir` that opens FlashFind on `search`
+///
+/// Prefers a `.lnk` to this executable. Without one (or off Windows), falls
+/// back to a `.url` link when the flashfind:// protocol is registered.
+/// Replaces an existing shortcut of the same name; ask before calling.
+pub fn create_shortcut(dir: &Path, name: &str, search: &LaunchSearch) -> Result<PathBuf> {
+    let exe = std::env::current_exe().map_err(|e| FlashFindError::ShortcutFailed(e.to_string()))?;
+    let name = shortcut_name(name);
+
+    let link = dir.join(format!("{}.lnk", name));
+    let link_error = match shell_link::write(&link, &exe, &search.arguments(), &name) {
+        Ok(()) => {
+            remove_other(dir, &name, "url");
+            info!("Created shortcut {}", redact(&link));
+            return Ok(link);
+        }
+        Err(e) => e,
+    };
+
+    if !shell_link::protocol_registered() {
+        return Err(link_error);
+    }
+    warn!("Cannot write .lnk shortcut ({}), writing a flashfind:// link instead", link_error);
+
+    let url = dir.join(format!("{}.url", name));
+    fs::write(&url, url_file(search, &exe)).map_err(|e| FlashFindError::FileWriteError {
+        path: url.display().to_string(),
+        source: e,
+    })?;
+    remove_other(dir, &name, "lnk");
+    info!("Created shortcut {}", redact(&url));
+    Ok(url)
+}
+
+/// Drop a replaced shortcut of the other kind so only one remains
+fn remove_other(dir: &Path, name: &str, ext: &str) {
+    let other = dir.join(format!("{}.{}", name, ext));
+    if other.exists() {
+        let _ = fs::remove_file(other);
+    }
+}
+
+/// Contents of an Internet Shortcut file for `search`
+fn url_file(search: &LaunchSearch, exe: &Path) -> String {
+    format!(
+        "[InternetShortcut]\r\nURL={}\r\nIconFile={}\r\nIconIndex=0\r\n",
+        search.url(),
+        exe.display()
+    )
+}
+
+/// Shell link creation through the COM `IShellLinkW` interface
+#[cfg(windows)]
+mod shell_link {
+    use std::ffi::{c_void, OsStr};
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use windows_sys::core::{GUID, HRESULT, PCWSTR};
+    use windows_sys::Win32::System::Com::{
+        CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED,
+    };
+    use windows_sys::Win32::System::Registry::{RegCloseKey, RegOpenKeyExW, HKEY, HKEY_CLASSES_ROOT, KEY_READ};
+    use windows_sys::Win32::UI::Shell::ShellLink;
+    use windows_sys::Win32::UI::WindowsAndMessaging::SW_SHOWNORMAL;
+
+    use crate::error::{FlashFindError, Result};
+
+    const IID_ISHELLLINKW: GUID = GUID::from_u128(0x000214f9_0000_0000_c000_000000000046);
+    const IID_IPERSISTFILE: GUID = GUID::from_u128(0x0000010b_0000_0000_c000_000000000046);
+
+    type Method = usize;
+
+    /// `IShellLinkW` vtable; only the setters used here are typed
+    #[repr(C)]
+    #[allow(dead_code)] // untyped slots only keep the layout
+    struct ShellLinkVtbl {
+        query_interface: unsafe extern "system" fn(*mut c_void, *const GUID, *mut *mut c_void) -> HRESULT,
+        add_ref: Method,
+        release: unsafe extern "system" fn(*mut c_void) -> u32,
+        get_path: Method,
+        get_id_list: Method,
+        set_id_list: Method,
+        get_description: Method,
+        set_description: unsafe extern "system" fn(*mut c_void, PCWSTR) -> HRESULT,
+        get_working_directory: Method,
+        set_working_directory: unsafe extern "system" fn(*mut c_void, PCWSTR) -> HRESULT,
+        get_arguments: Method,
+        set_arguments: unsafe extern "system" fn(*mut c_void, PCWSTR) -> HRESULT,
+        get_hotkey: Method,
+        set_hotkey: Method,
+        get_show_cmd: Method,
+        set_show_cmd: unsafe extern "system" fn(*mut c_void, i32) -> HRESULT,
+        get_icon_location: Method,
+        set_icon_location: unsafe extern "system" fn(*mut c_void, PCWSTR, i32) -> HRESULT,
+        set_relative_path: Method,
+        resolve: Method,
+        set_path: unsafe extern "system" fn(*mut c_void, PCWSTR) -> HRESULT,
+    }
+
+    /// `IPersistFile` vtable
+    #[repr(C)]
+    #[allow(dead_code)]
+    struct PersistFileVtbl {
+        query_interface: Method,
+        add_ref: Method,
+        release: unsafe extern "system" fn(*mut c_void) -> u32,
+        get_class_id: Method,
+        is_dirty: Method,
+        load: Method,
+        save: unsafe extern "system" fn(*mut c_void, PCWSTR, i32) -> HRESULT,
+        save_completed: Method,
+        get_cur_file: Method,
+    }
+
+    fn wide(s: &OsStr) -> Vec<u16> {
+        s.encode_wide().chain(std::iter::once(0)).collect()
+    }
+
+    fn check(hr: HRESULT, step: &str) -> Result<()> {
+        if hr < 0 {
+            return Err(FlashFindError::ShortcutFailed(format!("{} failed (0x{:08X})", step, hr as u32)));
+        }
+        Ok(())
+    }
+
+    /// Save a `.lnk` at `path` that runs `exe` with `arguments`, using its icon
+    pub fn write(path: &Path, exe: &Path, arguments: &str, description: &str) -> Result<()> {
+        let exe_w = wide(exe.as_os_str());
+        let dir_w = wide(exe.parent().unwrap_or(Path::new(".")).as_os_str());
+        let args_w = wide(OsStr::new(arguments));
+        let description_w = wide(OsStr::new(description));
+        let path_w = wide(path.as_os_str());
+
+        // SAFETY: COM is initialized for this call only; every interface pointer
+        // comes from CoCreateInstance/QueryInterface, is used through its own
+        // vtable layout, and is released before returning. All strings are
+        // NUL-terminated UTF-16 buffers that outlive the calls.
+        unsafe {
+            let initialized = CoInitializeEx(std::ptr::null(), COINIT_APARTMENTTHREADED as u32) >= 0;
+            let result = (|| {
+                let mut link: *mut c_void = std::ptr::null_mut();
+                check(
+                    CoCreateInstance(&ShellLink, std::ptr::null_mut(), CLSCTX_INPROC_SERVER, &IID_ISHELLLINKW, &mut link),
+                    "Creating the shell link",
+                )?;
+                let vtbl = &**(link as *mut *const ShellLinkVtbl);
+
+                let configured = (|| {
+                    check((vtbl.set_path)(link, exe_w.as_ptr()), "Setting the target")?;
+                    check((vtbl.set_arguments)(link, args_w.as_ptr()), "Setting the arguments")?;
+                    check((vtbl.set_working_directory)(link, dir_w.as_ptr()), "Setting the working folder")?;
+                    check((vtbl.set_description)(link, description_w.as_ptr()), "Setting the description")?;
+                    check((vtbl.set_icon_location)(link, exe_w.as_ptr(), 0), "Setting the icon")?;
+                    check((vtbl.set_show_cmd)(link, SW_SHOWNORMAL as i32), "Setting the window state")?;
+
+                    let mut file: *mut c_void = std::ptr::null_mut();
+                    check((vtbl.query_interface)(link, &IID_IPERSISTFILE, &mut file), "Opening the link file")?;
+                    let file_vtbl = &**(file as *mut *const PersistFileVtbl);
+                    let saved = check((file_vtbl.save)(file, path_w.as_ptr(), 1), "Saving the shortcut");
+                    (file_vtbl.release)(file);
+                    saved
+                })();
+                (vtbl.release)(link);
+                configured
+            })();
+            if initialized {
+                CoUninitialize();
+            }
+            result
+        }
+    }
+
+    /// Whether the flashfind:// protocol has a handler in the registry
+    pub fn protocol_registered() -> bool {
+        let key_name = wide(OsStr::new("flashfind"));
+        let mut key: HKEY = 0;
+        // SAFETY: the key name is a NUL-terminated UTF-16 buffer and the opened
+        // key is closed right away
+        unsafe {
+            if RegOpenKeyExW(HKEY_CLASSES_ROOT, key_name.as_ptr(), 0, KEY_READ, &mut key) != 0 {
+                return false;
+            }
+            RegCloseKey(key);
+        }
+        true
+    }
+}
+
+/// Shell links only exist on Windows
+#[cfg(not(windows))]
+mod shell_link {
+    use std::path::Path;
+
+    use crate::error::{FlashFindError, Result};
+
+    pub fn write(_path: &Path, _exe: &Path, _arguments: &str, _description: &str) -> Result<()> {
+        Err(FlashFindError::ShortcutFailed("desktop shortcuts are only available on Windows".to_string()))
+    }
+
+    pub fn protocol_registered() -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Split a command line the way `CommandLineToArgvW` does (arguments after the program)
+    fn split_command_line(line: &str) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut current = String::new();
+        let mut in_arg = false;
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    let mut count = 1;
+                    while chars.peek() == Some(&'\\') {
+                        chars.next();
+                        count += 1;
+                    }
+                    if chars.peek() == Some(&'"') {
+                        current.push_str(&"\\".repeat(count / 2));
+                        if count % 2 == 1 {
+                            chars.next();
+                            current.push('"');
+                        }
+                    } else {
+                        current.push_str(&"\\".repeat(count));
+                    }
+                    in_arg = true;
+                }
+                '"' => {
+                    quoted = !quoted;
+                    in_arg = true;
+                }
+                ' ' | '\t' if !quoted => {
+                    if in_arg {
+                        args.push(std::mem::take(&mut current));
+                        in_arg = false;
+                    }
+                }
+                _ => {
+                    current.push(c);
+                    in_arg = true;
+                }
+            }
+        }
+        if in_arg {
+            args.push(current);
+        }
+        args
+    }
+
+    #[test]
+    fn test_quoting_spaces_quotes_and_backslashes() {
+        assert_eq!(quote_argument("invoice"), "invoice");
+        assert_eq!(quote_argument("tax return"), "\"tax return\"");
+        assert_eq!(quote_argument("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(quote_argument(""), "\"\"");
+        assert_eq!(quote_argument("C:\\my docs\\"), "\"C:\\my docs\\\\\"");
+        assert_eq!(quote_argument("C:\\docs\\"), "C:\\docs\\");
+
+        for arg in ["tax return", "say \"hi\"", "", "C:\\my docs\\", "a\\\"b", "  spaced  ", "\"\""] {
+            assert_eq!(split_command_line(&quote_argument(arg)), vec![arg.to_string()], "argument {:?}", arg);
+        }
+    }
+
+    #[test]
+    fn test_shortcut_arguments_round_trip() {
+        let search = LaunchSearch { query: "budget \"final\" 2024".into(), filter: Some("documents".into()) };
+        let mut args = vec!["flashfind.exe".to_string()];
+        args.extend(split_command_line(&search.arguments()));
+        assert_eq!(args[1..], ["--query", "budget \"final\" 2024", "--filter", "documents"]);
+        assert_eq!(LaunchSearch::from_args(&args), Some(search.clone()));
+
+        let url_args = vec!["flashfind.exe".to_string(), search.url()];
+        assert!(search.url().starts_with("flashfind://search?query=budget%20%22final%22%202024&filter=documents"));
+        assert_eq!(LaunchSearch::from_args(&url_args), Some(search));
+
+        assert_eq!(LaunchSearch::from_args(&["flashfind.exe".to_string()]), None);
+        assert_eq!(LaunchSearch::from_args(&["flashfind.exe".to_string(), "--query".to_string()]), None);
+    }
+
+    #[test]
+    fn test_shortcut_names_and_url_file() {
+        assert_eq!(shortcut_name("Taxes: 2024/25?"), "Taxes_ 2024_25_");
+        assert_eq!(shortcut_name(" ... "), "FlashFind search");
+        assert_eq!(shortcut_name("v1.2 notes"), "v1.2 notes");
+
+        let search = LaunchSearch { query: "ünïcode".into(), filter: None };
+        let file = url_file(&search, Path::new("C:\\Apps\\flashfind.exe"));
+        assert!(file.starts_with("[InternetShortcut]\r\nURL=flashfind://search?query=%C3%BCn%C3%AFcode\r\n"));
+        assert!(file.contains("IconFile=C:\\Apps\\flashfind.exe\r\n"));
+        assert_eq!(LaunchSearch::from_url(&search.url()), Some(search));
+    }
+}
@@ -0,0 +1,195 @@
+use parking_lot::RwLock;
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tracing::info;
+
+use crate::archive::{entry_kind, EntryKind};
+use crate::paths::to_long_path;
+
+/// Most files stat'ed for one total, so 500k results cannot run away
+pub const MAX_STAT_FILES: usize = 100_000;
+
+/// Files stat'ed per batch between progress updates and cancel checks
+const STAT_BATCH: usize = 512;
+
+/// Running or finished size total for a result set
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SizeTotal {
+    /// Files measured so far
+    pub files: usize,
+    pub bytes: u64,
+    /// Files that no longer exist or could not be read
+    pub missing: usize,
+    /// Files left out because the result set exceeds `MAX_STAT_FILES`
+    pub skipped: usize,
+    pub done: bool,
+    pub cancelled: bool,
+}
+
+impl SizeTotal {
+    /// Average size of the measured files
+    pub fn average(&self) -> u64 {
+        if self.files == 0 {
+            0
+        } else {
+            self.bytes / self.files as u64
+        }
+    }
+}
+
+/// Background job summing file sizes for a result set
+///
+/// The index stores no sizes, so every file is stat'ed in batches on a
+/// background thread, with progress the UI can poll.
+pub struct SizeJob {
+    progress: Arc<RwLock<SizeTotal>>,
+    cancel: Arc<AtomicBool>,
+    /// Files this job will measure (after the cap)
+    pub total: usize,
+}
+
+impl SizeJob {
+    /// Start summing the sizes of `paths` on a background thread
+    pub fn start(paths: Vec<PathBuf>) -> Self {
+        let progress = Arc::new(RwLock::new(SizeTotal::default()));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let total = paths.len().min(MAX_STAT_FILES);
+
+        let thread_progress = progress.clone();
+        let thread_cancel = cancel.clone();
+        thread::spawn(move || sum_sizes(&paths, MAX_STAT_FILES, &thread_progress, &thread_cancel));
+
+        Self { progress, cancel, total }
+    }
+
+    /// Snapshot of the total so far
+    pub fn progress(&self) -> SizeTotal {
+        self.progress.read().clone()
+    }
+
+    /// Stop after the current batch
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Stat up to `cap` of `paths` in batches, publishing progress after each
+fn sum_sizes(paths: &[PathBuf], cap: usize, progress: &RwLock<SizeTotal>, cancel: &AtomicBool) {
+    let measured = &paths[..paths.len().min(cap)];
+    progress.write().skipped = paths.len() - measured.len();
+
+    for batch in measured.chunks(STAT_BATCH) {
+        if cancel.load(Ordering::SeqCst) {
+            progress.write().cancelled = true;
+            break;
+        }
+
+        let sizes: Vec<Option<u64>> = batch
+            .par_iter()
+            .map(|path| std::fs::metadata(to_long_path(path)).ok().map(|m| m.len()))
+            .collect();
+
+        let mut total = progress.write();
+        for size in sizes {
+            match size {
+                Some(bytes) => {
+                    total.files += 1;
+                    total.bytes += bytes;
+                }
+                None => total.missing += 1,
+            }
+        }
+    }
+
+    let mut total = progress.write();
+    total.done = true;
+    info!("Size total: {} files, {} bytes", total.files, total.bytes);
+}
+
+/// Size of an indexed file on disk, if it can be measured
+///
+/// Archive members and entries that are gone or not regular files have none.
+pub fn file_size(path: &Path) -> Option<u64> {
+    if entry_kind(path) == EntryKind::ArchiveMember {
+        return None;
+    }
+    std::fs::metadata(to_long_path(path)).ok().filter(|m| m.is_file()).map(|m| m.len())
+}
+
+/// Human-readable size with binary units ("1.5 GB")
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use std::fs;
+
+    fn scratch_files(name: &str, sizes: &[usize]) -> (TestDir, Vec<PathBuf>) {
+        let dir = TestDir::new(&format!("size_{}", name));
+        let files = sizes
+            .iter()
+            .enumerate()
+            .map(|(i, &size)| {
+                let file = dir.join(format!("{}.bin", i));
+                fs::write(&file, vec![0u8; size]).unwrap();
+                file
+            })
+            .collect();
+        (dir, files)
+    }
+
+    #[test]
+    fn test_sums_sizes_and_counts_missing() {
+        let (dir, mut files) = scratch_files("sum", &[100, 300, 2_000]);
+        files.push(dir.join("gone.bin"));
+
+        let progress = RwLock::new(SizeTotal::default());
+        sum_sizes(&files, MAX_STAT_FILES, &progress, &AtomicBool::new(false));
+        let total = progress.read().clone();
+
+        assert!(total.done && !total.cancelled);
+        assert_eq!((total.files, total.bytes, total.missing), (3, 2_400, 1));
+        assert_eq!(total.average(), 800);
+    }
+
+    #[test]
+    fn test_cap_and_cancel() {
+        let (_dir, files) = scratch_files("cap", &[10; 5]);
+
+        let progress = RwLock::new(SizeTotal::default());
+        sum_sizes(&files, 2, &progress, &AtomicBool::new(false));
+        assert_eq!((progress.read().files, progress.read().skipped), (2, 3));
+
+        let progress = RwLock::new(SizeTotal::default());
+        sum_sizes(&files, MAX_STAT_FILES, &progress, &AtomicBool::new(true));
+        let total = progress.read().clone();
+        assert!(total.cancelled && total.done);
+        assert_eq!(total.files, 0);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GB");
+    }
+}
@@ -0,0 +1,551 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use parking_lot::Mutex;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+use crate::error::{FlashFindError, Result};
+use crate::paths::path_key;
+use crate::persistence::get_app_data_dir;
+use crate::sharded::ShardedIndex;
+use crate::size::file_size;
+
+/// Layout version written at the start of every snapshot file
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Folder under the app data dir holding the snapshots
+const SNAPSHOT_DIR: &str = "snapshots";
+
+/// Entries sized or compared per batch between progress updates and cancel checks
+const ENTRY_BATCH: usize = 4_096;
+
+/// Name and summary of a saved snapshot, kept next to it as `<id>.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub id: String,
+    pub name: String,
+    /// Seconds since the Unix epoch
+    pub created: u64,
+    pub files: usize,
+}
+
+impl SnapshotInfo {
+    pub fn created_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.created)
+    }
+}
+
+/// A file present in both the snapshot and the index whose size changed
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeChange {
+    pub path: PathBuf,
+    pub before: u64,
+    pub after: u64,
+}
+
+/// What changed between a snapshot and the current index
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SnapshotDiff {
+    pub added: Vec<PathBuf>,
+    /// Removed files with their size at snapshot time
+    pub removed: Vec<(PathBuf, Option<u64>)>,
+    pub changed: Vec<SizeChange>,
+}
+
+/// Snapshots saved in one folder
+///
+/// Each snapshot is a gzip-compressed bincode stream: the layout version, the
+/// entry count, then `(path, size)` entries sorted by `path_key`. The sorted
+/// order lets a diff stream the snapshot against the sorted current index
+/// without loading the snapshot into memory.
+pub struct SnapshotStore {
+    dir: PathBuf,
+}
+
+impl SnapshotStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The store in the app data folder
+    pub fn open_default() -> Result<Self> {
+        Ok(Self::new(get_app_data_dir()?.join(SNAPSHOT_DIR)))
+    }
+
+    /// Saved snapshots, newest first; unreadable entries are skipped
+    pub fn list(&self) -> Vec<SnapshotInfo> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+
+        let mut snapshots: Vec<SnapshotInfo> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| match read_info(&path) {
+                Ok(info) => Some(info),
+                Err(e) => {
+                    warn!("Skipping snapshot: {}", e);
+                    None
+                }
+            })
+            .collect();
+        snapshots.sort_by(|a, b| b.created.cmp(&a.created).then_with(|| b.id.cmp(&a.id)));
+        snapshots
+    }
+
+    /// Save the path set and sizes of `paths` under `name`
+    ///
+    /// `progress` counts entries written; a set `cancel` stops the write and
+    /// leaves nothing behind.
+    pub fn create<F>(&self, name: &str, paths: Vec<PathBuf>, size_of: F, progress: &AtomicUsize, cancel: &AtomicBool) -> Result<SnapshotInfo>
+    where
+        F: Fn(&Path) -> Option<u64> + Sync,
+    {
+        fs::create_dir_all(&self.dir).map_err(|e| FlashFindError::DirectoryCreationError {
+            path: self.dir.display().to_string(),
+            source: e,
+        })?;
+
+        let sorted = sort_by_key(paths);
+        let created = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let id = self.unused_id(created.as_millis());
+        let info = SnapshotInfo { id, name: name.trim().to_string(), created: created.as_secs(), files: sorted.len() };
+
+        let data_path = self.data_path(&info.id);
+        let temp_path = data_path.with_extension("snap.tmp");
+        let written = write_entries(&temp_path, &sorted, size_of, progress, cancel);
+        if let Err(e) = written.and_then(|_| rename_into_place(&temp_path, &data_path)) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e);
+        }
+        if let Err(e) = self.write_info(&info) {
+            let _ = fs::remove_file(&data_path);
+            return Err(e);
+        }
+
+        info!("Saved snapshot '{}' with {} files", info.name, info.files);
+        Ok(info)
+    }
+
+    /// Give a snapshot a new name
+    pub fn rename(&self, id: &str, name: &str) -> Result<()> {
+        let mut info = read_info(&self.info_path(id))?;
+        info.name = name.trim().to_string();
+        self.write_info(&info)
+    }
+
+    /// Remove a snapshot and its summary
+    pub fn delete(&self, id: &str) -> Result<()> {
+        for path in [self.data_path(id), self.info_path(id)] {
+            match fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(source) => {
+                    return Err(FlashFindError::FileWriteError { path: path.display().to_string(), source });
+                }
+            }
+        }
+        info!("Deleted snapshot {}", id);
+        Ok(())
+    }
+
+    /// Compare a snapshot with `current`, streaming the snapshot from disk
+    ///
+    /// `progress` counts snapshot entries compared.
+    pub fn diff<F>(&self, id: &str, current: Vec<PathBuf>, size_of: F, progress: &AtomicUsize, cancel: &AtomicBool) -> Result<SnapshotDiff>
+    where
+        F: Fn(&Path) -> Option<u64> + Sync,
+    {
+        let entries = EntryReader::open(&self.data_path(id))?;
+        let diff = diff_sorted(entries, &sort_by_key(current), size_of, progress, cancel)?;
+        info!(
+            "Snapshot diff: {} added, {} removed, {} changed",
+            diff.added.len(),
+            diff.removed.len(),
+            diff.changed.len()
+        );
+        Ok(diff)
+    }
+
+    fn data_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.snap", id))
+    }
+
+    fn info_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    /// A millisecond timestamp, bumped until no snapshot uses it
+    fn unused_id(&self, mut millis: u128) -> String {
+        while self.info_path(&millis.to_string()).exists() || self.data_path(&millis.to_string()).exists() {
+            millis += 1;
+        }
+        millis.to_string()
+    }
+
+    fn write_info(&self, info: &SnapshotInfo) -> Result<()> {
+        let path = self.info_path(&info.id);
+        let json = serde_json::to_string_pretty(info).map_err(|e| FlashFindError::CorruptSnapshot {
+            path: path.display().to_string(),
+            reason: e.to_string(),
+        })?;
+        fs::write(&path, json).map_err(|e| FlashFindError::FileWriteError { path: path.display().to_string(), source: e })
+    }
+}
+
+fn read_info(path: &Path) -> Result<SnapshotInfo> {
+    let json = fs::read_to_string(path).map_err(|e| FlashFindError::FileReadError {
+        path: path.display().to_string(),
+        source: e,
+    })?;
+    serde_json::from_str(&json).map_err(|e| FlashFindError::CorruptSnapshot {
+        path: path.display().to_string(),
+        reason: e.to_string(),
+    })
+}
+
+fn rename_into_place(from: &Path, to: &Path) -> Result<()> {
+    fs::rename(from, to).map_err(|e| FlashFindError::FileWriteError { path: to.display().to_string(), source: e })
+}
+
+/// Pair each path with its `path_key` and sort, dropping same-key duplicates
+fn sort_by_key(paths: Vec<PathBuf>) -> Vec<(String, PathBuf)> {
+    let mut keyed: Vec<(String, PathBuf)> = paths.into_par_iter().map(|path| (path_key(&path), path)).collect();
+    keyed.par_sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    keyed.dedup_by(|a, b| a.0 == b.0);
+    keyed
+}
+
+/// Stream sorted entries with their sizes into a compressed snapshot file
+fn write_entries<F>(path: &Path, sorted: &[(String, PathBuf)], size_of: F, progress: &AtomicUsize, cancel: &AtomicBool) -> Result<()>
+where
+    F: Fn(&Path) -> Option<u64> + Sync,
+{
+    let write_error = |source| FlashFindError::FileWriteError { path: path.display().to_string(), source };
+    let encode_error = |e: bincode::Error| write_error(std::io::Error::other(e));
+
+    let file = File::create(path).map_err(write_error)?;
+    let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+    bincode::serialize_into(&mut encoder, &SNAPSHOT_VERSION).map_err(encode_error)?;
+    bincode::serialize_into(&mut encoder, &(sorted.len() as u64)).map_err(encode_error)?;
+
+    for batch in sorted.chunks(ENTRY_BATCH) {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(FlashFindError::Cancelled);
+        }
+        let sizes: Vec<Option<u64>> = batch.par_iter().map(|(_, path)| size_of(path)).collect();
+        for ((_, path), size) in batch.iter().zip(sizes) {
+            bincode::serialize_into(&mut encoder, &(path.to_string_lossy(), size)).map_err(encode_error)?;
+        }
+        progress.fetch_add(batch.len(), Ordering::Relaxed);
+    }
+
+    encoder.finish().and_then(|mut writer| writer.flush()).map_err(write_error)
+}
+
+/// Snapshot entries read one at a time from disk
+struct EntryReader {
+    decoder: GzDecoder<BufReader<File>>,
+    remaining: u64,
+    path: PathBuf,
+}
+
+impl EntryReader {
+    fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(|e| FlashFindError::FileReadError {
+            path: path.display().to_string(),
+            source: e,
+        })?;
+        let mut reader = Self { decoder: GzDecoder::new(BufReader::new(file)), remaining: 0, path: path.to_path_buf() };
+
+        let version: u32 = reader.decode()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(reader.corrupt(format!("unsupported version {}", version)));
+        }
+        reader.remaining = reader.decode()?;
+        Ok(reader)
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(&mut self) -> Result<T> {
+        bincode::deserialize_from(&mut self.decoder).map_err(|e| self.corrupt(e.to_string()))
+    }
+
+    fn corrupt(&self, reason: String) -> FlashFindError {
+        FlashFindError::CorruptSnapshot { path: self.path.display().to_string(), reason }
+    }
+}
+
+impl Iterator for EntryReader {
+    type Item = Result<(PathBuf, Option<u64>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(self.decode::<(String, Option<u64>)>().map(|(path, size)| (PathBuf::from(path), size)))
+    }
+}
+
+/// Merge a key-sorted snapshot stream with the key-sorted current paths
+///
+/// Only files present on both sides are sized, in batches; a file whose
+/// size is unknown on either side is never reported as changed.
+fn diff_sorted<I, F>(snapshot: I, current: &[(String, PathBuf)], size_of: F, progress: &AtomicUsize, cancel: &AtomicBool) -> Result<SnapshotDiff>
+where
+    I: Iterator<Item = Result<(PathBuf, Option<u64>)>>,
+    F: Fn(&Path) -> Option<u64> + Sync,
+{
+    let mut diff = SnapshotDiff::default();
+    let mut kept: Vec<(&Path, u64)> = Vec::new();
+    let mut current = current.iter().peekable();
+    let mut compared = 0;
+
+    let compare_sizes = |kept: &mut Vec<(&Path, u64)>, diff: &mut SnapshotDiff| {
+        let sizes: Vec<Option<u64>> = kept.par_iter().map(|(path, _)| size_of(path)).collect();
+        for ((path, before), after) in kept.drain(..).zip(sizes) {
+            if let Some(after) = after.filter(|&after| after != before) {
+                diff.changed.push(SizeChange { path: path.to_path_buf(), before, after });
+            }
+        }
+    };
+
+    for entry in snapshot {
+        let (old_path, old_size) = entry?;
+        let old_key = path_key(&old_path);
+
+        while let Some((_, path)) = current.next_if(|(key, _)| *key < old_key) {
+            diff.added.push(path.clone());
+        }
+        match current.next_if(|(key, _)| *key == old_key) {
+            Some((_, path)) => {
+                if let Some(before) = old_size {
+                    kept.push((path, before));
+                }
+            }
+            None => diff.removed.push((old_path, old_size)),
+        }
+
+        compared += 1;
+        if compared % ENTRY_BATCH == 0 {
+            if cancel.load(Ordering::SeqCst) {
+                return Err(FlashFindError::Cancelled);
+            }
+            compare_sizes(&mut kept, &mut diff);
+            progress.fetch_add(ENTRY_BATCH, Ordering::Relaxed);
+        }
+    }
+    diff.added.extend(current.map(|(_, path)| path.clone()));
+    compare_sizes(&mut kept, &mut diff);
+    progress.fetch_add(compared % ENTRY_BATCH, Ordering::Relaxed);
+
+    Ok(diff)
+}
+
+/// Finished snapshot work, for the UI to pick up
+#[derive(Debug)]
+pub enum SnapshotOutcome {
+    Created(SnapshotInfo),
+    Diffed(SnapshotInfo, Arc<SnapshotDiff>),
+}
+
+/// Snapshot creation or diff running on a background thread
+pub struct SnapshotJob {
+    processed: Arc<AtomicUsize>,
+    total: Arc<AtomicUsize>,
+    cancel: Arc<AtomicBool>,
+    outcome: Arc<Mutex<Option<Result<SnapshotOutcome>>>>,
+}
+
+impl SnapshotJob {
+    /// Snapshot every live entry of `index` under `name`
+    pub fn create(store: SnapshotStore, index: Arc<ShardedIndex>, name: String) -> Self {
+        Self::spawn(move |processed, total, cancel| {
+            let paths: Vec<PathBuf> = index.read().par_iter().map(|(path, _)| path).collect();
+            total.store(paths.len(), Ordering::Relaxed);
+            store.create(&name, paths, file_size, processed, cancel).map(SnapshotOutcome::Created)
+        })
+    }
+
+    /// Compare the snapshot `info` with every live entry of `index`
+    pub fn diff(store: SnapshotStore, index: Arc<ShardedIndex>, info: SnapshotInfo) -> Self {
+        Self::spawn(move |processed, total, cancel| {
+            total.store(info.files, Ordering::Relaxed);
+            let paths: Vec<PathBuf> = index.read().par_iter().map(|(path, _)| path).collect();
+            let diff = store.diff(&info.id, paths, file_size, processed, cancel)?;
+            Ok(SnapshotOutcome::Diffed(info, Arc::new(diff)))
+        })
+    }
+
+    fn spawn<F>(work: F) -> Self
+    where
+        F: FnOnce(&AtomicUsize, &AtomicUsize, &AtomicBool) -> Result<SnapshotOutcome> + Send + 'static,
+    {
+        let job = Self {
+            processed: Arc::new(AtomicUsize::new(0)),
+            total: Arc::new(AtomicUsize::new(0)),
+            cancel: Arc::new(AtomicBool::new(false)),
+            outcome: Arc::new(Mutex::new(None)),
+        };
+
+        let (processed, total, cancel, outcome) =
+            (job.processed.clone(), job.total.clone(), job.cancel.clone(), job.outcome.clone());
+        thread::spawn(move || {
+            let result = work(&processed, &total, &cancel);
+            *outcome.lock() = Some(result);
+        });
+        job
+    }
+
+    /// Entries handled so far and the total expected
+    pub fn progress(&self) -> (usize, usize) {
+        (self.processed.load(Ordering::Relaxed), self.total.load(Ordering::Relaxed))
+    }
+
+    /// The result once the job has finished
+    pub fn take_outcome(&self) -> Option<Result<SnapshotOutcome>> {
+        self.outcome.lock().take()
+    }
+
+    /// Stop after the current batch
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::FileIndex;
+    use crate::test_dir::TestDir;
+    use ahash::AHashMap;
+
+    fn scratch_store(name: &str) -> (TestDir, SnapshotStore) {
+        let dir = TestDir::new(&format!("snapshot_{}", name));
+        let store = SnapshotStore::new(dir.to_path_buf());
+        (dir, store)
+    }
+
+    /// A synthetic index and the file sizes it stands for
+    fn synthetic(files: &[(&str, Option<u64>)]) -> (FileIndex, AHashMap<PathBuf, u64>) {
+        let mut index = FileIndex::new();
+        let mut sizes = AHashMap::new();
+        for (path, size) in files {
+            index.insert(PathBuf::from(path)).unwrap();
+            if let Some(size) = size {
+                sizes.insert(PathBuf::from(path), *size);
+            }
+        }
+        (index, sizes)
+    }
+
+    fn live_paths(index: &FileIndex) -> Vec<PathBuf> {
+        index.iter().map(|(_, path)| path).collect()
+    }
+
+    #[test]
+    fn test_diff_reports_added_removed_and_resized() {
+        let (_dir, store) = scratch_store("diff");
+        let (before, before_sizes) = synthetic(&[
+            ("C:\\Program Files\\App\\app.exe", Some(1_000)),
+            ("C:\\Program Files\\App\\old.dll", Some(200)),
+            ("C:\\Users\\me\\notes.txt", Some(10)),
+            ("C:\\Users\\me\\video.mp4", None),
+        ]);
+        let (progress, cancel) = (AtomicUsize::new(0), AtomicBool::new(false));
+        let info = store.create(" before install ", live_paths(&before), |p| before_sizes.get(p).copied(), &progress, &cancel).unwrap();
+        assert_eq!((info.name.as_str(), info.files), ("before install", 4));
+        assert_eq!(progress.load(Ordering::Relaxed), 4);
+
+        let (after, after_sizes) = synthetic(&[
+            ("C:\\Program Files\\App\\app.exe", Some(1_500)),
+            ("C:\\Program Files\\App\\new.dll", Some(300)),
+            // Same file under another casing is not a change on Windows
+            ("C:\\users\\me\\NOTES.txt", Some(10)),
+            ("C:\\Users\\me\\video.mp4", Some(9_000)),
+            ("D:\\cache\\blob.bin", Some(1)),
+        ]);
+        let progress = AtomicUsize::new(0);
+        let diff = store.diff(&info.id, live_paths(&after), |p| after_sizes.get(p).copied(), &progress, &cancel).unwrap();
+
+        assert_eq!(diff.added, vec![PathBuf::from("C:\\Program Files\\App\\new.dll"), PathBuf::from("D:\\cache\\blob.bin")]);
+        assert_eq!(diff.removed, vec![(PathBuf::from("C:\\Program Files\\App\\old.dll"), Some(200))]);
+        assert_eq!(
+            diff.changed,
+            vec![SizeChange { path: PathBuf::from("C:\\Program Files\\App\\app.exe"), before: 1_000, after: 1_500 }]
+        );
+        assert_eq!(progress.load(Ordering::Relaxed), 4);
+
+    }
+
+    #[test]
+    fn test_diff_streams_across_batches() {
+        let (_dir, store) = scratch_store("batches");
+        let count = ENTRY_BATCH * 2 + 17;
+        let before: Vec<PathBuf> = (0..count).map(|i| PathBuf::from(format!("/data/{:06}.bin", i))).collect();
+        let (progress, cancel) = (AtomicUsize::new(0), AtomicBool::new(false));
+        let info = store.create("big", before.clone(), |_| Some(5), &progress, &cancel).unwrap();
+
+        // Every third file removed, every fifth grown, and a few new ones
+        let mut after: Vec<PathBuf> = before.iter().enumerate().filter(|(i, _)| i % 3 != 0).map(|(_, p)| p.clone()).collect();
+        after.push(PathBuf::from("/data/zz_new.bin"));
+        let grown = |p: &Path| {
+            let n: usize = p.file_stem()?.to_str()?.parse().ok()?;
+            Some(if n.is_multiple_of(5) { 6 } else { 5 })
+        };
+        let progress = AtomicUsize::new(0);
+        let diff = store.diff(&info.id, after, grown, &progress, &cancel).unwrap();
+
+        assert_eq!(diff.removed.len(), count.div_ceil(3));
+        assert_eq!(diff.added, vec![PathBuf::from("/data/zz_new.bin")]);
+        let expected_changed = (0..count).filter(|i| i % 3 != 0 && i % 5 == 0).count();
+        assert_eq!(diff.changed.len(), expected_changed);
+        assert_eq!(progress.load(Ordering::Relaxed), count);
+
+    }
+
+    #[test]
+    fn test_list_rename_delete() {
+        let (dir, store) = scratch_store("manage");
+        assert!(store.list().is_empty());
+        let (progress, cancel) = (AtomicUsize::new(0), AtomicBool::new(false));
+        let first = store.create("first", vec![PathBuf::from("/a")], |_| None, &progress, &cancel).unwrap();
+        let second = store.create("second", vec![PathBuf::from("/b")], |_| None, &progress, &cancel).unwrap();
+        assert_ne!(first.id, second.id);
+
+        store.rename(&first.id, "renamed").unwrap();
+        let names: Vec<String> = store.list().into_iter().map(|info| info.name).collect();
+        assert_eq!(names, vec!["second", "renamed"]);
+
+        store.delete(&second.id).unwrap();
+        assert_eq!(store.list().len(), 1);
+        assert!(!dir.join(format!("{}.snap", second.id)).exists());
+
+    }
+
+    #[test]
+    fn test_cancelled_or_corrupt_snapshots() {
+        let (dir, store) = scratch_store("cancel");
+        let paths: Vec<PathBuf> = (0..10).map(|i| PathBuf::from(format!("/x/{}", i))).collect();
+        let result = store.create("never", paths, |_| None, &AtomicUsize::new(0), &AtomicBool::new(true));
+        assert!(matches!(result, Err(FlashFindError::Cancelled)));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        let (progress, cancel) = (AtomicUsize::new(0), AtomicBool::new(false));
+        let info = store.create("ok", vec![PathBuf::from("/x/1")], |_| None, &progress, &cancel).unwrap();
+        fs::write(dir.join(format!("{}.snap", info.id)), b"not a snapshot").unwrap();
+        let result = store.diff(&info.id, Vec::new(), |_| None, &progress, &cancel);
+        assert!(matches!(result, Err(FlashFindError::CorruptSnapshot { .. })));
+
+    }
+}
@@ -0,0 +1,358 @@
+use ahash::AHashMap;
+use parking_lot::RwLock;
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+use tracing::debug;
+
+use crate::config::SavedSearch;
+use crate::index::{parse_query, FileIndex, ParsedQuery};
+use crate::sharded::{IndexView, ShardedIndex};
+
+/// Most suggestions shown under the search box
+pub const MAX_SUGGESTIONS: usize = 8;
+
+/// Shortest word that gets filename completions
+const MIN_PREFIX_CHARS: usize = 2;
+
+/// Most "did you mean" names offered when a search finds nothing
+pub const MAX_DID_YOU_MEAN: usize = 5;
+
+/// Shortest query that gets "did you mean" names; shorter ones are close
+/// to nearly everything
+const MIN_DID_YOU_MEAN_CHARS: usize = 5;
+
+/// Most edits between a query and a name offered for it
+const MAX_DID_YOU_MEAN_EDITS: usize = 2;
+
+/// Words from indexed filenames, sorted for prefix lookup, with file counts
+///
+/// A filename's words are the runs of letters and digits in its stem, the
+/// same boundaries the search uses for its word-start tier.
+#[derive(Debug, Default)]
+pub struct TokenIndex {
+    /// Index generation the words were collected at
+    generation: u64,
+    tokens: Vec<(String, usize)>,
+}
+
+impl TokenIndex {
+    pub fn build(index: &IndexView) -> Self {
+        let start = Instant::now();
+        let mut counts: AHashMap<&str, usize> = AHashMap::new();
+        for (name, files) in index.shards().flat_map(FileIndex::filename_counts) {
+            for token in name_tokens(name) {
+                *counts.entry(token).or_insert(0) += files;
+            }
+        }
+
+        let mut tokens: Vec<(String, usize)> = counts.into_iter().map(|(t, n)| (t.to_string(), n)).collect();
+        tokens.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        debug!("Built {} completion words in {:?}", tokens.len(), start.elapsed());
+
+        Self { generation: index.generation(), tokens }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Words longer than `prefix` that start with it, most files first
+    pub fn complete(&self, prefix: &str, limit: usize) -> Vec<(&str, usize)> {
+        let first = self.tokens.partition_point(|(token, _)| token.as_str() < prefix);
+        let mut matches: Vec<(&str, usize)> = self.tokens[first..]
+            .iter()
+            .take_while(|(token, _)| token.starts_with(prefix))
+            .filter(|(token, _)| token.len() > prefix.len())
+            .map(|(token, files)| (token.as_str(), *files))
+            .collect();
+
+        matches.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        matches.truncate(limit);
+        matches
+    }
+}
+
+/// Letter and digit runs in a filename's stem
+fn name_tokens(name: &str) -> impl Iterator<Item = &str> {
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    stem.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| token.chars().count() >= MIN_PREFIX_CHARS)
+}
+
+/// Where a suggestion came from
+#[derive(Debug, Clone, PartialEq)]
+pub enum SuggestionSource {
+    /// A saved search; carries its name
+    SavedSearch(String),
+    /// A filename word found in this many files
+    Filename(usize),
+}
+
+/// A completion for the search box
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// The whole query after accepting it
+    pub query: String,
+    pub source: SuggestionSource,
+}
+
+impl Suggestion {
+    /// The text shown in the dropdown
+    pub fn label(&self) -> String {
+        match &self.source {
+            SuggestionSource::SavedSearch(name) if *name == self.query => format!("⭐ {}", name),
+            SuggestionSource::SavedSearch(name) => format!("⭐ {} — {}", name, self.query),
+            SuggestionSource::Filename(1) => format!("{} — 1 file", self.query),
+            SuggestionSource::Filename(files) => format!("{} — {} files", self.query, files),
+        }
+    }
+}
+
+/// Completions for `query`: matching saved searches first, then filename words
+///
+/// Only the last word of the query is completed; the text before it is kept.
+/// Paths, `*` and operator queries get saved searches only.
+pub fn suggest(query: &str, tokens: Option<&TokenIndex>, saved: &[SavedSearch]) -> Vec<Suggestion> {
+    let typed = query.trim_start().to_lowercase();
+    if typed.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let mut suggestions: Vec<Suggestion> = saved
+        .iter()
+        .filter(|s| {
+            let saved_query = s.query.to_lowercase();
+            saved_query != typed.trim_end()
+                && (saved_query.starts_with(&typed) || s.name.to_lowercase().starts_with(&typed))
+        })
+        .map(|s| Suggestion { query: s.query.clone(), source: SuggestionSource::SavedSearch(s.name.clone()) })
+        .take(MAX_SUGGESTIONS)
+        .collect();
+
+    let Some(tokens) = tokens else {
+        return suggestions;
+    };
+    if !matches!(parse_query(&typed), ParsedQuery::Filename(_) | ParsedQuery::Boolean(_)) {
+        return suggestions;
+    }
+
+    // Keep the earlier words as typed, so AND, OR and NOT stay operators
+    let original = query.trim_start();
+    let word_start = original.rfind(char::is_whitespace).map_or(0, |pos| pos + 1);
+    let word = original[word_start..].to_lowercase();
+    if word.chars().count() < MIN_PREFIX_CHARS || matches!(&original[word_start..], "AND" | "OR" | "NOT") {
+        return suggestions;
+    }
+
+    let room = MAX_SUGGESTIONS - suggestions.len();
+    for (token, files) in tokens.complete(&word, room) {
+        let completed = format!("{}{}", &original[..word_start], token);
+        if suggestions.iter().all(|s| s.query.to_lowercase() != completed.to_lowercase()) {
+            suggestions.push(Suggestion { query: completed, source: SuggestionSource::Filename(files) });
+        }
+    }
+    suggestions
+}
+
+/// Indexed filenames closest to a plain filename query that found nothing
+///
+/// Each distinct filename, and its stem without the extension, is compared
+/// with the whole query, allowing two edits (insertions, deletions,
+/// substitutions and swaps of neighbouring letters). That is more than the
+/// fuzzy search allows below eight characters, so names it missed can still
+/// be offered. Closest first, then the names most files share.
+pub fn did_you_mean(index: &IndexView, query: &str, limit: usize) -> Vec<String> {
+    let ParsedQuery::Filename(needle) = parse_query(query) else {
+        return Vec::new();
+    };
+    let needle: Vec<char> = needle.chars().collect();
+    if needle.len() < MIN_DID_YOU_MEAN_CHARS {
+        return Vec::new();
+    }
+
+    // A renamed entry can leave a name counted in more than one shard
+    let mut counts: AHashMap<&str, usize> = AHashMap::new();
+    for (name, files) in index.shards().flat_map(FileIndex::filename_counts) {
+        *counts.entry(name).or_insert(0) += files;
+    }
+    let mut close: Vec<(usize, usize, &str)> = counts
+        .into_iter()
+        .filter_map(|(name, files)| {
+            let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+            let distance = |candidate: &str| {
+                edit_distance(&candidate.chars().collect::<Vec<char>>(), &needle, MAX_DID_YOU_MEAN_EDITS)
+            };
+            let edits = match (distance(name), distance(stem)) {
+                (Some(a), Some(b)) => a.min(b),
+                (edits, None) | (None, edits) => edits?,
+            };
+            // An exact name would have been found
+            (edits > 0).then_some((edits, files, name))
+        })
+        .collect();
+    close.sort_unstable_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)).then_with(|| a.2.cmp(b.2)));
+    close.into_iter().take(limit).map(|(_, _, name)| name.to_string()).collect()
+}
+
+/// Edits turning `a` into `b`, if no more than `max`
+fn edit_distance(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut before: Vec<usize> = (0..=b.len()).collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut d = (previous[j - 1] + cost).min(previous[j] + 1).min(current[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d = d.min(before[j - 2] + 1);
+            }
+            current[j] = d;
+        }
+        // Every later row is at least the smallest of this one
+        if current.iter().min().is_some_and(|&least| least > max) {
+            return None;
+        }
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    Some(previous[b.len()]).filter(|&d| d <= max)
+}
+
+/// Background rebuild of the completion words after the index changes
+pub struct TokenIndexJob {
+    result: Arc<RwLock<Option<TokenIndex>>>,
+}
+
+impl TokenIndexJob {
+    pub fn start(index: Arc<ShardedIndex>) -> Self {
+        let result = Arc::new(RwLock::new(None));
+        let thread_result = result.clone();
+        thread::spawn(move || {
+            let tokens = TokenIndex::build(&index.read());
+            *thread_result.write() = Some(tokens);
+        });
+
+        Self { result }
+    }
+
+    /// The finished word list, once the job is done
+    pub fn take(&self) -> Option<TokenIndex> {
+        self.result.write().take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn sample_tokens() -> TokenIndex {
+        let index = ShardedIndex::new();
+        for path in [
+            "/docs/invoice_2023.pdf",
+            "/docs/invoice_2024.pdf",
+            "/docs/Invoice-March.xlsx",
+            "/docs/inventory.csv",
+            "/docs/wedding invitation.docx",
+            "/docs/inv.txt",
+        ] {
+            index.insert(PathBuf::from(path)).unwrap();
+        }
+        index.publish();
+        TokenIndex::build(&index.read())
+    }
+
+    fn queries(suggestions: &[Suggestion]) -> Vec<&str> {
+        suggestions.iter().map(|s| s.query.as_str()).collect()
+    }
+
+    #[test]
+    fn test_completes_words_by_file_count() {
+        let tokens = sample_tokens();
+        assert_eq!(tokens.complete("inv", 8), vec![("invoice", 3), ("inventory", 1), ("invitation", 1)]);
+        assert_eq!(tokens.complete("inv", 1), vec![("invoice", 3)]);
+        assert_eq!(tokens.complete("march", 8), Vec::<(&str, usize)>::new());
+        assert!(tokens.complete("zzz", 8).is_empty());
+    }
+
+    #[test]
+    fn test_saved_searches_come_first_and_last_word_is_completed() {
+        let tokens = sample_tokens();
+        let saved = vec![
+            SavedSearch { name: "Invoices".into(), query: "invoice .pdf".into(), filter: None, watch: false },
+            SavedSearch { name: "Music".into(), query: ".mp3".into(), filter: None, watch: false },
+        ];
+
+        let suggestions = suggest("Inv", Some(&tokens), &saved);
+        assert_eq!(queries(&suggestions), vec!["invoice .pdf", "invoice", "inventory", "invitation"]);
+        assert_eq!(suggestions[0].label(), "⭐ Invoices — invoice .pdf");
+        assert_eq!(suggestions[1].label(), "invoice — 3 files");
+        assert_eq!(suggestions[2].label(), "inventory — 1 file");
+
+        let suggestions = suggest("wedding inv", Some(&tokens), &[]);
+        assert_eq!(queries(&suggestions)[0], "wedding invoice");
+        let suggestions = suggest("March OR inv", Some(&tokens), &[]);
+        assert_eq!(queries(&suggestions)[0], "March OR invoice");
+        assert!(suggest("invoice OR", Some(&tokens), &[]).is_empty());
+    }
+
+    #[test]
+    fn test_did_you_mean_offers_the_closest_names() {
+        let index = ShardedIndex::new();
+        for path in [
+            "/docs/report.pdf",
+            "/old/report.pdf",
+            "/docs/repost.txt",
+            "/docs/invoice.pdf",
+            "/docs/budget.xlsx",
+            "/photos/holiday.jpg",
+            "/docs/ab.txt",
+            "/q3/quarterly_reports.xlsx",
+            "/q3/quarterly_report.pdf",
+        ] {
+            index.insert(PathBuf::from(path)).unwrap();
+        }
+        index.publish();
+        let mean = |query: &str| did_you_mean(&index.read(), query, MAX_DID_YOU_MEAN);
+
+        // A swapped pair is one edit, and the stem counts without its extension
+        assert_eq!(mean("reprot"), ["report.pdf", "repost.txt"]);
+        assert_eq!(mean("quarterly_reprot"), ["quarterly_report.pdf", "quarterly_reports.xlsx"]);
+        assert_eq!(mean("invoce"), ["invoice.pdf"]);
+        assert_eq!(mean("Budgte.xlsx"), ["budget.xlsx"]);
+        assert_eq!(mean("holidya"), ["holiday.jpg"]);
+        assert_eq!(did_you_mean(&index.read(), "reprot", 1), ["report.pdf"]);
+        assert!(mean("zzzzzz").is_empty());
+        // Too short, or not a filename query
+        assert!(mean("abxy").is_empty());
+        assert!(mean("ext:pfd").is_empty());
+        assert!(mean("/docs/reprot").is_empty());
+    }
+
+    #[test]
+    fn test_edit_distance_is_bounded() {
+        let chars = |s: &str| s.chars().collect::<Vec<char>>();
+        let distance = |a: &str, b: &str, max| edit_distance(&chars(a), &chars(b), max);
+        assert_eq!(distance("report", "reprot", 2), Some(1));
+        assert_eq!(distance("invoice", "invoce", 2), Some(1));
+        assert_eq!(distance("invoice", "invoicexx", 2), Some(2));
+        assert_eq!(distance("invoice", "invoicexxx", 2), None);
+        assert_eq!(distance("budget", "gudbet", 1), None);
+        assert_eq!(distance("same", "same", 0), Some(0));
+    }
+
+    #[test]
+    fn test_no_filename_words_for_short_or_special_queries() {
+        let tokens = sample_tokens();
+        assert!(suggest("", Some(&tokens), &[]).is_empty());
+        assert!(suggest("i", Some(&tokens), &[]).is_empty());
+        assert!(suggest("/docs/inv", Some(&tokens), &[]).is_empty());
+        assert!(suggest("inv", None, &[]).is_empty());
+    }
+}
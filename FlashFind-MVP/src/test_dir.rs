@@ -0,0 +1,43 @@
+//! Scratch directories for tests
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// Scratch directory for one test, removed when dropped
+///
+/// Lives under the crate's `target` dir rather than the temp dir, which the
+/// default exclusions skip, so scans and watchers see its files.
+pub struct TestDir(PathBuf);
+
+impl TestDir {
+    /// Create an empty `target/flashfind_<name>_<pid>`
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::current_dir()
+            .unwrap()
+            .join("target")
+            .join(format!("flashfind_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TestDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(crate::paths::to_long_path(&self.0));
+    }
+}
@@ -0,0 +1,73 @@
+//! Log capture for tests that check what reaches the log
+
+use parking_lot::{Mutex, MutexGuard};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::{fmt, Layer};
+
+/// Held by the test capturing the log; it may also flip the process-wide
+/// privacy and redaction flags, which every thread reads
+static CAPTURE: Mutex<()> = Mutex::new(());
+
+static CAPTURING: AtomicBool = AtomicBool::new(false);
+
+static LOGGED: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+/// Everything logged by any thread while alive, restoring the flags on drop
+pub struct CapturedLog {
+    incognito: bool,
+    redaction: bool,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl CapturedLog {
+    /// Start capturing, waiting for any other capturing test to finish
+    pub fn start() -> Self {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            let layer = fmt::layer()
+                .with_ansi(false)
+                .with_writer(|| Writer)
+                .with_filter(filter_fn(|_| CAPTURING.load(Ordering::Relaxed)));
+            tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer))
+                .expect("no other global subscriber in tests");
+        });
+
+        let lock = CAPTURE.lock();
+        LOGGED.lock().clear();
+        CAPTURING.store(true, Ordering::Relaxed);
+        Self {
+            incognito: crate::privacy::is_incognito(),
+            redaction: crate::redact::is_enabled(),
+            _lock: lock,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&LOGGED.lock()).into_owned()
+    }
+}
+
+impl Drop for CapturedLog {
+    fn drop(&mut self) {
+        CAPTURING.store(false, Ordering::Relaxed);
+        crate::privacy::set_incognito(self.incognito);
+        crate::redact::set_enabled(self.redaction);
+    }
+}
+
+struct Writer;
+
+impl io::Write for Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        LOGGED.lock().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
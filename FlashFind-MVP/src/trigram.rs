@@ -0,0 +1,107 @@
+use ahash::AHashMap;
+
+/// Inverted index from every three-byte run of a lowercased filename to the
+/// pool slots whose name contains it
+///
+/// A query of three or more bytes can only match names holding all of its
+/// trigrams, so intersecting their posting lists narrows a substring search
+/// to a few candidates instead of every distinct name. Ids are only ever
+/// added: removed slots linger until compaction rebuilds the index, so
+/// callers must verify each candidate.
+#[derive(Debug, Clone, Default)]
+pub struct TrigramIndex {
+    postings: AHashMap<[u8; 3], Vec<u32>>,
+}
+
+impl TrigramIndex {
+    /// File a pool slot under each trigram of its lowercased name
+    ///
+    /// Slots must be added in increasing order so posting lists stay sorted.
+    pub fn add(&mut self, idx: u32, name: &str) {
+        for trigram in name.as_bytes().windows(3) {
+            let ids = self.postings.entry([trigram[0], trigram[1], trigram[2]]).or_default();
+            // A name repeating a trigram ("aaaa") must list its slot once
+            if ids.last() != Some(&idx) {
+                ids.push(idx);
+            }
+        }
+    }
+
+    /// Sorted slots whose name holds every trigram of `query`
+    ///
+    /// `None` for queries shorter than three bytes, which have no trigrams
+    /// and need the full scan. Candidates may be stale or only contain the
+    /// trigrams scattered, so each must still be checked against the query.
+    pub fn candidates(&self, query: &str) -> Option<Vec<u32>> {
+        let bytes = query.as_bytes();
+        if bytes.len() < 3 {
+            return None;
+        }
+
+        let mut lists = Vec::new();
+        for trigram in bytes.windows(3) {
+            match self.postings.get(trigram) {
+                Some(ids) => lists.push(ids),
+                None => return Some(Vec::new()),
+            }
+        }
+        // Start from the rarest trigram so every step shrinks a short list
+        lists.sort_by_key(|ids| ids.len());
+        lists.dedup_by(|a, b| std::ptr::eq(*a, *b));
+
+        let mut found = lists[0].clone();
+        for ids in &lists[1..] {
+            found.retain(|id| ids.binary_search(id).is_ok());
+            if found.is_empty() {
+                break;
+            }
+        }
+        Some(found)
+    }
+
+    pub fn clear(&mut self) {
+        self.postings.clear();
+    }
+
+    /// Approximate heap use of the posting lists in bytes
+    pub fn memory_bytes(&self) -> usize {
+        self.postings
+            .values()
+            .map(|ids| ids.capacity() * std::mem::size_of::<u32>() + std::mem::size_of::<([u8; 3], Vec<u32>)>())
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates_hold_every_trigram() {
+        let mut index = TrigramIndex::default();
+        for (idx, name) in ["invoice.pdf", "voice memo.m4a", "notes.txt", "aaaa"].iter().enumerate() {
+            index.add(idx as u32, name);
+        }
+
+        assert_eq!(index.candidates("voice"), Some(vec![0, 1]));
+        assert_eq!(index.candidates("invoice"), Some(vec![0]));
+        assert_eq!(index.candidates("aaa"), Some(vec![3]));
+        assert_eq!(index.candidates("xyz"), Some(vec![]));
+        // Too short to have a trigram
+        assert_eq!(index.candidates("in"), None);
+        assert_eq!(index.candidates(""), None);
+
+        index.clear();
+        assert_eq!(index.candidates("voice"), Some(vec![]));
+    }
+
+    #[test]
+    fn test_multibyte_names_use_byte_trigrams() {
+        let mut index = TrigramIndex::default();
+        index.add(0, "résumé.docx");
+        index.add(1, "resume.docx");
+        assert_eq!(index.candidates("sumé"), Some(vec![0]));
+        assert_eq!(index.candidates("ré"), Some(vec![0]));
+        assert_eq!(index.candidates("sum"), Some(vec![0, 1]));
+    }
+}
@@ -0,0 +1,373 @@
+use ahash::AHashMap;
+use parking_lot::RwLock;
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use tracing::info;
+
+use crate::index::UNKNOWN_SIZE;
+use crate::paths::{is_within, path_key};
+use crate::redact::redact;
+use crate::sharded::{IndexView, ShardedIndex};
+
+/// Files counted per batch between progress updates and cancel checks
+const USAGE_BATCH: usize = 4_096;
+
+/// Files and bytes under one folder, including its subfolders
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirUsage {
+    pub files: usize,
+    pub bytes: u64,
+    /// Files counted in `files` whose size is unknown
+    pub unsized_files: usize,
+}
+
+impl DirUsage {
+    fn add(&mut self, size: Option<u64>) {
+        self.files += 1;
+        match size {
+            Some(bytes) => self.bytes += bytes,
+            None => self.unsized_files += 1,
+        }
+    }
+}
+
+/// Column the space usage list is sorted by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UsageSort {
+    #[default]
+    Size,
+    Files,
+    Name,
+}
+
+impl UsageSort {
+    pub const ALL: [UsageSort; 3] = [UsageSort::Size, UsageSort::Files, UsageSort::Name];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            UsageSort::Size => "Size",
+            UsageSort::Files => "Files",
+            UsageSort::Name => "Name",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct DirNode {
+    path: PathBuf,
+    usage: DirUsage,
+    /// Keys of the direct subfolders
+    children: Vec<String>,
+}
+
+/// Per-folder totals for every folder under a root, built from indexed paths
+///
+/// Folders are keyed by `path_key`, so Windows paths that differ only in case
+/// or separator style add up in one folder.
+#[derive(Debug)]
+pub struct UsageReport {
+    root_key: String,
+    /// Path components in the root
+    root_depth: usize,
+    dirs: AHashMap<String, DirNode>,
+}
+
+impl UsageReport {
+    pub fn new(root: &Path) -> Self {
+        let root_str = root.to_string_lossy();
+        let trimmed = root_str.trim_end_matches(['/', '\\']);
+        let root_key = path_key(Path::new(trimmed));
+        let root_node = DirNode { path: root.to_path_buf(), usage: DirUsage::default(), children: Vec::new() };
+
+        Self {
+            root_depth: trimmed.split(['/', '\\']).filter(|c| !c.is_empty()).count(),
+            dirs: AHashMap::from_iter([(root_key.clone(), root_node)]),
+            root_key,
+        }
+    }
+
+    /// Count a file inside the root into each of its folders
+    pub fn add(&mut self, path: &Path, size: Option<u64>) {
+        // Split on both separators so Windows paths aggregate the same everywhere
+        let path_str = path.to_string_lossy();
+        let mut depth = 0;
+        let mut in_component = false;
+        let mut parent: Option<String> = None;
+
+        // A filesystem root ("/") is reached before any component
+        if self.root_depth == 0 {
+            if let Some(root) = self.dirs.get_mut(&self.root_key) {
+                root.usage.add(size);
+            }
+            parent = Some(self.root_key.clone());
+        }
+
+        for (pos, c) in path_str.char_indices() {
+            if c != '/' && c != '\\' {
+                in_component = true;
+                continue;
+            }
+            if !in_component {
+                continue;
+            }
+            in_component = false;
+            depth += 1;
+            if depth < self.root_depth {
+                continue;
+            }
+
+            let dir = &path_str[..pos];
+            let key = if depth == self.root_depth { self.root_key.clone() } else { path_key(Path::new(dir)) };
+            if !self.dirs.contains_key(&key) {
+                let node = DirNode { path: PathBuf::from(dir), usage: DirUsage::default(), children: Vec::new() };
+                self.dirs.insert(key.clone(), node);
+                if let Some(node) = parent.as_ref().and_then(|p| self.dirs.get_mut(p)) {
+                    node.children.push(key.clone());
+                }
+            }
+            if let Some(node) = self.dirs.get_mut(&key) {
+                node.usage.add(size);
+            }
+            parent = Some(key);
+        }
+    }
+
+    /// Totals for `dir`, or nothing if no indexed file is inside it
+    pub fn usage(&self, dir: &Path) -> DirUsage {
+        self.node(dir).map(|node| node.usage).unwrap_or_default()
+    }
+
+    /// Direct subfolders of `dir` with their totals, sorted by `sort`
+    pub fn children(&self, dir: &Path, sort: UsageSort) -> Vec<(PathBuf, DirUsage)> {
+        let Some(node) = self.node(dir) else {
+            return Vec::new();
+        };
+        let mut children: Vec<(PathBuf, DirUsage)> = node
+            .children
+            .iter()
+            .filter_map(|key| self.dirs.get(key))
+            .map(|child| (child.path.clone(), child.usage))
+            .collect();
+
+        let name = |p: &Path| p.to_string_lossy().to_lowercase();
+        match sort {
+            UsageSort::Size => children.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then_with(|| name(&a.0).cmp(&name(&b.0)))),
+            UsageSort::Files => children.sort_by(|a, b| b.1.files.cmp(&a.1.files).then_with(|| name(&a.0).cmp(&name(&b.0)))),
+            UsageSort::Name => children.sort_by_key(|(path, _)| name(path)),
+        }
+        children
+    }
+
+    /// Files directly in `dir`, outside any subfolder
+    pub fn direct_files(&self, dir: &Path) -> DirUsage {
+        let mut direct = self.usage(dir);
+        for (_, child) in self.children(dir, UsageSort::Name) {
+            direct.files -= child.files;
+            direct.bytes -= child.bytes;
+            direct.unsized_files -= child.unsized_files;
+        }
+        direct
+    }
+
+    fn node(&self, dir: &Path) -> Option<&DirNode> {
+        let dir_str = dir.to_string_lossy();
+        let key = path_key(Path::new(dir_str.trim_end_matches(['/', '\\'])));
+        self.dirs.get(&key)
+    }
+}
+
+/// Progress of a space usage job; `report` is set once it finishes
+#[derive(Debug, Clone, Default)]
+pub struct UsageProgress {
+    pub processed: usize,
+    pub total: usize,
+    pub done: bool,
+    pub cancelled: bool,
+    pub report: Option<Arc<UsageReport>>,
+}
+
+/// Background job aggregating the indexed files under a root by folder
+///
+/// Paths and sizes come from the in-memory index; no folder is walked and no
+/// file is stat'ed. Files whose size the index does not know (archive
+/// members, entries imported without metadata) are counted as unsized.
+pub struct UsageJob {
+    progress: Arc<RwLock<UsageProgress>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl UsageJob {
+    pub fn start(index: Arc<ShardedIndex>, root: PathBuf) -> Self {
+        let progress = Arc::new(RwLock::new(UsageProgress::default()));
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let thread_progress = progress.clone();
+        let thread_cancel = cancel.clone();
+        thread::spawn(move || {
+            let files = sized_files_within(&index.read(), &root);
+            aggregate(&root, &files, &thread_progress, &thread_cancel);
+        });
+
+        Self { progress, cancel }
+    }
+
+    /// Snapshot of the job so far
+    pub fn progress(&self) -> UsageProgress {
+        self.progress.read().clone()
+    }
+
+    /// Stop after the current batch
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Every indexed file inside `root` with its stored size, if known
+fn sized_files_within(index: &IndexView, root: &Path) -> Vec<(PathBuf, Option<u64>)> {
+    index
+        .par_iter()
+        .filter(|(path, meta)| !meta.is_dir && is_within(path, root))
+        .map(|(path, meta)| (path, (meta.size != UNKNOWN_SIZE).then_some(meta.size)))
+        .collect()
+}
+
+/// Build the report in batches, publishing progress after each
+fn aggregate(root: &Path, files: &[(PathBuf, Option<u64>)], progress: &RwLock<UsageProgress>, cancel: &AtomicBool) {
+    progress.write().total = files.len();
+    let mut report = UsageReport::new(root);
+
+    for batch in files.chunks(USAGE_BATCH) {
+        if cancel.load(Ordering::SeqCst) {
+            let mut state = progress.write();
+            state.cancelled = true;
+            state.done = true;
+            return;
+        }
+
+        for (path, size) in batch {
+            report.add(path, *size);
+        }
+        progress.write().processed += batch.len();
+    }
+
+    let total = report.usage(root);
+    info!("Space usage for {}: {} files, {} bytes, {} unsized", redact(root), total.files, total.bytes, total.unsized_files);
+    let mut state = progress.write();
+    state.report = Some(Arc::new(report));
+    state.done = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_report() -> UsageReport {
+        let mut report = UsageReport::new(Path::new("C:\\Users\\me\\"));
+        for (path, size) in [
+            ("C:\\Users\\me\\notes.txt", Some(10)),
+            ("C:\\Users\\me\\Videos\\trip.mp4", Some(5_000)),
+            ("C:\\Users\\me\\Videos\\2023\\clip.mp4", Some(3_000)),
+            ("C:\\Users\\me\\Documents\\a.docx", Some(200)),
+            ("C:\\Users\\me\\documents\\b.docx", None),
+            ("C:\\Users\\me\\Documents\\Taxes\\2023.pdf", Some(100)),
+            ("C:\\Users\\me\\Documents\\Taxes\\2024.pdf", Some(150)),
+            ("C:\\Users\\me\\Documents\\Taxes\\2025.pdf", Some(50)),
+        ] {
+            report.add(Path::new(path), size);
+        }
+        report
+    }
+
+    #[test]
+    fn test_folders_total_their_subtrees() {
+        let report = sample_report();
+        let root = Path::new("C:\\Users\\me");
+        assert_eq!(report.usage(root), DirUsage { files: 8, bytes: 8_510, unsized_files: 1 });
+        assert_eq!(report.direct_files(root), DirUsage { files: 1, bytes: 10, unsized_files: 0 });
+
+        // "documents" and "Documents" are one folder on Windows
+        let documents = Path::new("C:\\Users\\me\\Documents");
+        assert_eq!(report.usage(documents), DirUsage { files: 5, bytes: 500, unsized_files: 1 });
+        assert_eq!(report.direct_files(documents), DirUsage { files: 2, bytes: 200, unsized_files: 1 });
+    }
+
+    #[test]
+    fn test_children_sort_and_drill_down() {
+        let report = sample_report();
+        let names = |sort| -> Vec<String> {
+            report
+                .children(Path::new("C:\\Users\\me\\"), sort)
+                .into_iter()
+                .map(|(path, _)| path.to_string_lossy().into_owned())
+                .collect()
+        };
+        assert_eq!(names(UsageSort::Size), vec!["C:\\Users\\me\\Videos", "C:\\Users\\me\\Documents"]);
+        assert_eq!(names(UsageSort::Files), vec!["C:\\Users\\me\\Documents", "C:\\Users\\me\\Videos"]);
+        assert_eq!(names(UsageSort::Name), vec!["C:\\Users\\me\\Documents", "C:\\Users\\me\\Videos"]);
+
+        let taxes = report.children(Path::new("C:\\Users\\me\\Documents"), UsageSort::Size);
+        assert_eq!(taxes, vec![(PathBuf::from("C:\\Users\\me\\Documents\\Taxes"), DirUsage { files: 3, bytes: 300, unsized_files: 0 })]);
+        assert!(report.children(Path::new("C:\\Users\\me\\Documents\\Taxes"), UsageSort::Size).is_empty());
+        assert_eq!(report.usage(Path::new("C:\\Elsewhere")), DirUsage::default());
+    }
+
+    #[test]
+    fn test_unix_root_and_cancel() {
+        let files: Vec<(PathBuf, Option<u64>)> = ["/home/me/a.txt", "/home/me/src/main.rs", "/home/me/src/lib.rs"]
+            .iter()
+            .map(|path| (PathBuf::from(path), Some(7)))
+            .collect();
+        let root = Path::new("/home/me");
+
+        let progress = RwLock::new(UsageProgress::default());
+        aggregate(root, &files, &progress, &AtomicBool::new(false));
+        let state = progress.read().clone();
+        assert!(state.done && !state.cancelled);
+        assert_eq!((state.processed, state.total), (3, 3));
+        let report = state.report.unwrap();
+        assert_eq!(report.usage(Path::new("/home/me/src")), DirUsage { files: 2, bytes: 14, unsized_files: 0 });
+
+        let progress = RwLock::new(UsageProgress::default());
+        aggregate(root, &files, &progress, &AtomicBool::new(true));
+        let state = progress.read().clone();
+        assert!(state.cancelled && state.report.is_none());
+    }
+
+    #[test]
+    fn test_filesystem_root_links_its_children() {
+        let mut report = UsageReport::new(Path::new("/"));
+        for (path, size) in [("/swapfile", Some(100)), ("/home/me/a.txt", Some(10)), ("/etc/hosts", None)] {
+            report.add(Path::new(path), size);
+        }
+
+        let root = Path::new("/");
+        assert_eq!(report.usage(root), DirUsage { files: 3, bytes: 110, unsized_files: 1 });
+        assert_eq!(report.direct_files(root), DirUsage { files: 1, bytes: 100, unsized_files: 0 });
+        let children: Vec<PathBuf> = report.children(root, UsageSort::Name).into_iter().map(|(path, _)| path).collect();
+        assert_eq!(children, vec![PathBuf::from("/etc"), PathBuf::from("/home")]);
+        assert_eq!(report.usage(Path::new("/home/me")), DirUsage { files: 1, bytes: 10, unsized_files: 0 });
+    }
+
+    #[test]
+    fn test_sizes_come_from_the_index() {
+        use crate::index::EntryMeta;
+
+        let index = ShardedIndex::new();
+        let meta = |size| EntryMeta { size, modified: 0, is_dir: false, attributes: 0 };
+        index.insert_with_meta(PathBuf::from("/data/report.pdf"), meta(2_048)).unwrap();
+        index.insert_with_meta(PathBuf::from("/data/photos.zip::IMG_001.jpg"), EntryMeta::UNKNOWN).unwrap();
+        index.insert_with_meta(PathBuf::from("/data/photos"), EntryMeta { is_dir: true, ..meta(UNKNOWN_SIZE) }).unwrap();
+        index.insert_with_meta(PathBuf::from("/elsewhere/notes.txt"), meta(5)).unwrap();
+        index.publish();
+
+        let mut files = sized_files_within(&index.read(), Path::new("/data"));
+        files.sort();
+        assert_eq!(
+            files,
+            vec![(PathBuf::from("/data/photos.zip::IMG_001.jpg"), None), (PathBuf::from("/data/report.pdf"), Some(2_048))]
+        );
+    }
+}
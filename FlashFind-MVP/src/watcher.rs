@@ -1,26 +1,57 @@
+use notify::event::{CreateKind, ModifyKind, RenameMode};
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as NotifyWatcher};
 use parking_lot::RwLock;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use tracing::{debug, error, info, warn};
 
+use crate::activity::ActivityLog;
+use crate::archive::{is_archive, is_member_of, list_members};
+use crate::alerts::SearchAlerts;
+use crate::changes::{ChangeFeed, RecentChange};
+use crate::config::{Config, IncludeFilter};
 use crate::error::{FlashFindError, Result};
-use crate::index::FileIndex;
+use crate::fold::fold;
+use crate::index::{EntryMeta, Glob};
+use crate::kinds::{find_kind_in, has_extension, KindGroup};
+use crate::paths::{from_long_path, is_windows_style, is_within, to_long_path};
+use crate::redact::redact;
+use crate::roots::RootMonitor;
+use crate::sharded::ShardedIndex;
 
 /// Filesystem watcher that monitors directories for changes
 pub struct Watcher {
     watcher: RecommendedWatcher,
     watched_dirs: Vec<PathBuf>,
+    /// Files the watcher recently added or removed
+    changes: Arc<ChangeFeed>,
 }
 
 impl Watcher {
-    /// Create a new watcher with the given index
-    pub fn new(index: Arc<RwLock<FileIndex>>) -> Result<Self> {
+    /// Create a new watcher with the given index and exclusion rules
+    ///
+    /// Events under roots the monitor reports as offline are ignored, so a
+    /// drive going to sleep neither floods the log nor drops indexed files.
+    /// Newly indexed files are checked against the watched saved searches,
+    /// and files added and removed are counted in `activity`.
+    pub fn new(
+        index: Arc<ShardedIndex>,
+        exclusions: Arc<RwLock<Exclusions>>,
+        roots: Arc<RootMonitor>,
+        alerts: Arc<SearchAlerts>,
+        activity: Arc<ActivityLog>,
+    ) -> Result<Self> {
         info!("Initializing filesystem watcher");
         
+        let changes = Arc::new(ChangeFeed::new());
+        let feed = changes.clone();
+        
         let watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
             match res {
-                Ok(event) => handle_fs_event(event, &index),
+                Ok(event) => handle_fs_event(event, &index, &exclusions, &roots, &feed, &alerts, &activity),
+                Err(e) if e.paths.iter().any(|p| !roots.is_available(&from_long_path(p))) => {
+                    debug!("Watcher error under offline root: {}", e);
+                }
                 Err(e) => error!("Watcher error: {}", e),
             }
         })
@@ -29,34 +60,46 @@ impl Watcher {
         Ok(Self {
             watcher,
             watched_dirs: Vec::new(),
+            changes,
         })
     }
     
     /// Watch a directory recursively
     pub fn watch_directory(&mut self, path: PathBuf) -> Result<()> {
-        if !path.exists() {
-            warn!("Cannot watch non-existent directory: {}", path.display());
+        let fs_path = to_long_path(&path);
+        
+        if !fs_path.exists() {
+            warn!("Cannot watch non-existent directory: {}", redact(&path));
             return Ok(()); // Don't fail, just skip
         }
         
-        if !path.is_dir() {
+        if !fs_path.is_dir() {
             return Err(FlashFindError::InvalidPath(
                 format!("{} is not a directory", path.display())
             ));
         }
         
         self.watcher
-            .watch(&path, RecursiveMode::Recursive)
+            .watch(&fs_path, RecursiveMode::Recursive)
             .map_err(|e| FlashFindError::WatchError {
                 path: path.display().to_string(),
                 source: e,
             })?;
         
-        info!("Watching directory: {}", path.display());
+        info!("Watching directory: {}", redact(&path));
         self.watched_dirs.push(path);
         Ok(())
     }
     
+    /// Re-establish the watch on a root that went offline and came back
+    ///
+    /// The old watch handle died with the directory, so it is dropped first.
+    pub fn rewatch(&mut self, path: &Path) -> Result<()> {
+        let _ = self.watcher.unwatch(&to_long_path(path));
+        self.watched_dirs.retain(|dir| dir != path);
+        self.watch_directory(path.to_path_buf())
+    }
+    
     /// Clear all watched directories
     pub fn clear_watches(&mut self) {
         info!("Clearing {} watched directories", self.watched_dirs.len());
@@ -82,6 +125,11 @@ impl Watcher {
         Ok(errors)
     }
     
+    /// Recent watcher-driven index changes, newest first
+    pub fn recent_changes(&self) -> Vec<RecentChange> {
+        self.changes.recent()
+    }
+    
     /// Get list of currently watched directories (used in settings)
     pub fn watched_directories(&self) -> &[PathBuf] {
         &self.watched_dirs
@@ -89,28 +137,157 @@ impl Watcher {
 }
 
 /// Handle filesystem events and update the index
-fn handle_fs_event(event: Event, index: &Arc<RwLock<FileIndex>>) {
+fn handle_fs_event(
+    event: Event,
+    index: &Arc<ShardedIndex>,
+    exclusions: &Arc<RwLock<Exclusions>>,
+    roots: &RootMonitor,
+    changes: &ChangeFeed,
+    alerts: &SearchAlerts,
+    activity: &ActivityLog,
+) {
+    index.record_watcher_event();
+    apply_fs_event(event, index, exclusions, roots, changes, alerts, activity);
+}
+
+/// Apply one filesystem event to the index
+fn apply_fs_event(
+    event: Event,
+    index: &Arc<ShardedIndex>,
+    exclusions: &Arc<RwLock<Exclusions>>,
+    roots: &RootMonitor,
+    changes: &ChangeFeed,
+    alerts: &SearchAlerts,
+    activity: &ActivityLog,
+) {
     match event.kind {
+        // A rename reported with both names moves the entry (see
+        // `ShardedIndex::rename`); one the index would not keep under the new
+        // name is a removal and a creation
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+            let (from, to) = (from_long_path(&event.paths[0]), from_long_path(&event.paths[1]));
+            let kept = roots.is_available(&to)
+                && !is_recycle_bin(&to)
+                && !is_temp_file(&to)
+                // Archive members are listed again on creation
+                && !is_archive(&from)
+                && !is_archive(&to)
+                && !exclusions.read().excludes(&to, index.is_directory(&from));
+            if kept {
+                let renamed = index.rename(&from, to.clone());
+                match renamed {
+                    Ok(true) => {
+                        debug!("Renamed in index: {} -> {}", redact(&from), redact(&to));
+                        changes.record_removed(from);
+                        changes.record_added(to.clone());
+                        alerts.evaluate(std::slice::from_ref(&to));
+                        return;
+                    }
+                    Ok(false) => {} // Not indexed under the old name
+                    Err(e) => warn!("Failed to rename in index: {}", e),
+                }
+            }
+            
+            let mut paths = event.paths.into_iter();
+            if let (Some(old), Some(new)) = (paths.next(), paths.next()) {
+                let removed = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::From))).add_path(old);
+                apply_fs_event(removed, index, exclusions, roots, changes, alerts, activity);
+                let created = Event::new(EventKind::Create(CreateKind::Any)).add_path(new);
+                apply_fs_event(created, index, exclusions, roots, changes, alerts, activity);
+            }
+        }
+        // A rename reports the old name as `From`, e.g. when moving to the Recycle Bin
+        EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            // With archives off no members are indexed, so skip the full pass
+            let archives_indexed = exclusions.read().archive_limit().is_some();
+            for path in event.paths {
+                let path = from_long_path(&path);
+                
+                // Keep entries while their root is offline; the files still exist
+                if !roots.is_available(&path) {
+                    continue;
+                }
+                
+                debug!("File removed: {}", redact(&path));
+                
+                let was_directory = index.is_directory(&path);
+                match index.remove(&path) {
+                    Ok(true) => {
+                        debug!("Removed from index: {}", redact(&path));
+                        changes.record_removed(path.clone());
+                        if !was_directory {
+                            activity.record_removed(path.parent(), 1);
+                        }
+                    }
+                    Ok(false) => {}, // Not in index
+                    Err(e) => warn!("Failed to remove file: {}", e),
+                }
+                
+                // A folder moved away reports only itself, not what it held
+                if was_directory {
+                    let removed = index.write().remove_where(|entry| is_within(entry, &path));
+                    debug!("Removed {} entries under folder {}", removed, redact(&path));
+                    activity.record_removed(Some(&path), removed as u64);
+                }
+                
+                if archives_indexed && is_archive(&path) {
+                    index.write().remove_where(|entry| is_member_of(entry, &path));
+                }
+            }
+        }
         EventKind::Create(_) | EventKind::Modify(_) => {
+            // Files are checked outside any lock, then each goes in under its
+            // own shard's lock, so only searches of that shard wait
+            let mut pending = Vec::new();
             for path in event.paths {
+                // Index the normal form, touch the filesystem via the long form
+                let path = from_long_path(&path);
+                let fs_path = to_long_path(&path);
+                
+                if !roots.is_available(&path) {
+                    continue; // Offline root, nothing reliable to read
+                }
+                
+                // Trashed files are never indexed, but pair up with removals
+                if is_recycle_bin(&path) {
+                    if fs_path.is_file() {
+                        changes.record_trash_arrival(path);
+                    }
+                    continue;
+                }
+                
                 // Check permissions before processing
-                if !has_read_permission(&path) {
-                    debug!("Skipping file without read permission: {}", path.display());
+                if !has_read_permission(&fs_path) {
+                    debug!("Skipping file without read permission: {}", redact(&path));
                     continue;
                 }
                 
-                if path.is_file() && !is_excluded(&path) && !is_temp_file(&path) {
-                    debug!("File created/modified: {}", path.display());
+                let is_dir = fs_path.is_dir();
+                let wanted = fs_path.is_file() || (is_dir && exclusions.read().indexes_directories());
+                if wanted && !exclusions.read().excludes(&path, is_dir) && !is_temp_file(&path) {
+                    debug!("File created/modified: {}", redact(&path));
                     
                     // Verify file is stable (not being written) before indexing
-                    if !is_file_stable(&path) {
-                        debug!("File not stable, skipping: {}", path.display());
+                    if !is_dir && !is_file_stable(&fs_path) {
+                        debug!("File not stable, skipping: {}", redact(&path));
                         continue;
                     }
                     
-                    let mut lock = index.write();
-                    match lock.insert(path.clone()) {
-                        Ok(true) => debug!("Added to index: {}", path.display()),
+                    // A modify event on an indexed file only refreshes its size and date
+                    let meta = std::fs::metadata(&fs_path).map_or(EntryMeta::UNKNOWN, |m| EntryMeta::from_metadata(&m));
+                    pending.push((path, meta));
+                }
+            }
+            
+            let mut added = Vec::new();
+            if !pending.is_empty() {
+                for (path, meta) in &pending {
+                    match index.insert_with_meta(path.clone(), *meta) {
+                        Ok(true) => {
+                            debug!("Added to index: {}", redact(path));
+                            changes.record_added(path.clone());
+                            added.push((path.clone(), *meta));
+                        }
                         Ok(false) => {}, // Duplicate, ignore
                         Err(e) => {
                             if !e.is_recoverable() {
@@ -120,23 +297,53 @@ fn handle_fs_event(event: Event, index: &Arc<RwLock<FileIndex>>) {
                     }
                 }
             }
-        }
-        EventKind::Remove(_) => {
-            for path in event.paths {
-                debug!("File removed: {}", path.display());
-                
-                let mut lock = index.write();
-                match lock.remove(&path) {
-                    Ok(true) => debug!("Removed from index: {}", path.display()),
-                    Ok(false) => {}, // Not in index
-                    Err(e) => warn!("Failed to remove file: {}", e),
+            
+            let limit = exclusions.read().archive_limit();
+            if let Some(limit) = limit {
+                for (path, _) in pending.iter().filter(|(path, _)| is_archive(path)) {
+                    reindex_archive(path, limit, index, exclusions);
                 }
             }
+            
+            if !added.is_empty() {
+                activity.record_added(&added);
+                let added: Vec<PathBuf> = added.into_iter().map(|(path, _)| path).collect();
+                alerts.evaluate(&added);
+            }
         }
         _ => {}
     }
 }
 
+/// Replace the indexed members of a changed archive with its current listing
+///
+/// An unreadable archive keeps no members; the next full scan reports it.
+fn reindex_archive(
+    archive: &Path,
+    limit: u64,
+    index: &Arc<ShardedIndex>,
+    exclusions: &Arc<RwLock<Exclusions>>,
+) {
+    let members = match list_members(archive, limit) {
+        Ok(members) => members,
+        Err(e) => {
+            warn!("Skipping archive {}: {}", redact(archive), e);
+            Vec::new()
+        }
+    };
+    
+    let rules = exclusions.read();
+    let mut lock = index.write();
+    let removed = lock.remove_where(|entry| is_member_of(entry, archive));
+    let mut added = 0;
+    for member in members.into_iter().filter(|m| !rules.excludes(m, false)) {
+        if matches!(lock.insert(member), Ok(true)) {
+            added += 1;
+        }
+    }
+    debug!("Re-indexed archive {}: {} members out, {} in", redact(archive), removed, added);
+}
+
 /// Check if a file is stable (not currently being written)
 fn is_file_stable(path: &Path) -> bool {
     use std::thread;
@@ -179,155 +386,354 @@ fn is_temp_file(path: &Path) -> bool {
         || filename.contains(".tmp.")    // Embedded temp markers
 }
 
-/// Check if a path should be excluded from indexing
-pub fn is_excluded(path: &Path) -> bool {
-    let path_str = path.to_string_lossy().to_lowercase();
-    
-    // System directories to exclude
-    let excluded = [
-        "$recycle.bin",
-        "appdata\\local",
-        "appdata\\locallow", 
-        "node_modules",
-        ".git",
-        ".svn",
-        ".hg",
-        "__pycache__",
-        "target\\debug",    // Rust build artifacts
-        "target\\release",
-        ".vs",              // Visual Studio
-        ".vscode",
-        "bin\\debug",       // .NET build artifacts
-        "bin\\release",
-        "obj",
-        "packages",         // NuGet packages
-        "bower_components",
-        ".cache",
-        "temp",
-        "tmp",
-        "windows\\temp",
-        "windows\\winsxs", // Windows component store (huge)
-        "windows\\installer",
-        "programdata\\microsoft", // System data
-    ];
-    
-    for pattern in &excluded {
-        if path_str.contains(pattern) {
-            return true;
+/// Built-in exclusion patterns, matched against directory components
+///
+/// Pattern syntax (case-insensitive):
+/// - `name` matches a directory component exactly
+/// - `prefix*` matches components starting with `prefix`
+/// - `*.ext`, `a?c` match components using `*`/`?` globs
+/// - `a/b` matches consecutive components `a` then `b`
+const EXCLUDED_DIRS: &[&str] = &[
+    "appdata/local",
+    "appdata/locallow",
+    "node_modules",
+    ".git",
+    ".svn",
+    ".hg",
+    "__pycache__",
+    "target/debug",          // Rust build artifacts
+    "target/release",
+    ".vs",                   // Visual Studio
+    ".vscode",
+    "bin/debug",             // .NET build artifacts
+    "bin/release",
+    "obj",
+    "packages",              // NuGet packages
+    "bower_components",
+    ".cache",
+    "temp",
+    "tmp",
+    "windows/winsxs",        // Windows component store (huge)
+    "windows/installer",
+    "programdata/microsoft", // System data
+];
+
+/// Rules that genuinely need raw substring matching on the full lowercased path
+///
+/// Prefer component patterns in `EXCLUDED_DIRS`; substrings cause false positives.
+const EXCLUDED_SUBSTRINGS: &[&str] = &[];
+
+/// A single exclusion rule matched against the directory components of a path
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExclusionRule {
+    /// Component equals the pattern exactly
+    Component(String),
+    /// Component starts with the pattern
+    Prefix(String),
+    /// Component matches a `*`/`?` glob
+    Glob(String),
+    /// Consecutive components equal the sequence
+    Sequence(Vec<String>),
+}
+
+impl ExclusionRule {
+    /// Parse a pattern string (see `EXCLUDED_DIRS` for the syntax)
+    pub fn parse(pattern: &str) -> Option<Self> {
+        let pattern = pattern.trim().to_lowercase();
+        let parts: Vec<String> = pattern
+            .split(['/', '\\'])
+            .filter(|p| !p.is_empty())
+            .map(str::to_string)
+            .collect();
+        
+        match parts.len() {
+            0 => None,
+            1 => {
+                let part = parts.into_iter().next()?;
+                let wildcards = part.matches(['*', '?']).count();
+                if wildcards == 0 {
+                    Some(ExclusionRule::Component(part))
+                } else if wildcards == 1 && part.ends_with('*') && part.len() > 1 {
+                    Some(ExclusionRule::Prefix(part.trim_end_matches('*').to_string()))
+                } else {
+                    Some(ExclusionRule::Glob(part))
+                }
+            }
+            _ => Some(ExclusionRule::Sequence(parts)),
         }
     }
     
-    // Exclude hidden files (starting with .)
-    if let Some(filename) = path.file_name() {
-        let filename_str = filename.to_string_lossy();
-        if filename_str.starts_with('.') && filename_str != "." && filename_str != ".." {
-            return true;
+    /// Check the rule against lowercased directory components
+    pub fn matches(&self, dirs: &[&str]) -> bool {
+        match self {
+            ExclusionRule::Component(name) => dirs.iter().any(|d| d == name),
+            ExclusionRule::Prefix(prefix) => dirs.iter().any(|d| d.starts_with(prefix.as_str())),
+            ExclusionRule::Glob(glob) => dirs.iter().any(|d| glob_match(glob, d)),
+            ExclusionRule::Sequence(seq) => dirs
+                .windows(seq.len())
+                .any(|w| w.iter().zip(seq).all(|(d, s)| d == s)),
         }
     }
-    
-    // Exclude system files
-    if path_str.ends_with(".sys") || 
-       path_str.ends_with(".dll") ||
-       path_str.ends_with(".tmp") {
-        return true;
-    }
-    
-    false
 }
 
-/// Get default directories to index based on Windows user folders
-pub fn get_default_directories() -> Vec<PathBuf> {
-    get_directories_for_drives(&['C'])
+/// The files indexed under one folder with an include filter
+#[derive(Debug, Clone)]
+struct IncludeRule {
+    root: PathBuf,
+    /// Folded extensions without the dot, kind names already expanded
+    extensions: Vec<String>,
+    globs: Vec<Glob>,
 }
 
-/// Get available Windows drive letters
-pub fn get_available_drives() -> Vec<char> {
-    let mut drives = Vec::new();
-    
-    #[cfg(target_os = "windows")]
-    {
-        // Check common drive letters A-Z
-        for letter in 'A'..='Z' {
-            let drive_path = format!("{}:\\", letter);
-            if std::path::Path::new(&drive_path).exists() {
-                drives.push(letter);
+impl IncludeRule {
+    /// Compile a configured filter, resolving kind names against `groups`
+    fn new(filter: &IncludeFilter, groups: &[KindGroup]) -> Self {
+        let mut rule = Self {
+            root: filter.root.clone(),
+            extensions: Vec::new(),
+            globs: Vec::new(),
+        };
+        for pattern in filter.patterns.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            if let Some(group) = find_kind_in(groups, pattern) {
+                rule.extensions.extend(group.extensions.iter().cloned());
+            } else if let Some(glob) = Glob::parse(&fold(pattern)) {
+                rule.globs.push(glob);
+            } else {
+                rule.extensions.push(fold(pattern.trim_start_matches('.')));
             }
         }
+        rule
     }
     
-    #[cfg(not(target_os = "windows"))]
-    {
-        // Non-Windows: just return root
-        drives.push('/');
+    /// Check a filename against the extensions and patterns
+    fn matches(&self, file_name: &str) -> bool {
+        let name = fold(file_name);
+        self.extensions.iter().any(|ext| has_extension(&name, ext)) || self.globs.iter().any(|glob| glob.matches(&name))
     }
-    
-    drives
 }
 
-/// Get directories for specified drives
-pub fn get_directories_for_drives(drive_letters: &[char]) -> Vec<PathBuf> {
-    let mut dirs = Vec::new();
+/// Parsed built-in rules, built once on first use
+fn builtin_rules() -> &'static [ExclusionRule] {
+    static RULES: OnceLock<Vec<ExclusionRule>> = OnceLock::new();
+    RULES.get_or_init(|| EXCLUDED_DIRS.iter().filter_map(|p| ExclusionRule::parse(p)).collect())
+}
+
+/// Match `text` against a glob supporting `*` (any run) and `?` (any char)
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
     
-    #[cfg(target_os = "windows")]
-    {
-        use known_folders::{get_known_folder_path, KnownFolder};
-        
-        // Only add user folders if C: drive is enabled
-        if drive_letters.contains(&'C') {
-            let folders = vec![
-                (KnownFolder::Documents, "Documents"),
-                (KnownFolder::Downloads, "Downloads"),
-                (KnownFolder::Desktop, "Desktop"),
-                (KnownFolder::Pictures, "Pictures"),
-                (KnownFolder::Videos, "Videos"),
-                (KnownFolder::Music, "Music"),
-            ];
-            
-            for (folder, name) in folders {
-                if let Some(path) = get_known_folder_path(folder) {
-                    if path.exists() {
-                        info!("Added default directory: {} ({})", name, path.display());
-                        dirs.push(path);
-                    } else {
-                        warn!("Known folder {} does not exist: {}", name, path.display());
-                    }
-                } else {
-                    warn!("Could not get path for known folder: {}", name);
-                }
-            }
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((star_pi, star_ti)) = star {
+            // Backtrack: let the last '*' swallow one more character
+            pi = star_pi + 1;
+            ti = star_ti + 1;
+            star = Some((star_pi, star_ti + 1));
+        } else {
+            return false;
         }
+    }
+    
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// Top-level folders (directly under a drive root) treated as system locations
+const SYSTEM_ROOTS: &[&str] = &["windows", "program files", "program files (x86)"];
+
+/// Binary extensions only excluded inside system locations by default
+const SYSTEM_FILE_EXTENSIONS: &[&str] = &[".dll", ".sys"];
+
+/// Top-level folders on Unix-like systems that hold virtual or system files
+const UNIX_SYSTEM_ROOTS: &[&str] = &["proc", "sys", "dev", "run", "snap", "lost+found"];
+
+/// Where udisks mounts removable drives (`/run/media/$USER/USB`), kept
+/// indexed although it lies under the excluded `/run`
+const UNIX_MEDIA_ROOT: &[&str] = &["run", "media"];
+
+/// Per-volume recycle bin used since Vista, holding one SID-named folder per user
+const RECYCLE_BIN: &str = "$recycle.bin";
+
+/// Older recycle-bin folders, only recognized directly under a drive root
+/// (`RECYCLER` on XP-era NTFS, `Recycled` on FAT volumes)
+const LEGACY_RECYCLE_BINS: &[&str] = &["recycler", "recycled"];
+
+/// Check whether a path lies inside a recycle bin or desktop trash folder
+///
+/// Matches whole components on any drive letter, UNC share or mount, in any
+/// case and with either separator, so `D:\$RECYCLE.BIN\S-1-5-21-...\$RX1.txt`
+/// is caught as well as `C:\$Recycle.Bin\file.txt`.
+pub fn is_recycle_bin(path: &Path) -> bool {
+    let path_str = path.to_string_lossy().to_lowercase();
+    let components: Vec<&str> = path_str
+        .split(['/', '\\'])
+        .filter(|c| !c.is_empty())
+        .collect();
+    let dirs = components.split_last().map_or(&[][..], |(_, dirs)| dirs);
+    is_recycle_bin_dirs(dirs)
+}
+
+/// Recycle-bin check on lowercased directory components
+fn is_recycle_bin_dirs(dirs: &[&str]) -> bool {
+    let legacy = dirs
+        .windows(2)
+        .any(|w| w[0].ends_with(':') && LEGACY_RECYCLE_BINS.contains(&w[1]));
+    // Freedesktop trash: `.Trash-<uid>` on removable volumes, `~/.local/share/Trash`
+    let freedesktop = dirs.iter().any(|d| *d == ".trash" || d.starts_with(".trash-"))
+        || dirs.windows(3).any(|w| w == [".local", "share", "trash"]);
+    
+    dirs.contains(&RECYCLE_BIN) || legacy || freedesktop
+}
+
+/// Check whether directory components point inside a Windows system location
+fn is_system_location(dirs: &[&str]) -> bool {
+    matches!(dirs, [drive, top, ..] if drive.ends_with(':') && SYSTEM_ROOTS.contains(top))
+}
+
+/// Exclusion rules applied by both the scanner and the watcher
+///
+/// Built from `Config` so user toggles and custom patterns affect every
+/// code path that decides whether a file belongs in the index.
+#[derive(Debug, Clone, Default)]
+pub struct Exclusions {
+    /// Index .dll/.sys files even inside system locations
+    index_system_file_types: bool,
+    /// User patterns that exclude additional paths
+    custom: Vec<ExclusionRule>,
+    /// User patterns prefixed with `!` that are never excluded
+    overrides: Vec<ExclusionRule>,
+    /// Size limit in bytes for listing zip members; `None` leaves archives closed
+    archive_limit: Option<u64>,
+    /// Index folders as well as files
+    index_directories: bool,
+    /// Folders under which only matching files are indexed
+    includes: Vec<IncludeRule>,
+}
+
+impl Exclusions {
+    /// Build exclusion rules from the user configuration
+    pub fn from_config(config: &Config) -> Self {
+        let mut exclusions = Self {
+            index_system_file_types: config.index_system_file_types,
+            archive_limit: config
+                .index_archives
+                .then(|| config.max_archive_size_mb.saturating_mul(1024 * 1024)),
+            index_directories: config.index_directories,
+            includes: config
+                .include_filters
+                .iter()
+                .map(|filter| IncludeRule::new(filter, &config.kind_groups))
+                .collect(),
+            ..Self::default()
+        };
         
-        // Add root of other enabled drives (excluding C:)
-        for &drive in drive_letters {
-            if drive != 'C' {
-                let drive_root = PathBuf::from(format!("{}:\\", drive));
-                if drive_root.exists() {
-                    info!("Added drive root: {}", drive_root.display());
-                    dirs.push(drive_root);
-                }
+        for pattern in &config.custom_exclusions {
+            match pattern.trim().strip_prefix('!') {
+                Some(rest) => exclusions.overrides.extend(ExclusionRule::parse(rest)),
+                None => exclusions.custom.extend(ExclusionRule::parse(pattern)),
             }
         }
+        
+        exclusions
     }
     
-    #[cfg(not(target_os = "windows"))]
-    {
-        // Fallback for non-Windows systems
-        if let Ok(home) = std::env::var("HOME") {
-            let home = PathBuf::from(home);
-            for dir in &["Documents", "Downloads", "Desktop"] {
-                let path = home.join(dir);
-                if path.exists() {
-                    dirs.push(path);
-                }
-            }
-        }
+    /// Size limit for archives whose members are indexed, if enabled
+    pub fn archive_limit(&self) -> Option<u64> {
+        self.archive_limit
+    }
+    
+    /// Check whether folders are indexed alongside files
+    pub fn indexes_directories(&self) -> bool {
+        self.index_directories
+    }
+    
+    /// Check whether a file passes the include filter of the closest
+    /// filtered folder above it; files under no such folder always do
+    pub fn includes(&self, path: &Path) -> bool {
+        let Some(rule) = self
+            .includes
+            .iter()
+            .filter(|rule| is_within(path, &rule.root))
+            .max_by_key(|rule| rule.root.as_os_str().len())
+        else {
+            return true;
+        };
+        path.file_name().is_some_and(|name| rule.matches(&name.to_string_lossy()))
     }
     
-    if dirs.is_empty() {
-        warn!("No default directories found!");
+    /// Check if an entry should be left out of the index; include filters
+    /// only apply to files, so filtered folders keep their subfolders
+    pub fn excludes(&self, path: &Path, is_dir: bool) -> bool {
+        self.is_excluded(path) || (!is_dir && !self.includes(path))
     }
     
-    dirs
+    /// Check if a path should be excluded from indexing
+    ///
+    /// Directory rules only look at whole components (split on both `/` and `\\`),
+    /// so `temperature_logs` or `objects_for_class` are not caught by `temp`/`obj`.
+    /// Custom patterns and `!` overrides also see the filename component.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        let path_str = path.to_string_lossy().to_lowercase();
+        
+        let components: Vec<&str> = path_str
+            .split(['/', '\\'])
+            .filter(|c| !c.is_empty())
+            .collect();
+        let Some((filename, dirs)) = components.split_last() else {
+            return false;
+        };
+        
+        // Trashed files are never indexed, even under a `!` override
+        if is_recycle_bin_dirs(dirs) {
+            return true;
+        }
+        
+        if self.overrides.iter().any(|rule| rule.matches(&components)) {
+            return false;
+        }
+        
+        if EXCLUDED_SUBSTRINGS.iter().any(|s| path_str.contains(s)) {
+            return true;
+        }
+        
+        if builtin_rules().iter().any(|rule| rule.matches(dirs)) {
+            return true;
+        }
+        
+        if self.custom.iter().any(|rule| rule.matches(&components)) {
+            return true;
+        }
+        
+        // Hidden files are indexed; searches leave them out while
+        // `Config::show_hidden_files` is off, so toggling it needs no rescan.
+        // Unix dot directories (~/.config, ~/.local) are still skipped, along
+        // with virtual filesystems at the top level
+        if !is_windows_style(&path_str) {
+            if dirs.iter().any(|d| d.starts_with('.') && *d != "." && *d != "..") {
+                return true;
+            }
+            if dirs.first().is_some_and(|d| UNIX_SYSTEM_ROOTS.contains(d)) && !dirs.starts_with(UNIX_MEDIA_ROOT) {
+                return true;
+            }
+        }
+        
+        // Exclude system binaries, but only where Windows keeps its own
+        if !self.index_system_file_types
+            && is_system_location(dirs)
+            && SYSTEM_FILE_EXTENSIONS.iter().any(|ext| filename.ends_with(ext))
+        {
+            return true;
+        }
+        
+        filename.ends_with(".tmp")
+    }
 }
 
 /// Check if we have read permission for a path
@@ -338,15 +744,15 @@ pub fn has_read_permission(path: &Path) -> bool {
             use std::io::ErrorKind;
             match e.kind() {
                 ErrorKind::PermissionDenied => {
-                    debug!("Permission denied: {}", path.display());
+                    debug!("Permission denied: {}", redact(&path));
                     false
                 }
                 ErrorKind::NotFound => {
-                    debug!("Path not found: {}", path.display());
+                    debug!("Path not found: {}", redact(&path));
                     false
                 }
                 _ => {
-                    warn!("Error accessing {}: {}", path.display(), e);
+                    warn!("Error accessing {}: {}", redact(&path), e);
                     false
                 }
             }
@@ -357,6 +763,11 @@ pub fn has_read_permission(path: &Path) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_dir::TestDir;
+
+    fn is_excluded(path: &Path) -> bool {
+        Exclusions::default().is_excluded(path)
+    }
 
     #[test]
     fn test_exclusion_patterns() {
@@ -368,8 +779,48 @@ mod tests {
     }
 
     #[test]
-    fn test_hidden_files() {
-        assert!(is_excluded(Path::new("C:\\Users\\Test\\.hidden")));
+    fn test_component_false_positives() {
+        assert!(!is_excluded(Path::new("C:\\Users\\me\\Documents\\temperature_logs\\readings.csv")));
+        assert!(!is_excluded(Path::new("D:\\objects_for_class\\notes.txt")));
+        assert!(!is_excluded(Path::new("E:\\my packages list.txt")));
+        assert!(!is_excluded(Path::new("C:\\Users\\me\\attempt\\draft.docx")));
+        assert!(!is_excluded(Path::new("C:\\src\\target\\debugging-notes.md")));
+    }
+
+    #[test]
+    fn test_component_true_positives() {
+        assert!(is_excluded(Path::new("C:\\Users\\me\\Temp\\file.txt")));
+        assert!(is_excluded(Path::new("C:\\src\\app\\obj\\build.cache")));
+        assert!(is_excluded(Path::new("C:\\Users\\Test\\AppData\\LocalLow\\file.txt")));
+        assert!(is_excluded(Path::new("C:\\src\\crate\\target\\release\\app.exe")));
+        assert!(is_excluded(Path::new("/home/me/project/node_modules/lib/index.js")));
+    }
+
+    #[test]
+    fn test_rule_parsing() {
+        assert_eq!(ExclusionRule::parse("Temp"), Some(ExclusionRule::Component("temp".into())));
+        assert_eq!(ExclusionRule::parse("build*"), Some(ExclusionRule::Prefix("build".into())));
+        assert_eq!(ExclusionRule::parse("*.egg-info"), Some(ExclusionRule::Glob("*.egg-info".into())));
+        assert_eq!(
+            ExclusionRule::parse("AppData\\Local"),
+            Some(ExclusionRule::Sequence(vec!["appdata".into(), "local".into()]))
+        );
+        assert_eq!(ExclusionRule::parse("  "), None);
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.egg-info", "mypkg.egg-info"));
+        assert!(glob_match("b?ild", "build"));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(!glob_match("*.egg-info", "egg-info-notes"));
+        assert!(!glob_match("b?ild", "bld"));
+    }
+
+    #[test]
+    fn test_hidden_files_are_indexed() {
+        assert!(!is_excluded(Path::new("C:\\Users\\Test\\.hidden")));
+        assert!(!is_excluded(Path::new("/home/me/.bashrc")));
         assert!(!is_excluded(Path::new("C:\\Users\\Test\\visible.txt")));
     }
 
@@ -379,4 +830,240 @@ mod tests {
         assert!(is_excluded(Path::new("C:\\Program Files\\app.dll")));
         assert!(!is_excluded(Path::new("C:\\Users\\Test\\document.pdf")));
     }
+
+    #[test]
+    fn test_system_file_types_scoped_to_system_locations() {
+        assert!(!is_excluded(Path::new("C:\\Users\\Dev\\Documents\\proj\\out\\plugin.dll")));
+        assert!(!is_excluded(Path::new("D:\\drivers\\custom.sys")));
+        assert!(is_excluded(Path::new("C:\\Windows\\System32\\kernel32.dll")));
+        assert!(is_excluded(Path::new("C:\\Program Files (x86)\\Tool\\tool.dll")));
+    }
+
+    #[test]
+    fn test_index_system_file_types_toggle() {
+        let config = Config {
+            index_system_file_types: true,
+            ..Config::default()
+        };
+        let exclusions = Exclusions::from_config(&config);
+        assert!(!exclusions.is_excluded(Path::new("C:\\Windows\\System32\\kernel32.dll")));
+        assert!(exclusions.is_excluded(Path::new("C:\\Windows\\System32\\scratch.tmp")));
+    }
+
+    #[test]
+    fn test_custom_exclusions_and_overrides() {
+        let config = Config {
+            custom_exclusions: vec!["*.dll".into(), "build*".into(), "!node_modules".into()],
+            ..Config::default()
+        };
+        let exclusions = Exclusions::from_config(&config);
+        assert!(exclusions.is_excluded(Path::new("C:\\Users\\Dev\\proj\\out\\plugin.dll")));
+        assert!(exclusions.is_excluded(Path::new("C:\\Users\\Dev\\proj\\build-x64\\app.exe")));
+        assert!(!exclusions.is_excluded(Path::new("C:\\Users\\Dev\\proj\\node_modules\\lib.js")));
+    }
+
+    #[test]
+    fn test_offline_root_keeps_entries_on_remove() {
+        let root = TestDir::new("watch_offline");
+        let file = root.join("report.pdf");
+
+        let index = Arc::new(ShardedIndex::new());
+        index.insert(file.clone()).unwrap();
+        let exclusions = Arc::new(RwLock::new(Exclusions::default()));
+        let roots = RootMonitor::new();
+        roots.set_roots(&[root.to_path_buf()]);
+
+        // Root disappears mid-session, then the OS reports its files as removed
+        std::fs::remove_dir_all(&root).unwrap();
+        roots.probe();
+        assert!(!roots.is_available(&file));
+
+        let event = Event::new(EventKind::Remove(notify::event::RemoveKind::File)).add_path(file.clone());
+        handle_fs_event(event, &index, &exclusions, &roots, &ChangeFeed::new(), &SearchAlerts::new(), &ActivityLog::default());
+        assert_eq!(index.search("report").len(), 1);
+    }
+
+    #[test]
+    fn test_unix_hidden_dirs_and_system_roots() {
+        assert!(is_excluded(Path::new("/home/me/.config/app/settings.json")));
+        assert!(is_excluded(Path::new("/home/me/.local/share/file.txt")));
+        assert!(is_excluded(Path::new("/proc/1/status")));
+        assert!(is_excluded(Path::new("/sys/class/net/eth0")));
+        assert!(is_excluded(Path::new("/run/user/1000/bus")));
+        // Removable drives mount under /run/media
+        assert!(!is_excluded(Path::new("/run/media/user/USB/file.txt")));
+        assert!(!is_excluded(Path::new("/home/me/Documents/report.pdf")));
+        assert!(!is_excluded(Path::new("/home/me/projects/sys/readme.md")));
+        // Windows keeps its own hidden attribute; dot folders there stay indexed
+        assert!(!is_excluded(Path::new("C:\\Users\\me\\.dotnet_notes\\a.txt")));
+    }
+
+    #[test]
+    fn test_changed_archive_is_reindexed() {
+        use crate::archive::member_path;
+        use std::io::Write;
+        
+        let root = TestDir::new("watch_archive");
+        let archive = root.join("bundle.zip");
+        let write_zip = |members: &[&str]| {
+            let mut zip = zip::ZipWriter::new(std::fs::File::create(&archive).unwrap());
+            for member in members {
+                zip.start_file(*member, zip::write::SimpleFileOptions::default()).unwrap();
+                zip.write_all(b"x").unwrap();
+            }
+            zip.finish().unwrap();
+        };
+        
+        let index = Arc::new(ShardedIndex::new());
+        let config = Config { index_archives: true, ..Config::default() };
+        let exclusions = Arc::new(RwLock::new(Exclusions::from_config(&config)));
+        let roots = RootMonitor::new();
+        let feed = ChangeFeed::new();
+        let modified = || Event::new(EventKind::Modify(notify::event::ModifyKind::Any)).add_path(archive.clone());
+        
+        write_zip(&["old_notes.txt"]);
+        handle_fs_event(modified(), &index, &exclusions, &roots, &feed, &SearchAlerts::new(), &ActivityLog::default());
+        assert_eq!(index.search("old_notes"), vec![member_path(&archive, "old_notes.txt")]);
+        
+        write_zip(&["new_notes.txt"]);
+        handle_fs_event(modified(), &index, &exclusions, &roots, &feed, &SearchAlerts::new(), &ActivityLog::default());
+        assert!(index.search("old_notes").is_empty());
+        assert_eq!(index.search("new_notes"), vec![member_path(&archive, "new_notes.txt")]);
+        
+        std::fs::remove_file(&archive).unwrap();
+        let removed = Event::new(EventKind::Remove(notify::event::RemoveKind::File)).add_path(archive.clone());
+        handle_fs_event(removed, &index, &exclusions, &roots, &feed, &SearchAlerts::new(), &ActivityLog::default());
+        assert!(index.search("notes").is_empty());
+    }
+
+    #[test]
+    fn test_recycle_bin_excluded_on_every_drive() {
+        for path in [
+            "C:\\$Recycle.Bin\\file.txt",
+            "D:\\$RECYCLE.BIN\\S-1-5-21-3623811015-3361044348-30300820-1013\\$RAB12CD.docx",
+            "e:/$recycle.bin/S-1-5-18/$I4F2A9B.pdf",
+            "\\\\nas\\share\\$Recycle.Bin\\S-1-5-21-1\\notes.txt",
+            "F:\\RECYCLER\\S-1-5-21-1\\Dc1.txt",
+            "G:\\Recycled\\DC2.TXT",
+            "/media/usb/.Trash-1000/files/photo.jpg",
+        ] {
+            assert!(is_recycle_bin(Path::new(path)), "missed {}", path);
+            assert!(is_excluded(Path::new(path)), "indexed {}", path);
+        }
+        
+        // Legacy names only count directly under a drive root
+        assert!(!is_excluded(Path::new("D:\\Projects\\recycler\\design.md")));
+        assert!(!is_recycle_bin(Path::new("C:\\Users\\me\\Documents\\recycle bin notes.txt")));
+        
+        // A `!` override cannot pull trash back into the index
+        let config = Config { custom_exclusions: vec!["!$recycle.bin".to_string()], ..Config::default() };
+        assert!(Exclusions::from_config(&config).is_excluded(Path::new("C:\\$Recycle.Bin\\S-1-5-18\\a.txt")));
+    }
+    
+    #[test]
+    fn test_removal_into_recycle_bin_is_annotated() {
+        use crate::changes::ChangeKind;
+        
+        let root = TestDir::new("watch_trash");
+        let bin = root.join("$Recycle.Bin").join("S-1-5-21-1001");
+        std::fs::create_dir_all(&bin).unwrap();
+        let original = root.join("budget.xlsx");
+        let trashed = bin.join("$RK3J9QX.xlsx");
+        std::fs::write(&trashed, b"x").unwrap();
+        
+        let index = Arc::new(ShardedIndex::new());
+        index.insert(original.clone()).unwrap();
+        index.insert(root.join("plain.txt")).unwrap();
+        let exclusions = Arc::new(RwLock::new(Exclusions::default()));
+        let roots = RootMonitor::new();
+        let feed = ChangeFeed::new();
+        
+        // Windows reports the move as a rename out of the folder plus the new name in the bin
+        let renamed = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::From))).add_path(original.clone());
+        handle_fs_event(renamed, &index, &exclusions, &roots, &feed, &SearchAlerts::new(), &ActivityLog::default());
+        let arrived = Event::new(EventKind::Create(notify::event::CreateKind::File)).add_path(trashed.clone());
+        handle_fs_event(arrived, &index, &exclusions, &roots, &feed, &SearchAlerts::new(), &ActivityLog::default());
+        // An ordinary delete stays a bare removal
+        let deleted = Event::new(EventKind::Remove(notify::event::RemoveKind::File)).add_path(root.join("plain.txt"));
+        handle_fs_event(deleted, &index, &exclusions, &roots, &feed, &SearchAlerts::new(), &ActivityLog::default());
+        
+        let recent = feed.recent();
+        assert_eq!(recent.len(), 2);
+        assert_eq!((recent[0].kind, recent[1].kind), (ChangeKind::Removed, ChangeKind::MovedToTrash));
+        assert_eq!(recent[1].path, original);
+        assert!(index.search("budget").is_empty());
+        assert!(index.search("rk3j9qx").is_empty());
+    }
+
+    #[test]
+    fn test_paired_rename_keeps_the_entry() {
+        let root = TestDir::new("watch_rename");
+        let (draft, saved) = (root.join("~draft.txt"), root.join("letter.txt"));
+        std::fs::write(&saved, b"x").unwrap();
+        
+        let index = Arc::new(ShardedIndex::new());
+        let dated = EntryMeta { size: 1, modified: 1_700_000_000, is_dir: false, attributes: 0 };
+        index.insert_with_meta(draft.clone(), dated).unwrap();
+        let exclusions = Arc::new(RwLock::new(Exclusions::default()));
+        let feed = ChangeFeed::new();
+        
+        let renamed = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both))).add_path(draft.clone()).add_path(saved.clone());
+        handle_fs_event(renamed, &index, &exclusions, &RootMonitor::new(), &feed, &SearchAlerts::new(), &ActivityLog::default());
+        
+        assert!(index.search("draft").is_empty());
+        assert_eq!(index.search("letter"), vec![saved.clone()]);
+        // Renamed in place, so the date read before is kept
+        assert_eq!(index.metadata_for(&saved), Some(dated));
+        assert_eq!(feed.recent().len(), 2);
+    }
+
+    #[test]
+    fn test_new_file_matching_watched_search_raises_alert() {
+        use crate::config::SavedSearch;
+        
+        let root = TestDir::new("watch_alert");
+        let file = root.join("invoice_0042.pdf");
+        std::fs::write(&file, b"x").unwrap();
+        
+        let index = Arc::new(ShardedIndex::new());
+        let exclusions = Arc::new(RwLock::new(Exclusions::default()));
+        let alerts = SearchAlerts::new();
+        alerts.set_watches(&[SavedSearch { name: "Invoices".to_string(), query: "invoice".to_string(), filter: None, watch: true }]);
+        
+        let created = Event::new(EventKind::Create(notify::event::CreateKind::File)).add_path(file.clone());
+        handle_fs_event(created, &index, &exclusions, &RootMonitor::new(), &ChangeFeed::new(), &alerts, &ActivityLog::default());
+        let raised = alerts.take();
+        assert_eq!(raised.len(), 1);
+        assert_eq!(raised[0].path, file);
+    }
+
+    #[test]
+    fn test_watched_changes_are_counted_as_activity() {
+        use crate::activity::week_start;
+        use crate::dates::today;
+        
+        let root = TestDir::new("watch_activity");
+        let file = root.join("weekly.txt");
+        std::fs::write(&file, b"twelve bytes").unwrap();
+        
+        let index = Arc::new(ShardedIndex::new());
+        let exclusions = Arc::new(RwLock::new(Exclusions::default()));
+        let activity = ActivityLog::default();
+        activity.set_enabled(true);
+        let apply = |event: Event| {
+            handle_fs_event(event, &index, &exclusions, &RootMonitor::new(), &ChangeFeed::new(), &SearchAlerts::new(), &activity);
+        };
+        
+        apply(Event::new(EventKind::Create(CreateKind::File)).add_path(file.clone()));
+        // Already indexed, so not added again
+        apply(Event::new(EventKind::Modify(ModifyKind::Any)).add_path(file.clone()));
+        std::fs::remove_file(&file).unwrap();
+        apply(Event::new(EventKind::Remove(notify::event::RemoveKind::File)).add_path(file.clone()));
+        
+        let week = activity.week(week_start(today()));
+        assert_eq!((week.added, week.removed), (1, 1));
+        assert_eq!(week.folders, [(root.to_path_buf(), 2)]);
+        assert_eq!(week.biggest, [(file, 12)]);
+    }
 }
+
@@ -0,0 +1,166 @@
+use std::ops::Range;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::fold::fold;
+
+/// Split a filename into folded words for `words:` queries
+///
+/// Words end at anything that is not a letter or digit (`_`, `-`, `.`,
+/// spaces) and at case changes, so `UserProfileController.cs` and
+/// `user_profile_controller.rs` both hold "user", "profile" and
+/// "controller". A run of capitals is one word that ends before a capital
+/// starting a lowercase word (`HTMLParser` is "html" and "parser"). Digits
+/// stay with their letters, keeping "mp3" and "v2" whole.
+pub fn words(name: &str) -> Vec<String> {
+    // Composed form keeps accented letters from splitting words
+    let chars: Vec<char> = name.nfc().collect();
+    let mut words = Vec::new();
+    let mut start = None;
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if let Some(from) = start.take() {
+                words.push(fold(&chars[from..i].iter().collect::<String>()));
+            }
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|p| chars[p]);
+        let next = chars.get(i + 1);
+        let case_change = prev.is_some_and(|p| {
+            (p.is_lowercase() && c.is_uppercase())
+                || (p.is_uppercase() && c.is_uppercase() && next.is_some_and(|n| n.is_lowercase()))
+        });
+        match start {
+            Some(from) if case_change => {
+                words.push(fold(&chars[from..i].iter().collect::<String>()));
+                start = Some(i);
+            }
+            Some(_) => {}
+            None => start = Some(i),
+        }
+    }
+    if let Some(from) = start {
+        words.push(fold(&chars[from..].iter().collect::<String>()));
+    }
+    words
+}
+
+/// Initials of the words of a filename's stem that start with a letter,
+/// folded, as in "fbr" for `financial_budget_report_2024.xlsx`; `None` for
+/// names with fewer than two such words
+pub fn acronym(name: &str) -> Option<String> {
+    let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+    let initials: String = words(stem)
+        .iter()
+        .filter_map(|word| word.chars().next())
+        .filter(|c| c.is_alphabetic())
+        .collect();
+    (initials.chars().count() >= 2).then_some(initials)
+}
+
+/// Whether every query word starts one of `name_words`
+pub fn words_match(name_words: &[String], query: &[String]) -> bool {
+    query.iter().all(|word| name_words.iter().any(|w| w.starts_with(word.as_str())))
+}
+
+/// Whether a word of `name` (as `words` splits it) starts at byte `at`
+pub fn starts_word(name: &str, at: usize) -> bool {
+    let letters = |text: &str| text.chars().filter(|&c| !is_combining_mark(c)).collect::<Vec<char>>();
+    // An accent is part of the letter before it, never a start
+    let Some(c) = name[at..].chars().next().filter(|c| c.is_alphanumeric()) else {
+        return false;
+    };
+    let mut after = letters(&name[at..]).into_iter().skip(1);
+    let Some(prev) = letters(&name[..at]).pop().filter(|p| p.is_alphanumeric()) else {
+        return true;
+    };
+    (prev.is_lowercase() && c.is_uppercase())
+        || (prev.is_uppercase() && c.is_uppercase() && after.next().is_some_and(|n| n.is_lowercase()))
+}
+
+/// Whether a word of `name` (as `words` splits it) ends at byte `at`
+pub fn ends_word(name: &str, at: usize) -> bool {
+    let rest = name[at..].trim_start_matches(is_combining_mark);
+    match rest.chars().next() {
+        None => true,
+        Some(c) => !c.is_alphanumeric() || starts_word(name, name.len() - rest.len()),
+    }
+}
+
+/// Whether `range` of `name` holds whole words: it begins at the start of a
+/// word or at a separator, and ends likewise, so "art" is whole in
+/// `art_deco.png` and `ArtBook.pdf` but not in `startup.txt`
+pub fn is_whole_word(name: &str, range: Range<usize>) -> bool {
+    let text = &name[range.clone()];
+    let starts = text.chars().next().is_some_and(|c| !c.is_alphanumeric()) || starts_word(name, range.start);
+    let ends = text.chars().next_back().is_some_and(|c| !c.is_alphanumeric()) || ends_word(name, range.end);
+    starts && ends
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words_split_on_case_and_separators() {
+        assert_eq!(words("UserProfileController.cs"), ["user", "profile", "controller", "cs"]);
+        assert_eq!(words("user_profile_controller.rs"), ["user", "profile", "controller", "rs"]);
+        assert_eq!(words("HTMLParser-v2.test.js"), ["html", "parser", "v2", "test", "js"]);
+        assert_eq!(words("IMG_2024 (1).JPG"), ["img", "2024", "1", "jpg"]);
+        assert_eq!(words("getURL"), ["get", "url"]);
+        assert_eq!(words("Cafe\u{301}Menu.pdf"), ["cafe", "menu", "pdf"]);
+        assert!(words("__--..").is_empty());
+    }
+
+    #[test]
+    fn test_acronyms_take_each_word_initial() {
+        assert_eq!(acronym("financial_budget_report_2024.xlsx").as_deref(), Some("fbr"));
+        assert_eq!(acronym("quarterly-sales-review.pdf").as_deref(), Some("qsr"));
+        assert_eq!(acronym("UserProfileController.cs").as_deref(), Some("upc"));
+        assert_eq!(acronym("HTMLParser v2.js").as_deref(), Some("hpv"));
+        assert_eq!(acronym("Édition_Spéciale.doc").as_deref(), Some("es"));
+        assert_eq!(acronym("report.pdf"), None);
+        assert_eq!(acronym(".bashrc"), None);
+    }
+
+    #[test]
+    fn test_query_words_must_start_name_words() {
+        let name = words("UserProfileController.cs");
+        let query = |q: &str| words(q);
+        assert!(words_match(&name, &query("profile controller")));
+        assert!(words_match(&name, &query("prof contr")));
+        assert!(words_match(&name, &query("ControllerUser")));
+        assert!(words_match(&name, &query("cs")));
+        // "roller" is inside a word, not at its start
+        assert!(!words_match(&name, &query("roller")));
+        assert!(!words_match(&name, &query("profile view")));
+    }
+
+    #[test]
+    fn test_word_starts_follow_the_split() {
+        let name = "HTMLParser-v2 Cafe\u{301}Menu.cs";
+        let starts: Vec<usize> = (0..name.len()).filter(|&at| name.is_char_boundary(at) && starts_word(name, at)).collect();
+        let at = |word: &str| name.find(word).unwrap();
+        assert_eq!(starts, [0, at("Parser"), at("v2"), at("Cafe"), at("Menu"), at("cs")]);
+    }
+
+    #[test]
+    fn test_whole_words_end_at_separators_and_case_changes() {
+        let whole = |name: &str, word: &str| {
+            let at = name.find(word).unwrap();
+            is_whole_word(name, at..at + word.len())
+        };
+        assert!(whole("art_deco.png", "art"));
+        assert!(whole("ArtBook.pdf", "Art"));
+        assert!(whole("pop-art", "art"));
+        assert!(whole("my art.txt", "art"));
+        assert!(whole("HTMLParser.js", "Parser"));
+        assert!(!whole("startup_notes.txt", "art"));
+        assert!(!whole("particle.png", "art"));
+        assert!(!whole("artist.txt", "art"));
+        assert!(!whole("Cafe\u{301}.txt", "Caf"));
+        // Separators in the term bound it themselves
+        assert!(whole("old_draft.doc", "_draft"));
+    }
+}